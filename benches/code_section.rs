@@ -0,0 +1,81 @@
+//! Decoding of a large code section.
+//!
+//! Compare `cargo bench --bench code_section` with `cargo bench --bench code_section --features
+//! rayon` to measure the speedup of decoding function bodies in parallel.
+//!
+//! Each iteration drops the module it parsed. With glibc, freeing it from the main thread shrinks
+//! the heap, and the next iteration page faults it back in: the sequential decoding spends about
+//! half of its time in the kernel. The bodies decoded by `rayon` are allocated in the arena of a
+//! worker thread, which glibc trims less eagerly. Set `MALLOC_TRIM_THRESHOLD_=1000000000` to
+//! compare the decoding alone.
+//!
+//! Measured on a single core, so without any actual parallelism, over three runs each:
+//!
+//! | Decoding   | Default          | No heap trimming |
+//! |------------|------------------|------------------|
+//! | Sequential | 294 to 306 ms    | 153 to 166 ms    |
+//! | `rayon`    | 192 to 202 ms    | 152 ms           |
+
+#![feature(test)]
+
+extern crate test;
+
+use test::Bencher;
+
+use wasm_parse::parse::{Parse, ParsingData, WasmBinary};
+use wasm_parse::wasm::module::Module;
+
+const FUNCS: u32 = 2_000;
+const INSTRS: u32 = 500;
+
+fn leb(mut value: u32, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn section(id: u8, contents: &[u8], out: &mut Vec<u8>) {
+    out.push(id);
+    leb(contents.len() as u32, out);
+    out.extend_from_slice(contents);
+}
+
+/// Module of [FUNCS] functions of type `[] -> []`, each pushing and dropping [INSTRS] constants.
+fn module() -> WasmBinary {
+    let mut body = vec![0x00]; // No locals
+    for i in 0..INSTRS {
+        body.push(0x41); // i32.const
+        leb(i, &mut body);
+        body.push(0x1A); // drop
+    }
+    body.push(0x0B);
+
+    let mut funcs = Vec::new();
+    leb(FUNCS, &mut funcs);
+    funcs.resize(funcs.len() + FUNCS as usize, 0x00);
+    let mut code = Vec::new();
+    leb(FUNCS, &mut code);
+    for _ in 0..FUNCS {
+        leb(body.len() as u32, &mut code);
+        code.extend_from_slice(&body);
+    }
+
+    let mut bytes = vec![0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+    section(1, &[0x01, 0x60, 0x00, 0x00], &mut bytes);
+    section(3, &funcs, &mut bytes);
+    section(10, &code, &mut bytes);
+    bytes.into()
+}
+
+#[bench]
+fn parse_module(b: &mut Bencher) {
+    let bytes = module();
+    b.bytes = bytes.len() as u64;
+    b.iter(|| Module::parse(&mut ParsingData::new(&bytes)).unwrap());
+}
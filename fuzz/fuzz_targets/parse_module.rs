@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use wasm_parse::parse::{Parse, ParsingData, WasmBinary};
+use wasm_parse::wasm::module::Module;
+
+fuzz_target!(|data: &[u8]| {
+    let wasm: WasmBinary = data.into();
+    let mut wasm = ParsingData::new(&wasm);
+    let _ = Module::parse(&mut wasm);
+});
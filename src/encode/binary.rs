@@ -0,0 +1,40 @@
+//! Binary format encoder
+//!
+//! This module defines the necessary components to serialize web assembly components into the
+//! binary format. Objects that can be encoded will implement [Encode].
+
+mod global;
+mod indices;
+mod instr;
+mod module;
+mod preamble;
+mod sections;
+mod types;
+mod values;
+
+/// Encodes a web assembly component into its binary representation.
+///
+/// # Examples
+///
+/// ```
+/// # use wasm_parse::encode::binary::*;
+/// # use wasm_parse::wasm::types::NumType;
+/// assert_eq!(NumType::I32.to_bytes(), vec![0x7F]);
+/// ```
+///
+/// ```
+/// # use wasm_parse::encode::binary::*;
+/// # use wasm_parse::wasm::values::U32;
+/// assert_eq!(U32(624485).to_bytes(), vec![0xE5, 0x8E, 0x26]);
+/// ```
+pub trait Encode {
+    /// Appends the binary representation of the component to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Encodes the component into a newly allocated buffer.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+}
@@ -0,0 +1,9 @@
+use super::Encode;
+use crate::wasm::global::Global;
+
+impl Encode for Global {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+        self.init.encode(out);
+    }
+}
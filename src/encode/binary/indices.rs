@@ -0,0 +1,77 @@
+use super::Encode;
+use crate::wasm::indices::{
+    DataIdx, ElemIdx, FieldIdx, FuncIdx, GlobalIdx, LabelIdx, LaneIdx, LocalIdx, MemIdx, TableIdx,
+    TagIdx, TypeIdx,
+};
+
+impl Encode for FuncIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for DataIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for ElemIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for GlobalIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for TagIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for FieldIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for LabelIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for LocalIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for MemIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for TypeIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for TableIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
+
+impl Encode for LaneIdx {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out)
+    }
+}
@@ -0,0 +1,1275 @@
+use super::values::{write_signed, write_unsigned};
+use super::Encode;
+use crate::wasm::indices::LabelIdx;
+use crate::wasm::instr::{
+    AggregateInstr, AtomicInstr, Block, BlockType, Catch, ControlInstr, Expr, FBinop, FRelop,
+    FUnop, FloatType, IBinop, IRelop, ITestop, IUnop, IfElseBlock, Instr, IntType, MemArg,
+    MemoryInstr, NumericInstr, ReferenceInstr, Sign, TableInstr, TryBlock, TryHandler,
+    TryTableBlock, VariableInstr, VectorInstr,
+};
+use crate::wasm::types::RefType;
+use crate::wasm::values::U32;
+
+/// Writes an instruction from the `0xFC` prefixed family.
+fn write_prefixed(selector: u32, out: &mut Vec<u8>) {
+    out.push(0xFC);
+    write_unsigned(selector.into(), out);
+}
+
+/// Writes an instruction from the `0xFB` prefixed family.
+fn write_gc(selector: u32, out: &mut Vec<u8>) {
+    out.push(0xFB);
+    write_unsigned(selector.into(), out);
+}
+
+/// Writes a `br_on_cast` style instruction, whose flags carry the nullability of both types.
+fn write_br_on_cast(selector: u32, l: &LabelIdx, rt1: &RefType, rt2: &RefType, out: &mut Vec<u8>) {
+    write_gc(selector, out);
+    out.push(rt1.nullable as u8 | (rt2.nullable as u8) << 1);
+    l.encode(out);
+    rt1.heap.encode(out);
+    rt2.heap.encode(out);
+}
+
+fn write_instrs(instrs: &[Instr], out: &mut Vec<u8>) {
+    for instr in instrs {
+        instr.encode(out);
+    }
+}
+
+impl Encode for Instr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Instr::Numeric(instr) => instr.encode(out),
+            Instr::Vector(instr) => instr.encode(out),
+            Instr::Atomic(instr) => instr.encode(out),
+            Instr::Reference(instr) => instr.encode(out),
+            Instr::Aggregate(instr) => instr.encode(out),
+            Instr::Dropp => out.push(0x1A),
+            Instr::Select(types) if types.is_empty() => out.push(0x1B),
+            Instr::Select(types) => {
+                out.push(0x1C);
+                types.encode(out);
+            }
+            Instr::Variable(instr) => instr.encode(out),
+            Instr::Table(instr) => instr.encode(out),
+            Instr::Memory(instr) => instr.encode(out),
+            Instr::Control(instr) => instr.encode(out),
+        }
+    }
+}
+
+impl Encode for Expr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_instrs(&self.instr, out);
+        out.push(0x0B);
+    }
+}
+
+impl Encode for MemArg {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // Memory 0 is implicit, the others set bit 6 of the alignment.
+        if *self.mem.0 == 0 {
+            self.align.encode(out);
+        } else {
+            U32(*self.align | 1 << 6).encode(out);
+            self.mem.encode(out);
+        }
+        self.offset.encode(out);
+    }
+}
+
+impl Encode for BlockType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            BlockType::Empty => out.push(0x40),
+            BlockType::Val(tpe) => tpe.encode(out),
+            // Type indices are encoded as a positive s33.
+            BlockType::Type(idx) => write_signed((idx.0).0.into(), out),
+        }
+    }
+}
+
+impl Encode for Block {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+        write_instrs(&self.instr, out);
+        out.push(0x0B);
+    }
+}
+
+impl Encode for IfElseBlock {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+        write_instrs(&self.if_br, out);
+        if !self.else_br.is_empty() {
+            out.push(0x05);
+            write_instrs(&self.else_br, out);
+        }
+        out.push(0x0B);
+    }
+}
+
+impl Encode for TryBlock {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+        write_instrs(&self.instr, out);
+        match &self.handler {
+            TryHandler::Catch { catches, catch_all } => {
+                for catch in catches {
+                    out.push(0x07);
+                    catch.tag.encode(out);
+                    write_instrs(&catch.instr, out);
+                }
+                if let Some(instr) = catch_all {
+                    out.push(0x19);
+                    write_instrs(instr, out);
+                }
+                out.push(0x0B);
+            }
+            // `delegate` replaces the `end` of the block.
+            TryHandler::Delegate(l) => {
+                out.push(0x18);
+                l.encode(out);
+            }
+        }
+    }
+}
+
+impl Encode for TryTableBlock {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+        self.catches.encode(out);
+        write_instrs(&self.instr, out);
+        out.push(0x0B);
+    }
+}
+
+impl Encode for Catch {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Catch::Catch(x, l) => {
+                out.push(0x00);
+                x.encode(out);
+                l.encode(out);
+            }
+            Catch::CatchRef(x, l) => {
+                out.push(0x01);
+                x.encode(out);
+                l.encode(out);
+            }
+            Catch::CatchAll(l) => {
+                out.push(0x02);
+                l.encode(out);
+            }
+            Catch::CatchAllRef(l) => {
+                out.push(0x03);
+                l.encode(out);
+            }
+        }
+    }
+}
+
+impl Encode for ControlInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ControlInstr::Unreachable => out.push(0x00),
+            ControlInstr::Nop => out.push(0x01),
+            ControlInstr::Block(block) => {
+                out.push(0x02);
+                block.encode(out);
+            }
+            ControlInstr::Loop(block) => {
+                out.push(0x03);
+                block.encode(out);
+            }
+            ControlInstr::If(block) => {
+                out.push(0x04);
+                block.encode(out);
+            }
+            ControlInstr::Branch(l) => {
+                out.push(0x0C);
+                l.encode(out);
+            }
+            ControlInstr::BranchIf(l) => {
+                out.push(0x0D);
+                l.encode(out);
+            }
+            ControlInstr::BranchTable(labels, default) => {
+                out.push(0x0E);
+                labels.encode(out);
+                default.encode(out);
+            }
+            ControlInstr::Return => out.push(0x0F),
+            ControlInstr::Call(x) => {
+                out.push(0x10);
+                x.encode(out);
+            }
+            ControlInstr::CallIndirect(x, y) => {
+                out.push(0x11);
+                y.encode(out);
+                x.encode(out);
+            }
+            ControlInstr::ReturnCall(x) => {
+                out.push(0x12);
+                x.encode(out);
+            }
+            ControlInstr::ReturnCallIndirect(x, y) => {
+                out.push(0x13);
+                y.encode(out);
+                x.encode(out);
+            }
+            ControlInstr::Try(block) => {
+                out.push(0x06);
+                block.encode(out);
+            }
+            ControlInstr::TryTable(block) => {
+                out.push(0x1F);
+                block.encode(out);
+            }
+            ControlInstr::Throw(x) => {
+                out.push(0x08);
+                x.encode(out);
+            }
+            ControlInstr::Rethrow(l) => {
+                out.push(0x09);
+                l.encode(out);
+            }
+            ControlInstr::ThrowRef => out.push(0x0A),
+            ControlInstr::CallRef(x) => {
+                out.push(0x14);
+                x.encode(out);
+            }
+            ControlInstr::ReturnCallRef(x) => {
+                out.push(0x15);
+                x.encode(out);
+            }
+            ControlInstr::BrOnNull(l) => {
+                out.push(0xD5);
+                l.encode(out);
+            }
+            ControlInstr::BrOnNonNull(l) => {
+                out.push(0xD6);
+                l.encode(out);
+            }
+            ControlInstr::BrOnCast(l, rt1, rt2) => write_br_on_cast(24, l, rt1, rt2, out),
+            ControlInstr::BrOnCastFail(l, rt1, rt2) => write_br_on_cast(25, l, rt1, rt2, out),
+        }
+    }
+}
+
+impl Encode for ReferenceInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ReferenceInstr::RefNull(t) => {
+                out.push(0xD0);
+                t.encode(out);
+            }
+            ReferenceInstr::RefIsNull => out.push(0xD1),
+            ReferenceInstr::RefFunc(x) => {
+                out.push(0xD2);
+                x.encode(out);
+            }
+            ReferenceInstr::RefEq => out.push(0xD3),
+            ReferenceInstr::RefAsNonNull => out.push(0xD4),
+            ReferenceInstr::RefTest(t) => {
+                write_gc(if t.nullable { 21 } else { 20 }, out);
+                t.heap.encode(out);
+            }
+            ReferenceInstr::RefCast(t) => {
+                write_gc(if t.nullable { 23 } else { 22 }, out);
+                t.heap.encode(out);
+            }
+            ReferenceInstr::AnyConvertExtern => write_gc(26, out),
+            ReferenceInstr::ExternConvertAny => write_gc(27, out),
+            ReferenceInstr::RefI31 => write_gc(28, out),
+            ReferenceInstr::I31Get(Sign::Signed) => write_gc(29, out),
+            ReferenceInstr::I31Get(Sign::Unsigned) => write_gc(30, out),
+        }
+    }
+}
+
+impl Encode for AggregateInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            AggregateInstr::StructNew(x) => {
+                write_gc(0, out);
+                x.encode(out);
+            }
+            AggregateInstr::StructNewDefault(x) => {
+                write_gc(1, out);
+                x.encode(out);
+            }
+            AggregateInstr::StructGet(sign, x, y) => {
+                let selector = match sign {
+                    None => 2,
+                    Some(Sign::Signed) => 3,
+                    Some(Sign::Unsigned) => 4,
+                };
+                write_gc(selector, out);
+                x.encode(out);
+                y.encode(out);
+            }
+            AggregateInstr::StructSet(x, y) => {
+                write_gc(5, out);
+                x.encode(out);
+                y.encode(out);
+            }
+            AggregateInstr::ArrayNew(x) => {
+                write_gc(6, out);
+                x.encode(out);
+            }
+            AggregateInstr::ArrayNewDefault(x) => {
+                write_gc(7, out);
+                x.encode(out);
+            }
+            AggregateInstr::ArrayNewFixed(x, n) => {
+                write_gc(8, out);
+                x.encode(out);
+                n.encode(out);
+            }
+            AggregateInstr::ArrayNewData(x, y) => {
+                write_gc(9, out);
+                x.encode(out);
+                y.encode(out);
+            }
+            AggregateInstr::ArrayNewElem(x, y) => {
+                write_gc(10, out);
+                x.encode(out);
+                y.encode(out);
+            }
+            AggregateInstr::ArrayGet(sign, x) => {
+                let selector = match sign {
+                    None => 11,
+                    Some(Sign::Signed) => 12,
+                    Some(Sign::Unsigned) => 13,
+                };
+                write_gc(selector, out);
+                x.encode(out);
+            }
+            AggregateInstr::ArraySet(x) => {
+                write_gc(14, out);
+                x.encode(out);
+            }
+            AggregateInstr::ArrayLen => write_gc(15, out),
+            AggregateInstr::ArrayFill(x) => {
+                write_gc(16, out);
+                x.encode(out);
+            }
+            AggregateInstr::ArrayCopy(x, y) => {
+                write_gc(17, out);
+                x.encode(out);
+                y.encode(out);
+            }
+            AggregateInstr::ArrayInitData(x, y) => {
+                write_gc(18, out);
+                x.encode(out);
+                y.encode(out);
+            }
+            AggregateInstr::ArrayInitElem(x, y) => {
+                write_gc(19, out);
+                x.encode(out);
+                y.encode(out);
+            }
+        }
+    }
+}
+
+impl Encode for VariableInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            VariableInstr::LocalGet(x) => {
+                out.push(0x20);
+                x.encode(out);
+            }
+            VariableInstr::LocalSet(x) => {
+                out.push(0x21);
+                x.encode(out);
+            }
+            VariableInstr::LocalTee(x) => {
+                out.push(0x22);
+                x.encode(out);
+            }
+            VariableInstr::GlobalGet(x) => {
+                out.push(0x23);
+                x.encode(out);
+            }
+            VariableInstr::GlobalSet(x) => {
+                out.push(0x24);
+                x.encode(out);
+            }
+        }
+    }
+}
+
+impl Encode for TableInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            TableInstr::TableGet(x) => {
+                out.push(0x25);
+                x.encode(out);
+            }
+            TableInstr::TableSet(x) => {
+                out.push(0x26);
+                x.encode(out);
+            }
+            TableInstr::TableInit(x, y) => {
+                write_prefixed(12, out);
+                y.encode(out);
+                x.encode(out);
+            }
+            TableInstr::ElemDrop(x) => {
+                write_prefixed(13, out);
+                x.encode(out);
+            }
+            TableInstr::TableCopy(x, y) => {
+                write_prefixed(14, out);
+                x.encode(out);
+                y.encode(out);
+            }
+            TableInstr::TableGrow(x) => {
+                write_prefixed(15, out);
+                x.encode(out);
+            }
+            TableInstr::TableSize(x) => {
+                write_prefixed(16, out);
+                x.encode(out);
+            }
+            TableInstr::TableFill(x) => {
+                write_prefixed(17, out);
+                x.encode(out);
+            }
+        }
+    }
+}
+
+impl Encode for MemoryInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let (opcode, memarg) = match self {
+            MemoryInstr::ILoad(IntType::I32, m) => (0x28, m),
+            MemoryInstr::ILoad(IntType::I64, m) => (0x29, m),
+            MemoryInstr::FLoad(FloatType::F32, m) => (0x2A, m),
+            MemoryInstr::FLoad(FloatType::F64, m) => (0x2B, m),
+            MemoryInstr::ILoad8(IntType::I32, Sign::Signed, m) => (0x2C, m),
+            MemoryInstr::ILoad8(IntType::I32, Sign::Unsigned, m) => (0x2D, m),
+            MemoryInstr::ILoad16(IntType::I32, Sign::Signed, m) => (0x2E, m),
+            MemoryInstr::ILoad16(IntType::I32, Sign::Unsigned, m) => (0x2F, m),
+            MemoryInstr::ILoad8(IntType::I64, Sign::Signed, m) => (0x30, m),
+            MemoryInstr::ILoad8(IntType::I64, Sign::Unsigned, m) => (0x31, m),
+            MemoryInstr::ILoad16(IntType::I64, Sign::Signed, m) => (0x32, m),
+            MemoryInstr::ILoad16(IntType::I64, Sign::Unsigned, m) => (0x33, m),
+            MemoryInstr::I64Load32(Sign::Signed, m) => (0x34, m),
+            MemoryInstr::I64Load32(Sign::Unsigned, m) => (0x35, m),
+            MemoryInstr::IStore(IntType::I32, m) => (0x36, m),
+            MemoryInstr::IStore(IntType::I64, m) => (0x37, m),
+            MemoryInstr::FStore(FloatType::F32, m) => (0x38, m),
+            MemoryInstr::FStore(FloatType::F64, m) => (0x39, m),
+            MemoryInstr::IStore8(IntType::I32, m) => (0x3A, m),
+            MemoryInstr::IStore16(IntType::I32, m) => (0x3B, m),
+            MemoryInstr::IStore8(IntType::I64, m) => (0x3C, m),
+            MemoryInstr::IStore16(IntType::I64, m) => (0x3D, m),
+            MemoryInstr::I64Store32(m) => (0x3E, m),
+            MemoryInstr::MemorySize(x) => {
+                out.push(0x3F);
+                return x.encode(out);
+            }
+            MemoryInstr::MemoryGrow(x) => {
+                out.push(0x40);
+                return x.encode(out);
+            }
+            MemoryInstr::MemoryInit(x, y) => {
+                write_prefixed(8, out);
+                y.encode(out);
+                return x.encode(out);
+            }
+            MemoryInstr::DataDrop(x) => {
+                write_prefixed(9, out);
+                return x.encode(out);
+            }
+            MemoryInstr::MemoryCopy(x, y) => {
+                write_prefixed(10, out);
+                x.encode(out);
+                return y.encode(out);
+            }
+            MemoryInstr::MemoryFill(x) => {
+                write_prefixed(11, out);
+                return x.encode(out);
+            }
+        };
+        out.push(opcode);
+        memarg.encode(out);
+    }
+}
+
+fn iunop<const T: IntType>(op: &IUnop<T>) -> u8 {
+    match op {
+        IUnop::Clz => 0,
+        IUnop::Ctz => 1,
+        IUnop::Popcnt => 2,
+    }
+}
+
+fn ibinop<const T: IntType>(op: &IBinop<T>) -> u8 {
+    match op {
+        IBinop::Add => 0,
+        IBinop::Sub => 1,
+        IBinop::Mul => 2,
+        IBinop::DivS => 3,
+        IBinop::DivU => 4,
+        IBinop::RemS => 5,
+        IBinop::RemU => 6,
+        IBinop::And => 7,
+        IBinop::Or => 8,
+        IBinop::Xor => 9,
+        IBinop::Shl => 10,
+        IBinop::ShrS => 11,
+        IBinop::ShrU => 12,
+        IBinop::Rotl => 13,
+        IBinop::Rotr => 14,
+    }
+}
+
+fn funop<const T: FloatType>(op: &FUnop<T>) -> u8 {
+    match op {
+        FUnop::Abs => 0,
+        FUnop::Neg => 1,
+        FUnop::Ceil => 2,
+        FUnop::Floor => 3,
+        FUnop::Trunc => 4,
+        FUnop::Nearest => 5,
+        FUnop::Sqrt => 6,
+    }
+}
+
+fn fbinop<const T: FloatType>(op: &FBinop<T>) -> u8 {
+    match op {
+        FBinop::Add => 0,
+        FBinop::Sub => 1,
+        FBinop::Mul => 2,
+        FBinop::Div => 3,
+        FBinop::Min => 4,
+        FBinop::Max => 5,
+        FBinop::CopySign => 6,
+    }
+}
+
+fn itestop<const T: IntType>(op: &ITestop<T>) -> u8 {
+    match op {
+        ITestop::Eqz => 0,
+    }
+}
+
+fn irelop<const T: IntType>(op: &IRelop<T>) -> u8 {
+    match op {
+        IRelop::Equ => 0,
+        IRelop::Ne => 1,
+        IRelop::LtS => 2,
+        IRelop::LtU => 3,
+        IRelop::GtS => 4,
+        IRelop::GtU => 5,
+        IRelop::LeS => 6,
+        IRelop::LeU => 7,
+        IRelop::GeS => 8,
+        IRelop::GeU => 9,
+    }
+}
+
+fn frelop<const T: FloatType>(op: &FRelop<T>) -> u8 {
+    match op {
+        FRelop::Equ => 0,
+        FRelop::Ne => 1,
+        FRelop::Lt => 2,
+        FRelop::Gt => 3,
+        FRelop::Le => 4,
+        FRelop::Ge => 5,
+    }
+}
+
+impl Encode for NumericInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let opcode = match self {
+            NumericInstr::I32Const(x) => {
+                out.push(0x41);
+                return x.encode(out);
+            }
+            NumericInstr::I64Const(x) => {
+                out.push(0x42);
+                return x.encode(out);
+            }
+            NumericInstr::F32Const(x) => {
+                out.push(0x43);
+                return x.encode(out);
+            }
+            NumericInstr::F64Const(x) => {
+                out.push(0x44);
+                return x.encode(out);
+            }
+            NumericInstr::I32Test(op) => 0x45 + itestop(op),
+            NumericInstr::I32Relop(op) => 0x46 + irelop(op),
+            NumericInstr::I64Test(op) => 0x50 + itestop(op),
+            NumericInstr::I64Relop(op) => 0x51 + irelop(op),
+            NumericInstr::F32Relop(op) => 0x5B + frelop(op),
+            NumericInstr::F64Relop(op) => 0x61 + frelop(op),
+            NumericInstr::I32Unary(op) => 0x67 + iunop(op),
+            NumericInstr::I32Binary(op) => 0x6A + ibinop(op),
+            NumericInstr::I64Unary(op) => 0x79 + iunop(op),
+            NumericInstr::I64Binary(op) => 0x7C + ibinop(op),
+            NumericInstr::F32Unary(op) => 0x8B + funop(op),
+            NumericInstr::F32Binary(op) => 0x92 + fbinop(op),
+            NumericInstr::F64Unary(op) => 0x99 + funop(op),
+            NumericInstr::F64Binary(op) => 0xA0 + fbinop(op),
+            NumericInstr::I32WrapI64 => 0xA7,
+            NumericInstr::I32TruncF32S => 0xA8,
+            NumericInstr::I32TruncF32U => 0xA9,
+            NumericInstr::I32TruncF64S => 0xAA,
+            NumericInstr::I32TruncF64U => 0xAB,
+            NumericInstr::I64ExtendI32S => 0xAC,
+            NumericInstr::I64ExtendI32U => 0xAD,
+            NumericInstr::I64TruncF32S => 0xAE,
+            NumericInstr::I64TruncF32U => 0xAF,
+            NumericInstr::I64TruncF64S => 0xB0,
+            NumericInstr::I64TruncF64U => 0xB1,
+            NumericInstr::F32ConvertI32S => 0xB2,
+            NumericInstr::F32ConvertI32U => 0xB3,
+            NumericInstr::F32ConvertI64S => 0xB4,
+            NumericInstr::F32ConvertI64U => 0xB5,
+            NumericInstr::F32DemoteF64 => 0xB6,
+            NumericInstr::F64ConvertI32S => 0xB7,
+            NumericInstr::F64ConvertI32U => 0xB8,
+            NumericInstr::F64ConvertI64S => 0xB9,
+            NumericInstr::F64ConvertI64U => 0xBA,
+            NumericInstr::F64PromoteF32 => 0xBB,
+            NumericInstr::I32ReinterpretF32 => 0xBC,
+            NumericInstr::I64ReinterpretF64 => 0xBD,
+            NumericInstr::F32ReinterpretI32 => 0xBE,
+            NumericInstr::F64ReinterpretI64 => 0xBF,
+            NumericInstr::I32Extend8S => 0xC0,
+            NumericInstr::I32Extend16S => 0xC1,
+            NumericInstr::I64Extend8S => 0xC2,
+            NumericInstr::I64Extend16S => 0xC3,
+            NumericInstr::I64Extend32S => 0xC4,
+            NumericInstr::I32TruncSatF32S => return write_prefixed(0, out),
+            NumericInstr::I32TruncSatF32U => return write_prefixed(1, out),
+            NumericInstr::I32TruncSatF64S => return write_prefixed(2, out),
+            NumericInstr::I32TruncSatF64U => return write_prefixed(3, out),
+            NumericInstr::I64TruncSatF32S => return write_prefixed(4, out),
+            NumericInstr::I64TruncSatF32U => return write_prefixed(5, out),
+            NumericInstr::I64TruncSatF64S => return write_prefixed(6, out),
+            NumericInstr::I64TruncSatF64U => return write_prefixed(7, out),
+        };
+        out.push(opcode);
+    }
+}
+
+impl Encode for VectorInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0xFD);
+        write_unsigned(vector_opcode(self).into(), out);
+        match self {
+            VectorInstr::V128Const(x) => x.encode(out),
+            VectorInstr::I8x16Shuffle(lanes) => {
+                for lane in lanes {
+                    lane.encode(out);
+                }
+            }
+            VectorInstr::V128Load(m)
+            | VectorInstr::V128Load8x8S(m)
+            | VectorInstr::V128Load8x8U(m)
+            | VectorInstr::V128Load16x4S(m)
+            | VectorInstr::V128Load16x4U(m)
+            | VectorInstr::V128Load32x2S(m)
+            | VectorInstr::V128Load32x2U(m)
+            | VectorInstr::V128Load8Splat(m)
+            | VectorInstr::V128Load16Splat(m)
+            | VectorInstr::V128Load32Splat(m)
+            | VectorInstr::V128Load64Splat(m)
+            | VectorInstr::V128Store(m)
+            | VectorInstr::V128Load32Zero(m)
+            | VectorInstr::V128Load64Zero(m) => m.encode(out),
+            VectorInstr::V128Load8Lane(m, lane)
+            | VectorInstr::V128Load16Lane(m, lane)
+            | VectorInstr::V128Load32Lane(m, lane)
+            | VectorInstr::V128Load64Lane(m, lane)
+            | VectorInstr::V128Store8Lane(m, lane)
+            | VectorInstr::V128Store16Lane(m, lane)
+            | VectorInstr::V128Store32Lane(m, lane)
+            | VectorInstr::V128Store64Lane(m, lane) => {
+                m.encode(out);
+                lane.encode(out);
+            }
+            VectorInstr::I8x16ExtractLaneS(lane)
+            | VectorInstr::I8x16ExtractLaneU(lane)
+            | VectorInstr::I8x16ReplaceLane(lane)
+            | VectorInstr::I16x8ExtractLaneS(lane)
+            | VectorInstr::I16x8ExtractLaneU(lane)
+            | VectorInstr::I16x8ReplaceLane(lane)
+            | VectorInstr::I32x4ExtractLane(lane)
+            | VectorInstr::I32x4ReplaceLane(lane)
+            | VectorInstr::I64x2ExtractLane(lane)
+            | VectorInstr::I64x2ReplaceLane(lane)
+            | VectorInstr::F32x4ExtractLane(lane)
+            | VectorInstr::F32x4ReplaceLane(lane)
+            | VectorInstr::F64x2ExtractLane(lane)
+            | VectorInstr::F64x2ReplaceLane(lane) => lane.encode(out),
+            _ => {}
+        }
+    }
+}
+
+fn vector_opcode(instr: &VectorInstr) -> u32 {
+    match instr {
+        VectorInstr::V128Load(_) => 0x00,
+        VectorInstr::V128Load8x8S(_) => 0x01,
+        VectorInstr::V128Load8x8U(_) => 0x02,
+        VectorInstr::V128Load16x4S(_) => 0x03,
+        VectorInstr::V128Load16x4U(_) => 0x04,
+        VectorInstr::V128Load32x2S(_) => 0x05,
+        VectorInstr::V128Load32x2U(_) => 0x06,
+        VectorInstr::V128Load8Splat(_) => 0x07,
+        VectorInstr::V128Load16Splat(_) => 0x08,
+        VectorInstr::V128Load32Splat(_) => 0x09,
+        VectorInstr::V128Load64Splat(_) => 0x0A,
+        VectorInstr::V128Store(_) => 0x0B,
+        VectorInstr::V128Const(_) => 0x0C,
+        VectorInstr::I8x16Shuffle(_) => 0x0D,
+        VectorInstr::I8x16Swizzle => 0x0E,
+        VectorInstr::I8x16Splat => 0x0F,
+        VectorInstr::I16x8Splat => 0x10,
+        VectorInstr::I32x4Splat => 0x11,
+        VectorInstr::I64x2Splat => 0x12,
+        VectorInstr::F32x4Splat => 0x13,
+        VectorInstr::F64x2Splat => 0x14,
+        VectorInstr::I8x16ExtractLaneS(_) => 0x15,
+        VectorInstr::I8x16ExtractLaneU(_) => 0x16,
+        VectorInstr::I8x16ReplaceLane(_) => 0x17,
+        VectorInstr::I16x8ExtractLaneS(_) => 0x18,
+        VectorInstr::I16x8ExtractLaneU(_) => 0x19,
+        VectorInstr::I16x8ReplaceLane(_) => 0x1A,
+        VectorInstr::I32x4ExtractLane(_) => 0x1B,
+        VectorInstr::I32x4ReplaceLane(_) => 0x1C,
+        VectorInstr::I64x2ExtractLane(_) => 0x1D,
+        VectorInstr::I64x2ReplaceLane(_) => 0x1E,
+        VectorInstr::F32x4ExtractLane(_) => 0x1F,
+        VectorInstr::F32x4ReplaceLane(_) => 0x20,
+        VectorInstr::F64x2ExtractLane(_) => 0x21,
+        VectorInstr::F64x2ReplaceLane(_) => 0x22,
+        VectorInstr::I8x16Eq => 0x23,
+        VectorInstr::I8x16Ne => 0x24,
+        VectorInstr::I8x16LtS => 0x25,
+        VectorInstr::I8x16LtU => 0x26,
+        VectorInstr::I8x16GtS => 0x27,
+        VectorInstr::I8x16GtU => 0x28,
+        VectorInstr::I8x16LeS => 0x29,
+        VectorInstr::I8x16LeU => 0x2A,
+        VectorInstr::I8x16GeS => 0x2B,
+        VectorInstr::I8x16GeU => 0x2C,
+        VectorInstr::I16x8Eq => 0x2D,
+        VectorInstr::I16x8Ne => 0x2E,
+        VectorInstr::I16x8LtS => 0x2F,
+        VectorInstr::I16x8LtU => 0x30,
+        VectorInstr::I16x8GtS => 0x31,
+        VectorInstr::I16x8GtU => 0x32,
+        VectorInstr::I16x8LeS => 0x33,
+        VectorInstr::I16x8LeU => 0x34,
+        VectorInstr::I16x8GeS => 0x35,
+        VectorInstr::I16x8GeU => 0x36,
+        VectorInstr::I32x4Eq => 0x37,
+        VectorInstr::I32x4Ne => 0x38,
+        VectorInstr::I32x4LtS => 0x39,
+        VectorInstr::I32x4LtU => 0x3A,
+        VectorInstr::I32x4GtS => 0x3B,
+        VectorInstr::I32x4GtU => 0x3C,
+        VectorInstr::I32x4LeS => 0x3D,
+        VectorInstr::I32x4LeU => 0x3E,
+        VectorInstr::I32x4GeS => 0x3F,
+        VectorInstr::I32x4GeU => 0x40,
+        VectorInstr::F32x4Eq => 0x41,
+        VectorInstr::F32x4Ne => 0x42,
+        VectorInstr::F32x4Lt => 0x43,
+        VectorInstr::F32x4Gt => 0x44,
+        VectorInstr::F32x4Le => 0x45,
+        VectorInstr::F32x4Ge => 0x46,
+        VectorInstr::F64x2Eq => 0x47,
+        VectorInstr::F64x2Ne => 0x48,
+        VectorInstr::F64x2Lt => 0x49,
+        VectorInstr::F64x2Gt => 0x4A,
+        VectorInstr::F64x2Le => 0x4B,
+        VectorInstr::F64x2Ge => 0x4C,
+        VectorInstr::V128Not => 0x4D,
+        VectorInstr::V128And => 0x4E,
+        VectorInstr::V128Andnot => 0x4F,
+        VectorInstr::V128Or => 0x50,
+        VectorInstr::V128Xor => 0x51,
+        VectorInstr::V128Bitselect => 0x52,
+        VectorInstr::V128AnyTrue => 0x53,
+        VectorInstr::V128Load8Lane(..) => 0x54,
+        VectorInstr::V128Load16Lane(..) => 0x55,
+        VectorInstr::V128Load32Lane(..) => 0x56,
+        VectorInstr::V128Load64Lane(..) => 0x57,
+        VectorInstr::V128Store8Lane(..) => 0x58,
+        VectorInstr::V128Store16Lane(..) => 0x59,
+        VectorInstr::V128Store32Lane(..) => 0x5A,
+        VectorInstr::V128Store64Lane(..) => 0x5B,
+        VectorInstr::V128Load32Zero(_) => 0x5C,
+        VectorInstr::V128Load64Zero(_) => 0x5D,
+        VectorInstr::F32x4DemoteF64x2Zero => 0x5E,
+        VectorInstr::F64x2PromoteLowF32x4 => 0x5F,
+        VectorInstr::I8x16Abs => 0x60,
+        VectorInstr::I8x16Neg => 0x61,
+        VectorInstr::I8x16Popcnt => 0x62,
+        VectorInstr::I8x16AllTrue => 0x63,
+        VectorInstr::I8x16Bitmask => 0x64,
+        VectorInstr::I8x16NarrowI16x8S => 0x65,
+        VectorInstr::I8x16NarrowI16x8U => 0x66,
+        VectorInstr::F32x4Ceil => 0x67,
+        VectorInstr::F32x4Floor => 0x68,
+        VectorInstr::F32x4Trunc => 0x69,
+        VectorInstr::F32x4Nearest => 0x6A,
+        VectorInstr::I8x16Shl => 0x6B,
+        VectorInstr::I8x16ShrS => 0x6C,
+        VectorInstr::I8x16ShrU => 0x6D,
+        VectorInstr::I8x16Add => 0x6E,
+        VectorInstr::I8x16AddSatS => 0x6F,
+        VectorInstr::I8x16AddSatU => 0x70,
+        VectorInstr::I8x16Sub => 0x71,
+        VectorInstr::I8x16SubSatS => 0x72,
+        VectorInstr::I8x16SubSatU => 0x73,
+        VectorInstr::F64x2Ceil => 0x74,
+        VectorInstr::F64x2Floor => 0x75,
+        VectorInstr::I8x16MinS => 0x76,
+        VectorInstr::I8x16MinU => 0x77,
+        VectorInstr::I8x16MaxS => 0x78,
+        VectorInstr::I8x16MaxU => 0x79,
+        VectorInstr::F64x2Trunc => 0x7A,
+        VectorInstr::I8x16AvgrU => 0x7B,
+        VectorInstr::I16x8ExtaddPairwiseI8x16S => 0x7C,
+        VectorInstr::I16x8ExtaddPairwiseI8x16U => 0x7D,
+        VectorInstr::I32x4ExtaddPairwiseI16x8S => 0x7E,
+        VectorInstr::I32x4ExtaddPairwiseI16x8U => 0x7F,
+        VectorInstr::I16x8Abs => 0x80,
+        VectorInstr::I16x8Neg => 0x81,
+        VectorInstr::I16x8Q15mulrSatS => 0x82,
+        VectorInstr::I16x8AllTrue => 0x83,
+        VectorInstr::I16x8Bitmask => 0x84,
+        VectorInstr::I16x8NarrowI32x4S => 0x85,
+        VectorInstr::I16x8NarrowI32x4U => 0x86,
+        VectorInstr::I16x8ExtendLowI8x16S => 0x87,
+        VectorInstr::I16x8ExtendHighI8x16S => 0x88,
+        VectorInstr::I16x8ExtendLowI8x16U => 0x89,
+        VectorInstr::I16x8ExtendHighI8x16U => 0x8A,
+        VectorInstr::I16x8Shl => 0x8B,
+        VectorInstr::I16x8ShrS => 0x8C,
+        VectorInstr::I16x8ShrU => 0x8D,
+        VectorInstr::I16x8Add => 0x8E,
+        VectorInstr::I16x8AddSatS => 0x8F,
+        VectorInstr::I16x8AddSatU => 0x90,
+        VectorInstr::I16x8Sub => 0x91,
+        VectorInstr::I16x8SubSatS => 0x92,
+        VectorInstr::I16x8SubSatU => 0x93,
+        VectorInstr::F64x2Nearest => 0x94,
+        VectorInstr::I16x8Mul => 0x95,
+        VectorInstr::I16x8MinS => 0x96,
+        VectorInstr::I16x8MinU => 0x97,
+        VectorInstr::I16x8MaxS => 0x98,
+        VectorInstr::I16x8MaxU => 0x99,
+        VectorInstr::I16x8AvgrU => 0x9B,
+        VectorInstr::I16x8ExtmulLowI8x16S => 0x9C,
+        VectorInstr::I16x8ExtmulHighI8x16S => 0x9D,
+        VectorInstr::I16x8ExtmulLowI8x16U => 0x9E,
+        VectorInstr::I16x8ExtmulHighI8x16U => 0x9F,
+        VectorInstr::I32x4Abs => 0xA0,
+        VectorInstr::I32x4Neg => 0xA1,
+        VectorInstr::I32x4AllTrue => 0xA3,
+        VectorInstr::I32x4Bitmask => 0xA4,
+        VectorInstr::I32x4ExtendLowI16x8S => 0xA7,
+        VectorInstr::I32x4ExtendHighI16x8S => 0xA8,
+        VectorInstr::I32x4ExtendLowI16x8U => 0xA9,
+        VectorInstr::I32x4ExtendHighI16x8U => 0xAA,
+        VectorInstr::I32x4Shl => 0xAB,
+        VectorInstr::I32x4ShrS => 0xAC,
+        VectorInstr::I32x4ShrU => 0xAD,
+        VectorInstr::I32x4Add => 0xAE,
+        VectorInstr::I32x4Sub => 0xB1,
+        VectorInstr::I32x4Mul => 0xB5,
+        VectorInstr::I32x4MinS => 0xB6,
+        VectorInstr::I32x4MinU => 0xB7,
+        VectorInstr::I32x4MaxS => 0xB8,
+        VectorInstr::I32x4MaxU => 0xB9,
+        VectorInstr::I32x4DotI16x8S => 0xBA,
+        VectorInstr::I32x4ExtmulLowI16x8S => 0xBC,
+        VectorInstr::I32x4ExtmulHighI16x8S => 0xBD,
+        VectorInstr::I32x4ExtmulLowI16x8U => 0xBE,
+        VectorInstr::I32x4ExtmulHighI16x8U => 0xBF,
+        VectorInstr::I64x2Abs => 0xC0,
+        VectorInstr::I64x2Neg => 0xC1,
+        VectorInstr::I64x2AllTrue => 0xC3,
+        VectorInstr::I64x2Bitmask => 0xC4,
+        VectorInstr::I64x2ExtendLowI32x4S => 0xC7,
+        VectorInstr::I64x2ExtendHighI32x4S => 0xC8,
+        VectorInstr::I64x2ExtendLowI32x4U => 0xC9,
+        VectorInstr::I64x2ExtendHighI32x4U => 0xCA,
+        VectorInstr::I64x2Shl => 0xCB,
+        VectorInstr::I64x2ShrS => 0xCC,
+        VectorInstr::I64x2ShrU => 0xCD,
+        VectorInstr::I64x2Add => 0xCE,
+        VectorInstr::I64x2Sub => 0xD1,
+        VectorInstr::I64x2Mul => 0xD5,
+        VectorInstr::I64x2Eq => 0xD6,
+        VectorInstr::I64x2Ne => 0xD7,
+        VectorInstr::I64x2LtS => 0xD8,
+        VectorInstr::I64x2GtS => 0xD9,
+        VectorInstr::I64x2LeS => 0xDA,
+        VectorInstr::I64x2GeS => 0xDB,
+        VectorInstr::I64x2ExtmulLowI32x4S => 0xDC,
+        VectorInstr::I64x2ExtmulHighI32x4S => 0xDD,
+        VectorInstr::I64x2ExtmulLowI32x4U => 0xDE,
+        VectorInstr::I64x2ExtmulHighI32x4U => 0xDF,
+        VectorInstr::F32x4Abs => 0xE0,
+        VectorInstr::F32x4Neg => 0xE1,
+        VectorInstr::F32x4Sqrt => 0xE3,
+        VectorInstr::F32x4Add => 0xE4,
+        VectorInstr::F32x4Sub => 0xE5,
+        VectorInstr::F32x4Mul => 0xE6,
+        VectorInstr::F32x4Div => 0xE7,
+        VectorInstr::F32x4Min => 0xE8,
+        VectorInstr::F32x4Max => 0xE9,
+        VectorInstr::F32x4Pmin => 0xEA,
+        VectorInstr::F32x4Pmax => 0xEB,
+        VectorInstr::F64x2Abs => 0xEC,
+        VectorInstr::F64x2Neg => 0xED,
+        VectorInstr::F64x2Sqrt => 0xEF,
+        VectorInstr::F64x2Add => 0xF0,
+        VectorInstr::F64x2Sub => 0xF1,
+        VectorInstr::F64x2Mul => 0xF2,
+        VectorInstr::F64x2Div => 0xF3,
+        VectorInstr::F64x2Min => 0xF4,
+        VectorInstr::F64x2Max => 0xF5,
+        VectorInstr::F64x2Pmin => 0xF6,
+        VectorInstr::F64x2Pmax => 0xF7,
+        VectorInstr::I32x4TruncSatF32x4S => 0xF8,
+        VectorInstr::I32x4TruncSatF32x4U => 0xF9,
+        VectorInstr::F32x4ConvertI32x4S => 0xFA,
+        VectorInstr::F32x4ConvertI32x4U => 0xFB,
+        VectorInstr::I32x4TruncSatF64x2SZero => 0xFC,
+        VectorInstr::I32x4TruncSatF64x2UZero => 0xFD,
+        VectorInstr::F64x2ConvertLowI32x4S => 0xFE,
+        VectorInstr::F64x2ConvertLowI32x4U => 0xFF,
+    }
+}
+
+impl Encode for AtomicInstr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0xFE);
+        write_unsigned(atomic_opcode(self).into(), out);
+        match self {
+            AtomicInstr::AtomicFence => out.push(0x00),
+            AtomicInstr::MemoryAtomicNotify(m)
+            | AtomicInstr::MemoryAtomicWait32(m)
+            | AtomicInstr::MemoryAtomicWait64(m)
+            | AtomicInstr::I32AtomicLoad(m)
+            | AtomicInstr::I64AtomicLoad(m)
+            | AtomicInstr::I32AtomicLoad8U(m)
+            | AtomicInstr::I32AtomicLoad16U(m)
+            | AtomicInstr::I64AtomicLoad8U(m)
+            | AtomicInstr::I64AtomicLoad16U(m)
+            | AtomicInstr::I64AtomicLoad32U(m)
+            | AtomicInstr::I32AtomicStore(m)
+            | AtomicInstr::I64AtomicStore(m)
+            | AtomicInstr::I32AtomicStore8(m)
+            | AtomicInstr::I32AtomicStore16(m)
+            | AtomicInstr::I64AtomicStore8(m)
+            | AtomicInstr::I64AtomicStore16(m)
+            | AtomicInstr::I64AtomicStore32(m)
+            | AtomicInstr::I32AtomicRmwAdd(m)
+            | AtomicInstr::I64AtomicRmwAdd(m)
+            | AtomicInstr::I32AtomicRmw8AddU(m)
+            | AtomicInstr::I32AtomicRmw16AddU(m)
+            | AtomicInstr::I64AtomicRmw8AddU(m)
+            | AtomicInstr::I64AtomicRmw16AddU(m)
+            | AtomicInstr::I64AtomicRmw32AddU(m)
+            | AtomicInstr::I32AtomicRmwSub(m)
+            | AtomicInstr::I64AtomicRmwSub(m)
+            | AtomicInstr::I32AtomicRmw8SubU(m)
+            | AtomicInstr::I32AtomicRmw16SubU(m)
+            | AtomicInstr::I64AtomicRmw8SubU(m)
+            | AtomicInstr::I64AtomicRmw16SubU(m)
+            | AtomicInstr::I64AtomicRmw32SubU(m)
+            | AtomicInstr::I32AtomicRmwAnd(m)
+            | AtomicInstr::I64AtomicRmwAnd(m)
+            | AtomicInstr::I32AtomicRmw8AndU(m)
+            | AtomicInstr::I32AtomicRmw16AndU(m)
+            | AtomicInstr::I64AtomicRmw8AndU(m)
+            | AtomicInstr::I64AtomicRmw16AndU(m)
+            | AtomicInstr::I64AtomicRmw32AndU(m)
+            | AtomicInstr::I32AtomicRmwOr(m)
+            | AtomicInstr::I64AtomicRmwOr(m)
+            | AtomicInstr::I32AtomicRmw8OrU(m)
+            | AtomicInstr::I32AtomicRmw16OrU(m)
+            | AtomicInstr::I64AtomicRmw8OrU(m)
+            | AtomicInstr::I64AtomicRmw16OrU(m)
+            | AtomicInstr::I64AtomicRmw32OrU(m)
+            | AtomicInstr::I32AtomicRmwXor(m)
+            | AtomicInstr::I64AtomicRmwXor(m)
+            | AtomicInstr::I32AtomicRmw8XorU(m)
+            | AtomicInstr::I32AtomicRmw16XorU(m)
+            | AtomicInstr::I64AtomicRmw8XorU(m)
+            | AtomicInstr::I64AtomicRmw16XorU(m)
+            | AtomicInstr::I64AtomicRmw32XorU(m)
+            | AtomicInstr::I32AtomicRmwXchg(m)
+            | AtomicInstr::I64AtomicRmwXchg(m)
+            | AtomicInstr::I32AtomicRmw8XchgU(m)
+            | AtomicInstr::I32AtomicRmw16XchgU(m)
+            | AtomicInstr::I64AtomicRmw8XchgU(m)
+            | AtomicInstr::I64AtomicRmw16XchgU(m)
+            | AtomicInstr::I64AtomicRmw32XchgU(m)
+            | AtomicInstr::I32AtomicRmwCmpxchg(m)
+            | AtomicInstr::I64AtomicRmwCmpxchg(m)
+            | AtomicInstr::I32AtomicRmw8CmpxchgU(m)
+            | AtomicInstr::I32AtomicRmw16CmpxchgU(m)
+            | AtomicInstr::I64AtomicRmw8CmpxchgU(m)
+            | AtomicInstr::I64AtomicRmw16CmpxchgU(m)
+            | AtomicInstr::I64AtomicRmw32CmpxchgU(m) => m.encode(out),
+        }
+    }
+}
+
+/// Opcode of an atomic instruction, after the `0xFE` prefix.
+fn atomic_opcode(instr: &AtomicInstr) -> u32 {
+    match instr {
+        AtomicInstr::MemoryAtomicNotify(_) => 0x00,
+        AtomicInstr::MemoryAtomicWait32(_) => 0x01,
+        AtomicInstr::MemoryAtomicWait64(_) => 0x02,
+        AtomicInstr::AtomicFence => 0x03,
+        AtomicInstr::I32AtomicLoad(_) => 0x10,
+        AtomicInstr::I64AtomicLoad(_) => 0x11,
+        AtomicInstr::I32AtomicLoad8U(_) => 0x12,
+        AtomicInstr::I32AtomicLoad16U(_) => 0x13,
+        AtomicInstr::I64AtomicLoad8U(_) => 0x14,
+        AtomicInstr::I64AtomicLoad16U(_) => 0x15,
+        AtomicInstr::I64AtomicLoad32U(_) => 0x16,
+        AtomicInstr::I32AtomicStore(_) => 0x17,
+        AtomicInstr::I64AtomicStore(_) => 0x18,
+        AtomicInstr::I32AtomicStore8(_) => 0x19,
+        AtomicInstr::I32AtomicStore16(_) => 0x1A,
+        AtomicInstr::I64AtomicStore8(_) => 0x1B,
+        AtomicInstr::I64AtomicStore16(_) => 0x1C,
+        AtomicInstr::I64AtomicStore32(_) => 0x1D,
+        AtomicInstr::I32AtomicRmwAdd(_) => 0x1E,
+        AtomicInstr::I64AtomicRmwAdd(_) => 0x1F,
+        AtomicInstr::I32AtomicRmw8AddU(_) => 0x20,
+        AtomicInstr::I32AtomicRmw16AddU(_) => 0x21,
+        AtomicInstr::I64AtomicRmw8AddU(_) => 0x22,
+        AtomicInstr::I64AtomicRmw16AddU(_) => 0x23,
+        AtomicInstr::I64AtomicRmw32AddU(_) => 0x24,
+        AtomicInstr::I32AtomicRmwSub(_) => 0x25,
+        AtomicInstr::I64AtomicRmwSub(_) => 0x26,
+        AtomicInstr::I32AtomicRmw8SubU(_) => 0x27,
+        AtomicInstr::I32AtomicRmw16SubU(_) => 0x28,
+        AtomicInstr::I64AtomicRmw8SubU(_) => 0x29,
+        AtomicInstr::I64AtomicRmw16SubU(_) => 0x2A,
+        AtomicInstr::I64AtomicRmw32SubU(_) => 0x2B,
+        AtomicInstr::I32AtomicRmwAnd(_) => 0x2C,
+        AtomicInstr::I64AtomicRmwAnd(_) => 0x2D,
+        AtomicInstr::I32AtomicRmw8AndU(_) => 0x2E,
+        AtomicInstr::I32AtomicRmw16AndU(_) => 0x2F,
+        AtomicInstr::I64AtomicRmw8AndU(_) => 0x30,
+        AtomicInstr::I64AtomicRmw16AndU(_) => 0x31,
+        AtomicInstr::I64AtomicRmw32AndU(_) => 0x32,
+        AtomicInstr::I32AtomicRmwOr(_) => 0x33,
+        AtomicInstr::I64AtomicRmwOr(_) => 0x34,
+        AtomicInstr::I32AtomicRmw8OrU(_) => 0x35,
+        AtomicInstr::I32AtomicRmw16OrU(_) => 0x36,
+        AtomicInstr::I64AtomicRmw8OrU(_) => 0x37,
+        AtomicInstr::I64AtomicRmw16OrU(_) => 0x38,
+        AtomicInstr::I64AtomicRmw32OrU(_) => 0x39,
+        AtomicInstr::I32AtomicRmwXor(_) => 0x3A,
+        AtomicInstr::I64AtomicRmwXor(_) => 0x3B,
+        AtomicInstr::I32AtomicRmw8XorU(_) => 0x3C,
+        AtomicInstr::I32AtomicRmw16XorU(_) => 0x3D,
+        AtomicInstr::I64AtomicRmw8XorU(_) => 0x3E,
+        AtomicInstr::I64AtomicRmw16XorU(_) => 0x3F,
+        AtomicInstr::I64AtomicRmw32XorU(_) => 0x40,
+        AtomicInstr::I32AtomicRmwXchg(_) => 0x41,
+        AtomicInstr::I64AtomicRmwXchg(_) => 0x42,
+        AtomicInstr::I32AtomicRmw8XchgU(_) => 0x43,
+        AtomicInstr::I32AtomicRmw16XchgU(_) => 0x44,
+        AtomicInstr::I64AtomicRmw8XchgU(_) => 0x45,
+        AtomicInstr::I64AtomicRmw16XchgU(_) => 0x46,
+        AtomicInstr::I64AtomicRmw32XchgU(_) => 0x47,
+        AtomicInstr::I32AtomicRmwCmpxchg(_) => 0x48,
+        AtomicInstr::I64AtomicRmwCmpxchg(_) => 0x49,
+        AtomicInstr::I32AtomicRmw8CmpxchgU(_) => 0x4A,
+        AtomicInstr::I32AtomicRmw16CmpxchgU(_) => 0x4B,
+        AtomicInstr::I64AtomicRmw8CmpxchgU(_) => 0x4C,
+        AtomicInstr::I64AtomicRmw16CmpxchgU(_) => 0x4D,
+        AtomicInstr::I64AtomicRmw32CmpxchgU(_) => 0x4E,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::{Parse, ParsingData, WasmBinary};
+
+    /// Checks that every byte sequence parses and encodes back into itself.
+    fn roundtrip(bytes: &[u8]) {
+        let wasm: WasmBinary = bytes.into();
+        let mut wasm = ParsingData::new(&wasm);
+        let instr = Instr::parse(&mut wasm).unwrap();
+        assert!(wasm.is_empty());
+        assert_eq!(instr.to_bytes(), bytes, "{:?}", instr);
+    }
+
+    #[test]
+    fn single_byte_instructions() {
+        let skip = [
+            0x02, 0x03, 0x04, 0x0C, 0x0D, 0x0E, 0x10, 0x11, 0x1C, 0x3F, 0x40, 0x41, 0x42, 0x43,
+            0x44,
+        ];
+        let opcodes = [0x00, 0x01, 0x0F, 0x1A, 0x1B, 0xD1]
+            .iter()
+            .copied()
+            .chain((0x45..=0xC4).filter(|x| !skip.contains(x)));
+        for opcode in opcodes {
+            roundtrip(&[opcode]);
+        }
+    }
+
+    #[test]
+    fn instructions_with_immediates() {
+        roundtrip(&[0x02, 0x40, 0x01, 0x0B]);
+        roundtrip(&[0x03, 0x7F, 0x0C, 0x00, 0x0B]);
+        roundtrip(&[0x02, 0x05, 0x0B]);
+        roundtrip(&[0x04, 0x40, 0x01, 0x0B]);
+        roundtrip(&[0x04, 0x40, 0x01, 0x05, 0x00, 0x0B]);
+        roundtrip(&[0x0E, 0x02, 0x00, 0x01, 0x02]);
+        roundtrip(&[0x11, 0x03, 0x01]);
+        roundtrip(&[0x12, 0x02]);
+        roundtrip(&[0x13, 0x03, 0x01]);
+        roundtrip(&[0x1C, 0x01, 0x7F]);
+        roundtrip(&[0x28, 0x02, 0x80, 0x01]);
+        roundtrip(&[0x28, 0x42, 0x01, 0x00]);
+        roundtrip(&[0x28, 0x02, 0x80, 0x80, 0x80, 0x80, 0x10]);
+        roundtrip(&[0x3F, 0x00]);
+        roundtrip(&[0x3F, 0x01]);
+        roundtrip(&[0x40, 0x00]);
+        roundtrip(&[0x41, 0x7F]);
+        roundtrip(&[0x42, 0x80, 0x7F]);
+        roundtrip(&[0x43, 0x00, 0x00, 0xC0, 0x7F]);
+        roundtrip(&[0x44, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x7F]);
+        roundtrip(&[0xD0, 0x6F]);
+        roundtrip(&[0xD2, 0x05]);
+    }
+
+    #[test]
+    fn prefixed_instructions() {
+        for selector in 0..=7 {
+            roundtrip(&[0xFC, selector]);
+        }
+        roundtrip(&[0xFC, 0x08, 0x01, 0x00]);
+        roundtrip(&[0xFC, 0x09, 0x01]);
+        roundtrip(&[0xFC, 0x08, 0x01, 0x02]);
+        roundtrip(&[0xFC, 0x0A, 0x00, 0x00]);
+        roundtrip(&[0xFC, 0x0A, 0x01, 0x02]);
+        roundtrip(&[0xFC, 0x0B, 0x00]);
+        roundtrip(&[0xFC, 0x0B, 0x03]);
+        roundtrip(&[0xFC, 0x0C, 0x01, 0x02]);
+        roundtrip(&[0xFC, 0x0D, 0x01]);
+        roundtrip(&[0xFC, 0x0E, 0x01, 0x02]);
+        roundtrip(&[0xFC, 0x0F, 0x01]);
+        roundtrip(&[0xFC, 0x10, 0x01]);
+        roundtrip(&[0xFC, 0x11, 0x01]);
+    }
+
+    #[test]
+    fn vector_instructions() {
+        let unused = [
+            0x9A, 0xA2, 0xA5, 0xA6, 0xAF, 0xB0, 0xB2, 0xB3, 0xB4, 0xBB, 0xC2, 0xC5, 0xC6, 0xCF,
+            0xD0, 0xD2, 0xD3, 0xD4, 0xE2, 0xEE,
+        ];
+        let opcodes = (0x0E..=0x14)
+            .chain(0x23..=0x53)
+            .chain((0x5E..=0xFF).filter(|x| !unused.contains(x)));
+        for opcode in opcodes {
+            if opcode < 0x80 {
+                roundtrip(&[0xFD, opcode]);
+            } else {
+                roundtrip(&[0xFD, opcode | 0x80, 0x01]);
+            }
+        }
+        for opcode in 0x00..=0x0B {
+            roundtrip(&[0xFD, opcode, 0x04, 0x10]);
+        }
+        roundtrip(&[0xFD, 0x5C, 0x02, 0x00]);
+        roundtrip(&[0xFD, 0x5D, 0x03, 0x00]);
+        for opcode in 0x54..=0x5B {
+            roundtrip(&[0xFD, opcode, 0x00, 0x08, 0x01]);
+        }
+        for opcode in 0x15..=0x22 {
+            roundtrip(&[0xFD, opcode, 0x03]);
+        }
+        let mut bytes = vec![0xFD, 0x0C];
+        bytes.extend(1..=16);
+        roundtrip(&bytes);
+        bytes[1] = 0x0D;
+        roundtrip(&bytes);
+    }
+
+    #[test]
+    fn atomic_instructions() {
+        roundtrip(&[0xFE, 0x03, 0x00]);
+        for opcode in (0x00..=0x02).chain(0x10..=0x4E) {
+            roundtrip(&[0xFE, opcode, 0x02, 0x08]);
+        }
+    }
+
+    #[test]
+    fn exception_instructions() {
+        roundtrip(&[0x08, 0x01]);
+        roundtrip(&[0x0A]);
+        roundtrip(&[0x06, 0x40, 0x0B]);
+        roundtrip(&[0x06, 0x7F, 0x08, 0x00, 0x07, 0x00, 0x09, 0x00, 0x19, 0x0B]);
+        roundtrip(&[0x06, 0x40, 0x07, 0x00, 0x07, 0x01, 0x01, 0x0B]);
+        roundtrip(&[0x06, 0x40, 0x06, 0x40, 0x18, 0x01, 0x18, 0x00]);
+        roundtrip(&[0x1F, 0x40, 0x00, 0x0B]);
+        roundtrip(&[
+            0x1F, 0x69, 0x04, 0x00, 0x01, 0x00, 0x01, 0x02, 0x03, 0x02, 0x00, 0x03, 0x01, 0x0A,
+            0x0B,
+        ]);
+    }
+
+    #[test]
+    fn typed_reference_instructions() {
+        roundtrip(&[0x14, 0x02]);
+        roundtrip(&[0x15, 0x02]);
+        roundtrip(&[0xD0, 0x6E]);
+        roundtrip(&[0xD0, 0x03]);
+        roundtrip(&[0xD3]);
+        roundtrip(&[0xD4]);
+        roundtrip(&[0xD5, 0x01]);
+        roundtrip(&[0xD6, 0x01]);
+        roundtrip(&[0x02, 0x64, 0x6D, 0x0B]);
+        roundtrip(&[0x02, 0x63, 0x00, 0x0B]);
+    }
+
+    #[test]
+    fn gc_instructions() {
+        for opcode in [0x00, 0x01, 0x06, 0x07, 0x0B, 0x0C, 0x0D, 0x0E, 0x10] {
+            roundtrip(&[0xFB, opcode, 0x02]);
+        }
+        for opcode in [0x02, 0x03, 0x04, 0x05, 0x08, 0x09, 0x0A, 0x11, 0x12, 0x13] {
+            roundtrip(&[0xFB, opcode, 0x02, 0x01]);
+        }
+        for opcode in (0x0F..=0x0F).chain(0x1A..=0x1E) {
+            roundtrip(&[0xFB, opcode]);
+        }
+        for opcode in 0x14..=0x17 {
+            roundtrip(&[0xFB, opcode, 0x6C]);
+            roundtrip(&[0xFB, opcode, 0x01]);
+        }
+        for flags in 0x00..=0x03 {
+            roundtrip(&[0xFB, 0x18, flags, 0x00, 0x6E, 0x6C]);
+            roundtrip(&[0xFB, 0x19, flags, 0x01, 0x6D, 0x02]);
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use super::sections::{write_names, write_section};
+use super::values::write_len;
+use super::Encode;
+use crate::parse::binary::module::BinaryModule;
+use crate::parse::binary::preamble::{Preamble, Version};
+use crate::parse::binary::sections::{
+    code::{self, CodeSection},
+    custom::CustomSection,
+    data::DataSection,
+    elem::ElemSection,
+    export::ExportSection,
+    func::FuncSection,
+    global::GlobalSection,
+    import::ImportSection,
+    mem::MemSection,
+    names::NAME_SECTION,
+    start::StartSection,
+    table::TableSection,
+    tag::TagSection,
+    types::TypeSection,
+};
+use crate::wasm::data::Data;
+use crate::wasm::elem::Elem;
+use crate::wasm::export::Export;
+use crate::wasm::func::Func;
+use crate::wasm::global::Global;
+use crate::wasm::import::Import;
+use crate::wasm::mem::Mem;
+use crate::wasm::module::Module;
+use crate::wasm::names::NameSection;
+use crate::wasm::start::Start;
+use crate::wasm::table::Table;
+use crate::wasm::tag::Tag;
+use crate::wasm::types::RecType;
+use crate::wasm::values::{Name, U32};
+
+/// Returns the size of the data count section, which is only needed when the code refers to data
+/// segments.
+fn data_count(module: &Module) -> Option<U32> {
+    if module
+        .funcs
+        .iter()
+        .any(|f| code::uses_data_count(&f.body.instr))
+    {
+        Some(U32(module.datas.len() as u32))
+    } else {
+        None
+    }
+}
+
+/// Contents of the sections of a module, borrowed from either a [BinaryModule] or a [Module] so
+/// that both can be encoded without copying them. Empty sections are omitted.
+struct Sections<'a> {
+    header: &'a Preamble,
+    custom: &'a [CustomSection],
+    types: &'a [RecType],
+    imports: &'a [Import],
+    /// Type indices of the defined functions, and whether there are none.
+    funcs: (&'a dyn Encode, bool),
+    tables: &'a [Table],
+    mems: &'a [Mem],
+    tags: &'a [Tag],
+    globals: &'a [Global],
+    exports: &'a [Export],
+    start: Option<&'a Start>,
+    elems: &'a [Elem],
+    data_count: Option<U32>,
+    /// Entries of the code section, and whether there are none.
+    code: (&'a dyn Encode, bool),
+    datas: &'a [Data],
+}
+
+/// Writes the sections in the order defined by the specification. Custom sections are written
+/// right after the section they followed when parsed.
+fn write_module(module: Sections, out: &mut Vec<u8>) {
+    let write_custom = |after: Option<u8>, out: &mut Vec<u8>| {
+        for custom in module.custom.iter().filter(|custom| custom.after == after) {
+            write_section(0, custom, false, out);
+        }
+    };
+    module.header.encode(out);
+    write_custom(None, out);
+    write_section(1, module.types, module.types.is_empty(), out);
+    write_custom(Some(1), out);
+    write_section(2, module.imports, module.imports.is_empty(), out);
+    write_custom(Some(2), out);
+    write_section(3, module.funcs.0, module.funcs.1, out);
+    write_custom(Some(3), out);
+    write_section(4, module.tables, module.tables.is_empty(), out);
+    write_custom(Some(4), out);
+    write_section(5, module.mems, module.mems.is_empty(), out);
+    write_custom(Some(5), out);
+    write_section(13, module.tags, module.tags.is_empty(), out);
+    write_custom(Some(13), out);
+    write_section(6, module.globals, module.globals.is_empty(), out);
+    write_custom(Some(6), out);
+    write_section(7, module.exports, module.exports.is_empty(), out);
+    write_custom(Some(7), out);
+    if let Some(start) = module.start {
+        write_section(8, start, false, out);
+    }
+    write_custom(Some(8), out);
+    write_section(9, module.elems, module.elems.is_empty(), out);
+    write_custom(Some(9), out);
+    if let Some(count) = &module.data_count {
+        write_section(12, count, false, out);
+    }
+    write_custom(Some(12), out);
+    write_section(10, module.code.0, module.code.1, out);
+    write_custom(Some(10), out);
+    write_section(11, module.datas, module.datas.is_empty(), out);
+    write_custom(Some(11), out);
+}
+
+impl Encode for BinaryModule {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_module(
+            Sections {
+                header: &self.header,
+                custom: &self.custom,
+                types: &self.types.types,
+                imports: &self.imports.imports,
+                funcs: (&self.functions, self.functions.funcs.is_empty()),
+                tables: &self.tables.tables,
+                mems: &self.mems.mems,
+                tags: &self.tags.tags,
+                globals: &self.globals.globals,
+                exports: &self.exports.exports,
+                start: self.start.start.as_ref(),
+                elems: &self.elems.seg,
+                data_count: self.data_count,
+                code: (&self.code, self.code.code.is_empty()),
+                datas: &self.data.data,
+            },
+            out,
+        );
+    }
+}
+
+/// Type indices of the functions of a [Module], encoded as the function section.
+struct FuncTypes<'a>(&'a [Func]);
+
+impl Encode for FuncTypes<'_> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_len(self.0.len(), out);
+        for func in self.0 {
+            func.index.encode(out);
+        }
+    }
+}
+
+/// The module is encoded in place: the function bodies are encoded as entries of the code
+/// section and the name section is built from [Module::name] and [Module::names].
+impl Encode for Module {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let name = self.name.as_ref().or(self.names.module.as_ref());
+        let mut custom = Vec::new();
+        if name.is_some() || self.names != NameSection::default() {
+            let mut data = Vec::new();
+            write_names(&self.names, name, &mut data);
+            custom.push(name_section(data));
+        }
+        write_module(
+            Sections {
+                header: &Preamble {
+                    version: Version::V1_0_0_0,
+                },
+                custom: &custom,
+                types: &self.types,
+                imports: &self.imports,
+                funcs: (&FuncTypes(&self.funcs), self.funcs.is_empty()),
+                tables: &self.tables,
+                mems: &self.mems,
+                tags: &self.tags,
+                globals: &self.globals,
+                exports: &self.exports,
+                start: self.start.as_ref(),
+                elems: &self.elems,
+                data_count: data_count(self),
+                code: (&self.funcs, self.funcs.is_empty()),
+                datas: &self.datas,
+            },
+            out,
+        );
+    }
+}
+
+/// Custom section holding the encoded name section `data`.
+fn name_section(data: Vec<u8>) -> CustomSection {
+    CustomSection {
+        name: Name {
+            name: NAME_SECTION.to_string(),
+        },
+        data,
+        // The name section is expected after the data section.
+        after: Some(11),
+    }
+}
+
+impl From<Module> for BinaryModule {
+    fn from(from: Module) -> BinaryModule {
+        let data_count = data_count(&from);
+        let names = NameSection {
+            module: from.name.or(from.names.module),
+            ..from.names
+        };
+        let mut custom = Vec::new();
+        if names != NameSection::default() {
+            custom.push(name_section(names.to_bytes()));
+        }
+        let functions = FuncSection {
+            funcs: from.funcs.iter().map(|f| f.index).collect(),
+        };
+        let code = CodeSection {
+            code: from
+                .funcs
+                .into_iter()
+                .map(|f| code::Func {
+                    locals: f.locals,
+                    code: f.body,
+                })
+                .collect(),
+        };
+        BinaryModule {
+            header: Preamble {
+                version: Version::V1_0_0_0,
+            },
+            custom,
+            types: TypeSection { types: from.types },
+            imports: ImportSection {
+                imports: from.imports,
+            },
+            functions,
+            tables: TableSection {
+                tables: from.tables,
+            },
+            mems: MemSection { mems: from.mems },
+            tags: TagSection { tags: from.tags },
+            globals: GlobalSection {
+                globals: from.globals,
+            },
+            exports: ExportSection {
+                exports: from.exports,
+            },
+            start: StartSection { start: from.start },
+            elems: ElemSection { seg: from.elems },
+            code,
+            data: DataSection { data: from.datas },
+            data_count,
+            names: Some(names),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::{Parse, ParsingData, WasmBinary};
+    use std::fs;
+
+    fn parse(bytes: Vec<u8>) -> Module {
+        let wasm: WasmBinary = bytes.into();
+        let mut wasm = ParsingData::new(&wasm);
+        let module = Module::parse(&mut wasm).unwrap();
+        assert!(wasm.is_empty());
+        module
+    }
+
+    #[test]
+    fn roundtrip_examples() {
+        for entry in fs::read_dir("wasm-examples").unwrap() {
+            let path = entry.unwrap().path();
+            let module = parse(fs::read(&path).unwrap());
+            let encoded = module.to_binary();
+            // Encoding the module in place is the same as converting it first.
+            let converted = BinaryModule::from(module.clone()).to_bytes();
+            assert_eq!(encoded, converted, "{}", path.display());
+            let reparsed = parse(encoded.clone());
+            assert_eq!(
+                format!("{:?}", module),
+                format!("{:?}", reparsed),
+                "{} changed after a round trip",
+                path.display()
+            );
+            // Encoding is deterministic, so a second round trip should produce the same bytes.
+            assert_eq!(encoded, reparsed.to_binary(), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn custom_sections_keep_their_place() {
+        let wasm = vec![
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // Preamble
+            0x00, 0x02, 0x01, b'a', // Custom section "a"
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section
+            0x00, 0x02, 0x01, b'b', // Custom section "b"
+            0x03, 0x02, 0x01, 0x00, // Function section
+            0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B, // Code section
+            0x00, 0x02, 0x01, b'c', // Custom section "c"
+        ];
+        let binary: WasmBinary = wasm.clone().into();
+        let module = BinaryModule::parse(&mut ParsingData::new(&binary)).unwrap();
+        assert_eq!(module.to_bytes(), wasm);
+    }
+
+    #[test]
+    fn empty_module() {
+        assert_eq!(
+            Module::default().to_binary(),
+            vec![0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00]
+        );
+    }
+
+    #[test]
+    fn data_count_when_required() {
+        // A function `() -> ()` calling `data.drop 0` and a passive data segment.
+        let module = parse(vec![
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // Preamble
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section
+            0x03, 0x02, 0x01, 0x00, // Function section
+            0x0C, 0x01, 0x01, // Data count section
+            0x0A, 0x07, 0x01, 0x05, 0x00, 0xFC, 0x09, 0x00, 0x0B, // Code section
+            0x0B, 0x03, 0x01, 0x01, 0x00, // Data section
+        ]);
+        let encoded = module.to_binary();
+        let sections: Vec<u8> = vec![0x01, 0x03, 0x0C, 0x0A, 0x0B];
+        let mut ids = Vec::new();
+        let mut pos = 8;
+        while pos < encoded.len() {
+            ids.push(encoded[pos]);
+            pos += 2 + encoded[pos + 1] as usize;
+        }
+        assert_eq!(ids, sections);
+    }
+}
@@ -0,0 +1,11 @@
+use super::Encode;
+use crate::parse::binary::preamble::{Preamble, Version, MAGIC};
+
+impl Encode for Preamble {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&MAGIC);
+        match self.version {
+            Version::V1_0_0_0 => out.extend_from_slice(&[0x01, 0x00, 0x00, 0x00]),
+        }
+    }
+}
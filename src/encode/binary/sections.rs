@@ -0,0 +1,81 @@
+use super::values::write_len;
+use super::Encode;
+use crate::parse::binary::sections::Section;
+
+mod code;
+mod custom;
+mod data;
+mod elem;
+mod export;
+mod func;
+mod global;
+mod import;
+mod mem;
+mod names;
+mod start;
+mod table;
+mod tag;
+mod types;
+
+pub(crate) use names::write_names;
+
+impl Section {
+    /// Returns the section id used in the binary format.
+    pub fn id(&self) -> u8 {
+        match self {
+            Section::Custom(_) | Section::Name(..) => 0,
+            Section::Type(_) => 1,
+            Section::Import(_) => 2,
+            Section::Function(_) => 3,
+            Section::Table(_) => 4,
+            Section::Memory(_) => 5,
+            Section::Global(_) => 6,
+            Section::Tag(_) => 13,
+            Section::Export(_) => 7,
+            Section::Start(_) => 8,
+            Section::Element(_) => 9,
+            Section::Code(_) => 10,
+            Section::Data(_) => 11,
+            Section::DataCount(_) => 12,
+        }
+    }
+}
+
+/// Writes the section id followed by the size prefixed `contents`, unless the section is `empty`.
+pub(crate) fn write_section<T: Encode + ?Sized>(
+    id: u8,
+    contents: &T,
+    empty: bool,
+    out: &mut Vec<u8>,
+) {
+    if empty {
+        return;
+    }
+    let contents = contents.to_bytes();
+    out.push(id);
+    write_len(contents.len(), out);
+    out.extend(contents);
+}
+
+/// Encodes the section id followed by the size prefixed contents of the section.
+impl Encode for Section {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let id = self.id();
+        match self {
+            Section::Custom(s) | Section::Name(s, _) => write_section(id, s, false, out),
+            Section::Type(s) => write_section(id, s, false, out),
+            Section::Import(s) => write_section(id, s, false, out),
+            Section::Function(s) => write_section(id, s, false, out),
+            Section::Table(s) => write_section(id, s, false, out),
+            Section::Memory(s) => write_section(id, s, false, out),
+            Section::Global(s) => write_section(id, s, false, out),
+            Section::Tag(s) => write_section(id, s, false, out),
+            Section::Export(s) => write_section(id, s, false, out),
+            Section::Start(s) => write_section(id, s, false, out),
+            Section::Element(s) => write_section(id, s, false, out),
+            Section::Code(s) => write_section(id, s, false, out),
+            Section::Data(s) => write_section(id, s, false, out),
+            Section::DataCount(s) => write_section(id, s, false, out),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::encode::binary::values::write_len;
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::code::{CodeSection, Func};
+use crate::wasm::func;
+use crate::wasm::instr::Expr;
+use crate::wasm::types::ValType;
+use crate::wasm::values::U32;
+
+impl Encode for CodeSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.code.encode(out);
+    }
+}
+
+/// Encodes the size prefixed function body. Consecutive locals of the same type are grouped
+/// together.
+fn write_body(locals: &[ValType], code: &Expr, out: &mut Vec<u8>) {
+    let mut groups = Vec::new();
+    for tpe in locals {
+        match groups.last_mut() {
+            Some((n, t)) if t == tpe => *n += 1,
+            _ => groups.push((1u32, *tpe)),
+        }
+    }
+    let mut body = Vec::new();
+    write_len(groups.len(), &mut body);
+    for (n, tpe) in groups {
+        U32(n).encode(&mut body);
+        tpe.encode(&mut body);
+    }
+    code.encode(&mut body);
+    write_len(body.len(), out);
+    out.extend(body);
+}
+
+impl Encode for Func {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_body(&self.locals, &self.code, out);
+    }
+}
+
+/// Encodes the body of a function as its entry of the code section, so that modules can be
+/// encoded without copying their code.
+impl Encode for func::Func {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_body(&self.locals, &self.body, out);
+    }
+}
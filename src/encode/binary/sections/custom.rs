@@ -0,0 +1,9 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::custom::CustomSection;
+
+impl Encode for CustomSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        out.extend_from_slice(&self.data);
+    }
+}
@@ -0,0 +1,32 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::data::DataSection;
+use crate::wasm::data::{Data, DataMode};
+use crate::wasm::indices::MemIdx;
+use crate::wasm::values::U32;
+
+impl Encode for DataSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.data.encode(out);
+    }
+}
+
+impl Encode for Data {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match &self.mode {
+            DataMode::Active {
+                memory: MemIdx(U32(0)),
+                offset,
+            } => {
+                out.push(0x00);
+                offset.encode(out);
+            }
+            DataMode::Passive => out.push(0x01),
+            DataMode::Active { memory, offset } => {
+                out.push(0x02);
+                memory.encode(out);
+                offset.encode(out);
+            }
+        }
+        self.init.encode(out);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::elem::ElemSection;
+use crate::wasm::elem::{Elem, ElemMode};
+use crate::wasm::indices::{FuncIdx, TableIdx};
+use crate::wasm::instr::{Expr, Instr, ReferenceInstr};
+use crate::wasm::types::RefType;
+use crate::wasm::values::U32;
+
+impl Encode for ElemSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.seg.encode(out);
+    }
+}
+
+/// Returns the function index if the expression is a single `ref.func`.
+fn as_func(expr: &Expr) -> Option<FuncIdx> {
+    match expr.instr.as_slice() {
+        [Instr::Reference(ReferenceInstr::RefFunc(x))] => Some(*x),
+        _ => None,
+    }
+}
+
+/// Encodes the element segment using the most compact of the eight encodings.
+impl Encode for Elem {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let funcs: Option<Vec<FuncIdx>> = if self.tpe == RefType::FUNCREF {
+            self.init.iter().map(as_func).collect()
+        } else {
+            None
+        };
+        // Bit 2 of the flags selects expressions over function indices.
+        let exprs = if funcs.is_some() { 0 } else { 4 };
+        match &self.mode {
+            ElemMode::Active {
+                table: TableIdx(U32(0)),
+                offset,
+            } if self.tpe == RefType::FUNCREF => {
+                out.push(exprs);
+                offset.encode(out);
+            }
+            ElemMode::Passive => {
+                out.push(exprs | 1);
+                self.write_kind(funcs.is_some(), out);
+            }
+            ElemMode::Active { table, offset } => {
+                out.push(exprs | 2);
+                table.encode(out);
+                offset.encode(out);
+                self.write_kind(funcs.is_some(), out);
+            }
+            ElemMode::Declarative => {
+                out.push(exprs | 3);
+                self.write_kind(funcs.is_some(), out);
+            }
+        }
+        match funcs {
+            Some(funcs) => funcs.encode(out),
+            None => self.init.encode(out),
+        }
+    }
+}
+
+impl Elem {
+    /// Writes the `elemkind` when using function indices and the `reftype` otherwise.
+    fn write_kind(&self, funcs: bool, out: &mut Vec<u8>) {
+        if funcs {
+            out.push(0x00);
+        } else {
+            self.tpe.encode(out);
+        }
+    }
+}
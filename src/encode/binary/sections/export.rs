@@ -0,0 +1,43 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::export::ExportSection;
+use crate::wasm::export::{Export, ExportDesc};
+
+impl Encode for ExportSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.exports.encode(out);
+    }
+}
+
+impl Encode for Export {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        self.desc.encode(out);
+    }
+}
+
+impl Encode for ExportDesc {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ExportDesc::Func(x) => {
+                out.push(0x00);
+                x.encode(out);
+            }
+            ExportDesc::Table(x) => {
+                out.push(0x01);
+                x.encode(out);
+            }
+            ExportDesc::Mem(x) => {
+                out.push(0x02);
+                x.encode(out);
+            }
+            ExportDesc::Global(x) => {
+                out.push(0x03);
+                x.encode(out);
+            }
+            ExportDesc::Tag(x) => {
+                out.push(0x04);
+                x.encode(out);
+            }
+        }
+    }
+}
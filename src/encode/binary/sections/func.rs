@@ -0,0 +1,8 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::func::FuncSection;
+
+impl Encode for FuncSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.funcs.encode(out);
+    }
+}
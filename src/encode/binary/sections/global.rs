@@ -0,0 +1,8 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::global::GlobalSection;
+
+impl Encode for GlobalSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.globals.encode(out);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::import::ImportSection;
+use crate::wasm::import::{Import, ImportDesc};
+use crate::wasm::tag::Tag;
+
+impl Encode for ImportSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.imports.encode(out);
+    }
+}
+
+impl Encode for Import {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.module.encode(out);
+        self.name.encode(out);
+        self.desc.encode(out);
+    }
+}
+
+impl Encode for ImportDesc {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ImportDesc::Func(x) => {
+                out.push(0x00);
+                x.encode(out);
+            }
+            ImportDesc::Table(t) => {
+                out.push(0x01);
+                t.encode(out);
+            }
+            ImportDesc::Mem(t) => {
+                out.push(0x02);
+                t.encode(out);
+            }
+            ImportDesc::Global(t) => {
+                out.push(0x03);
+                t.encode(out);
+            }
+            ImportDesc::Tag(x) => {
+                out.push(0x04);
+                Tag { tpe: *x }.encode(out);
+            }
+        }
+    }
+}
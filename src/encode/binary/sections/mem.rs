@@ -0,0 +1,15 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::mem::MemSection;
+use crate::wasm::mem::Mem;
+
+impl Encode for MemSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.mems.encode(out);
+    }
+}
+
+impl Encode for Mem {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+    }
+}
@@ -0,0 +1,61 @@
+use crate::encode::binary::values::write_len;
+use crate::encode::binary::Encode;
+use crate::wasm::names::{IndirectNameMap, NameMap, NameSection};
+use crate::wasm::values::{Name, U32};
+
+impl Encode for NameMap {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_len(self.names.len(), out);
+        for (idx, name) in &self.names {
+            U32(*idx).encode(out);
+            name.encode(out);
+        }
+    }
+}
+
+impl Encode for IndirectNameMap {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_len(self.names.len(), out);
+        for (idx, map) in &self.names {
+            U32(*idx).encode(out);
+            map.encode(out);
+        }
+    }
+}
+
+/// Writes the subsection `id` unless it is empty.
+fn write_subsection<T: Encode>(id: u8, subsection: &T, empty: bool, out: &mut Vec<u8>) {
+    if empty {
+        return;
+    }
+    let contents = subsection.to_bytes();
+    out.push(id);
+    write_len(contents.len(), out);
+    out.extend(contents);
+}
+
+/// Writes the contents of the name section `names`, with `module` as the module name rather than
+/// the one of `names`.
+pub(crate) fn write_names(names: &NameSection, module: Option<&Name>, out: &mut Vec<u8>) {
+    if let Some(module) = module {
+        write_subsection(0, module, false, out);
+    }
+    write_subsection(1, &names.funcs, names.funcs.is_empty(), out);
+    write_subsection(2, &names.locals, names.locals.is_empty(), out);
+    write_subsection(3, &names.labels, names.labels.is_empty(), out);
+    write_subsection(4, &names.types, names.types.is_empty(), out);
+    write_subsection(5, &names.tables, names.tables.is_empty(), out);
+    write_subsection(6, &names.mems, names.mems.is_empty(), out);
+    write_subsection(7, &names.globals, names.globals.is_empty(), out);
+    write_subsection(8, &names.elems, names.elems.is_empty(), out);
+    write_subsection(9, &names.datas, names.datas.is_empty(), out);
+    write_subsection(11, &names.tags, names.tags.is_empty(), out);
+}
+
+/// Encodes the contents of the name section (i.e. the custom section data after its name).
+/// Empty subsections are omitted.
+impl Encode for NameSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_names(self, self.module.as_ref(), out);
+    }
+}
@@ -0,0 +1,17 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::start::StartSection;
+use crate::wasm::start::Start;
+
+impl Encode for StartSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        if let Some(start) = &self.start {
+            start.encode(out);
+        }
+    }
+}
+
+impl Encode for Start {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.func.encode(out);
+    }
+}
@@ -0,0 +1,22 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::table::TableSection;
+use crate::wasm::table::Table;
+
+impl Encode for TableSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tables.encode(out);
+    }
+}
+
+impl Encode for Table {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match &self.init {
+            None => self.tpe.encode(out),
+            Some(init) => {
+                out.extend([0x40, 0x00]);
+                self.tpe.encode(out);
+                init.encode(out);
+            }
+        }
+    }
+}
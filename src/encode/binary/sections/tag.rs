@@ -0,0 +1,17 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::tag::TagSection;
+use crate::wasm::tag::Tag;
+
+impl Encode for TagSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tags.encode(out);
+    }
+}
+
+impl Encode for Tag {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // Exception attribute.
+        out.push(0x00);
+        self.tpe.encode(out);
+    }
+}
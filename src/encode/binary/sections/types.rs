@@ -0,0 +1,8 @@
+use crate::encode::binary::Encode;
+use crate::parse::binary::sections::types::TypeSection;
+
+impl Encode for TypeSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.types.encode(out);
+    }
+}
@@ -0,0 +1,321 @@
+use super::values::write_signed;
+use super::Encode;
+use crate::wasm::types::{
+    ArrayType, CompType, FieldType, FuncType, GlobalType, HeapType, IndexType, Limits, MemType,
+    Mutability, NumType, RecType, RefType, ResultType, StorageType, StructType, SubType, TableType,
+    ValType, VecType,
+};
+
+impl Encode for NumType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            NumType::I32 => 0x7F,
+            NumType::I64 => 0x7E,
+            NumType::F32 => 0x7D,
+            NumType::F64 => 0x7C,
+        });
+    }
+}
+
+impl Encode for VecType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            VecType::V128 => 0x7B,
+        });
+    }
+}
+
+impl Encode for HeapType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let byte = match self {
+            HeapType::Func => 0x70,
+            HeapType::NoFunc => 0x73,
+            HeapType::Extern => 0x6F,
+            HeapType::NoExtern => 0x72,
+            HeapType::Any => 0x6E,
+            HeapType::Eq => 0x6D,
+            HeapType::I31 => 0x6C,
+            HeapType::Struct => 0x6B,
+            HeapType::Array => 0x6A,
+            HeapType::None => 0x71,
+            HeapType::Exn => 0x69,
+            HeapType::NoExn => 0x74,
+            HeapType::Concrete(idx) => return write_signed((idx.0).0.into(), out),
+        };
+        out.push(byte);
+    }
+}
+
+impl Encode for RefType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match (self.nullable, &self.heap) {
+            // Nullable abstract types use their single byte shorthand
+            (true, HeapType::Concrete(_)) => out.push(0x63),
+            (true, _) => {}
+            (false, _) => out.push(0x64),
+        }
+        self.heap.encode(out);
+    }
+}
+
+impl Encode for ValType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ValType::Num(t) => t.encode(out),
+            ValType::Vec(t) => t.encode(out),
+            ValType::Ref(t) => t.encode(out),
+        }
+    }
+}
+
+impl Encode for ResultType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.types.encode(out);
+    }
+}
+
+impl Encode for FuncType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0x60);
+        self.params.encode(out);
+        self.result.encode(out);
+    }
+}
+
+impl Encode for StorageType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            StorageType::Val(t) => t.encode(out),
+            StorageType::I8 => out.push(0x78),
+            StorageType::I16 => out.push(0x77),
+        }
+    }
+}
+
+impl Encode for FieldType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+        self.mutability.encode(out);
+    }
+}
+
+impl Encode for CompType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            CompType::Func(t) => t.encode(out),
+            CompType::Struct(StructType { fields }) => {
+                out.push(0x5F);
+                fields.encode(out);
+            }
+            CompType::Array(ArrayType { field }) => {
+                out.push(0x5E);
+                field.encode(out);
+            }
+        }
+    }
+}
+
+impl Encode for SubType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // Final types without supertypes use the short form
+        if !self.is_final || !self.supers.is_empty() {
+            out.push(if self.is_final { 0x4F } else { 0x50 });
+            self.supers.encode(out);
+        }
+        self.comp.encode(out);
+    }
+}
+
+impl Encode for RecType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        if let [tpe] = &self.types[..] {
+            tpe.encode(out);
+        } else {
+            out.push(0x4E);
+            self.types.encode(out);
+        }
+    }
+}
+
+impl Encode for Limits {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_limits(self, 0x00, out);
+    }
+}
+
+/// Writes limits whose flag byte carries the extra `flags` bits.
+fn write_limits(lim: &Limits, flags: u8, out: &mut Vec<u8>) {
+    match lim.max {
+        None => {
+            out.push(flags);
+            lim.min.encode(out);
+        }
+        Some(max) => {
+            out.push(flags | 0x01);
+            lim.min.encode(out);
+            max.encode(out);
+        }
+    }
+}
+
+impl Encode for MemType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut flags = if self.shared { 0x02 } else { 0x00 };
+        if self.index == IndexType::I64 {
+            flags |= 0x04;
+        }
+        write_limits(&self.lim, flags, out);
+    }
+}
+
+impl Encode for TableType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+        let flags = if self.index == IndexType::I64 {
+            0x04
+        } else {
+            0x00
+        };
+        write_limits(&self.lim, flags, out);
+    }
+}
+
+impl Encode for GlobalType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tpe.encode(out);
+        self.mutability.encode(out);
+    }
+}
+
+impl Encode for Mutability {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            Mutability::Const => 0x00,
+            Mutability::Mut => 0x01,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::indices::TypeIdx;
+    use crate::wasm::values::{U32, U64};
+
+    #[test]
+    fn func_type() {
+        let tpe = FuncType {
+            params: ResultType {
+                types: vec![ValType::Ref(RefType::EXTERNREF)],
+            },
+            result: ResultType {
+                types: vec![ValType::Num(NumType::F32)],
+            },
+        };
+        assert_eq!(tpe.to_bytes(), vec![0x60, 0x01, 0x6F, 0x01, 0x7D]);
+    }
+
+    #[test]
+    fn ref_type() {
+        assert_eq!(RefType::FUNCREF.to_bytes(), vec![0x70]);
+        assert_eq!(RefType::non_null(HeapType::Eq).to_bytes(), vec![0x64, 0x6D]);
+        let tpe = RefType::null(HeapType::Concrete(TypeIdx(U32(3))));
+        assert_eq!(tpe.to_bytes(), vec![0x63, 0x03]);
+    }
+
+    #[test]
+    fn rec_type() {
+        let sub = SubType {
+            is_final: false,
+            supers: vec![TypeIdx(U32(0))],
+            comp: CompType::Array(ArrayType {
+                field: FieldType {
+                    tpe: StorageType::I8,
+                    mutability: Mutability::Mut,
+                },
+            }),
+        };
+        let rec = RecType {
+            types: vec![sub.clone()],
+        };
+        assert_eq!(rec.to_bytes(), vec![0x50, 0x01, 0x00, 0x5E, 0x78, 0x01]);
+        let rec = RecType {
+            types: vec![
+                SubType::from_comp(CompType::Struct(StructType { fields: vec![] })),
+                sub,
+            ],
+        };
+        assert_eq!(
+            rec.to_bytes(),
+            vec![0x4E, 0x02, 0x5F, 0x00, 0x50, 0x01, 0x00, 0x5E, 0x78, 0x01]
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let lim = Limits {
+            min: U64(4),
+            max: None,
+        };
+        assert_eq!(lim.to_bytes(), vec![0x00, 0x04]);
+        let lim = Limits {
+            min: U64(4),
+            max: Some(U64(5)),
+        };
+        assert_eq!(lim.to_bytes(), vec![0x01, 0x04, 0x05]);
+    }
+
+    #[test]
+    fn mem_type() {
+        let tpe = MemType {
+            lim: Limits {
+                min: U64(1),
+                max: Some(U64(2)),
+            },
+            index: IndexType::I32,
+            shared: true,
+        };
+        assert_eq!(tpe.to_bytes(), vec![0x03, 0x01, 0x02]);
+        let tpe = MemType {
+            shared: false,
+            ..tpe
+        };
+        assert_eq!(tpe.to_bytes(), vec![0x01, 0x01, 0x02]);
+        let tpe = MemType {
+            lim: Limits {
+                min: U64(1 << 32),
+                max: None,
+            },
+            index: IndexType::I64,
+            shared: false,
+        };
+        assert_eq!(tpe.to_bytes(), vec![0x04, 0x80, 0x80, 0x80, 0x80, 0x10]);
+    }
+
+    #[test]
+    fn table_type() {
+        let tpe = TableType {
+            lim: Limits {
+                min: U64(1),
+                max: None,
+            },
+            index: IndexType::I32,
+            tpe: RefType::FUNCREF,
+        };
+        assert_eq!(tpe.to_bytes(), vec![0x70, 0x00, 0x01]);
+        let tpe = TableType {
+            index: IndexType::I64,
+            ..tpe
+        };
+        assert_eq!(tpe.to_bytes(), vec![0x70, 0x04, 0x01]);
+    }
+
+    #[test]
+    fn global_type() {
+        let tpe = GlobalType {
+            mutability: Mutability::Mut,
+            tpe: ValType::Num(NumType::I64),
+        };
+        assert_eq!(tpe.to_bytes(), vec![0x7E, 0x01]);
+    }
+}
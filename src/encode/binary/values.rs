@@ -0,0 +1,192 @@
+use nano_leb128::SLEB128;
+use nano_leb128::ULEB128;
+
+use super::Encode;
+use crate::wasm::values::{Byte, Name, F32, F64, I16, I32, I64, I8, S32, S64, U32, U64, V128};
+
+/// Maximum number of bytes a 64 bit LEB128 value can take.
+const MAX_LEB128_LEN: usize = 10;
+
+pub(crate) fn write_unsigned(value: u64, out: &mut Vec<u8>) {
+    let mut buf = [0; MAX_LEB128_LEN];
+    let len = ULEB128::from(value)
+        .write_into(&mut buf)
+        .expect("Buffer should fit any u64");
+    out.extend_from_slice(&buf[..len]);
+}
+
+pub(crate) fn write_signed(value: i64, out: &mut Vec<u8>) {
+    let mut buf = [0; MAX_LEB128_LEN];
+    let len = SLEB128::from(value)
+        .write_into(&mut buf)
+        .expect("Buffer should fit any i64");
+    out.extend_from_slice(&buf[..len]);
+}
+
+/// Writes `len` as a u32 length prefix.
+pub(crate) fn write_len(len: usize, out: &mut Vec<u8>) {
+    assert!(
+        len <= u32::MAX as usize,
+        "Length {} doesn't fit in u32",
+        len
+    );
+    write_unsigned(len as u64, out);
+}
+
+impl Encode for Byte {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.0);
+    }
+}
+
+impl Encode for U32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_unsigned(self.0.into(), out);
+    }
+}
+
+impl Encode for U64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_unsigned(self.0, out);
+    }
+}
+
+impl Encode for S32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_signed(self.0.into(), out);
+    }
+}
+
+impl Encode for S64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_signed(self.0, out);
+    }
+}
+
+impl Encode for I8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_signed((self.0 as i8).into(), out);
+    }
+}
+
+impl Encode for I16 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_signed((self.0 as i16).into(), out);
+    }
+}
+
+impl Encode for I32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_signed((self.0 as i32).into(), out);
+    }
+}
+
+impl Encode for I64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_signed(self.0 as i64, out);
+    }
+}
+
+impl Encode for F32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0.to_bits().to_le_bytes());
+    }
+}
+
+impl Encode for F64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0.to_bits().to_le_bytes());
+    }
+}
+
+impl Encode for V128 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.0.to_le_bytes());
+    }
+}
+
+impl Encode for Name {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_len(self.name.len(), out);
+        out.extend_from_slice(self.name.as_bytes());
+    }
+}
+
+impl<T> Encode for [T]
+where
+    T: Encode,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_len(self.len(), out);
+        for t in self {
+            t.encode(out);
+        }
+    }
+}
+
+impl<T> Encode for Vec<T>
+where
+    T: Encode,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_slice().encode(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::{Parse, ParsingData, WasmBinary};
+    use std::fmt::Debug;
+
+    fn roundtrip<T: Encode + Parse + PartialEq + Debug>(value: T) {
+        let wasm: WasmBinary = value.to_bytes().into();
+        let mut wasm = ParsingData::new(&wasm);
+        assert_eq!(T::parse(&mut wasm).unwrap(), value);
+        assert!(wasm.is_empty());
+    }
+
+    #[test]
+    fn unsigned() {
+        assert_eq!(U32(0).to_bytes(), vec![0x00]);
+        assert_eq!(U32(127).to_bytes(), vec![0x7F]);
+        assert_eq!(U32(128).to_bytes(), vec![0x80, 0x01]);
+        roundtrip(U32(u32::MAX));
+        roundtrip(U64(u64::MAX));
+    }
+
+    #[test]
+    fn signed() {
+        assert_eq!(S32(-1).to_bytes(), vec![0x7F]);
+        assert_eq!(S32(63).to_bytes(), vec![0x3F]);
+        assert_eq!(S32(64).to_bytes(), vec![0xC0, 0x00]);
+        assert_eq!(I32(-123456i32 as u32).to_bytes(), vec![0xC0, 0xBB, 0x78]);
+        roundtrip(S32(i32::MIN));
+        roundtrip(S64(i64::MIN));
+        roundtrip(I8(0x80));
+        roundtrip(I16(0xFFFF));
+        roundtrip(I32(0x8000_0000));
+        roundtrip(I64(u64::MAX));
+    }
+
+    #[test]
+    fn floats_keep_their_bits() {
+        let nan = f32::from_bits(0x7FC0_0001);
+        assert_eq!(F32(nan).to_bytes(), vec![0x01, 0x00, 0xC0, 0x7F]);
+        assert_eq!(F64(1.0).to_bytes(), 1.0f64.to_le_bytes().to_vec());
+    }
+
+    #[test]
+    fn name() {
+        let name = Name {
+            name: "héllo".to_string(),
+        };
+        assert_eq!(name.to_bytes()[0], 6);
+        roundtrip(name);
+    }
+
+    #[test]
+    fn vector() {
+        assert_eq!(vec![U32(1), U32(2)].to_bytes(), vec![0x02, 0x01, 0x02]);
+    }
+}
@@ -0,0 +1,9 @@
+//! Encoding elements for the web assembly.
+//!
+//! This is the inverse of [parse](crate::parse). Modules can be encoded in both the binary format
+//! and the text format specified in the web assembly specification. As the binary format is the
+//! primary one, this module reexports all of the components within the binary submodule.
+
+pub mod binary;
+pub mod text;
+pub use binary::*;
@@ -0,0 +1,183 @@
+//! Text format printer
+//!
+//! This module prints a [Module] in the web assembly text format (`.wat`). It is the inverse of
+//! the [text parser](crate::parse::text): parsing the printed text gives back the same module.
+//!
+//! Entities are referred to by the identifiers of the name section when there are some. Names
+//! that can't be written as identifiers, or that are already taken within the same index space,
+//! are left out in favor of plain indices. Every definition is followed by its index as a
+//! `(;index;)` comment.
+//!
+//! # Examples
+//!
+//! ```
+//! use wasm_parse::encode::text::{self, Style};
+//! use wasm_parse::wasm::module::Module;
+//!
+//! let module = Module::from_wat(
+//!     r#"(func $add (export "add") (param $a i32) (param $b i32) (result i32)
+//!         local.get $a
+//!         local.get $b
+//!         i32.add)"#,
+//! )?;
+//! assert_eq!(
+//!     text::print(&module, Style::Folded),
+//!     r#"(module
+//!   (type (;0;) (func (param i32 i32) (result i32)))
+//!   (func $add (;0;) (type 0) (param $a i32) (param $b i32) (result i32)
+//!     (i32.add
+//!       (local.get $a)
+//!       (local.get $b)
+//!     )
+//!   )
+//!   (export "add" (func $add))
+//! )
+//! "#
+//! );
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use crate::wasm::instr::Instr;
+use crate::wasm::module::Module;
+use crate::wasm::names::NameMap;
+use crate::wasm::types::FuncType;
+
+mod instr;
+mod module;
+mod values;
+
+/// Layout of the instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// One instruction per line, with blocks closed by `end`.
+    Flat,
+    /// S-expressions, with the operands of an instruction nested within it.
+    Folded,
+}
+
+/// Prints a module in the text format.
+pub fn print(module: &Module, style: Style) -> String {
+    let mut printer = Printer::new(module, style);
+    printer.module();
+    printer.out.push('\n');
+    printer.out
+}
+
+/// Identifiers of an index space, without the leading `$`.
+type Ids<'a> = HashMap<u32, &'a str>;
+
+/// Keeps the names of `map` that are valid identifiers, dropping the duplicates.
+fn ids(map: &NameMap) -> Ids<'_> {
+    let mut used = HashSet::new();
+    map.names
+        .iter()
+        .filter(|(_, name)| values::is_id(&name.name) && used.insert(name.name.as_str()))
+        .map(|(idx, name)| (*idx, name.name.as_str()))
+        .collect()
+}
+
+/// Reference to the entity `idx`, by identifier if it has one.
+fn index(ids: &Ids<'_>, idx: u32) -> String {
+    match ids.get(&idx) {
+        Some(id) => format!("${}", id),
+        None => idx.to_string(),
+    }
+}
+
+/// Identifier and index comment of the definition of entity `idx`.
+fn define(ids: &Ids<'_>, idx: u32) -> String {
+    match ids.get(&idx) {
+        Some(id) => format!("${} (;{};)", id, idx),
+        None => format!("(;{};)", idx),
+    }
+}
+
+pub(crate) struct Printer<'a> {
+    module: &'a Module,
+    style: Style,
+    out: String,
+    indent: usize,
+    /// Whether everything goes on the current line (e.g. for the initializer of a global).
+    inline: bool,
+    types: Ids<'a>,
+    funcs: Ids<'a>,
+    tables: Ids<'a>,
+    mems: Ids<'a>,
+    globals: Ids<'a>,
+    tags: Ids<'a>,
+    elems: Ids<'a>,
+    datas: Ids<'a>,
+    /// Identifiers of the fields of the struct types, by type index.
+    fields: HashMap<u32, Ids<'a>>,
+    /// Type of every function, imports included. `None` if the type index is out of bounds.
+    func_types: Vec<Option<&'a FuncType>>,
+    /// Type of every tag, imports included. `None` if the type index is out of bounds.
+    tag_types: Vec<Option<&'a FuncType>>,
+    /// Identifiers of the locals of the current function.
+    locals: Ids<'a>,
+    /// Identifiers of the labelled blocks of the current function, keyed by the address of the
+    /// block instruction.
+    block_labels: HashMap<*const Instr, &'a str>,
+    /// Labels of the blocks enclosing the current instruction, innermost last.
+    labels: Vec<Option<&'a str>>,
+    /// Number of results of the current function.
+    results: usize,
+}
+
+impl<'a> Printer<'a> {
+    fn new(module: &'a Module, style: Style) -> Printer<'a> {
+        let names = &module.names;
+        Printer {
+            module,
+            style,
+            out: String::new(),
+            indent: 0,
+            inline: false,
+            types: ids(&names.types),
+            funcs: ids(&names.funcs),
+            tables: ids(&names.tables),
+            mems: ids(&names.mems),
+            globals: ids(&names.globals),
+            tags: ids(&names.tags),
+            elems: ids(&names.elems),
+            datas: ids(&names.datas),
+            fields: names
+                .fields
+                .names
+                .iter()
+                .map(|(idx, map)| (*idx, ids(map)))
+                .collect(),
+            func_types: Vec::new(),
+            tag_types: Vec::new(),
+            locals: Ids::new(),
+            block_labels: HashMap::new(),
+            labels: Vec::new(),
+            results: 0,
+        }
+    }
+
+    /// Writes `text` on a new line at the current indentation. When printing inline, `text` is
+    /// separated from the previous item by a space instead, except for closing parentheses.
+    pub(crate) fn line(&mut self, text: &str) {
+        if self.inline {
+            if !text.starts_with(')') {
+                self.out.push(' ');
+            }
+        } else {
+            if !self.out.is_empty() {
+                self.out.push('\n');
+            }
+            for _ in 0..self.indent {
+                self.out.push_str("  ");
+            }
+        }
+        self.out.push_str(text);
+    }
+
+    /// Appends `text` to the current line.
+    pub(crate) fn push(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+}
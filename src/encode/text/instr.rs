@@ -0,0 +1,1489 @@
+//! Plain and folded instructions.
+
+use std::collections::HashMap;
+
+use super::{index, values, Ids, Printer, Style};
+use crate::wasm::indices::{FieldIdx, MemIdx, TypeIdx};
+use crate::wasm::instr::{
+    AggregateInstr, AtomicInstr, BlockType, Catch, ControlInstr, Expr, FBinop, FRelop, FUnop,
+    FloatType, IBinop, IRelop, ITestop, IUnop, Instr, IntType, MemArg, MemoryInstr, NumericInstr,
+    ReferenceInstr, Sign, TableInstr, TryBlock, TryHandler, TryTableBlock, VariableInstr,
+    VectorInstr,
+};
+use crate::wasm::names::NameMap;
+use crate::wasm::types::{CompType, HeapType, NumType, RefType, ValType, VecType};
+
+/// Operands are only folded up to this depth, which keeps long chains of instructions (e.g. a
+/// hundred additions in a row) from drifting to the right.
+const MAX_FOLDED_DEPTH: usize = 32;
+
+/// Instruction along with the instructions that produce its operands, in the folded style.
+struct Node<'a> {
+    instr: &'a Instr,
+    operands: Vec<Node<'a>>,
+    results: usize,
+    depth: usize,
+}
+
+/// Pending output while printing instructions.
+///
+/// Blocks and folded instructions are printed with an explicit stack rather than recursion so
+/// that deeply nested code can't overflow the call stack.
+enum Work<'a> {
+    Instrs(&'a [Instr]),
+    Node(Node<'a>),
+    Line(&'static str),
+    /// Line whose text depends on the instruction, e.g. a `catch` clause.
+    Text(String),
+    Indent,
+    Dedent,
+    Label(Option<&'a str>),
+    EndLabel,
+}
+
+/// Value type, whose type indices are referred to by the identifiers `types`.
+pub(crate) fn valtype(tpe: &ValType, types: &Ids<'_>) -> String {
+    match tpe {
+        ValType::Num(NumType::I32) => "i32".into(),
+        ValType::Num(NumType::I64) => "i64".into(),
+        ValType::Num(NumType::F32) => "f32".into(),
+        ValType::Num(NumType::F64) => "f64".into(),
+        ValType::Vec(VecType::V128) => "v128".into(),
+        ValType::Ref(tpe) => reftype(tpe, types),
+    }
+}
+
+/// Reference type, abbreviated when it's nullable and its heap type is abstract.
+pub(crate) fn reftype(tpe: &RefType, types: &Ids<'_>) -> String {
+    let shorthand = match tpe.heap {
+        HeapType::Func => "funcref",
+        HeapType::NoFunc => "nullfuncref",
+        HeapType::Extern => "externref",
+        HeapType::NoExtern => "nullexternref",
+        HeapType::Any => "anyref",
+        HeapType::Eq => "eqref",
+        HeapType::I31 => "i31ref",
+        HeapType::Struct => "structref",
+        HeapType::Array => "arrayref",
+        HeapType::None => "nullref",
+        HeapType::Exn => "exnref",
+        HeapType::NoExn => "nullexnref",
+        HeapType::Concrete(_) => "",
+    };
+    match tpe.nullable {
+        true if !shorthand.is_empty() => shorthand.into(),
+        true => format!("(ref null {})", heap_type(&tpe.heap, types)),
+        false => format!("(ref {})", heap_type(&tpe.heap, types)),
+    }
+}
+
+pub(crate) fn heap_type(tpe: &HeapType, types: &Ids<'_>) -> String {
+    let name = match tpe {
+        HeapType::Func => "func",
+        HeapType::NoFunc => "nofunc",
+        HeapType::Extern => "extern",
+        HeapType::NoExtern => "noextern",
+        HeapType::Any => "any",
+        HeapType::Eq => "eq",
+        HeapType::I31 => "i31",
+        HeapType::Struct => "struct",
+        HeapType::Array => "array",
+        HeapType::None => "none",
+        HeapType::Exn => "exn",
+        HeapType::NoExn => "noexn",
+        HeapType::Concrete(idx) => return index(types, *idx.0),
+    };
+    name.into()
+}
+
+/// Identifiers of the labelled blocks of a function body, keyed by the address of the block
+/// instruction. Blocks are numbered in the order in which they're opened, as in the name section.
+pub(crate) fn block_labels<'a>(
+    body: &'a [Instr],
+    names: &'a NameMap,
+) -> HashMap<*const Instr, &'a str> {
+    let mut labels = HashMap::new();
+    let mut blocks = 0;
+    let mut stack = vec![body.iter()];
+    while let Some(iter) = stack.last_mut() {
+        let instr = match iter.next() {
+            Some(instr) => instr,
+            None => {
+                stack.pop();
+                continue;
+            }
+        };
+        let bodies: Vec<&[Instr]> = match instr {
+            Instr::Control(ControlInstr::Block(block))
+            | Instr::Control(ControlInstr::Loop(block)) => vec![&block.instr],
+            Instr::Control(ControlInstr::If(block)) => vec![&block.if_br, &block.else_br],
+            Instr::Control(ControlInstr::Try(block)) => try_bodies(block),
+            Instr::Control(ControlInstr::TryTable(block)) => vec![&block.instr],
+            _ => continue,
+        };
+        if let Some(name) = names.get(blocks).filter(|name| values::is_id(&name.name)) {
+            labels.insert(instr as *const Instr, name.name.as_str());
+        }
+        blocks += 1;
+        stack.extend(bodies.into_iter().rev().map(|body| body.iter()));
+    }
+    labels
+}
+
+/// Body of a `try` block followed by its `catch` and `catch_all` clauses.
+fn try_bodies(block: &TryBlock) -> Vec<&[Instr]> {
+    let mut bodies = vec![block.instr.as_slice()];
+    if let TryHandler::Catch { catches, catch_all } = &block.handler {
+        bodies.extend(catches.iter().map(|catch| catch.instr.as_slice()));
+        bodies.extend(catch_all.as_deref());
+    }
+    bodies
+}
+
+impl<'a> Printer<'a> {
+    /// Prints a sequence of instructions, each on its own line unless printing inline.
+    pub(crate) fn instrs(&mut self, instrs: &'a [Instr]) {
+        let mut work = vec![Work::Instrs(instrs)];
+        while let Some(item) = work.pop() {
+            match item {
+                Work::Instrs(instrs) => match self.style {
+                    Style::Flat => self.flat(instrs, &mut work),
+                    Style::Folded => {
+                        let nodes = self.fold(instrs);
+                        work.extend(nodes.into_iter().rev().map(Work::Node));
+                    }
+                },
+                Work::Node(node) => self.node(node, &mut work),
+                Work::Line(text) => self.line(text),
+                Work::Text(text) => self.line(&text),
+                Work::Indent => self.indent += 1,
+                Work::Dedent => self.indent -= 1,
+                Work::Label(label) => self.labels.push(label),
+                Work::EndLabel => {
+                    self.labels.pop();
+                }
+            }
+        }
+    }
+
+    /// Prints an expression on the current line, e.g. the initializer of a global.
+    pub(crate) fn inline_expr(&mut self, expr: &'a Expr) {
+        self.inline = true;
+        self.instrs(&expr.instr);
+        self.inline = false;
+    }
+
+    /// Whether `expr` prints as a single s-expression, which some fields accept in place of a
+    /// complete expression (e.g. the offset of a segment).
+    pub(crate) fn is_folded(&self, expr: &'a Expr) -> bool {
+        self.style == Style::Folded && self.fold(&expr.instr).len() == 1
+    }
+
+    /// Prints instructions in the flat style up to the first block. The block and the remaining
+    /// instructions are left on the stack.
+    fn flat(&mut self, instrs: &'a [Instr], work: &mut Vec<Work<'a>>) {
+        for (i, instr) in instrs.iter().enumerate() {
+            let label = self.block_labels.get(&(instr as *const Instr)).copied();
+            let pending = match instr {
+                Instr::Control(ControlInstr::Block(block))
+                | Instr::Control(ControlInstr::Loop(block)) => {
+                    let kw = match instr {
+                        Instr::Control(ControlInstr::Block(_)) => "block",
+                        _ => "loop",
+                    };
+                    self.line(&self.block_head(kw, label, &block.tpe));
+                    vec![
+                        Work::Indent,
+                        Work::Label(label),
+                        Work::Instrs(&block.instr),
+                        Work::EndLabel,
+                        Work::Dedent,
+                        Work::Line("end"),
+                    ]
+                }
+                Instr::Control(ControlInstr::If(block)) => {
+                    self.line(&self.block_head("if", label, &block.tpe));
+                    let mut pending =
+                        vec![Work::Indent, Work::Label(label), Work::Instrs(&block.if_br)];
+                    if !block.else_br.is_empty() {
+                        pending.extend(vec![
+                            Work::Dedent,
+                            Work::Line("else"),
+                            Work::Indent,
+                            Work::Instrs(&block.else_br),
+                        ]);
+                    }
+                    pending.extend(vec![Work::EndLabel, Work::Dedent, Work::Line("end")]);
+                    pending
+                }
+                Instr::Control(ControlInstr::Try(block)) => {
+                    self.line(&self.block_head("try", label, &block.tpe));
+                    let mut pending =
+                        vec![Work::Indent, Work::Label(label), Work::Instrs(&block.instr)];
+                    if let TryHandler::Catch { catches, catch_all } = &block.handler {
+                        for catch in catches {
+                            pending.extend(vec![
+                                Work::Dedent,
+                                Work::Text(format!("catch {}", index(&self.tags, *catch.tag.0))),
+                                Work::Indent,
+                                Work::Instrs(&catch.instr),
+                            ]);
+                        }
+                        if let Some(instr) = catch_all {
+                            pending.extend(vec![
+                                Work::Dedent,
+                                Work::Line("catch_all"),
+                                Work::Indent,
+                                Work::Instrs(instr),
+                            ]);
+                        }
+                    }
+                    let end = Work::Text(self.try_end(block));
+                    pending.extend(vec![Work::EndLabel, Work::Dedent, end]);
+                    pending
+                }
+                Instr::Control(ControlInstr::TryTable(block)) => {
+                    let mut head = self.block_head("try_table", label, &block.tpe);
+                    head.push_str(&self.catches(&block.catches));
+                    self.line(&head);
+                    vec![
+                        Work::Indent,
+                        Work::Label(label),
+                        Work::Instrs(&block.instr),
+                        Work::EndLabel,
+                        Work::Dedent,
+                        Work::Line("end"),
+                    ]
+                }
+                _ => {
+                    self.line(&self.plain(instr));
+                    continue;
+                }
+            };
+            work.push(Work::Instrs(&instrs[i + 1..]));
+            work.extend(pending.into_iter().rev());
+            return;
+        }
+    }
+
+    /// Prints a folded instruction. Its operands and the contents of blocks are left on the stack.
+    fn node(&mut self, node: Node<'a>, work: &mut Vec<Work<'a>>) {
+        let label = self
+            .block_labels
+            .get(&(node.instr as *const Instr))
+            .copied();
+        let pending = match node.instr {
+            Instr::Control(ControlInstr::Block(block))
+            | Instr::Control(ControlInstr::Loop(block)) => {
+                let kw = match node.instr {
+                    Instr::Control(ControlInstr::Block(_)) => "(block",
+                    _ => "(loop",
+                };
+                self.line(&self.block_head(kw, label, &block.tpe));
+                vec![
+                    Work::Indent,
+                    Work::Label(label),
+                    Work::Instrs(&block.instr),
+                    Work::EndLabel,
+                    Work::Dedent,
+                    Work::Line(")"),
+                ]
+            }
+            Instr::Control(ControlInstr::If(block)) => {
+                self.line(&self.block_head("(if", label, &block.tpe));
+                // The condition isn't within the scope of the label.
+                let mut pending = vec![Work::Indent];
+                pending.extend(node.operands.into_iter().map(Work::Node));
+                pending.extend(vec![
+                    Work::Label(label),
+                    Work::Line("(then"),
+                    Work::Indent,
+                    Work::Instrs(&block.if_br),
+                    Work::Dedent,
+                    Work::Line(")"),
+                ]);
+                if !block.else_br.is_empty() {
+                    pending.extend(vec![
+                        Work::Line("(else"),
+                        Work::Indent,
+                        Work::Instrs(&block.else_br),
+                        Work::Dedent,
+                        Work::Line(")"),
+                    ]);
+                }
+                pending.extend(vec![Work::EndLabel, Work::Dedent, Work::Line(")")]);
+                pending
+            }
+            Instr::Control(ControlInstr::Try(block)) => {
+                self.line(&self.block_head("(try", label, &block.tpe));
+                let mut pending = vec![
+                    Work::Indent,
+                    Work::Label(label),
+                    Work::Line("(do"),
+                    Work::Indent,
+                    Work::Instrs(&block.instr),
+                    Work::Dedent,
+                    Work::Line(")"),
+                ];
+                match &block.handler {
+                    TryHandler::Catch { catches, catch_all } => {
+                        for catch in catches {
+                            pending.extend(vec![
+                                Work::Text(format!("(catch {}", index(&self.tags, *catch.tag.0))),
+                                Work::Indent,
+                                Work::Instrs(&catch.instr),
+                                Work::Dedent,
+                                Work::Line(")"),
+                            ]);
+                        }
+                        if let Some(instr) = catch_all {
+                            pending.extend(vec![
+                                Work::Line("(catch_all"),
+                                Work::Indent,
+                                Work::Instrs(instr),
+                                Work::Dedent,
+                                Work::Line(")"),
+                            ]);
+                        }
+                        pending.push(Work::EndLabel);
+                    }
+                    TryHandler::Delegate(_) => {
+                        let delegate = format!("({})", self.try_end(block));
+                        pending.extend(vec![Work::EndLabel, Work::Text(delegate)]);
+                    }
+                }
+                pending.extend(vec![Work::Dedent, Work::Line(")")]);
+                pending
+            }
+            Instr::Control(ControlInstr::TryTable(block)) => {
+                let mut head = self.block_head("(try_table", label, &block.tpe);
+                head.push_str(&self.catches(&block.catches));
+                self.line(&head);
+                vec![
+                    Work::Indent,
+                    Work::Label(label),
+                    Work::Instrs(&block.instr),
+                    Work::EndLabel,
+                    Work::Dedent,
+                    Work::Line(")"),
+                ]
+            }
+            _ if node.operands.is_empty() => {
+                self.line(&format!("({})", self.plain(node.instr)));
+                return;
+            }
+            _ => {
+                self.line(&format!("({}", self.plain(node.instr)));
+                let mut pending = vec![Work::Indent];
+                pending.extend(node.operands.into_iter().map(Work::Node));
+                pending.extend(vec![Work::Dedent, Work::Line(")")]);
+                pending
+            }
+        };
+        work.extend(pending.into_iter().rev());
+    }
+
+    /// Groups instructions into folded instructions. An instruction takes the preceding ones as
+    /// operands when they produce exactly the values it consumes.
+    ///
+    /// The folded form always unfolds into the original sequence, so the arities only affect how
+    /// the code looks.
+    fn fold(&self, instrs: &'a [Instr]) -> Vec<Node<'a>> {
+        let mut nodes: Vec<Node<'a>> = Vec::new();
+        for instr in instrs {
+            let (params, results) = self.arity(instr);
+            let mut node = Node {
+                instr,
+                operands: Vec::new(),
+                results,
+                depth: 1,
+            };
+            let (mut values, mut taken, mut depth) = (0, 0, 0);
+            for operand in nodes.iter().rev() {
+                if values >= params || operand.results == 0 {
+                    break;
+                }
+                values += operand.results;
+                taken += 1;
+                depth = depth.max(operand.depth);
+            }
+            if params > 0 && values == params && depth < MAX_FOLDED_DEPTH {
+                node.operands = nodes.split_off(nodes.len() - taken);
+                node.depth = depth + 1;
+            }
+            nodes.push(node);
+        }
+        nodes
+    }
+
+    /// Number of results of a block of type `tpe`.
+    fn block_results(&self, tpe: &BlockType) -> usize {
+        match tpe {
+            BlockType::Empty => 0,
+            BlockType::Val(_) => 1,
+            BlockType::Type(idx) => self
+                .module
+                .func_type(*idx)
+                .map_or(0, |tpe| tpe.result.types.len()),
+        }
+    }
+
+    /// Number of operands an instruction takes and number of values it produces. The parameters
+    /// of blocks and the values passed along by branches are ignored.
+    fn arity(&self, instr: &Instr) -> (usize, usize) {
+        match instr {
+            Instr::Numeric(instr) => match instr {
+                NumericInstr::I32Const(_)
+                | NumericInstr::I64Const(_)
+                | NumericInstr::F32Const(_)
+                | NumericInstr::F64Const(_) => (0, 1),
+                NumericInstr::I32Binary(_)
+                | NumericInstr::I64Binary(_)
+                | NumericInstr::F32Binary(_)
+                | NumericInstr::F64Binary(_)
+                | NumericInstr::I32Relop(_)
+                | NumericInstr::I64Relop(_)
+                | NumericInstr::F32Relop(_)
+                | NumericInstr::F64Relop(_) => (2, 1),
+                _ => (1, 1),
+            },
+            Instr::Vector(instr) => match instr {
+                VectorInstr::V128Const(_) => (0, 1),
+                VectorInstr::V128Bitselect => (3, 1),
+                VectorInstr::V128Store(_)
+                | VectorInstr::V128Store8Lane(..)
+                | VectorInstr::V128Store16Lane(..)
+                | VectorInstr::V128Store32Lane(..)
+                | VectorInstr::V128Store64Lane(..) => (2, 0),
+                VectorInstr::V128Not
+                | VectorInstr::F32x4DemoteF64x2Zero
+                | VectorInstr::F64x2PromoteLowF32x4
+                | VectorInstr::I8x16Abs
+                | VectorInstr::I8x16Neg
+                | VectorInstr::I8x16Popcnt
+                | VectorInstr::F32x4Ceil
+                | VectorInstr::F32x4Floor
+                | VectorInstr::F32x4Trunc
+                | VectorInstr::F32x4Nearest
+                | VectorInstr::F64x2Ceil
+                | VectorInstr::F64x2Floor
+                | VectorInstr::F64x2Trunc
+                | VectorInstr::I16x8ExtaddPairwiseI8x16S
+                | VectorInstr::I16x8ExtaddPairwiseI8x16U
+                | VectorInstr::I32x4ExtaddPairwiseI16x8S
+                | VectorInstr::I32x4ExtaddPairwiseI16x8U
+                | VectorInstr::I16x8Abs
+                | VectorInstr::I16x8Neg
+                | VectorInstr::I16x8ExtendLowI8x16S
+                | VectorInstr::I16x8ExtendHighI8x16S
+                | VectorInstr::I16x8ExtendLowI8x16U
+                | VectorInstr::I16x8ExtendHighI8x16U
+                | VectorInstr::F64x2Nearest
+                | VectorInstr::I32x4Abs
+                | VectorInstr::I32x4Neg
+                | VectorInstr::I32x4ExtendLowI16x8S
+                | VectorInstr::I32x4ExtendHighI16x8S
+                | VectorInstr::I32x4ExtendLowI16x8U
+                | VectorInstr::I32x4ExtendHighI16x8U
+                | VectorInstr::I64x2Abs
+                | VectorInstr::I64x2Neg
+                | VectorInstr::I64x2ExtendLowI32x4S
+                | VectorInstr::I64x2ExtendHighI32x4S
+                | VectorInstr::I64x2ExtendLowI32x4U
+                | VectorInstr::I64x2ExtendHighI32x4U
+                | VectorInstr::F32x4Abs
+                | VectorInstr::F32x4Neg
+                | VectorInstr::F32x4Sqrt
+                | VectorInstr::F64x2Abs
+                | VectorInstr::F64x2Neg
+                | VectorInstr::F64x2Sqrt
+                | VectorInstr::I32x4TruncSatF32x4S
+                | VectorInstr::I32x4TruncSatF32x4U
+                | VectorInstr::F32x4ConvertI32x4S
+                | VectorInstr::F32x4ConvertI32x4U
+                | VectorInstr::I32x4TruncSatF64x2SZero
+                | VectorInstr::I32x4TruncSatF64x2UZero
+                | VectorInstr::F64x2ConvertLowI32x4S
+                | VectorInstr::F64x2ConvertLowI32x4U
+                | VectorInstr::V128AnyTrue
+                | VectorInstr::I8x16AllTrue
+                | VectorInstr::I8x16Bitmask
+                | VectorInstr::I16x8AllTrue
+                | VectorInstr::I16x8Bitmask
+                | VectorInstr::I32x4AllTrue
+                | VectorInstr::I32x4Bitmask
+                | VectorInstr::I64x2AllTrue
+                | VectorInstr::I64x2Bitmask
+                | VectorInstr::I8x16ExtractLaneS(_)
+                | VectorInstr::I8x16ExtractLaneU(_)
+                | VectorInstr::I16x8ExtractLaneS(_)
+                | VectorInstr::I16x8ExtractLaneU(_)
+                | VectorInstr::I32x4ExtractLane(_)
+                | VectorInstr::I64x2ExtractLane(_)
+                | VectorInstr::F32x4ExtractLane(_)
+                | VectorInstr::F64x2ExtractLane(_)
+                | VectorInstr::I8x16Splat
+                | VectorInstr::I16x8Splat
+                | VectorInstr::I32x4Splat
+                | VectorInstr::I64x2Splat
+                | VectorInstr::F32x4Splat
+                | VectorInstr::F64x2Splat
+                | VectorInstr::V128Load(_)
+                | VectorInstr::V128Load8x8S(_)
+                | VectorInstr::V128Load8x8U(_)
+                | VectorInstr::V128Load16x4S(_)
+                | VectorInstr::V128Load16x4U(_)
+                | VectorInstr::V128Load32x2S(_)
+                | VectorInstr::V128Load32x2U(_)
+                | VectorInstr::V128Load8Splat(_)
+                | VectorInstr::V128Load16Splat(_)
+                | VectorInstr::V128Load32Splat(_)
+                | VectorInstr::V128Load64Splat(_)
+                | VectorInstr::V128Load32Zero(_)
+                | VectorInstr::V128Load64Zero(_) => (1, 1),
+                _ => (2, 1),
+            },
+            Instr::Atomic(instr) => match instr {
+                AtomicInstr::MemoryAtomicWait32(_)
+                | AtomicInstr::MemoryAtomicWait64(_)
+                | AtomicInstr::I32AtomicRmwCmpxchg(_)
+                | AtomicInstr::I64AtomicRmwCmpxchg(_)
+                | AtomicInstr::I32AtomicRmw8CmpxchgU(_)
+                | AtomicInstr::I32AtomicRmw16CmpxchgU(_)
+                | AtomicInstr::I64AtomicRmw8CmpxchgU(_)
+                | AtomicInstr::I64AtomicRmw16CmpxchgU(_)
+                | AtomicInstr::I64AtomicRmw32CmpxchgU(_) => (3, 1),
+                AtomicInstr::AtomicFence => (0, 0),
+                AtomicInstr::I32AtomicLoad(_)
+                | AtomicInstr::I64AtomicLoad(_)
+                | AtomicInstr::I32AtomicLoad8U(_)
+                | AtomicInstr::I32AtomicLoad16U(_)
+                | AtomicInstr::I64AtomicLoad8U(_)
+                | AtomicInstr::I64AtomicLoad16U(_)
+                | AtomicInstr::I64AtomicLoad32U(_) => (1, 1),
+                AtomicInstr::I32AtomicStore(_)
+                | AtomicInstr::I64AtomicStore(_)
+                | AtomicInstr::I32AtomicStore8(_)
+                | AtomicInstr::I32AtomicStore16(_)
+                | AtomicInstr::I64AtomicStore8(_)
+                | AtomicInstr::I64AtomicStore16(_)
+                | AtomicInstr::I64AtomicStore32(_) => (2, 0),
+                _ => (2, 1),
+            },
+            Instr::Reference(ReferenceInstr::RefNull(_))
+            | Instr::Reference(ReferenceInstr::RefFunc(_)) => (0, 1),
+            Instr::Reference(ReferenceInstr::RefEq) => (2, 1),
+            Instr::Reference(_) => (1, 1),
+            Instr::Aggregate(instr) => match instr {
+                AggregateInstr::StructNew(idx) => match self.module.sub_type(*idx) {
+                    Some(tpe) => match &tpe.comp {
+                        CompType::Struct(tpe) => (tpe.fields.len(), 1),
+                        _ => (0, 1),
+                    },
+                    None => (0, 1),
+                },
+                AggregateInstr::StructNewDefault(_) => (0, 1),
+                AggregateInstr::ArrayNewFixed(_, n) => (**n as usize, 1),
+                AggregateInstr::StructGet(..)
+                | AggregateInstr::ArrayNewDefault(_)
+                | AggregateInstr::ArrayLen => (1, 1),
+                AggregateInstr::StructSet(..) => (2, 0),
+                AggregateInstr::ArrayNew(_)
+                | AggregateInstr::ArrayNewData(..)
+                | AggregateInstr::ArrayNewElem(..)
+                | AggregateInstr::ArrayGet(..) => (2, 1),
+                AggregateInstr::ArraySet(_) => (3, 0),
+                AggregateInstr::ArrayFill(_)
+                | AggregateInstr::ArrayInitData(..)
+                | AggregateInstr::ArrayInitElem(..) => (4, 0),
+                AggregateInstr::ArrayCopy(..) => (5, 0),
+            },
+            Instr::Dropp => (1, 0),
+            Instr::Select(_) => (3, 1),
+            Instr::Variable(VariableInstr::LocalGet(_))
+            | Instr::Variable(VariableInstr::GlobalGet(_)) => (0, 1),
+            Instr::Variable(VariableInstr::LocalTee(_)) => (1, 1),
+            Instr::Variable(_) => (1, 0),
+            Instr::Table(instr) => match instr {
+                TableInstr::TableGet(_) => (1, 1),
+                TableInstr::TableSet(_) => (2, 0),
+                TableInstr::TableSize(_) => (0, 1),
+                TableInstr::TableGrow(_) => (2, 1),
+                TableInstr::TableFill(_)
+                | TableInstr::TableCopy(..)
+                | TableInstr::TableInit(..) => (3, 0),
+                TableInstr::ElemDrop(_) => (0, 0),
+            },
+            Instr::Memory(instr) => match instr {
+                MemoryInstr::IStore(..)
+                | MemoryInstr::FStore(..)
+                | MemoryInstr::IStore8(..)
+                | MemoryInstr::IStore16(..)
+                | MemoryInstr::I64Store32(_) => (2, 0),
+                MemoryInstr::MemorySize(_) => (0, 1),
+                MemoryInstr::MemoryFill(_)
+                | MemoryInstr::MemoryCopy(..)
+                | MemoryInstr::MemoryInit(..) => (3, 0),
+                MemoryInstr::DataDrop(_) => (0, 0),
+                _ => (1, 1),
+            },
+            Instr::Control(instr) => match instr {
+                ControlInstr::Block(block) | ControlInstr::Loop(block) => {
+                    (0, self.block_results(&block.tpe))
+                }
+                ControlInstr::If(block) => (1, self.block_results(&block.tpe)),
+                ControlInstr::Try(TryBlock { tpe, .. })
+                | ControlInstr::TryTable(TryTableBlock { tpe, .. }) => (0, self.block_results(tpe)),
+                ControlInstr::Throw(idx) => match self.tag_types.get(*idx.0 as usize) {
+                    Some(Some(tpe)) => (tpe.params.types.len(), 0),
+                    _ => (0, 0),
+                },
+                ControlInstr::ThrowRef => (1, 0),
+                ControlInstr::BranchIf(_) | ControlInstr::BranchTable(..) => (1, 0),
+                ControlInstr::Return => (self.results, 0),
+                ControlInstr::Call(idx) => match self.func_types.get(*idx.0 as usize) {
+                    Some(Some(tpe)) => (tpe.params.types.len(), tpe.result.types.len()),
+                    _ => (0, 0),
+                },
+                ControlInstr::CallIndirect(_, idx) | ControlInstr::CallRef(idx) => {
+                    match self.module.func_type(*idx) {
+                        Some(tpe) => (tpe.params.types.len() + 1, tpe.result.types.len()),
+                        None => (1, 0),
+                    }
+                }
+                ControlInstr::ReturnCall(idx) => match self.func_types.get(*idx.0 as usize) {
+                    Some(Some(tpe)) => (tpe.params.types.len(), 0),
+                    _ => (0, 0),
+                },
+                ControlInstr::ReturnCallIndirect(_, idx) | ControlInstr::ReturnCallRef(idx) => {
+                    match self.module.func_type(*idx) {
+                        Some(tpe) => (tpe.params.types.len() + 1, 0),
+                        None => (1, 0),
+                    }
+                }
+                ControlInstr::BrOnNull(_)
+                | ControlInstr::BrOnCast(..)
+                | ControlInstr::BrOnCastFail(..) => (1, 1),
+                ControlInstr::BrOnNonNull(_) => (1, 0),
+                _ => (0, 0),
+            },
+        }
+    }
+
+    /// Keyword, label and type of a block.
+    fn block_head(&self, kw: &str, label: Option<&str>, tpe: &BlockType) -> String {
+        let mut head = kw.to_string();
+        if let Some(label) = label {
+            head.push_str(&format!(" ${}", label));
+        }
+        match tpe {
+            BlockType::Empty => {}
+            BlockType::Val(tpe) => {
+                head.push_str(&format!(" (result {})", valtype(tpe, &self.types)))
+            }
+            BlockType::Type(idx) => {
+                head.push_str(&format!(" (type {})", index(&self.types, *idx.0)))
+            }
+        }
+        head
+    }
+
+    /// Instruction ending a `try` block: `end`, or `delegate` along with its label. The label is
+    /// outside of the block, so this is called before entering it.
+    fn try_end(&self, block: &TryBlock) -> String {
+        match block.handler {
+            TryHandler::Catch { .. } => "end".into(),
+            TryHandler::Delegate(depth) => format!("delegate {}", self.label(*depth.0)),
+        }
+    }
+
+    /// Handlers of a `try_table` block. Their labels are outside of the block.
+    fn catches(&self, catches: &[Catch]) -> String {
+        let mut text = String::new();
+        for catch in catches {
+            text.push_str(&match catch {
+                Catch::Catch(tag, l) => format!(
+                    " (catch {} {})",
+                    index(&self.tags, *tag.0),
+                    self.label(*l.0)
+                ),
+                Catch::CatchRef(tag, l) => format!(
+                    " (catch_ref {} {})",
+                    index(&self.tags, *tag.0),
+                    self.label(*l.0)
+                ),
+                Catch::CatchAll(l) => format!(" (catch_all {})", self.label(*l.0)),
+                Catch::CatchAllRef(l) => format!(" (catch_all_ref {})", self.label(*l.0)),
+            });
+        }
+        text
+    }
+
+    /// Reference to the label `depth` blocks out. Labels shadowed by an inner block with the same
+    /// identifier are referred to by depth.
+    fn label(&self, depth: u32) -> String {
+        let depth = depth as usize;
+        if depth < self.labels.len() {
+            let target = self.labels.len() - 1 - depth;
+            if let Some(label) = self.labels[target] {
+                if !self.labels[target + 1..].contains(&Some(label)) {
+                    return format!("${}", label);
+                }
+            }
+        }
+        depth.to_string()
+    }
+
+    /// Memory immediates, leaving out the zero offset and the natural alignment.
+    fn mem_arg(&self, name: &str, natural: u32, arg: &MemArg) -> String {
+        let mut text = self.memory(name, arg.mem);
+        if *arg.offset != 0 {
+            text.push_str(&format!(" offset={}", *arg.offset));
+        }
+        if *arg.align != natural {
+            let align = 1u64.checked_shl(*arg.align).unwrap_or(0);
+            text.push_str(&format!(" align={}", align));
+        }
+        text
+    }
+
+    /// Instruction `name` followed by its memory index, which is left out for memory 0.
+    fn memory(&self, name: &str, mem: MemIdx) -> String {
+        match *mem.0 {
+            0 => name.to_string(),
+            idx => format!("{} {}", name, index(&self.mems, idx)),
+        }
+    }
+
+    /// Text of an instruction other than a block, along with its immediates.
+    fn plain(&self, instr: &Instr) -> String {
+        use FloatType::{F32, F64};
+        use IntType::{I32, I64};
+        use Sign::{Signed, Unsigned};
+        match instr {
+            Instr::Numeric(instr) => numeric(instr),
+            Instr::Vector(instr) => self.vector(instr),
+            Instr::Atomic(instr) => self.atomic(instr),
+            Instr::Aggregate(instr) => self.aggregate(instr),
+            Instr::Reference(instr) => match instr {
+                ReferenceInstr::RefNull(tpe) => {
+                    format!("ref.null {}", heap_type(tpe, &self.types))
+                }
+                ReferenceInstr::RefIsNull => "ref.is_null".into(),
+                ReferenceInstr::RefFunc(idx) => format!("ref.func {}", index(&self.funcs, *idx.0)),
+                ReferenceInstr::RefEq => "ref.eq".into(),
+                ReferenceInstr::RefAsNonNull => "ref.as_non_null".into(),
+                ReferenceInstr::RefTest(tpe) => {
+                    format!("ref.test {}", reftype(tpe, &self.types))
+                }
+                ReferenceInstr::RefCast(tpe) => {
+                    format!("ref.cast {}", reftype(tpe, &self.types))
+                }
+                ReferenceInstr::RefI31 => "ref.i31".into(),
+                ReferenceInstr::I31Get(Signed) => "i31.get_s".into(),
+                ReferenceInstr::I31Get(Unsigned) => "i31.get_u".into(),
+                ReferenceInstr::AnyConvertExtern => "any.convert_extern".into(),
+                ReferenceInstr::ExternConvertAny => "extern.convert_any".into(),
+            },
+            Instr::Dropp => "drop".into(),
+            Instr::Select(types) if types.is_empty() => "select".into(),
+            Instr::Select(types) => {
+                let types: Vec<_> = types.iter().map(|tpe| valtype(tpe, &self.types)).collect();
+                format!("select (result {})", types.join(" "))
+            }
+            Instr::Variable(instr) => match instr {
+                VariableInstr::LocalGet(idx) => {
+                    format!("local.get {}", index(&self.locals, *idx.0))
+                }
+                VariableInstr::LocalSet(idx) => {
+                    format!("local.set {}", index(&self.locals, *idx.0))
+                }
+                VariableInstr::LocalTee(idx) => {
+                    format!("local.tee {}", index(&self.locals, *idx.0))
+                }
+                VariableInstr::GlobalGet(idx) => {
+                    format!("global.get {}", index(&self.globals, *idx.0))
+                }
+                VariableInstr::GlobalSet(idx) => {
+                    format!("global.set {}", index(&self.globals, *idx.0))
+                }
+            },
+            Instr::Table(instr) => match instr {
+                TableInstr::TableGet(idx) => format!("table.get {}", index(&self.tables, *idx.0)),
+                TableInstr::TableSet(idx) => format!("table.set {}", index(&self.tables, *idx.0)),
+                TableInstr::TableSize(idx) => {
+                    format!("table.size {}", index(&self.tables, *idx.0))
+                }
+                TableInstr::TableGrow(idx) => {
+                    format!("table.grow {}", index(&self.tables, *idx.0))
+                }
+                TableInstr::TableFill(idx) => {
+                    format!("table.fill {}", index(&self.tables, *idx.0))
+                }
+                TableInstr::TableCopy(dst, src) => format!(
+                    "table.copy {} {}",
+                    index(&self.tables, *dst.0),
+                    index(&self.tables, *src.0)
+                ),
+                TableInstr::TableInit(table, elem) => format!(
+                    "table.init {} {}",
+                    index(&self.tables, *table.0),
+                    index(&self.elems, *elem.0)
+                ),
+                TableInstr::ElemDrop(idx) => format!("elem.drop {}", index(&self.elems, *idx.0)),
+            },
+            Instr::Memory(instr) => match instr {
+                MemoryInstr::ILoad(I32, arg) => self.mem_arg("i32.load", 2, arg),
+                MemoryInstr::ILoad(I64, arg) => self.mem_arg("i64.load", 3, arg),
+                MemoryInstr::FLoad(F32, arg) => self.mem_arg("f32.load", 2, arg),
+                MemoryInstr::FLoad(F64, arg) => self.mem_arg("f64.load", 3, arg),
+                MemoryInstr::IStore(I32, arg) => self.mem_arg("i32.store", 2, arg),
+                MemoryInstr::IStore(I64, arg) => self.mem_arg("i64.store", 3, arg),
+                MemoryInstr::FStore(F32, arg) => self.mem_arg("f32.store", 2, arg),
+                MemoryInstr::FStore(F64, arg) => self.mem_arg("f64.store", 3, arg),
+                MemoryInstr::ILoad8(I32, Signed, arg) => self.mem_arg("i32.load8_s", 0, arg),
+                MemoryInstr::ILoad8(I32, Unsigned, arg) => self.mem_arg("i32.load8_u", 0, arg),
+                MemoryInstr::ILoad8(I64, Signed, arg) => self.mem_arg("i64.load8_s", 0, arg),
+                MemoryInstr::ILoad8(I64, Unsigned, arg) => self.mem_arg("i64.load8_u", 0, arg),
+                MemoryInstr::ILoad16(I32, Signed, arg) => self.mem_arg("i32.load16_s", 1, arg),
+                MemoryInstr::ILoad16(I32, Unsigned, arg) => self.mem_arg("i32.load16_u", 1, arg),
+                MemoryInstr::ILoad16(I64, Signed, arg) => self.mem_arg("i64.load16_s", 1, arg),
+                MemoryInstr::ILoad16(I64, Unsigned, arg) => self.mem_arg("i64.load16_u", 1, arg),
+                MemoryInstr::I64Load32(Signed, arg) => self.mem_arg("i64.load32_s", 2, arg),
+                MemoryInstr::I64Load32(Unsigned, arg) => self.mem_arg("i64.load32_u", 2, arg),
+                MemoryInstr::IStore8(I32, arg) => self.mem_arg("i32.store8", 0, arg),
+                MemoryInstr::IStore8(I64, arg) => self.mem_arg("i64.store8", 0, arg),
+                MemoryInstr::IStore16(I32, arg) => self.mem_arg("i32.store16", 1, arg),
+                MemoryInstr::IStore16(I64, arg) => self.mem_arg("i64.store16", 1, arg),
+                MemoryInstr::I64Store32(arg) => self.mem_arg("i64.store32", 2, arg),
+                MemoryInstr::MemorySize(mem) => self.memory("memory.size", *mem),
+                MemoryInstr::MemoryGrow(mem) => self.memory("memory.grow", *mem),
+                MemoryInstr::MemoryFill(mem) => self.memory("memory.fill", *mem),
+                MemoryInstr::MemoryCopy(dst, src) if *dst.0 == 0 && *src.0 == 0 => {
+                    "memory.copy".into()
+                }
+                MemoryInstr::MemoryCopy(dst, src) => format!(
+                    "memory.copy {} {}",
+                    index(&self.mems, *dst.0),
+                    index(&self.mems, *src.0)
+                ),
+                MemoryInstr::MemoryInit(mem, idx) => format!(
+                    "{} {}",
+                    self.memory("memory.init", *mem),
+                    index(&self.datas, *idx.0)
+                ),
+                MemoryInstr::DataDrop(idx) => format!("data.drop {}", index(&self.datas, *idx.0)),
+            },
+            Instr::Control(instr) => match instr {
+                ControlInstr::Nop => "nop".into(),
+                ControlInstr::Unreachable => "unreachable".into(),
+                ControlInstr::Branch(depth) => format!("br {}", self.label(*depth.0)),
+                ControlInstr::BranchIf(depth) => format!("br_if {}", self.label(*depth.0)),
+                ControlInstr::BranchTable(labels, default) => {
+                    let mut text = "br_table".to_string();
+                    for depth in labels.iter().chain(Some(default)) {
+                        text.push(' ');
+                        text.push_str(&self.label(*depth.0));
+                    }
+                    text
+                }
+                ControlInstr::Return => "return".into(),
+                ControlInstr::Call(idx) => format!("call {}", index(&self.funcs, *idx.0)),
+                ControlInstr::CallIndirect(table, tpe) => format!(
+                    "call_indirect {} (type {})",
+                    index(&self.tables, *table.0),
+                    index(&self.types, *tpe.0)
+                ),
+                ControlInstr::ReturnCall(idx) => {
+                    format!("return_call {}", index(&self.funcs, *idx.0))
+                }
+                ControlInstr::ReturnCallIndirect(table, tpe) => format!(
+                    "return_call_indirect {} (type {})",
+                    index(&self.tables, *table.0),
+                    index(&self.types, *tpe.0)
+                ),
+                ControlInstr::Throw(idx) => format!("throw {}", index(&self.tags, *idx.0)),
+                ControlInstr::Rethrow(depth) => format!("rethrow {}", self.label(*depth.0)),
+                ControlInstr::ThrowRef => "throw_ref".into(),
+                ControlInstr::CallRef(tpe) => format!("call_ref {}", index(&self.types, *tpe.0)),
+                ControlInstr::ReturnCallRef(tpe) => {
+                    format!("return_call_ref {}", index(&self.types, *tpe.0))
+                }
+                ControlInstr::BrOnNull(depth) => format!("br_on_null {}", self.label(*depth.0)),
+                ControlInstr::BrOnNonNull(depth) => {
+                    format!("br_on_non_null {}", self.label(*depth.0))
+                }
+                ControlInstr::BrOnCast(depth, rt1, rt2) => format!(
+                    "br_on_cast {} {} {}",
+                    self.label(*depth.0),
+                    reftype(rt1, &self.types),
+                    reftype(rt2, &self.types)
+                ),
+                ControlInstr::BrOnCastFail(depth, rt1, rt2) => format!(
+                    "br_on_cast_fail {} {} {}",
+                    self.label(*depth.0),
+                    reftype(rt1, &self.types),
+                    reftype(rt2, &self.types)
+                ),
+                ControlInstr::Block(_)
+                | ControlInstr::Loop(_)
+                | ControlInstr::If(_)
+                | ControlInstr::Try(_)
+                | ControlInstr::TryTable(_) => {
+                    unreachable!("Blocks are printed on their own")
+                }
+            },
+        }
+    }
+
+    /// Text of a vector instruction, along with its immediates.
+    fn vector(&self, instr: &VectorInstr) -> String {
+        use VectorInstr::*;
+        let name = match instr {
+            V128Const(value) => {
+                let lanes: Vec<_> = (0..4)
+                    .map(|i| format!("0x{:08x}", (**value >> (32 * i)) as u32))
+                    .collect();
+                return format!("v128.const i32x4 {}", lanes.join(" "));
+            }
+            I8x16Shuffle(lanes) => {
+                let lanes: Vec<_> = lanes.iter().map(|lane| lane.0.to_string()).collect();
+                return format!("i8x16.shuffle {}", lanes.join(" "));
+            }
+            V128Load(arg) => return self.mem_arg("v128.load", 4, arg),
+            V128Load8x8S(arg) => return self.mem_arg("v128.load8x8_s", 3, arg),
+            V128Load8x8U(arg) => return self.mem_arg("v128.load8x8_u", 3, arg),
+            V128Load16x4S(arg) => return self.mem_arg("v128.load16x4_s", 3, arg),
+            V128Load16x4U(arg) => return self.mem_arg("v128.load16x4_u", 3, arg),
+            V128Load32x2S(arg) => return self.mem_arg("v128.load32x2_s", 3, arg),
+            V128Load32x2U(arg) => return self.mem_arg("v128.load32x2_u", 3, arg),
+            V128Load8Splat(arg) => return self.mem_arg("v128.load8_splat", 0, arg),
+            V128Load16Splat(arg) => return self.mem_arg("v128.load16_splat", 1, arg),
+            V128Load32Splat(arg) => return self.mem_arg("v128.load32_splat", 2, arg),
+            V128Load64Splat(arg) => return self.mem_arg("v128.load64_splat", 3, arg),
+            V128Store(arg) => return self.mem_arg("v128.store", 4, arg),
+            I8x16Swizzle => "i8x16.swizzle",
+            I8x16Splat => "i8x16.splat",
+            I16x8Splat => "i16x8.splat",
+            I32x4Splat => "i32x4.splat",
+            I64x2Splat => "i64x2.splat",
+            F32x4Splat => "f32x4.splat",
+            F64x2Splat => "f64x2.splat",
+            I8x16ExtractLaneS(lane) => return format!("i8x16.extract_lane_s {}", *lane.0),
+            I8x16ExtractLaneU(lane) => return format!("i8x16.extract_lane_u {}", *lane.0),
+            I8x16ReplaceLane(lane) => return format!("i8x16.replace_lane {}", *lane.0),
+            I16x8ExtractLaneS(lane) => return format!("i16x8.extract_lane_s {}", *lane.0),
+            I16x8ExtractLaneU(lane) => return format!("i16x8.extract_lane_u {}", *lane.0),
+            I16x8ReplaceLane(lane) => return format!("i16x8.replace_lane {}", *lane.0),
+            I32x4ExtractLane(lane) => return format!("i32x4.extract_lane {}", *lane.0),
+            I32x4ReplaceLane(lane) => return format!("i32x4.replace_lane {}", *lane.0),
+            I64x2ExtractLane(lane) => return format!("i64x2.extract_lane {}", *lane.0),
+            I64x2ReplaceLane(lane) => return format!("i64x2.replace_lane {}", *lane.0),
+            F32x4ExtractLane(lane) => return format!("f32x4.extract_lane {}", *lane.0),
+            F32x4ReplaceLane(lane) => return format!("f32x4.replace_lane {}", *lane.0),
+            F64x2ExtractLane(lane) => return format!("f64x2.extract_lane {}", *lane.0),
+            F64x2ReplaceLane(lane) => return format!("f64x2.replace_lane {}", *lane.0),
+            I8x16Eq => "i8x16.eq",
+            I8x16Ne => "i8x16.ne",
+            I8x16LtS => "i8x16.lt_s",
+            I8x16LtU => "i8x16.lt_u",
+            I8x16GtS => "i8x16.gt_s",
+            I8x16GtU => "i8x16.gt_u",
+            I8x16LeS => "i8x16.le_s",
+            I8x16LeU => "i8x16.le_u",
+            I8x16GeS => "i8x16.ge_s",
+            I8x16GeU => "i8x16.ge_u",
+            I16x8Eq => "i16x8.eq",
+            I16x8Ne => "i16x8.ne",
+            I16x8LtS => "i16x8.lt_s",
+            I16x8LtU => "i16x8.lt_u",
+            I16x8GtS => "i16x8.gt_s",
+            I16x8GtU => "i16x8.gt_u",
+            I16x8LeS => "i16x8.le_s",
+            I16x8LeU => "i16x8.le_u",
+            I16x8GeS => "i16x8.ge_s",
+            I16x8GeU => "i16x8.ge_u",
+            I32x4Eq => "i32x4.eq",
+            I32x4Ne => "i32x4.ne",
+            I32x4LtS => "i32x4.lt_s",
+            I32x4LtU => "i32x4.lt_u",
+            I32x4GtS => "i32x4.gt_s",
+            I32x4GtU => "i32x4.gt_u",
+            I32x4LeS => "i32x4.le_s",
+            I32x4LeU => "i32x4.le_u",
+            I32x4GeS => "i32x4.ge_s",
+            I32x4GeU => "i32x4.ge_u",
+            F32x4Eq => "f32x4.eq",
+            F32x4Ne => "f32x4.ne",
+            F32x4Lt => "f32x4.lt",
+            F32x4Gt => "f32x4.gt",
+            F32x4Le => "f32x4.le",
+            F32x4Ge => "f32x4.ge",
+            F64x2Eq => "f64x2.eq",
+            F64x2Ne => "f64x2.ne",
+            F64x2Lt => "f64x2.lt",
+            F64x2Gt => "f64x2.gt",
+            F64x2Le => "f64x2.le",
+            F64x2Ge => "f64x2.ge",
+            V128Not => "v128.not",
+            V128And => "v128.and",
+            V128Andnot => "v128.andnot",
+            V128Or => "v128.or",
+            V128Xor => "v128.xor",
+            V128Bitselect => "v128.bitselect",
+            V128AnyTrue => "v128.any_true",
+            V128Load8Lane(arg, lane) => {
+                return format!("{} {}", self.mem_arg("v128.load8_lane", 0, arg), *lane.0)
+            }
+            V128Load16Lane(arg, lane) => {
+                return format!("{} {}", self.mem_arg("v128.load16_lane", 1, arg), *lane.0)
+            }
+            V128Load32Lane(arg, lane) => {
+                return format!("{} {}", self.mem_arg("v128.load32_lane", 2, arg), *lane.0)
+            }
+            V128Load64Lane(arg, lane) => {
+                return format!("{} {}", self.mem_arg("v128.load64_lane", 3, arg), *lane.0)
+            }
+            V128Store8Lane(arg, lane) => {
+                return format!("{} {}", self.mem_arg("v128.store8_lane", 0, arg), *lane.0)
+            }
+            V128Store16Lane(arg, lane) => {
+                return format!("{} {}", self.mem_arg("v128.store16_lane", 1, arg), *lane.0)
+            }
+            V128Store32Lane(arg, lane) => {
+                return format!("{} {}", self.mem_arg("v128.store32_lane", 2, arg), *lane.0)
+            }
+            V128Store64Lane(arg, lane) => {
+                return format!("{} {}", self.mem_arg("v128.store64_lane", 3, arg), *lane.0)
+            }
+            V128Load32Zero(arg) => return self.mem_arg("v128.load32_zero", 2, arg),
+            V128Load64Zero(arg) => return self.mem_arg("v128.load64_zero", 3, arg),
+            F32x4DemoteF64x2Zero => "f32x4.demote_f64x2_zero",
+            F64x2PromoteLowF32x4 => "f64x2.promote_low_f32x4",
+            I8x16Abs => "i8x16.abs",
+            I8x16Neg => "i8x16.neg",
+            I8x16Popcnt => "i8x16.popcnt",
+            I8x16AllTrue => "i8x16.all_true",
+            I8x16Bitmask => "i8x16.bitmask",
+            I8x16NarrowI16x8S => "i8x16.narrow_i16x8_s",
+            I8x16NarrowI16x8U => "i8x16.narrow_i16x8_u",
+            F32x4Ceil => "f32x4.ceil",
+            F32x4Floor => "f32x4.floor",
+            F32x4Trunc => "f32x4.trunc",
+            F32x4Nearest => "f32x4.nearest",
+            I8x16Shl => "i8x16.shl",
+            I8x16ShrS => "i8x16.shr_s",
+            I8x16ShrU => "i8x16.shr_u",
+            I8x16Add => "i8x16.add",
+            I8x16AddSatS => "i8x16.add_sat_s",
+            I8x16AddSatU => "i8x16.add_sat_u",
+            I8x16Sub => "i8x16.sub",
+            I8x16SubSatS => "i8x16.sub_sat_s",
+            I8x16SubSatU => "i8x16.sub_sat_u",
+            F64x2Ceil => "f64x2.ceil",
+            F64x2Floor => "f64x2.floor",
+            I8x16MinS => "i8x16.min_s",
+            I8x16MinU => "i8x16.min_u",
+            I8x16MaxS => "i8x16.max_s",
+            I8x16MaxU => "i8x16.max_u",
+            F64x2Trunc => "f64x2.trunc",
+            I8x16AvgrU => "i8x16.avgr_u",
+            I16x8ExtaddPairwiseI8x16S => "i16x8.extadd_pairwise_i8x16_s",
+            I16x8ExtaddPairwiseI8x16U => "i16x8.extadd_pairwise_i8x16_u",
+            I32x4ExtaddPairwiseI16x8S => "i32x4.extadd_pairwise_i16x8_s",
+            I32x4ExtaddPairwiseI16x8U => "i32x4.extadd_pairwise_i16x8_u",
+            I16x8Abs => "i16x8.abs",
+            I16x8Neg => "i16x8.neg",
+            I16x8Q15mulrSatS => "i16x8.q15mulr_sat_s",
+            I16x8AllTrue => "i16x8.all_true",
+            I16x8Bitmask => "i16x8.bitmask",
+            I16x8NarrowI32x4S => "i16x8.narrow_i32x4_s",
+            I16x8NarrowI32x4U => "i16x8.narrow_i32x4_u",
+            I16x8ExtendLowI8x16S => "i16x8.extend_low_i8x16_s",
+            I16x8ExtendHighI8x16S => "i16x8.extend_high_i8x16_s",
+            I16x8ExtendLowI8x16U => "i16x8.extend_low_i8x16_u",
+            I16x8ExtendHighI8x16U => "i16x8.extend_high_i8x16_u",
+            I16x8Shl => "i16x8.shl",
+            I16x8ShrS => "i16x8.shr_s",
+            I16x8ShrU => "i16x8.shr_u",
+            I16x8Add => "i16x8.add",
+            I16x8AddSatS => "i16x8.add_sat_s",
+            I16x8AddSatU => "i16x8.add_sat_u",
+            I16x8Sub => "i16x8.sub",
+            I16x8SubSatS => "i16x8.sub_sat_s",
+            I16x8SubSatU => "i16x8.sub_sat_u",
+            F64x2Nearest => "f64x2.nearest",
+            I16x8Mul => "i16x8.mul",
+            I16x8MinS => "i16x8.min_s",
+            I16x8MinU => "i16x8.min_u",
+            I16x8MaxS => "i16x8.max_s",
+            I16x8MaxU => "i16x8.max_u",
+            I16x8AvgrU => "i16x8.avgr_u",
+            I16x8ExtmulLowI8x16S => "i16x8.extmul_low_i8x16_s",
+            I16x8ExtmulHighI8x16S => "i16x8.extmul_high_i8x16_s",
+            I16x8ExtmulLowI8x16U => "i16x8.extmul_low_i8x16_u",
+            I16x8ExtmulHighI8x16U => "i16x8.extmul_high_i8x16_u",
+            I32x4Abs => "i32x4.abs",
+            I32x4Neg => "i32x4.neg",
+            I32x4AllTrue => "i32x4.all_true",
+            I32x4Bitmask => "i32x4.bitmask",
+            I32x4ExtendLowI16x8S => "i32x4.extend_low_i16x8_s",
+            I32x4ExtendHighI16x8S => "i32x4.extend_high_i16x8_s",
+            I32x4ExtendLowI16x8U => "i32x4.extend_low_i16x8_u",
+            I32x4ExtendHighI16x8U => "i32x4.extend_high_i16x8_u",
+            I32x4Shl => "i32x4.shl",
+            I32x4ShrS => "i32x4.shr_s",
+            I32x4ShrU => "i32x4.shr_u",
+            I32x4Add => "i32x4.add",
+            I32x4Sub => "i32x4.sub",
+            I32x4Mul => "i32x4.mul",
+            I32x4MinS => "i32x4.min_s",
+            I32x4MinU => "i32x4.min_u",
+            I32x4MaxS => "i32x4.max_s",
+            I32x4MaxU => "i32x4.max_u",
+            I32x4DotI16x8S => "i32x4.dot_i16x8_s",
+            I32x4ExtmulLowI16x8S => "i32x4.extmul_low_i16x8_s",
+            I32x4ExtmulHighI16x8S => "i32x4.extmul_high_i16x8_s",
+            I32x4ExtmulLowI16x8U => "i32x4.extmul_low_i16x8_u",
+            I32x4ExtmulHighI16x8U => "i32x4.extmul_high_i16x8_u",
+            I64x2Abs => "i64x2.abs",
+            I64x2Neg => "i64x2.neg",
+            I64x2AllTrue => "i64x2.all_true",
+            I64x2Bitmask => "i64x2.bitmask",
+            I64x2ExtendLowI32x4S => "i64x2.extend_low_i32x4_s",
+            I64x2ExtendHighI32x4S => "i64x2.extend_high_i32x4_s",
+            I64x2ExtendLowI32x4U => "i64x2.extend_low_i32x4_u",
+            I64x2ExtendHighI32x4U => "i64x2.extend_high_i32x4_u",
+            I64x2Shl => "i64x2.shl",
+            I64x2ShrS => "i64x2.shr_s",
+            I64x2ShrU => "i64x2.shr_u",
+            I64x2Add => "i64x2.add",
+            I64x2Sub => "i64x2.sub",
+            I64x2Mul => "i64x2.mul",
+            I64x2Eq => "i64x2.eq",
+            I64x2Ne => "i64x2.ne",
+            I64x2LtS => "i64x2.lt_s",
+            I64x2GtS => "i64x2.gt_s",
+            I64x2LeS => "i64x2.le_s",
+            I64x2GeS => "i64x2.ge_s",
+            I64x2ExtmulLowI32x4S => "i64x2.extmul_low_i32x4_s",
+            I64x2ExtmulHighI32x4S => "i64x2.extmul_high_i32x4_s",
+            I64x2ExtmulLowI32x4U => "i64x2.extmul_low_i32x4_u",
+            I64x2ExtmulHighI32x4U => "i64x2.extmul_high_i32x4_u",
+            F32x4Abs => "f32x4.abs",
+            F32x4Neg => "f32x4.neg",
+            F32x4Sqrt => "f32x4.sqrt",
+            F32x4Add => "f32x4.add",
+            F32x4Sub => "f32x4.sub",
+            F32x4Mul => "f32x4.mul",
+            F32x4Div => "f32x4.div",
+            F32x4Min => "f32x4.min",
+            F32x4Max => "f32x4.max",
+            F32x4Pmin => "f32x4.pmin",
+            F32x4Pmax => "f32x4.pmax",
+            F64x2Abs => "f64x2.abs",
+            F64x2Neg => "f64x2.neg",
+            F64x2Sqrt => "f64x2.sqrt",
+            F64x2Add => "f64x2.add",
+            F64x2Sub => "f64x2.sub",
+            F64x2Mul => "f64x2.mul",
+            F64x2Div => "f64x2.div",
+            F64x2Min => "f64x2.min",
+            F64x2Max => "f64x2.max",
+            F64x2Pmin => "f64x2.pmin",
+            F64x2Pmax => "f64x2.pmax",
+            I32x4TruncSatF32x4S => "i32x4.trunc_sat_f32x4_s",
+            I32x4TruncSatF32x4U => "i32x4.trunc_sat_f32x4_u",
+            F32x4ConvertI32x4S => "f32x4.convert_i32x4_s",
+            F32x4ConvertI32x4U => "f32x4.convert_i32x4_u",
+            I32x4TruncSatF64x2SZero => "i32x4.trunc_sat_f64x2_s_zero",
+            I32x4TruncSatF64x2UZero => "i32x4.trunc_sat_f64x2_u_zero",
+            F64x2ConvertLowI32x4S => "f64x2.convert_low_i32x4_s",
+            F64x2ConvertLowI32x4U => "f64x2.convert_low_i32x4_u",
+        };
+        name.to_string()
+    }
+
+    /// Text of a struct or array instruction, along with its immediates.
+    fn aggregate(&self, instr: &AggregateInstr) -> String {
+        use AggregateInstr::*;
+        let sign = |sign: &Option<Sign>| match sign {
+            None => "",
+            Some(Sign::Signed) => "_s",
+            Some(Sign::Unsigned) => "_u",
+        };
+        let tpe = |idx: &TypeIdx| index(&self.types, *idx.0);
+        match instr {
+            StructNew(idx) => format!("struct.new {}", tpe(idx)),
+            StructNewDefault(idx) => format!("struct.new_default {}", tpe(idx)),
+            StructGet(s, idx, field) => format!(
+                "struct.get{} {} {}",
+                sign(s),
+                tpe(idx),
+                self.field(idx, field)
+            ),
+            StructSet(idx, field) => {
+                format!("struct.set {} {}", tpe(idx), self.field(idx, field))
+            }
+            ArrayNew(idx) => format!("array.new {}", tpe(idx)),
+            ArrayNewDefault(idx) => format!("array.new_default {}", tpe(idx)),
+            ArrayNewFixed(idx, n) => format!("array.new_fixed {} {}", tpe(idx), **n),
+            ArrayNewData(idx, data) => format!(
+                "array.new_data {} {}",
+                tpe(idx),
+                index(&self.datas, *data.0)
+            ),
+            ArrayNewElem(idx, elem) => format!(
+                "array.new_elem {} {}",
+                tpe(idx),
+                index(&self.elems, *elem.0)
+            ),
+            ArrayGet(s, idx) => format!("array.get{} {}", sign(s), tpe(idx)),
+            ArraySet(idx) => format!("array.set {}", tpe(idx)),
+            ArrayLen => "array.len".into(),
+            ArrayFill(idx) => format!("array.fill {}", tpe(idx)),
+            ArrayCopy(dst, src) => format!("array.copy {} {}", tpe(dst), tpe(src)),
+            ArrayInitData(idx, data) => format!(
+                "array.init_data {} {}",
+                tpe(idx),
+                index(&self.datas, *data.0)
+            ),
+            ArrayInitElem(idx, elem) => format!(
+                "array.init_elem {} {}",
+                tpe(idx),
+                index(&self.elems, *elem.0)
+            ),
+        }
+    }
+
+    /// Reference to a field of the struct type `tpe`, by identifier if it has one.
+    fn field(&self, tpe: &TypeIdx, field: &FieldIdx) -> String {
+        match self.fields.get(&*tpe.0) {
+            Some(ids) => index(ids, *field.0),
+            None => (*field.0).to_string(),
+        }
+    }
+
+    /// Text of an atomic instruction, along with its memory immediate.
+    fn atomic(&self, instr: &AtomicInstr) -> String {
+        use AtomicInstr::*;
+        let (name, natural, arg) = match instr {
+            AtomicFence => return "atomic.fence".into(),
+            MemoryAtomicNotify(arg) => ("memory.atomic.notify", 2, arg),
+            MemoryAtomicWait32(arg) => ("memory.atomic.wait32", 2, arg),
+            MemoryAtomicWait64(arg) => ("memory.atomic.wait64", 3, arg),
+            I32AtomicLoad(arg) => ("i32.atomic.load", 2, arg),
+            I64AtomicLoad(arg) => ("i64.atomic.load", 3, arg),
+            I32AtomicLoad8U(arg) => ("i32.atomic.load8_u", 0, arg),
+            I32AtomicLoad16U(arg) => ("i32.atomic.load16_u", 1, arg),
+            I64AtomicLoad8U(arg) => ("i64.atomic.load8_u", 0, arg),
+            I64AtomicLoad16U(arg) => ("i64.atomic.load16_u", 1, arg),
+            I64AtomicLoad32U(arg) => ("i64.atomic.load32_u", 2, arg),
+            I32AtomicStore(arg) => ("i32.atomic.store", 2, arg),
+            I64AtomicStore(arg) => ("i64.atomic.store", 3, arg),
+            I32AtomicStore8(arg) => ("i32.atomic.store8", 0, arg),
+            I32AtomicStore16(arg) => ("i32.atomic.store16", 1, arg),
+            I64AtomicStore8(arg) => ("i64.atomic.store8", 0, arg),
+            I64AtomicStore16(arg) => ("i64.atomic.store16", 1, arg),
+            I64AtomicStore32(arg) => ("i64.atomic.store32", 2, arg),
+            I32AtomicRmwAdd(arg) => ("i32.atomic.rmw.add", 2, arg),
+            I64AtomicRmwAdd(arg) => ("i64.atomic.rmw.add", 3, arg),
+            I32AtomicRmw8AddU(arg) => ("i32.atomic.rmw8.add_u", 0, arg),
+            I32AtomicRmw16AddU(arg) => ("i32.atomic.rmw16.add_u", 1, arg),
+            I64AtomicRmw8AddU(arg) => ("i64.atomic.rmw8.add_u", 0, arg),
+            I64AtomicRmw16AddU(arg) => ("i64.atomic.rmw16.add_u", 1, arg),
+            I64AtomicRmw32AddU(arg) => ("i64.atomic.rmw32.add_u", 2, arg),
+            I32AtomicRmwSub(arg) => ("i32.atomic.rmw.sub", 2, arg),
+            I64AtomicRmwSub(arg) => ("i64.atomic.rmw.sub", 3, arg),
+            I32AtomicRmw8SubU(arg) => ("i32.atomic.rmw8.sub_u", 0, arg),
+            I32AtomicRmw16SubU(arg) => ("i32.atomic.rmw16.sub_u", 1, arg),
+            I64AtomicRmw8SubU(arg) => ("i64.atomic.rmw8.sub_u", 0, arg),
+            I64AtomicRmw16SubU(arg) => ("i64.atomic.rmw16.sub_u", 1, arg),
+            I64AtomicRmw32SubU(arg) => ("i64.atomic.rmw32.sub_u", 2, arg),
+            I32AtomicRmwAnd(arg) => ("i32.atomic.rmw.and", 2, arg),
+            I64AtomicRmwAnd(arg) => ("i64.atomic.rmw.and", 3, arg),
+            I32AtomicRmw8AndU(arg) => ("i32.atomic.rmw8.and_u", 0, arg),
+            I32AtomicRmw16AndU(arg) => ("i32.atomic.rmw16.and_u", 1, arg),
+            I64AtomicRmw8AndU(arg) => ("i64.atomic.rmw8.and_u", 0, arg),
+            I64AtomicRmw16AndU(arg) => ("i64.atomic.rmw16.and_u", 1, arg),
+            I64AtomicRmw32AndU(arg) => ("i64.atomic.rmw32.and_u", 2, arg),
+            I32AtomicRmwOr(arg) => ("i32.atomic.rmw.or", 2, arg),
+            I64AtomicRmwOr(arg) => ("i64.atomic.rmw.or", 3, arg),
+            I32AtomicRmw8OrU(arg) => ("i32.atomic.rmw8.or_u", 0, arg),
+            I32AtomicRmw16OrU(arg) => ("i32.atomic.rmw16.or_u", 1, arg),
+            I64AtomicRmw8OrU(arg) => ("i64.atomic.rmw8.or_u", 0, arg),
+            I64AtomicRmw16OrU(arg) => ("i64.atomic.rmw16.or_u", 1, arg),
+            I64AtomicRmw32OrU(arg) => ("i64.atomic.rmw32.or_u", 2, arg),
+            I32AtomicRmwXor(arg) => ("i32.atomic.rmw.xor", 2, arg),
+            I64AtomicRmwXor(arg) => ("i64.atomic.rmw.xor", 3, arg),
+            I32AtomicRmw8XorU(arg) => ("i32.atomic.rmw8.xor_u", 0, arg),
+            I32AtomicRmw16XorU(arg) => ("i32.atomic.rmw16.xor_u", 1, arg),
+            I64AtomicRmw8XorU(arg) => ("i64.atomic.rmw8.xor_u", 0, arg),
+            I64AtomicRmw16XorU(arg) => ("i64.atomic.rmw16.xor_u", 1, arg),
+            I64AtomicRmw32XorU(arg) => ("i64.atomic.rmw32.xor_u", 2, arg),
+            I32AtomicRmwXchg(arg) => ("i32.atomic.rmw.xchg", 2, arg),
+            I64AtomicRmwXchg(arg) => ("i64.atomic.rmw.xchg", 3, arg),
+            I32AtomicRmw8XchgU(arg) => ("i32.atomic.rmw8.xchg_u", 0, arg),
+            I32AtomicRmw16XchgU(arg) => ("i32.atomic.rmw16.xchg_u", 1, arg),
+            I64AtomicRmw8XchgU(arg) => ("i64.atomic.rmw8.xchg_u", 0, arg),
+            I64AtomicRmw16XchgU(arg) => ("i64.atomic.rmw16.xchg_u", 1, arg),
+            I64AtomicRmw32XchgU(arg) => ("i64.atomic.rmw32.xchg_u", 2, arg),
+            I32AtomicRmwCmpxchg(arg) => ("i32.atomic.rmw.cmpxchg", 2, arg),
+            I64AtomicRmwCmpxchg(arg) => ("i64.atomic.rmw.cmpxchg", 3, arg),
+            I32AtomicRmw8CmpxchgU(arg) => ("i32.atomic.rmw8.cmpxchg_u", 0, arg),
+            I32AtomicRmw16CmpxchgU(arg) => ("i32.atomic.rmw16.cmpxchg_u", 1, arg),
+            I64AtomicRmw8CmpxchgU(arg) => ("i64.atomic.rmw8.cmpxchg_u", 0, arg),
+            I64AtomicRmw16CmpxchgU(arg) => ("i64.atomic.rmw16.cmpxchg_u", 1, arg),
+            I64AtomicRmw32CmpxchgU(arg) => ("i64.atomic.rmw32.cmpxchg_u", 2, arg),
+        };
+        self.mem_arg(name, natural, arg)
+    }
+}
+
+/// Text of a numeric instruction, along with its immediate for constants.
+fn numeric(instr: &NumericInstr) -> String {
+    use NumericInstr::*;
+    let name = match instr {
+        I32Const(value) => return format!("i32.const {}", value.0 as i32),
+        I64Const(value) => return format!("i64.const {}", value.0 as i64),
+        F32Const(value) => return format!("f32.const {}", values::f32(value.0)),
+        F64Const(value) => return format!("f64.const {}", values::f64(value.0)),
+        I32Unary(op) => return format!("i32.{}", iunop(op)),
+        I64Unary(op) => return format!("i64.{}", iunop(op)),
+        F32Unary(op) => return format!("f32.{}", funop(op)),
+        F64Unary(op) => return format!("f64.{}", funop(op)),
+        I32Binary(op) => return format!("i32.{}", ibinop(op)),
+        I64Binary(op) => return format!("i64.{}", ibinop(op)),
+        F32Binary(op) => return format!("f32.{}", fbinop(op)),
+        F64Binary(op) => return format!("f64.{}", fbinop(op)),
+        I32Test(op) => return format!("i32.{}", itestop(op)),
+        I64Test(op) => return format!("i64.{}", itestop(op)),
+        I32Relop(op) => return format!("i32.{}", irelop(op)),
+        I64Relop(op) => return format!("i64.{}", irelop(op)),
+        F32Relop(op) => return format!("f32.{}", frelop(op)),
+        F64Relop(op) => return format!("f64.{}", frelop(op)),
+        I32Extend8S => "i32.extend8_s",
+        I64Extend8S => "i64.extend8_s",
+        I32Extend16S => "i32.extend16_s",
+        I64Extend16S => "i64.extend16_s",
+        I64Extend32S => "i64.extend32_s",
+        I32WrapI64 => "i32.wrap_i64",
+        I64ExtendI32S => "i64.extend_i32_s",
+        I64ExtendI32U => "i64.extend_i32_u",
+        I32TruncF32S => "i32.trunc_f32_s",
+        I32TruncF32U => "i32.trunc_f32_u",
+        I64TruncF32S => "i64.trunc_f32_s",
+        I64TruncF32U => "i64.trunc_f32_u",
+        I32TruncF64S => "i32.trunc_f64_s",
+        I32TruncF64U => "i32.trunc_f64_u",
+        I64TruncF64S => "i64.trunc_f64_s",
+        I64TruncF64U => "i64.trunc_f64_u",
+        I32TruncSatF32S => "i32.trunc_sat_f32_s",
+        I32TruncSatF32U => "i32.trunc_sat_f32_u",
+        I32TruncSatF64S => "i32.trunc_sat_f64_s",
+        I32TruncSatF64U => "i32.trunc_sat_f64_u",
+        I64TruncSatF32S => "i64.trunc_sat_f32_s",
+        I64TruncSatF32U => "i64.trunc_sat_f32_u",
+        I64TruncSatF64S => "i64.trunc_sat_f64_s",
+        I64TruncSatF64U => "i64.trunc_sat_f64_u",
+        F32DemoteF64 => "f32.demote_f64",
+        F64PromoteF32 => "f64.promote_f32",
+        F32ConvertI32S => "f32.convert_i32_s",
+        F32ConvertI32U => "f32.convert_i32_u",
+        F32ConvertI64S => "f32.convert_i64_s",
+        F32ConvertI64U => "f32.convert_i64_u",
+        F64ConvertI32S => "f64.convert_i32_s",
+        F64ConvertI32U => "f64.convert_i32_u",
+        F64ConvertI64S => "f64.convert_i64_s",
+        F64ConvertI64U => "f64.convert_i64_u",
+        I32ReinterpretF32 => "i32.reinterpret_f32",
+        I64ReinterpretF64 => "i64.reinterpret_f64",
+        F32ReinterpretI32 => "f32.reinterpret_i32",
+        F64ReinterpretI64 => "f64.reinterpret_i64",
+    };
+    name.to_string()
+}
+
+fn iunop<const T: IntType>(op: &IUnop<T>) -> &'static str {
+    match op {
+        IUnop::Clz => "clz",
+        IUnop::Ctz => "ctz",
+        IUnop::Popcnt => "popcnt",
+    }
+}
+
+fn ibinop<const T: IntType>(op: &IBinop<T>) -> &'static str {
+    match op {
+        IBinop::Add => "add",
+        IBinop::Sub => "sub",
+        IBinop::Mul => "mul",
+        IBinop::DivS => "div_s",
+        IBinop::DivU => "div_u",
+        IBinop::RemS => "rem_s",
+        IBinop::RemU => "rem_u",
+        IBinop::And => "and",
+        IBinop::Or => "or",
+        IBinop::Xor => "xor",
+        IBinop::Shl => "shl",
+        IBinop::ShrS => "shr_s",
+        IBinop::ShrU => "shr_u",
+        IBinop::Rotl => "rotl",
+        IBinop::Rotr => "rotr",
+    }
+}
+
+fn itestop<const T: IntType>(op: &ITestop<T>) -> &'static str {
+    match op {
+        ITestop::Eqz => "eqz",
+    }
+}
+
+fn irelop<const T: IntType>(op: &IRelop<T>) -> &'static str {
+    match op {
+        IRelop::Equ => "eq",
+        IRelop::Ne => "ne",
+        IRelop::LtS => "lt_s",
+        IRelop::LtU => "lt_u",
+        IRelop::GtS => "gt_s",
+        IRelop::GtU => "gt_u",
+        IRelop::LeS => "le_s",
+        IRelop::LeU => "le_u",
+        IRelop::GeS => "ge_s",
+        IRelop::GeU => "ge_u",
+    }
+}
+
+fn funop<const T: FloatType>(op: &FUnop<T>) -> &'static str {
+    match op {
+        FUnop::Abs => "abs",
+        FUnop::Neg => "neg",
+        FUnop::Sqrt => "sqrt",
+        FUnop::Ceil => "ceil",
+        FUnop::Floor => "floor",
+        FUnop::Trunc => "trunc",
+        FUnop::Nearest => "nearest",
+    }
+}
+
+fn fbinop<const T: FloatType>(op: &FBinop<T>) -> &'static str {
+    match op {
+        FBinop::Add => "add",
+        FBinop::Sub => "sub",
+        FBinop::Mul => "mul",
+        FBinop::Div => "div",
+        FBinop::Min => "min",
+        FBinop::Max => "max",
+        FBinop::CopySign => "copysign",
+    }
+}
+
+fn frelop<const T: FloatType>(op: &FRelop<T>) -> &'static str {
+    match op {
+        FRelop::Equ => "eq",
+        FRelop::Ne => "ne",
+        FRelop::Lt => "lt",
+        FRelop::Gt => "gt",
+        FRelop::Le => "le",
+        FRelop::Ge => "ge",
+    }
+}
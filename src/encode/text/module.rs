@@ -0,0 +1,854 @@
+//! Module fields.
+
+use super::instr::{block_labels, reftype, valtype};
+use super::{define, ids, index, values, Ids, Printer};
+use crate::wasm::data::{Data, DataMode};
+use crate::wasm::elem::{Elem, ElemMode};
+use crate::wasm::export::{Export, ExportDesc};
+use crate::wasm::func::Func;
+use crate::wasm::global::Global;
+use crate::wasm::import::{Import, ImportDesc};
+use crate::wasm::indices::TypeIdx;
+use crate::wasm::instr::{Expr, Instr, ReferenceInstr};
+use crate::wasm::types::{
+    CompType, FieldType, FuncType, GlobalType, IndexType, Limits, MemType, Mutability, RefType,
+    StorageType, SubType, TableType, ValType,
+};
+
+/// Declarations such as ` (param $a i32) (param i32 i32)`, numbering the values from `first`.
+/// Values with an identifier get their own declaration while the others are grouped. Type
+/// indices are referred to by the identifiers `types`.
+fn decls(kw: &str, tpes: &[ValType], first: u32, ids: Option<&Ids<'_>>, types: &Ids<'_>) -> String {
+    let mut text = String::new();
+    let mut group = false;
+    for (i, tpe) in tpes.iter().enumerate() {
+        let id = ids.and_then(|ids| ids.get(&(first + i as u32)));
+        let tpe = valtype(tpe, types);
+        match id {
+            Some(id) => text.push_str(&format!(" ({} ${} {})", kw, id, tpe)),
+            None if group => {
+                text.pop();
+                text.push_str(&format!(" {})", tpe));
+            }
+            None => text.push_str(&format!(" ({} {})", kw, tpe)),
+        }
+        group = id.is_none();
+    }
+    text
+}
+
+fn signature(tpe: &FuncType, ids: Option<&Ids<'_>>, types: &Ids<'_>) -> String {
+    let mut text = decls("param", &tpe.params.types, 0, ids, types);
+    text.push_str(&decls("result", &tpe.result.types, 0, None, types));
+    text
+}
+
+fn field_type(tpe: &FieldType, types: &Ids<'_>) -> String {
+    let storage = match &tpe.tpe {
+        StorageType::Val(tpe) => valtype(tpe, types),
+        StorageType::I8 => "i8".into(),
+        StorageType::I16 => "i16".into(),
+    };
+    match tpe.mutability {
+        Mutability::Const => storage,
+        Mutability::Mut => format!("(mut {})", storage),
+    }
+}
+
+/// Definition of a type such as `(func (param i32))`, with the identifiers `fields` for the
+/// fields of a struct.
+fn comp_type(tpe: &CompType, fields: Option<&Ids<'_>>, types: &Ids<'_>) -> String {
+    match tpe {
+        CompType::Func(tpe) => format!("(func{})", signature(tpe, None, types)),
+        CompType::Struct(tpe) => {
+            let mut text = "(struct".to_string();
+            for (i, field) in tpe.fields.iter().enumerate() {
+                match fields.and_then(|ids| ids.get(&(i as u32))) {
+                    Some(id) => text.push_str(&format!(" (field ${}", id)),
+                    None => text.push_str(" (field"),
+                }
+                text.push_str(&format!(" {})", field_type(field, types)));
+            }
+            text.push(')');
+            text
+        }
+        CompType::Array(tpe) => format!("(array {})", field_type(&tpe.field, types)),
+    }
+}
+
+/// Type definition, without the `(sub ...)` around the final ones that have no supertypes.
+fn sub_type(tpe: &SubType, fields: Option<&Ids<'_>>, types: &Ids<'_>) -> String {
+    let comp = comp_type(&tpe.comp, fields, types);
+    if tpe.is_final && tpe.supers.is_empty() {
+        return comp;
+    }
+    let mut text = "(sub".to_string();
+    if tpe.is_final {
+        text.push_str(" final");
+    }
+    for idx in &tpe.supers {
+        text.push(' ');
+        text.push_str(&index(types, *idx.0));
+    }
+    format!("{} {})", text, comp)
+}
+
+fn limits(lim: &Limits) -> String {
+    match lim.max {
+        Some(max) => format!("{} {}", *lim.min, *max),
+        None => lim.min.to_string(),
+    }
+}
+
+fn mem_type(tpe: &MemType) -> String {
+    let mut text = match tpe.index {
+        IndexType::I32 => limits(&tpe.lim),
+        IndexType::I64 => format!("i64 {}", limits(&tpe.lim)),
+    };
+    if tpe.shared {
+        text.push_str(" shared");
+    }
+    text
+}
+
+fn table_type(tpe: &TableType, types: &Ids<'_>) -> String {
+    let lim = match tpe.index {
+        IndexType::I32 => limits(&tpe.lim),
+        IndexType::I64 => format!("i64 {}", limits(&tpe.lim)),
+    };
+    format!("{} {}", lim, reftype(&tpe.tpe, types))
+}
+
+fn global_type(tpe: &GlobalType, types: &Ids<'_>) -> String {
+    match tpe.mutability {
+        Mutability::Const => valtype(&tpe.tpe, types),
+        Mutability::Mut => format!("(mut {})", valtype(&tpe.tpe, types)),
+    }
+}
+
+/// The functions referred to by the initializers of an element segment, if they're all plain
+/// function references.
+fn elem_funcs(elem: &Elem) -> Option<Vec<u32>> {
+    if elem.tpe != RefType::FUNCREF {
+        return None;
+    }
+    elem.init
+        .iter()
+        .map(|item| match item.instr.as_slice() {
+            [Instr::Reference(ReferenceInstr::RefFunc(idx))] => Some(*idx.0),
+            _ => None,
+        })
+        .collect()
+}
+
+impl<'a> Printer<'a> {
+    pub(crate) fn module(&mut self) {
+        let module = self.module;
+        self.func_types = module
+            .imports
+            .iter()
+            .filter_map(|import| match &import.desc {
+                ImportDesc::Func(idx) => Some(idx),
+                _ => None,
+            })
+            .chain(module.funcs.iter().map(|func| &func.index))
+            .map(|idx| module.func_type(*idx))
+            .collect();
+        self.tag_types = module
+            .imports
+            .iter()
+            .filter_map(|import| match &import.desc {
+                ImportDesc::Tag(idx) => Some(idx),
+                _ => None,
+            })
+            .chain(module.tags.iter().map(|tag| &tag.tpe))
+            .map(|idx| module.func_type(*idx))
+            .collect();
+
+        match module
+            .name
+            .as_ref()
+            .filter(|name| values::is_id(&name.name))
+        {
+            Some(name) => self.line(&format!("(module ${}", name.name)),
+            None => self.line("(module"),
+        }
+        self.indent += 1;
+        let mut idx = 0;
+        for rec in &module.types {
+            // Types on their own are left out of a `rec`.
+            let grouped = rec.types.len() != 1;
+            if grouped {
+                self.line("(rec");
+                self.indent += 1;
+            }
+            for tpe in &rec.types {
+                let text = format!(
+                    "(type {} {})",
+                    define(&self.types, idx),
+                    sub_type(tpe, self.fields.get(&idx), &self.types)
+                );
+                self.line(&text);
+                idx += 1;
+            }
+            if grouped {
+                self.indent -= 1;
+                self.line(")");
+            }
+        }
+        let mut counts = [0; 5];
+        for import in &module.imports {
+            self.import(import, &mut counts);
+        }
+        let [funcs, tables, mems, globals, tags] = counts;
+        for (idx, func) in module.funcs.iter().enumerate() {
+            self.func(funcs + idx as u32, func);
+        }
+        for (idx, table) in module.tables.iter().enumerate() {
+            let text = format!(
+                "(table {} {}",
+                define(&self.tables, tables + idx as u32),
+                table_type(&table.tpe, &self.types)
+            );
+            self.line(&text);
+            if let Some(init) = &table.init {
+                self.inline_expr(init);
+            }
+            self.push(")");
+        }
+        for (idx, mem) in module.mems.iter().enumerate() {
+            let text = format!(
+                "(memory {} {})",
+                define(&self.mems, mems + idx as u32),
+                mem_type(&mem.tpe)
+            );
+            self.line(&text);
+        }
+        for (idx, tag) in module.tags.iter().enumerate() {
+            let text = format!(
+                "(tag {}{})",
+                define(&self.tags, tags + idx as u32),
+                self.type_use(&tag.tpe, None)
+            );
+            self.line(&text);
+        }
+        for (idx, global) in module.globals.iter().enumerate() {
+            self.global(globals + idx as u32, global);
+        }
+        for export in &module.exports {
+            self.export(export);
+        }
+        if let Some(start) = &module.start {
+            let text = format!("(start {})", index(&self.funcs, *start.func.0));
+            self.line(&text);
+        }
+        for (idx, elem) in module.elems.iter().enumerate() {
+            self.elem(idx as u32, elem);
+        }
+        for (idx, data) in module.datas.iter().enumerate() {
+            self.data(idx as u32, data);
+        }
+        self.indent -= 1;
+        self.line(")");
+    }
+
+    /// Explicit type use of a function along with its signature, for readability.
+    fn type_use(&self, idx: &TypeIdx, ids: Option<&Ids<'_>>) -> String {
+        let mut text = format!(" (type {})", index(&self.types, *idx.0));
+        if let Some(tpe) = self.module.func_type(*idx) {
+            text.push_str(&signature(tpe, ids, &self.types));
+        }
+        text
+    }
+
+    /// Prints an import. `counts` holds the number of functions, tables, memories, globals and
+    /// tags imported so far.
+    fn import(&mut self, import: &Import, counts: &mut [u32; 5]) {
+        let (kw, ids, count, desc) = match &import.desc {
+            ImportDesc::Func(idx) => ("func", &self.funcs, 0, self.type_use(idx, None)),
+            ImportDesc::Table(tpe) => (
+                "table",
+                &self.tables,
+                1,
+                format!(" {}", table_type(tpe, &self.types)),
+            ),
+            ImportDesc::Mem(tpe) => ("memory", &self.mems, 2, format!(" {}", mem_type(tpe))),
+            ImportDesc::Global(tpe) => (
+                "global",
+                &self.globals,
+                3,
+                format!(" {}", global_type(tpe, &self.types)),
+            ),
+            ImportDesc::Tag(idx) => ("tag", &self.tags, 4, self.type_use(idx, None)),
+        };
+        let text = format!(
+            "(import {} {} ({} {}{}))",
+            values::name(&import.module.name),
+            values::name(&import.name.name),
+            kw,
+            define(ids, counts[count]),
+            desc
+        );
+        counts[count] += 1;
+        self.line(&text);
+    }
+
+    fn func(&mut self, idx: u32, func: &'a Func) {
+        let module = self.module;
+        let names = &module.names;
+        self.locals = names.locals.names.get(&idx).map(ids).unwrap_or_default();
+        self.block_labels = names
+            .labels
+            .names
+            .get(&idx)
+            .map(|labels| block_labels(&func.body.instr, labels))
+            .unwrap_or_default();
+        let tpe = module.func_type(func.index);
+        self.results = tpe.map_or(0, |tpe| tpe.result.types.len());
+
+        let text = format!(
+            "(func {}{}",
+            define(&self.funcs, idx),
+            self.type_use(&func.index, Some(&self.locals))
+        );
+        self.line(&text);
+        if func.locals.is_empty() && func.body.instr.is_empty() {
+            self.push(")");
+        } else {
+            self.indent += 1;
+            if !func.locals.is_empty() {
+                let params = tpe.map_or(0, |tpe| tpe.params.types.len()) as u32;
+                let text = decls(
+                    "local",
+                    &func.locals,
+                    params,
+                    Some(&self.locals),
+                    &self.types,
+                );
+                self.line(text.trim_start());
+            }
+            self.instrs(&func.body.instr);
+            self.indent -= 1;
+            self.line(")");
+        }
+        self.locals.clear();
+        self.block_labels.clear();
+    }
+
+    fn global(&mut self, idx: u32, global: &'a Global) {
+        let text = format!(
+            "(global {} {}",
+            define(&self.globals, idx),
+            global_type(&global.tpe, &self.types)
+        );
+        self.line(&text);
+        self.inline_expr(&global.init);
+        self.push(")");
+    }
+
+    fn export(&mut self, export: &Export) {
+        let (kw, idx) = match &export.desc {
+            ExportDesc::Func(idx) => ("func", index(&self.funcs, *idx.0)),
+            ExportDesc::Table(idx) => ("table", index(&self.tables, *idx.0)),
+            ExportDesc::Mem(idx) => ("memory", index(&self.mems, *idx.0)),
+            ExportDesc::Global(idx) => ("global", index(&self.globals, *idx.0)),
+            ExportDesc::Tag(idx) => ("tag", index(&self.tags, *idx.0)),
+        };
+        let text = format!(
+            "(export {} ({} {}))",
+            values::name(&export.name.name),
+            kw,
+            idx
+        );
+        self.line(&text);
+    }
+
+    /// Prints the offset of an active segment, abbreviated to a single folded instruction when
+    /// possible.
+    fn offset(&mut self, offset: &'a Expr) {
+        if self.is_folded(offset) {
+            self.inline_expr(offset);
+        } else {
+            self.push(" (offset");
+            self.inline_expr(offset);
+            self.push(")");
+        }
+    }
+
+    fn elem(&mut self, idx: u32, elem: &'a Elem) {
+        let mut text = format!("(elem {}", define(&self.elems, idx));
+        match &elem.mode {
+            ElemMode::Passive => {}
+            ElemMode::Declarative => text.push_str(" declare"),
+            ElemMode::Active { table, .. } if *table.0 != 0 => {
+                text.push_str(&format!(" (table {})", index(&self.tables, *table.0)))
+            }
+            ElemMode::Active { .. } => {}
+        }
+        self.line(&text);
+        if let ElemMode::Active { offset, .. } = &elem.mode {
+            self.offset(offset);
+        }
+        match elem_funcs(elem) {
+            Some(funcs) => {
+                self.push(" func");
+                for func in funcs {
+                    let text = format!(" {}", index(&self.funcs, func));
+                    self.push(&text);
+                }
+            }
+            None => {
+                self.push(&format!(" {}", reftype(&elem.tpe, &self.types)));
+                for item in &elem.init {
+                    self.push(" (item");
+                    self.inline_expr(item);
+                    self.push(")");
+                }
+            }
+        }
+        self.push(")");
+    }
+
+    fn data(&mut self, idx: u32, data: &'a Data) {
+        let mut text = format!("(data {}", define(&self.datas, idx));
+        if let DataMode::Active { memory, .. } = &data.mode {
+            if *memory.0 != 0 {
+                text.push_str(&format!(" (memory {})", index(&self.mems, *memory.0)));
+            }
+        }
+        self.line(&text);
+        if let DataMode::Active { offset, .. } = &data.mode {
+            self.offset(offset);
+        }
+        let init: Vec<u8> = data.init.iter().map(|byte| byte.0).collect();
+        self.push(&format!(" {})", values::string(&init)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::encode::text::{print, Style};
+    use crate::parse::binary::MAX_NESTING;
+    use crate::parse::text::parse;
+    use crate::wasm::module::Module;
+    use crate::wasm::values::Name;
+
+    /// Checks that printing `module` in both styles and parsing the result gives the same module.
+    fn roundtrip(module: &Module) {
+        for style in [Style::Flat, Style::Folded] {
+            let text = print(module, style);
+            let reparsed = parse(&text).unwrap_or_else(|err| panic!("{}\n{}", err, text));
+            assert_eq!(reparsed.to_binary(), module.to_binary(), "{}", text);
+            assert_eq!(reparsed.names, module.names, "{}", text);
+        }
+    }
+
+    fn roundtrip_text(text: &str) {
+        roundtrip(&parse(text).unwrap());
+    }
+
+    const BLOCKS: &str = r#"(module $m
+        (func $f (export "f") (param $x i32) (result i32) (local $y i64) (local f32 f32)
+            (block $out
+                (loop $top
+                    (br_if $out (i32.eqz (local.get $x)))
+                    (local.set $x (i32.sub (local.get $x) (i32.const 1)))
+                    (br $top)))
+            (if $l (result i32) (local.get $x)
+                (then (i32.const 1))
+                (else (call $f (i32.const 0))))))"#;
+
+    #[test]
+    fn flat_style() {
+        let module = parse(BLOCKS).unwrap();
+        assert_eq!(
+            print(&module, Style::Flat),
+            r#"(module $m
+  (type (;0;) (func (param i32) (result i32)))
+  (func $f (;0;) (type 0) (param $x i32) (result i32)
+    (local $y i64) (local f32 f32)
+    block $out
+      loop $top
+        local.get $x
+        i32.eqz
+        br_if $out
+        local.get $x
+        i32.const 1
+        i32.sub
+        local.set $x
+        br $top
+      end
+    end
+    local.get $x
+    if $l (result i32)
+      i32.const 1
+    else
+      i32.const 0
+      call $f
+    end
+  )
+  (export "f" (func $f))
+)
+"#
+        );
+        assert_eq!(module.to_string(), print(&module, Style::Flat));
+        assert_eq!(module.to_wat(), print(&module, Style::Flat));
+    }
+
+    #[test]
+    fn folded_style() {
+        let module = parse(BLOCKS).unwrap();
+        assert_eq!(
+            format!("{:#}", module),
+            r#"(module $m
+  (type (;0;) (func (param i32) (result i32)))
+  (func $f (;0;) (type 0) (param $x i32) (result i32)
+    (local $y i64) (local f32 f32)
+    (block $out
+      (loop $top
+        (br_if $out
+          (i32.eqz
+            (local.get $x)
+          )
+        )
+        (local.set $x
+          (i32.sub
+            (local.get $x)
+            (i32.const 1)
+          )
+        )
+        (br $top)
+      )
+    )
+    (if $l (result i32)
+      (local.get $x)
+      (then
+        (i32.const 1)
+      )
+      (else
+        (call $f
+          (i32.const 0)
+        )
+      )
+    )
+  )
+  (export "f" (func $f))
+)
+"#
+        );
+    }
+
+    #[test]
+    fn module_fields() {
+        let text = r#"(module
+            (type $t (func (param i64) (result f64)))
+            (import "env" "f" (func $imported (type $t)))
+            (import "env" "table" (table $tab 1 funcref))
+            (import "env" "mem" (memory 1 2))
+            (import "env" "g" (global $g (mut i32)))
+            (func $a (type $t) f64.const 1.5)
+            (func $b call_indirect $tab (type $t) drop)
+            (table $other 2 externref)
+            (memory $mem 1)
+            (global $h f32 (f32.const -0))
+            (global i64 global.get $g i64.extend_i32_u)
+            (export "a" (func $a))
+            (export "\"quoted\"\n" (global $h))
+            (start $b)
+            (elem $e (i32.const 0) $a $imported)
+            (elem funcref (ref.null func) (item ref.func $b))
+            (elem (table $other) (offset i32.const 1) externref (ref.null extern))
+            (elem declare func $a)
+            (data (memory $mem) (offset (i32.const 1) (i32.const 2) i32.add) "a\00\ff\"")
+            (data $d "passive"))"#;
+        roundtrip_text(text);
+
+        let module = parse(text).unwrap();
+        let printed = print(&module, Style::Folded);
+        assert!(
+            printed.contains(
+                r#"(import "env" "f" (func $imported (;0;) (type $t) (param i64) (result f64)))"#
+            ),
+            "{}",
+            printed
+        );
+        assert!(
+            printed.contains("(global $h (;1;) f32 (f32.const -0.0))"),
+            "{}",
+            printed
+        );
+        assert!(
+            printed.contains("(elem $e (;0;) (i32.const 0) func $a $imported)"),
+            "{}",
+            printed
+        );
+        assert!(
+            printed.contains("(elem (;1;) funcref (item (ref.null func)) (item (ref.func $b)))"),
+            "{}",
+            printed
+        );
+        assert!(
+            printed.contains(r#"(export "\"quoted\"\n" (global $h))"#),
+            "{}",
+            printed
+        );
+        let printed = print(&module, Style::Flat);
+        assert!(
+            printed.contains("(global (;2;) i64 global.get $g i64.extend_i32_u)"),
+            "{}",
+            printed
+        );
+        assert!(
+            printed.contains(
+                "(elem (;2;) (table $other) (offset i32.const 1) externref (item ref.null extern))"
+            ),
+            "{}",
+            printed
+        );
+        assert!(
+            printed.contains(
+                r#"(data (;0;) (memory $mem) (offset i32.const 1 i32.const 2 i32.add) "a\00\ff\"")"#
+            ),
+            "{}",
+            printed
+        );
+    }
+
+    #[test]
+    fn instructions() {
+        roundtrip_text(
+            r#"(module
+                (type (func (param i32 i32) (result i32 i32)))
+                (memory 1)
+                (table 1 funcref)
+                (elem $e func)
+                (data $d "")
+                (func (param i32) (result i32) (local i64 f32 f64 externref)
+                    i32.const -2147483648 i64.const -1 f32.const 0x1p-149 f64.const -nan:0x1
+                    f32.const nan f64.const inf drop drop drop drop drop
+                    i32.clz i32.popcnt i32.eqz i64.extend_i32_s i32.wrap_i64
+                    i32.const 1 i32.rotr i32.const 2 i32.ge_u
+                    f32.convert_i32_s f32.sqrt f32.const 2 f32.copysign f32.const 3 f32.le
+                    i32.trunc_sat_f64_u
+                    f64.promote_f32 i64.trunc_sat_f64_s f64.reinterpret_i64 i64.reinterpret_f64
+                    i32.wrap_i64 i32.extend8_s
+                    i64.load32_s offset=8 align=2 i64.store16 offset=1
+                    i32.const 0 i64.load8_u align=1 drop
+                    i32.const 0 f64.load offset=4294967295 drop
+                    memory.size memory.grow drop
+                    i32.const 0 i32.const 0 i32.const 0 memory.copy
+                    i32.const 0 i32.const 0 i32.const 0 memory.fill
+                    i32.const 0 i32.const 0 i32.const 0 memory.init $d data.drop $d
+                    i32.const 0 i32.const 0 i32.const 0 table.init 0 $e elem.drop $e
+                    i32.const 0 table.get 0 ref.is_null drop
+                    ref.null func i32.const 1 table.grow 0 drop
+                    table.size 0 drop
+                    i32.const 0 ref.func 0 i32.const 0 table.fill 0
+                    i32.const 0 i32.const 0 i32.const 0 table.copy 0 0
+                    i32.const 1 i32.const 2 local.get 0 select
+                    ref.null extern ref.null extern local.get 0 select (result externref)
+                    local.set 4 local.tee 0
+                    i32.const 0 i32.const 1 block (type 0) i32.add i32.const 0 end drop
+                    block (result i32) br_table 0 0 0 end
+                    if i32.const 0 call_indirect (type 0) unreachable end
+                    i32.const 0 return_call 0 i32.const 0 return_call_indirect (type 0)
+                    nop i32.const 0 return))"#,
+        );
+    }
+
+    #[test]
+    fn vector_instructions() {
+        roundtrip_text(
+            r#"(module
+                (memory 1)
+                (func (param v128) (result v128) (local i32)
+                    v128.const i8x16 -1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 255
+                    v128.const f32x4 nan -0 0x1p-149 inf
+                    i8x16.shuffle 0 31 1 30 2 29 3 28 4 27 5 26 6 25 7 24
+                    v128.const i64x2 -1 0x8000_0000_0000_0000 i32x4.add
+                    i16x8.extract_lane_s 7 i8x16.splat
+                    local.get 0 i8x16.swizzle f64x2.promote_low_f32x4
+                    v128.const f64x2 1.5 -nan:0x1 v128.const i16x8 0 1 2 3 4 5 6 7
+                    v128.bitselect i32.const 3 i64x2.shr_u
+                    f32.const 1 f32x4.replace_lane 3 i16x8.q15mulr_sat_s
+                    i32x4.trunc_sat_f64x2_s_zero i32x4.extmul_high_i16x8_u
+                    i8x16.all_true local.set 1
+                    local.get 1 v128.load offset=16 align=1 v128.any_true
+                    v128.load64_splat v128.load32_zero align=4
+                    local.get 1 local.get 0 v128.load16_lane offset=2 7
+                    local.get 1 local.get 0 v128.store64_lane 1 drop
+                    local.get 1 local.get 0 v128.store))"#,
+        );
+    }
+
+    #[test]
+    fn atomic_instructions() {
+        roundtrip_text(
+            r#"(module
+                (import "env" "mem" (memory 1 2 shared))
+                (memory 1 1 shared)
+                (func (param i32 i64) (result i64)
+                    atomic.fence
+                    local.get 0 i32.const 1 memory.atomic.notify drop
+                    local.get 0 i32.const 0 i64.const -1 memory.atomic.wait32 drop
+                    local.get 0 local.get 1 i64.const 0 memory.atomic.wait64 offset=8 drop
+                    local.get 0 i32.atomic.load16_u i32.atomic.load8_u drop
+                    local.get 0 local.get 1 i64.atomic.store32 offset=4
+                    local.get 0 i32.const 7 i32.atomic.rmw8.xor_u drop
+                    local.get 0 local.get 1 i64.atomic.rmw.add drop
+                    local.get 0 local.get 1 local.get 1 i64.atomic.rmw32.cmpxchg_u))"#,
+        );
+    }
+
+    #[test]
+    fn multiple_memories() {
+        roundtrip_text(
+            r#"(module
+                (memory 1)
+                (memory $m 2 3)
+                (data (memory $m) (i32.const 0) "x")
+                (data "y")
+                (func (param i32 v128) (result i32)
+                    local.get 0 i32.load $m offset=4 drop
+                    local.get 0 i64.const 1 i64.store8 1
+                    local.get 0 local.get 1 v128.store64_lane $m align=1 1
+                    local.get 0 local.get 1 v128.load8_lane 1 15 drop
+                    local.get 0 local.get 0 local.get 0 memory.copy 0 $m
+                    local.get 0 local.get 0 local.get 0 memory.copy
+                    local.get 0 local.get 0 local.get 0 memory.init $m 1
+                    local.get 0 local.get 0 local.get 0 memory.init 1
+                    local.get 0 local.get 0 local.get 0 memory.fill 1
+                    i32.const 1 memory.grow $m drop
+                    memory.size 1))"#,
+        );
+    }
+
+    #[test]
+    fn memory64() {
+        roundtrip_text(
+            r#"(module
+                (import "env" "mem" (memory i64 1 shared))
+                (import "env" "table" (table i64 1 funcref))
+                (memory i64 0x100000000)
+                (table i64 2 0x100000000 funcref)
+                (data (memory 1) (i64.const 0x100000000) "x")
+                (func (param i64) (result i64)
+                    local.get 0 table.get 1 drop
+                    table.size drop
+                    local.get 0 i64.load 1 offset=0x100000000
+                    local.get 0 local.get 0 i64.const 1 memory.fill 1
+                    memory.size drop
+                    i64.const 1 memory.grow 1 i64.add))"#,
+        );
+    }
+
+    #[test]
+    fn exceptions() {
+        roundtrip_text(
+            r#"(module
+                (import "env" "e" (tag $imported (param i32)))
+                (tag $e (export "e") (param i64 i32))
+                (tag $empty)
+                (func (param i32) (result i32) (local exnref)
+                    block $b (result i64 i32 exnref)
+                        try_table (result i32) (catch_ref $e $b) (catch $imported 1)
+                            local.get 0 throw $imported
+                        end
+                        return
+                    end
+                    local.set 1 drop drop
+                    try $t (result i32)
+                        throw $empty
+                    catch $imported
+                        try
+                            rethrow $t
+                        delegate $t
+                    catch_all
+                        i32.const 0
+                    end
+                    block $c (result exnref)
+                        try_table (catch_all_ref $c) local.get 1 throw_ref end
+                        ref.null exn
+                    end
+                    drop))"#,
+        );
+        roundtrip_text(
+            r#"(module
+                (tag $e (param i32))
+                (func (result i32)
+                    (try (result i32)
+                        (do (throw $e (i32.const 1)))
+                        (catch $e)
+                        (catch_all (i32.const 0)))
+                    (try (do) (delegate 0))))"#,
+        );
+    }
+
+    #[test]
+    fn names() {
+        roundtrip_text(BLOCKS);
+
+        // Names that aren't valid identifiers or that are taken are replaced by indices.
+        let mut module = parse("(func $a) (func $b) (func $c (local i32 i32) call 1)").unwrap();
+        module
+            .names
+            .funcs
+            .names
+            .insert(1, Name { name: "a".into() });
+        module.names.funcs.names.insert(
+            2,
+            Name {
+                name: "has space".into(),
+            },
+        );
+        let printed = print(&module, Style::Flat);
+        assert!(printed.contains("(func $a (;0;) (type 0)"), "{}", printed);
+        assert!(printed.contains("(func (;1;) (type 0)"), "{}", printed);
+        assert!(printed.contains("(func (;2;) (type 0)"), "{}", printed);
+        assert!(printed.contains("call 1"), "{}", printed);
+        let mut reparsed = parse(&printed).unwrap();
+        assert_eq!(reparsed.names.funcs.names.len(), 1);
+        reparsed.names = module.names.clone();
+        assert_eq!(reparsed.to_binary(), module.to_binary());
+    }
+
+    #[test]
+    fn shadowed_labels() {
+        let text = "(func
+            (block $l (block $l (br $l) (br 1)) (br $l))
+            (block $l (if $m (i32.const 0) (then (br $l) (br $m)))))";
+        roundtrip_text(text);
+        let printed = print(&parse(text).unwrap(), Style::Flat);
+        assert!(printed.contains("br $l\n        br 1\n"), "{}", printed);
+        assert!(printed.contains("br $l\n        br $m\n"), "{}", printed);
+    }
+
+    #[test]
+    fn examples() {
+        for entry in fs::read_dir("wasm-examples").unwrap() {
+            let path = entry.unwrap().path();
+            roundtrip(&Module::from_binary(fs::read(&path).unwrap()));
+        }
+    }
+
+    #[test]
+    fn deep_code() {
+        let blocks = format!(
+            "(func {}{})",
+            "(block ".repeat(MAX_NESTING),
+            ")".repeat(MAX_NESTING)
+        );
+        roundtrip_text(&blocks);
+
+        // Long chains of operands are only folded up to some depth.
+        let chain = format!(
+            "(func (result i32) i32.const 0 {})",
+            "i32.const 1 i32.add ".repeat(5000)
+        );
+        roundtrip_text(&chain);
+        let printed = print(&parse(&chain).unwrap(), Style::Folded);
+        assert!(printed.lines().all(|line| line.len() < 100));
+    }
+}
@@ -0,0 +1,172 @@
+//! Formatting of identifiers, numeric literals and strings.
+//!
+//! Everything written here reads back into the same value through the
+//! [text parser](crate::parse::text).
+
+use crate::parse::text::lexer::is_idchar;
+
+/// Checks whether `name` can be written as a symbolic identifier (`$name`).
+pub(crate) fn is_id(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(is_idchar)
+}
+
+/// Formats a NaN with its sign and payload. The canonical payload is left out.
+fn nan(negative: bool, payload: u64, canonical: u64) -> String {
+    let sign = if negative { "-" } else { "" };
+    if payload == canonical {
+        format!("{}nan", sign)
+    } else {
+        format!("{}nan:0x{:x}", sign, payload)
+    }
+}
+
+/// Formats a 32 bit float so that reading it back gives the exact same bits.
+///
+/// Finite values use the shortest decimal representation that rounds to the same float.
+pub(crate) fn f32(value: f32) -> String {
+    if value.is_nan() {
+        let bits = value.to_bits();
+        nan(bits >> 31 != 0, u64::from(bits & 0x7F_FFFF), 1 << 22)
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// Formats a 64 bit float so that reading it back gives the exact same bits.
+///
+/// Finite values use the shortest decimal representation that rounds to the same float.
+pub(crate) fn f64(value: f64) -> String {
+    if value.is_nan() {
+        let bits = value.to_bits();
+        nan(bits >> 63 != 0, bits & 0xF_FFFF_FFFF_FFFF, 1 << 51)
+    } else {
+        format!("{:?}", value)
+    }
+}
+
+/// Escape sequence of the characters that can't appear as they are within a string.
+fn escape(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\t' => "\\t",
+        '\n' => "\\n",
+        '\r' => "\\r",
+        '"' => "\\\"",
+        '\\' => "\\\\",
+        _ => return None,
+    })
+}
+
+/// Formats raw bytes (e.g. the contents of a data segment) as a string literal. Anything outside
+/// of printable ASCII is written as a `\hh` escape.
+pub(crate) fn string(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() + 2);
+    out.push('"');
+    for &byte in bytes {
+        match escape(byte as char) {
+            Some(escaped) => out.push_str(escaped),
+            None if (0x20..0x7F).contains(&byte) => out.push(byte as char),
+            None => out.push_str(&format!("\\{:02x}", byte)),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Formats a name as a string literal. Unlike [string], printable unicode characters are kept as
+/// they are.
+pub(crate) fn name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 2);
+    out.push('"');
+    for c in name.chars() {
+        match escape(c) {
+            Some(escaped) => out.push_str(escaped),
+            None if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            None => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::text::values::{parse_f32, parse_f64, parse_string};
+
+    #[test]
+    fn identifiers() {
+        assert!(is_id("add"));
+        assert!(is_id("a.b/c!"));
+        assert!(!is_id(""));
+        assert!(!is_id("with space"));
+        assert!(!is_id("é"));
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(f32(1.5), "1.5");
+        assert_eq!(f32(-0.0), "-0.0");
+        assert_eq!(f32(f32::NEG_INFINITY), "-inf");
+        assert_eq!(f32(f32::from_bits(0x7FC0_0000)), "nan");
+        assert_eq!(f32(f32::from_bits(0xFFC0_0000)), "-nan");
+        assert_eq!(f32(f32::from_bits(0x7F80_0001)), "nan:0x1");
+        assert_eq!(f64(f64::from_bits(0x7FF0_0000_0000_0123)), "nan:0x123");
+        assert_eq!(f64(f64::from_bits(0xFFF8_0000_0000_0000)), "-nan");
+
+        let bits32 = [
+            0,
+            1,
+            0x0080_0000,
+            0x3DCC_CCCD,
+            0x7F7F_FFFF,
+            0x7F80_0000,
+            0x7FA0_0000,
+            0xFFFF_FFFF,
+            0x8000_0001,
+        ];
+        for bits in bits32 {
+            assert_eq!(
+                parse_f32(&f32(f32::from_bits(bits))),
+                Some(bits),
+                "{:#x}",
+                bits
+            );
+        }
+        let bits64 = [
+            0,
+            1,
+            0x0010_0000_0000_0000,
+            0x3FB9_9999_9999_999A,
+            0x7FEF_FFFF_FFFF_FFFF,
+            0x7FF0_0000_0000_0000,
+            0x7FF0_0000_0000_0001,
+            0xFFFF_FFFF_FFFF_FFFF,
+        ];
+        for bits in bits64 {
+            assert_eq!(
+                parse_f64(&f64(f64::from_bits(bits))),
+                Some(bits),
+                "{:#x}",
+                bits
+            );
+        }
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(string(b"a\"b\\\n\x00\xFF"), r#""a\"b\\\n\00\ff""#);
+        assert_eq!(name("caf\u{e9}\u{7f}"), "\"caf\u{e9}\\u{7f}\"");
+
+        let bytes: Vec<u8> = (0..=255).collect();
+        let printed = string(&bytes);
+        assert_eq!(
+            parse_string(&printed[1..printed.len() - 1]),
+            Some(bytes.clone())
+        );
+        let printed = name("a\u{0}\u{1F600}\"");
+        assert_eq!(
+            parse_string(&printed[1..printed.len() - 1]),
+            Some("a\u{0}\u{1F600}\"".as_bytes().to_vec())
+        );
+    }
+}
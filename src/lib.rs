@@ -23,5 +23,8 @@
 #![feature(assert_matches)]
 #![feature(const_generics)]
 
+pub mod encode;
 pub mod parse;
+pub mod runtime;
 pub mod wasm;
+pub mod wast;
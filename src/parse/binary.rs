@@ -10,12 +10,20 @@ use thiserror::Error;
 mod global;
 mod indices;
 mod instr;
-mod module;
-mod preamble;
-mod sections;
+pub(crate) mod module;
+pub(crate) mod preamble;
+pub(crate) mod sections;
+pub(crate) mod spans;
+mod stream;
 mod types;
 mod values;
 
+pub use instr::MAX_NESTING;
+pub use preamble::{Preamble, Version};
+pub use sections::code::MAX_LOCALS;
+pub use spans::Spans;
+pub use stream::{Event, Progress, StreamingParser};
+
 /// Parses a chunk of data into its individual web assembly component.
 ///
 /// # Examples
@@ -48,7 +56,7 @@ mod values;
 /// ## Implementation
 ///
 /// ```
-/// use wasm_parse::parse::{Consume, Parse, ParseError, ParsingData};
+/// use wasm_parse::parse::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
 /// struct Foo {
 ///     byte1: u8,
 ///     byte2: u8,
@@ -57,14 +65,11 @@ mod values;
 /// impl Parse for Foo {
 ///     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
 ///         if data.len() < 2 {
-///             return Err(ParseError::new(
-///                 data,
-///                 "We need 2 bytes to prase foo".to_string(),
-///             ));
+///             return Err(ParseError::new(data, ParseErrorKind::UnexpectedEof));
 ///         }
 ///         Ok(Foo {
-///             byte1: data.consume(()),
-///             byte2: data.consume(()),
+///             byte1: data.consume(())?,
+///             byte2: data.consume(())?,
 ///         })
 ///     }
 /// }
@@ -82,31 +87,193 @@ pub trait Parse: Sized {
         Ok(())
     }
 }
+/// Parses a web assembly component that borrows from the binary instead of copying it.
+///
+/// Large byte strings such as the contents of data segments and custom sections are kept as
+/// slices of the [WasmBinary], which must then outlive the component.
+///
+/// ```
+/// use wasm_parse::parse::{ParseBorrowed, ParsingData, WasmBinary};
+///
+/// let wasm: WasmBinary = [0x03, b'a', b'b', b'c'].as_ref().into();
+/// let mut data = ParsingData::new(&wasm);
+/// let name = <&str>::parse_borrowed(&mut data).unwrap();
+/// assert_eq!(name, "abc");
+/// assert!(std::ptr::eq(name.as_bytes(), &wasm[1..]));
+/// ```
+pub trait ParseBorrowed<'a>: Sized {
+    fn parse_borrowed(data: &mut ParsingData<'a>) -> Result<Self, ParseError>;
+}
+
 /// Error case struct for [Parse]
 ///
-/// The [ParseError] keeps track of the location in the data stream the error occured as well as
-/// the reason the error occurred.
-#[derive(Debug, Error)]
-#[error("Parsing error at byte {}. Description: {}", location, reason)]
+/// The [ParseError] keeps track of the location in the data stream the error occured, the
+/// [kind](ParseErrorKind) of error and the context (section, function, item...) in which it
+/// occurred.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "Parsing error at byte {}. Description: {}{}",
+    location,
+    display_context(.context),
+    kind
+)]
 pub struct ParseError {
     location: usize,
-    reason: String,
+    kind: ParseErrorKind,
+    context: Vec<ErrorContext>,
+}
+
+/// The reason a [ParseError] occurred.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
+    #[error("Invalid magic number. Expected {:X?}", preamble::MAGIC)]
+    InvalidMagic,
+    #[error("Unsupported version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Unknown section id {0}")]
+    UnknownSection(u8),
+    #[error("Unknown opcode {}", display_opcode(.prefix, .opcode))]
+    UnknownOpcode { prefix: Option<u8>, opcode: u32 },
+    #[error("Integer representation too long or out of range")]
+    LebOverflow,
+    #[error("Invalid UTF-8 encoding")]
+    InvalidUtf8,
+    #[error("Section size mismatch. Expected {expected} bytes, got {actual}")]
+    SectionSizeMismatch { expected: usize, actual: usize },
+    #[error("Section {0} is out of order")]
+    OutOfOrderSection(u8),
+    #[error("Section {0} is duplicated")]
+    DuplicateSection(u8),
+    #[error("Invalid {what} encoding {value:#X}")]
+    InvalidEncoding { what: &'static str, value: u32 },
+    #[error("Invalid block type {0}")]
+    InvalidBlockType(i64),
+    #[error("Expected a zero byte, got {0:#X}")]
+    ZeroByteExpected(u8),
+    #[error("Indices should be unique and increasing. Got {0}")]
+    UnsortedIndices(u32),
+    #[error("Function and code sections have different lengths ({funcs} and {bodies})")]
+    FuncCodeMismatch { funcs: usize, bodies: usize },
+    #[error("Data count {count} does not match the number of data segments {datas}")]
+    DataCountMismatch { count: u32, datas: usize },
+    #[error("Data count section required by the instructions referring to data segments")]
+    DataCountRequired,
+    #[error("Function declares {0} locals")]
+    TooManyLocals(u64),
+    #[error("Blocks are nested too deeply")]
+    NestingTooDeep,
+}
+
+/// Where a [ParseError] occurred, from the innermost to the outermost context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorContext {
+    /// Id of the section being parsed.
+    Section(u8),
+    /// Index of the element within the vector being parsed.
+    Item(usize),
+    /// Index of the function (in the function index space) whose body was being parsed.
+    Func(u32),
+    /// Free form description of what was being parsed.
+    Note(String),
+}
+
+fn display_context(context: &[ErrorContext]) -> String {
+    context
+        .iter()
+        .rev()
+        .map(|ctx| match ctx {
+            ErrorContext::Section(id) => format!("In section {} --- ", id),
+            ErrorContext::Item(idx) => format!("In item {} --- ", idx),
+            ErrorContext::Func(idx) => format!("In function {} --- ", idx),
+            ErrorContext::Note(message) => format!("{} --- ", message),
+        })
+        .collect()
+}
+
+fn display_opcode(prefix: &Option<u8>, opcode: &u32) -> String {
+    match prefix {
+        Some(prefix) => format!("{:#X} {}", prefix, opcode),
+        None => format!("{:#X}", opcode),
+    }
 }
 
 impl ParseError {
     /// Constructs a ParseError.
-    pub fn new(data: &ParsingData, reason: String) -> ParseError {
+    pub fn new(data: &ParsingData, kind: ParseErrorKind) -> ParseError {
         ParseError {
-            location: data.start,
-            reason,
+            location: data.offset(),
+            kind,
+            context: Vec::new(),
         }
     }
 
-    /// Extends the error message by prepending the given `message` to the error's message.
+    /// Constructs a [ParseErrorKind::UnexpectedEof] error.
+    pub fn eof(data: &ParsingData) -> ParseError {
+        ParseError::new(data, ParseErrorKind::UnexpectedEof)
+    }
+
+    /// Adds a note describing what was being parsed when the error occurred.
     pub fn extend(self, message: &str) -> ParseError {
-        let mut err = self;
-        err.reason = format!("{} --- {}", message, err.reason);
-        err
+        self.with_context(ErrorContext::Note(message.to_string()))
+    }
+
+    /// Adds a context entry. Entries should be added from the innermost to the outermost.
+    pub fn with_context(mut self, context: ErrorContext) -> ParseError {
+        self.context.push(context);
+        self
+    }
+
+    /// Byte offset within the binary where the error occurred.
+    pub fn location(&self) -> usize {
+        self.location
+    }
+
+    /// The reason the error occurred.
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// Context stack of the error, from the innermost to the outermost entry.
+    pub fn context(&self) -> &[ErrorContext] {
+        &self.context
+    }
+
+    /// Id of the section in which the error occurred, if any.
+    pub fn section(&self) -> Option<u8> {
+        self.context.iter().find_map(|ctx| match ctx {
+            ErrorContext::Section(id) => Some(*id),
+            _ => None,
+        })
+    }
+
+    /// Index of the innermost vector element in which the error occurred, if any.
+    pub fn item(&self) -> Option<usize> {
+        self.context.iter().find_map(|ctx| match ctx {
+            ErrorContext::Item(idx) => Some(*idx),
+            _ => None,
+        })
+    }
+
+    /// Index of the function whose body was being parsed when the error occurred, if any.
+    pub fn func(&self) -> Option<u32> {
+        self.context.iter().find_map(|ctx| match ctx {
+            ErrorContext::Func(idx) => Some(*idx),
+            _ => None,
+        })
+    }
+
+    /// Shifts the function index by `imports` so that it refers to the function index space
+    /// rather than to the position of the body within the code section.
+    pub(crate) fn offset_func(mut self, imports: u32) -> ParseError {
+        for ctx in self.context.iter_mut() {
+            if let ErrorContext::Func(idx) = ctx {
+                *idx = idx.saturating_add(imports);
+            }
+        }
+        self
     }
 }
 
@@ -134,7 +301,10 @@ pub struct WasmBinary(Box<[u8]>);
 /// offsets.
 #[derive(Debug, Clone)]
 pub struct ParsingData<'a> {
-    data: &'a WasmBinary,
+    data: &'a [u8],
+    /// Position of `data` within the binary, which differs from 0 for parts of a binary that is
+    /// received in chunks.
+    base: usize,
     start: usize,
     end: usize,
 }
@@ -165,15 +335,32 @@ impl From<&[u8]> for WasmBinary {
     }
 }
 
-impl ParsingData<'_> {
-    pub fn new(data: &WasmBinary) -> ParsingData {
+impl<'a> ParsingData<'a> {
+    pub fn new(data: &'a WasmBinary) -> ParsingData<'a> {
+        ParsingData::at(data, 0)
+    }
+
+    /// Parsing data over a part of a binary starting at position `base`.
+    pub(crate) fn at(data: &'a [u8], base: usize) -> ParsingData<'a> {
         ParsingData {
             data,
+            base,
             start: 0,
             end: data.len(),
         }
     }
 
+    /// Remaining data as a slice of the [WasmBinary] itself, which unlike the slice the parsing
+    /// data dereferences into may outlive it.
+    pub fn bytes(&self) -> &'a [u8] {
+        &self.data[self.start..self.end]
+    }
+
+    /// Position of the remaining data within the [WasmBinary].
+    pub fn offset(&self) -> usize {
+        self.base + self.start
+    }
+
     pub fn seek(&mut self, increment: usize) {
         self.start += increment
     }
@@ -235,16 +422,20 @@ impl Consume<RangeFull> for ParsingData<'_> {
 }
 
 /// Reads or consumes one element.
+///
+/// Fails with [ParseErrorKind::UnexpectedEof] if the data is empty.
 impl Consume<()> for ParsingData<'_> {
-    type Output = u8;
-    fn read(&self, _idx: ()) -> u8 {
-        assert!(self.len() > 0);
-        self.data[self.start]
+    type Output = Result<u8, ParseError>;
+    fn read(&self, _idx: ()) -> Self::Output {
+        if self.is_empty() {
+            return Err(ParseError::eof(self));
+        }
+        Ok(self.data[self.start])
     }
 
-    fn consume(&mut self, idx: ()) -> u8 {
-        let data = self.read(idx);
+    fn consume(&mut self, idx: ()) -> Self::Output {
+        let data = self.read(idx)?;
         self.start += 1;
-        data
+        Ok(data)
     }
 }
@@ -1,8 +1,9 @@
 use super::{Parse, ParseError, ParsingData};
 use crate::wasm::indices::{
-    DataIdx, ElemIdx, FuncIdx, GlobalIdx, LabelIdx, LocalIdx, MemIdx, TableIdx, TypeIdx,
+    DataIdx, ElemIdx, FieldIdx, FuncIdx, GlobalIdx, LabelIdx, LaneIdx, LocalIdx, MemIdx, TableIdx,
+    TagIdx, TypeIdx,
 };
-use crate::wasm::values::U32;
+use crate::wasm::values::{Byte, U32};
 
 impl Parse for FuncIdx {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
@@ -32,6 +33,20 @@ impl Parse for GlobalIdx {
     }
 }
 
+impl Parse for TagIdx {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let idx = U32::parse(data).map_err(|err| err.extend("Can't parse u32"))?;
+        Ok(Self(idx))
+    }
+}
+
+impl Parse for FieldIdx {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let idx = U32::parse(data).map_err(|err| err.extend("Can't parse u32"))?;
+        Ok(Self(idx))
+    }
+}
+
 impl Parse for LabelIdx {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         let idx = U32::parse(data).map_err(|err| err.extend("Can't parse u32"))?;
@@ -66,3 +81,9 @@ impl Parse for TableIdx {
         Ok(Self(idx))
     }
 }
+
+impl Parse for LaneIdx {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        Ok(Self(Byte::parse(data)?))
+    }
+}
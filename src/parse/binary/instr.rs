@@ -1,32 +1,35 @@
 use std::convert::TryInto;
+use std::ops::Range;
 
-use super::{Consume, Parse, ParseError, ParsingData};
+use super::types::abstract_heap_type;
+use super::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
 use crate::wasm::indices::{
-    DataIdx, ElemIdx, FuncIdx, GlobalIdx, LabelIdx, LocalIdx, TableIdx, TypeIdx,
+    DataIdx, ElemIdx, FieldIdx, FuncIdx, GlobalIdx, LabelIdx, LaneIdx, LocalIdx, MemIdx, TableIdx,
+    TagIdx, TypeIdx,
 };
 use crate::wasm::instr::{
-    Block, BlockType, ControlInstr, Expr, FBinop, FRelop, FUnop, FloatType, IBinop, IRelop,
-    ITestop, IUnop, IfElseBlock, Instr, IntType, MemArg, MemoryInstr, NumericInstr, ReferenceInstr,
-    Sign, TableInstr, VariableInstr,
+    AggregateInstr, AtomicInstr, Block, BlockType, Catch, ControlInstr, Expr, FBinop, FRelop,
+    FUnop, FloatType, IBinop, IRelop, ITestop, IUnop, IfElseBlock, Instr, IntType, MemArg,
+    MemoryInstr, NumericInstr, ReferenceInstr, Sign, TableInstr, TryBlock, TryHandler,
+    TryTableBlock, VariableInstr, VectorInstr,
 };
-use crate::wasm::types::{RefType, ValType};
-use crate::wasm::values::{F32, F64, I32, I64, S64, U32};
+use crate::wasm::types::{HeapType, RefType, ValType};
+use crate::wasm::values::{Byte, F32, F64, I32, I64, S64, U32, U64, V128};
 
 // TODO: This
 impl Parse for Instr {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't parse instruction. Buffer too small.".to_string(),
-            ));
-        }
-        let instr = match data.consume(()) {
+        let instr = match data.consume(())? {
             0x00 => Instr::Control(ControlInstr::Unreachable),
             0x01 => Instr::Control(ControlInstr::Nop),
             0x02 => Instr::Control(ControlInstr::Block(Block::parse(data)?)),
             0x03 => Instr::Control(ControlInstr::Loop(Block::parse(data)?)),
             0x04 => Instr::Control(ControlInstr::If(IfElseBlock::parse(data)?)),
+            0x06 => Instr::Control(ControlInstr::Try(TryBlock::parse(data)?)),
+            0x08 => Instr::Control(ControlInstr::Throw(TagIdx::parse(data)?)),
+            0x09 => Instr::Control(ControlInstr::Rethrow(LabelIdx::parse(data)?)),
+            0x0A => Instr::Control(ControlInstr::ThrowRef),
+            0x1F => Instr::Control(ControlInstr::TryTable(TryTableBlock::parse(data)?)),
             0x0C => Instr::Control(ControlInstr::Branch(LabelIdx::parse(data)?)),
             0x0D => Instr::Control(ControlInstr::BranchIf(LabelIdx::parse(data)?)),
             0x0E => {
@@ -41,9 +44,21 @@ impl Parse for Instr {
                 let x = TableIdx::parse(data)?;
                 Instr::Control(ControlInstr::CallIndirect(x, y))
             }
-            0xD0 => Instr::Reference(ReferenceInstr::RefNull(RefType::parse(data)?)),
+            0x12 => Instr::Control(ControlInstr::ReturnCall(FuncIdx::parse(data)?)),
+            0x13 => {
+                let y = TypeIdx::parse(data)?;
+                let x = TableIdx::parse(data)?;
+                Instr::Control(ControlInstr::ReturnCallIndirect(x, y))
+            }
+            0x14 => Instr::Control(ControlInstr::CallRef(TypeIdx::parse(data)?)),
+            0x15 => Instr::Control(ControlInstr::ReturnCallRef(TypeIdx::parse(data)?)),
+            0xD0 => Instr::Reference(ReferenceInstr::RefNull(HeapType::parse(data)?)),
             0xD1 => Instr::Reference(ReferenceInstr::RefIsNull),
             0xD2 => Instr::Reference(ReferenceInstr::RefFunc(FuncIdx::parse(data)?)),
+            0xD3 => Instr::Reference(ReferenceInstr::RefEq),
+            0xD4 => Instr::Reference(ReferenceInstr::RefAsNonNull),
+            0xD5 => Instr::Control(ControlInstr::BrOnNull(LabelIdx::parse(data)?)),
+            0xD6 => Instr::Control(ControlInstr::BrOnNonNull(LabelIdx::parse(data)?)),
             0x1A => Instr::Dropp,
             0x1B => Instr::Select(Vec::new()),
             0x1C => Instr::Select(Vec::parse(data)?),
@@ -69,40 +84,20 @@ impl Parse for Instr {
 
                     // Mem instructions:
                     8 => {
-                        let x = DataIdx::parse(data)?;
-                        if data.consume(()) != 0x00 {
-                            return Err(ParseError::new(
-                                data,
-                                "Invalid memory size instruction. Second byte should be 0x00"
-                                    .to_string(),
-                            ));
-                        }
-                        Instr::Memory(MemoryInstr::MemoryInit(x))
+                        let y = DataIdx::parse(data)?;
+                        let x = MemIdx::parse(data)?;
+                        Instr::Memory(MemoryInstr::MemoryInit(x, y))
                     }
                     9 => {
                         let x = DataIdx::parse(data)?;
                         Instr::Memory(MemoryInstr::DataDrop(x))
                     }
                     10 => {
-                        if *data.consume(2) != [0x00, 0x00] {
-                            return Err(ParseError::new(
-                                data,
-                                "Invalid memory copy instruction. Proceeding 2 bytes should be 0x00"
-                                    .to_string(),
-                            ));
-                        }
-                        Instr::Memory(MemoryInstr::MemoryCopy)
-                    }
-                    11 => {
-                        if data.consume(()) != 0x00 {
-                            return Err(ParseError::new(
-                                data,
-                                "Invalid memory fill instruction. Second byte should be 0x00"
-                                    .to_string(),
-                            ));
-                        }
-                        Instr::Memory(MemoryInstr::MemoryFill)
+                        let x = MemIdx::parse(data)?;
+                        let y = MemIdx::parse(data)?;
+                        Instr::Memory(MemoryInstr::MemoryCopy(x, y))
                     }
+                    11 => Instr::Memory(MemoryInstr::MemoryFill(MemIdx::parse(data)?)),
 
                     // Table instructions
                     12 => {
@@ -123,11 +118,146 @@ impl Parse for Instr {
                     x => {
                         return Err(ParseError::new(
                             data,
-                            format!("Unknown 0xFC instruction {}", x),
+                            ParseErrorKind::UnknownOpcode {
+                                prefix: Some(0xFC),
+                                opcode: x,
+                            },
+                        ))
+                    }
+                }
+            }
+            0xFB => {
+                let selector = *U32::parse(data)?;
+                match selector {
+                    // Struct instructions
+                    0 => Instr::Aggregate(AggregateInstr::StructNew(TypeIdx::parse(data)?)),
+                    1 => Instr::Aggregate(AggregateInstr::StructNewDefault(TypeIdx::parse(data)?)),
+                    2..=4 => {
+                        let sign = match selector {
+                            2 => None,
+                            3 => Some(Sign::Signed),
+                            _ => Some(Sign::Unsigned),
+                        };
+                        let x = TypeIdx::parse(data)?;
+                        let y = FieldIdx::parse(data)?;
+                        Instr::Aggregate(AggregateInstr::StructGet(sign, x, y))
+                    }
+                    5 => {
+                        let x = TypeIdx::parse(data)?;
+                        let y = FieldIdx::parse(data)?;
+                        Instr::Aggregate(AggregateInstr::StructSet(x, y))
+                    }
+
+                    // Array instructions
+                    6 => Instr::Aggregate(AggregateInstr::ArrayNew(TypeIdx::parse(data)?)),
+                    7 => Instr::Aggregate(AggregateInstr::ArrayNewDefault(TypeIdx::parse(data)?)),
+                    8 => {
+                        let x = TypeIdx::parse(data)?;
+                        let n = U32::parse(data)?;
+                        Instr::Aggregate(AggregateInstr::ArrayNewFixed(x, n))
+                    }
+                    9 => {
+                        let x = TypeIdx::parse(data)?;
+                        let y = DataIdx::parse(data)?;
+                        Instr::Aggregate(AggregateInstr::ArrayNewData(x, y))
+                    }
+                    10 => {
+                        let x = TypeIdx::parse(data)?;
+                        let y = ElemIdx::parse(data)?;
+                        Instr::Aggregate(AggregateInstr::ArrayNewElem(x, y))
+                    }
+                    11 => Instr::Aggregate(AggregateInstr::ArrayGet(None, TypeIdx::parse(data)?)),
+                    12 => Instr::Aggregate(AggregateInstr::ArrayGet(
+                        Some(Sign::Signed),
+                        TypeIdx::parse(data)?,
+                    )),
+                    13 => Instr::Aggregate(AggregateInstr::ArrayGet(
+                        Some(Sign::Unsigned),
+                        TypeIdx::parse(data)?,
+                    )),
+                    14 => Instr::Aggregate(AggregateInstr::ArraySet(TypeIdx::parse(data)?)),
+                    15 => Instr::Aggregate(AggregateInstr::ArrayLen),
+                    16 => Instr::Aggregate(AggregateInstr::ArrayFill(TypeIdx::parse(data)?)),
+                    17 => {
+                        let x = TypeIdx::parse(data)?;
+                        let y = TypeIdx::parse(data)?;
+                        Instr::Aggregate(AggregateInstr::ArrayCopy(x, y))
+                    }
+                    18 => {
+                        let x = TypeIdx::parse(data)?;
+                        let y = DataIdx::parse(data)?;
+                        Instr::Aggregate(AggregateInstr::ArrayInitData(x, y))
+                    }
+                    19 => {
+                        let x = TypeIdx::parse(data)?;
+                        let y = ElemIdx::parse(data)?;
+                        Instr::Aggregate(AggregateInstr::ArrayInitElem(x, y))
+                    }
+
+                    // Casts
+                    20 | 21 => {
+                        let heap = HeapType::parse(data)?;
+                        let tpe = RefType {
+                            nullable: selector == 21,
+                            heap,
+                        };
+                        Instr::Reference(ReferenceInstr::RefTest(tpe))
+                    }
+                    22 | 23 => {
+                        let heap = HeapType::parse(data)?;
+                        let tpe = RefType {
+                            nullable: selector == 23,
+                            heap,
+                        };
+                        Instr::Reference(ReferenceInstr::RefCast(tpe))
+                    }
+                    24 | 25 => {
+                        let flags = data.consume(())?;
+                        if flags > 0b11 {
+                            return Err(ParseError::new(
+                                data,
+                                ParseErrorKind::InvalidEncoding {
+                                    what: "cast flags",
+                                    value: flags as u32,
+                                },
+                            ));
+                        }
+                        let l = LabelIdx::parse(data)?;
+                        let rt1 = RefType {
+                            nullable: flags & 0b01 != 0,
+                            heap: HeapType::parse(data)?,
+                        };
+                        let rt2 = RefType {
+                            nullable: flags & 0b10 != 0,
+                            heap: HeapType::parse(data)?,
+                        };
+                        if selector == 24 {
+                            Instr::Control(ControlInstr::BrOnCast(l, rt1, rt2))
+                        } else {
+                            Instr::Control(ControlInstr::BrOnCastFail(l, rt1, rt2))
+                        }
+                    }
+
+                    // Extern conversion and i31
+                    26 => Instr::Reference(ReferenceInstr::AnyConvertExtern),
+                    27 => Instr::Reference(ReferenceInstr::ExternConvertAny),
+                    28 => Instr::Reference(ReferenceInstr::RefI31),
+                    29 => Instr::Reference(ReferenceInstr::I31Get(Sign::Signed)),
+                    30 => Instr::Reference(ReferenceInstr::I31Get(Sign::Unsigned)),
+
+                    x => {
+                        return Err(ParseError::new(
+                            data,
+                            ParseErrorKind::UnknownOpcode {
+                                prefix: Some(0xFB),
+                                opcode: x,
+                            },
                         ))
                     }
                 }
             }
+            0xFD => Instr::Vector(VectorInstr::parse(data)?),
+            0xFE => Instr::Atomic(AtomicInstr::parse(data)?),
             0x28 => Instr::Memory(MemoryInstr::ILoad(IntType::I32, MemArg::parse(data)?)),
             0x29 => Instr::Memory(MemoryInstr::ILoad(IntType::I64, MemArg::parse(data)?)),
             0x2A => Instr::Memory(MemoryInstr::FLoad(FloatType::F32, MemArg::parse(data)?)),
@@ -183,24 +313,8 @@ impl Parse for Instr {
             0x3C => Instr::Memory(MemoryInstr::IStore8(IntType::I64, MemArg::parse(data)?)),
             0x3D => Instr::Memory(MemoryInstr::IStore16(IntType::I64, MemArg::parse(data)?)),
             0x3E => Instr::Memory(MemoryInstr::I64Store32(MemArg::parse(data)?)),
-            0x3F => {
-                if data.consume(()) != 0x00 {
-                    return Err(ParseError::new(
-                        data,
-                        "Invalid memory size instruction. Second byte should be 0x00".to_string(),
-                    ));
-                }
-                Instr::Memory(MemoryInstr::MemorySize)
-            }
-            0x40 => {
-                if data.consume(()) != 0x00 {
-                    return Err(ParseError::new(
-                        data,
-                        "Invalid memory grow instruction. Second byte should be 0x00".to_string(),
-                    ));
-                }
-                Instr::Memory(MemoryInstr::MemoryGrow)
-            }
+            0x3F => Instr::Memory(MemoryInstr::MemorySize(MemIdx::parse(data)?)),
+            0x40 => Instr::Memory(MemoryInstr::MemoryGrow(MemIdx::parse(data)?)),
             0x41 => Instr::Numeric(NumericInstr::I32Const(I32::parse(data)?)),
             0x42 => Instr::Numeric(NumericInstr::I64Const(I64::parse(data)?)),
             0x43 => Instr::Numeric(NumericInstr::F32Const(F32::parse(data)?)),
@@ -233,12 +347,12 @@ impl Parse for Instr {
             0x5E => Instr::Numeric(NumericInstr::F32Relop(FRelop::Gt)),
             0x5F => Instr::Numeric(NumericInstr::F32Relop(FRelop::Le)),
             0x60 => Instr::Numeric(NumericInstr::F32Relop(FRelop::Ge)),
-            0x61 => Instr::Numeric(NumericInstr::F32Relop(FRelop::Equ)),
-            0x62 => Instr::Numeric(NumericInstr::F32Relop(FRelop::Ne)),
-            0x63 => Instr::Numeric(NumericInstr::F32Relop(FRelop::Lt)),
-            0x64 => Instr::Numeric(NumericInstr::F32Relop(FRelop::Gt)),
-            0x65 => Instr::Numeric(NumericInstr::F32Relop(FRelop::Le)),
-            0x66 => Instr::Numeric(NumericInstr::F32Relop(FRelop::Ge)),
+            0x61 => Instr::Numeric(NumericInstr::F64Relop(FRelop::Equ)),
+            0x62 => Instr::Numeric(NumericInstr::F64Relop(FRelop::Ne)),
+            0x63 => Instr::Numeric(NumericInstr::F64Relop(FRelop::Lt)),
+            0x64 => Instr::Numeric(NumericInstr::F64Relop(FRelop::Gt)),
+            0x65 => Instr::Numeric(NumericInstr::F64Relop(FRelop::Le)),
+            0x66 => Instr::Numeric(NumericInstr::F64Relop(FRelop::Ge)),
 
             0x67 => Instr::Numeric(NumericInstr::I32Unary(IUnop::Clz)),
             0x68 => Instr::Numeric(NumericInstr::I32Unary(IUnop::Ctz)),
@@ -343,7 +457,10 @@ impl Parse for Instr {
             x => {
                 return Err(ParseError::new(
                     data,
-                    format!("Unknown instruction number {:X}", x),
+                    ParseErrorKind::UnknownOpcode {
+                        prefix: None,
+                        opcode: x.into(),
+                    },
                 ));
             }
         };
@@ -352,34 +469,614 @@ impl Parse for Instr {
     }
 }
 
-impl Parse for Expr {
+/// Parses the instruction after the `0xFD` prefix.
+impl Parse for VectorInstr {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let mut instr = Vec::new();
-        while data.read(()) != 0x0B {
-            instr.push(Instr::parse(data)?);
+        use VectorInstr::*;
+        let instr = match *U32::parse(data)? {
+            0x00 => V128Load(MemArg::parse(data)?),
+            0x01 => V128Load8x8S(MemArg::parse(data)?),
+            0x02 => V128Load8x8U(MemArg::parse(data)?),
+            0x03 => V128Load16x4S(MemArg::parse(data)?),
+            0x04 => V128Load16x4U(MemArg::parse(data)?),
+            0x05 => V128Load32x2S(MemArg::parse(data)?),
+            0x06 => V128Load32x2U(MemArg::parse(data)?),
+            0x07 => V128Load8Splat(MemArg::parse(data)?),
+            0x08 => V128Load16Splat(MemArg::parse(data)?),
+            0x09 => V128Load32Splat(MemArg::parse(data)?),
+            0x0A => V128Load64Splat(MemArg::parse(data)?),
+            0x0B => V128Store(MemArg::parse(data)?),
+            0x0C => V128Const(V128::parse(data)?),
+            0x0D => {
+                let mut lanes = [LaneIdx(Byte(0)); 16];
+                for lane in lanes.iter_mut() {
+                    *lane = LaneIdx::parse(data)?;
+                }
+                I8x16Shuffle(lanes)
+            }
+            0x0E => I8x16Swizzle,
+            0x0F => I8x16Splat,
+            0x10 => I16x8Splat,
+            0x11 => I32x4Splat,
+            0x12 => I64x2Splat,
+            0x13 => F32x4Splat,
+            0x14 => F64x2Splat,
+            0x15 => I8x16ExtractLaneS(LaneIdx::parse(data)?),
+            0x16 => I8x16ExtractLaneU(LaneIdx::parse(data)?),
+            0x17 => I8x16ReplaceLane(LaneIdx::parse(data)?),
+            0x18 => I16x8ExtractLaneS(LaneIdx::parse(data)?),
+            0x19 => I16x8ExtractLaneU(LaneIdx::parse(data)?),
+            0x1A => I16x8ReplaceLane(LaneIdx::parse(data)?),
+            0x1B => I32x4ExtractLane(LaneIdx::parse(data)?),
+            0x1C => I32x4ReplaceLane(LaneIdx::parse(data)?),
+            0x1D => I64x2ExtractLane(LaneIdx::parse(data)?),
+            0x1E => I64x2ReplaceLane(LaneIdx::parse(data)?),
+            0x1F => F32x4ExtractLane(LaneIdx::parse(data)?),
+            0x20 => F32x4ReplaceLane(LaneIdx::parse(data)?),
+            0x21 => F64x2ExtractLane(LaneIdx::parse(data)?),
+            0x22 => F64x2ReplaceLane(LaneIdx::parse(data)?),
+            0x23 => I8x16Eq,
+            0x24 => I8x16Ne,
+            0x25 => I8x16LtS,
+            0x26 => I8x16LtU,
+            0x27 => I8x16GtS,
+            0x28 => I8x16GtU,
+            0x29 => I8x16LeS,
+            0x2A => I8x16LeU,
+            0x2B => I8x16GeS,
+            0x2C => I8x16GeU,
+            0x2D => I16x8Eq,
+            0x2E => I16x8Ne,
+            0x2F => I16x8LtS,
+            0x30 => I16x8LtU,
+            0x31 => I16x8GtS,
+            0x32 => I16x8GtU,
+            0x33 => I16x8LeS,
+            0x34 => I16x8LeU,
+            0x35 => I16x8GeS,
+            0x36 => I16x8GeU,
+            0x37 => I32x4Eq,
+            0x38 => I32x4Ne,
+            0x39 => I32x4LtS,
+            0x3A => I32x4LtU,
+            0x3B => I32x4GtS,
+            0x3C => I32x4GtU,
+            0x3D => I32x4LeS,
+            0x3E => I32x4LeU,
+            0x3F => I32x4GeS,
+            0x40 => I32x4GeU,
+            0x41 => F32x4Eq,
+            0x42 => F32x4Ne,
+            0x43 => F32x4Lt,
+            0x44 => F32x4Gt,
+            0x45 => F32x4Le,
+            0x46 => F32x4Ge,
+            0x47 => F64x2Eq,
+            0x48 => F64x2Ne,
+            0x49 => F64x2Lt,
+            0x4A => F64x2Gt,
+            0x4B => F64x2Le,
+            0x4C => F64x2Ge,
+            0x4D => V128Not,
+            0x4E => V128And,
+            0x4F => V128Andnot,
+            0x50 => V128Or,
+            0x51 => V128Xor,
+            0x52 => V128Bitselect,
+            0x53 => V128AnyTrue,
+            0x54 => {
+                let memarg = MemArg::parse(data)?;
+                V128Load8Lane(memarg, LaneIdx::parse(data)?)
+            }
+            0x55 => {
+                let memarg = MemArg::parse(data)?;
+                V128Load16Lane(memarg, LaneIdx::parse(data)?)
+            }
+            0x56 => {
+                let memarg = MemArg::parse(data)?;
+                V128Load32Lane(memarg, LaneIdx::parse(data)?)
+            }
+            0x57 => {
+                let memarg = MemArg::parse(data)?;
+                V128Load64Lane(memarg, LaneIdx::parse(data)?)
+            }
+            0x58 => {
+                let memarg = MemArg::parse(data)?;
+                V128Store8Lane(memarg, LaneIdx::parse(data)?)
+            }
+            0x59 => {
+                let memarg = MemArg::parse(data)?;
+                V128Store16Lane(memarg, LaneIdx::parse(data)?)
+            }
+            0x5A => {
+                let memarg = MemArg::parse(data)?;
+                V128Store32Lane(memarg, LaneIdx::parse(data)?)
+            }
+            0x5B => {
+                let memarg = MemArg::parse(data)?;
+                V128Store64Lane(memarg, LaneIdx::parse(data)?)
+            }
+            0x5C => V128Load32Zero(MemArg::parse(data)?),
+            0x5D => V128Load64Zero(MemArg::parse(data)?),
+            0x5E => F32x4DemoteF64x2Zero,
+            0x5F => F64x2PromoteLowF32x4,
+            0x60 => I8x16Abs,
+            0x61 => I8x16Neg,
+            0x62 => I8x16Popcnt,
+            0x63 => I8x16AllTrue,
+            0x64 => I8x16Bitmask,
+            0x65 => I8x16NarrowI16x8S,
+            0x66 => I8x16NarrowI16x8U,
+            0x67 => F32x4Ceil,
+            0x68 => F32x4Floor,
+            0x69 => F32x4Trunc,
+            0x6A => F32x4Nearest,
+            0x6B => I8x16Shl,
+            0x6C => I8x16ShrS,
+            0x6D => I8x16ShrU,
+            0x6E => I8x16Add,
+            0x6F => I8x16AddSatS,
+            0x70 => I8x16AddSatU,
+            0x71 => I8x16Sub,
+            0x72 => I8x16SubSatS,
+            0x73 => I8x16SubSatU,
+            0x74 => F64x2Ceil,
+            0x75 => F64x2Floor,
+            0x76 => I8x16MinS,
+            0x77 => I8x16MinU,
+            0x78 => I8x16MaxS,
+            0x79 => I8x16MaxU,
+            0x7A => F64x2Trunc,
+            0x7B => I8x16AvgrU,
+            0x7C => I16x8ExtaddPairwiseI8x16S,
+            0x7D => I16x8ExtaddPairwiseI8x16U,
+            0x7E => I32x4ExtaddPairwiseI16x8S,
+            0x7F => I32x4ExtaddPairwiseI16x8U,
+            0x80 => I16x8Abs,
+            0x81 => I16x8Neg,
+            0x82 => I16x8Q15mulrSatS,
+            0x83 => I16x8AllTrue,
+            0x84 => I16x8Bitmask,
+            0x85 => I16x8NarrowI32x4S,
+            0x86 => I16x8NarrowI32x4U,
+            0x87 => I16x8ExtendLowI8x16S,
+            0x88 => I16x8ExtendHighI8x16S,
+            0x89 => I16x8ExtendLowI8x16U,
+            0x8A => I16x8ExtendHighI8x16U,
+            0x8B => I16x8Shl,
+            0x8C => I16x8ShrS,
+            0x8D => I16x8ShrU,
+            0x8E => I16x8Add,
+            0x8F => I16x8AddSatS,
+            0x90 => I16x8AddSatU,
+            0x91 => I16x8Sub,
+            0x92 => I16x8SubSatS,
+            0x93 => I16x8SubSatU,
+            0x94 => F64x2Nearest,
+            0x95 => I16x8Mul,
+            0x96 => I16x8MinS,
+            0x97 => I16x8MinU,
+            0x98 => I16x8MaxS,
+            0x99 => I16x8MaxU,
+            0x9B => I16x8AvgrU,
+            0x9C => I16x8ExtmulLowI8x16S,
+            0x9D => I16x8ExtmulHighI8x16S,
+            0x9E => I16x8ExtmulLowI8x16U,
+            0x9F => I16x8ExtmulHighI8x16U,
+            0xA0 => I32x4Abs,
+            0xA1 => I32x4Neg,
+            0xA3 => I32x4AllTrue,
+            0xA4 => I32x4Bitmask,
+            0xA7 => I32x4ExtendLowI16x8S,
+            0xA8 => I32x4ExtendHighI16x8S,
+            0xA9 => I32x4ExtendLowI16x8U,
+            0xAA => I32x4ExtendHighI16x8U,
+            0xAB => I32x4Shl,
+            0xAC => I32x4ShrS,
+            0xAD => I32x4ShrU,
+            0xAE => I32x4Add,
+            0xB1 => I32x4Sub,
+            0xB5 => I32x4Mul,
+            0xB6 => I32x4MinS,
+            0xB7 => I32x4MinU,
+            0xB8 => I32x4MaxS,
+            0xB9 => I32x4MaxU,
+            0xBA => I32x4DotI16x8S,
+            0xBC => I32x4ExtmulLowI16x8S,
+            0xBD => I32x4ExtmulHighI16x8S,
+            0xBE => I32x4ExtmulLowI16x8U,
+            0xBF => I32x4ExtmulHighI16x8U,
+            0xC0 => I64x2Abs,
+            0xC1 => I64x2Neg,
+            0xC3 => I64x2AllTrue,
+            0xC4 => I64x2Bitmask,
+            0xC7 => I64x2ExtendLowI32x4S,
+            0xC8 => I64x2ExtendHighI32x4S,
+            0xC9 => I64x2ExtendLowI32x4U,
+            0xCA => I64x2ExtendHighI32x4U,
+            0xCB => I64x2Shl,
+            0xCC => I64x2ShrS,
+            0xCD => I64x2ShrU,
+            0xCE => I64x2Add,
+            0xD1 => I64x2Sub,
+            0xD5 => I64x2Mul,
+            0xD6 => I64x2Eq,
+            0xD7 => I64x2Ne,
+            0xD8 => I64x2LtS,
+            0xD9 => I64x2GtS,
+            0xDA => I64x2LeS,
+            0xDB => I64x2GeS,
+            0xDC => I64x2ExtmulLowI32x4S,
+            0xDD => I64x2ExtmulHighI32x4S,
+            0xDE => I64x2ExtmulLowI32x4U,
+            0xDF => I64x2ExtmulHighI32x4U,
+            0xE0 => F32x4Abs,
+            0xE1 => F32x4Neg,
+            0xE3 => F32x4Sqrt,
+            0xE4 => F32x4Add,
+            0xE5 => F32x4Sub,
+            0xE6 => F32x4Mul,
+            0xE7 => F32x4Div,
+            0xE8 => F32x4Min,
+            0xE9 => F32x4Max,
+            0xEA => F32x4Pmin,
+            0xEB => F32x4Pmax,
+            0xEC => F64x2Abs,
+            0xED => F64x2Neg,
+            0xEF => F64x2Sqrt,
+            0xF0 => F64x2Add,
+            0xF1 => F64x2Sub,
+            0xF2 => F64x2Mul,
+            0xF3 => F64x2Div,
+            0xF4 => F64x2Min,
+            0xF5 => F64x2Max,
+            0xF6 => F64x2Pmin,
+            0xF7 => F64x2Pmax,
+            0xF8 => I32x4TruncSatF32x4S,
+            0xF9 => I32x4TruncSatF32x4U,
+            0xFA => F32x4ConvertI32x4S,
+            0xFB => F32x4ConvertI32x4U,
+            0xFC => I32x4TruncSatF64x2SZero,
+            0xFD => I32x4TruncSatF64x2UZero,
+            0xFE => F64x2ConvertLowI32x4S,
+            0xFF => F64x2ConvertLowI32x4U,
+            x => {
+                return Err(ParseError::new(
+                    data,
+                    ParseErrorKind::UnknownOpcode {
+                        prefix: Some(0xFD),
+                        opcode: x,
+                    },
+                ))
+            }
+        };
+        Ok(instr)
+    }
+}
+
+/// Parses the instruction after the `0xFE` prefix.
+impl Parse for AtomicInstr {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        use AtomicInstr::*;
+        let instr = match *U32::parse(data)? {
+            0x00 => MemoryAtomicNotify(MemArg::parse(data)?),
+            0x01 => MemoryAtomicWait32(MemArg::parse(data)?),
+            0x02 => MemoryAtomicWait64(MemArg::parse(data)?),
+            0x03 => {
+                let zero = data.consume(())?;
+                if zero != 0x00 {
+                    return Err(ParseError::new(
+                        data,
+                        ParseErrorKind::ZeroByteExpected(zero),
+                    ));
+                }
+                AtomicFence
+            }
+            0x10 => I32AtomicLoad(MemArg::parse(data)?),
+            0x11 => I64AtomicLoad(MemArg::parse(data)?),
+            0x12 => I32AtomicLoad8U(MemArg::parse(data)?),
+            0x13 => I32AtomicLoad16U(MemArg::parse(data)?),
+            0x14 => I64AtomicLoad8U(MemArg::parse(data)?),
+            0x15 => I64AtomicLoad16U(MemArg::parse(data)?),
+            0x16 => I64AtomicLoad32U(MemArg::parse(data)?),
+            0x17 => I32AtomicStore(MemArg::parse(data)?),
+            0x18 => I64AtomicStore(MemArg::parse(data)?),
+            0x19 => I32AtomicStore8(MemArg::parse(data)?),
+            0x1A => I32AtomicStore16(MemArg::parse(data)?),
+            0x1B => I64AtomicStore8(MemArg::parse(data)?),
+            0x1C => I64AtomicStore16(MemArg::parse(data)?),
+            0x1D => I64AtomicStore32(MemArg::parse(data)?),
+            0x1E => I32AtomicRmwAdd(MemArg::parse(data)?),
+            0x1F => I64AtomicRmwAdd(MemArg::parse(data)?),
+            0x20 => I32AtomicRmw8AddU(MemArg::parse(data)?),
+            0x21 => I32AtomicRmw16AddU(MemArg::parse(data)?),
+            0x22 => I64AtomicRmw8AddU(MemArg::parse(data)?),
+            0x23 => I64AtomicRmw16AddU(MemArg::parse(data)?),
+            0x24 => I64AtomicRmw32AddU(MemArg::parse(data)?),
+            0x25 => I32AtomicRmwSub(MemArg::parse(data)?),
+            0x26 => I64AtomicRmwSub(MemArg::parse(data)?),
+            0x27 => I32AtomicRmw8SubU(MemArg::parse(data)?),
+            0x28 => I32AtomicRmw16SubU(MemArg::parse(data)?),
+            0x29 => I64AtomicRmw8SubU(MemArg::parse(data)?),
+            0x2A => I64AtomicRmw16SubU(MemArg::parse(data)?),
+            0x2B => I64AtomicRmw32SubU(MemArg::parse(data)?),
+            0x2C => I32AtomicRmwAnd(MemArg::parse(data)?),
+            0x2D => I64AtomicRmwAnd(MemArg::parse(data)?),
+            0x2E => I32AtomicRmw8AndU(MemArg::parse(data)?),
+            0x2F => I32AtomicRmw16AndU(MemArg::parse(data)?),
+            0x30 => I64AtomicRmw8AndU(MemArg::parse(data)?),
+            0x31 => I64AtomicRmw16AndU(MemArg::parse(data)?),
+            0x32 => I64AtomicRmw32AndU(MemArg::parse(data)?),
+            0x33 => I32AtomicRmwOr(MemArg::parse(data)?),
+            0x34 => I64AtomicRmwOr(MemArg::parse(data)?),
+            0x35 => I32AtomicRmw8OrU(MemArg::parse(data)?),
+            0x36 => I32AtomicRmw16OrU(MemArg::parse(data)?),
+            0x37 => I64AtomicRmw8OrU(MemArg::parse(data)?),
+            0x38 => I64AtomicRmw16OrU(MemArg::parse(data)?),
+            0x39 => I64AtomicRmw32OrU(MemArg::parse(data)?),
+            0x3A => I32AtomicRmwXor(MemArg::parse(data)?),
+            0x3B => I64AtomicRmwXor(MemArg::parse(data)?),
+            0x3C => I32AtomicRmw8XorU(MemArg::parse(data)?),
+            0x3D => I32AtomicRmw16XorU(MemArg::parse(data)?),
+            0x3E => I64AtomicRmw8XorU(MemArg::parse(data)?),
+            0x3F => I64AtomicRmw16XorU(MemArg::parse(data)?),
+            0x40 => I64AtomicRmw32XorU(MemArg::parse(data)?),
+            0x41 => I32AtomicRmwXchg(MemArg::parse(data)?),
+            0x42 => I64AtomicRmwXchg(MemArg::parse(data)?),
+            0x43 => I32AtomicRmw8XchgU(MemArg::parse(data)?),
+            0x44 => I32AtomicRmw16XchgU(MemArg::parse(data)?),
+            0x45 => I64AtomicRmw8XchgU(MemArg::parse(data)?),
+            0x46 => I64AtomicRmw16XchgU(MemArg::parse(data)?),
+            0x47 => I64AtomicRmw32XchgU(MemArg::parse(data)?),
+            0x48 => I32AtomicRmwCmpxchg(MemArg::parse(data)?),
+            0x49 => I64AtomicRmwCmpxchg(MemArg::parse(data)?),
+            0x4A => I32AtomicRmw8CmpxchgU(MemArg::parse(data)?),
+            0x4B => I32AtomicRmw16CmpxchgU(MemArg::parse(data)?),
+            0x4C => I64AtomicRmw8CmpxchgU(MemArg::parse(data)?),
+            0x4D => I64AtomicRmw16CmpxchgU(MemArg::parse(data)?),
+            0x4E => I64AtomicRmw32CmpxchgU(MemArg::parse(data)?),
+            x => {
+                return Err(ParseError::new(
+                    data,
+                    ParseErrorKind::UnknownOpcode {
+                        prefix: Some(0xFE),
+                        opcode: x,
+                    },
+                ))
+            }
+        };
+        Ok(instr)
+    }
+}
+
+/// Maximum nesting of structured instructions within an expression.
+///
+/// Blocks are parsed iteratively but most consumers of the syntax tree (including `Drop`) recurse
+/// into them, so this bounds their stack usage for hostile inputs.
+pub const MAX_NESTING: usize = 1024;
+
+/// A structured instruction whose `end` hasn't been reached yet.
+enum Open {
+    Block(BlockType),
+    Loop(BlockType),
+    If(BlockType),
+    Else(BlockType, Vec<Instr>),
+    Try(BlockType),
+    /// Within a `catch` clause of the given tag, or the `catch_all` clause if there is none. The
+    /// block holds the body and the previous clauses.
+    Catch(TryBlock, Option<TagIdx>),
+    TryTable(BlockType, Vec<Catch>),
+}
+
+/// Instruction terminating a sequence of instructions.
+#[derive(Debug)]
+enum Terminator {
+    End,
+    Else,
+    Catch(TagIdx),
+    CatchAll,
+    Delegate(LabelIdx),
+}
+
+// Opcodes of the instructions splitting structured instructions into several sequences.
+const ELSE: u8 = 0x05;
+const CATCH: u8 = 0x07;
+const DELEGATE: u8 = 0x18;
+const CATCH_ALL: u8 = 0x19;
+
+/// Reads the terminator with the given opcode along with its immediate.
+fn terminator(data: &mut ParsingData, opcode: u8) -> Result<Terminator, ParseError> {
+    data.consume(())?;
+    Ok(match opcode {
+        ELSE => Terminator::Else,
+        CATCH => Terminator::Catch(TagIdx::parse(data)?),
+        DELEGATE => Terminator::Delegate(LabelIdx::parse(data)?),
+        CATCH_ALL => Terminator::CatchAll,
+        _ => Terminator::End,
+    })
+}
+
+/// Parses a sequence of instructions up to and including its `end`, or any of the `terminators`
+/// (`else`, `catch`, `catch_all` or `delegate`). Returns the instructions and what ended them.
+///
+/// Nested blocks are tracked with an explicit stack rather than recursion so that deeply nested
+/// bodies can't overflow the call stack.
+fn parse_instrs(
+    data: &mut ParsingData,
+    terminators: &[u8],
+) -> Result<(Vec<Instr>, Terminator), ParseError> {
+    parse_spanned_instrs(data, terminators, None)
+}
+
+/// Parses instructions like [parse_instrs], pushing the byte range of each of them to `spans` if
+/// given. See [parse_spanned_expr] for their order.
+fn parse_spanned_instrs(
+    data: &mut ParsingData,
+    terminators: &[u8],
+    mut spans: Option<&mut Vec<Range<usize>>>,
+) -> Result<(Vec<Instr>, Terminator), ParseError> {
+    let mut stack: Vec<(Open, Vec<Instr>)> = Vec::new();
+    let mut instr = Vec::new();
+    // Positions within `spans` of the structured instructions in `stack`, whose end isn't known yet.
+    let mut opened: Vec<usize> = Vec::new();
+    loop {
+        let start = data.offset();
+        match data.read(())? {
+            opcode @ (0x02..=0x04 | 0x06 | 0x1F) => {
+                if stack.len() >= MAX_NESTING {
+                    return Err(ParseError::new(data, ParseErrorKind::NestingTooDeep));
+                }
+                data.consume(())?;
+                let tpe = BlockType::parse(data)?;
+                let open = match opcode {
+                    0x02 => Open::Block(tpe),
+                    0x03 => Open::Loop(tpe),
+                    0x04 => Open::If(tpe),
+                    0x06 => Open::Try(tpe),
+                    _ => Open::TryTable(tpe, Vec::parse(data)?),
+                };
+                stack.push((open, std::mem::take(&mut instr)));
+                if let Some(spans) = spans.as_mut() {
+                    opened.push(spans.len());
+                    spans.push(start..start);
+                }
+            }
+            opcode if stack.is_empty() && terminators.contains(&opcode) => {
+                let end = terminator(data, opcode)?;
+                return Ok((instr, end));
+            }
+            ELSE if matches!(stack.last(), Some((Open::If(_), _))) => {
+                data.consume(())?;
+                if let Some((Open::If(tpe), outer)) = stack.pop() {
+                    stack.push((Open::Else(tpe, std::mem::take(&mut instr)), outer));
+                }
+            }
+            opcode @ (CATCH | CATCH_ALL)
+                if matches!(
+                    stack.last(),
+                    Some((Open::Try(_), _)) | Some((Open::Catch(_, Some(_)), _))
+                ) =>
+            {
+                let tag = match terminator(data, opcode)? {
+                    Terminator::Catch(tag) => Some(tag),
+                    _ => None,
+                };
+                let inner = std::mem::take(&mut instr);
+                let (block, outer) = match stack.pop() {
+                    Some((Open::Try(tpe), outer)) => (TryBlock::new(tpe, inner), outer),
+                    Some((Open::Catch(mut block, prev), outer)) => {
+                        block.add_catch(prev, inner);
+                        (block, outer)
+                    }
+                    _ => unreachable!("Checked above"),
+                };
+                stack.push((Open::Catch(block, tag), outer));
+            }
+            DELEGATE if matches!(stack.last(), Some((Open::Try(_), _))) => {
+                let label = match terminator(data, DELEGATE)? {
+                    Terminator::Delegate(label) => label,
+                    _ => unreachable!("Read from the opcode"),
+                };
+                if let Some((Open::Try(tpe), outer)) = stack.pop() {
+                    let inner = std::mem::replace(&mut instr, outer);
+                    instr.push(Instr::Control(ControlInstr::Try(TryBlock {
+                        tpe,
+                        instr: inner,
+                        handler: TryHandler::Delegate(label),
+                    })));
+                }
+                close(&mut spans, &mut opened, data.offset());
+            }
+            0x0B => {
+                data.consume(())?;
+                let (open, outer) = match stack.pop() {
+                    Some(frame) => frame,
+                    None => return Ok((instr, Terminator::End)),
+                };
+                close(&mut spans, &mut opened, data.offset());
+                let inner = std::mem::replace(&mut instr, outer);
+                instr.push(Instr::Control(match open {
+                    Open::Block(tpe) => ControlInstr::Block(Block { tpe, instr: inner }),
+                    Open::Loop(tpe) => ControlInstr::Loop(Block { tpe, instr: inner }),
+                    Open::If(tpe) => ControlInstr::If(IfElseBlock {
+                        tpe,
+                        if_br: inner,
+                        else_br: Vec::new(),
+                    }),
+                    Open::Else(tpe, if_br) => ControlInstr::If(IfElseBlock {
+                        tpe,
+                        if_br,
+                        else_br: inner,
+                    }),
+                    Open::Try(tpe) => ControlInstr::Try(TryBlock::new(tpe, inner)),
+                    Open::Catch(mut block, tag) => {
+                        block.add_catch(tag, inner);
+                        ControlInstr::Try(block)
+                    }
+                    Open::TryTable(tpe, catches) => ControlInstr::TryTable(TryTableBlock {
+                        tpe,
+                        catches,
+                        instr: inner,
+                    }),
+                }));
+            }
+            _ => {
+                instr.push(Instr::parse(data)?);
+                if let Some(spans) = spans.as_mut() {
+                    spans.push(start..data.offset());
+                }
+            }
         }
-        data.consume(());
+    }
+}
+
+/// Sets the end of the span of the innermost structured instruction, which was just closed.
+fn close(spans: &mut Option<&mut Vec<Range<usize>>>, opened: &mut Vec<usize>, end: usize) {
+    if let (Some(spans), Some(idx)) = (spans.as_mut(), opened.pop()) {
+        spans[idx].end = end;
+    }
+}
 
+impl Parse for Expr {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let (instr, _) = parse_instrs(data, &[])?;
         Ok(Expr { instr })
     }
 }
 
+/// Parses an expression and pushes the byte range of each of its instructions to `spans`.
+///
+/// The instructions come in the order they appear in the binary, which is the pre-order of the
+/// nested instructions: a structured instruction comes before the instructions it contains and its
+/// range extends up to and including its `end`. The final `end` of the expression has no range.
+pub(crate) fn parse_spanned_expr(
+    data: &mut ParsingData,
+    spans: &mut Vec<Range<usize>>,
+) -> Result<Expr, ParseError> {
+    let (instr, _) = parse_spanned_instrs(data, &[], Some(spans))?;
+    Ok(Expr { instr })
+}
+
+/// Bit of the alignment field which tells that a memory index follows it.
+const MEM_IDX_FLAG: u32 = 1 << 6;
+
 impl Parse for MemArg {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         let align = U32::parse(data)?;
-        let offset = U32::parse(data)?;
-        Ok(MemArg { align, offset })
+        let (align, mem) = if *align & MEM_IDX_FLAG != 0 {
+            (U32(*align & !MEM_IDX_FLAG), MemIdx::parse(data)?)
+        } else {
+            (align, MemIdx(U32(0)))
+        };
+        let offset = U64::parse(data)?;
+        Ok(MemArg { align, offset, mem })
     }
 }
 
 impl Parse for Block {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         let tpe = BlockType::parse(data)?;
-        let mut instr = Vec::new();
-        while data.read(()) != 0x0B {
-            instr.push(Instr::parse(data)?);
-        }
-        data.consume(());
+        let (instr, _) = parse_instrs(data, &[])?;
         Ok(Block { tpe, instr })
     }
 }
@@ -387,59 +1084,98 @@ impl Parse for Block {
 impl Parse for IfElseBlock {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         let tpe = BlockType::parse(data)?;
-        let mut if_br = Vec::new();
-        let mut else_br = Vec::new();
+        let (if_br, end) = parse_instrs(data, &[ELSE])?;
+        let else_br = match end {
+            Terminator::Else => parse_instrs(data, &[])?.0,
+            _ => Vec::new(),
+        };
+        Ok(IfElseBlock {
+            tpe,
+            if_br,
+            else_br,
+        })
+    }
+}
+
+impl Parse for TryBlock {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let tpe = BlockType::parse(data)?;
+        let (instr, mut end) = parse_instrs(data, &[CATCH, CATCH_ALL, DELEGATE])?;
+        if let Terminator::Delegate(label) = end {
+            return Ok(TryBlock {
+                tpe,
+                instr,
+                handler: TryHandler::Delegate(label),
+            });
+        }
+        let mut block = TryBlock::new(tpe, instr);
         loop {
-            match data.read(()) {
-                0x0B => {
-                    data.consume(());
-                    return Ok(IfElseBlock {
-                        tpe,
-                        if_br,
-                        else_br,
-                    });
-                }
-                0x05 => {
-                    data.consume(());
-                    break;
-                }
-                _ => {
-                    if_br.push(Instr::parse(data)?);
-                }
-            }
+            let tag = match end {
+                Terminator::Catch(tag) => Some(tag),
+                Terminator::CatchAll => None,
+                _ => return Ok(block),
+            };
+            // Nothing but `end` may follow the `catch_all` clause.
+            let terminators: &[u8] = if tag.is_some() {
+                &[CATCH, CATCH_ALL]
+            } else {
+                &[]
+            };
+            let (instr, next) = parse_instrs(data, terminators)?;
+            block.add_catch(tag, instr);
+            end = next;
         }
+    }
+}
 
-        loop {
-            match data.read(()) {
-                0x0B => {
-                    data.consume(());
-                    return Ok(IfElseBlock {
-                        tpe,
-                        if_br,
-                        else_br,
-                    });
-                }
-                _ => {
-                    else_br.push(Instr::parse(data)?);
-                }
+impl Parse for TryTableBlock {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let tpe = BlockType::parse(data)?;
+        let catches = Vec::parse(data)?;
+        let (instr, _) = parse_instrs(data, &[])?;
+        Ok(TryTableBlock {
+            tpe,
+            catches,
+            instr,
+        })
+    }
+}
+
+impl Parse for Catch {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let catch = match data.consume(())? {
+            0x00 => Catch::Catch(TagIdx::parse(data)?, LabelIdx::parse(data)?),
+            0x01 => Catch::CatchRef(TagIdx::parse(data)?, LabelIdx::parse(data)?),
+            0x02 => Catch::CatchAll(LabelIdx::parse(data)?),
+            0x03 => Catch::CatchAllRef(LabelIdx::parse(data)?),
+            x => {
+                return Err(ParseError::new(
+                    data,
+                    ParseErrorKind::InvalidEncoding {
+                        what: "catch clause",
+                        value: x.into(),
+                    },
+                ))
             }
-        }
+        };
+        Ok(catch)
     }
 }
 
 impl Parse for BlockType {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let blocktype = match data.read(()) {
+        let blocktype = match data.read(())? {
             0x40 => {
-                data.consume(());
+                data.consume(())?;
                 BlockType::Empty
             }
-            0x7C..=0x7F | 0x6F | 0x70 => BlockType::Val(ValType::parse(data)?),
+            0x7B..=0x7F | 0x63 | 0x64 => BlockType::Val(ValType::parse(data)?),
+            b if abstract_heap_type(b).is_some() => BlockType::Val(ValType::parse(data)?),
             _ => {
                 let val = *S64::parse(data)?;
                 let val: u32 = val.try_into().or(Err(ParseError::new(
                     data,
-                    format!("Can't decode block type. Invalid type index {}", val),
+                    ParseErrorKind::InvalidBlockType(val),
                 )))?;
                 BlockType::Type(TypeIdx(U32(val)))
             }
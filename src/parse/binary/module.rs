@@ -1,14 +1,29 @@
 use sections::{
-    code::CodeSection, custom::CustomSection, data::DataSection, elem::ElemSection,
-    export::ExportSection, func::FuncSection, global::GlobalSection, import::ImportSection,
-    mem::MemSection, start::StartSection, table::TableSection, types::TypeSection, Section,
+    code::{self, CodeSection},
+    custom::CustomSection,
+    data::DataSection,
+    elem::ElemSection,
+    export::ExportSection,
+    func::FuncSection,
+    global::GlobalSection,
+    import::ImportSection,
+    mem::MemSection,
+    start::StartSection,
+    table::TableSection,
+    tag::TagSection,
+    types::TypeSection,
+    Section,
 };
 
 use super::preamble;
-use super::sections;
-use super::{Parse, ParseError, ParsingData};
-use crate::wasm::func::Func;
-use crate::wasm::module::Module;
+use super::sections::{self, names::NAME_SECTION};
+use super::{Consume, ErrorContext, Parse, ParseBorrowed, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::custom::BorrowedCustomSection;
+use crate::wasm::data::{BorrowedData, Data};
+use crate::wasm::func::{BorrowedFunc, Func};
+use crate::wasm::import::ImportDesc;
+use crate::wasm::module::{BorrowedModule, Module};
+use crate::wasm::names::NameSection;
 use crate::wasm::values::U32;
 
 pub struct BinaryModule {
@@ -19,25 +34,55 @@ pub struct BinaryModule {
     pub functions: FuncSection,
     pub tables: TableSection,
     pub mems: MemSection,
+    pub tags: TagSection,
     pub globals: GlobalSection,
     pub exports: ExportSection,
     pub start: StartSection,
     pub elems: ElemSection,
     pub code: CodeSection,
     pub data: DataSection,
+    pub data_count: Option<U32>,
+    pub names: Option<NameSection>,
+    /// Recoverable errors found while parsing the module (e.g. a malformed name section). They
+    /// aren't part of [Module], see [Module::parse_with_warnings].
+    pub warnings: Vec<ParseError>,
 }
 
 impl BinaryModule {
+    /// Assembles a module from its sections.
+    ///
+    /// Non-custom sections must appear at most once and in the order defined by the specification.
+    /// Custom sections may appear anywhere and remember the section they follow.
     pub fn new(
         header: preamble::Preamble,
         sections: Vec<Section>,
-    ) -> Result<BinaryModule, &'static str> {
+    ) -> Result<BinaryModule, ParseErrorKind> {
+        let module = BinaryModule::assemble(header, sections)?;
+        module.check_bodies(module.code.code.len())?;
+        let uses_data = module
+            .code
+            .code
+            .iter()
+            .any(|func| code::uses_data_count(&func.code.instr));
+        if uses_data && module.data_count.is_none() {
+            return Err(ParseErrorKind::DataCountRequired);
+        }
+        Ok(module)
+    }
+
+    /// Assembles a module like [BinaryModule::new] without checking that there are as many
+    /// function bodies as functions, for when the bodies are kept apart from the module.
+    fn assemble(
+        header: preamble::Preamble,
+        sections: Vec<Section>,
+    ) -> Result<BinaryModule, ParseErrorKind> {
         let mut custom: Vec<CustomSection> = Vec::new();
         let mut types: Option<TypeSection> = None;
         let mut imports: Option<ImportSection> = None;
         let mut functions: Option<FuncSection> = None;
         let mut tables: Option<TableSection> = None;
         let mut mems: Option<MemSection> = None;
+        let mut tags: Option<TagSection> = None;
         let mut globals: Option<GlobalSection> = None;
         let mut exports: Option<ExportSection> = None;
         let mut start: Option<StartSection> = None;
@@ -45,15 +90,35 @@ impl BinaryModule {
         let mut code: Option<CodeSection> = None;
         let mut data: Option<DataSection> = None;
         let mut data_count: Option<U32> = None;
+        let mut names: Option<NameSection> = None;
+        let mut warnings: Vec<ParseError> = Vec::new();
+        let mut last: Option<u8> = None;
 
         for section in sections {
+            let id = section.id();
+            if id != 0 {
+                sections::check_order(last, id)?;
+                last = Some(id);
+            }
             match section {
-                Section::Custom(s) => custom.push(s),
+                Section::Custom(mut s) => {
+                    s.after = last;
+                    custom.push(s)
+                }
+                Section::Name(mut s, n) => {
+                    s.after = last;
+                    custom.push(s);
+                    match n {
+                        Ok(n) => names = Some(n),
+                        Err(err) => warnings.push(err),
+                    }
+                }
                 Section::Type(s) => types = Some(s),
                 Section::Import(s) => imports = Some(s),
                 Section::Function(s) => functions = Some(s),
                 Section::Table(s) => tables = Some(s),
                 Section::Memory(s) => mems = Some(s),
+                Section::Tag(s) => tags = Some(s),
                 Section::Global(s) => globals = Some(s),
                 Section::Export(s) => exports = Some(s),
                 Section::Start(s) => start = Some(s),
@@ -72,47 +137,185 @@ impl BinaryModule {
             functions: functions.unwrap_or_default(),
             tables: tables.unwrap_or_default(),
             mems: mems.unwrap_or_default(),
+            tags: tags.unwrap_or_default(),
             globals: globals.unwrap_or_default(),
             exports: exports.unwrap_or_default(),
             start: start.unwrap_or_default(),
             elems: elems.unwrap_or_default(),
             code: code.unwrap_or_default(),
             data: data.unwrap_or_default(),
+            data_count,
+            names,
+            warnings,
         };
 
-        if let Some(len) = data_count {
-            if module.data.data.len() != *len as usize {
-                return Err(
-                    "Malformed module. Data section should have a length equal to that of data_count.",
-                );
+        if let Some(count) = data_count {
+            if module.data.data.len() != *count as usize {
+                return Err(ParseErrorKind::DataCountMismatch {
+                    count: *count,
+                    datas: module.data.data.len(),
+                });
             }
         }
 
-        if module.functions.funcs.len() != module.code.code.len() {
-            return Err("Malformed module. Func and Code sections should have the same length.");
-        }
         Ok(module)
     }
+
+    /// Checks that the function section declares as many functions as there are `bodies`.
+    fn check_bodies(&self, bodies: usize) -> Result<(), ParseErrorKind> {
+        if self.functions.funcs.len() != bodies {
+            return Err(ParseErrorKind::FuncCodeMismatch {
+                funcs: self.functions.funcs.len(),
+                bodies,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Parses the preamble and the sections of a module, with `parse_section` parsing each section
+/// starting from its id.
+pub(crate) fn parse_module<'a>(
+    data: &mut ParsingData<'a>,
+    parse_section: impl FnMut(&mut ParsingData<'a>) -> Result<Section, ParseError>,
+) -> Result<BinaryModule, ParseError> {
+    let (header, sections) = parse_sections(data, parse_section)?;
+    BinaryModule::new(header, sections).map_err(|kind| ParseError::new(data, kind))
+}
+
+/// Parses the preamble and the sections of a module like [parse_module], without assembling them.
+fn parse_sections<'a>(
+    data: &mut ParsingData<'a>,
+    mut parse_section: impl FnMut(&mut ParsingData<'a>) -> Result<Section, ParseError>,
+) -> Result<(preamble::Preamble, Vec<Section>), ParseError> {
+    let header = preamble::Preamble::parse(data).map_err(|err| err.extend("Can't parse header"))?;
+    let mut sections: Vec<Section> = Vec::new();
+    // Function bodies are numbered from the first non-imported function.
+    let mut imported_funcs = 0;
+    let mut last: Option<u8> = None;
+    while !data.is_empty() {
+        // Check the order before decoding the section so the error points at its id.
+        let id = data.read(())?;
+        if id != 0 {
+            sections::check_order(last, id).map_err(|kind| ParseError::new(data, kind))?;
+            last = Some(id);
+        }
+        let section = parse_section(data).map_err(|err| err.offset_func(imported_funcs))?;
+        if let Section::Import(imports) = &section {
+            imported_funcs = imports
+                .imports
+                .iter()
+                .filter(|import| matches!(import.desc, ImportDesc::Func(_)))
+                .count() as u32;
+        }
+        sections.push(section);
+    }
+    Ok((header, sections))
 }
 
 impl Parse for BinaryModule {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let header =
-            preamble::Preamble::parse(data).map_err(|err| err.extend("Can't parse header"))?;
-        let mut sections: Vec<Section> = Vec::new();
-        while !data.is_empty() {
-            sections
-                .push(Section::parse(data).map_err(|err| err.extend("Couldn't parse section"))?);
-        }
+        parse_module(data, Section::parse)
+    }
+}
 
-        Ok(BinaryModule::new(header, sections)
-            .map_err(|err| ParseError::new(data, err.to_string()))?)
+/// Parses the module like [BinaryModule], except for the code, data and custom sections whose
+/// contents are kept as slices of the binary. The module is still assembled from sections to check
+/// their order and counts, with the code section left out and data segments and custom sections
+/// left empty.
+impl<'a> ParseBorrowed<'a> for BorrowedModule<'a> {
+    fn parse_borrowed(data: &mut ParsingData<'a>) -> Result<Self, ParseError> {
+        let mut code = Vec::new();
+        let mut datas = Vec::new();
+        let mut custom = Vec::new();
+        let sections = parse_sections(data, |data| match data.read(())? {
+            0 => {
+                let (id, _, mut bytes) = sections::read_section(data)?;
+                let context = |err: ParseError| err.with_context(ErrorContext::Section(id));
+                let section = BorrowedCustomSection::parse_borrowed(&mut bytes).map_err(context)?;
+                custom.push(section);
+                let empty = CustomSection::from(BorrowedCustomSection {
+                    data: &[],
+                    ..section
+                });
+                Ok(if section.name == NAME_SECTION {
+                    Section::Name(empty, NameSection::parse(&mut bytes).map_err(context))
+                } else {
+                    Section::Custom(empty)
+                })
+            }
+            10 => {
+                let (id, size, mut bytes) = sections::read_section(data)?;
+                code = sections::code::parse_lazy(&mut bytes)
+                    .map_err(|err| err.with_context(ErrorContext::Section(id)))?;
+                sections::check_size(id, size, &bytes)?;
+                Ok(Section::Code(CodeSection::default()))
+            }
+            11 => {
+                let (id, size, mut bytes) = sections::read_section(data)?;
+                datas = Vec::<BorrowedData>::parse_borrowed(&mut bytes)
+                    .map_err(|err| err.with_context(ErrorContext::Section(id)))?;
+                sections::check_size(id, size, &bytes)?;
+                let data = datas
+                    .iter()
+                    .map(|data| Data {
+                        init: Vec::new(),
+                        mode: data.mode.clone(),
+                    })
+                    .collect();
+                Ok(Section::Data(DataSection { data }))
+            }
+            _ => Section::parse(data),
+        });
+        let mut binary = sections
+            .and_then(|(header, sections)| {
+                let binary = BinaryModule::assemble(header, sections)
+                    .and_then(|binary| binary.check_bodies(code.len()).map(|()| binary));
+                binary.map_err(|kind| ParseError::new(data, kind))
+            })
+            .map_err(|err| err.extend("Can't parse binary module"))?;
+        let types = std::mem::take(&mut binary.functions.funcs);
+        let warnings = std::mem::take(&mut binary.warnings);
+        let module = Module::from(binary);
+        let imported = module
+            .imports
+            .iter()
+            .filter(|import| matches!(import.desc, ImportDesc::Func(_)))
+            .count() as u32;
+        let funcs = types
+            .into_iter()
+            .zip(code)
+            .enumerate()
+            .map(|(i, (index, code))| BorrowedFunc {
+                index,
+                func: imported + i as u32,
+                code,
+            })
+            .collect();
+        Ok(BorrowedModule {
+            types: module.types,
+            funcs,
+            tables: module.tables,
+            mems: module.mems,
+            tags: module.tags,
+            globals: module.globals,
+            elems: module.elems,
+            datas,
+            start: module.start,
+            imports: module.imports,
+            exports: module.exports,
+            name: module.name,
+            names: module.names,
+            custom,
+            warnings,
+        })
     }
 }
 
 impl Parse for Module {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let binary = BinaryModule::parse(data).map_err(|err| err.extend("Can't binary module"))?;
+        let binary =
+            BinaryModule::parse(data).map_err(|err| err.extend("Can't parse binary module"))?;
         Ok(binary.into())
     }
 }
@@ -130,18 +333,513 @@ impl From<BinaryModule> for Module {
                 body: cd.code,
             })
             .collect();
+        let names = from.names.unwrap_or_default();
         Module {
             types: from.types.types,
             funcs: funcs,
             tables: from.tables.tables,
             mems: from.mems.mems,
+            tags: from.tags.tags,
             globals: from.globals.globals,
             elems: from.elems.seg,
             datas: from.data.data,
             start: from.start.start,
             imports: from.imports.imports,
             exports: from.exports.exports,
-            name: None,
+            name: names.module.clone(),
+            names,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::WasmBinary;
+    use crate::wasm::instr::{ControlInstr, Instr};
+    use crate::wasm::types::{NumType, ValType};
+
+    const PREAMBLE: [u8; 8] = [0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+
+    fn parse(sections: &[u8]) -> Result<Module, ParseError> {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(sections.iter())
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        let mut wasm = ParsingData::new(&wasm);
+        Module::parse(&mut wasm)
+    }
+
+    #[test]
+    fn unknown_section() {
+        let err = parse(&[0x0E, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnknownSection(0x0E));
+        assert_eq!(err.location(), 8);
+    }
+
+    #[test]
+    fn empty_start_section() {
+        let err = parse(&[0x08, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.section(), Some(8));
+        assert_eq!(err.location(), 10);
+    }
+
+    #[test]
+    fn unknown_opcode_context() {
+        let err = parse(&[
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+            0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00, 0x00, // Import "m" "f" (func 0)
+            0x03, 0x03, 0x02, 0x00, 0x00, // Function section: 2 functions
+            0x0A, 0x08, 0x02, // Code section: 2 bodies
+            0x02, 0x00, 0x0B, // Body of func 1
+            0x03, 0x00, 0xFC, 0x7F, // Body of func 2: unknown 0xFC instruction
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::UnknownOpcode {
+                prefix: Some(0xFC),
+                opcode: 0x7F
+            }
+        );
+        assert_eq!(err.section(), Some(10));
+        assert_eq!(err.func(), Some(2));
+        assert_eq!(err.to_string().matches("In function 2").count(), 1);
+    }
+
+    #[test]
+    fn out_of_order_section() {
+        let err = parse(&[
+            0x03, 0x01, 0x00, // Function section: no functions
+            0x01, 0x01, 0x00, // Type section: no types
+        ])
+        .unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::OutOfOrderSection(1));
+        assert_eq!(err.location(), 11);
+    }
+
+    #[test]
+    fn duplicate_section() {
+        let err = parse(&[
+            0x01, 0x01, 0x00, // Type section: no types
+            0x00, 0x02, 0x01, b'c', // Custom section "c"
+            0x01, 0x01, 0x00, // Type section: no types
+        ])
+        .unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::DuplicateSection(1));
+        assert_eq!(err.location(), 15);
+    }
+
+    #[test]
+    fn data_count_before_code() {
+        assert_eq!(
+            parse(&[0x0A, 0x01, 0x00, 0x0C, 0x01, 0x00])
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::OutOfOrderSection(12)
+        );
+        assert!(parse(&[0x0C, 0x01, 0x00, 0x0A, 0x01, 0x00]).is_ok());
+    }
+
+    #[test]
+    fn tag_section_order() {
+        let module = parse(&[
+            0x01, 0x05, 0x01, 0x60, 0x01, 0x7F, 0x00, // Type section: [i32] -> []
+            0x05, 0x03, 0x01, 0x00, 0x01, // Memory section
+            0x0D, 0x03, 0x01, 0x00, 0x00, // Tag section: (tag (type 0))
+            0x06, 0x01, 0x00, // Global section: no globals
+        ])
+        .unwrap();
+        assert_eq!(module.tags.len(), 1);
+        assert_eq!(
+            parse(&[0x06, 0x01, 0x00, 0x0D, 0x01, 0x00])
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::OutOfOrderSection(13)
+        );
+        assert_eq!(
+            parse(&[0x0D, 0x02, 0x01, 0x01]).unwrap_err().kind(),
+            &ParseErrorKind::InvalidEncoding {
+                what: "tag attribute",
+                value: 0x01
+            }
+        );
+    }
+
+    #[test]
+    fn custom_section_placement() {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(&[
+                0x00, 0x02, 0x01, b'a', // Custom section "a"
+                0x01, 0x01, 0x00, // Type section: no types
+                0x00, 0x02, 0x01, b'b', // Custom section "b"
+                0x07, 0x01, 0x00, // Export section: no exports
+                0x00, 0x02, 0x01, b'c', // Custom section "c"
+            ])
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        let module = BinaryModule::parse(&mut ParsingData::new(&wasm)).unwrap();
+        let placement: Vec<(&str, Option<u8>)> = module
+            .custom
+            .iter()
+            .map(|custom| (custom.name.name.as_str(), custom.after))
+            .collect();
+        assert_eq!(placement, [("a", None), ("b", Some(1)), ("c", Some(7))]);
+    }
+
+    #[test]
+    fn section_size_mismatch() {
+        // Type section declaring 5 bytes with a trailing byte after its single type.
+        let err = parse(&[0x01, 0x05, 0x01, 0x60, 0x00, 0x00, 0xFF]).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::SectionSizeMismatch {
+                expected: 5,
+                actual: 4
+            }
+        );
+        assert_eq!(err.section(), Some(1));
+        // Type section declaring more bytes than available.
+        let err = parse(&[0x01, 0x05, 0x01, 0x60, 0x00, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.section(), Some(1));
+        // Type section declaring fewer bytes than its contents.
+        let err = parse(&[0x01, 0x03, 0x01, 0x60, 0x00, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn func_size_mismatch() {
+        // Trailing nop after the end of the body.
+        let err = parse(&func(&[0x00, 0x0B, 0x01])).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::SectionSizeMismatch {
+                expected: 3,
+                actual: 2
+            }
+        );
+        assert_eq!(err.func(), Some(0));
+        // Body size larger than the code section.
+        let mut wasm = func(&[0x00, 0x0B]);
+        let len = wasm.len();
+        wasm[len - 3] = 0x03;
+        let err = parse(&wasm).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.func(), Some(0));
+    }
+
+    #[test]
+    fn func_code_mismatch() {
+        let err = parse(&[
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+            0x03, 0x02, 0x01, 0x00, // Function section: 1 function
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::FuncCodeMismatch {
+                funcs: 1,
+                bodies: 0
+            }
+        );
+    }
+
+    fn write_len(mut len: usize, out: &mut Vec<u8>) {
+        while len >= 0x80 {
+            out.push((len as u8 & 0x7F) | 0x80);
+            len >>= 7;
+        }
+        out.push(len as u8);
+    }
+
+    /// Single `[] -> []` function with the given code entry (locals followed by the body).
+    fn func(code: &[u8]) -> Vec<u8> {
+        let mut sections = vec![
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+            0x03, 0x02, 0x01, 0x00, // Function section: 1 function
+        ];
+        let mut contents = vec![0x01];
+        write_len(code.len(), &mut contents);
+        contents.extend(code);
+        sections.push(0x0A);
+        write_len(contents.len(), &mut sections);
+        sections.extend(contents);
+        sections
+    }
+
+    #[test]
+    fn fuzz_regressions() {
+        for entry in std::fs::read_dir("fuzz/regressions/parse_module").unwrap() {
+            let wasm: WasmBinary = std::fs::read(entry.unwrap().path()).unwrap().into();
+            assert!(Module::parse(&mut ParsingData::new(&wasm)).is_err());
+        }
+    }
+
+    #[test]
+    fn truncated_body() {
+        // No locals, nop and a missing `end`.
+        let err = parse(&func(&[0x00, 0x01])).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.func(), Some(0));
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth: usize| {
+            let mut code = vec![0x00];
+            code.extend([0x02, 0x40].repeat(depth));
+            code.extend(vec![0x0B; depth + 1]);
+            func(&code)
+        };
+        // Consumers of the parsed module recurse into blocks so make sure they cope with the limit.
+        let module = parse(&nested(crate::parse::binary::MAX_NESTING)).unwrap();
+        module.validate().unwrap();
+        assert_eq!(
+            format!(
+                "{:?}",
+                parse(&module.to_binary()[PREAMBLE.len()..]).unwrap()
+            ),
+            format!("{:?}", module)
+        );
+        assert_eq!(
+            parse(&nested(crate::parse::binary::MAX_NESTING + 1))
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::NestingTooDeep
+        );
+    }
+
+    #[test]
+    fn too_many_locals() {
+        // 2 local entries: 0xFFFFFFFF i32 and 1 i64.
+        let err = parse(&func(&[
+            0x02, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 0x7F, 0x01, 0x7E, 0x0B,
+        ]))
+        .unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::TooManyLocals(0x1_0000_0000));
+    }
+
+    #[test]
+    fn name_section() {
+        let module = parse(&[
+            0x00, 0x0B, 0x04, b'n', b'a', b'm', b'e', // Custom section "name"
+            0x00, 0x04, 0x03, b'f', b'o', b'o', // Module name: "foo"
+        ])
+        .unwrap();
+        assert_eq!(module.name.unwrap().name, "foo");
+        assert_eq!(module.names.module.unwrap().name, "foo");
+    }
+
+    #[test]
+    fn malformed_name_section_is_a_warning() {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(&[
+                0x00, 0x08, 0x04, b'n', b'a', b'm', b'e', // Custom section "name"
+                0x00, 0x04, 0x03, // Module name subsection larger than the section
+            ])
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        let (module, warnings) = Module::parse_with_warnings(&mut ParsingData::new(&wasm)).unwrap();
+        assert!(module.name.is_none());
+        assert_eq!(warnings.len(), 1);
+    }
+
+    #[test]
+    fn borrowed_module() {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(&[
+                0x00, 0x0B, 0x04, b'n', b'a', b'm', b'e', // Custom section "name"
+                0x00, 0x04, 0x03, b'f', b'o', b'o', // Module name: "foo"
+                0x05, 0x03, 0x01, 0x00, 0x01, // Memory section: 1 memory of 1 page
+                0x00, 0x04, 0x01, b'x', 0xAB, 0xCD, // Custom section "x"
+                0x0B, 0x0B, 0x02, // Data section: 2 segments
+                0x00, 0x41, 0x00, 0x0B, 0x02, b'h', b'i', // Active "hi" at 0
+                0x01, 0x01, b'!', // Passive "!"
+            ])
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        let module = BorrowedModule::parse_borrowed(&mut ParsingData::new(&wasm)).unwrap();
+        let inits: Vec<&[u8]> = module.datas.iter().map(|data| data.init).collect();
+        assert_eq!(inits, [&b"hi"[..], b"!"]);
+        assert!(std::ptr::eq(module.datas[1].init, &wasm[wasm.len() - 1..]));
+        let custom: Vec<(&str, &[u8])> = module
+            .custom
+            .iter()
+            .map(|custom| (custom.name, custom.data))
+            .collect();
+        assert_eq!(
+            custom,
+            [
+                ("name", &[0x00, 0x04, 0x03, b'f', b'o', b'o'][..]),
+                ("x", &[0xAB, 0xCD])
+            ]
+        );
+        assert_eq!(module.name.as_ref().unwrap().name, "foo");
+
+        let owned = Module::parse(&mut ParsingData::new(&wasm)).unwrap();
+        assert_eq!(
+            format!("{:?}", module.into_owned().unwrap()),
+            format!("{:?}", owned)
+        );
+
+        // The sections are checked just as when parsing an owned module.
+        let err =
+            BorrowedModule::parse_borrowed(&mut ParsingData::new(&wasm[..wasm.len() - 1].into()))
+                .unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn borrowed_slices() {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(&[
+                0x00, 0x03, 0x01, b'a', 0x01, // Custom section "a"
+                0x05, 0x03, 0x01, 0x00, 0x01, // Memory section: 1 memory of 1 page
+                0x00, 0x04, 0x01, b'b', 0x02, 0x03, // Custom section "b"
+                0x0B, 0x0F, 0x03, // Data section: 3 segments
+                0x00, 0x41, 0x00, 0x0B, 0x02, b'h', b'i', // Active "hi" at 0
+                0x01, 0x00, // Passive ""
+                0x01, 0x03, b'a', b'b', b'c', // Passive "abc"
+                0x00, 0x02, 0x01, b'c', // Custom section "c"
+            ])
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        let module = BorrowedModule::parse_borrowed(&mut ParsingData::new(&wasm)).unwrap();
+        let range = wasm.as_ptr_range();
+        let within = |bytes: &[u8]| {
+            let ptrs = bytes.as_ptr_range();
+            range.start <= ptrs.start && ptrs.end <= range.end
+        };
+        let offset = |bytes: &[u8]| bytes.as_ptr() as usize - range.start as usize;
+
+        assert_eq!(module.datas.len(), 3);
+        assert!(module.datas.iter().all(|data| within(data.init)));
+        let datas: Vec<(usize, usize)> = module
+            .datas
+            .iter()
+            .map(|data| (offset(data.init), data.init.len()))
+            .collect();
+        assert_eq!(datas, [(32, 2), (36, 0), (38, 3)]);
+
+        assert_eq!(module.custom.len(), 3);
+        assert!(module
+            .custom
+            .iter()
+            .all(|custom| within(custom.name.as_bytes()) && within(custom.data)));
+        let custom: Vec<(&str, usize, usize)> = module
+            .custom
+            .iter()
+            .map(|custom| {
+                (
+                    custom.name,
+                    offset(custom.name.as_bytes()),
+                    offset(custom.data),
+                )
+            })
+            .collect();
+        assert_eq!(custom, [("a", 11, 12), ("b", 21, 22), ("c", 44, 45)]);
+        assert_eq!(module.custom[2].data, []);
+    }
+
+    #[test]
+    fn lazy_function_bodies() {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(&[
+                0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+                0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00,
+                0x00, // Import "m" "f" (func 0)
+                0x03, 0x03, 0x02, 0x00, 0x00, // Function section: 2 functions
+                0x0A, 0x0B, 0x02, // Code section: 2 bodies
+                0x05, 0x01, 0x01, 0x7F, 0x01, 0x0B, // Body of func 1: (local i32) nop
+                0x03, 0x00, 0xFC, 0x7F, // Body of func 2: unknown 0xFC instruction
+            ])
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        // Malformed bodies are only reported once decoded.
+        let module = BorrowedModule::parse_borrowed(&mut ParsingData::new(&wasm)).unwrap();
+        assert_eq!(module.funcs.len(), 2);
+        assert!(matches!(
+            module.funcs[0].locals(),
+            [ValType::Num(NumType::I32)]
+        ));
+        assert_eq!(module.funcs[0].range(), 35..37);
+        assert_eq!(module.funcs[0].bytes(), [0x01, 0x0B]);
+        assert!(matches!(
+            module.funcs[0].instructions().unwrap()[..],
+            [Instr::Control(ControlInstr::Nop)]
+        ));
+
+        let err = module.funcs[1].body().unwrap_err();
+        assert_eq!(err.location(), 41);
+        assert_eq!(err.func(), Some(2));
+        let err = module.into_owned().unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::UnknownOpcode {
+                prefix: Some(0xFC),
+                opcode: 0x7F
+            }
+        );
+        assert_eq!(err.func(), Some(2));
+
+        // Malformed locals are still reported upfront.
+        let mut bytes = wasm.to_vec();
+        bytes[34] = 0x00; // Invalid value type
+        let err = BorrowedModule::parse_borrowed(&mut ParsingData::new(&bytes.into())).unwrap_err();
+        assert_eq!(err.func(), Some(1));
+    }
+
+    #[test]
+    fn lazy_body_ranges() {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(&[
+                0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+                0x03, 0x03, 0x02, 0x00, 0x00, // Function section: 2 functions
+                0x0A, 0x0A, 0x02, // Code section: 2 bodies
+                0x04, 0x00, 0x01, 0x0B, 0x01, // Body of func 0: nop, then a byte past the end
+                0x03, 0x00, 0xFF, 0xFF, // Body of func 1: garbage
+            ])
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        // The garbage isn't looked at until the body is decoded.
+        let module = BorrowedModule::parse_borrowed(&mut ParsingData::new(&wasm)).unwrap();
+        assert_eq!(module.funcs[0].range(), 24..27);
+        assert_eq!(module.funcs[1].range(), 29..31);
+        for func in &module.funcs {
+            assert_eq!(&wasm[func.range()], func.bytes());
+        }
+        assert_eq!(module.funcs[1].bytes(), [0xFF, 0xFF]);
+
+        // The instructions must end with the body.
+        let err = module.funcs[0].body().unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::SectionSizeMismatch {
+                expected: 4,
+                actual: 3
+            }
+        );
+        assert_eq!(err.location(), 26);
+        assert_eq!(err.func(), Some(0));
+
+        let err = module.funcs[1].body().unwrap_err();
+        assert_eq!(err.location(), 30);
+        assert_eq!(err.func(), Some(1));
+    }
+}
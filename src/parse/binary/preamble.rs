@@ -1,6 +1,6 @@
-use super::{Consume, Parse, ParseError, ParsingData};
+use super::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
 
-const MAGIC: [u8; 4] = [0x00, 0x61, 0x73, 0x6D];
+pub(crate) const MAGIC: [u8; 4] = [0x00, 0x61, 0x73, 0x6D];
 
 #[derive(Debug)]
 pub enum Version {
@@ -15,21 +15,22 @@ pub struct Preamble {
 impl Parse for Preamble {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         let magic = data.consume(4);
+        if magic.len() < 4 {
+            return Err(ParseError::eof(data));
+        }
         if *magic != MAGIC {
-            return Err(ParseError::new(
-                data,
-                format!("Invalid magic! Expected {:X?}, got {:X?}", &MAGIC, &magic),
-            ));
+            return Err(ParseError::new(&magic, ParseErrorKind::InvalidMagic));
         }
         let version = data.consume(4);
         return match *version {
             [1, 0, 0, 0] => Ok(Preamble {
                 version: Version::V1_0_0_0,
             }),
-            _ => Err(ParseError::new(
-                data,
-                format!("Unknown version {:X?}", version),
+            [a, b, c, d] => Err(ParseError::new(
+                &version,
+                ParseErrorKind::UnsupportedVersion(u32::from_le_bytes([a, b, c, d])),
             )),
+            _ => Err(ParseError::eof(data)),
         };
     }
 }
@@ -53,4 +54,35 @@ mod tests {
         // We consumed the input.
         assert!(wasm.is_empty());
     }
+
+    fn parse(bytes: &[u8]) -> Result<Preamble, ParseError> {
+        use crate::parse::binary::*;
+        let wasm: WasmBinary = bytes.into();
+        let mut wasm = ParsingData::new(&wasm);
+        Preamble::parse(&mut wasm)
+    }
+
+    #[test]
+    fn invalid_magic() {
+        let err = parse(&[0x00, 0x61, 0x73, 0x6E, 0x01, 0x00, 0x00, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InvalidMagic);
+        assert_eq!(err.location(), 0);
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let err = parse(&[0x00, 0x61, 0x73, 0x6D, 0x02, 0x00, 0x00, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnsupportedVersion(2));
+        assert_eq!(err.location(), 4);
+    }
+
+    #[test]
+    fn truncated_preamble() {
+        let err = parse(&[0x00, 0x61, 0x73]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        let err = parse(&[0x01]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        let err = parse(&[0x00, 0x61, 0x73, 0x6D, 0x01]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+    }
 }
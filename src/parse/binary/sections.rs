@@ -1,4 +1,5 @@
-use super::{Consume, Parse, ParseError, ParsingData};
+use super::{Consume, ErrorContext, Parse, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::names::NameSection;
 use crate::wasm::values::{Byte, U32};
 
 pub mod code;
@@ -10,19 +11,27 @@ pub mod func;
 pub mod global;
 pub mod import;
 pub mod mem;
+pub mod names;
 pub mod start;
 pub mod table;
+pub mod tag;
 pub mod types;
 
 #[derive(Debug)]
 pub enum Section {
     Custom(custom::CustomSection),
+    /// The `name` custom section along with the result of decoding it.
+    ///
+    /// A malformed name section doesn't invalidate the module, so the error is kept around to be
+    /// reported as a warning.
+    Name(custom::CustomSection, Result<NameSection, ParseError>),
     Type(types::TypeSection),
     Import(import::ImportSection),
     Function(func::FuncSection),
     Table(table::TableSection),
     Memory(mem::MemSection),
     Global(global::GlobalSection),
+    Tag(tag::TagSection),
     Export(export::ExportSection),
     Start(start::StartSection),
     Element(elem::ElemSection),
@@ -31,71 +40,130 @@ pub enum Section {
     DataCount(U32),
 }
 
+/// Position of a non-custom section within the canonical section order, or `None` for custom and
+/// unknown sections.
+///
+/// The order follows the section ids except for the tag section which comes right after the memory
+/// section and the data count section which comes right before the code section.
+pub(crate) fn order(id: u8) -> Option<u8> {
+    match id {
+        1..=5 => Some(id),
+        13 => Some(6),
+        6..=9 => Some(id + 1),
+        12 => Some(11),
+        10 | 11 => Some(id + 2),
+        _ => None,
+    }
+}
+
+/// Checks that a section with the given `id` may follow the non-custom section `last`.
+///
+/// Custom sections may appear anywhere while every other section must appear at most once and in
+/// the canonical [order].
+pub(crate) fn check_order(last: Option<u8>, id: u8) -> Result<(), ParseErrorKind> {
+    match (last.and_then(order), order(id)) {
+        (Some(last), Some(next)) if last == next => Err(ParseErrorKind::DuplicateSection(id)),
+        (Some(last), Some(next)) if last > next => Err(ParseErrorKind::OutOfOrderSection(id)),
+        _ => Ok(()),
+    }
+}
+
+/// Reads the id and the size of the next section, and consumes its contents.
+pub(crate) fn read_section<'a>(
+    data: &mut ParsingData<'a>,
+) -> Result<(u8, usize, ParsingData<'a>), ParseError> {
+    let n = Byte::parse(data)?;
+    let size = *U32::parse(data)? as usize;
+    if size > data.len() {
+        return Err(ParseError::eof(data).with_context(ErrorContext::Section(*n)));
+    }
+    Ok((*n, size, data.consume(size)))
+}
+
+/// Checks that the contents of a section of `size` bytes were entirely parsed, with `bytes` the
+/// remaining contents. The contents of custom sections are opaque so they aren't checked.
+pub(crate) fn check_size(id: u8, size: usize, bytes: &ParsingData) -> Result<(), ParseError> {
+    if id != 0 && !bytes.is_empty() {
+        let kind = ParseErrorKind::SectionSizeMismatch {
+            expected: size,
+            actual: size - bytes.len(),
+        };
+        return Err(ParseError::new(bytes, kind).with_context(ErrorContext::Section(id)));
+    }
+    Ok(())
+}
+
 impl Parse for Section {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let n = Byte::parse(data)?;
-        let size = *U32::parse(data)? as usize;
-        let mut bytes = data.consume(size);
+        let id = data.read(1);
+        let (n, size, mut bytes) = read_section(data)?;
 
-        Ok(match *n {
-            0 => Section::Custom(
-                custom::CustomSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse custom section."))?,
-            ),
+        let section = match n {
+            0 => {
+                let custom = custom::CustomSection::parse(&mut bytes)
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?;
+                if custom.name.name == names::NAME_SECTION {
+                    let names = NameSection::parse(&mut bytes)
+                        .map_err(|err| err.with_context(ErrorContext::Section(n)));
+                    Section::Name(custom, names)
+                } else {
+                    Section::Custom(custom)
+                }
+            }
             1 => Section::Type(
                 types::TypeSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse type section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             2 => Section::Import(
                 import::ImportSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse import section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             3 => Section::Function(
                 func::FuncSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse function section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             4 => Section::Table(
                 table::TableSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse table section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             5 => Section::Memory(
                 mem::MemSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse memory section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             6 => Section::Global(
                 global::GlobalSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse global section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             7 => Section::Export(
                 export::ExportSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse export section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             8 => Section::Start(
                 start::StartSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse start section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             9 => Section::Element(
                 elem::ElemSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse element section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             10 => Section::Code(
                 code::CodeSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse code section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             11 => Section::Data(
                 data::DataSection::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse data section."))?,
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
             12 => Section::DataCount(
-                U32::parse(&mut bytes)
-                    .map_err(|err| err.extend("Can't parse data count section."))?,
+                U32::parse(&mut bytes).map_err(|err| err.with_context(ErrorContext::Section(n)))?,
             ),
-            n => {
-                return Err(ParseError::new(
-                    data,
-                    format!("Unknown section number {}", n),
-                ))
-            }
-        })
+            13 => Section::Tag(
+                tag::TagSection::parse(&mut bytes)
+                    .map_err(|err| err.with_context(ErrorContext::Section(n)))?,
+            ),
+            n => return Err(ParseError::new(&id, ParseErrorKind::UnknownSection(n))),
+        };
+        check_size(n, size, &bytes)?;
+        Ok(section)
     }
 }
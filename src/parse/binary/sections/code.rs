@@ -1,8 +1,13 @@
-use crate::wasm::instr::Expr;
+use std::ops::Range;
+
+use crate::wasm::instr::{AggregateInstr, ControlInstr, Expr, Instr, MemoryInstr, TryHandler};
 use crate::wasm::types::ValType;
 use crate::wasm::values::U32;
 
-use crate::parse::binary::{Consume, Parse, ParseError, ParsingData};
+use crate::parse::binary::instr::parse_spanned_expr;
+use crate::parse::binary::{
+    Consume, ErrorContext, Parse, ParseBorrowed, ParseError, ParseErrorKind, ParsingData,
+};
 
 #[derive(Debug, Default)]
 pub struct CodeSection {
@@ -15,42 +20,237 @@ pub struct Func {
     pub code: Expr,
 }
 
-struct Locals {
+/// Entry of the code section whose instructions are only decoded on demand, see [LazyFunc::body].
+#[derive(Debug, Clone)]
+pub struct LazyFunc<'a> {
     pub locals: Vec<ValType>,
+    /// Encoded instructions of the body, up to and including the final `end`.
+    pub code: ParsingData<'a>,
+    /// Size of the whole entry, locals included.
+    pub size: usize,
 }
 
+/// Maximum number of locals a function body may declare.
+///
+/// Parameters don't count towards the limit: the body doesn't know the type of its function.
+/// The specification leaves this up to the implementation. This is the limit used by web engines
+/// and keeps hostile inputs from declaring billions of locals in a handful of bytes.
+pub const MAX_LOCALS: u64 = 50_000;
+
+struct Locals {
+    n: u32,
+    tpe: ValType,
+}
+
+/// Errors within a function body are tagged with [ErrorContext::Func] holding the position of the
+/// body within the section. The module parser shifts it by the number of imported functions.
+///
+/// The section is split into bodies using their sizes before they're decoded, one after the other
+/// or across threads with the `rayon` feature. The result, errors included, is the same as when
+/// splitting and decoding each body in turn: a body that can't be split only gets reported if all
+/// the bodies before it decode.
 impl Parse for CodeSection {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        Ok(CodeSection {
-            code: Vec::parse(data)?,
-        })
+        let (funcs, error) = split(data)?;
+        let code = decode(funcs)?;
+        match error {
+            Some(err) => Err(err),
+            None => Ok(CodeSection { code }),
+        }
+    }
+}
+
+/// Parses the code section without decoding the function bodies. Errors are tagged as in
+/// [CodeSection::parse].
+pub fn parse_lazy<'a>(data: &mut ParsingData<'a>) -> Result<Vec<LazyFunc<'a>>, ParseError> {
+    match split(data)? {
+        (code, None) => Ok(code),
+        (_, Some(err)) => Err(err),
     }
 }
 
+/// Splits the section into bodies, stopping at the first one that can't be split. Its error is
+/// returned along with the bodies before it.
+fn split<'a>(
+    data: &mut ParsingData<'a>,
+) -> Result<(Vec<LazyFunc<'a>>, Option<ParseError>), ParseError> {
+    let n = *U32::parse(data)?;
+    let mut code = Vec::with_capacity(std::cmp::min(n as usize, data.len()));
+    for i in 0..n {
+        match LazyFunc::parse_borrowed(data) {
+            Ok(func) => code.push(func),
+            Err(err) => return Ok((code, Some(err.with_context(ErrorContext::Func(i))))),
+        }
+    }
+    Ok((code, None))
+}
+
+#[cfg(not(feature = "rayon"))]
+fn decode(funcs: Vec<LazyFunc>) -> Result<Vec<Func>, ParseError> {
+    funcs.into_iter().enumerate().map(decode_func).collect()
+}
+
+#[cfg(feature = "rayon")]
+fn decode(funcs: Vec<LazyFunc>) -> Result<Vec<Func>, ParseError> {
+    use rayon::prelude::*;
+
+    let code: Vec<Result<Func, ParseError>> =
+        funcs.into_par_iter().enumerate().map(decode_func).collect();
+    code.into_iter().collect()
+}
+
+fn decode_func((i, func): (usize, LazyFunc)) -> Result<Func, ParseError> {
+    let code = func
+        .body()
+        .map_err(|err| err.with_context(ErrorContext::Func(i as u32)))?;
+    Ok(Func {
+        locals: func.locals,
+        code,
+    })
+}
+
 impl Parse for Func {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let func = LazyFunc::parse_borrowed(data)?;
+        Ok(Func {
+            code: func.body()?,
+            locals: func.locals,
+        })
+    }
+}
+
+/// Only reads the size and the locals of the entry, leaving the instructions to [LazyFunc::body].
+impl<'a> ParseBorrowed<'a> for LazyFunc<'a> {
+    fn parse_borrowed(data: &mut ParsingData<'a>) -> Result<Self, ParseError> {
         let size = *U32::parse(data)? as usize;
-        let mut data = data.consume(size);
-        let l: Vec<Locals> = Vec::parse(&mut data)?;
-        let mut locals = Vec::new();
+        if size > data.len() {
+            return Err(ParseError::eof(data));
+        }
+        let mut code = data.consume(size);
+        let l: Vec<Locals> = Vec::parse(&mut code)?;
+        let count: u64 = l.iter().map(|local| u64::from(local.n)).sum();
+        if count > MAX_LOCALS {
+            return Err(ParseError::new(&code, ParseErrorKind::TooManyLocals(count)));
+        }
+        let mut locals = Vec::with_capacity(count as usize);
         for local in l {
-            for tpe in local.locals {
-                locals.push(tpe.clone());
-            }
+            locals.resize(locals.len() + local.n as usize, local.tpe);
         }
-        let code = Expr::parse(&mut data)?;
-        Ok(Func { locals, code })
+        Ok(LazyFunc { locals, code, size })
+    }
+}
+
+impl LazyFunc<'_> {
+    /// Decodes the instructions of the body, which must span the rest of the entry.
+    pub fn body(&self) -> Result<Expr, ParseError> {
+        self.decode(Expr::parse)
+    }
+
+    /// Decodes the body like [LazyFunc::body], pushing the byte range of each instruction to
+    /// `spans`.
+    pub(crate) fn spanned_body(&self, spans: &mut Vec<Range<usize>>) -> Result<Expr, ParseError> {
+        self.decode(|data| parse_spanned_expr(data, spans))
     }
+
+    fn decode(
+        &self,
+        parse: impl FnOnce(&mut ParsingData) -> Result<Expr, ParseError>,
+    ) -> Result<Expr, ParseError> {
+        let mut data = self.code.clone();
+        let code = parse(&mut data)?;
+        if !data.is_empty() {
+            let kind = ParseErrorKind::SectionSizeMismatch {
+                expected: self.size,
+                actual: self.size - data.len(),
+            };
+            return Err(ParseError::new(&data, kind));
+        }
+        Ok(code)
+    }
+}
+
+/// Returns true if any of the instructions requires the data count section to be present.
+pub(crate) fn uses_data_count(instrs: &[Instr]) -> bool {
+    instrs.iter().any(|instr| match instr {
+        Instr::Memory(MemoryInstr::MemoryInit(..))
+        | Instr::Memory(MemoryInstr::DataDrop(_))
+        | Instr::Aggregate(AggregateInstr::ArrayNewData(..))
+        | Instr::Aggregate(AggregateInstr::ArrayInitData(..)) => true,
+        Instr::Control(ControlInstr::Block(block)) | Instr::Control(ControlInstr::Loop(block)) => {
+            uses_data_count(&block.instr)
+        }
+        Instr::Control(ControlInstr::If(block)) => {
+            uses_data_count(&block.if_br) || uses_data_count(&block.else_br)
+        }
+        Instr::Control(ControlInstr::Try(block)) => {
+            uses_data_count(&block.instr)
+                || match &block.handler {
+                    TryHandler::Catch { catches, catch_all } => {
+                        catches.iter().any(|catch| uses_data_count(&catch.instr))
+                            || catch_all.as_deref().map_or(false, uses_data_count)
+                    }
+                    TryHandler::Delegate(_) => false,
+                }
+        }
+        Instr::Control(ControlInstr::TryTable(block)) => uses_data_count(&block.instr),
+        _ => false,
+    })
 }
 
 impl Parse for Locals {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let n = *U32::parse(data)? as usize;
-        let t = ValType::parse(data)?;
-        let mut locals = Vec::with_capacity(n);
-        for _ in 0..n {
-            locals.push(t.clone());
-        }
-        Ok(Locals { locals })
+        let n = *U32::parse(data)?;
+        let tpe = ValType::parse(data)?;
+        Ok(Locals { n, tpe })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::WasmBinary;
+
+    fn parse(bytes: &[u8]) -> Result<CodeSection, ParseError> {
+        let wasm: WasmBinary = bytes.into();
+        CodeSection::parse(&mut ParsingData::new(&wasm))
+    }
+
+    #[test]
+    fn bodies_in_order() {
+        let code = parse(&[
+            0x03, // 3 bodies
+            0x02, 0x00, 0x0B, // No locals, no instructions
+            0x05, 0x01, 0x02, 0x7E, 0x01, 0x0B, // 2 i64 locals, nop
+            0x04, 0x00, 0x41, 0x2A, 0x0B, // i32.const 42
+        ])
+        .unwrap();
+        let locals: Vec<usize> = code.code.iter().map(|func| func.locals.len()).collect();
+        assert_eq!(locals, [0, 2, 0]);
+        let instrs: Vec<usize> = code.code.iter().map(|func| func.code.instr.len()).collect();
+        assert_eq!(instrs, [0, 1, 1]);
+    }
+
+    #[test]
+    fn first_error() {
+        // The first body has an unknown instruction and the second can't even be split from the
+        // rest of the section. The first error is reported.
+        let err = parse(&[
+            0x02, // 2 bodies
+            0x03, 0x00, 0xFF, 0x0B, // Unknown 0xFF instruction
+            0x10, 0x00, 0x0B, // Body larger than the section
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::UnknownOpcode {
+                prefix: None,
+                opcode: 0xFF
+            }
+        );
+        assert_eq!(err.func(), Some(0));
+
+        let err = parse(&[0x02, 0x02, 0x00, 0x0B, 0x10, 0x00, 0x0B]).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.func(), Some(1));
     }
 }
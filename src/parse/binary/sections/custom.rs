@@ -1,19 +1,43 @@
-use crate::parse::binary::{Consume, Parse, ParseError, ParsingData};
+use crate::parse::binary::{Consume, Parse, ParseBorrowed, ParseError, ParsingData};
+use crate::wasm::custom::BorrowedCustomSection;
 use crate::wasm::values::Name;
 
 #[derive(Debug)]
 pub struct CustomSection {
     pub name: Name,
     pub data: Vec<u8>,
+    /// Id of the last non-custom section preceding this one, or `None` if it comes before all of
+    /// them. This lets the encoder put the section back where it was found.
+    pub after: Option<u8>,
+}
+
+impl From<BorrowedCustomSection<'_>> for CustomSection {
+    fn from(from: BorrowedCustomSection) -> CustomSection {
+        CustomSection {
+            name: Name {
+                name: from.name.to_string(),
+            },
+            data: from.data.to_vec(),
+            after: None,
+        }
+    }
 }
 
 impl Parse for CustomSection {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let name =
-            Name::parse(data).map_err(|err| err.extend("Can't parse custom section name"))?;
-        Ok(CustomSection {
-            name: name,
-            data: (*data.read(..)).to_owned(),
+        Ok(BorrowedCustomSection::parse_borrowed(data)?.into())
+    }
+}
+
+/// Parses the name of the section and takes the rest of the data as its contents, without
+/// consuming it so that known sections such as the name section can be decoded further.
+impl<'a> ParseBorrowed<'a> for BorrowedCustomSection<'a> {
+    fn parse_borrowed(data: &mut ParsingData<'a>) -> Result<Self, ParseError> {
+        let name = <&str>::parse_borrowed(data)
+            .map_err(|err| err.extend("Can't parse custom section name"))?;
+        Ok(BorrowedCustomSection {
+            name,
+            data: data.read(..).bytes(),
         })
     }
 }
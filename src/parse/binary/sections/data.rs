@@ -1,8 +1,7 @@
-use crate::parse::binary::{Consume, Parse, ParseError, ParsingData};
-use crate::wasm::data::{Data, DataMode};
+use crate::parse::binary::{Parse, ParseBorrowed, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::data::{BorrowedData, Data, DataMode};
 use crate::wasm::indices::MemIdx;
 use crate::wasm::instr::Expr;
-use crate::wasm::values::Byte;
 use crate::wasm::values::U32;
 
 #[derive(Debug, Default)]
@@ -20,50 +19,39 @@ impl Parse for DataSection {
 
 impl Parse for Data {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't decode Data. Buffer too small.".to_string(),
-            ));
-        }
-        let d = match data.consume(()) {
-            0x00 => {
-                let e = Expr::parse(data)?;
-                let b: Vec<Byte> = Vec::parse(data)?;
-                Data {
-                    init: b,
-                    mode: DataMode::Active {
-                        memory: MemIdx(U32(0)),
-                        offset: e,
-                    },
-                }
-            }
-            0x01 => {
-                let b: Vec<Byte> = Vec::parse(data)?;
-                Data {
-                    init: b,
-                    mode: DataMode::Passive,
-                }
-            }
+        Ok(BorrowedData::parse_borrowed(data)?.into_owned())
+    }
+}
+
+impl<'a> ParseBorrowed<'a> for BorrowedData<'a> {
+    fn parse_borrowed(data: &mut ParsingData<'a>) -> Result<Self, ParseError> {
+        let flags = *U32::parse(data).map_err(|err| err.extend("Can't parse data flags."))?;
+        let mode = match flags {
+            0x00 => DataMode::Active {
+                memory: MemIdx(U32(0)),
+                offset: Expr::parse(data)?,
+            },
+            0x01 => DataMode::Passive,
             0x02 => {
-                let x = MemIdx::parse(data)?;
-                let e = Expr::parse(data)?;
-                let b: Vec<Byte> = Vec::parse(data)?;
-                Data {
-                    init: b,
-                    mode: DataMode::Active {
-                        memory: x,
-                        offset: e,
-                    },
+                let memory = MemIdx::parse(data)?;
+                DataMode::Active {
+                    memory,
+                    offset: Expr::parse(data)?,
                 }
             }
             x => {
                 return Err(ParseError::new(
                     data,
-                    format!("Unknown data option: {:X}", x),
+                    ParseErrorKind::InvalidEncoding {
+                        what: "data segment flags",
+                        value: x,
+                    },
                 ))
             }
         };
-        Ok(d)
+        Ok(BorrowedData {
+            init: <&[u8]>::parse_borrowed(data)?,
+            mode,
+        })
     }
 }
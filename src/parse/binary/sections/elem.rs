@@ -1,6 +1,9 @@
-use crate::parse::binary::{Parse, ParseError, ParsingData};
+use crate::parse::binary::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
 use crate::wasm::elem::{Elem, ElemMode};
+use crate::wasm::indices::{FuncIdx, TableIdx};
+use crate::wasm::instr::{Expr, Instr, ReferenceInstr};
 use crate::wasm::types::RefType;
+use crate::wasm::values::U32;
 
 #[derive(Debug, Default)]
 pub struct ElemSection {
@@ -14,37 +17,278 @@ impl Parse for ElemSection {
     }
 }
 
+/// Parses an `elemkind`. The only kind currently defined by the specification is `funcref`.
+fn parse_elemkind(data: &mut ParsingData) -> Result<RefType, ParseError> {
+    match data.consume(())? {
+        0x00 => Ok(RefType::FUNCREF),
+        x => Err(ParseError::new(
+            data,
+            ParseErrorKind::InvalidEncoding {
+                what: "element kind",
+                value: x.into(),
+            },
+        )),
+    }
+}
+
+/// Parses a vector of function indices into their equivalent `ref.func` initializer expressions.
+fn parse_func_init(data: &mut ParsingData) -> Result<Vec<Expr>, ParseError> {
+    let funcs: Vec<FuncIdx> =
+        Vec::parse(data).map_err(|err| err.extend("Can't parse element function indices."))?;
+    Ok(funcs
+        .into_iter()
+        .map(|func| Expr {
+            instr: vec![Instr::Reference(ReferenceInstr::RefFunc(func))],
+        })
+        .collect())
+}
+
+/// Parses a vector of initializer expressions.
+fn parse_expr_init(data: &mut ParsingData) -> Result<Vec<Expr>, ParseError> {
+    Vec::parse(data).map_err(|err| err.extend("Can't parse element expressions."))
+}
+
 impl Parse for Elem {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't parse element. Buffer too small.".to_string(),
-            ));
-        }
-        // TODO: Do this
-        /*
-        let elem = match data.consume(()) {
-            0x00 => {
-                let expr = Expr::parse(data)?;
-                let y: Vec<FuncIdx> = Vec::parse(data)?;
-                // TODO: Fix expr.
-                Elem {
-                    tpe: RefType::FuncRef,
-                    init: Vec::new(),
+        let flags = *U32::parse(data).map_err(|err| err.extend("Can't parse element flags."))?;
+        let elem = match flags {
+            0 => {
+                let offset = Expr::parse(data)?;
+                let init = parse_func_init(data)?;
+                Elem {
+                    tpe: RefType::FUNCREF,
+                    init,
                     mode: ElemMode::Active {
                         table: TableIdx(U32(0)),
-                        offset: expr,
+                        offset,
                     },
                 }
             }
-        };
-        */
-        let elem = Elem {
-            tpe: RefType::FuncRef,
-            init: Vec::new(),
-            mode: ElemMode::Passive,
+            1 => {
+                let tpe = parse_elemkind(data)?;
+                let init = parse_func_init(data)?;
+                Elem {
+                    tpe,
+                    init,
+                    mode: ElemMode::Passive,
+                }
+            }
+            2 => {
+                let table = TableIdx::parse(data)?;
+                let offset = Expr::parse(data)?;
+                let tpe = parse_elemkind(data)?;
+                let init = parse_func_init(data)?;
+                Elem {
+                    tpe,
+                    init,
+                    mode: ElemMode::Active { table, offset },
+                }
+            }
+            3 => {
+                let tpe = parse_elemkind(data)?;
+                let init = parse_func_init(data)?;
+                Elem {
+                    tpe,
+                    init,
+                    mode: ElemMode::Declarative,
+                }
+            }
+            4 => {
+                let offset = Expr::parse(data)?;
+                let init = parse_expr_init(data)?;
+                Elem {
+                    tpe: RefType::FUNCREF,
+                    init,
+                    mode: ElemMode::Active {
+                        table: TableIdx(U32(0)),
+                        offset,
+                    },
+                }
+            }
+            5 => {
+                let tpe = RefType::parse(data)?;
+                let init = parse_expr_init(data)?;
+                Elem {
+                    tpe,
+                    init,
+                    mode: ElemMode::Passive,
+                }
+            }
+            6 => {
+                let table = TableIdx::parse(data)?;
+                let offset = Expr::parse(data)?;
+                let tpe = RefType::parse(data)?;
+                let init = parse_expr_init(data)?;
+                Elem {
+                    tpe,
+                    init,
+                    mode: ElemMode::Active { table, offset },
+                }
+            }
+            7 => {
+                let tpe = RefType::parse(data)?;
+                let init = parse_expr_init(data)?;
+                Elem {
+                    tpe,
+                    init,
+                    mode: ElemMode::Declarative,
+                }
+            }
+            x => {
+                return Err(ParseError::new(
+                    data,
+                    ParseErrorKind::InvalidEncoding {
+                        what: "element segment flags",
+                        value: x,
+                    },
+                ))
+            }
         };
         Ok(elem)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::WasmBinary;
+    use crate::wasm::instr::NumericInstr;
+    use crate::wasm::types::HeapType;
+    use crate::wasm::values::I32;
+
+    fn parse(bytes: &[u8]) -> Result<Elem, ParseError> {
+        let wasm: WasmBinary = bytes.into();
+        let mut wasm = ParsingData::new(&wasm);
+        let elem = Elem::parse(&mut wasm);
+        // We consumed the input.
+        assert!(elem.is_err() || wasm.is_empty());
+        elem
+    }
+
+    fn is_ref_func(expr: &Expr, idx: u32) -> bool {
+        matches!(
+            expr.instr.as_slice(),
+            [Instr::Reference(ReferenceInstr::RefFunc(FuncIdx(U32(x))))] if *x == idx
+        )
+    }
+
+    fn is_i32_const(expr: &Expr, value: u32) -> bool {
+        matches!(
+            expr.instr.as_slice(),
+            [Instr::Numeric(NumericInstr::I32Const(I32(x)))] if *x == value
+        )
+    }
+
+    #[test]
+    fn active_implicit_table_funcs() {
+        // offset: i32.const 1, funcs: [2, 3]
+        let elem = parse(&[0x00, 0x41, 0x01, 0x0B, 0x02, 0x02, 0x03]).unwrap();
+        assert_eq!(elem.tpe, RefType::FUNCREF);
+        assert_matches!(
+            &elem.mode,
+            ElemMode::Active { table: TableIdx(U32(0)), offset } if is_i32_const(offset, 1)
+        );
+        assert_eq!(elem.init.len(), 2);
+        assert!(is_ref_func(&elem.init[0], 2));
+        assert!(is_ref_func(&elem.init[1], 3));
+    }
+
+    #[test]
+    fn passive_funcs() {
+        let elem = parse(&[0x01, 0x00, 0x01, 0x05]).unwrap();
+        assert_eq!(elem.tpe, RefType::FUNCREF);
+        assert_matches!(elem.mode, ElemMode::Passive);
+        assert_eq!(elem.init.len(), 1);
+        assert!(is_ref_func(&elem.init[0], 5));
+    }
+
+    #[test]
+    fn active_explicit_table_funcs() {
+        // table: 1, offset: i32.const 0, elemkind: funcref, funcs: [7]
+        let elem = parse(&[0x02, 0x01, 0x41, 0x00, 0x0B, 0x00, 0x01, 0x07]).unwrap();
+        assert_eq!(elem.tpe, RefType::FUNCREF);
+        assert_matches!(
+            &elem.mode,
+            ElemMode::Active { table: TableIdx(U32(1)), offset } if is_i32_const(offset, 0)
+        );
+        assert_eq!(elem.init.len(), 1);
+        assert!(is_ref_func(&elem.init[0], 7));
+    }
+
+    #[test]
+    fn declarative_funcs() {
+        let elem = parse(&[0x03, 0x00, 0x02, 0x00, 0x01]).unwrap();
+        assert_eq!(elem.tpe, RefType::FUNCREF);
+        assert_matches!(elem.mode, ElemMode::Declarative);
+        assert_eq!(elem.init.len(), 2);
+        assert!(is_ref_func(&elem.init[0], 0));
+        assert!(is_ref_func(&elem.init[1], 1));
+    }
+
+    #[test]
+    fn active_implicit_table_exprs() {
+        // offset: i32.const 2, exprs: [ref.func 4, ref.null func]
+        let elem = parse(&[
+            0x04, 0x41, 0x02, 0x0B, 0x02, 0xD2, 0x04, 0x0B, 0xD0, 0x70, 0x0B,
+        ])
+        .unwrap();
+        assert_eq!(elem.tpe, RefType::FUNCREF);
+        assert_matches!(
+            &elem.mode,
+            ElemMode::Active { table: TableIdx(U32(0)), offset } if is_i32_const(offset, 2)
+        );
+        assert_eq!(elem.init.len(), 2);
+        assert!(is_ref_func(&elem.init[0], 4));
+        assert_matches!(
+            elem.init[1].instr.as_slice(),
+            [Instr::Reference(ReferenceInstr::RefNull(HeapType::Func))]
+        );
+    }
+
+    #[test]
+    fn passive_exprs() {
+        // reftype: externref, exprs: [ref.null extern]
+        let elem = parse(&[0x05, 0x6F, 0x01, 0xD0, 0x6F, 0x0B]).unwrap();
+        assert_eq!(elem.tpe, RefType::EXTERNREF);
+        assert_matches!(elem.mode, ElemMode::Passive);
+        assert_eq!(elem.init.len(), 1);
+    }
+
+    #[test]
+    fn active_explicit_table_exprs() {
+        // table: 2, offset: i32.const 3, reftype: funcref, exprs: [ref.func 1]
+        let elem = parse(&[0x06, 0x02, 0x41, 0x03, 0x0B, 0x70, 0x01, 0xD2, 0x01, 0x0B]).unwrap();
+        assert_eq!(elem.tpe, RefType::FUNCREF);
+        assert_matches!(
+            &elem.mode,
+            ElemMode::Active { table: TableIdx(U32(2)), offset } if is_i32_const(offset, 3)
+        );
+        assert_eq!(elem.init.len(), 1);
+        assert!(is_ref_func(&elem.init[0], 1));
+    }
+
+    #[test]
+    fn declarative_exprs() {
+        let elem = parse(&[0x07, 0x70, 0x01, 0xD2, 0x09, 0x0B]).unwrap();
+        assert_eq!(elem.tpe, RefType::FUNCREF);
+        assert_matches!(elem.mode, ElemMode::Declarative);
+        assert_eq!(elem.init.len(), 1);
+        assert!(is_ref_func(&elem.init[0], 9));
+    }
+
+    #[test]
+    fn unknown_flags() {
+        assert_eq!(
+            parse(&[0x08, 0x00]).unwrap_err().kind(),
+            &ParseErrorKind::InvalidEncoding {
+                what: "element segment flags",
+                value: 8
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_elemkind() {
+        assert!(parse(&[0x01, 0x01, 0x00]).is_err());
+    }
+}
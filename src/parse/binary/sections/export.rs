@@ -1,6 +1,6 @@
-use crate::parse::binary::{Consume, Parse, ParseError, ParsingData};
+use crate::parse::binary::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
 use crate::wasm::export::{Export, ExportDesc};
-use crate::wasm::indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx};
+use crate::wasm::indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx, TagIdx};
 use crate::wasm::values::Name;
 
 #[derive(Debug, Default)]
@@ -25,13 +25,7 @@ impl Parse for Export {
 
 impl Parse for ExportDesc {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't read export description. Buffer too small.".to_string(),
-            ));
-        }
-        match data.consume(()) {
+        match data.consume(())? {
             0x00 => {
                 let x = FuncIdx::parse(data)?;
                 Ok(ExportDesc::Func(x))
@@ -48,9 +42,16 @@ impl Parse for ExportDesc {
                 let x = GlobalIdx::parse(data)?;
                 Ok(ExportDesc::Global(x))
             }
+            0x04 => {
+                let x = TagIdx::parse(data)?;
+                Ok(ExportDesc::Tag(x))
+            }
             x => Err(ParseError::new(
                 data,
-                format!("Unknown export description type {:X}", x),
+                ParseErrorKind::InvalidEncoding {
+                    what: "export description",
+                    value: x.into(),
+                },
             )),
         }
     }
@@ -1,8 +1,9 @@
-use crate::parse::binary::{Consume, Parse, ParseError, ParsingData};
+use crate::parse::binary::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
 use crate::wasm::values::Name;
 
 use crate::wasm::import::{Import, ImportDesc};
 use crate::wasm::indices::TypeIdx;
+use crate::wasm::tag::Tag;
 use crate::wasm::types::{GlobalType, MemType, TableType};
 
 #[derive(Debug, Default)]
@@ -28,13 +29,7 @@ impl Parse for Import {
 
 impl Parse for ImportDesc {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Invalid import description. Buffer too small.".to_string(),
-            ));
-        }
-        match data.consume(()) {
+        match data.consume(())? {
             0x00 => {
                 let tpe = TypeIdx::parse(data)?;
                 Ok(ImportDesc::Func(tpe))
@@ -51,10 +46,17 @@ impl Parse for ImportDesc {
                 let tpe = GlobalType::parse(data)?;
                 Ok(ImportDesc::Global(tpe))
             }
+            0x04 => {
+                let tag = Tag::parse(data)?;
+                Ok(ImportDesc::Tag(tag.tpe))
+            }
             x => {
                 return Err(ParseError::new(
                     data,
-                    format!("Invalid Import description value: {:X}", x),
+                    ParseErrorKind::InvalidEncoding {
+                        what: "import description",
+                        value: x.into(),
+                    },
                 ))
             }
         }
@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use crate::parse::binary::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::names::{IndirectNameMap, NameMap, NameSection};
+use crate::wasm::values::{Byte, Name, U32};
+
+/// Name of the custom section holding the [NameSection].
+pub const NAME_SECTION: &str = "name";
+
+impl Parse for NameMap {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let n = *U32::parse(data).map_err(|err| err.extend("Can't parse name map length"))?;
+        let mut names = BTreeMap::new();
+        let mut last: Option<u32> = None;
+        for _ in 0..n {
+            let idx = *U32::parse(data).map_err(|err| err.extend("Can't parse name index"))?;
+            if last.map_or(false, |last| idx <= last) {
+                return Err(ParseError::new(data, ParseErrorKind::UnsortedIndices(idx)));
+            }
+            let name = Name::parse(data).map_err(|err| err.extend("Can't parse name"))?;
+            names.insert(idx, name);
+            last = Some(idx);
+        }
+        Ok(NameMap { names })
+    }
+}
+
+impl Parse for IndirectNameMap {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let n =
+            *U32::parse(data).map_err(|err| err.extend("Can't parse indirect name map length"))?;
+        let mut names = BTreeMap::new();
+        let mut last: Option<u32> = None;
+        for _ in 0..n {
+            let idx = *U32::parse(data).map_err(|err| err.extend("Can't parse name index"))?;
+            if last.map_or(false, |last| idx <= last) {
+                return Err(ParseError::new(data, ParseErrorKind::UnsortedIndices(idx)));
+            }
+            let map = NameMap::parse(data).map_err(|err| err.extend("Can't parse name map"))?;
+            names.insert(idx, map);
+            last = Some(idx);
+        }
+        Ok(IndirectNameMap { names })
+    }
+}
+
+/// Parses the contents of the name section (i.e. the custom section data after its name).
+///
+/// Subsections must appear at most once and in increasing id order. Unknown subsections are
+/// skipped.
+impl Parse for NameSection {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let mut names = NameSection::default();
+        let mut last: Option<u8> = None;
+        while !data.is_empty() {
+            let id = *Byte::parse(data).map_err(|err| err.extend("Can't parse subsection id"))?;
+            if last.map_or(false, |last| id <= last) {
+                let kind = if last == Some(id) {
+                    ParseErrorKind::DuplicateSection(id)
+                } else {
+                    ParseErrorKind::OutOfOrderSection(id)
+                };
+                return Err(ParseError::new(data, kind).extend("Invalid name subsection"));
+            }
+            last = Some(id);
+            let size = *U32::parse(data).map_err(|err| err.extend("Can't parse subsection size"))?
+                as usize;
+            if size > data.len() {
+                return Err(ParseError::eof(data).extend("Name subsection larger than section"));
+            }
+            let mut bytes = data.consume(size);
+            let bytes = &mut bytes;
+            let start = bytes.len();
+            match id {
+                0 => {
+                    names.module = Some(
+                        Name::parse(bytes).map_err(|err| err.extend("Can't parse module name"))?,
+                    )
+                }
+                1 => names.funcs.parse_into(bytes)?,
+                2 => names.locals.parse_into(bytes)?,
+                3 => names.labels.parse_into(bytes)?,
+                4 => names.types.parse_into(bytes)?,
+                5 => names.tables.parse_into(bytes)?,
+                6 => names.mems.parse_into(bytes)?,
+                7 => names.globals.parse_into(bytes)?,
+                8 => names.elems.parse_into(bytes)?,
+                9 => names.datas.parse_into(bytes)?,
+                11 => names.tags.parse_into(bytes)?,
+                _ => {
+                    bytes.consume(..);
+                }
+            }
+            if !bytes.is_empty() {
+                return Err(ParseError::new(
+                    bytes,
+                    ParseErrorKind::SectionSizeMismatch {
+                        expected: size,
+                        actual: start - bytes.len(),
+                    },
+                )
+                .extend("Invalid name subsection"));
+            }
+        }
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::WasmBinary;
+
+    fn parse(bytes: &[u8]) -> Result<NameSection, ParseError> {
+        let wasm: WasmBinary = bytes.into();
+        let mut wasm = ParsingData::new(&wasm);
+        NameSection::parse(&mut wasm)
+    }
+
+    #[test]
+    fn module_and_function_names() {
+        let names = parse(&[
+            // Module name: "m"
+            0x00, 0x02, 0x01, b'm', //
+            // Function names: 0 -> "f", 2 -> "gh"
+            0x01, 0x08, 0x02, 0x00, 0x01, b'f', 0x02, 0x02, b'g', b'h',
+        ])
+        .unwrap();
+        assert_eq!(names.module.unwrap().name, "m");
+        assert_eq!(names.funcs.get(0).unwrap().name, "f");
+        assert_eq!(names.funcs.get(1), None);
+        assert_eq!(names.funcs.get(2).unwrap().name, "gh");
+    }
+
+    #[test]
+    fn local_names() {
+        let names = parse(&[
+            // Local names: func 1 -> {0 -> "a", 1 -> "b"}
+            0x02, 0x09, 0x01, 0x01, 0x02, 0x00, 0x01, b'a', 0x01, 0x01, b'b',
+        ])
+        .unwrap();
+        assert_eq!(names.locals.get(1, 0).unwrap().name, "a");
+        assert_eq!(names.locals.get(1, 1).unwrap().name, "b");
+        assert_eq!(names.locals.get(0, 0), None);
+    }
+
+    #[test]
+    fn extended_names() {
+        let names = parse(&[
+            // Global names: 0 -> "g"
+            0x07, 0x04, 0x01, 0x00, 0x01, b'g', //
+            // Data names: 3 -> "d"
+            0x09, 0x04, 0x01, 0x03, 0x01, b'd',
+        ])
+        .unwrap();
+        assert_eq!(names.globals.get(0).unwrap().name, "g");
+        assert_eq!(names.datas.get(3).unwrap().name, "d");
+    }
+
+    #[test]
+    fn unknown_subsection_is_skipped() {
+        let names = parse(&[0x7F, 0x02, 0xAA, 0xBB]).unwrap();
+        assert_eq!(names, NameSection::default());
+    }
+
+    #[test]
+    fn out_of_order_subsection() {
+        assert_eq!(
+            parse(&[0x01, 0x01, 0x00, 0x00, 0x02, 0x01, b'm'])
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::OutOfOrderSection(0)
+        );
+        assert_eq!(
+            parse(&[0x00, 0x02, 0x01, b'm', 0x00, 0x02, 0x01, b'm'])
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::DuplicateSection(0)
+        );
+    }
+
+    #[test]
+    fn subsection_size_mismatch() {
+        // Module name subsection with a trailing byte.
+        assert_eq!(
+            parse(&[0x00, 0x03, 0x01, b'm', 0x00]).unwrap_err().kind(),
+            &ParseErrorKind::SectionSizeMismatch {
+                expected: 3,
+                actual: 2
+            }
+        );
+        // Module name subsection larger than the remaining data.
+        assert_eq!(
+            parse(&[0x00, 0x05, 0x01, b'm']).unwrap_err().kind(),
+            &ParseErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn unsorted_name_map() {
+        assert_eq!(
+            parse(&[0x01, 0x07, 0x02, 0x01, 0x01, b'a', 0x00, 0x01, b'b'])
+                .unwrap_err()
+                .kind(),
+            &ParseErrorKind::UnsortedIndices(0)
+        );
+    }
+}
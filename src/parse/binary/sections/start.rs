@@ -9,12 +9,8 @@ pub struct StartSection {
 
 impl Parse for StartSection {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.is_empty() {
-            Ok(StartSection { start: None })
-        } else {
-            let start = Start::parse(data)?;
-            Ok(StartSection { start: Some(start) })
-        }
+        let start = Start::parse(data)?;
+        Ok(StartSection { start: Some(start) })
     }
 }
 
@@ -1,4 +1,5 @@
-use crate::parse::binary::{Parse, ParseError, ParsingData};
+use crate::parse::binary::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::instr::Expr;
 use crate::wasm::table::Table;
 use crate::wasm::types::TableType;
 
@@ -14,9 +15,26 @@ impl Parse for TableSection {
     }
 }
 
+/// Tables with an initial value are prefixed by 0x40 0x00.
 impl Parse for Table {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        if data.read(())? != 0x40 {
+            let tpe = TableType::parse(data)?;
+            return Ok(Table { tpe, init: None });
+        }
+        data.consume(())?;
+        let zero = data.consume(())?;
+        if zero != 0x00 {
+            return Err(ParseError::new(
+                data,
+                ParseErrorKind::ZeroByteExpected(zero),
+            ));
+        }
         let tpe = TableType::parse(data)?;
-        Ok(Table { tpe })
+        let init = Expr::parse(data)?;
+        Ok(Table {
+            tpe,
+            init: Some(init),
+        })
     }
 }
@@ -0,0 +1,34 @@
+use crate::parse::binary::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::indices::TypeIdx;
+use crate::wasm::tag::Tag;
+
+#[derive(Debug, Default)]
+pub struct TagSection {
+    pub tags: Vec<Tag>,
+}
+
+impl Parse for TagSection {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let tags = Vec::parse(data)?;
+        Ok(TagSection { tags })
+    }
+}
+
+/// Tags start with an attribute byte, for which only exceptions (`0x00`) are defined.
+impl Parse for Tag {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        match data.consume(())? {
+            0x00 => {
+                let tpe = TypeIdx::parse(data)?;
+                Ok(Tag { tpe })
+            }
+            x => Err(ParseError::new(
+                data,
+                ParseErrorKind::InvalidEncoding {
+                    what: "tag attribute",
+                    value: x.into(),
+                },
+            )),
+        }
+    }
+}
@@ -1,9 +1,9 @@
 use crate::parse::binary::{Parse, ParseError, ParsingData};
-use crate::wasm::types::FuncType;
+use crate::wasm::types::RecType;
 
 #[derive(Debug, Default)]
 pub struct TypeSection {
-    pub types: Vec<FuncType>,
+    pub types: Vec<RecType>,
 }
 
 impl Parse for TypeSection {
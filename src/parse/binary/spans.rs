@@ -0,0 +1,204 @@
+//! Byte ranges of the items of a module within its binary.
+
+use std::ops::Range;
+
+use super::module::parse_module;
+use super::sections::code::{self, CodeSection, LazyFunc};
+use super::sections::{
+    self, data::DataSection, elem::ElemSection, export::ExportSection, global::GlobalSection,
+    import::ImportSection, Section,
+};
+use super::{Consume, ErrorContext, Parse, ParseBorrowed, ParseError, ParsingData};
+use crate::wasm::module::Module;
+use crate::wasm::values::U32;
+
+/// Byte ranges within the binary of the items of a [Module], indexed like the corresponding
+/// vectors of the module. They are kept apart from the module so that it doesn't grow when they
+/// aren't needed, see [Module::parse_with_spans].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Spans {
+    /// Sections in the order they appear along with their id, from the id to the end of the
+    /// contents.
+    pub sections: Vec<(u8, Range<usize>)>,
+    pub imports: Vec<Range<usize>>,
+    pub globals: Vec<Range<usize>>,
+    pub exports: Vec<Range<usize>>,
+    pub elems: Vec<Range<usize>>,
+    pub datas: Vec<Range<usize>>,
+    /// Entries of the code section, from the size of the body to its final `end`.
+    pub funcs: Vec<Range<usize>>,
+    /// Instructions of each function body in the order they appear in the binary, which is the
+    /// pre-order of the nested instructions: a structured instruction comes before the
+    /// instructions it contains and its range extends up to and including its `end`. The final
+    /// `end` of the body has no range.
+    pub instrs: Vec<Vec<Range<usize>>>,
+}
+
+impl Spans {
+    /// Range of the first section with the given `id`.
+    pub fn section(&self, id: u8) -> Option<Range<usize>> {
+        self.sections
+            .iter()
+            .find(|(section, _)| *section == id)
+            .map(|(_, range)| range.clone())
+    }
+}
+
+/// Parses a module like [Module::parse], along with the spans of its items.
+pub(crate) fn parse_spanned_module(data: &mut ParsingData) -> Result<(Module, Spans), ParseError> {
+    let mut spans = Spans::default();
+    let binary = parse_module(data, |data| {
+        let start = data.offset();
+        let id = data.read(())?;
+        let section = match id {
+            2 | 6 | 7 | 9 | 10 | 11 => {
+                let (id, size, mut bytes) = sections::read_section(data)?;
+                let section = parse_spanned_section(id, &mut bytes, &mut spans)
+                    .map_err(|err| err.with_context(ErrorContext::Section(id)))?;
+                sections::check_size(id, size, &bytes)?;
+                section
+            }
+            _ => Section::parse(data)?,
+        };
+        spans.sections.push((id, start..data.offset()));
+        Ok(section)
+    })
+    .map_err(|err| err.extend("Can't parse binary module"))?;
+    Ok((binary.into(), spans))
+}
+
+/// Parses the contents of a section whose items have spans.
+fn parse_spanned_section(
+    id: u8,
+    data: &mut ParsingData,
+    spans: &mut Spans,
+) -> Result<Section, ParseError> {
+    Ok(match id {
+        2 => Section::Import(ImportSection {
+            imports: parse_spanned_vec(data, &mut spans.imports)?,
+        }),
+        6 => Section::Global(GlobalSection {
+            globals: parse_spanned_vec(data, &mut spans.globals)?,
+        }),
+        7 => Section::Export(ExportSection {
+            exports: parse_spanned_vec(data, &mut spans.exports)?,
+        }),
+        9 => Section::Element(ElemSection {
+            seg: parse_spanned_vec(data, &mut spans.elems)?,
+        }),
+        11 => Section::Data(DataSection {
+            data: parse_spanned_vec(data, &mut spans.datas)?,
+        }),
+        _ => Section::Code(parse_spanned_code(data, spans)?),
+    })
+}
+
+/// Parses a vector like [Vec::parse], pushing the range of each element to `spans`.
+fn parse_spanned_vec<T: Parse>(
+    data: &mut ParsingData,
+    spans: &mut Vec<Range<usize>>,
+) -> Result<Vec<T>, ParseError> {
+    let n = U32::parse(data).map_err(|err| err.extend("Couldn't read U32"))?;
+    let mut result = Vec::with_capacity(std::cmp::min(*n as usize, data.len()));
+    for i in 0..*n as usize {
+        let start = data.offset();
+        result.push(T::parse(data).map_err(|err| err.with_context(ErrorContext::Item(i)))?);
+        spans.push(start..data.offset());
+    }
+    Ok(result)
+}
+
+/// Parses the code section like [CodeSection::parse], pushing the ranges of the entries and of
+/// their instructions to `spans`.
+fn parse_spanned_code(
+    data: &mut ParsingData,
+    spans: &mut Spans,
+) -> Result<CodeSection, ParseError> {
+    let n = *U32::parse(data)?;
+    let mut code = Vec::with_capacity(std::cmp::min(n as usize, data.len()));
+    for i in 0..n {
+        let start = data.offset();
+        let mut instrs = Vec::new();
+        let func = LazyFunc::parse_borrowed(data)
+            .and_then(|func| {
+                Ok(code::Func {
+                    code: func.spanned_body(&mut instrs)?,
+                    locals: func.locals,
+                })
+            })
+            .map_err(|err| err.with_context(ErrorContext::Func(i)))?;
+        code.push(func);
+        spans.funcs.push(start..data.offset());
+        spans.instrs.push(instrs);
+    }
+    Ok(CodeSection { code })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::WasmBinary;
+
+    const PREAMBLE: [u8; 8] = [0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+
+    #[test]
+    fn item_spans() {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(&[
+                0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+                0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00,
+                0x00, // Import "m" "f" (func 0)
+                0x03, 0x02, 0x01, 0x00, // Function section: 1 function
+                0x05, 0x03, 0x01, 0x00, 0x01, // Memory section: 1 memory of 1 page
+                0x06, 0x06, 0x01, 0x7F, 0x00, 0x41, 0x2A, 0x0B, // Global: i32 42
+                0x07, 0x05, 0x01, 0x01, b'f', 0x00, 0x01, // Export "f" (func 1)
+                0x0A, 0x0B, 0x01, // Code section: 1 body
+                0x09, 0x00, // Body of func 1
+                0x02, 0x40, 0x41, 0x01, 0x1A, 0x0B, // block (i32.const 1) drop end
+                0x01, 0x0B, // nop end
+                0x0B, 0x05, 0x01, 0x01, 0x02, b'h', b'i', // Data section: passive "hi"
+            ])
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        let (module, spans) = parse_spanned_module(&mut ParsingData::new(&wasm)).unwrap();
+        let ids: Vec<u8> = spans.sections.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [1, 2, 3, 5, 6, 7, 10, 11]);
+        assert_eq!(spans.section(1), Some(8..14));
+        assert_eq!(spans.section(10), Some(47..60));
+        assert_eq!(spans.section(11), Some(60..67));
+        assert_eq!(spans.section(4), None);
+        assert_eq!(spans.imports, [17..23]);
+        assert_eq!(spans.globals, [35..40]);
+        assert_eq!(spans.exports, [43..47]);
+        assert_eq!(spans.datas, [63..67]);
+        assert_eq!(spans.funcs, [50..60]);
+        // The block comes before the instructions it contains.
+        assert_eq!(spans.instrs, [vec![52..58, 54..56, 56..57, 58..59]]);
+
+        let owned = Module::parse(&mut ParsingData::new(&wasm)).unwrap();
+        assert_eq!(format!("{:?}", module), format!("{:?}", owned));
+    }
+
+    #[test]
+    fn same_errors() {
+        let wasm: WasmBinary = PREAMBLE
+            .iter()
+            .chain(&[
+                0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+                0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00,
+                0x00, // Import "m" "f" (func 0)
+                0x03, 0x03, 0x02, 0x00, 0x00, // Function section: 2 functions
+                0x0A, 0x08, 0x02, // Code section: 2 bodies
+                0x02, 0x00, 0x0B, // Body of func 1
+                0x03, 0x00, 0xFC, 0x7F, // Body of func 2: unknown 0xFC instruction
+            ])
+            .copied()
+            .collect::<Vec<u8>>()
+            .into();
+        let err = parse_spanned_module(&mut ParsingData::new(&wasm)).unwrap_err();
+        let expected = Module::parse(&mut ParsingData::new(&wasm)).unwrap_err();
+        assert_eq!(err, expected);
+    }
+}
@@ -0,0 +1,623 @@
+//! Push-based parser for binaries received in chunks.
+
+use super::preamble::Preamble;
+use super::sections::{self, code};
+use super::{Consume, ErrorContext, Parse, ParseBorrowed, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::custom::BorrowedCustomSection;
+use crate::wasm::data::Data;
+use crate::wasm::elem::Elem;
+use crate::wasm::export::Export;
+use crate::wasm::func::Func;
+use crate::wasm::global::Global;
+use crate::wasm::import::{Import, ImportDesc};
+use crate::wasm::indices::TypeIdx;
+use crate::wasm::mem::Mem;
+use crate::wasm::start::Start;
+use crate::wasm::table::Table;
+use crate::wasm::tag::Tag;
+use crate::wasm::types::RecType;
+use crate::wasm::values::U32;
+
+/// Component of a module decoded by the [StreamingParser].
+#[derive(Debug)]
+pub enum Event {
+    Preamble(Preamble),
+    /// Start of a section whose contents span `size` bytes. The items of the section follow.
+    SectionStart {
+        id: u8,
+        size: usize,
+    },
+    Type(RecType),
+    Import(Import),
+    /// Type of a function, from the function section.
+    Function(TypeIdx),
+    Table(Table),
+    Memory(Mem),
+    Tag(Tag),
+    Global(Global),
+    Export(Export),
+    Start(Start),
+    Element(Elem),
+    DataCount(u32),
+    /// Function assembled from its body and the type declared in the function section.
+    FunctionBody(Func),
+    Data(Data),
+    /// Custom section, including the `name` section whose contents are left undecoded.
+    CustomSection {
+        name: String,
+        data: Vec<u8>,
+    },
+    /// End of the module, returned once the input is [finished](StreamingParser::finish).
+    End,
+}
+
+#[derive(Debug)]
+pub enum Progress {
+    Event(Event),
+    /// The data pushed so far ends within an item, which needs at least this many more bytes.
+    NeedMoreData(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Preamble,
+    /// Expecting the header of a section or the end of the module.
+    Section,
+    /// Expecting the number of items of a section spanning `start..end`.
+    Count {
+        id: u8,
+        start: usize,
+        end: usize,
+    },
+    Items {
+        id: u8,
+        start: usize,
+        end: usize,
+        index: u32,
+        count: u32,
+    },
+    Custom {
+        end: usize,
+    },
+    End,
+}
+
+/// Parser which decodes a module as its bytes come in, rather than from a
+/// [WasmBinary](super::WasmBinary) holding all of them.
+///
+/// Chunks of the binary are handed over with [push](Self::push) and the events decoded with
+/// [next_event](Self::next_event) until it asks for more data. Bytes are dropped once decoded, so
+/// only the item being decoded is buffered. The sections are checked as when parsing a
+/// [Module](crate::wasm::module::Module): their order, their sizes and the number of function
+/// bodies and data segments. Errors are located within the whole binary.
+///
+/// ```
+/// use wasm_parse::parse::{Event, Progress, StreamingParser};
+///
+/// let bytes = std::fs::read("wasm-examples/hello.wasm")?;
+/// let mut parser = StreamingParser::new();
+/// let mut funcs = 0;
+/// for chunk in bytes.chunks(16) {
+///     parser.push(chunk);
+///     while let Progress::Event(event) = parser.next_event()? {
+///         if let Event::FunctionBody(_) = event {
+///             funcs += 1;
+///         }
+///     }
+/// }
+/// parser.finish();
+/// assert!(matches!(parser.next_event()?, Progress::Event(Event::End)));
+/// assert!(funcs > 0);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct StreamingParser {
+    buffer: Vec<u8>,
+    /// Position of `buffer` within the binary.
+    base: usize,
+    /// Position of the next item within `buffer`.
+    pos: usize,
+    finished: bool,
+    state: State,
+    last: Option<u8>,
+    imported_funcs: u32,
+    funcs: Vec<TypeIdx>,
+    bodies: usize,
+    /// Whether a body refers to data segments, which requires the data count section.
+    uses_data: bool,
+    data_count: Option<u32>,
+    datas: usize,
+}
+
+impl Default for StreamingParser {
+    fn default() -> Self {
+        StreamingParser::new()
+    }
+}
+
+impl StreamingParser {
+    pub fn new() -> StreamingParser {
+        StreamingParser {
+            buffer: Vec::new(),
+            base: 0,
+            pos: 0,
+            finished: false,
+            state: State::Preamble,
+            last: None,
+            imported_funcs: 0,
+            funcs: Vec::new(),
+            bodies: 0,
+            uses_data: false,
+            data_count: None,
+            datas: 0,
+        }
+    }
+
+    /// Appends the next chunk of the binary.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.drain(..self.pos);
+        self.base += self.pos;
+        self.pos = 0;
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Tells that the whole binary was pushed. A module that ends within an item is then an
+    /// [UnexpectedEof](ParseErrorKind::UnexpectedEof) error rather than a request for more data.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Position within the binary of the next byte to decode.
+    pub fn offset(&self) -> usize {
+        self.base + self.pos
+    }
+
+    /// Decodes the next event, or tells how much more data it needs.
+    pub fn next_event(&mut self) -> Result<Progress, ParseError> {
+        loop {
+            match self.state {
+                State::Preamble => {
+                    let preamble = match self.item(8, Preamble::parse)? {
+                        Some(preamble) => preamble,
+                        None => return Ok(self.need(8)),
+                    };
+                    self.state = State::Section;
+                    return Ok(Progress::Event(Event::Preamble(preamble)));
+                }
+                State::Section if self.available() == self.offset() => {
+                    if !self.finished {
+                        return Ok(self.need(self.offset() + 1));
+                    }
+                    self.check_counts()?;
+                    self.state = State::End;
+                }
+                State::Section => {
+                    let last = self.last;
+                    let header = self.item(usize::MAX, |data| {
+                        let id = data.read(())?;
+                        if id != 0 {
+                            if sections::order(id).is_none() {
+                                return Err(ParseError::new(
+                                    data,
+                                    ParseErrorKind::UnknownSection(id),
+                                ));
+                            }
+                            sections::check_order(last, id)
+                                .map_err(|kind| ParseError::new(data, kind))?;
+                        }
+                        data.seek(1);
+                        let size = *U32::parse(data)? as usize;
+                        Ok((id, size))
+                    })?;
+                    let (id, size) = match header {
+                        Some(header) => header,
+                        None => return Ok(self.need(self.available() + 1)),
+                    };
+                    if id != 0 {
+                        self.last = Some(id);
+                    }
+                    let start = self.offset();
+                    let end = start + size;
+                    self.state = match id {
+                        0 => State::Custom { end },
+                        8 | 12 => State::Items {
+                            id,
+                            start,
+                            end,
+                            index: 0,
+                            count: 1,
+                        },
+                        _ => State::Count { id, start, end },
+                    };
+                    return Ok(Progress::Event(Event::SectionStart { id, size }));
+                }
+                State::Count { id, start, end } => {
+                    let count = self.item(end, U32::parse).map_err(|err| {
+                        err.extend("Couldn't read U32")
+                            .with_context(ErrorContext::Section(id))
+                    })?;
+                    let count = match count {
+                        Some(count) => *count,
+                        None => return Ok(self.need(self.available() + 1)),
+                    };
+                    if id == 10 && count as usize != self.funcs.len() {
+                        let kind = ParseErrorKind::FuncCodeMismatch {
+                            funcs: self.funcs.len(),
+                            bodies: count as usize,
+                        };
+                        let data = ParsingData::at(&[], self.offset());
+                        return Err(ParseError::new(&data, kind));
+                    }
+                    self.state = State::Items {
+                        id,
+                        start,
+                        end,
+                        index: 0,
+                        count,
+                    };
+                }
+                State::Items {
+                    id,
+                    start,
+                    end,
+                    index,
+                    count,
+                } if index == count => {
+                    if self.offset() != end {
+                        let kind = ParseErrorKind::SectionSizeMismatch {
+                            expected: end - start,
+                            actual: self.offset() - start,
+                        };
+                        let data = ParsingData::at(&[], self.offset());
+                        return Err(
+                            ParseError::new(&data, kind).with_context(ErrorContext::Section(id))
+                        );
+                    }
+                    self.state = State::Section;
+                }
+                State::Items {
+                    id,
+                    start,
+                    end,
+                    index,
+                    count,
+                } => {
+                    let event = match self.section_item(id, end, index)? {
+                        Ok(event) => event,
+                        Err(progress) => return Ok(progress),
+                    };
+                    self.state = State::Items {
+                        id,
+                        start,
+                        end,
+                        index: index + 1,
+                        count,
+                    };
+                    return Ok(Progress::Event(event));
+                }
+                State::Custom { end } => {
+                    let custom = self
+                        .item(end, |data| {
+                            if data.len() < end - data.offset() {
+                                return Err(ParseError::eof(data));
+                            }
+                            let custom = BorrowedCustomSection::parse_borrowed(data)?;
+                            data.consume(..);
+                            Ok(Event::CustomSection {
+                                name: custom.name.to_string(),
+                                data: custom.data.to_vec(),
+                            })
+                        })
+                        .map_err(|err| err.with_context(ErrorContext::Section(0)))?;
+                    return Ok(match custom {
+                        Some(custom) => {
+                            self.state = State::Section;
+                            Progress::Event(custom)
+                        }
+                        None => self.need(end),
+                    });
+                }
+                State::End => return Ok(Progress::Event(Event::End)),
+            }
+        }
+    }
+
+    /// Decodes the item at position `index` of the section `id` ending at `end`. The error case of
+    /// the inner result asks for more data.
+    fn section_item(
+        &mut self,
+        id: u8,
+        end: usize,
+        index: u32,
+    ) -> Result<Result<Event, Progress>, ParseError> {
+        let imported_funcs = self.imported_funcs;
+        let context = |err: ParseError| {
+            let err = match id {
+                10 => err.with_context(ErrorContext::Func(index)),
+                8 | 12 => err,
+                _ => err.with_context(ErrorContext::Item(index as usize)),
+            };
+            err.offset_func(imported_funcs)
+                .with_context(ErrorContext::Section(id))
+        };
+        let event = match id {
+            1 => self.item(end, RecType::parse).map(|t| t.map(Event::Type)),
+            2 => self.item(end, Import::parse).map(|t| t.map(Event::Import)),
+            3 => self
+                .item(end, TypeIdx::parse)
+                .map(|t| t.map(Event::Function)),
+            4 => self.item(end, Table::parse).map(|t| t.map(Event::Table)),
+            5 => self.item(end, Mem::parse).map(|t| t.map(Event::Memory)),
+            6 => self.item(end, Global::parse).map(|t| t.map(Event::Global)),
+            7 => self.item(end, Export::parse).map(|t| t.map(Event::Export)),
+            8 => self.item(end, Start::parse).map(|t| t.map(Event::Start)),
+            9 => self.item(end, Elem::parse).map(|t| t.map(Event::Element)),
+            10 => {
+                // The size of the body tells how much data to wait for.
+                let size = self.item(end, |data| {
+                    let mut data = data.read(..);
+                    let before = data.offset();
+                    let size = *U32::parse(&mut data)? as usize;
+                    Ok(data.offset() - before + size)
+                });
+                match size.map_err(context)? {
+                    Some(size) if self.available() < self.offset() + size && !self.finished => {
+                        return Ok(Err(self.need(self.offset() + size)))
+                    }
+                    Some(_) => {}
+                    None => return Ok(Err(self.need(self.available() + 1))),
+                }
+                let index = self.funcs[index as usize];
+                self.item(end, code::Func::parse).map(|func| {
+                    func.map(|func| {
+                        Event::FunctionBody(Func {
+                            index,
+                            locals: func.locals,
+                            body: func.code,
+                        })
+                    })
+                })
+            }
+            11 => self.item(end, Data::parse).map(|t| t.map(Event::Data)),
+            12 => self
+                .item(end, U32::parse)
+                .map(|t| t.map(|count| Event::DataCount(*count))),
+            13 => self.item(end, Tag::parse).map(|t| t.map(Event::Tag)),
+            _ => unreachable!("unknown sections are rejected with their header"),
+        };
+        let event = match event.map_err(context)? {
+            Some(event) => event,
+            None => return Ok(Err(self.need(self.available() + 1))),
+        };
+        match &event {
+            Event::Import(import) if matches!(import.desc, ImportDesc::Func(_)) => {
+                self.imported_funcs += 1
+            }
+            Event::Function(idx) => self.funcs.push(*idx),
+            Event::FunctionBody(func) => {
+                self.bodies += 1;
+                self.uses_data |= code::uses_data_count(&func.body.instr);
+            }
+            Event::DataCount(count) => self.data_count = Some(*count),
+            Event::Data(_) => self.datas += 1,
+            _ => {}
+        }
+        Ok(Ok(event))
+    }
+
+    /// Decodes an item which ends before position `end` with `parse`. Returns `None` if the
+    /// buffered data ends within the item and more of it may come.
+    fn item<T>(
+        &mut self,
+        end: usize,
+        parse: impl FnOnce(&mut ParsingData) -> Result<T, ParseError>,
+    ) -> Result<Option<T>, ParseError> {
+        let available = self.available();
+        let len = std::cmp::min(end, available) - self.offset();
+        let mut data = ParsingData::at(&self.buffer[self.pos..self.pos + len], self.offset());
+        match parse(&mut data) {
+            Ok(item) => {
+                self.pos = data.offset() - self.base;
+                Ok(Some(item))
+            }
+            Err(err)
+                if err.kind() == &ParseErrorKind::UnexpectedEof
+                    && available < end
+                    && !self.finished =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Position within the binary of the end of the data pushed so far.
+    fn available(&self) -> usize {
+        self.base + self.buffer.len()
+    }
+
+    /// Asks for the data up to position `end`.
+    fn need(&self, end: usize) -> Progress {
+        Progress::NeedMoreData(std::cmp::max(end.saturating_sub(self.available()), 1))
+    }
+
+    fn check_counts(&self) -> Result<(), ParseError> {
+        let data = ParsingData::at(&[], self.offset());
+        if self.funcs.len() != self.bodies {
+            let kind = ParseErrorKind::FuncCodeMismatch {
+                funcs: self.funcs.len(),
+                bodies: self.bodies,
+            };
+            return Err(ParseError::new(&data, kind));
+        }
+        if self.uses_data && self.data_count.is_none() {
+            return Err(ParseError::new(&data, ParseErrorKind::DataCountRequired));
+        }
+        match self.data_count {
+            Some(count) if count as usize != self.datas => {
+                let kind = ParseErrorKind::DataCountMismatch {
+                    count,
+                    datas: self.datas,
+                };
+                Err(ParseError::new(&data, kind))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::WasmBinary;
+    use crate::wasm::module::Module;
+
+    const PREAMBLE: [u8; 8] = [0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00];
+
+    /// Pushes `bytes` in chunks of `size` bytes and collects the events.
+    fn events(bytes: &[u8], size: usize) -> Result<Vec<Event>, ParseError> {
+        let mut parser = StreamingParser::new();
+        let mut events = Vec::new();
+        for chunk in bytes.chunks(size) {
+            parser.push(chunk);
+            while let Progress::Event(event) = parser.next_event()? {
+                events.push(event);
+            }
+        }
+        parser.finish();
+        loop {
+            match parser.next_event()? {
+                Progress::Event(Event::End) => return Ok(events),
+                Progress::Event(event) => events.push(event),
+                Progress::NeedMoreData(_) => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_sizes() {
+        let bytes = std::fs::read("wasm-examples/hello.wasm").unwrap();
+        let whole = format!("{:?}", events(&bytes, bytes.len()).unwrap());
+        assert_eq!(format!("{:?}", events(&bytes, 1).unwrap()), whole);
+        assert_eq!(format!("{:?}", events(&bytes, 7).unwrap()), whole);
+
+        let module = Module::parse(&mut ParsingData::new(&bytes.as_slice().into())).unwrap();
+        let funcs: Vec<String> = events(&bytes, 1)
+            .unwrap()
+            .iter()
+            .filter_map(|event| match event {
+                Event::FunctionBody(func) => Some(format!("{:?}", func)),
+                _ => None,
+            })
+            .collect();
+        let expected: Vec<String> = module
+            .funcs
+            .iter()
+            .map(|func| format!("{:?}", func))
+            .collect();
+        assert_eq!(funcs, expected);
+    }
+
+    #[test]
+    fn need_more_data() {
+        let mut parser = StreamingParser::new();
+        parser.push(&PREAMBLE[..3]);
+        assert!(matches!(parser.next_event(), Ok(Progress::NeedMoreData(5))));
+        parser.push(&PREAMBLE[3..]);
+        assert!(matches!(
+            parser.next_event(),
+            Ok(Progress::Event(Event::Preamble(_)))
+        ));
+        parser.push(&[
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+            0x03, 0x02, 0x01, 0x00, // Function section: 1 function
+            0x0A, 0x07, 0x01, // Code section: 1 body
+            0x05, 0x00, 0x41, // Body: i32.const ...
+        ]);
+        let mut events = Vec::new();
+        let hint = loop {
+            match parser.next_event().unwrap() {
+                Progress::Event(event) => events.push(event),
+                Progress::NeedMoreData(hint) => break hint,
+            }
+        };
+        assert_eq!(events.len(), 5);
+        assert!(matches!(events[4], Event::SectionStart { id: 10, size: 7 }));
+        // The size of the body is known, unlike the size of the other items.
+        assert_eq!(hint, 3);
+        parser.push(&[0x2A, 0x1A]);
+        assert!(matches!(parser.next_event(), Ok(Progress::NeedMoreData(1))));
+        parser.push(&[0x0B]);
+        assert!(matches!(
+            parser.next_event(),
+            Ok(Progress::Event(Event::FunctionBody(_)))
+        ));
+        assert!(matches!(parser.next_event(), Ok(Progress::NeedMoreData(1))));
+        parser.finish();
+        assert!(matches!(
+            parser.next_event(),
+            Ok(Progress::Event(Event::End))
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        let module =
+            |sections: &[u8]| -> Vec<u8> { PREAMBLE.iter().chain(sections).copied().collect() };
+        // Errors are the same as when parsing the whole binary.
+        let bytes = module(&[
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+            0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00, 0x00, // Import "m" "f" (func 0)
+            0x03, 0x03, 0x02, 0x00, 0x00, // Function section: 2 functions
+            0x0A, 0x08, 0x02, // Code section: 2 bodies
+            0x02, 0x00, 0x0B, // Body of func 1
+            0x03, 0x00, 0xFC, 0x7F, // Body of func 2: unknown 0xFC instruction
+        ]);
+        let wasm: WasmBinary = bytes.as_slice().into();
+        let expected = Module::parse(&mut ParsingData::new(&wasm)).unwrap_err();
+        for size in [1, 5, bytes.len()] {
+            let err = events(&bytes, size).unwrap_err();
+            assert_eq!(err.kind(), expected.kind());
+            assert_eq!(err.location(), expected.location());
+            assert_eq!(err.func(), Some(2));
+            assert_eq!(err.section(), Some(10));
+        }
+
+        let err = events(&module(&[0x0E, 0x00]), 1).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnknownSection(0x0E));
+        assert_eq!(err.location(), 8);
+
+        let err = events(&module(&[0x03, 0x01, 0x00, 0x01, 0x01, 0x00]), 2).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::OutOfOrderSection(1));
+        assert_eq!(err.location(), 11);
+
+        // The module ends within the type section.
+        let err = events(&module(&[0x01, 0x04, 0x01, 0x60]), 1).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.location(), 12);
+
+        let err = events(&module(&[0x03, 0x02, 0x01, 0x00]), 3).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::FuncCodeMismatch {
+                funcs: 1,
+                bodies: 0
+            }
+        );
+
+        // An empty start section has no function index.
+        let err = events(&module(&[0x08, 0x00]), 1).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.section(), Some(8));
+
+        let err = events(&module(&[0x01, 0x02, 0x00, 0x00]), 3).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ParseErrorKind::SectionSizeMismatch {
+                expected: 2,
+                actual: 1
+            }
+        );
+        assert_eq!(err.section(), Some(1));
+    }
+}
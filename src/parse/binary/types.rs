@@ -1,9 +1,13 @@
-use super::{Consume, Parse, ParseError, ParsingData};
-use crate::wasm::values::U32;
+use std::convert::TryFrom;
+
+use super::{Consume, Parse, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::indices::TypeIdx;
+use crate::wasm::values::{S64, U32, U64};
 
 use crate::wasm::types::{
-    FuncType, GlobalType, Limits, MemType, Mutability, NumType, RefType, ResultType, TableType,
-    ValType,
+    ArrayType, CompType, FieldType, FuncType, GlobalType, HeapType, IndexType, Limits, MemType,
+    Mutability, NumType, RecType, RefType, ResultType, StorageType, StructType, SubType, TableType,
+    ValType, VecType,
 };
 
 /// ```
@@ -17,53 +21,109 @@ use crate::wasm::types::{
 /// ```
 impl Parse for NumType {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't read number type. Buffer to small".to_string(),
-            ));
-        }
-        match data.consume(()) {
+        match data.consume(())? {
             0x7F => Ok(NumType::I32),
             0x7E => Ok(NumType::I64),
             0x7D => Ok(NumType::F32),
             0x7C => Ok(NumType::F64),
             x => Err(ParseError::new(
                 data,
-                format!("Unknown number type {:X?}", x),
+                ParseErrorKind::InvalidEncoding {
+                    what: "number type",
+                    value: x.into(),
+                },
             )),
         }
     }
 }
 
+/// Abstract heap type encoded by a byte. The same byte encodes the nullable reference to it.
+pub(crate) fn abstract_heap_type(byte: u8) -> Option<HeapType> {
+    let heap = match byte {
+        0x70 => HeapType::Func,
+        0x73 => HeapType::NoFunc,
+        0x6F => HeapType::Extern,
+        0x72 => HeapType::NoExtern,
+        0x6E => HeapType::Any,
+        0x6D => HeapType::Eq,
+        0x6C => HeapType::I31,
+        0x6B => HeapType::Struct,
+        0x6A => HeapType::Array,
+        0x71 => HeapType::None,
+        0x69 => HeapType::Exn,
+        0x74 => HeapType::NoExn,
+        _ => return None,
+    };
+    Some(heap)
+}
+
 /// ```
 /// # use wasm_parse::parse::binary::*;
-/// # use wasm_parse::wasm::types::RefType;
-/// let wasm: WasmBinary = [0x6F].as_ref().into();
+/// # use wasm_parse::wasm::indices::TypeIdx;
+/// # use wasm_parse::wasm::types::HeapType;
+/// # use wasm_parse::wasm::values::U32;
+/// let wasm: WasmBinary = [0x6E, 0x03].as_ref().into();
 /// let mut wasm = ParsingData::new(&wasm);
-/// assert_eq!(RefType::parse(&mut wasm).unwrap(), RefType::ExternRef);
-/// // We consumed the input.
+/// assert_eq!(HeapType::parse(&mut wasm).unwrap(), HeapType::Any);
+/// // Concrete heap types are positive signed 33 bit integers.
+/// assert_eq!(
+///     HeapType::parse(&mut wasm).unwrap(),
+///     HeapType::Concrete(TypeIdx(U32(3)))
+/// );
 /// assert!(wasm.is_empty());
 /// ```
-impl Parse for RefType {
+impl Parse for HeapType {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't read reference type. Buffer to small".to_string(),
-            ));
+        let byte = data.read(())?;
+        if let Some(heap) = abstract_heap_type(byte) {
+            data.consume(())?;
+            return Ok(heap);
         }
-        match data.consume(()) {
-            0x70 => Ok(RefType::FuncRef),
-            0x6F => Ok(RefType::ExternRef),
-            x => Err(ParseError::new(
+        match u32::try_from(*S64::parse(data)?) {
+            Ok(idx) => Ok(HeapType::Concrete(TypeIdx(U32(idx)))),
+            Err(_) => Err(ParseError::new(
                 data,
-                format!("Unknown reference type {:X?}", x),
+                ParseErrorKind::InvalidEncoding {
+                    what: "heap type",
+                    value: byte.into(),
+                },
             )),
         }
     }
 }
 
+/// ```
+/// # use wasm_parse::parse::binary::*;
+/// # use wasm_parse::wasm::types::{HeapType, RefType};
+/// let wasm: WasmBinary = [0x6F, 0x64, 0x6D].as_ref().into();
+/// let mut wasm = ParsingData::new(&wasm);
+/// assert_eq!(RefType::parse(&mut wasm).unwrap(), RefType::EXTERNREF);
+/// assert_eq!(
+///     RefType::parse(&mut wasm).unwrap(),
+///     RefType::non_null(HeapType::Eq)
+/// );
+/// // We consumed the input.
+/// assert!(wasm.is_empty());
+/// ```
+impl Parse for RefType {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        match data.consume(())? {
+            0x63 => Ok(RefType::null(HeapType::parse(data)?)),
+            0x64 => Ok(RefType::non_null(HeapType::parse(data)?)),
+            x => match abstract_heap_type(x) {
+                Some(heap) => Ok(RefType::null(heap)),
+                None => Err(ParseError::new(
+                    data,
+                    ParseErrorKind::InvalidEncoding {
+                        what: "reference type",
+                        value: x.into(),
+                    },
+                )),
+            },
+        }
+    }
+}
+
 /// ```
 /// # use wasm_parse::parse::binary::*;
 /// # use wasm_parse::wasm::types::ValType;
@@ -72,31 +132,34 @@ impl Parse for RefType {
 /// let mut wasm = ParsingData::new(&wasm);
 /// assert_eq!(
 ///     ValType::parse(&mut wasm).unwrap(),
-///     ValType::Ref(RefType::ExternRef)
+///     ValType::Ref(RefType::EXTERNREF)
 /// );
 /// // We consumed the input.
 /// assert!(wasm.is_empty());
 /// ```
 impl Parse for ValType {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't read value type. Buffer to small".to_string(),
-            ));
-        }
-        match data.consume(()) {
-            0x7F => Ok(ValType::Num(NumType::I32)),
-            0x7E => Ok(ValType::Num(NumType::I64)),
-            0x7D => Ok(ValType::Num(NumType::F32)),
-            0x7C => Ok(ValType::Num(NumType::F64)),
-            0x70 => Ok(ValType::Ref(RefType::FuncRef)),
-            0x6F => Ok(ValType::Ref(RefType::ExternRef)),
-            x => Err(ParseError::new(
-                data,
-                format!("Unknown value type {:X?}", x),
-            )),
-        }
+        let tpe = match data.read(())? {
+            0x7F => ValType::Num(NumType::I32),
+            0x7E => ValType::Num(NumType::I64),
+            0x7D => ValType::Num(NumType::F32),
+            0x7C => ValType::Num(NumType::F64),
+            0x7B => ValType::Vec(VecType::V128),
+            x if x == 0x63 || x == 0x64 || abstract_heap_type(x).is_some() => {
+                return Ok(ValType::Ref(RefType::parse(data)?));
+            }
+            x => {
+                return Err(ParseError::new(
+                    data,
+                    ParseErrorKind::InvalidEncoding {
+                        what: "value type",
+                        value: x.into(),
+                    },
+                ))
+            }
+        };
+        data.consume(())?;
+        Ok(tpe)
     }
 }
 
@@ -131,20 +194,14 @@ impl Parse for ResultType {
 /// ```
 impl Parse for FuncType {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't read function type. Buffer to small".to_string(),
-            ));
-        }
-        let magic = data.consume(());
+        let magic = data.consume(())?;
         if magic != 0x60u8 {
             return Err(ParseError::new(
                 data,
-                format!(
-                    "Incorrect function magic: Got {:X}, Expected {:X}",
-                    magic, 0x60
-                ),
+                ParseErrorKind::InvalidEncoding {
+                    what: "function type",
+                    value: magic.into(),
+                },
             ));
         }
         let params = ResultType::parse(data)
@@ -155,18 +212,133 @@ impl Parse for FuncType {
     }
 }
 
+/// ```
+/// # use wasm_parse::parse::binary::*;
+/// # use wasm_parse::wasm::types::{Mutability, NumType, StorageType, ValType};
+/// let wasm: WasmBinary = [0x78, 0x7F].as_ref().into();
+/// let mut wasm = ParsingData::new(&wasm);
+/// assert_eq!(StorageType::parse(&mut wasm).unwrap(), StorageType::I8);
+/// assert_eq!(
+///     StorageType::parse(&mut wasm).unwrap(),
+///     StorageType::Val(ValType::Num(NumType::I32))
+/// );
+/// ```
+impl Parse for StorageType {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        match data.read(())? {
+            0x78 => {
+                data.consume(())?;
+                Ok(StorageType::I8)
+            }
+            0x77 => {
+                data.consume(())?;
+                Ok(StorageType::I16)
+            }
+            _ => Ok(StorageType::Val(ValType::parse(data)?)),
+        }
+    }
+}
+
+impl Parse for FieldType {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let tpe = StorageType::parse(data)?;
+        let mutability = Mutability::parse(data)?;
+        Ok(FieldType { tpe, mutability })
+    }
+}
+
+/// ```
+/// # #![feature(assert_matches)]
+/// # use wasm_parse::parse::binary::*;
+/// # use wasm_parse::wasm::types::{ArrayType, CompType, StructType};
+/// // Struct of an i32 and a mutable i8, and array of mutable anyref.
+/// let wasm: WasmBinary = [0x5F, 0x02, 0x7F, 0x00, 0x78, 0x01, 0x5E, 0x6E, 0x01].as_ref().into();
+/// let mut wasm = ParsingData::new(&wasm);
+/// assert_matches!(
+///     CompType::parse(&mut wasm),
+///     Ok(CompType::Struct(StructType { fields })) if fields.len() == 2
+/// );
+/// assert_matches!(CompType::parse(&mut wasm), Ok(CompType::Array(ArrayType { .. })));
+/// assert!(wasm.is_empty());
+/// ```
+impl Parse for CompType {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        match data.read(())? {
+            0x5F => {
+                data.consume(())?;
+                let fields = Vec::parse(data).map_err(|err| err.extend("Can't parse fields"))?;
+                Ok(CompType::Struct(StructType { fields }))
+            }
+            0x5E => {
+                data.consume(())?;
+                let field = FieldType::parse(data)?;
+                Ok(CompType::Array(ArrayType { field }))
+            }
+            _ => Ok(CompType::Func(FuncType::parse(data)?)),
+        }
+    }
+}
+
+/// Type definition: `sub` (0x50) and `sub final` (0x4F) list the supertypes, a bare composite
+/// type is final and has none.
+impl Parse for SubType {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let is_final = match data.read(())? {
+            0x50 => false,
+            0x4F => true,
+            _ => return Ok(SubType::from_comp(CompType::parse(data)?)),
+        };
+        data.consume(())?;
+        let supers = Vec::parse(data).map_err(|err| err.extend("Can't parse supertypes"))?;
+        let comp = CompType::parse(data)?;
+        Ok(SubType {
+            is_final,
+            supers,
+            comp,
+        })
+    }
+}
+
+/// ```
+/// # #![feature(assert_matches)]
+/// # use wasm_parse::parse::binary::*;
+/// # use wasm_parse::wasm::types::{RecType, SubType};
+/// // A group of a function type and a non-final struct type, then a type alone.
+/// let wasm: WasmBinary = [0x4E, 0x02, 0x60, 0x00, 0x00, 0x50, 0x00, 0x5F, 0x00, 0x60, 0x00, 0x00]
+///     .as_ref()
+///     .into();
+/// let mut wasm = ParsingData::new(&wasm);
+/// assert_matches!(
+///     RecType::parse(&mut wasm),
+///     Ok(RecType { types }) if types.len() == 2 && !types[1].is_final
+/// );
+/// assert_matches!(RecType::parse(&mut wasm), Ok(RecType { types }) if types.len() == 1);
+/// assert!(wasm.is_empty());
+/// ```
+impl Parse for RecType {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        let types = if data.read(())? == 0x4E {
+            data.consume(())?;
+            Vec::parse(data).map_err(|err| err.extend("Can't parse recursion group"))?
+        } else {
+            vec![SubType::parse(data)?]
+        };
+        Ok(RecType { types })
+    }
+}
+
 /// ```
 /// # #![feature(assert_matches)]
 /// # use wasm_parse::parse::binary::*;
 /// # use wasm_parse::wasm::types::Limits;
-/// # use wasm_parse::wasm::values::U32;
+/// # use wasm_parse::wasm::values::U64;
 /// let wasm: WasmBinary = [0x01, 0x04, 0x05].as_ref().into();
 /// let mut wasm = ParsingData::new(&wasm);
 /// assert_matches!(
 ///     Limits::parse(&mut wasm),
 ///     Ok(Limits {
-///         min: U32(4),
-///         max: Some(U32(5))
+///         min: U64(4),
+///         max: Some(U64(5))
 ///     })
 /// );
 /// // We consumed the input.
@@ -174,80 +346,165 @@ impl Parse for FuncType {
 /// ```
 impl Parse for Limits {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
+        let flag = data.consume(())?;
+        // 64-bit limits come with the index type of a table or memory, which plain limits don't
+        // have. `TableType` and `MemType` read the flag themselves.
+        if flag & 0x04 != 0 {
             return Err(ParseError::new(
                 data,
-                "Can't read limit type. Buffer to small".to_string(),
+                ParseErrorKind::InvalidEncoding {
+                    what: "limits",
+                    value: flag.into(),
+                },
             ));
         }
-
-        let (min, max) = match data.consume(()) {
-            0x00 => {
-                let min = U32::parse(data).map_err(|err| err.extend("Can't get U32 limit min"))?;
-                (min, None)
-            }
-            0x01 => {
-                let min = U32::parse(data).map_err(|err| err.extend("Can't get U32 limit min"))?;
-                let max = U32::parse(data).map_err(|err| err.extend("Can't get U32 limit max"))?;
-                (min, Some(max))
-            }
-            x => return Err(ParseError::new(data, format!("Can't decode limit {:X}", x))),
-        };
-        Ok(Limits { min, max })
+        limits(data, flag)
     }
 }
 
+/// Parses the bounds of limits whose flag byte was already read. The bounds of 64-bit limits
+/// (flag 0x04) are `U64`, the others `U32`.
+fn limits(data: &mut ParsingData, flag: u8) -> Result<Limits, ParseError> {
+    let bound = |data: &mut ParsingData, flag: u8| -> Result<U64, ParseError> {
+        if flag & 0x04 == 0 {
+            Ok(U64(U32::parse(data)?.0.into()))
+        } else {
+            U64::parse(data)
+        }
+    };
+    let (min, max) = match flag {
+        0x00 | 0x04 => {
+            let min = bound(data, flag).map_err(|err| err.extend("Can't get limit min"))?;
+            (min, None)
+        }
+        0x01 | 0x05 => {
+            let min = bound(data, flag).map_err(|err| err.extend("Can't get limit min"))?;
+            let max = bound(data, flag).map_err(|err| err.extend("Can't get limit max"))?;
+            (min, Some(max))
+        }
+        x => {
+            return Err(ParseError::new(
+                data,
+                ParseErrorKind::InvalidEncoding {
+                    what: "limits",
+                    value: x.into(),
+                },
+            ))
+        }
+    };
+    Ok(Limits { min, max })
+}
+
 /// ```
 /// # #![feature(assert_matches)]
 /// # use wasm_parse::parse::binary::*;
-/// # use wasm_parse::wasm::types::{Limits, MemType};
-/// # use wasm_parse::wasm::values::U32;
+/// # use wasm_parse::wasm::types::{IndexType, Limits, MemType};
+/// # use wasm_parse::wasm::values::U64;
 /// let wasm: WasmBinary = [0x01, 0x04, 0x05].as_ref().into();
 /// let mut wasm = ParsingData::new(&wasm);
 /// assert_matches!(
 ///     MemType::parse(&mut wasm),
 ///     Ok(MemType {
 ///         lim: Limits {
-///             min: U32(4),
-///             max: Some(U32(5))
-///         }
+///             min: U64(4),
+///             max: Some(U64(5))
+///         },
+///         index: IndexType::I32,
+///         shared: false
 ///     })
 /// );
 /// // We consumed the input.
 /// assert!(wasm.is_empty());
+///
+/// // Shared memories set the second bit of the limits flag.
+/// let wasm: WasmBinary = [0x03, 0x01, 0x02].as_ref().into();
+/// let mut wasm = ParsingData::new(&wasm);
+/// assert_matches!(MemType::parse(&mut wasm), Ok(MemType { shared: true, .. }));
+///
+/// // 64-bit memories set the third bit, and their limits don't have to fit in 32 bits.
+/// let wasm: WasmBinary = [0x04, 0x80, 0x80, 0x80, 0x80, 0x10].as_ref().into();
+/// let mut wasm = ParsingData::new(&wasm);
+/// assert_matches!(
+///     MemType::parse(&mut wasm),
+///     Ok(MemType {
+///         lim: Limits {
+///             min: U64(0x1_0000_0000),
+///             max: None
+///         },
+///         index: IndexType::I64,
+///         ..
+///     })
+/// );
 /// ```
 impl Parse for MemType {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let lim = Limits::parse(data).map_err(|err| err.extend("Can't parse limit"))?;
-        Ok(MemType { lim })
+        let flag = data.consume(())?;
+        let lim = limits(data, flag & !0x02).map_err(|err| err.extend("Can't parse limit"))?;
+        let index = if flag & 0x04 != 0 {
+            IndexType::I64
+        } else {
+            IndexType::I32
+        };
+        Ok(MemType {
+            lim,
+            index,
+            shared: flag & 0x02 != 0,
+        })
     }
 }
 
 /// ```
 /// # #![feature(assert_matches)]
 /// # use wasm_parse::parse::binary::*;
-/// # use wasm_parse::wasm::types::{Limits, RefType, TableType};
-/// # use wasm_parse::wasm::values::U32;
+/// # use wasm_parse::wasm::types::{IndexType, Limits, RefType, TableType};
+/// # use wasm_parse::wasm::values::U64;
 /// let wasm: WasmBinary = [0x70, 0x01, 0x04, 0x05].as_ref().into();
 /// let mut wasm = ParsingData::new(&wasm);
 /// assert_matches!(
 ///     TableType::parse(&mut wasm),
 ///     Ok(TableType {
 ///         lim: Limits {
-///             min: U32(4),
-///             max: Some(U32(5))
+///             min: U64(4),
+///             max: Some(U64(5))
 ///         },
-///         tpe: RefType::FuncRef
+///         index: IndexType::I32,
+///         tpe: RefType::FUNCREF
 ///     })
 /// );
 /// // We consumed the input.
 /// assert!(wasm.is_empty());
+///
+/// // Like memories, 64-bit tables set the third bit of the limits flag.
+/// let wasm: WasmBinary = [0x70, 0x04, 0x80, 0x80, 0x80, 0x80, 0x10].as_ref().into();
+/// let mut wasm = ParsingData::new(&wasm);
+/// assert_matches!(
+///     TableType::parse(&mut wasm),
+///     Ok(TableType {
+///         lim: Limits {
+///             min: U64(0x1_0000_0000),
+///             max: None
+///         },
+///         index: IndexType::I64,
+///         ..
+///     })
+/// );
+///
+/// // Tables can't be shared.
+/// let wasm: WasmBinary = [0x70, 0x03, 0x01, 0x02].as_ref().into();
+/// let mut wasm = ParsingData::new(&wasm);
+/// assert!(TableType::parse(&mut wasm).is_err());
 /// ```
 impl Parse for TableType {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         let tpe = RefType::parse(data).map_err(|err| err.extend("Can't parse RefType"))?;
-        let lim = Limits::parse(data).map_err(|err| err.extend("Can't parse limit"))?;
-        Ok(TableType { lim, tpe })
+        let flag = data.consume(())?;
+        let lim = limits(data, flag).map_err(|err| err.extend("Can't parse limit"))?;
+        let index = if flag & 0x04 != 0 {
+            IndexType::I64
+        } else {
+            IndexType::I32
+        };
+        Ok(TableType { lim, index, tpe })
     }
 }
 
@@ -287,19 +544,15 @@ impl Parse for GlobalType {
 /// ```
 impl Parse for Mutability {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't read mutability type. Buffer too small".to_string(),
-            ));
-        }
-
-        match data.consume(()) {
+        match data.consume(())? {
             0x00 => Ok(Mutability::Const),
             0x01 => Ok(Mutability::Mut),
             x => Err(ParseError::new(
                 data,
-                format!("Invalid mutability encoding {:X}", x),
+                ParseErrorKind::InvalidEncoding {
+                    what: "mutability",
+                    value: x.into(),
+                },
             )),
         }
     }
@@ -1,31 +1,42 @@
 use std::convert::TryInto;
 
+use nano_leb128::LEB128DecodeError;
 use nano_leb128::SLEB128;
 use nano_leb128::ULEB128;
 
-use super::{Consume, Parse, ParseError, ParsingData};
-use crate::wasm::values::{Byte, Name, F32, F64, I16, I32, I64, I8, S32, S64, U32, U64};
+use super::{Consume, ErrorContext, Parse, ParseBorrowed, ParseError, ParseErrorKind, ParsingData};
+use crate::wasm::values::{Byte, Name, F32, F64, I16, I32, I64, I8, S32, S64, U32, U64, V128};
+
+fn leb_error(data: &ParsingData, err: LEB128DecodeError) -> ParseError {
+    match err {
+        LEB128DecodeError::BufferOverflow => ParseError::eof(data),
+        LEB128DecodeError::IntegerOverflow => ParseError::new(data, ParseErrorKind::LebOverflow),
+    }
+}
+
+/// Checks that the LEB128 encoding of a `bits` wide integer takes no more bytes than needed for
+/// the widest value. Smaller values may still be padded with `0x80` bytes up to that length.
+fn check_len(data: &ParsingData, len: usize, bits: usize) -> Result<(), ParseError> {
+    if len > (bits + 6) / 7 {
+        return Err(ParseError::new(data, ParseErrorKind::LebOverflow));
+    }
+    Ok(())
+}
 
 impl Parse for Byte {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        if data.len() < 1 {
-            return Err(ParseError::new(
-                data,
-                "Can't decode byte. Buffer too small".to_string(),
-            ));
-        }
-        Ok(Byte(data.consume(())))
+        Ok(Byte(data.consume(())?))
     }
 }
 
 impl Parse for U32 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let (value, len) = ULEB128::read_from(&*data.read(..))
-            .or(Err(ParseError::new(data, "Can't decode u32".to_string())))?;
-        let value = u64::from(value).try_into().or(Err(ParseError::new(
-            data,
-            "Conversion failure. Expected u32, got u64".to_string(),
-        )))?;
+        let (value, len) =
+            ULEB128::read_from(&data.read(..)).map_err(|err| leb_error(data, err))?;
+        check_len(data, len, 32)?;
+        let value = u64::from(value)
+            .try_into()
+            .or(Err(ParseError::new(data, ParseErrorKind::LebOverflow)))?;
         data.consume(len);
         Ok(U32(value))
     }
@@ -33,8 +44,9 @@ impl Parse for U32 {
 
 impl Parse for U64 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let (value, len) = ULEB128::read_from(&data.read(..))
-            .or(Err(ParseError::new(data, "Can't decode u64".to_string())))?;
+        let (value, len) =
+            ULEB128::read_from(&data.read(..)).map_err(|err| leb_error(data, err))?;
+        check_len(data, len, 64)?;
         data.consume(len);
         Ok(U64(value.into()))
     }
@@ -42,12 +54,12 @@ impl Parse for U64 {
 
 impl Parse for S32 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let (value, len) = SLEB128::read_from(&data.read(..))
-            .or(Err(ParseError::new(data, "Can't decode s32".to_string())))?;
-        let value = i64::from(value).try_into().or(Err(ParseError::new(
-            data,
-            "Conversion failure. Expected s32, got s64".to_string(),
-        )))?;
+        let (value, len) =
+            SLEB128::read_from(&data.read(..)).map_err(|err| leb_error(data, err))?;
+        check_len(data, len, 32)?;
+        let value = i64::from(value)
+            .try_into()
+            .or(Err(ParseError::new(data, ParseErrorKind::LebOverflow)))?;
         data.consume(len);
         Ok(S32(value))
     }
@@ -55,8 +67,9 @@ impl Parse for S32 {
 
 impl Parse for S64 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let (value, len) = SLEB128::read_from(&data.read(..))
-            .or(Err(ParseError::new(data, "Can't decode s32".to_string())))?;
+        let (value, len) =
+            SLEB128::read_from(&data.read(..)).map_err(|err| leb_error(data, err))?;
+        check_len(data, len, 64)?;
         data.consume(len);
         Ok(S64(value.into()))
     }
@@ -64,12 +77,12 @@ impl Parse for S64 {
 
 impl Parse for I8 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let (value, len) = SLEB128::read_from(&data.read(..))
-            .or(Err(ParseError::new(data, "Can't decode i8".to_string())))?;
-        let value: i8 = i64::from(value).try_into().or(Err(ParseError::new(
-            data,
-            "Conversion failure. Expected i8, got i64".to_string(),
-        )))?;
+        let (value, len) =
+            SLEB128::read_from(&data.read(..)).map_err(|err| leb_error(data, err))?;
+        check_len(data, len, 8)?;
+        let value: i8 = i64::from(value)
+            .try_into()
+            .or(Err(ParseError::new(data, ParseErrorKind::LebOverflow)))?;
         data.consume(len);
         Ok(I8(value as u8))
     }
@@ -77,12 +90,12 @@ impl Parse for I8 {
 
 impl Parse for I16 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let (value, len) = SLEB128::read_from(&data.read(..))
-            .or(Err(ParseError::new(data, "Can't decode i16".to_string())))?;
-        let value: i16 = i64::from(value).try_into().or(Err(ParseError::new(
-            data,
-            "Conversion failure. Expected i16, got i64".to_string(),
-        )))?;
+        let (value, len) =
+            SLEB128::read_from(&data.read(..)).map_err(|err| leb_error(data, err))?;
+        check_len(data, len, 16)?;
+        let value: i16 = i64::from(value)
+            .try_into()
+            .or(Err(ParseError::new(data, ParseErrorKind::LebOverflow)))?;
         data.consume(len);
         Ok(I16(value as u16))
     }
@@ -90,12 +103,12 @@ impl Parse for I16 {
 
 impl Parse for I32 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let (value, len) = SLEB128::read_from(&data.read(..))
-            .or(Err(ParseError::new(data, "Can't decode i32".to_string())))?;
-        let value: i32 = i64::from(value).try_into().or(Err(ParseError::new(
-            data,
-            "Conversion failure. Expected i32, got i64".to_string(),
-        )))?;
+        let (value, len) =
+            SLEB128::read_from(&data.read(..)).map_err(|err| leb_error(data, err))?;
+        check_len(data, len, 32)?;
+        let value: i32 = i64::from(value)
+            .try_into()
+            .or(Err(ParseError::new(data, ParseErrorKind::LebOverflow)))?;
         data.consume(len);
         Ok(I32(value as u32))
     }
@@ -103,8 +116,9 @@ impl Parse for I32 {
 
 impl Parse for I64 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
-        let (value, len) = SLEB128::read_from(&data.read(..))
-            .or(Err(ParseError::new(data, "Can't decode i64".to_string())))?;
+        let (value, len) =
+            SLEB128::read_from(&data.read(..)).map_err(|err| leb_error(data, err))?;
+        check_len(data, len, 64)?;
         let value: i64 = value.into();
         data.consume(len);
         Ok(I64(value as u64))
@@ -114,10 +128,7 @@ impl Parse for I64 {
 impl Parse for F32 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         if data.len() < 4 {
-            return Err(ParseError::new(
-                data,
-                "Buffer too small for parsing f32.".to_string(),
-            ));
+            return Err(ParseError::eof(data));
         }
         Ok(F32(f32::from_le_bytes({
             let bytes: &[u8] = &data.consume(4);
@@ -129,10 +140,7 @@ impl Parse for F32 {
 impl Parse for F64 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         if data.len() < 8 {
-            return Err(ParseError::new(
-                data,
-                "Buffer too small for parsing f64.".to_string(),
-            ));
+            return Err(ParseError::eof(data));
         }
         Ok(F64(f64::from_le_bytes({
             let bytes: &[u8] = &data.consume(8);
@@ -141,33 +149,104 @@ impl Parse for F64 {
     }
 }
 
-impl Parse for Name {
+impl Parse for V128 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
+        if data.len() < 16 {
+            return Err(ParseError::eof(data));
+        }
+        Ok(V128(u128::from_le_bytes({
+            let bytes: &[u8] = &data.consume(16);
+            bytes.try_into().expect("Buffer should have 16 elements")
+        })))
+    }
+}
+
+/// Parses a byte vector, i.e. a size followed by as many bytes.
+impl<'a> ParseBorrowed<'a> for &'a [u8] {
+    fn parse_borrowed(data: &mut ParsingData<'a>) -> Result<Self, ParseError> {
+        let n = *U32::parse(data).map_err(|err| err.extend("Couldn't read U32"))? as usize;
+        if n > data.len() {
+            return Err(ParseError::eof(data));
+        }
+        Ok(data.consume(n).bytes())
+    }
+}
+
+impl<'a> ParseBorrowed<'a> for &'a str {
+    fn parse_borrowed(data: &mut ParsingData<'a>) -> Result<Self, ParseError> {
         let bytes =
-            Vec::<Byte>::parse(data).map_err(|err| err.extend("Can't parse byte vector."))?;
-        // Unsafe explanation: This should be okay since Byte and u8 have the same memory
-        // representation (transparent).
-        let bytes = unsafe { std::mem::transmute(bytes) };
+            <&[u8]>::parse_borrowed(data).map_err(|err| err.extend("Can't parse byte vector."))?;
+        std::str::from_utf8(bytes).or(Err(ParseError::new(data, ParseErrorKind::InvalidUtf8)))
+    }
+}
+
+impl Parse for Name {
+    fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         Ok(Name {
-            name: String::from_utf8(bytes).or(Err(ParseError::new(
-                data,
-                "Can't decode UTF-8 from bytes.".to_string(),
-            )))?,
+            name: <&str>::parse_borrowed(data)?.to_string(),
         })
     }
 }
 
+impl<'a, T> ParseBorrowed<'a> for Vec<T>
+where
+    T: ParseBorrowed<'a>,
+{
+    fn parse_borrowed(data: &mut ParsingData<'a>) -> Result<Self, ParseError> {
+        let n = U32::parse(data).map_err(|err| err.extend("Couldn't read U32"))?;
+        let mut result = Vec::with_capacity(std::cmp::min(*n as usize, data.len()));
+        for i in 0..*n as usize {
+            let t =
+                T::parse_borrowed(data).map_err(|err| err.with_context(ErrorContext::Item(i)))?;
+            result.push(t);
+        }
+        Ok(result)
+    }
+}
+
 impl<T> Parse for Vec<T>
 where
     T: Parse,
 {
     fn parse(data: &mut ParsingData) -> Result<Self, ParseError> {
         let n = U32::parse(data).map_err(|err| err.extend("Couldn't read U32"))?;
-        let mut result = Vec::with_capacity(*n as usize);
-        for _ in 0..*n {
-            let t = T::parse(data).map_err(|err| err.extend("Couldn't read data type."))?;
+        // Every element takes at least one byte so don't trust `n` any further than that.
+        let mut result = Vec::with_capacity(std::cmp::min(*n as usize, data.len()));
+        for i in 0..*n as usize {
+            let t = T::parse(data).map_err(|err| err.with_context(ErrorContext::Item(i)))?;
             result.push(t);
         }
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::WasmBinary;
+
+    #[test]
+    fn borrowed_str() {
+        let wasm: WasmBinary = [0x02, b'h', b'i', 0x00].as_ref().into();
+        let mut data = ParsingData::new(&wasm);
+        let name = <&str>::parse_borrowed(&mut data).unwrap();
+        assert_eq!(name, "hi");
+        assert!(std::ptr::eq(name.as_bytes(), &wasm[1..3]));
+        assert_eq!(data.len(), 1);
+
+        // The bytes are consumed before they're checked.
+        let wasm: WasmBinary = [0x02, 0xC3, 0x28].as_ref().into();
+        let err = <&str>::parse_borrowed(&mut ParsingData::new(&wasm)).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InvalidUtf8);
+        assert_eq!(err.location(), 3);
+
+        // The length goes past the end of the data.
+        let wasm: WasmBinary = [0x03, b'h', b'i'].as_ref().into();
+        let err = <&str>::parse_borrowed(&mut ParsingData::new(&wasm)).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.location(), 1);
+        let wasm: WasmBinary = [].as_ref().into();
+        let err = <&str>::parse_borrowed(&mut ParsingData::new(&wasm)).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedEof);
+    }
+}
@@ -1,9 +1,9 @@
 //! Parsing elements for the web assembly.
 //!
-//! As of this moment, the only supported format is the binary format specified in the web assembly
-//! specification. For this reason, this module will just reexport all of the componenets within
-//! the binary submodule.
+//! Both the binary and the text formats specified in the web assembly specification are
+//! supported. The [binary] format is the main one and, for this reason, all of its componenets are
+//! reexported by this module. The [text] format is parsed by [text::parse].
 
 pub mod binary;
-// TODO: Maybe change this if added support for text format.
+pub mod text;
 pub use binary::*;
@@ -0,0 +1,140 @@
+//! Text format parser
+//!
+//! This module parses the web assembly text format (`.wat`) into the same
+//! [Module](crate::wasm::module::Module) the [binary](super::binary) parser produces. Symbolic
+//! identifiers are resolved to indices and the abbreviations of the specification (folded
+//! instructions, inline imports and exports, inline type uses, inline elements and data...) are
+//! expanded into their plain form.
+//!
+//! # Examples
+//!
+//! ```
+//! use wasm_parse::parse::text;
+//!
+//! let module = text::parse(
+//!     r#"(module
+//!         (func $add (export "add") (param $a i32) (param $b i32) (result i32)
+//!             (i32.add (local.get $a) (local.get $b))))"#,
+//! )?;
+//! assert_eq!(module.funcs.len(), 1);
+//! assert_eq!(module.exports[0].name.name, "add");
+//! assert!(module.validate().is_ok());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use thiserror::Error;
+
+use crate::wasm::module::Module;
+
+mod instr;
+pub(crate) mod lexer;
+mod module;
+pub(crate) mod parser;
+pub(crate) mod values;
+
+/// Parses a module written in the text format.
+///
+/// The text may either be a single `(module ...)` or the list of module fields without the
+/// surrounding `module`.
+pub fn parse(text: &str) -> Result<Module, TextError> {
+    let mut parser = parser::Parser::new(text)?;
+    let module = parser.module()?;
+    parser.finish()?;
+    Ok(module)
+}
+
+/// Error case for the text [parser](parse).
+///
+/// Keeps the byte offset within the text along with the line and column (both starting at 1) at
+/// which the error occurred.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "Parsing error at line {}, column {}. Description: {}",
+    line,
+    column,
+    kind
+)]
+pub struct TextError {
+    location: usize,
+    line: usize,
+    column: usize,
+    kind: TextErrorKind,
+}
+
+/// The reason a [TextError] occurred.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum TextErrorKind {
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
+    #[error("Unexpected character {0:?}")]
+    UnexpectedChar(char),
+    #[error("Unterminated string")]
+    UnterminatedString,
+    #[error("Unterminated block comment")]
+    UnterminatedComment,
+    #[error("Expected {expected}, found {found}")]
+    Unexpected {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("Invalid number {0}")]
+    InvalidNumber(String),
+    #[error("Invalid string {0:?}")]
+    InvalidString(String),
+    #[error("Invalid UTF-8 encoding")]
+    InvalidUtf8,
+    #[error("Unknown instruction {0}")]
+    UnknownInstruction(String),
+    #[error("Unknown {space} {id}")]
+    UnknownIdentifier { space: &'static str, id: String },
+    #[error("Duplicate {space} {id}")]
+    DuplicateIdentifier { space: &'static str, id: String },
+    #[error("Label {0} doesn't match the block it closes")]
+    MismatchedLabel(String),
+    #[error("Imports must precede the definitions of functions, tables, memories and globals")]
+    ImportAfterDefinition,
+    #[error("Inline function type doesn't match type {0}")]
+    TypeMismatch(u32),
+    #[error("Alignment {0} is not a power of two")]
+    InvalidAlignment(u32),
+    #[error("Multiple start functions")]
+    MultipleStart,
+    #[error("Instructions are nested too deeply")]
+    NestingTooDeep,
+}
+
+impl TextError {
+    /// Constructs a TextError at the byte offset `location` within `text`.
+    pub fn new(text: &str, location: usize, kind: TextErrorKind) -> TextError {
+        let before = &text[..location];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |pos| pos + 1);
+        let column = before[line_start..].chars().count() + 1;
+        TextError {
+            location,
+            line,
+            column,
+            kind,
+        }
+    }
+
+    /// Byte offset within the text where the error occurred.
+    pub fn location(&self) -> usize {
+        self.location
+    }
+
+    /// Line where the error occurred, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Column (in characters) where the error occurred, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn kind(&self) -> &TextErrorKind {
+        &self.kind
+    }
+}
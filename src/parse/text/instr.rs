@@ -0,0 +1,1394 @@
+//! Plain and folded instructions.
+
+use super::lexer::Token;
+use super::parser::{Parser, Space};
+use super::values;
+use super::{TextError, TextErrorKind};
+use crate::wasm::indices::{
+    DataIdx, ElemIdx, FieldIdx, FuncIdx, GlobalIdx, LabelIdx, LaneIdx, LocalIdx, MemIdx, TableIdx,
+    TagIdx, TypeIdx,
+};
+use crate::wasm::instr::{
+    AggregateInstr, AtomicInstr, Block, BlockType, Catch, ControlInstr, Expr, FBinop, FRelop,
+    FUnop, FloatType, IBinop, IRelop, ITestop, IUnop, IfElseBlock, Instr, IntType, MemArg,
+    MemoryInstr, NumericInstr, ReferenceInstr, Sign, TableInstr, TryBlock, TryHandler,
+    TryTableBlock, VariableInstr, VectorInstr,
+};
+use crate::wasm::types::{FuncType, ResultType};
+use crate::wasm::values::{Byte, F32, F64, I32, I64, U32, U64, V128};
+
+/// Instruction whose parsing is in progress.
+enum Open<'a> {
+    /// `block`, `loop` or `if` in the plain form, closed by `end`.
+    Plain(BlockKind, BlockType, Option<&'a str>),
+    /// The `else` branch of a plain `if`, along with the `then` branch.
+    Else(BlockType, Option<&'a str>, Vec<Instr>),
+    /// `block` or `loop` in the folded form.
+    Folded(BlockKind, BlockType),
+    /// Folded `if` whose condition is being parsed. The condition goes before the `if` itself.
+    Cond(BlockType, Option<&'a str>),
+    /// Folded `if` within its `(then ...)`.
+    Then(BlockType),
+    /// Folded `if` after its `(then ...)`, along with the `then` branch.
+    AfterThen(BlockType, Vec<Instr>),
+    /// Folded `if` within its `(else ...)`, along with the `then` branch.
+    FoldedElse(BlockType, Vec<Instr>),
+    /// Folded `if` after its `(else ...)`, along with both branches.
+    AfterElse(BlockType, Vec<Instr>, Vec<Instr>),
+    /// `try` in the plain form, within its body.
+    PlainTry(BlockType, Option<&'a str>),
+    /// Plain `try` within the `catch` clause of the given tag, or within its `catch_all` clause if
+    /// there is none. The block holds the body and the previous clauses.
+    PlainCatch(TryBlock, Option<&'a str>, Option<TagIdx>),
+    /// `try_table` in the plain form, along with its handlers.
+    PlainTryTable(BlockType, Option<&'a str>, Vec<Catch>),
+    /// Folded `try` before its `(do ...)`.
+    FoldedTry(BlockType),
+    /// Folded `try` within its `(do ...)`.
+    Do(BlockType),
+    /// Folded `try` between its clauses, along with the body and the previous clauses.
+    AfterDo(TryBlock),
+    /// Folded `try` within a `(catch ...)` clause, or a `(catch_all ...)` one if there's no tag.
+    FoldedCatch(TryBlock, Option<TagIdx>),
+    /// `try_table` in the folded form, along with its handlers.
+    FoldedTryTable(BlockType, Vec<Catch>),
+    /// Any other folded instruction, which goes after its operands.
+    Op(Instr),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Block,
+    Loop,
+    If,
+}
+
+fn block(kind: BlockKind, tpe: BlockType, instr: Vec<Instr>) -> Instr {
+    Instr::Control(match kind {
+        BlockKind::Block => ControlInstr::Block(Block { tpe, instr }),
+        BlockKind::Loop => ControlInstr::Loop(Block { tpe, instr }),
+        BlockKind::If => ControlInstr::If(IfElseBlock {
+            tpe,
+            if_br: instr,
+            else_br: Vec::new(),
+        }),
+    })
+}
+
+fn try_table(tpe: BlockType, catches: Vec<Catch>, instr: Vec<Instr>) -> Instr {
+    Instr::Control(ControlInstr::TryTable(TryTableBlock {
+        tpe,
+        catches,
+        instr,
+    }))
+}
+
+fn if_else(tpe: BlockType, if_br: Vec<Instr>, else_br: Vec<Instr>) -> Instr {
+    Instr::Control(ControlInstr::If(IfElseBlock {
+        tpe,
+        if_br,
+        else_br,
+    }))
+}
+
+impl<'a> Parser<'a> {
+    /// Parses instructions, both plain and folded, up to the first token that can't continue the
+    /// sequence (e.g. a closing parenthesis).
+    pub(crate) fn instrs(&mut self) -> Result<Vec<Instr>, TextError> {
+        self.parse_instrs(false)
+    }
+
+    /// Parses a single folded instruction, preceded by its operands.
+    pub(crate) fn folded(&mut self) -> Result<Vec<Instr>, TextError> {
+        self.parse_instrs(true)
+    }
+
+    /// Parses instructions up to the closing parenthesis of the enclosing s-expression.
+    pub(crate) fn expr(&mut self) -> Result<Expr, TextError> {
+        let instr = self.instrs()?;
+        match self.peek() {
+            Some(Token::RParen) => Ok(Expr { instr }),
+            _ => Err(self.unexpected("instruction")),
+        }
+    }
+
+    /// Parses a sequence of instructions, or a single folded instruction if `single` is set.
+    ///
+    /// Blocks and folded instructions are tracked with an explicit stack rather than recursion so
+    /// that deeply nested code can't overflow the call stack. Each entry holds the open
+    /// instruction along with the sequence it'll be added to. Folded instructions other than
+    /// blocks add their operands (or condition) straight to that sequence.
+    fn parse_instrs(&mut self, single: bool) -> Result<Vec<Instr>, TextError> {
+        let mut stack: Vec<(Open<'a>, Vec<Instr>)> = Vec::new();
+        let mut instr = Vec::new();
+        loop {
+            if single && stack.is_empty() {
+                if !instr.is_empty() {
+                    return Ok(instr);
+                }
+                if self.peek() != Some(&Token::LParen) {
+                    return Err(self.unexpected("("));
+                }
+            }
+            match (self.peek(), stack.last()) {
+                (Some(Token::LParen), Some((Open::Cond(..), _))) if self.peek_field("then") => {
+                    self.eat_field("then");
+                    if let Some((Open::Cond(tpe, label), _)) = stack.pop() {
+                        self.push_label(label)?;
+                        stack.push((Open::Then(tpe), std::mem::take(&mut instr)));
+                    }
+                }
+                (Some(Token::LParen), Some((Open::AfterThen(..), _))) => {
+                    if !self.eat_field("else") {
+                        return Err(self.unexpected("(else"));
+                    }
+                    if let Some((Open::AfterThen(tpe, if_br), outer)) = stack.pop() {
+                        stack.push((Open::FoldedElse(tpe, if_br), outer));
+                    }
+                }
+                (Some(Token::LParen), Some((Open::AfterElse(..), _))) => {
+                    return Err(self.unexpected(")"))
+                }
+                (Some(Token::LParen), Some((Open::FoldedTry(..), _))) => {
+                    if !self.eat_field("do") {
+                        return Err(self.unexpected("(do"));
+                    }
+                    if let Some((Open::FoldedTry(tpe), outer)) = stack.pop() {
+                        stack.push((Open::Do(tpe), outer));
+                    }
+                }
+                (Some(Token::LParen), Some((Open::AfterDo(..), _))) => {
+                    let (mut block, outer) = match stack.pop() {
+                        Some((Open::AfterDo(block), outer)) => (block, outer),
+                        _ => unreachable!("Checked above"),
+                    };
+                    // Either `catch` clauses followed by an optional `catch_all` or `delegate`.
+                    let (first, done) = match &block.handler {
+                        TryHandler::Catch { catches, catch_all } => (
+                            catches.is_empty() && catch_all.is_none(),
+                            catch_all.is_some(),
+                        ),
+                        TryHandler::Delegate(_) => (false, true),
+                    };
+                    if done {
+                        return Err(self.unexpected(")"));
+                    } else if self.eat_field("catch") {
+                        let tag = self.tag()?;
+                        stack.push((Open::FoldedCatch(block, Some(tag)), outer));
+                    } else if self.eat_field("catch_all") {
+                        stack.push((Open::FoldedCatch(block, None), outer));
+                    } else if first && self.eat_field("delegate") {
+                        // The label of `delegate` is outside of the block.
+                        self.labels.pop();
+                        block.handler = TryHandler::Delegate(LabelIdx(U32(self.label()?)));
+                        self.rparen()?;
+                        stack.push((Open::AfterDo(block), outer));
+                    } else {
+                        return Err(self.unexpected("(catch"));
+                    }
+                }
+                (Some(Token::LParen), _) => {
+                    self.lparen()?;
+                    let pos = self.position();
+                    let kw = self.any_keyword()?;
+                    match kw {
+                        "block" | "loop" => {
+                            let label = self.eat_id();
+                            let tpe = self.block_type()?;
+                            self.push_label(label)?;
+                            let kind = if kw == "block" {
+                                BlockKind::Block
+                            } else {
+                                BlockKind::Loop
+                            };
+                            stack.push((Open::Folded(kind, tpe), std::mem::take(&mut instr)));
+                        }
+                        "if" => {
+                            let label = self.eat_id();
+                            let tpe = self.block_type()?;
+                            stack.push((Open::Cond(tpe, label), Vec::new()));
+                        }
+                        "try" => {
+                            let label = self.eat_id();
+                            let tpe = self.block_type()?;
+                            self.push_label(label)?;
+                            stack.push((Open::FoldedTry(tpe), std::mem::take(&mut instr)));
+                        }
+                        "try_table" => {
+                            let label = self.eat_id();
+                            let tpe = self.block_type()?;
+                            let catches = self.catches()?;
+                            self.push_label(label)?;
+                            let open = Open::FoldedTryTable(tpe, catches);
+                            stack.push((open, std::mem::take(&mut instr)));
+                        }
+                        _ => {
+                            let op = self.plain(pos, kw)?;
+                            stack.push((Open::Op(op), Vec::new()));
+                        }
+                    }
+                }
+                (Some(Token::RParen), None) => return Ok(instr),
+                (Some(Token::RParen), Some((Open::Cond(..), _))) => {
+                    return Err(self.unexpected("(then"))
+                }
+                (Some(Token::RParen), Some((Open::FoldedTry(..), _))) => {
+                    return Err(self.unexpected("(do"))
+                }
+                (Some(Token::RParen), Some((Open::Plain(..), _)))
+                | (Some(Token::RParen), Some((Open::Else(..), _)))
+                | (Some(Token::RParen), Some((Open::PlainTry(..), _)))
+                | (Some(Token::RParen), Some((Open::PlainCatch(..), _)))
+                | (Some(Token::RParen), Some((Open::PlainTryTable(..), _))) => {
+                    return Err(self.unexpected("end"))
+                }
+                (Some(Token::RParen), Some(_)) => {
+                    self.rparen()?;
+                    let (open, outer) = stack.pop().expect("The stack isn't empty");
+                    match open {
+                        Open::Folded(kind, tpe) => {
+                            self.labels.pop();
+                            let inner = std::mem::replace(&mut instr, outer);
+                            instr.push(block(kind, tpe, inner));
+                        }
+                        Open::Op(op) => instr.push(op),
+                        Open::Then(tpe) => {
+                            let if_br = std::mem::take(&mut instr);
+                            stack.push((Open::AfterThen(tpe, if_br), outer));
+                        }
+                        Open::FoldedElse(tpe, if_br) => {
+                            let else_br = std::mem::take(&mut instr);
+                            stack.push((Open::AfterElse(tpe, if_br, else_br), outer));
+                        }
+                        Open::AfterThen(tpe, if_br) => {
+                            self.labels.pop();
+                            instr = outer;
+                            instr.push(if_else(tpe, if_br, Vec::new()));
+                        }
+                        Open::AfterElse(tpe, if_br, else_br) => {
+                            self.labels.pop();
+                            instr = outer;
+                            instr.push(if_else(tpe, if_br, else_br));
+                        }
+                        Open::Do(tpe) => {
+                            let block = TryBlock::new(tpe, std::mem::take(&mut instr));
+                            stack.push((Open::AfterDo(block), outer));
+                        }
+                        Open::FoldedCatch(mut block, tag) => {
+                            block.add_catch(tag, std::mem::take(&mut instr));
+                            stack.push((Open::AfterDo(block), outer));
+                        }
+                        Open::AfterDo(block) => {
+                            // The label of a delegating block was popped with `(delegate`.
+                            if let TryHandler::Catch { .. } = block.handler {
+                                self.labels.pop();
+                            }
+                            instr = outer;
+                            instr.push(Instr::Control(ControlInstr::Try(block)));
+                        }
+                        Open::FoldedTryTable(tpe, catches) => {
+                            self.labels.pop();
+                            let inner = std::mem::replace(&mut instr, outer);
+                            instr.push(try_table(tpe, catches, inner));
+                        }
+                        Open::Cond(..)
+                        | Open::Plain(..)
+                        | Open::Else(..)
+                        | Open::PlainTry(..)
+                        | Open::PlainCatch(..)
+                        | Open::PlainTryTable(..)
+                        | Open::FoldedTry(..) => {
+                            unreachable!("Checked above")
+                        }
+                    }
+                }
+                (Some(Token::Keyword(_)), Some((Open::Cond(..), _)))
+                | (Some(Token::Keyword(_)), Some((Open::AfterThen(..), _)))
+                | (Some(Token::Keyword(_)), Some((Open::AfterElse(..), _)))
+                | (Some(Token::Keyword(_)), Some((Open::FoldedTry(..), _)))
+                | (Some(Token::Keyword(_)), Some((Open::AfterDo(..), _))) => {
+                    return Err(self.unexpected("("))
+                }
+                (Some(&Token::Keyword(kw @ "catch")), Some((Open::PlainTry(..), _)))
+                | (
+                    Some(&Token::Keyword(kw @ "catch")),
+                    Some((Open::PlainCatch(_, _, Some(_)), _)),
+                )
+                | (Some(&Token::Keyword(kw @ "catch_all")), Some((Open::PlainTry(..), _)))
+                | (
+                    Some(&Token::Keyword(kw @ "catch_all")),
+                    Some((Open::PlainCatch(_, _, Some(_)), _)),
+                ) => {
+                    self.next()?;
+                    let (block, label, outer) = match stack.pop() {
+                        Some((Open::PlainTry(tpe, label), outer)) => {
+                            (TryBlock::new(tpe, std::mem::take(&mut instr)), label, outer)
+                        }
+                        Some((Open::PlainCatch(mut block, label, prev), outer)) => {
+                            block.add_catch(prev, std::mem::take(&mut instr));
+                            (block, label, outer)
+                        }
+                        _ => unreachable!("Checked above"),
+                    };
+                    // The label may be repeated, before the tag of a `catch`.
+                    let tag = if kw == "catch" {
+                        if matches!(self.peek_at(1), Some(Token::Number(_)) | Some(Token::Id(_))) {
+                            self.end_label(label)?;
+                        }
+                        Some(self.tag()?)
+                    } else {
+                        self.end_label(label)?;
+                        None
+                    };
+                    stack.push((Open::PlainCatch(block, label, tag), outer));
+                }
+                (Some(Token::Keyword("delegate")), Some((Open::PlainTry(..), _))) => {
+                    self.next()?;
+                    // The label of `delegate` is outside of the block.
+                    self.labels.pop();
+                    let label = LabelIdx(U32(self.label()?));
+                    if let Some((Open::PlainTry(tpe, _), outer)) = stack.pop() {
+                        let inner = std::mem::replace(&mut instr, outer);
+                        instr.push(Instr::Control(ControlInstr::Try(TryBlock {
+                            tpe,
+                            instr: inner,
+                            handler: TryHandler::Delegate(label),
+                        })));
+                    }
+                }
+                (Some(Token::Keyword("else")), Some((Open::Plain(BlockKind::If, ..), _))) => {
+                    self.next()?;
+                    if let Some((Open::Plain(_, tpe, label), outer)) = stack.pop() {
+                        self.end_label(label)?;
+                        let if_br = std::mem::take(&mut instr);
+                        stack.push((Open::Else(tpe, label, if_br), outer));
+                    }
+                }
+                (Some(Token::Keyword("end")), Some((Open::Plain(..), _)))
+                | (Some(Token::Keyword("end")), Some((Open::Else(..), _)))
+                | (Some(Token::Keyword("end")), Some((Open::PlainTry(..), _)))
+                | (Some(Token::Keyword("end")), Some((Open::PlainCatch(..), _)))
+                | (Some(Token::Keyword("end")), Some((Open::PlainTryTable(..), _))) => {
+                    self.next()?;
+                    self.labels.pop();
+                    let (open, outer) = stack.pop().expect("The stack isn't empty");
+                    let inner = std::mem::replace(&mut instr, outer);
+                    match open {
+                        Open::Plain(kind, tpe, label) => {
+                            self.end_label(label)?;
+                            instr.push(block(kind, tpe, inner));
+                        }
+                        Open::Else(tpe, label, if_br) => {
+                            self.end_label(label)?;
+                            instr.push(if_else(tpe, if_br, inner));
+                        }
+                        Open::PlainTry(tpe, label) => {
+                            self.end_label(label)?;
+                            instr
+                                .push(Instr::Control(ControlInstr::Try(TryBlock::new(tpe, inner))));
+                        }
+                        Open::PlainCatch(mut block, label, tag) => {
+                            self.end_label(label)?;
+                            block.add_catch(tag, inner);
+                            instr.push(Instr::Control(ControlInstr::Try(block)));
+                        }
+                        Open::PlainTryTable(tpe, label, catches) => {
+                            self.end_label(label)?;
+                            instr.push(try_table(tpe, catches, inner));
+                        }
+                        _ => unreachable!("Checked above"),
+                    }
+                }
+                (Some(Token::Keyword("end")), None) | (Some(Token::Keyword("else")), None) => {
+                    return Ok(instr)
+                }
+                (Some(Token::Keyword("end")), _) | (Some(Token::Keyword("else")), _) => {
+                    return Err(self.unexpected("instruction"))
+                }
+                (Some(&Token::Keyword(kw)), _) => {
+                    let pos = self.position();
+                    self.next()?;
+                    match kw {
+                        "block" | "loop" | "if" => {
+                            let label = self.eat_id();
+                            let tpe = self.block_type()?;
+                            self.push_label(label)?;
+                            let kind = match kw {
+                                "block" => BlockKind::Block,
+                                "loop" => BlockKind::Loop,
+                                _ => BlockKind::If,
+                            };
+                            let open = Open::Plain(kind, tpe, label);
+                            stack.push((open, std::mem::take(&mut instr)));
+                        }
+                        "try" => {
+                            let label = self.eat_id();
+                            let tpe = self.block_type()?;
+                            self.push_label(label)?;
+                            stack.push((Open::PlainTry(tpe, label), std::mem::take(&mut instr)));
+                        }
+                        "try_table" => {
+                            let label = self.eat_id();
+                            let tpe = self.block_type()?;
+                            let catches = self.catches()?;
+                            self.push_label(label)?;
+                            let open = Open::PlainTryTable(tpe, label, catches);
+                            stack.push((open, std::mem::take(&mut instr)));
+                        }
+                        _ => {
+                            let op = self.plain(pos, kw)?;
+                            instr.push(op);
+                        }
+                    }
+                }
+                (_, None) => return Ok(instr),
+                (_, Some(_)) => return Err(self.unexpected("instruction")),
+            }
+        }
+    }
+
+    /// Parses the handlers of a `try_table`. Their labels are outside of the block.
+    fn catches(&mut self) -> Result<Vec<Catch>, TextError> {
+        let mut catches = Vec::new();
+        loop {
+            let catch = if self.eat_field("catch") {
+                Catch::Catch(self.tag()?, LabelIdx(U32(self.label()?)))
+            } else if self.eat_field("catch_ref") {
+                Catch::CatchRef(self.tag()?, LabelIdx(U32(self.label()?)))
+            } else if self.eat_field("catch_all") {
+                Catch::CatchAll(LabelIdx(U32(self.label()?)))
+            } else if self.eat_field("catch_all_ref") {
+                Catch::CatchAllRef(LabelIdx(U32(self.label()?)))
+            } else {
+                return Ok(catches);
+            };
+            self.rparen()?;
+            catches.push(catch);
+        }
+    }
+
+    /// Checks the optional label repeated after `end` or `else`.
+    fn end_label(&mut self, label: Option<&'a str>) -> Result<(), TextError> {
+        let pos = self.position();
+        match self.eat_id() {
+            Some(id) if Some(id) != label => {
+                Err(self.error_at(pos, TextErrorKind::MismatchedLabel(id.to_string())))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn block_type(&mut self) -> Result<BlockType, TextError> {
+        if !self.peek_field("type") && !self.peek_field("param") {
+            let mut results = self.results()?;
+            return Ok(match results.len() {
+                0 => BlockType::Empty,
+                1 => BlockType::Val(results.remove(0)),
+                _ => BlockType::Type(TypeIdx(U32(self.type_index(FuncType {
+                    params: ResultType { types: Vec::new() },
+                    result: ResultType { types: results },
+                })))),
+            });
+        }
+        let start = self.position();
+        let (idx, names) = self.type_use()?;
+        if names.iter().any(Option::is_some) {
+            // Unlike the parameters of functions, those of blocks can't be named.
+            self.rewind(start);
+            while !(self.peek_field("param") && matches!(self.peek_at(2), Some(Token::Id(_)))) {
+                self.next()?;
+            }
+            self.rewind(self.position() + 2);
+            return Err(self.unexpected("value type"));
+        }
+        Ok(BlockType::Type(TypeIdx(U32(idx))))
+    }
+
+    fn mem_arg(&mut self, natural: u32) -> Result<MemArg, TextError> {
+        let mem = self.memory()?;
+        self.offset_align(mem, natural)
+    }
+
+    /// Parses the memory immediate and lane index of a lane load or store. The memory index is
+    /// optional and the lane isn't, so a single index is the lane.
+    fn lane_mem_arg(&mut self, natural: u32) -> Result<(MemArg, LaneIdx), TextError> {
+        let has_mem = self.peek_index()
+            && match self.peek_at(1) {
+                Some(Token::Number(_)) | Some(Token::Id(_)) => true,
+                Some(Token::Keyword(kw)) => kw.starts_with("offset=") || kw.starts_with("align="),
+                _ => false,
+            };
+        let mem = if has_mem {
+            self.memory()?
+        } else {
+            MemIdx(U32(0))
+        };
+        let arg = self.offset_align(mem, natural)?;
+        Ok((arg, self.lane()?))
+    }
+
+    /// Parses the optional `offset=` and `align=` of a memory immediate.
+    fn offset_align(&mut self, mem: MemIdx, natural: u32) -> Result<MemArg, TextError> {
+        let mut offset = 0;
+        if let Some(&Token::Keyword(kw)) = self.peek() {
+            if let Some(value) = kw.strip_prefix("offset=") {
+                offset = values::parse_u64(value)
+                    .ok_or_else(|| self.error(TextErrorKind::InvalidNumber(value.into())))?;
+                self.next()?;
+            }
+        }
+        let mut align = natural;
+        if let Some(&Token::Keyword(kw)) = self.peek() {
+            if let Some(value) = kw.strip_prefix("align=") {
+                align = values::parse_u32(value)
+                    .ok_or_else(|| self.error(TextErrorKind::InvalidNumber(value.into())))?;
+                if !align.is_power_of_two() {
+                    return Err(self.error(TextErrorKind::InvalidAlignment(align)));
+                }
+                self.next()?;
+            }
+        }
+        Ok(MemArg {
+            offset: U64(offset),
+            align: U32(align.trailing_zeros()),
+            mem,
+        })
+    }
+
+    /// Parses the immediates of the instruction `kw`, whose keyword is at position `pos`.
+    fn plain(&mut self, pos: usize, kw: &'a str) -> Result<Instr, TextError> {
+        use Sign::{Signed, Unsigned};
+        let instr = match kw {
+            "unreachable" => Instr::Control(ControlInstr::Unreachable),
+            "nop" => Instr::Control(ControlInstr::Nop),
+            "br" => Instr::Control(ControlInstr::Branch(LabelIdx(U32(self.label()?)))),
+            "br_if" => Instr::Control(ControlInstr::BranchIf(LabelIdx(U32(self.label()?)))),
+            "br_table" => {
+                let mut labels = vec![LabelIdx(U32(self.label()?))];
+                while self.peek_index() {
+                    labels.push(LabelIdx(U32(self.label()?)));
+                }
+                let default = labels.pop().expect("There's at least one label");
+                Instr::Control(ControlInstr::BranchTable(labels, default))
+            }
+            "return" => Instr::Control(ControlInstr::Return),
+            "throw" => Instr::Control(ControlInstr::Throw(self.tag()?)),
+            "rethrow" => Instr::Control(ControlInstr::Rethrow(LabelIdx(U32(self.label()?)))),
+            "throw_ref" => Instr::Control(ControlInstr::ThrowRef),
+            "call" => Instr::Control(ControlInstr::Call(FuncIdx(U32(self.index(Space::Func)?)))),
+            "call_indirect" => {
+                let (table, tpe) = self.call_indirect()?;
+                Instr::Control(ControlInstr::CallIndirect(table, tpe))
+            }
+            "return_call" => Instr::Control(ControlInstr::ReturnCall(FuncIdx(U32(
+                self.index(Space::Func)?
+            )))),
+            "return_call_indirect" => {
+                let (table, tpe) = self.call_indirect()?;
+                Instr::Control(ControlInstr::ReturnCallIndirect(table, tpe))
+            }
+            "call_ref" => Instr::Control(ControlInstr::CallRef(self.tpe()?)),
+            "return_call_ref" => Instr::Control(ControlInstr::ReturnCallRef(self.tpe()?)),
+            "br_on_null" => Instr::Control(ControlInstr::BrOnNull(LabelIdx(U32(self.label()?)))),
+            "br_on_non_null" => {
+                Instr::Control(ControlInstr::BrOnNonNull(LabelIdx(U32(self.label()?))))
+            }
+            "br_on_cast" | "br_on_cast_fail" => {
+                let l = LabelIdx(U32(self.label()?));
+                let rt1 = self.reftype()?;
+                let rt2 = self.reftype()?;
+                if kw == "br_on_cast" {
+                    Instr::Control(ControlInstr::BrOnCast(l, rt1, rt2))
+                } else {
+                    Instr::Control(ControlInstr::BrOnCastFail(l, rt1, rt2))
+                }
+            }
+            "drop" => Instr::Dropp,
+            "select" => {
+                let results = self.results()?;
+                Instr::Select(results)
+            }
+            "local.get" => Instr::Variable(VariableInstr::LocalGet(self.local()?)),
+            "local.set" => Instr::Variable(VariableInstr::LocalSet(self.local()?)),
+            "local.tee" => Instr::Variable(VariableInstr::LocalTee(self.local()?)),
+            "global.get" => Instr::Variable(VariableInstr::GlobalGet(self.global()?)),
+            "global.set" => Instr::Variable(VariableInstr::GlobalSet(self.global()?)),
+            "table.get" => Instr::Table(TableInstr::TableGet(self.table()?)),
+            "table.set" => Instr::Table(TableInstr::TableSet(self.table()?)),
+            "table.size" => Instr::Table(TableInstr::TableSize(self.table()?)),
+            "table.grow" => Instr::Table(TableInstr::TableGrow(self.table()?)),
+            "table.fill" => Instr::Table(TableInstr::TableFill(self.table()?)),
+            "table.copy" => {
+                let dst = self.table()?;
+                let src = self.table()?;
+                Instr::Table(TableInstr::TableCopy(dst, src))
+            }
+            "table.init" => {
+                // Either `table.init elem` or `table.init table elem`.
+                let table = match self.peek_at(1) {
+                    Some(Token::Number(_)) | Some(Token::Id(_)) => self.table()?,
+                    _ => TableIdx(U32(0)),
+                };
+                let elem = ElemIdx(U32(self.index(Space::Elem)?));
+                Instr::Table(TableInstr::TableInit(table, elem))
+            }
+            "elem.drop" => {
+                Instr::Table(TableInstr::ElemDrop(ElemIdx(U32(self.index(Space::Elem)?))))
+            }
+            "i32.load" => Instr::Memory(MemoryInstr::ILoad(IntType::I32, self.mem_arg(4)?)),
+            "i64.load" => Instr::Memory(MemoryInstr::ILoad(IntType::I64, self.mem_arg(8)?)),
+            "f32.load" => Instr::Memory(MemoryInstr::FLoad(FloatType::F32, self.mem_arg(4)?)),
+            "f64.load" => Instr::Memory(MemoryInstr::FLoad(FloatType::F64, self.mem_arg(8)?)),
+            "i32.load8_s" => {
+                Instr::Memory(MemoryInstr::ILoad8(IntType::I32, Signed, self.mem_arg(1)?))
+            }
+            "i32.load8_u" => Instr::Memory(MemoryInstr::ILoad8(
+                IntType::I32,
+                Unsigned,
+                self.mem_arg(1)?,
+            )),
+            "i32.load16_s" => {
+                Instr::Memory(MemoryInstr::ILoad16(IntType::I32, Signed, self.mem_arg(2)?))
+            }
+            "i32.load16_u" => Instr::Memory(MemoryInstr::ILoad16(
+                IntType::I32,
+                Unsigned,
+                self.mem_arg(2)?,
+            )),
+            "i64.load8_s" => {
+                Instr::Memory(MemoryInstr::ILoad8(IntType::I64, Signed, self.mem_arg(1)?))
+            }
+            "i64.load8_u" => Instr::Memory(MemoryInstr::ILoad8(
+                IntType::I64,
+                Unsigned,
+                self.mem_arg(1)?,
+            )),
+            "i64.load16_s" => {
+                Instr::Memory(MemoryInstr::ILoad16(IntType::I64, Signed, self.mem_arg(2)?))
+            }
+            "i64.load16_u" => Instr::Memory(MemoryInstr::ILoad16(
+                IntType::I64,
+                Unsigned,
+                self.mem_arg(2)?,
+            )),
+            "i64.load32_s" => Instr::Memory(MemoryInstr::I64Load32(Signed, self.mem_arg(4)?)),
+            "i64.load32_u" => Instr::Memory(MemoryInstr::I64Load32(Unsigned, self.mem_arg(4)?)),
+            "i32.store" => Instr::Memory(MemoryInstr::IStore(IntType::I32, self.mem_arg(4)?)),
+            "i64.store" => Instr::Memory(MemoryInstr::IStore(IntType::I64, self.mem_arg(8)?)),
+            "f32.store" => Instr::Memory(MemoryInstr::FStore(FloatType::F32, self.mem_arg(4)?)),
+            "f64.store" => Instr::Memory(MemoryInstr::FStore(FloatType::F64, self.mem_arg(8)?)),
+            "i32.store8" => Instr::Memory(MemoryInstr::IStore8(IntType::I32, self.mem_arg(1)?)),
+            "i32.store16" => Instr::Memory(MemoryInstr::IStore16(IntType::I32, self.mem_arg(2)?)),
+            "i64.store8" => Instr::Memory(MemoryInstr::IStore8(IntType::I64, self.mem_arg(1)?)),
+            "i64.store16" => Instr::Memory(MemoryInstr::IStore16(IntType::I64, self.mem_arg(2)?)),
+            "i64.store32" => Instr::Memory(MemoryInstr::I64Store32(self.mem_arg(4)?)),
+            "memory.size" => Instr::Memory(MemoryInstr::MemorySize(self.memory()?)),
+            "memory.grow" => Instr::Memory(MemoryInstr::MemoryGrow(self.memory()?)),
+            "memory.fill" => Instr::Memory(MemoryInstr::MemoryFill(self.memory()?)),
+            "memory.copy" => {
+                let dst = self.memory()?;
+                let src = self.memory()?;
+                Instr::Memory(MemoryInstr::MemoryCopy(dst, src))
+            }
+            "memory.init" => {
+                // Either `memory.init data` or `memory.init memory data`.
+                let mem = match self.peek_at(1) {
+                    Some(Token::Number(_)) | Some(Token::Id(_)) => self.memory()?,
+                    _ => MemIdx(U32(0)),
+                };
+                let data = DataIdx(U32(self.index(Space::Data)?));
+                Instr::Memory(MemoryInstr::MemoryInit(mem, data))
+            }
+            "data.drop" => Instr::Memory(MemoryInstr::DataDrop(DataIdx(U32(
+                self.index(Space::Data)?
+            )))),
+            "i32.const" => {
+                Instr::Numeric(NumericInstr::I32Const(I32(self.number(values::parse_i32)?)))
+            }
+            "i64.const" => {
+                Instr::Numeric(NumericInstr::I64Const(I64(self.number(values::parse_i64)?)))
+            }
+            "f32.const" => Instr::Numeric(NumericInstr::F32Const(F32(f32::from_bits(
+                self.number(values::parse_f32)?,
+            )))),
+            "f64.const" => Instr::Numeric(NumericInstr::F64Const(F64(f64::from_bits(
+                self.number(values::parse_f64)?,
+            )))),
+            "ref.null" => Instr::Reference(ReferenceInstr::RefNull(self.heap_type()?)),
+            "ref.is_null" => Instr::Reference(ReferenceInstr::RefIsNull),
+            "ref.func" => Instr::Reference(ReferenceInstr::RefFunc(FuncIdx(U32(
+                self.index(Space::Func)?
+            )))),
+            "ref.eq" => Instr::Reference(ReferenceInstr::RefEq),
+            "ref.as_non_null" => Instr::Reference(ReferenceInstr::RefAsNonNull),
+            "ref.test" => Instr::Reference(ReferenceInstr::RefTest(self.reftype()?)),
+            "ref.cast" => Instr::Reference(ReferenceInstr::RefCast(self.reftype()?)),
+            "ref.i31" => Instr::Reference(ReferenceInstr::RefI31),
+            "i31.get_s" => Instr::Reference(ReferenceInstr::I31Get(Signed)),
+            "i31.get_u" => Instr::Reference(ReferenceInstr::I31Get(Unsigned)),
+            "any.convert_extern" => Instr::Reference(ReferenceInstr::AnyConvertExtern),
+            "extern.convert_any" => Instr::Reference(ReferenceInstr::ExternConvertAny),
+            _ => match numeric(kw) {
+                Some(instr) => Instr::Numeric(instr),
+                None => match self.vector(kw)? {
+                    Some(instr) => Instr::Vector(instr),
+                    None => match self.atomic(kw)? {
+                        Some(instr) => Instr::Atomic(instr),
+                        None => match self.aggregate(kw)? {
+                            Some(instr) => Instr::Aggregate(instr),
+                            None => {
+                                return Err(self
+                                    .error_at(pos, TextErrorKind::UnknownInstruction(kw.into())))
+                            }
+                        },
+                    },
+                },
+            },
+        };
+        Ok(instr)
+    }
+
+    /// Parses the immediates of the vector instruction `kw`. Returns `None` for anything that
+    /// isn't a vector instruction.
+    fn vector(&mut self, kw: &str) -> Result<Option<VectorInstr>, TextError> {
+        use VectorInstr::*;
+        let instr = match kw {
+            "v128.const" => V128Const(self.v128()?),
+            "i8x16.shuffle" => {
+                let mut lanes = [LaneIdx(Byte(0)); 16];
+                for lane in lanes.iter_mut() {
+                    *lane = self.lane()?;
+                }
+                I8x16Shuffle(lanes)
+            }
+            "v128.load" => V128Load(self.mem_arg(16)?),
+            "v128.load8x8_s" => V128Load8x8S(self.mem_arg(8)?),
+            "v128.load8x8_u" => V128Load8x8U(self.mem_arg(8)?),
+            "v128.load16x4_s" => V128Load16x4S(self.mem_arg(8)?),
+            "v128.load16x4_u" => V128Load16x4U(self.mem_arg(8)?),
+            "v128.load32x2_s" => V128Load32x2S(self.mem_arg(8)?),
+            "v128.load32x2_u" => V128Load32x2U(self.mem_arg(8)?),
+            "v128.load8_splat" => V128Load8Splat(self.mem_arg(1)?),
+            "v128.load16_splat" => V128Load16Splat(self.mem_arg(2)?),
+            "v128.load32_splat" => V128Load32Splat(self.mem_arg(4)?),
+            "v128.load64_splat" => V128Load64Splat(self.mem_arg(8)?),
+            "v128.store" => V128Store(self.mem_arg(16)?),
+            "i8x16.swizzle" => I8x16Swizzle,
+            "i8x16.splat" => I8x16Splat,
+            "i16x8.splat" => I16x8Splat,
+            "i32x4.splat" => I32x4Splat,
+            "i64x2.splat" => I64x2Splat,
+            "f32x4.splat" => F32x4Splat,
+            "f64x2.splat" => F64x2Splat,
+            "i8x16.extract_lane_s" => I8x16ExtractLaneS(self.lane()?),
+            "i8x16.extract_lane_u" => I8x16ExtractLaneU(self.lane()?),
+            "i8x16.replace_lane" => I8x16ReplaceLane(self.lane()?),
+            "i16x8.extract_lane_s" => I16x8ExtractLaneS(self.lane()?),
+            "i16x8.extract_lane_u" => I16x8ExtractLaneU(self.lane()?),
+            "i16x8.replace_lane" => I16x8ReplaceLane(self.lane()?),
+            "i32x4.extract_lane" => I32x4ExtractLane(self.lane()?),
+            "i32x4.replace_lane" => I32x4ReplaceLane(self.lane()?),
+            "i64x2.extract_lane" => I64x2ExtractLane(self.lane()?),
+            "i64x2.replace_lane" => I64x2ReplaceLane(self.lane()?),
+            "f32x4.extract_lane" => F32x4ExtractLane(self.lane()?),
+            "f32x4.replace_lane" => F32x4ReplaceLane(self.lane()?),
+            "f64x2.extract_lane" => F64x2ExtractLane(self.lane()?),
+            "f64x2.replace_lane" => F64x2ReplaceLane(self.lane()?),
+            "i8x16.eq" => I8x16Eq,
+            "i8x16.ne" => I8x16Ne,
+            "i8x16.lt_s" => I8x16LtS,
+            "i8x16.lt_u" => I8x16LtU,
+            "i8x16.gt_s" => I8x16GtS,
+            "i8x16.gt_u" => I8x16GtU,
+            "i8x16.le_s" => I8x16LeS,
+            "i8x16.le_u" => I8x16LeU,
+            "i8x16.ge_s" => I8x16GeS,
+            "i8x16.ge_u" => I8x16GeU,
+            "i16x8.eq" => I16x8Eq,
+            "i16x8.ne" => I16x8Ne,
+            "i16x8.lt_s" => I16x8LtS,
+            "i16x8.lt_u" => I16x8LtU,
+            "i16x8.gt_s" => I16x8GtS,
+            "i16x8.gt_u" => I16x8GtU,
+            "i16x8.le_s" => I16x8LeS,
+            "i16x8.le_u" => I16x8LeU,
+            "i16x8.ge_s" => I16x8GeS,
+            "i16x8.ge_u" => I16x8GeU,
+            "i32x4.eq" => I32x4Eq,
+            "i32x4.ne" => I32x4Ne,
+            "i32x4.lt_s" => I32x4LtS,
+            "i32x4.lt_u" => I32x4LtU,
+            "i32x4.gt_s" => I32x4GtS,
+            "i32x4.gt_u" => I32x4GtU,
+            "i32x4.le_s" => I32x4LeS,
+            "i32x4.le_u" => I32x4LeU,
+            "i32x4.ge_s" => I32x4GeS,
+            "i32x4.ge_u" => I32x4GeU,
+            "f32x4.eq" => F32x4Eq,
+            "f32x4.ne" => F32x4Ne,
+            "f32x4.lt" => F32x4Lt,
+            "f32x4.gt" => F32x4Gt,
+            "f32x4.le" => F32x4Le,
+            "f32x4.ge" => F32x4Ge,
+            "f64x2.eq" => F64x2Eq,
+            "f64x2.ne" => F64x2Ne,
+            "f64x2.lt" => F64x2Lt,
+            "f64x2.gt" => F64x2Gt,
+            "f64x2.le" => F64x2Le,
+            "f64x2.ge" => F64x2Ge,
+            "v128.not" => V128Not,
+            "v128.and" => V128And,
+            "v128.andnot" => V128Andnot,
+            "v128.or" => V128Or,
+            "v128.xor" => V128Xor,
+            "v128.bitselect" => V128Bitselect,
+            "v128.any_true" => V128AnyTrue,
+            "v128.load8_lane" => {
+                let (arg, lane) = self.lane_mem_arg(1)?;
+                V128Load8Lane(arg, lane)
+            }
+            "v128.load16_lane" => {
+                let (arg, lane) = self.lane_mem_arg(2)?;
+                V128Load16Lane(arg, lane)
+            }
+            "v128.load32_lane" => {
+                let (arg, lane) = self.lane_mem_arg(4)?;
+                V128Load32Lane(arg, lane)
+            }
+            "v128.load64_lane" => {
+                let (arg, lane) = self.lane_mem_arg(8)?;
+                V128Load64Lane(arg, lane)
+            }
+            "v128.store8_lane" => {
+                let (arg, lane) = self.lane_mem_arg(1)?;
+                V128Store8Lane(arg, lane)
+            }
+            "v128.store16_lane" => {
+                let (arg, lane) = self.lane_mem_arg(2)?;
+                V128Store16Lane(arg, lane)
+            }
+            "v128.store32_lane" => {
+                let (arg, lane) = self.lane_mem_arg(4)?;
+                V128Store32Lane(arg, lane)
+            }
+            "v128.store64_lane" => {
+                let (arg, lane) = self.lane_mem_arg(8)?;
+                V128Store64Lane(arg, lane)
+            }
+            "v128.load32_zero" => V128Load32Zero(self.mem_arg(4)?),
+            "v128.load64_zero" => V128Load64Zero(self.mem_arg(8)?),
+            "f32x4.demote_f64x2_zero" => F32x4DemoteF64x2Zero,
+            "f64x2.promote_low_f32x4" => F64x2PromoteLowF32x4,
+            "i8x16.abs" => I8x16Abs,
+            "i8x16.neg" => I8x16Neg,
+            "i8x16.popcnt" => I8x16Popcnt,
+            "i8x16.all_true" => I8x16AllTrue,
+            "i8x16.bitmask" => I8x16Bitmask,
+            "i8x16.narrow_i16x8_s" => I8x16NarrowI16x8S,
+            "i8x16.narrow_i16x8_u" => I8x16NarrowI16x8U,
+            "f32x4.ceil" => F32x4Ceil,
+            "f32x4.floor" => F32x4Floor,
+            "f32x4.trunc" => F32x4Trunc,
+            "f32x4.nearest" => F32x4Nearest,
+            "i8x16.shl" => I8x16Shl,
+            "i8x16.shr_s" => I8x16ShrS,
+            "i8x16.shr_u" => I8x16ShrU,
+            "i8x16.add" => I8x16Add,
+            "i8x16.add_sat_s" => I8x16AddSatS,
+            "i8x16.add_sat_u" => I8x16AddSatU,
+            "i8x16.sub" => I8x16Sub,
+            "i8x16.sub_sat_s" => I8x16SubSatS,
+            "i8x16.sub_sat_u" => I8x16SubSatU,
+            "f64x2.ceil" => F64x2Ceil,
+            "f64x2.floor" => F64x2Floor,
+            "i8x16.min_s" => I8x16MinS,
+            "i8x16.min_u" => I8x16MinU,
+            "i8x16.max_s" => I8x16MaxS,
+            "i8x16.max_u" => I8x16MaxU,
+            "f64x2.trunc" => F64x2Trunc,
+            "i8x16.avgr_u" => I8x16AvgrU,
+            "i16x8.extadd_pairwise_i8x16_s" => I16x8ExtaddPairwiseI8x16S,
+            "i16x8.extadd_pairwise_i8x16_u" => I16x8ExtaddPairwiseI8x16U,
+            "i32x4.extadd_pairwise_i16x8_s" => I32x4ExtaddPairwiseI16x8S,
+            "i32x4.extadd_pairwise_i16x8_u" => I32x4ExtaddPairwiseI16x8U,
+            "i16x8.abs" => I16x8Abs,
+            "i16x8.neg" => I16x8Neg,
+            "i16x8.q15mulr_sat_s" => I16x8Q15mulrSatS,
+            "i16x8.all_true" => I16x8AllTrue,
+            "i16x8.bitmask" => I16x8Bitmask,
+            "i16x8.narrow_i32x4_s" => I16x8NarrowI32x4S,
+            "i16x8.narrow_i32x4_u" => I16x8NarrowI32x4U,
+            "i16x8.extend_low_i8x16_s" => I16x8ExtendLowI8x16S,
+            "i16x8.extend_high_i8x16_s" => I16x8ExtendHighI8x16S,
+            "i16x8.extend_low_i8x16_u" => I16x8ExtendLowI8x16U,
+            "i16x8.extend_high_i8x16_u" => I16x8ExtendHighI8x16U,
+            "i16x8.shl" => I16x8Shl,
+            "i16x8.shr_s" => I16x8ShrS,
+            "i16x8.shr_u" => I16x8ShrU,
+            "i16x8.add" => I16x8Add,
+            "i16x8.add_sat_s" => I16x8AddSatS,
+            "i16x8.add_sat_u" => I16x8AddSatU,
+            "i16x8.sub" => I16x8Sub,
+            "i16x8.sub_sat_s" => I16x8SubSatS,
+            "i16x8.sub_sat_u" => I16x8SubSatU,
+            "f64x2.nearest" => F64x2Nearest,
+            "i16x8.mul" => I16x8Mul,
+            "i16x8.min_s" => I16x8MinS,
+            "i16x8.min_u" => I16x8MinU,
+            "i16x8.max_s" => I16x8MaxS,
+            "i16x8.max_u" => I16x8MaxU,
+            "i16x8.avgr_u" => I16x8AvgrU,
+            "i16x8.extmul_low_i8x16_s" => I16x8ExtmulLowI8x16S,
+            "i16x8.extmul_high_i8x16_s" => I16x8ExtmulHighI8x16S,
+            "i16x8.extmul_low_i8x16_u" => I16x8ExtmulLowI8x16U,
+            "i16x8.extmul_high_i8x16_u" => I16x8ExtmulHighI8x16U,
+            "i32x4.abs" => I32x4Abs,
+            "i32x4.neg" => I32x4Neg,
+            "i32x4.all_true" => I32x4AllTrue,
+            "i32x4.bitmask" => I32x4Bitmask,
+            "i32x4.extend_low_i16x8_s" => I32x4ExtendLowI16x8S,
+            "i32x4.extend_high_i16x8_s" => I32x4ExtendHighI16x8S,
+            "i32x4.extend_low_i16x8_u" => I32x4ExtendLowI16x8U,
+            "i32x4.extend_high_i16x8_u" => I32x4ExtendHighI16x8U,
+            "i32x4.shl" => I32x4Shl,
+            "i32x4.shr_s" => I32x4ShrS,
+            "i32x4.shr_u" => I32x4ShrU,
+            "i32x4.add" => I32x4Add,
+            "i32x4.sub" => I32x4Sub,
+            "i32x4.mul" => I32x4Mul,
+            "i32x4.min_s" => I32x4MinS,
+            "i32x4.min_u" => I32x4MinU,
+            "i32x4.max_s" => I32x4MaxS,
+            "i32x4.max_u" => I32x4MaxU,
+            "i32x4.dot_i16x8_s" => I32x4DotI16x8S,
+            "i32x4.extmul_low_i16x8_s" => I32x4ExtmulLowI16x8S,
+            "i32x4.extmul_high_i16x8_s" => I32x4ExtmulHighI16x8S,
+            "i32x4.extmul_low_i16x8_u" => I32x4ExtmulLowI16x8U,
+            "i32x4.extmul_high_i16x8_u" => I32x4ExtmulHighI16x8U,
+            "i64x2.abs" => I64x2Abs,
+            "i64x2.neg" => I64x2Neg,
+            "i64x2.all_true" => I64x2AllTrue,
+            "i64x2.bitmask" => I64x2Bitmask,
+            "i64x2.extend_low_i32x4_s" => I64x2ExtendLowI32x4S,
+            "i64x2.extend_high_i32x4_s" => I64x2ExtendHighI32x4S,
+            "i64x2.extend_low_i32x4_u" => I64x2ExtendLowI32x4U,
+            "i64x2.extend_high_i32x4_u" => I64x2ExtendHighI32x4U,
+            "i64x2.shl" => I64x2Shl,
+            "i64x2.shr_s" => I64x2ShrS,
+            "i64x2.shr_u" => I64x2ShrU,
+            "i64x2.add" => I64x2Add,
+            "i64x2.sub" => I64x2Sub,
+            "i64x2.mul" => I64x2Mul,
+            "i64x2.eq" => I64x2Eq,
+            "i64x2.ne" => I64x2Ne,
+            "i64x2.lt_s" => I64x2LtS,
+            "i64x2.gt_s" => I64x2GtS,
+            "i64x2.le_s" => I64x2LeS,
+            "i64x2.ge_s" => I64x2GeS,
+            "i64x2.extmul_low_i32x4_s" => I64x2ExtmulLowI32x4S,
+            "i64x2.extmul_high_i32x4_s" => I64x2ExtmulHighI32x4S,
+            "i64x2.extmul_low_i32x4_u" => I64x2ExtmulLowI32x4U,
+            "i64x2.extmul_high_i32x4_u" => I64x2ExtmulHighI32x4U,
+            "f32x4.abs" => F32x4Abs,
+            "f32x4.neg" => F32x4Neg,
+            "f32x4.sqrt" => F32x4Sqrt,
+            "f32x4.add" => F32x4Add,
+            "f32x4.sub" => F32x4Sub,
+            "f32x4.mul" => F32x4Mul,
+            "f32x4.div" => F32x4Div,
+            "f32x4.min" => F32x4Min,
+            "f32x4.max" => F32x4Max,
+            "f32x4.pmin" => F32x4Pmin,
+            "f32x4.pmax" => F32x4Pmax,
+            "f64x2.abs" => F64x2Abs,
+            "f64x2.neg" => F64x2Neg,
+            "f64x2.sqrt" => F64x2Sqrt,
+            "f64x2.add" => F64x2Add,
+            "f64x2.sub" => F64x2Sub,
+            "f64x2.mul" => F64x2Mul,
+            "f64x2.div" => F64x2Div,
+            "f64x2.min" => F64x2Min,
+            "f64x2.max" => F64x2Max,
+            "f64x2.pmin" => F64x2Pmin,
+            "f64x2.pmax" => F64x2Pmax,
+            "i32x4.trunc_sat_f32x4_s" => I32x4TruncSatF32x4S,
+            "i32x4.trunc_sat_f32x4_u" => I32x4TruncSatF32x4U,
+            "f32x4.convert_i32x4_s" => F32x4ConvertI32x4S,
+            "f32x4.convert_i32x4_u" => F32x4ConvertI32x4U,
+            "i32x4.trunc_sat_f64x2_s_zero" => I32x4TruncSatF64x2SZero,
+            "i32x4.trunc_sat_f64x2_u_zero" => I32x4TruncSatF64x2UZero,
+            "f64x2.convert_low_i32x4_s" => F64x2ConvertLowI32x4S,
+            "f64x2.convert_low_i32x4_u" => F64x2ConvertLowI32x4U,
+            _ => return Ok(None),
+        };
+        Ok(Some(instr))
+    }
+
+    /// Parses the memory immediate of the atomic instruction `kw`. Returns `None` for anything
+    /// that isn't an atomic instruction.
+    fn atomic(&mut self, kw: &str) -> Result<Option<AtomicInstr>, TextError> {
+        use AtomicInstr::*;
+        let instr = match kw {
+            "atomic.fence" => AtomicFence,
+            "memory.atomic.notify" => MemoryAtomicNotify(self.mem_arg(4)?),
+            "memory.atomic.wait32" => MemoryAtomicWait32(self.mem_arg(4)?),
+            "memory.atomic.wait64" => MemoryAtomicWait64(self.mem_arg(8)?),
+            "i32.atomic.load" => I32AtomicLoad(self.mem_arg(4)?),
+            "i64.atomic.load" => I64AtomicLoad(self.mem_arg(8)?),
+            "i32.atomic.load8_u" => I32AtomicLoad8U(self.mem_arg(1)?),
+            "i32.atomic.load16_u" => I32AtomicLoad16U(self.mem_arg(2)?),
+            "i64.atomic.load8_u" => I64AtomicLoad8U(self.mem_arg(1)?),
+            "i64.atomic.load16_u" => I64AtomicLoad16U(self.mem_arg(2)?),
+            "i64.atomic.load32_u" => I64AtomicLoad32U(self.mem_arg(4)?),
+            "i32.atomic.store" => I32AtomicStore(self.mem_arg(4)?),
+            "i64.atomic.store" => I64AtomicStore(self.mem_arg(8)?),
+            "i32.atomic.store8" => I32AtomicStore8(self.mem_arg(1)?),
+            "i32.atomic.store16" => I32AtomicStore16(self.mem_arg(2)?),
+            "i64.atomic.store8" => I64AtomicStore8(self.mem_arg(1)?),
+            "i64.atomic.store16" => I64AtomicStore16(self.mem_arg(2)?),
+            "i64.atomic.store32" => I64AtomicStore32(self.mem_arg(4)?),
+            "i32.atomic.rmw.add" => I32AtomicRmwAdd(self.mem_arg(4)?),
+            "i64.atomic.rmw.add" => I64AtomicRmwAdd(self.mem_arg(8)?),
+            "i32.atomic.rmw8.add_u" => I32AtomicRmw8AddU(self.mem_arg(1)?),
+            "i32.atomic.rmw16.add_u" => I32AtomicRmw16AddU(self.mem_arg(2)?),
+            "i64.atomic.rmw8.add_u" => I64AtomicRmw8AddU(self.mem_arg(1)?),
+            "i64.atomic.rmw16.add_u" => I64AtomicRmw16AddU(self.mem_arg(2)?),
+            "i64.atomic.rmw32.add_u" => I64AtomicRmw32AddU(self.mem_arg(4)?),
+            "i32.atomic.rmw.sub" => I32AtomicRmwSub(self.mem_arg(4)?),
+            "i64.atomic.rmw.sub" => I64AtomicRmwSub(self.mem_arg(8)?),
+            "i32.atomic.rmw8.sub_u" => I32AtomicRmw8SubU(self.mem_arg(1)?),
+            "i32.atomic.rmw16.sub_u" => I32AtomicRmw16SubU(self.mem_arg(2)?),
+            "i64.atomic.rmw8.sub_u" => I64AtomicRmw8SubU(self.mem_arg(1)?),
+            "i64.atomic.rmw16.sub_u" => I64AtomicRmw16SubU(self.mem_arg(2)?),
+            "i64.atomic.rmw32.sub_u" => I64AtomicRmw32SubU(self.mem_arg(4)?),
+            "i32.atomic.rmw.and" => I32AtomicRmwAnd(self.mem_arg(4)?),
+            "i64.atomic.rmw.and" => I64AtomicRmwAnd(self.mem_arg(8)?),
+            "i32.atomic.rmw8.and_u" => I32AtomicRmw8AndU(self.mem_arg(1)?),
+            "i32.atomic.rmw16.and_u" => I32AtomicRmw16AndU(self.mem_arg(2)?),
+            "i64.atomic.rmw8.and_u" => I64AtomicRmw8AndU(self.mem_arg(1)?),
+            "i64.atomic.rmw16.and_u" => I64AtomicRmw16AndU(self.mem_arg(2)?),
+            "i64.atomic.rmw32.and_u" => I64AtomicRmw32AndU(self.mem_arg(4)?),
+            "i32.atomic.rmw.or" => I32AtomicRmwOr(self.mem_arg(4)?),
+            "i64.atomic.rmw.or" => I64AtomicRmwOr(self.mem_arg(8)?),
+            "i32.atomic.rmw8.or_u" => I32AtomicRmw8OrU(self.mem_arg(1)?),
+            "i32.atomic.rmw16.or_u" => I32AtomicRmw16OrU(self.mem_arg(2)?),
+            "i64.atomic.rmw8.or_u" => I64AtomicRmw8OrU(self.mem_arg(1)?),
+            "i64.atomic.rmw16.or_u" => I64AtomicRmw16OrU(self.mem_arg(2)?),
+            "i64.atomic.rmw32.or_u" => I64AtomicRmw32OrU(self.mem_arg(4)?),
+            "i32.atomic.rmw.xor" => I32AtomicRmwXor(self.mem_arg(4)?),
+            "i64.atomic.rmw.xor" => I64AtomicRmwXor(self.mem_arg(8)?),
+            "i32.atomic.rmw8.xor_u" => I32AtomicRmw8XorU(self.mem_arg(1)?),
+            "i32.atomic.rmw16.xor_u" => I32AtomicRmw16XorU(self.mem_arg(2)?),
+            "i64.atomic.rmw8.xor_u" => I64AtomicRmw8XorU(self.mem_arg(1)?),
+            "i64.atomic.rmw16.xor_u" => I64AtomicRmw16XorU(self.mem_arg(2)?),
+            "i64.atomic.rmw32.xor_u" => I64AtomicRmw32XorU(self.mem_arg(4)?),
+            "i32.atomic.rmw.xchg" => I32AtomicRmwXchg(self.mem_arg(4)?),
+            "i64.atomic.rmw.xchg" => I64AtomicRmwXchg(self.mem_arg(8)?),
+            "i32.atomic.rmw8.xchg_u" => I32AtomicRmw8XchgU(self.mem_arg(1)?),
+            "i32.atomic.rmw16.xchg_u" => I32AtomicRmw16XchgU(self.mem_arg(2)?),
+            "i64.atomic.rmw8.xchg_u" => I64AtomicRmw8XchgU(self.mem_arg(1)?),
+            "i64.atomic.rmw16.xchg_u" => I64AtomicRmw16XchgU(self.mem_arg(2)?),
+            "i64.atomic.rmw32.xchg_u" => I64AtomicRmw32XchgU(self.mem_arg(4)?),
+            "i32.atomic.rmw.cmpxchg" => I32AtomicRmwCmpxchg(self.mem_arg(4)?),
+            "i64.atomic.rmw.cmpxchg" => I64AtomicRmwCmpxchg(self.mem_arg(8)?),
+            "i32.atomic.rmw8.cmpxchg_u" => I32AtomicRmw8CmpxchgU(self.mem_arg(1)?),
+            "i32.atomic.rmw16.cmpxchg_u" => I32AtomicRmw16CmpxchgU(self.mem_arg(2)?),
+            "i64.atomic.rmw8.cmpxchg_u" => I64AtomicRmw8CmpxchgU(self.mem_arg(1)?),
+            "i64.atomic.rmw16.cmpxchg_u" => I64AtomicRmw16CmpxchgU(self.mem_arg(2)?),
+            "i64.atomic.rmw32.cmpxchg_u" => I64AtomicRmw32CmpxchgU(self.mem_arg(4)?),
+            _ => return Ok(None),
+        };
+        Ok(Some(instr))
+    }
+
+    /// Parses the immediates of the struct or array instruction `kw`. Returns `None` for anything
+    /// that isn't one.
+    fn aggregate(&mut self, kw: &str) -> Result<Option<AggregateInstr>, TextError> {
+        use AggregateInstr::*;
+        let instr = match kw {
+            "struct.new" => StructNew(self.tpe()?),
+            "struct.new_default" => StructNewDefault(self.tpe()?),
+            "struct.get" | "struct.get_s" | "struct.get_u" => {
+                let sign = match kw {
+                    "struct.get_s" => Some(Sign::Signed),
+                    "struct.get_u" => Some(Sign::Unsigned),
+                    _ => None,
+                };
+                let tpe = self.tpe()?;
+                let field = self.struct_field(&tpe)?;
+                StructGet(sign, tpe, field)
+            }
+            "struct.set" => {
+                let tpe = self.tpe()?;
+                let field = self.struct_field(&tpe)?;
+                StructSet(tpe, field)
+            }
+            "array.new" => ArrayNew(self.tpe()?),
+            "array.new_default" => ArrayNewDefault(self.tpe()?),
+            "array.new_fixed" => {
+                let tpe = self.tpe()?;
+                ArrayNewFixed(tpe, U32(self.u32()?))
+            }
+            "array.new_data" => {
+                let tpe = self.tpe()?;
+                ArrayNewData(tpe, DataIdx(U32(self.index(Space::Data)?)))
+            }
+            "array.new_elem" => {
+                let tpe = self.tpe()?;
+                ArrayNewElem(tpe, ElemIdx(U32(self.index(Space::Elem)?)))
+            }
+            "array.get" => ArrayGet(None, self.tpe()?),
+            "array.get_s" => ArrayGet(Some(Sign::Signed), self.tpe()?),
+            "array.get_u" => ArrayGet(Some(Sign::Unsigned), self.tpe()?),
+            "array.set" => ArraySet(self.tpe()?),
+            "array.len" => ArrayLen,
+            "array.fill" => ArrayFill(self.tpe()?),
+            "array.copy" => {
+                let dst = self.tpe()?;
+                let src = self.tpe()?;
+                ArrayCopy(dst, src)
+            }
+            "array.init_data" => {
+                let tpe = self.tpe()?;
+                ArrayInitData(tpe, DataIdx(U32(self.index(Space::Data)?)))
+            }
+            "array.init_elem" => {
+                let tpe = self.tpe()?;
+                ArrayInitElem(tpe, ElemIdx(U32(self.index(Space::Elem)?)))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(instr))
+    }
+
+    fn lane(&mut self) -> Result<LaneIdx, TextError> {
+        let lane = self.number(|text| values::parse_u32(text).filter(|&x| x <= 0xFF))?;
+        Ok(LaneIdx(Byte(lane as u8)))
+    }
+
+    /// Parses the shape and lanes of a `v128.const`.
+    pub(crate) fn v128(&mut self) -> Result<V128, TextError> {
+        let (lanes, parse): (u32, fn(&str) -> Option<u64>) = match self.peek() {
+            Some(Token::Keyword("i8x16")) => (16, |text| values::parse_i8(text).map(u64::from)),
+            Some(Token::Keyword("i16x8")) => (8, |text| values::parse_i16(text).map(u64::from)),
+            Some(Token::Keyword("i32x4")) => (4, |text| values::parse_i32(text).map(u64::from)),
+            Some(Token::Keyword("i64x2")) => (2, values::parse_i64),
+            Some(Token::Keyword("f32x4")) => (4, |text| values::parse_f32(text).map(u64::from)),
+            Some(Token::Keyword("f64x2")) => (2, values::parse_f64),
+            _ => return Err(self.unexpected("vector shape")),
+        };
+        self.next()?;
+        let bits = 128 / lanes;
+        let mut value = 0;
+        for i in 0..lanes {
+            value |= u128::from(self.number(parse)?) << (i * bits);
+        }
+        Ok(V128(value))
+    }
+
+    fn local(&mut self) -> Result<LocalIdx, TextError> {
+        Ok(LocalIdx(U32(self.index(Space::Local)?)))
+    }
+
+    fn global(&mut self) -> Result<GlobalIdx, TextError> {
+        Ok(GlobalIdx(U32(self.index(Space::Global)?)))
+    }
+
+    fn tpe(&mut self) -> Result<TypeIdx, TextError> {
+        Ok(TypeIdx(U32(self.index(Space::Type)?)))
+    }
+
+    /// Field of the struct type `tpe`.
+    fn struct_field(&mut self, tpe: &TypeIdx) -> Result<FieldIdx, TextError> {
+        Ok(FieldIdx(U32(self.field_index(*tpe.0)?)))
+    }
+
+    fn tag(&mut self) -> Result<TagIdx, TextError> {
+        Ok(TagIdx(U32(self.index(Space::Tag)?)))
+    }
+
+    /// Table index immediate, which defaults to table 0 when omitted.
+    fn table(&mut self) -> Result<TableIdx, TextError> {
+        Ok(TableIdx(U32(self.eat_index(Space::Table)?.unwrap_or(0))))
+    }
+
+    /// Table and type use of `call_indirect` and `return_call_indirect`, whose parameters can't
+    /// be named.
+    fn call_indirect(&mut self) -> Result<(TableIdx, TypeIdx), TextError> {
+        let table = self.table()?;
+        let (tpe, names) = self.type_use()?;
+        if let Some(Some(id)) = names.into_iter().find(Option::is_some) {
+            return Err(self.error(TextErrorKind::Unexpected {
+                expected: "unnamed parameter",
+                found: id.to_string(),
+            }));
+        }
+        Ok((table, TypeIdx(U32(tpe))))
+    }
+
+    /// Memory index immediate, which defaults to memory 0 when omitted.
+    fn memory(&mut self) -> Result<MemIdx, TextError> {
+        Ok(MemIdx(U32(self.eat_index(Space::Mem)?.unwrap_or(0))))
+    }
+}
+
+/// Numeric instructions without immediates.
+fn numeric(name: &str) -> Option<NumericInstr> {
+    use NumericInstr::*;
+    let instr = match name {
+        "i32.extend8_s" => I32Extend8S,
+        "i64.extend8_s" => I64Extend8S,
+        "i32.extend16_s" => I32Extend16S,
+        "i64.extend16_s" => I64Extend16S,
+        "i64.extend32_s" => I64Extend32S,
+        "i32.wrap_i64" => I32WrapI64,
+        "i64.extend_i32_s" => I64ExtendI32S,
+        "i64.extend_i32_u" => I64ExtendI32U,
+        "i32.trunc_f32_s" => I32TruncF32S,
+        "i32.trunc_f32_u" => I32TruncF32U,
+        "i64.trunc_f32_s" => I64TruncF32S,
+        "i64.trunc_f32_u" => I64TruncF32U,
+        "i32.trunc_f64_s" => I32TruncF64S,
+        "i32.trunc_f64_u" => I32TruncF64U,
+        "i64.trunc_f64_s" => I64TruncF64S,
+        "i64.trunc_f64_u" => I64TruncF64U,
+        "i32.trunc_sat_f32_s" => I32TruncSatF32S,
+        "i32.trunc_sat_f32_u" => I32TruncSatF32U,
+        "i32.trunc_sat_f64_s" => I32TruncSatF64S,
+        "i32.trunc_sat_f64_u" => I32TruncSatF64U,
+        "i64.trunc_sat_f32_s" => I64TruncSatF32S,
+        "i64.trunc_sat_f32_u" => I64TruncSatF32U,
+        "i64.trunc_sat_f64_s" => I64TruncSatF64S,
+        "i64.trunc_sat_f64_u" => I64TruncSatF64U,
+        "f32.demote_f64" => F32DemoteF64,
+        "f64.promote_f32" => F64PromoteF32,
+        "f32.convert_i32_s" => F32ConvertI32S,
+        "f32.convert_i32_u" => F32ConvertI32U,
+        "f32.convert_i64_s" => F32ConvertI64S,
+        "f32.convert_i64_u" => F32ConvertI64U,
+        "f64.convert_i32_s" => F64ConvertI32S,
+        "f64.convert_i32_u" => F64ConvertI32U,
+        "f64.convert_i64_s" => F64ConvertI64S,
+        "f64.convert_i64_u" => F64ConvertI64U,
+        "i32.reinterpret_f32" => I32ReinterpretF32,
+        "i64.reinterpret_f64" => I64ReinterpretF64,
+        "f32.reinterpret_i32" => F32ReinterpretI32,
+        "f64.reinterpret_i64" => F64ReinterpretI64,
+        _ => {
+            let (tpe, op) = name.split_once('.')?;
+            return match tpe {
+                "i32" => iunop(op)
+                    .map(I32Unary)
+                    .or_else(|| ibinop(op).map(I32Binary))
+                    .or_else(|| itestop(op).map(I32Test))
+                    .or_else(|| irelop(op).map(I32Relop)),
+                "i64" => iunop(op)
+                    .map(I64Unary)
+                    .or_else(|| ibinop(op).map(I64Binary))
+                    .or_else(|| itestop(op).map(I64Test))
+                    .or_else(|| irelop(op).map(I64Relop)),
+                "f32" => funop(op)
+                    .map(F32Unary)
+                    .or_else(|| fbinop(op).map(F32Binary))
+                    .or_else(|| frelop(op).map(F32Relop)),
+                "f64" => funop(op)
+                    .map(F64Unary)
+                    .or_else(|| fbinop(op).map(F64Binary))
+                    .or_else(|| frelop(op).map(F64Relop)),
+                _ => None,
+            };
+        }
+    };
+    Some(instr)
+}
+
+fn iunop<const T: IntType>(op: &str) -> Option<IUnop<T>> {
+    Some(match op {
+        "clz" => IUnop::Clz,
+        "ctz" => IUnop::Ctz,
+        "popcnt" => IUnop::Popcnt,
+        _ => return None,
+    })
+}
+
+fn ibinop<const T: IntType>(op: &str) -> Option<IBinop<T>> {
+    Some(match op {
+        "add" => IBinop::Add,
+        "sub" => IBinop::Sub,
+        "mul" => IBinop::Mul,
+        "div_s" => IBinop::DivS,
+        "div_u" => IBinop::DivU,
+        "rem_s" => IBinop::RemS,
+        "rem_u" => IBinop::RemU,
+        "and" => IBinop::And,
+        "or" => IBinop::Or,
+        "xor" => IBinop::Xor,
+        "shl" => IBinop::Shl,
+        "shr_s" => IBinop::ShrS,
+        "shr_u" => IBinop::ShrU,
+        "rotl" => IBinop::Rotl,
+        "rotr" => IBinop::Rotr,
+        _ => return None,
+    })
+}
+
+fn itestop<const T: IntType>(op: &str) -> Option<ITestop<T>> {
+    Some(match op {
+        "eqz" => ITestop::Eqz,
+        _ => return None,
+    })
+}
+
+fn irelop<const T: IntType>(op: &str) -> Option<IRelop<T>> {
+    Some(match op {
+        "eq" => IRelop::Equ,
+        "ne" => IRelop::Ne,
+        "lt_s" => IRelop::LtS,
+        "lt_u" => IRelop::LtU,
+        "gt_s" => IRelop::GtS,
+        "gt_u" => IRelop::GtU,
+        "le_s" => IRelop::LeS,
+        "le_u" => IRelop::LeU,
+        "ge_s" => IRelop::GeS,
+        "ge_u" => IRelop::GeU,
+        _ => return None,
+    })
+}
+
+fn funop<const T: FloatType>(op: &str) -> Option<FUnop<T>> {
+    Some(match op {
+        "abs" => FUnop::Abs,
+        "neg" => FUnop::Neg,
+        "sqrt" => FUnop::Sqrt,
+        "ceil" => FUnop::Ceil,
+        "floor" => FUnop::Floor,
+        "trunc" => FUnop::Trunc,
+        "nearest" => FUnop::Nearest,
+        _ => return None,
+    })
+}
+
+fn fbinop<const T: FloatType>(op: &str) -> Option<FBinop<T>> {
+    Some(match op {
+        "add" => FBinop::Add,
+        "sub" => FBinop::Sub,
+        "mul" => FBinop::Mul,
+        "div" => FBinop::Div,
+        "min" => FBinop::Min,
+        "max" => FBinop::Max,
+        "copysign" => FBinop::CopySign,
+        _ => return None,
+    })
+}
+
+fn frelop<const T: FloatType>(op: &str) -> Option<FRelop<T>> {
+    Some(match op {
+        "eq" => FRelop::Equ,
+        "ne" => FRelop::Ne,
+        "lt" => FRelop::Lt,
+        "gt" => FRelop::Gt,
+        "le" => FRelop::Le,
+        "ge" => FRelop::Ge,
+        _ => return None,
+    })
+}
@@ -0,0 +1,208 @@
+//! Splits the text format into tokens.
+
+use super::{TextError, TextErrorKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token<'a> {
+    LParen,
+    RParen,
+    /// Bare word starting with a lowercase letter (e.g. `module`, `i32.add`, `offset=4`).
+    Keyword(&'a str),
+    /// Symbolic identifier including the leading `$`.
+    Id(&'a str),
+    /// Integer or float literal, kept as written. The expected type decides how to read it.
+    Number(&'a str),
+    /// Contents of a string literal, without the quotes and with the escapes left in place.
+    String(&'a str),
+    /// Any other sequence of identifier characters. These are always an error.
+    Reserved(&'a str),
+}
+
+/// A token along with the byte offset at which it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Spanned<'a> {
+    pub token: Token<'a>,
+    pub offset: usize,
+}
+
+pub(crate) fn is_idchar(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&c)
+}
+
+fn is_number(word: &str) -> bool {
+    let word = word.strip_prefix(&['+', '-'][..]).unwrap_or(word);
+    word.starts_with(|c: char| c.is_ascii_digit())
+        || word == "inf"
+        || word == "nan"
+        || word.starts_with("nan:")
+}
+
+/// Splits `text` into tokens, dropping whitespace and comments.
+pub(crate) fn tokenize(text: &str) -> Result<Vec<Spanned<'_>>, TextError> {
+    let bytes = text.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let start = pos;
+        let token = match bytes[pos] {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                pos += 1;
+                continue;
+            }
+            b';' if bytes.get(pos + 1) == Some(&b';') => {
+                pos = bytes[pos..]
+                    .iter()
+                    .position(|&c| c == b'\n')
+                    .map_or(bytes.len(), |end| pos + end);
+                continue;
+            }
+            b'(' if bytes.get(pos + 1) == Some(&b';') => {
+                pos = block_comment(text, pos)?;
+                continue;
+            }
+            b'(' => {
+                pos += 1;
+                Token::LParen
+            }
+            b')' => {
+                pos += 1;
+                Token::RParen
+            }
+            b'"' => {
+                pos += 1;
+                loop {
+                    match bytes.get(pos) {
+                        None | Some(b'\n') => {
+                            return Err(TextError::new(
+                                text,
+                                start,
+                                TextErrorKind::UnterminatedString,
+                            ))
+                        }
+                        Some(b'"') => break,
+                        Some(b'\\') => pos += 2,
+                        Some(_) => pos += 1,
+                    }
+                }
+                pos += 1;
+                Token::String(&text[start + 1..pos - 1])
+            }
+            c if is_idchar(c) => {
+                while pos < bytes.len() && is_idchar(bytes[pos]) {
+                    pos += 1;
+                }
+                let word = &text[start..pos];
+                if is_number(word) {
+                    Token::Number(word)
+                } else if word.starts_with('$') && word.len() > 1 {
+                    Token::Id(word)
+                } else if word.starts_with(|c: char| c.is_ascii_lowercase()) {
+                    Token::Keyword(word)
+                } else {
+                    Token::Reserved(word)
+                }
+            }
+            _ => {
+                let c = text[start..]
+                    .chars()
+                    .next()
+                    .expect("Not at the end of the text");
+                return Err(TextError::new(
+                    text,
+                    start,
+                    TextErrorKind::UnexpectedChar(c),
+                ));
+            }
+        };
+        tokens.push(Spanned {
+            token,
+            offset: start,
+        });
+    }
+    Ok(tokens)
+}
+
+/// Skips a (possibly nested) block comment starting at `start` and returns the offset after it.
+fn block_comment(text: &str, start: usize) -> Result<usize, TextError> {
+    let bytes = text.as_bytes();
+    let mut depth = 0;
+    let mut pos = start;
+    while pos + 1 < bytes.len() {
+        match &bytes[pos..pos + 2] {
+            b"(;" => {
+                depth += 1;
+                pos += 2;
+            }
+            b";)" => {
+                depth -= 1;
+                pos += 2;
+                if depth == 0 {
+                    return Ok(pos);
+                }
+            }
+            _ => pos += 1,
+        }
+    }
+    Err(TextError::new(
+        text,
+        start,
+        TextErrorKind::UnterminatedComment,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<Token<'_>> {
+        tokenize(text)
+            .unwrap()
+            .into_iter()
+            .map(|spanned| spanned.token)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            tokens(r#"(module $m (func i32.load offset=4 -1 0x1p3 "a\"b" inf -nan:0x1 A))"#),
+            vec![
+                Token::LParen,
+                Token::Keyword("module"),
+                Token::Id("$m"),
+                Token::LParen,
+                Token::Keyword("func"),
+                Token::Keyword("i32.load"),
+                Token::Keyword("offset=4"),
+                Token::Number("-1"),
+                Token::Number("0x1p3"),
+                Token::String(r#"a\"b"#),
+                Token::Number("inf"),
+                Token::Number("-nan:0x1"),
+                Token::Reserved("A"),
+                Token::RParen,
+                Token::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(
+            tokens("(;a (; nested ;) b;) x ;; line ( comment\n y (;;)"),
+            vec![Token::Keyword("x"), Token::Keyword("y")]
+        );
+        let err = tokenize("x (; (; ;)").unwrap_err();
+        assert_eq!(err.kind(), &TextErrorKind::UnterminatedComment);
+        assert_eq!(err.location(), 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = tokenize("\n  \"abc").unwrap_err();
+        assert_eq!(err.kind(), &TextErrorKind::UnterminatedString);
+        assert_eq!((err.line(), err.column()), (2, 3));
+        let err = tokenize("(module {)").unwrap_err();
+        assert_eq!(err.kind(), &TextErrorKind::UnexpectedChar('{'));
+    }
+}
@@ -0,0 +1,911 @@
+//! Module fields.
+//!
+//! Modules are parsed in two passes. The first one binds the identifiers of every field and
+//! collects the explicit type definitions, which lets fields refer to entities defined further
+//! down. The second pass parses the fields themselves.
+
+use std::mem;
+
+use super::lexer::Token;
+use super::parser::{Parser, Space};
+use super::{TextError, TextErrorKind};
+use crate::wasm::data::{Data, DataMode};
+use crate::wasm::elem::{Elem, ElemMode};
+use crate::wasm::export::{Export, ExportDesc};
+use crate::wasm::func::Func;
+use crate::wasm::global::Global;
+use crate::wasm::import::{Import, ImportDesc};
+use crate::wasm::indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx, TagIdx, TypeIdx};
+use crate::wasm::instr::{Expr, Instr, NumericInstr, ReferenceInstr};
+use crate::wasm::mem::Mem;
+use crate::wasm::module::Module;
+use crate::wasm::start::Start;
+use crate::wasm::table::Table;
+use crate::wasm::tag::Tag;
+use crate::wasm::types::{IndexType, Limits, MemType, RecType, RefType, TableType};
+use crate::wasm::values::{Byte, Name, I32, I64, U32, U64};
+
+/// Size of a memory page in bytes.
+const PAGE_SIZE: usize = 65536;
+
+/// Offset of the segments defined inline within tables and memories, whose addresses are of
+/// type `index`.
+fn zero_offset(index: IndexType) -> Expr {
+    let instr = match index {
+        IndexType::I32 => NumericInstr::I32Const(I32(0)),
+        IndexType::I64 => NumericInstr::I64Const(I64(0)),
+    };
+    Expr {
+        instr: vec![Instr::Numeric(instr)],
+    }
+}
+
+/// Number of entries in an index space so far, imports included.
+fn count(module: &Module, space: Space) -> u32 {
+    let imports = module
+        .imports
+        .iter()
+        .filter(|import| {
+            matches!(
+                (&import.desc, space),
+                (ImportDesc::Func(_), Space::Func)
+                    | (ImportDesc::Table(_), Space::Table)
+                    | (ImportDesc::Mem(_), Space::Mem)
+                    | (ImportDesc::Global(_), Space::Global)
+                    | (ImportDesc::Tag(_), Space::Tag)
+            )
+        })
+        .count();
+    let defined = match space {
+        Space::Func => module.funcs.len(),
+        Space::Table => module.tables.len(),
+        Space::Mem => module.mems.len(),
+        Space::Global => module.globals.len(),
+        Space::Tag => module.tags.len(),
+        _ => unreachable!("Only functions, tables, memories, globals and tags can be imported"),
+    };
+    (imports + defined) as u32
+}
+
+/// Index space of the imports and exports of the given kind.
+fn extern_space(kw: &str) -> Option<Space> {
+    match kw {
+        "func" => Some(Space::Func),
+        "table" => Some(Space::Table),
+        "memory" => Some(Space::Mem),
+        "global" => Some(Space::Global),
+        "tag" => Some(Space::Tag),
+        _ => None,
+    }
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn module(&mut self) -> Result<Module, TextError> {
+        let wrapped = self.eat_field("module");
+        if wrapped {
+            if let Some(id) = self.eat_id() {
+                self.names.module = Some(Name {
+                    name: id[1..].to_string(),
+                });
+            }
+        }
+        let start = self.position();
+        let defs = self.scan()?;
+        self.type_defs(defs)?;
+        self.rewind(start);
+
+        let mut module = Module::default();
+        while self.peek() == Some(&Token::LParen) {
+            self.field(&mut module)?;
+        }
+        if wrapped {
+            self.rparen()?;
+        }
+        module.types = mem::take(&mut self.types);
+        module.names = mem::take(&mut self.names);
+        module.name = module.names.module.clone();
+        Ok(module)
+    }
+
+    /// Binds the identifiers of the module fields. Returns the positions of the type definitions
+    /// and recursion groups, which are parsed once all the type identifiers are bound.
+    fn scan(&mut self) -> Result<Vec<usize>, TextError> {
+        let mut defs = Vec::new();
+        // Whether a function, table, memory, global or tag was defined (rather than imported).
+        let mut defined = false;
+        while self.peek() == Some(&Token::LParen) {
+            self.lparen()?;
+            let pos = self.position();
+            match self.any_keyword()? {
+                "type" => {
+                    defs.push(pos);
+                    let id = self.eat_id();
+                    self.define(Space::Type, id)?;
+                    self.skip_to_close()?;
+                }
+                "rec" => {
+                    defs.push(pos);
+                    while self.eat_field("type") {
+                        let id = self.eat_id();
+                        self.define(Space::Type, id)?;
+                        self.skip_to_close()?;
+                    }
+                    self.rparen()?;
+                }
+                "import" => {
+                    if defined {
+                        return Err(self.error_at(pos, TextErrorKind::ImportAfterDefinition));
+                    }
+                    self.name()?;
+                    self.name()?;
+                    self.lparen()?;
+                    let space = self.extern_space()?;
+                    let id = self.eat_id();
+                    self.define(space, id)?;
+                    self.skip_to_close()?;
+                    self.rparen()?;
+                }
+                kw @ "func" | kw @ "table" | kw @ "memory" | kw @ "global" | kw @ "tag" => {
+                    let space = extern_space(kw).expect("Known kind");
+                    let id = self.eat_id();
+                    self.define(space, id)?;
+                    while self.eat_field("export") {
+                        self.skip_to_close()?;
+                    }
+                    if self.peek_field("import") {
+                        if defined {
+                            return Err(self.error_at(pos, TextErrorKind::ImportAfterDefinition));
+                        }
+                    } else {
+                        defined = true;
+                        if space == Space::Table && self.peek_reftype() {
+                            self.define(Space::Elem, None)?;
+                        } else if space == Space::Mem && self.peek_field("data") {
+                            self.define(Space::Data, None)?;
+                        }
+                    }
+                    self.skip_to_close()?;
+                }
+                "elem" => {
+                    let id = self.eat_id();
+                    self.define(Space::Elem, id)?;
+                    self.skip_to_close()?;
+                }
+                "data" => {
+                    let id = self.eat_id();
+                    self.define(Space::Data, id)?;
+                    self.skip_to_close()?;
+                }
+                "export" | "start" => self.skip_to_close()?,
+                _ => {
+                    self.rewind(pos);
+                    return Err(self.unexpected("module field"));
+                }
+            }
+        }
+        Ok(defs)
+    }
+
+    /// Parses the type definitions and recursion groups at the positions found by the first
+    /// pass, in order.
+    fn type_defs(&mut self, defs: Vec<usize>) -> Result<(), TextError> {
+        let mut idx = 0;
+        for pos in defs {
+            self.rewind(pos);
+            let rec = if self.any_keyword()? == "rec" {
+                self.rec_type(idx)?
+            } else {
+                RecType {
+                    types: vec![self.type_def(idx)?],
+                }
+            };
+            idx += rec.types.len() as u32;
+            self.types.push(rec);
+        }
+        Ok(())
+    }
+
+    /// Consumes the kind of an import or export description.
+    fn extern_space(&mut self) -> Result<Space, TextError> {
+        match self.peek() {
+            Some(&Token::Keyword(kw)) if extern_space(kw).is_some() => {
+                self.next()?;
+                Ok(extern_space(kw).expect("Known kind"))
+            }
+            _ => Err(self.unexpected("func, table, memory, global or tag")),
+        }
+    }
+
+    fn field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        self.lparen()?;
+        match self.any_keyword()? {
+            // Types were parsed after the first pass.
+            "type" | "rec" => self.skip_to_close(),
+            "import" => self.import_field(module),
+            "func" => self.func_field(module),
+            "table" => self.table_field(module),
+            "memory" => self.memory_field(module),
+            "global" => self.global_field(module),
+            "tag" => self.tag_field(module),
+            "export" => self.export_field(module),
+            "start" => self.start_field(module),
+            "elem" => self.elem_field(module),
+            "data" => self.data_field(module),
+            _ => unreachable!("Fields were checked by the first pass"),
+        }
+    }
+
+    /// Parses the `(export "name")` abbreviations of a definition.
+    fn inline_exports(&mut self) -> Result<Vec<Name>, TextError> {
+        let mut names = Vec::new();
+        while self.eat_field("export") {
+            names.push(self.name()?);
+            self.rparen()?;
+        }
+        Ok(names)
+    }
+
+    /// Parses the `(import "module" "name")` abbreviation of a definition.
+    fn inline_import(&mut self) -> Result<Option<(Name, Name)>, TextError> {
+        if !self.eat_field("import") {
+            return Ok(None);
+        }
+        let module = self.name()?;
+        let name = self.name()?;
+        self.rparen()?;
+        Ok(Some((module, name)))
+    }
+
+    fn import_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        let module_name = self.name()?;
+        let name = self.name()?;
+        self.lparen()?;
+        let space = self.extern_space()?;
+        self.eat_id();
+        let desc = match space {
+            Space::Func => ImportDesc::Func(TypeIdx(U32(self.type_use()?.0))),
+            Space::Table => ImportDesc::Table(self.table_type()?),
+            Space::Mem => ImportDesc::Mem(self.mem_type()?),
+            Space::Tag => ImportDesc::Tag(TypeIdx(U32(self.type_use()?.0))),
+            _ => ImportDesc::Global(self.global_type()?),
+        };
+        self.rparen()?;
+        self.rparen()?;
+        module.imports.push(Import {
+            module: module_name,
+            name,
+            desc,
+        });
+        Ok(())
+    }
+
+    fn func_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        self.eat_id();
+        let idx = count(module, Space::Func);
+        for name in self.inline_exports()? {
+            module.exports.push(Export {
+                name,
+                desc: ExportDesc::Func(FuncIdx(U32(idx))),
+            });
+        }
+        let import = self.inline_import()?;
+        let (tpe, params) = self.type_use()?;
+        if let Some((module_name, name)) = import {
+            module.imports.push(Import {
+                module: module_name,
+                name,
+                desc: ImportDesc::Func(TypeIdx(U32(tpe))),
+            });
+            return self.rparen();
+        }
+
+        self.reset_func();
+        for param in params {
+            self.define(Space::Local, param)?;
+        }
+        let mut locals = Vec::new();
+        while self.eat_field("local") {
+            if let Some(id) = self.eat_id() {
+                self.define(Space::Local, Some(id))?;
+                locals.push(self.valtype()?);
+            } else {
+                while self.peek() != Some(&Token::RParen) {
+                    self.define(Space::Local, None)?;
+                    locals.push(self.valtype()?);
+                }
+            }
+            self.rparen()?;
+        }
+        let body = self.expr()?;
+        self.rparen()?;
+
+        let local_names = mem::take(&mut self.local_names);
+        if !local_names.is_empty() {
+            self.names.locals.names.insert(idx, local_names);
+        }
+        let label_names = mem::take(&mut self.label_names);
+        if !label_names.is_empty() {
+            self.names.labels.names.insert(idx, label_names);
+        }
+        module.funcs.push(Func {
+            index: TypeIdx(U32(tpe)),
+            locals,
+            body,
+        });
+        Ok(())
+    }
+
+    fn table_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        self.eat_id();
+        let idx = count(module, Space::Table);
+        for name in self.inline_exports()? {
+            module.exports.push(Export {
+                name,
+                desc: ExportDesc::Table(TableIdx(U32(idx))),
+            });
+        }
+        if let Some((module_name, name)) = self.inline_import()? {
+            module.imports.push(Import {
+                module: module_name,
+                name,
+                desc: ImportDesc::Table(self.table_type()?),
+            });
+            return self.rparen();
+        }
+        let index = self.index_type();
+        if self.peek_reftype() {
+            // `(table reftype (elem ...))` defines a table exactly large enough for the segment.
+            let tpe = self.reftype()?;
+            self.lparen()?;
+            self.keyword("elem")?;
+            let init = self.elem_items(tpe == RefType::FUNCREF)?;
+            self.rparen()?;
+            let size = Some(U64(init.len() as u64));
+            module.tables.push(Table {
+                tpe: TableType {
+                    lim: Limits {
+                        min: U64(init.len() as u64),
+                        max: size,
+                    },
+                    index,
+                    tpe,
+                },
+                init: None,
+            });
+            module.elems.push(Elem {
+                tpe,
+                init,
+                mode: ElemMode::Active {
+                    table: TableIdx(U32(idx)),
+                    offset: zero_offset(index),
+                },
+            });
+        } else {
+            // The table type can be followed by the expression initializing its elements.
+            let tpe = self.table_type_of(index)?;
+            let init = match self.peek() {
+                Some(Token::RParen) => None,
+                _ => Some(self.expr()?),
+            };
+            module.tables.push(Table { tpe, init });
+        }
+        self.rparen()
+    }
+
+    fn memory_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        self.eat_id();
+        let idx = count(module, Space::Mem);
+        for name in self.inline_exports()? {
+            module.exports.push(Export {
+                name,
+                desc: ExportDesc::Mem(MemIdx(U32(idx))),
+            });
+        }
+        if let Some((module_name, name)) = self.inline_import()? {
+            module.imports.push(Import {
+                module: module_name,
+                name,
+                desc: ImportDesc::Mem(self.mem_type()?),
+            });
+            return self.rparen();
+        }
+        let index = self.index_type();
+        if self.eat_field("data") {
+            // `(memory (data ...))` defines a memory exactly large enough for the segment.
+            let init = self.data_strings()?;
+            self.rparen()?;
+            let pages = U64(((init.len() + PAGE_SIZE - 1) / PAGE_SIZE) as u64);
+            module.mems.push(Mem {
+                tpe: MemType {
+                    lim: Limits {
+                        min: pages,
+                        max: Some(pages),
+                    },
+                    index,
+                    shared: false,
+                },
+            });
+            module.datas.push(Data {
+                init,
+                mode: DataMode::Active {
+                    memory: MemIdx(U32(idx)),
+                    offset: zero_offset(index),
+                },
+            });
+        } else {
+            module.mems.push(Mem {
+                tpe: self.mem_type_of(index)?,
+            });
+        }
+        self.rparen()
+    }
+
+    fn global_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        self.eat_id();
+        let idx = count(module, Space::Global);
+        for name in self.inline_exports()? {
+            module.exports.push(Export {
+                name,
+                desc: ExportDesc::Global(GlobalIdx(U32(idx))),
+            });
+        }
+        let import = self.inline_import()?;
+        let tpe = self.global_type()?;
+        if let Some((module_name, name)) = import {
+            module.imports.push(Import {
+                module: module_name,
+                name,
+                desc: ImportDesc::Global(tpe),
+            });
+        } else {
+            let init = self.expr()?;
+            module.globals.push(Global { tpe, init });
+        }
+        self.rparen()
+    }
+
+    fn tag_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        self.eat_id();
+        let idx = count(module, Space::Tag);
+        for name in self.inline_exports()? {
+            module.exports.push(Export {
+                name,
+                desc: ExportDesc::Tag(TagIdx(U32(idx))),
+            });
+        }
+        let import = self.inline_import()?;
+        let tpe = TypeIdx(U32(self.type_use()?.0));
+        if let Some((module_name, name)) = import {
+            module.imports.push(Import {
+                module: module_name,
+                name,
+                desc: ImportDesc::Tag(tpe),
+            });
+        } else {
+            module.tags.push(Tag { tpe });
+        }
+        self.rparen()
+    }
+
+    fn export_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        let name = self.name()?;
+        self.lparen()?;
+        let space = self.extern_space()?;
+        let idx = U32(self.index(space)?);
+        let desc = match space {
+            Space::Func => ExportDesc::Func(FuncIdx(idx)),
+            Space::Table => ExportDesc::Table(TableIdx(idx)),
+            Space::Mem => ExportDesc::Mem(MemIdx(idx)),
+            Space::Tag => ExportDesc::Tag(TagIdx(idx)),
+            _ => ExportDesc::Global(GlobalIdx(idx)),
+        };
+        self.rparen()?;
+        self.rparen()?;
+        module.exports.push(Export { name, desc });
+        Ok(())
+    }
+
+    fn start_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        if module.start.is_some() {
+            return Err(self.error(TextErrorKind::MultipleStart));
+        }
+        let func = FuncIdx(U32(self.index(Space::Func)?));
+        module.start = Some(Start { func });
+        self.rparen()
+    }
+
+    fn elem_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        self.eat_id();
+        if self.eat_keyword("declare") {
+            let (tpe, init) = self.elem_list()?;
+            module.elems.push(Elem {
+                tpe,
+                init,
+                mode: ElemMode::Declarative,
+            });
+            return self.rparen();
+        }
+        let table = if self.eat_field("table") {
+            let idx = self.index(Space::Table)?;
+            self.rparen()?;
+            Some(idx)
+        } else {
+            // Older versions of the text format allowed the table index on its own.
+            self.eat_index(Space::Table)?
+        };
+        let offset = self.offset()?;
+        let mode = match offset {
+            Some(offset) => ElemMode::Active {
+                table: TableIdx(U32(table.unwrap_or(0))),
+                offset,
+            },
+            None if table.is_some() => return Err(self.unexpected("(offset")),
+            None => ElemMode::Passive,
+        };
+        let (tpe, init) = self.elem_list()?;
+        module.elems.push(Elem { tpe, init, mode });
+        self.rparen()
+    }
+
+    fn data_field(&mut self, module: &mut Module) -> Result<(), TextError> {
+        self.eat_id();
+        let memory = if self.eat_field("memory") {
+            let idx = self.index(Space::Mem)?;
+            self.rparen()?;
+            Some(idx)
+        } else {
+            // Older versions of the text format allowed the memory index on its own.
+            self.eat_index(Space::Mem)?
+        };
+        let offset = self.offset()?;
+        let mode = match offset {
+            Some(offset) => DataMode::Active {
+                memory: MemIdx(U32(memory.unwrap_or(0))),
+                offset,
+            },
+            None if memory.is_some() => return Err(self.unexpected("(offset")),
+            None => DataMode::Passive,
+        };
+        let init = self.data_strings()?;
+        module.datas.push(Data { init, mode });
+        self.rparen()
+    }
+
+    /// Parses the offset of an active segment, either `(offset instr*)` or a single folded
+    /// instruction.
+    fn offset(&mut self) -> Result<Option<Expr>, TextError> {
+        if self.eat_field("offset") {
+            let offset = self.expr()?;
+            self.rparen()?;
+            Ok(Some(offset))
+        } else if self.peek() == Some(&Token::LParen) && !self.peek_field("item") {
+            let instr = self.folded()?;
+            Ok(Some(Expr { instr }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Parses the type and initializers of an element segment: `func funcidx*`,
+    /// `reftype elemexpr*` or a bare list of function indices.
+    fn elem_list(&mut self) -> Result<(RefType, Vec<Expr>), TextError> {
+        if self.eat_keyword("func") {
+            Ok((RefType::FUNCREF, self.elem_items(true)?))
+        } else if self.peek_reftype() {
+            let tpe = self.reftype()?;
+            Ok((tpe, self.elem_items(false)?))
+        } else {
+            Ok((RefType::FUNCREF, self.elem_items(true)?))
+        }
+    }
+
+    /// Parses element initializers up to the closing parenthesis. Function indices are only
+    /// allowed if `funcs` is set while expressions are always allowed.
+    fn elem_items(&mut self, funcs: bool) -> Result<Vec<Expr>, TextError> {
+        let mut items = Vec::new();
+        loop {
+            let item = if funcs && self.peek_index() {
+                let func = FuncIdx(U32(self.index(Space::Func)?));
+                Expr {
+                    instr: vec![Instr::Reference(ReferenceInstr::RefFunc(func))],
+                }
+            } else if self.eat_field("item") {
+                let item = self.expr()?;
+                self.rparen()?;
+                item
+            } else if self.peek() == Some(&Token::LParen) {
+                Expr {
+                    instr: self.folded()?,
+                }
+            } else {
+                return Ok(items);
+            };
+            items.push(item);
+        }
+    }
+
+    /// Concatenates the string literals of a data segment.
+    fn data_strings(&mut self) -> Result<Vec<Byte>, TextError> {
+        let mut init = Vec::new();
+        while let Some(Token::String(_)) = self.peek() {
+            init.extend(self.string()?.into_iter().map(Byte));
+        }
+        Ok(init)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::binary::{Parse, ParsingData, WasmBinary, MAX_NESTING};
+    use crate::parse::text::{parse, TextErrorKind};
+    use crate::wasm::data::DataMode;
+    use crate::wasm::elem::ElemMode;
+    use crate::wasm::export::ExportDesc;
+    use crate::wasm::import::ImportDesc;
+    use crate::wasm::instr::{BlockType, ControlInstr, Instr};
+    use crate::wasm::module::Module;
+
+    /// Debug representation of a module without the names, to compare modules which only differ
+    /// in their identifiers.
+    fn unnamed(text: &str) -> String {
+        let mut module = parse(text).unwrap();
+        module.names = Default::default();
+        module.name = None;
+        format!("{:?}", module)
+    }
+
+    fn error(text: &str) -> TextErrorKind {
+        parse(text).unwrap_err().kind().clone()
+    }
+
+    #[test]
+    fn same_as_binary() {
+        let text = r#"(module
+            (func (export "add") (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                i32.add))"#;
+        let wasm: WasmBinary = vec![
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // Preamble
+            0x01, 0x07, 0x01, 0x60, 0x02, 0x7F, 0x7F, 0x01, 0x7F, // Type section
+            0x03, 0x02, 0x01, 0x00, // Function section
+            0x07, 0x07, 0x01, 0x03, b'a', b'd', b'd', 0x00, 0x00, // Export section
+            0x0A, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6A, 0x0B, // Code section
+        ]
+        .into();
+        let binary = Module::parse(&mut ParsingData::new(&wasm)).unwrap();
+        assert_eq!(
+            format!("{:?}", parse(text).unwrap()),
+            format!("{:?}", binary)
+        );
+    }
+
+    #[test]
+    fn folded_and_flat() {
+        let flat = unnamed(
+            "(func (param i32) (result i32)
+                local.get 0
+                if (result i32)
+                    i32.const 1
+                else
+                    block
+                        br 0
+                    end
+                    local.get 0
+                    i32.const 2
+                    i32.mul
+                end)",
+        );
+        let folded = unnamed(
+            "(module (func $f (param $x i32) (result i32)
+                (if $l (result i32) (local.get $x)
+                    (then (i32.const 1))
+                    (else
+                        (block $b (br $b))
+                        (i32.mul (local.get $x) (i32.const 2))))))",
+        );
+        assert_eq!(flat, folded);
+    }
+
+    #[test]
+    fn forward_references() {
+        let module = parse(
+            "(func (call $g) (drop (global.get $v)))
+             (func $g)
+             (global $v i32 (i32.const 0))",
+        )
+        .unwrap();
+        assert!(module.validate().is_ok());
+        assert_eq!(
+            unnamed("(func (call 1)) (func)"),
+            unnamed("(func (call $g)) (func $g)")
+        );
+    }
+
+    #[test]
+    fn inline_type_uses() {
+        let module = parse(
+            "(type $v (func))
+             (type (func (param i32) (result i32)))
+             (func (param i32) (result i32) (local.get 0))
+             (func (param f32))
+             (func)
+             (func (param f32))
+             (func (result i32 i32) (block (result i32 i32) (i32.const 1) (i32.const 2)))",
+        )
+        .unwrap();
+        assert_eq!(module.types.len(), 4);
+        let indices: Vec<_> = module.funcs.iter().map(|func| (func.index.0).0).collect();
+        assert_eq!(indices, vec![1, 2, 0, 2, 3]);
+        match &module.funcs[4].body.instr[0] {
+            Instr::Control(ControlInstr::Block(block)) => {
+                assert!(matches!(block.tpe, BlockType::Type(idx) if (idx.0).0 == 3))
+            }
+            instr => panic!("Unexpected instruction {:?}", instr),
+        }
+        assert_eq!(
+            error("(type (func)) (func (type 0) (param i32))"),
+            TextErrorKind::TypeMismatch(0)
+        );
+    }
+
+    #[test]
+    fn inline_imports_and_exports() {
+        let module = parse(
+            r#"(import "m" "f" (func $f))
+               (func $g (export "g") (export "g2") (import "m" "g") (param i32))
+               (memory (export "mem") (import "m" "mem") 1)
+               (func $h (export "h") (call $f) (call $g (i32.const 0)))
+               (global (export "glob") (mut i32) (i32.const 0))
+               (table (export "tab") 1 funcref)"#,
+        )
+        .unwrap();
+        assert_eq!(module.imports.len(), 3);
+        assert!(matches!(module.imports[2].desc, ImportDesc::Mem(_)));
+        let exports: Vec<_> = module
+            .exports
+            .iter()
+            .map(|export| (export.name.name.as_str(), format!("{:?}", export.desc)))
+            .collect();
+        assert_eq!(
+            exports,
+            vec![
+                ("g", format!("{:?}", ExportDesc::Func(func_idx(1)))),
+                ("g2", format!("{:?}", ExportDesc::Func(func_idx(1)))),
+                ("mem", "Mem(MemIdx(U32(0)))".to_string()),
+                ("h", format!("{:?}", ExportDesc::Func(func_idx(2)))),
+                ("glob", "Global(GlobalIdx(U32(0)))".to_string()),
+                ("tab", "Table(TableIdx(U32(0)))".to_string()),
+            ]
+        );
+        assert!(module.validate().is_ok());
+        assert_eq!(
+            error(r#"(func) (import "m" "f" (func))"#),
+            TextErrorKind::ImportAfterDefinition
+        );
+    }
+
+    fn func_idx(idx: u32) -> crate::wasm::indices::FuncIdx {
+        crate::wasm::indices::FuncIdx(crate::wasm::values::U32(idx))
+    }
+
+    #[test]
+    fn segments() {
+        let module = parse(
+            r#"(memory (data "ab" "\63"))
+               (data $d "passive")
+               (data (i32.const 4) "x")
+               (table $t funcref (elem $f $f))
+               (elem $e func $f)
+               (elem (table $t) (offset (i32.const 1)) funcref (item ref.func $f) (ref.null func))
+               (elem declare func $f)
+               (func $f (memory.init $d (i32.const 0) (i32.const 0) (i32.const 0))
+                        (table.init $t $e (i32.const 0) (i32.const 0) (i32.const 0)))"#,
+        )
+        .unwrap();
+        let memory = module.mems[0].tpe.lim;
+        assert_eq!((*memory.min, memory.max.map(|max| *max)), (1, Some(1)));
+        let data: Vec<u8> = module.datas[0].init.iter().map(|byte| **byte).collect();
+        assert_eq!(data, b"abc");
+        assert!(matches!(module.datas[1].mode, DataMode::Passive));
+        assert!(matches!(module.datas[2].mode, DataMode::Active { .. }));
+        let table = module.tables[0].tpe.lim;
+        assert_eq!((*table.min, table.max.map(|max| *max)), (2, Some(2)));
+        assert_eq!(module.elems.len(), 4);
+        assert!(matches!(module.elems[0].mode, ElemMode::Active { .. }));
+        assert!(matches!(module.elems[1].mode, ElemMode::Passive));
+        assert_eq!(module.elems[2].init.len(), 2);
+        assert!(matches!(module.elems[3].mode, ElemMode::Declarative));
+        assert!(module.validate().is_ok());
+        // Bare function indices are the legacy form of `func`.
+        assert_eq!(
+            unnamed("(table 1 funcref) (elem (i32.const 0) 0) (func)"),
+            unnamed("(table 1 funcref) (elem (table 0) (offset (i32.const 0)) func 0) (func)")
+        );
+    }
+
+    #[test]
+    fn names() {
+        let module = parse(
+            "(module $m
+                (func $f (param $a i32) (local i64) (local $b f32)
+                    (block $outer (loop (block $inner))))
+                (global $g i32 (i32.const 0)))",
+        )
+        .unwrap();
+        assert_eq!(module.name.unwrap().name, "m");
+        assert_eq!(module.names.func(func_idx(0)).unwrap().name, "f");
+        assert_eq!(module.names.locals.get(0, 0).unwrap().name, "a");
+        assert!(module.names.locals.get(0, 1).is_none());
+        assert_eq!(module.names.locals.get(0, 2).unwrap().name, "b");
+        assert_eq!(module.names.labels.get(0, 0).unwrap().name, "outer");
+        assert_eq!(module.names.labels.get(0, 2).unwrap().name, "inner");
+        assert_eq!(module.names.globals.get(0).unwrap().name, "g");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            error("(func (call $nope))"),
+            TextErrorKind::UnknownIdentifier {
+                space: "function",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("(func $f) (func $f)"),
+            TextErrorKind::DuplicateIdentifier {
+                space: "function",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error("(func (local $x i32) (local $x i32))"),
+            TextErrorKind::DuplicateIdentifier { space: "local", .. }
+        ));
+        assert_eq!(
+            error("(func (block $a end $b))"),
+            TextErrorKind::Unexpected {
+                expected: "instruction",
+                found: "end".to_string()
+            }
+        );
+        assert_eq!(
+            error("(func block $a end $b)"),
+            TextErrorKind::MismatchedLabel("$b".to_string())
+        );
+        assert_eq!(
+            error("(func i32.frobnicate)"),
+            TextErrorKind::UnknownInstruction("i32.frobnicate".to_string())
+        );
+        assert_eq!(
+            error("(func (i32.const 0x1_0000_0000))"),
+            TextErrorKind::InvalidNumber("0x1_0000_0000".to_string())
+        );
+        assert_eq!(
+            error("(func (i32.load align=3 (i32.const 0)))"),
+            TextErrorKind::InvalidAlignment(3)
+        );
+        assert_eq!(
+            error("(start 0) (start 0) (func)"),
+            TextErrorKind::MultipleStart
+        );
+        assert_eq!(error("(module (func)"), TextErrorKind::UnexpectedEof);
+
+        let err = parse("(module\n  (func\n    (local.get $x)))").unwrap_err();
+        assert_eq!((err.line(), err.column()), (3, 16));
+        assert_eq!(err.location(), 31);
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested =
+            |depth: usize| format!("(func {}{})", "(block ".repeat(depth), ")".repeat(depth));
+        let module = parse(&nested(MAX_NESTING)).unwrap();
+        module.validate().unwrap();
+        assert_eq!(
+            error(&nested(MAX_NESTING + 1)),
+            TextErrorKind::NestingTooDeep
+        );
+    }
+}
@@ -0,0 +1,806 @@
+//! Token cursor along with the helpers shared by the module and instruction parsers.
+
+use std::collections::HashMap;
+
+use super::lexer::{self, Spanned, Token};
+use super::values;
+use super::{TextError, TextErrorKind};
+use crate::parse::binary::MAX_NESTING;
+use crate::wasm::indices::TypeIdx;
+use crate::wasm::names::{NameMap, NameSection};
+use crate::wasm::types::{
+    ArrayType, CompType, FieldType, FuncType, GlobalType, HeapType, IndexType, Limits, MemType,
+    Mutability, NumType, RecType, RefType, ResultType, StorageType, StructType, SubType, TableType,
+    ValType, VecType,
+};
+use crate::wasm::values::{Name, U32, U64};
+
+/// The index spaces identifiers can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Space {
+    Type,
+    Func,
+    Table,
+    Mem,
+    Global,
+    Elem,
+    Data,
+    Tag,
+    Local,
+}
+
+impl Space {
+    fn describe(self) -> &'static str {
+        match self {
+            Space::Type => "type",
+            Space::Func => "function",
+            Space::Table => "table",
+            Space::Mem => "memory",
+            Space::Global => "global",
+            Space::Elem => "element segment",
+            Space::Data => "data segment",
+            Space::Tag => "tag",
+            Space::Local => "local",
+        }
+    }
+}
+
+/// Identifiers bound within an index space along with the number of entries in the space.
+#[derive(Debug, Default)]
+pub(crate) struct Names<'a> {
+    ids: HashMap<&'a str, u32>,
+    count: u32,
+}
+
+pub(crate) struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Spanned<'a>>,
+    pos: usize,
+    /// Module level index spaces, indexed by [Space].
+    spaces: [Names<'a>; 8],
+    pub(crate) locals: Names<'a>,
+    /// Labels of the enclosing blocks, from the outermost to the innermost.
+    pub(crate) labels: Vec<Option<&'a str>>,
+    /// Types of the module, both explicit and the function types added by inline type uses.
+    pub(crate) types: Vec<RecType>,
+    /// Field identifiers of the struct types, by type index.
+    fields: HashMap<u32, Names<'a>>,
+    /// Names of the identifiers bound so far, as they'd appear in the name section.
+    pub(crate) names: NameSection,
+    /// Names of the locals of the current function.
+    pub(crate) local_names: NameMap,
+    /// Names of the labels of the current function, indexed by the order of the blocks.
+    pub(crate) label_names: NameMap,
+    /// Number of blocks seen so far within the current function.
+    pub(crate) blocks: u32,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(text: &'a str) -> Result<Parser<'a>, TextError> {
+        Ok(Parser {
+            text,
+            tokens: lexer::tokenize(text)?,
+            pos: 0,
+            spaces: Default::default(),
+            locals: Names::default(),
+            labels: Vec::new(),
+            types: Vec::new(),
+            fields: HashMap::new(),
+            names: NameSection::default(),
+            local_names: NameMap::default(),
+            label_names: NameMap::default(),
+            blocks: 0,
+        })
+    }
+
+    /// Checks that all of the input was consumed.
+    pub(crate) fn finish(&self) -> Result<(), TextError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err(self.unexpected("end of input")),
+        }
+    }
+
+    pub(crate) fn peek(&self) -> Option<&Token<'a>> {
+        self.peek_at(0)
+    }
+
+    pub(crate) fn peek_at(&self, n: usize) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos + n).map(|spanned| &spanned.token)
+    }
+
+    /// Position of the cursor, to come back to it with [rewind](Self::rewind).
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub(crate) fn rewind(&mut self, pos: usize) {
+        self.pos = pos;
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Token<'a>, TextError> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.error(TextErrorKind::UnexpectedEof))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Constructs an error located at the next token.
+    pub(crate) fn error(&self, kind: TextErrorKind) -> TextError {
+        self.error_at(self.pos, kind)
+    }
+
+    /// Constructs an error located at the token at position `pos`.
+    pub(crate) fn error_at(&self, pos: usize, kind: TextErrorKind) -> TextError {
+        TextError::new(self.text, self.offset_at(pos), kind)
+    }
+
+    /// Byte offset of the token at position `pos`, or the length of the text past the last
+    /// token.
+    pub(crate) fn offset_at(&self, pos: usize) -> usize {
+        self.tokens
+            .get(pos)
+            .map_or(self.text.len(), |spanned| spanned.offset)
+    }
+
+    /// The whole text being parsed.
+    pub(crate) fn text(&self) -> &'a str {
+        self.text
+    }
+
+    /// Constructs an error for an unexpected next token.
+    pub(crate) fn unexpected(&self, expected: &'static str) -> TextError {
+        let found = match self.peek() {
+            None => return self.error(TextErrorKind::UnexpectedEof),
+            Some(Token::LParen) => "(".to_string(),
+            Some(Token::RParen) => ")".to_string(),
+            Some(Token::String(s)) => format!("\"{}\"", s),
+            Some(Token::Keyword(s))
+            | Some(Token::Id(s))
+            | Some(Token::Number(s))
+            | Some(Token::Reserved(s)) => s.to_string(),
+        };
+        self.error(TextErrorKind::Unexpected { expected, found })
+    }
+
+    pub(crate) fn lparen(&mut self) -> Result<(), TextError> {
+        match self.peek() {
+            Some(Token::LParen) => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.unexpected("(")),
+        }
+    }
+
+    pub(crate) fn rparen(&mut self) -> Result<(), TextError> {
+        match self.peek() {
+            Some(Token::RParen) => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.unexpected(")")),
+        }
+    }
+
+    /// Consumes the next token which must be a keyword.
+    pub(crate) fn any_keyword(&mut self) -> Result<&'a str, TextError> {
+        match self.peek() {
+            Some(&Token::Keyword(kw)) => {
+                self.pos += 1;
+                Ok(kw)
+            }
+            _ => Err(self.unexpected("keyword")),
+        }
+    }
+
+    /// Consumes the keyword `kw` which must be the next token.
+    pub(crate) fn keyword(&mut self, kw: &'static str) -> Result<(), TextError> {
+        if self.eat_keyword(kw) {
+            Ok(())
+        } else {
+            Err(self.unexpected(kw))
+        }
+    }
+
+    /// Consumes the keyword `kw` if it's the next token.
+    pub(crate) fn eat_keyword(&mut self, kw: &str) -> bool {
+        if self.peek() == Some(&Token::Keyword(kw)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Checks whether the next tokens open the s-expression `(kw ...`.
+    pub(crate) fn peek_field(&self, kw: &str) -> bool {
+        self.peek() == Some(&Token::LParen) && self.peek_at(1) == Some(&Token::Keyword(kw))
+    }
+
+    /// Consumes `(kw` if they are the next tokens.
+    pub(crate) fn eat_field(&mut self, kw: &str) -> bool {
+        if self.peek_field(kw) {
+            self.pos += 2;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes an identifier if it's the next token.
+    pub(crate) fn eat_id(&mut self) -> Option<&'a str> {
+        match self.peek() {
+            Some(&Token::Id(id)) => {
+                self.pos += 1;
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Skips tokens up to and including the parenthesis closing the current s-expression.
+    pub(crate) fn skip_to_close(&mut self) -> Result<(), TextError> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::LParen => depth += 1,
+                Token::RParen if depth == 0 => return Ok(()),
+                Token::RParen => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    pub(crate) fn string(&mut self) -> Result<Vec<u8>, TextError> {
+        match self.peek() {
+            Some(&Token::String(s)) => {
+                let bytes = values::parse_string(s)
+                    .ok_or_else(|| self.error(TextErrorKind::InvalidString(s.to_string())))?;
+                self.pos += 1;
+                Ok(bytes)
+            }
+            _ => Err(self.unexpected("string")),
+        }
+    }
+
+    pub(crate) fn name(&mut self) -> Result<Name, TextError> {
+        let pos = self.pos;
+        let bytes = self.string()?;
+        let name =
+            String::from_utf8(bytes).map_err(|_| self.error_at(pos, TextErrorKind::InvalidUtf8))?;
+        Ok(Name { name })
+    }
+
+    /// Consumes a number literal, converting it with `convert`.
+    pub(crate) fn number<T>(&mut self, convert: fn(&str) -> Option<T>) -> Result<T, TextError> {
+        match self.peek() {
+            Some(&Token::Number(n)) => {
+                let value =
+                    convert(n).ok_or_else(|| self.error(TextErrorKind::InvalidNumber(n.into())))?;
+                self.pos += 1;
+                Ok(value)
+            }
+            _ => Err(self.unexpected("number")),
+        }
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, TextError> {
+        self.number(values::parse_u32)
+    }
+
+    /// Adds an entry to an index space, binding `id` to it if present. Returns the index of the
+    /// entry.
+    pub(crate) fn define(&mut self, space: Space, id: Option<&'a str>) -> Result<u32, TextError> {
+        let names = self.names_mut(space);
+        let idx = names.count;
+        names.count += 1;
+        if let Some(id) = id {
+            if names.ids.insert(id, idx).is_some() {
+                let kind = TextErrorKind::DuplicateIdentifier {
+                    space: space.describe(),
+                    id: id.to_string(),
+                };
+                return Err(TextError::new(self.text, self.offset_of(id), kind));
+            }
+            let name = Name {
+                name: id[1..].to_string(),
+            };
+            self.name_map(space).names.insert(idx, name);
+        }
+        Ok(idx)
+    }
+
+    /// Byte offset of a token within the text.
+    pub(crate) fn offset_of(&self, token: &str) -> usize {
+        token.as_ptr() as usize - self.text.as_ptr() as usize
+    }
+
+    /// Forgets the locals and labels of the previous function.
+    pub(crate) fn reset_func(&mut self) {
+        self.locals = Names::default();
+        self.local_names = NameMap::default();
+        self.label_names = NameMap::default();
+        self.labels.clear();
+        self.blocks = 0;
+    }
+
+    /// Opens a block, binding its label if present. Blocks can be nested up to [MAX_NESTING]
+    /// levels, as in the binary format.
+    pub(crate) fn push_label(&mut self, label: Option<&'a str>) -> Result<(), TextError> {
+        if self.labels.len() == MAX_NESTING {
+            return Err(self.error(TextErrorKind::NestingTooDeep));
+        }
+        if let Some(label) = label {
+            let name = Name {
+                name: label[1..].to_string(),
+            };
+            self.label_names.names.insert(self.blocks, name);
+        }
+        self.blocks += 1;
+        self.labels.push(label);
+        Ok(())
+    }
+
+    fn name_map(&mut self, space: Space) -> &mut NameMap {
+        match space {
+            Space::Type => &mut self.names.types,
+            Space::Func => &mut self.names.funcs,
+            Space::Table => &mut self.names.tables,
+            Space::Mem => &mut self.names.mems,
+            Space::Global => &mut self.names.globals,
+            Space::Elem => &mut self.names.elems,
+            Space::Data => &mut self.names.datas,
+            Space::Tag => &mut self.names.tags,
+            Space::Local => &mut self.local_names,
+        }
+    }
+
+    fn names_mut(&mut self, space: Space) -> &mut Names<'a> {
+        match space {
+            Space::Local => &mut self.locals,
+            _ => &mut self.spaces[space as usize],
+        }
+    }
+
+    fn names(&self, space: Space) -> &Names<'a> {
+        match space {
+            Space::Local => &self.locals,
+            _ => &self.spaces[space as usize],
+        }
+    }
+
+    /// Checks whether the next token is an index (either numeric or symbolic).
+    pub(crate) fn peek_index(&self) -> bool {
+        matches!(self.peek(), Some(Token::Number(_)) | Some(Token::Id(_)))
+    }
+
+    /// Consumes an index into `space`, resolving identifiers.
+    pub(crate) fn index(&mut self, space: Space) -> Result<u32, TextError> {
+        match self.peek() {
+            Some(&Token::Id(id)) => {
+                let idx = self.names(space).ids.get(id).copied().ok_or_else(|| {
+                    self.error(TextErrorKind::UnknownIdentifier {
+                        space: space.describe(),
+                        id: id.to_string(),
+                    })
+                })?;
+                self.pos += 1;
+                Ok(idx)
+            }
+            Some(Token::Number(_)) => self.u32(),
+            _ => Err(self.unexpected("index")),
+        }
+    }
+
+    /// Consumes an index into `space` if one is next.
+    pub(crate) fn eat_index(&mut self, space: Space) -> Result<Option<u32>, TextError> {
+        if self.peek_index() {
+            self.index(space).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Consumes a label index, resolving identifiers against the enclosing blocks.
+    pub(crate) fn label(&mut self) -> Result<u32, TextError> {
+        match self.peek() {
+            Some(&Token::Id(id)) => {
+                let depth = self
+                    .labels
+                    .iter()
+                    .rev()
+                    .position(|label| *label == Some(id))
+                    .ok_or_else(|| {
+                        self.error(TextErrorKind::UnknownIdentifier {
+                            space: "label",
+                            id: id.to_string(),
+                        })
+                    })?;
+                self.pos += 1;
+                Ok(depth as u32)
+            }
+            Some(Token::Number(_)) => self.u32(),
+            _ => Err(self.unexpected("label")),
+        }
+    }
+
+    /// Parses a heap type: either an abstract type or a type index.
+    pub(crate) fn heap_type(&mut self) -> Result<HeapType, TextError> {
+        if let Some(&Token::Keyword(kw)) = self.peek() {
+            if let Some(heap) = abstract_heap_type(kw) {
+                self.pos += 1;
+                return Ok(heap);
+            }
+        }
+        if !self.peek_index() {
+            return Err(self.unexpected("heap type"));
+        }
+        Ok(HeapType::Concrete(TypeIdx(U32(self.index(Space::Type)?))))
+    }
+
+    /// Parses a reference type, either `(ref null? heaptype)` or an abbreviation such as
+    /// `funcref`.
+    pub(crate) fn reftype(&mut self) -> Result<RefType, TextError> {
+        if self.eat_field("ref") {
+            let nullable = self.eat_keyword("null");
+            let heap = self.heap_type()?;
+            self.rparen()?;
+            return Ok(RefType { nullable, heap });
+        }
+        match self.peek() {
+            Some(&Token::Keyword(kw)) if reftype_shorthand(kw).is_some() => {
+                self.pos += 1;
+                Ok(RefType::null(
+                    reftype_shorthand(kw).expect("Known shorthand"),
+                ))
+            }
+            _ => Err(self.unexpected("reference type")),
+        }
+    }
+
+    pub(crate) fn peek_reftype(&self) -> bool {
+        match self.peek() {
+            Some(&Token::Keyword(kw)) => reftype_shorthand(kw).is_some(),
+            _ => self.peek_field("ref"),
+        }
+    }
+
+    pub(crate) fn valtype(&mut self) -> Result<ValType, TextError> {
+        let tpe = match self.peek() {
+            Some(Token::Keyword("i32")) => ValType::Num(NumType::I32),
+            Some(Token::Keyword("i64")) => ValType::Num(NumType::I64),
+            Some(Token::Keyword("f32")) => ValType::Num(NumType::F32),
+            Some(Token::Keyword("f64")) => ValType::Num(NumType::F64),
+            Some(Token::Keyword("v128")) => ValType::Vec(VecType::V128),
+            _ => return self.reftype().map(ValType::Ref),
+        };
+        self.pos += 1;
+        Ok(tpe)
+    }
+
+    /// Parses limits whose bounds have the width of the index type.
+    fn index_limits(&mut self, index: IndexType) -> Result<Limits, TextError> {
+        let bound: fn(&str) -> Option<u64> = match index {
+            IndexType::I32 => |text| values::parse_u32(text).map(u64::from),
+            IndexType::I64 => values::parse_u64,
+        };
+        let min = U64(self.number(bound)?);
+        let max = match self.peek() {
+            Some(Token::Number(_)) => Some(U64(self.number(bound)?)),
+            _ => None,
+        };
+        Ok(Limits { min, max })
+    }
+
+    /// Parses the optional index type of a table or memory, which defaults to `i32`.
+    pub(crate) fn index_type(&mut self) -> IndexType {
+        if self.eat_keyword("i64") {
+            IndexType::I64
+        } else {
+            self.eat_keyword("i32");
+            IndexType::I32
+        }
+    }
+
+    pub(crate) fn mem_type(&mut self) -> Result<MemType, TextError> {
+        let index = self.index_type();
+        self.mem_type_of(index)
+    }
+
+    /// Parses the rest of a memory type whose index type was already read.
+    pub(crate) fn mem_type_of(&mut self, index: IndexType) -> Result<MemType, TextError> {
+        let lim = self.index_limits(index)?;
+        let shared = self.eat_keyword("shared");
+        Ok(MemType { lim, index, shared })
+    }
+
+    pub(crate) fn table_type(&mut self) -> Result<TableType, TextError> {
+        let index = self.index_type();
+        self.table_type_of(index)
+    }
+
+    /// Parses the rest of a table type whose index type was already read.
+    pub(crate) fn table_type_of(&mut self, index: IndexType) -> Result<TableType, TextError> {
+        let lim = self.index_limits(index)?;
+        let tpe = self.reftype()?;
+        Ok(TableType { lim, index, tpe })
+    }
+
+    pub(crate) fn global_type(&mut self) -> Result<GlobalType, TextError> {
+        if self.eat_field("mut") {
+            let tpe = self.valtype()?;
+            self.rparen()?;
+            Ok(GlobalType {
+                mutability: Mutability::Mut,
+                tpe,
+            })
+        } else {
+            Ok(GlobalType {
+                mutability: Mutability::Const,
+                tpe: self.valtype()?,
+            })
+        }
+    }
+
+    /// Parses the `(param ...)` declarations of a signature. Only single parameters can be named.
+    pub(crate) fn params(&mut self) -> Result<Vec<(Option<&'a str>, ValType)>, TextError> {
+        let mut params = Vec::new();
+        while self.eat_field("param") {
+            if let Some(id) = self.eat_id() {
+                params.push((Some(id), self.valtype()?));
+            } else {
+                while self.peek() != Some(&Token::RParen) {
+                    params.push((None, self.valtype()?));
+                }
+            }
+            self.rparen()?;
+        }
+        Ok(params)
+    }
+
+    pub(crate) fn results(&mut self) -> Result<Vec<ValType>, TextError> {
+        let mut results = Vec::new();
+        while self.eat_field("result") {
+            while self.peek() != Some(&Token::RParen) {
+                results.push(self.valtype()?);
+            }
+            self.rparen()?;
+        }
+        Ok(results)
+    }
+
+    /// Parses a type definition after the `type` keyword. Its identifier was bound by the first
+    /// pass and `idx` is its index.
+    pub(crate) fn type_def(&mut self, idx: u32) -> Result<SubType, TextError> {
+        self.eat_id();
+        let tpe = if self.eat_field("sub") {
+            let is_final = self.eat_keyword("final");
+            let mut supers = Vec::new();
+            while self.peek_index() {
+                supers.push(TypeIdx(U32(self.index(Space::Type)?)));
+            }
+            let comp = self.comp_type(idx)?;
+            self.rparen()?;
+            SubType {
+                is_final,
+                supers,
+                comp,
+            }
+        } else {
+            SubType::from_comp(self.comp_type(idx)?)
+        };
+        self.rparen()?;
+        Ok(tpe)
+    }
+
+    /// Parses the type definitions of a recursion group after the `rec` keyword. `idx` is the
+    /// index of its first type.
+    pub(crate) fn rec_type(&mut self, idx: u32) -> Result<RecType, TextError> {
+        let mut types = Vec::new();
+        while self.eat_field("type") {
+            types.push(self.type_def(idx + types.len() as u32)?);
+        }
+        self.rparen()?;
+        Ok(RecType { types })
+    }
+
+    /// Parses the `(func ...)`, `(struct ...)` or `(array ...)` of the type definition `idx`.
+    fn comp_type(&mut self, idx: u32) -> Result<CompType, TextError> {
+        self.lparen()?;
+        let pos = self.pos;
+        let comp = match self.any_keyword()? {
+            "func" => {
+                let params = self.params()?;
+                let results = self.results()?;
+                CompType::Func(func_type(&params, results))
+            }
+            "struct" => {
+                let mut fields = Vec::new();
+                while self.eat_field("field") {
+                    if let Some(id) = self.eat_id() {
+                        self.define_field(idx, fields.len() as u32, id)?;
+                        fields.push(self.field_type()?);
+                    } else {
+                        while self.peek() != Some(&Token::RParen) {
+                            fields.push(self.field_type()?);
+                        }
+                    }
+                    self.rparen()?;
+                }
+                CompType::Struct(StructType { fields })
+            }
+            "array" => CompType::Array(ArrayType {
+                field: self.field_type()?,
+            }),
+            _ => {
+                self.rewind(pos);
+                return Err(self.unexpected("func, struct or array"));
+            }
+        };
+        self.rparen()?;
+        Ok(comp)
+    }
+
+    fn field_type(&mut self) -> Result<FieldType, TextError> {
+        let mutable = self.eat_field("mut");
+        let tpe = if self.eat_keyword("i8") {
+            StorageType::I8
+        } else if self.eat_keyword("i16") {
+            StorageType::I16
+        } else {
+            StorageType::Val(self.valtype()?)
+        };
+        if mutable {
+            self.rparen()?;
+        }
+        let mutability = if mutable {
+            Mutability::Mut
+        } else {
+            Mutability::Const
+        };
+        Ok(FieldType { tpe, mutability })
+    }
+
+    /// Binds `id` to the field `field` of the struct type `tpe`.
+    fn define_field(&mut self, tpe: u32, field: u32, id: &'a str) -> Result<(), TextError> {
+        let names = self.fields.entry(tpe).or_default();
+        if names.ids.insert(id, field).is_some() {
+            let kind = TextErrorKind::DuplicateIdentifier {
+                space: "field",
+                id: id.to_string(),
+            };
+            return Err(TextError::new(self.text, self.offset_of(id), kind));
+        }
+        let name = Name {
+            name: id[1..].to_string(),
+        };
+        let names = self.names.fields.names.entry(tpe).or_default();
+        names.names.insert(field, name);
+        Ok(())
+    }
+
+    /// Consumes a field index of the struct type `tpe`, resolving identifiers.
+    pub(crate) fn field_index(&mut self, tpe: u32) -> Result<u32, TextError> {
+        match self.peek() {
+            Some(&Token::Id(id)) => {
+                let field = self.fields.get(&tpe).and_then(|names| names.ids.get(id));
+                let field = *field.ok_or_else(|| {
+                    self.error(TextErrorKind::UnknownIdentifier {
+                        space: "field",
+                        id: id.to_string(),
+                    })
+                })?;
+                self.pos += 1;
+                Ok(field)
+            }
+            Some(Token::Number(_)) => self.u32(),
+            _ => Err(self.unexpected("field")),
+        }
+    }
+
+    /// Parses a type use: an optional `(type x)` followed by optional parameters and results.
+    ///
+    /// Without an explicit type, the first type matching the signature is used and the signature
+    /// is added to the module types if there's none. Returns the type index along with the names
+    /// of the parameters.
+    pub(crate) fn type_use(&mut self) -> Result<(u32, Vec<Option<&'a str>>), TextError> {
+        let explicit = if self.eat_field("type") {
+            let pos = self.pos;
+            let idx = self.index(Space::Type)?;
+            self.rparen()?;
+            Some((idx, pos))
+        } else {
+            None
+        };
+        let params = self.params()?;
+        let results = self.results()?;
+        let names = params.iter().map(|(name, _)| *name).collect();
+        let tpe = func_type(&params, results);
+        match explicit {
+            Some((idx, pos)) => {
+                let declared = self
+                    .types
+                    .iter()
+                    .flat_map(|rec| &rec.types)
+                    .nth(idx as usize)
+                    .and_then(SubType::func);
+                if tpe.params.types.is_empty() && tpe.result.types.is_empty() {
+                    let names = declared.map_or(0, |tpe| tpe.params.types.len());
+                    return Ok((idx, vec![None; names]));
+                }
+                if declared != Some(&tpe) {
+                    return Err(self.error_at(pos, TextErrorKind::TypeMismatch(idx)));
+                }
+                Ok((idx, names))
+            }
+            None => Ok((self.type_index(tpe), names)),
+        }
+    }
+
+    /// Index of the first type defined as `tpe` on its own, adding it to the types if it's
+    /// missing.
+    pub(crate) fn type_index(&mut self, tpe: FuncType) -> u32 {
+        let tpe = SubType::from(tpe);
+        let mut idx = 0;
+        for rec in &self.types {
+            if matches!(&rec.types[..], [other] if *other == tpe) {
+                return idx;
+            }
+            idx += rec.types.len() as u32;
+        }
+        self.types.push(RecType { types: vec![tpe] });
+        idx
+    }
+}
+
+/// Abstract heap type named `kw`.
+pub(crate) fn abstract_heap_type(kw: &str) -> Option<HeapType> {
+    let heap = match kw {
+        "func" => HeapType::Func,
+        "nofunc" => HeapType::NoFunc,
+        "extern" => HeapType::Extern,
+        "noextern" => HeapType::NoExtern,
+        "any" => HeapType::Any,
+        "eq" => HeapType::Eq,
+        "i31" => HeapType::I31,
+        "struct" => HeapType::Struct,
+        "array" => HeapType::Array,
+        "none" => HeapType::None,
+        "exn" => HeapType::Exn,
+        "noexn" => HeapType::NoExn,
+        _ => return None,
+    };
+    Some(heap)
+}
+
+/// Heap type of the nullable reference type abbreviated as `kw`.
+fn reftype_shorthand(kw: &str) -> Option<HeapType> {
+    let heap = match kw {
+        "funcref" => HeapType::Func,
+        "nullfuncref" => HeapType::NoFunc,
+        "externref" => HeapType::Extern,
+        "nullexternref" => HeapType::NoExtern,
+        "anyref" => HeapType::Any,
+        "eqref" => HeapType::Eq,
+        "i31ref" => HeapType::I31,
+        "structref" => HeapType::Struct,
+        "arrayref" => HeapType::Array,
+        "nullref" => HeapType::None,
+        "exnref" => HeapType::Exn,
+        "nullexnref" => HeapType::NoExn,
+        _ => return None,
+    };
+    Some(heap)
+}
+
+fn func_type(params: &[(Option<&str>, ValType)], results: Vec<ValType>) -> FuncType {
+    FuncType {
+        params: ResultType {
+            types: params.iter().map(|(_, tpe)| *tpe).collect(),
+        },
+        result: ResultType { types: results },
+    }
+}
@@ -0,0 +1,427 @@
+//! Conversion of numeric and string literals into values.
+//!
+//! The functions here work on the raw text of a token and return `None` when the literal is
+//! malformed or out of range for the requested type.
+
+/// Strips the `_` separators between digits. A separator must sit between two digits.
+fn digits(text: &str, hex: bool) -> Option<String> {
+    let is_digit = |c: char| {
+        if hex {
+            c.is_ascii_hexdigit()
+        } else {
+            c.is_ascii_digit()
+        }
+    };
+    let mut result = String::with_capacity(text.len());
+    let mut last = None;
+    for c in text.chars() {
+        if c == '_' {
+            if !last.map_or(false, is_digit) {
+                return None;
+            }
+        } else if is_digit(c) {
+            result.push(c);
+        } else {
+            return None;
+        }
+        last = Some(c);
+    }
+    if result.is_empty() || last == Some('_') {
+        return None;
+    }
+    Some(result)
+}
+
+/// Splits an optional sign off a literal. Returns whether the literal is negative.
+fn sign(text: &str) -> (bool, &str) {
+    match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    }
+}
+
+/// Parses an unsigned integer (decimal or `0x` prefixed hexadecimal) without sign.
+fn magnitude(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(&digits(hex, true)?, 16).ok(),
+        None => digits(text, false)?.parse().ok(),
+    }
+}
+
+/// Parses an unsigned 32 bit integer such as an index, an alignment or a limit.
+pub(crate) fn parse_u32(text: &str) -> Option<u32> {
+    let value = magnitude(text)?;
+    if value > u64::from(u32::MAX) {
+        return None;
+    }
+    Some(value as u32)
+}
+
+/// Parses an unsigned 64 bit integer such as the limit or offset of a 64-bit memory.
+pub(crate) fn parse_u64(text: &str) -> Option<u64> {
+    magnitude(text)
+}
+
+/// Parses a 32 bit integer that can be written either signed or unsigned. The result is the two's
+/// complement bit pattern.
+pub(crate) fn parse_i32(text: &str) -> Option<u32> {
+    let (neg, text) = sign(text);
+    let value = magnitude(text)?;
+    if neg {
+        if value > 1 << 31 {
+            return None;
+        }
+        Some((value as u32).wrapping_neg())
+    } else if value > u64::from(u32::MAX) {
+        None
+    } else {
+        Some(value as u32)
+    }
+}
+
+/// Parses a 64 bit integer that can be written either signed or unsigned. The result is the two's
+/// complement bit pattern.
+pub(crate) fn parse_i64(text: &str) -> Option<u64> {
+    let (neg, text) = sign(text);
+    let value = magnitude(text)?;
+    if neg {
+        if value > 1 << 63 {
+            return None;
+        }
+        Some(value.wrapping_neg())
+    } else {
+        Some(value)
+    }
+}
+
+/// Parses an 8 bit integer that can be written either signed or unsigned, e.g. a lane of an
+/// `i8x16` vector. The result is the two's complement bit pattern.
+pub(crate) fn parse_i8(text: &str) -> Option<u8> {
+    parse_lane(text, 8).map(|value| value as u8)
+}
+
+/// Parses a 16 bit integer that can be written either signed or unsigned, e.g. a lane of an
+/// `i16x8` vector. The result is the two's complement bit pattern.
+pub(crate) fn parse_i16(text: &str) -> Option<u16> {
+    parse_lane(text, 16).map(|value| value as u16)
+}
+
+/// Parses an integer of less than 32 bits into its two's complement bit pattern.
+fn parse_lane(text: &str, bits: u32) -> Option<u64> {
+    let (neg, text) = sign(text);
+    let value = magnitude(text)?;
+    let max = (1 << bits) - 1;
+    if neg {
+        if value > 1 << (bits - 1) {
+            return None;
+        }
+        Some(value.wrapping_neg() & max)
+    } else if value > max {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Parses a 32 bit float literal into its bit pattern.
+pub(crate) fn parse_f32(text: &str) -> Option<u32> {
+    parse_float(text, 23, 8).map(|bits| bits as u32)
+}
+
+/// Parses a 64 bit float literal into its bit pattern.
+pub(crate) fn parse_f64(text: &str) -> Option<u64> {
+    parse_float(text, 52, 11)
+}
+
+/// Parses a float literal into the bit pattern of a float with `mant` mantissa bits and `exp`
+/// exponent bits. Results are rounded to nearest, ties to even. Literals that round to infinity
+/// are rejected.
+fn parse_float(text: &str, mant: u32, exp: u32) -> Option<u64> {
+    let (neg, text) = sign(text);
+    let sign_bit = (neg as u64) << (mant + exp);
+    let inf = ((1u64 << exp) - 1) << mant;
+    if text == "inf" {
+        return Some(sign_bit | inf);
+    }
+    if text == "nan" {
+        return Some(sign_bit | inf | 1 << (mant - 1));
+    }
+    if let Some(payload) = text.strip_prefix("nan:0x") {
+        let payload = u64::from_str_radix(&digits(payload, true)?, 16).ok()?;
+        if payload == 0 || payload >= 1 << mant {
+            return None;
+        }
+        return Some(sign_bit | inf | payload);
+    }
+    let bits = match text.strip_prefix("0x") {
+        Some(hex) => hex_float(hex, mant, exp)?,
+        None => dec_float(text, mant)?,
+    };
+    Some(sign_bit | bits)
+}
+
+/// Splits `int[.frac][sep[sign]exp]` into its digit groups and exponent.
+fn split_float(text: &str, hex: bool) -> Option<(String, String, i64)> {
+    let sep: &[char] = if hex { &['p', 'P'] } else { &['e', 'E'] };
+    let (mantissa, exponent) = match text.find(sep) {
+        Some(pos) => (&text[..pos], Some(&text[pos + 1..])),
+        None => (text, None),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(pos) => (&mantissa[..pos], &mantissa[pos + 1..]),
+        None => (mantissa, ""),
+    };
+    let int = digits(int, hex)?;
+    let frac = if frac.is_empty() {
+        String::new()
+    } else {
+        digits(frac, hex)?
+    };
+    let exponent = match exponent {
+        Some(exponent) => {
+            let (neg, exponent) = sign(exponent);
+            // Saturate absurd exponents. They'll round to zero or be rejected as infinite anyway.
+            let value = digits(exponent, false)?
+                .parse::<i64>()
+                .unwrap_or(i64::MAX / 2)
+                .min(i64::MAX / 2);
+            if neg {
+                -value
+            } else {
+                value
+            }
+        }
+        None => 0,
+    };
+    Some((int, frac, exponent))
+}
+
+fn dec_float(text: &str, mant: u32) -> Option<u64> {
+    let (int, frac, exponent) = split_float(text, false)?;
+    let normalized = format!(
+        "{}.{}e{}",
+        int,
+        if frac.is_empty() { "0" } else { &frac },
+        exponent
+    );
+    // The standard library rounds correctly to nearest, ties to even.
+    let bits = if mant == 23 {
+        let value: f32 = normalized.parse().ok()?;
+        if value.is_infinite() {
+            return None;
+        }
+        u64::from(value.to_bits())
+    } else {
+        let value: f64 = normalized.parse().ok()?;
+        if value.is_infinite() {
+            return None;
+        }
+        value.to_bits()
+    };
+    Some(bits)
+}
+
+fn hex_float(text: &str, mant: u32, exp: u32) -> Option<u64> {
+    let (int, frac, exponent) = split_float(text, true)?;
+    // Accumulate the significant digits, keeping track of whether any non-zero digits were dropped.
+    let mut acc: u128 = 0;
+    let mut shift: i64 = exponent;
+    let mut sticky = false;
+    for (i, c) in int.chars().chain(frac.chars()).enumerate() {
+        let digit = c.to_digit(16).expect("Digits were checked") as u128;
+        let fractional = i >= int.len();
+        if acc >> 120 == 0 {
+            acc = acc << 4 | digit;
+            if fractional {
+                shift -= 4;
+            }
+        } else {
+            sticky |= digit != 0;
+            if !fractional {
+                shift += 4;
+            }
+        }
+    }
+    if acc == 0 {
+        return Some(0);
+    }
+    let bias = (1i64 << (exp - 1)) - 1;
+    let msb = 127 - i64::from(acc.leading_zeros());
+    // Exponent of the least significant bit of the result.
+    let mut lsb = std::cmp::max(msb + shift, 1 - bias) - i64::from(mant);
+    let right = lsb - shift;
+    let mut mantissa = if right <= 0 {
+        // The value is exact, there's no rounding to do.
+        acc << -right
+    } else if right >= 128 {
+        0
+    } else {
+        let truncated = acc >> right;
+        let rem = acc & ((1 << right) - 1);
+        let half = 1 << (right - 1);
+        if rem > half || (rem == half && (sticky || truncated & 1 == 1)) {
+            truncated + 1
+        } else {
+            truncated
+        }
+    };
+    if mantissa == 1 << (mant + 1) {
+        mantissa >>= 1;
+        lsb += 1;
+    }
+    if mantissa >> mant == 0 {
+        // Subnormal (or zero after rounding).
+        return Some(mantissa as u64);
+    }
+    let biased = lsb + i64::from(mant) + bias;
+    if biased >= (1 << exp) - 1 {
+        return None;
+    }
+    Some((biased as u64) << mant | (mantissa as u64 & ((1 << mant) - 1)))
+}
+
+/// Decodes the contents of a string literal (without the quotes) into bytes.
+pub(crate) fn parse_string(text: &str) -> Option<Vec<u8>> {
+    let mut result = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            result.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next()? {
+            't' => result.push(b'\t'),
+            'n' => result.push(b'\n'),
+            'r' => result.push(b'\r'),
+            '"' => result.push(b'"'),
+            '\'' => result.push(b'\''),
+            '\\' => result.push(b'\\'),
+            'u' => {
+                if chars.next()? != '{' {
+                    return None;
+                }
+                let mut hex = String::new();
+                loop {
+                    match chars.next()? {
+                        '}' => break,
+                        c => hex.push(c),
+                    }
+                }
+                let code = u32::from_str_radix(&digits(&hex, true)?, 16).ok()?;
+                let c = std::char::from_u32(code)?;
+                let mut buf = [0; 4];
+                result.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+            high => {
+                let low = chars.next()?;
+                let byte = high.to_digit(16)? << 4 | low.to_digit(16)?;
+                result.push(byte as u8);
+            }
+        }
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers() {
+        assert_eq!(parse_u32("0"), Some(0));
+        assert_eq!(parse_u32("1_000"), Some(1000));
+        assert_eq!(parse_u32("0xFF_ff"), Some(0xFFFF));
+        assert_eq!(parse_u32("4294967296"), None);
+        assert_eq!(parse_u32("-1"), None);
+        assert_eq!(parse_u32("1__0"), None);
+        assert_eq!(parse_u32("_1"), None);
+        assert_eq!(parse_u32("1_"), None);
+        assert_eq!(parse_i32("-1"), Some(0xFFFF_FFFF));
+        assert_eq!(parse_i32("-0x8000_0000"), Some(0x8000_0000));
+        assert_eq!(parse_i32("-0x8000_0001"), None);
+        assert_eq!(parse_i32("+4294967295"), Some(u32::MAX));
+        assert_eq!(parse_i64("-9223372036854775808"), Some(1 << 63));
+        assert_eq!(parse_i64("18446744073709551615"), Some(u64::MAX));
+        assert_eq!(parse_i64("18446744073709551616"), None);
+        assert_eq!(parse_i8("-128"), Some(0x80));
+        assert_eq!(parse_i8("255"), Some(0xFF));
+        assert_eq!(parse_i8("256"), None);
+        assert_eq!(parse_i8("-129"), None);
+        assert_eq!(parse_i16("-0x8000"), Some(0x8000));
+        assert_eq!(parse_i16("0x1_0000"), None);
+    }
+
+    #[test]
+    fn decimal_floats() {
+        assert_eq!(parse_f32("1"), Some(1f32.to_bits()));
+        assert_eq!(parse_f32("-1.5e1_0"), Some((-1.5e10f32).to_bits()));
+        assert_eq!(parse_f64("0.1"), Some(0.1f64.to_bits()));
+        assert_eq!(parse_f64("1."), Some(1f64.to_bits()));
+        assert_eq!(parse_f64("-0"), Some((-0f64).to_bits()));
+        assert_eq!(parse_f32("1e39"), None);
+        assert_eq!(parse_f64("1e-400"), Some(0));
+        assert_eq!(parse_f64("1.e"), None);
+    }
+
+    #[test]
+    fn hex_floats() {
+        assert_eq!(parse_f32("0x1p0"), Some(1f32.to_bits()));
+        assert_eq!(parse_f32("0x1.8p1"), Some(3f32.to_bits()));
+        assert_eq!(
+            parse_f64("-0x1.fffffffffffffp1023"),
+            Some(f64::MIN.to_bits())
+        );
+        assert_eq!(parse_f64("0x1p1024"), None);
+        assert_eq!(parse_f32("0x1p-149"), Some(1));
+        assert_eq!(parse_f32("0x1p-150"), Some(0));
+        assert_eq!(parse_f32("0x1.000002p-150"), Some(1));
+        assert_eq!(parse_f32("0x1.fffffefffp127"), Some(f32::MAX.to_bits()));
+        assert_eq!(parse_f32("0x1.ffffffp127"), None);
+        // Ties round to even.
+        assert_eq!(parse_f32("0x1.000001p0"), Some(1f32.to_bits()));
+        assert_eq!(parse_f32("0x1.000003p0"), Some(1f32.to_bits() + 2));
+        assert_eq!(
+            parse_f32("0x1.0000010000000000000000000001p0"),
+            Some(1f32.to_bits() + 1)
+        );
+        assert_eq!(
+            parse_f32("0x0.0000000000000000000000000001p112"),
+            Some(1f32.to_bits())
+        );
+        assert_eq!(parse_f64("0x10"), Some(16f64.to_bits()));
+    }
+
+    #[test]
+    fn special_floats() {
+        assert_eq!(parse_f32("inf"), Some(f32::INFINITY.to_bits()));
+        assert_eq!(parse_f64("-inf"), Some(f64::NEG_INFINITY.to_bits()));
+        assert_eq!(parse_f32("nan"), Some(0x7FC0_0000));
+        assert_eq!(parse_f32("-nan:0x1"), Some(0xFF80_0001));
+        assert_eq!(parse_f32("nan:0x0"), None);
+        assert_eq!(parse_f32("nan:0x800000"), None);
+        assert_eq!(
+            parse_f64("nan:0xf_ffff_ffff_ffff"),
+            Some(0x7FFF_FFFF_FFFF_FFFF)
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(parse_string("abc"), Some(b"abc".to_vec()));
+        assert_eq!(
+            parse_string(r#"\t\n\r\"\'\\"#),
+            Some(b"\t\n\r\"'\\".to_vec())
+        );
+        assert_eq!(parse_string(r"\00\ff"), Some(vec![0, 0xFF]));
+        assert_eq!(
+            parse_string(r"\u{1F600}"),
+            Some("\u{1F600}".as_bytes().to_vec())
+        );
+        assert_eq!(parse_string("é"), Some("é".as_bytes().to_vec()));
+        assert_eq!(parse_string(r"\u{D800}"), None);
+        assert_eq!(parse_string(r"\g"), None);
+        assert_eq!(parse_string(r"\0"), None);
+    }
+}
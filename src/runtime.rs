@@ -0,0 +1,129 @@
+//! Web assembly runtime
+//!
+//! This module executes [modules](crate::wasm::module::Module) with a tree-walking interpreter
+//! that follows the execution semantics of the specification. Modules are instantiated within a
+//! [Store], which owns every function, table, memory and global, and their exported functions
+//! are called through [Instance::invoke]. Imports are provided by a [Linker].
+//!
+//! # Examples
+//!
+//! ```
+//! use wasm_parse::runtime::{Instance, Store, Value};
+//! use wasm_parse::wasm::module::Module;
+//!
+//! let module = Module::from_wat(
+//!     r#"(func (export "fac") (param i64) (result i64)
+//!         (if (result i64) (i64.eqz (local.get 0))
+//!             (then (i64.const 1))
+//!             (else (i64.mul (local.get 0) (call 0 (i64.sub (local.get 0) (i64.const 1)))))))"#,
+//! )?;
+//! let store = Store::new();
+//! let instance = Instance::new(&store, &module)?;
+//! assert_eq!(instance.invoke("fac", &[Value::I64(5)])?, vec![Value::I64(120)]);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use thiserror::Error;
+
+use crate::wasm::types::ExternType;
+use crate::wasm::validate::ValidationError;
+
+mod exec;
+mod instance;
+mod linker;
+mod numeric;
+mod store;
+mod values;
+mod vector;
+
+pub use exec::MAX_CALL_DEPTH;
+pub use instance::Instance;
+pub use linker::{Caller, Linker};
+pub use store::Store;
+pub use values::{AnyRef, Value};
+
+/// Runtime error which aborts the execution of the current call.
+///
+/// The messages are the ones the specification test suite expects.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum Trap {
+    #[error("unreachable")]
+    Unreachable,
+    #[error("integer divide by zero")]
+    DivisionByZero,
+    #[error("integer overflow")]
+    IntegerOverflow,
+    #[error("invalid conversion to integer")]
+    InvalidConversion,
+    #[error("out of bounds memory access")]
+    MemoryOutOfBounds,
+    #[error("out of bounds table access")]
+    TableOutOfBounds,
+    #[error("undefined element")]
+    UndefinedElement,
+    /// The table element at the index is a null reference.
+    #[error("uninitialized element {0}")]
+    UninitializedElement(u64),
+    #[error("indirect call type mismatch")]
+    IndirectCallTypeMismatch,
+    #[error("call stack exhausted")]
+    CallStackExhausted,
+    #[error("unaligned atomic")]
+    UnalignedAtomic,
+    #[error("expected shared memory")]
+    UnsharedMemory,
+    /// `memory.atomic.wait` with an infinite timeout, which no other thread can end.
+    #[error("deadlock")]
+    Deadlock,
+    /// `throw_ref` of a null reference.
+    #[error("null exception reference")]
+    NullExnRef,
+    /// `call_ref` of a null reference.
+    #[error("null function reference")]
+    NullFuncRef,
+    #[error("null structure reference")]
+    NullStructRef,
+    #[error("null array reference")]
+    NullArrayRef,
+    #[error("null i31 reference")]
+    NullI31Ref,
+    /// `ref.as_non_null` of a null reference.
+    #[error("null reference")]
+    NullReference,
+    /// `ref.cast` of a reference that doesn't have the type.
+    #[error("cast failure")]
+    CastFailure,
+    #[error("out of bounds array access")]
+    ArrayOutOfBounds,
+    /// Exception thrown out of the called function.
+    #[error("uncaught exception")]
+    UncaughtException,
+    #[error("unknown export {0}")]
+    UnknownExport(String),
+    #[error("arguments don't match the type of the function")]
+    ArgumentMismatch,
+    /// Error raised by a host function.
+    #[error("{0}")]
+    Host(String),
+}
+
+/// Error returned when a module can't be instantiated.
+#[derive(Debug, Clone, Error)]
+#[non_exhaustive]
+pub enum InstantiationError {
+    #[error(transparent)]
+    Invalid(#[from] ValidationError),
+    #[error("Unresolved import {module}.{name}")]
+    UnresolvedImport { module: String, name: String },
+    #[error("Incompatible import type for {module}.{name}: expected {expected:?}, got {actual:?}")]
+    IncompatibleImport {
+        module: String,
+        name: String,
+        expected: Box<ExternType>,
+        actual: Box<ExternType>,
+    },
+    /// A segment was out of bounds or the start function trapped.
+    #[error("Trap during instantiation: {0}")]
+    Trap(#[from] Trap),
+}
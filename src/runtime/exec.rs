@@ -0,0 +1,1738 @@
+//! Interpreter.
+//!
+//! Instructions are executed straight from the [Instr] tree of the module. Like the rest of the
+//! crate, the interpreter doesn't recurse on nested blocks or calls: the labels and call frames
+//! are kept on explicit stacks, so only [MAX_CALL_DEPTH] bounds how deep calls can go.
+
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use super::linker::Caller;
+use super::numeric;
+use super::store::{
+    ExnInst, FuncInst, GcObject, GlobalInst, MemInst, ModuleInst, StoreData, TableInst,
+};
+use super::values::{pop, AnyRef, Value};
+use super::vector;
+use super::Trap;
+use crate::wasm::indices::{MemIdx, TableIdx, TagIdx, TypeIdx};
+use crate::wasm::instr::{
+    AggregateInstr, AtomicInstr, BlockType, Catch, ControlInstr, Expr, FloatType, Instr, IntType,
+    MemArg, MemoryInstr, ReferenceInstr, Sign, TableInstr, TryHandler, VariableInstr, VectorInstr,
+};
+use crate::wasm::module::Module;
+use crate::wasm::registry::TypeRegistry;
+use crate::wasm::types::{
+    CompType, FieldType, FuncType, HeapType, IndexType, NumType, RefType, StorageType, ValType,
+    VecType,
+};
+use crate::wasm::values::U32;
+
+/// Maximum number of nested calls. Going deeper traps with [Trap::CallStackExhausted].
+pub const MAX_CALL_DEPTH: usize = 1 << 14;
+
+/// Label of a structured control instruction.
+struct Label<'a> {
+    /// Instructions within the block and the position of the next one.
+    instrs: &'a [Instr],
+    pc: usize,
+    /// Number of values a branch to the label carries: the parameters of a loop, the results of
+    /// any other block.
+    arity: usize,
+    /// Height of the operand stack below the parameters of the block.
+    height: usize,
+    is_loop: bool,
+    handler: Handler<'a>,
+}
+
+/// Exception handler of a label.
+#[derive(Clone, Copy)]
+enum Handler<'a> {
+    None,
+    /// Body of a legacy `try` block.
+    Try(&'a TryHandler),
+    /// `catch` or `catch_all` clause of a legacy `try` block, with the address of the caught
+    /// exception that `rethrow` throws again.
+    Caught(usize),
+    /// Body of a `try_table` block.
+    Table(&'a [Catch]),
+}
+
+/// Activation of a function.
+struct Frame {
+    /// Address of the module instance the function belongs to.
+    instance: usize,
+    /// Position of the first local of the function within [Executor::locals].
+    locals: usize,
+    /// Position of the label of the function body within [Executor::labels].
+    label: usize,
+}
+
+/// Checks that `n` items starting at `start` are within `len`.
+fn range(start: u64, n: u64, len: usize, trap: Trap) -> Result<Range<usize>, Trap> {
+    match start.checked_add(n) {
+        Some(end) if end <= len as u64 => Ok(start as usize..end as usize),
+        _ => Err(trap),
+    }
+}
+
+/// Pops an address or a table index, which is an `i64` for 64-bit memories and tables.
+fn pop_index(stack: &mut Vec<Value>, index: IndexType) -> u64 {
+    match index {
+        IndexType::I32 => u64::from(pop::<i32>(stack) as u32),
+        IndexType::I64 => pop::<i64>(stack) as u64,
+    }
+}
+
+/// Value of an address, table index or size of a memory or table with the given index type.
+fn index_value(index: IndexType, value: u64) -> Value {
+    match index {
+        IndexType::I32 => Value::I32(value as i32),
+        IndexType::I64 => Value::I64(value as i64),
+    }
+}
+
+/// Interpreter state of a call into the store.
+pub(crate) struct Executor<'a> {
+    funcs: &'a [FuncInst],
+    instances: &'a [ModuleInst],
+    tables: &'a mut [TableInst],
+    mems: &'a mut [MemInst],
+    globals: &'a mut [GlobalInst],
+    elems: &'a mut [Vec<Value>],
+    datas: &'a mut [Vec<u8>],
+    tags: &'a [FuncType],
+    exns: &'a mut Vec<ExnInst>,
+    objects: &'a mut Vec<GcObject>,
+    registry: &'a TypeRegistry,
+    stack: Vec<Value>,
+    locals: Vec<Value>,
+    labels: Vec<Label<'a>>,
+    frames: Vec<Frame>,
+}
+
+impl<'a> Executor<'a> {
+    pub(crate) fn new(store: &'a mut StoreData) -> Executor<'a> {
+        Executor {
+            funcs: &store.funcs,
+            instances: &store.instances,
+            tables: &mut store.tables,
+            mems: &mut store.mems,
+            globals: &mut store.globals,
+            elems: &mut store.elems,
+            datas: &mut store.datas,
+            tags: &store.tags,
+            exns: &mut store.exns,
+            objects: &mut store.objects,
+            registry: &store.registry,
+            stack: Vec::new(),
+            locals: Vec::new(),
+            labels: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Calls the function at address `func`. The arguments must match its type.
+    pub(crate) fn invoke(&mut self, func: usize, args: &[Value]) -> Result<Vec<Value>, Trap> {
+        let results = self.funcs[func].tpe().result.types.len();
+        let depth = self.frames.len();
+        self.stack.extend_from_slice(args);
+        self.call(func)?;
+        self.run(depth)?;
+        Ok(self.stack.split_off(self.stack.len() - results))
+    }
+
+    /// Evaluates a constant expression within the module instance at address `instance`.
+    pub(crate) fn eval(&mut self, instance: usize, expr: &'a Expr) -> Result<Value, Trap> {
+        let depth = self.frames.len();
+        self.frames.push(Frame {
+            instance,
+            locals: self.locals.len(),
+            label: self.labels.len(),
+        });
+        self.labels.push(Label {
+            instrs: &expr.instr,
+            pc: 0,
+            arity: 1,
+            height: self.stack.len(),
+            is_loop: false,
+            handler: Handler::None,
+        });
+        self.run(depth)?;
+        Ok(self
+            .stack
+            .pop()
+            .expect("constant expressions produce a value"))
+    }
+
+    /// Executes instructions until the frames above `depth` return.
+    fn run(&mut self, depth: usize) -> Result<(), Trap> {
+        while self.frames.len() > depth {
+            let label = self.labels.last_mut().expect("frames have a label");
+            let instrs = label.instrs;
+            match instrs.get(label.pc) {
+                Some(instr) => {
+                    label.pc += 1;
+                    self.execute(instr)?;
+                }
+                None => self.end(),
+            }
+        }
+        Ok(())
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames
+            .last()
+            .expect("instructions execute within a frame")
+    }
+
+    fn instance(&self) -> &'a ModuleInst {
+        let instances = self.instances;
+        &instances[self.frame().instance]
+    }
+
+    /// Address of a memory of the current module.
+    fn mem(&self, idx: MemIdx) -> usize {
+        self.instance().mems[*idx.0 as usize]
+    }
+
+    fn block_type(&self, tpe: &BlockType) -> (usize, usize) {
+        match tpe {
+            BlockType::Empty => (0, 0),
+            BlockType::Val(_) => (0, 1),
+            BlockType::Type(idx) => {
+                let tpe = self.instance().module.func_type(*idx).expect("validated");
+                (tpe.params.types.len(), tpe.result.types.len())
+            }
+        }
+    }
+
+    fn enter(&mut self, instrs: &'a [Instr], tpe: &BlockType, is_loop: bool, handler: Handler<'a>) {
+        let (params, results) = self.block_type(tpe);
+        self.labels.push(Label {
+            instrs,
+            pc: 0,
+            arity: if is_loop { params } else { results },
+            height: self.stack.len() - params,
+            is_loop,
+            handler,
+        });
+    }
+
+    /// Leaves the innermost block, returning from the function if it was its body. The results
+    /// are already on top of the stack.
+    fn end(&mut self) {
+        self.labels.pop();
+        let frame = self.frame();
+        if self.labels.len() == frame.label {
+            self.locals.truncate(frame.locals);
+            self.frames.pop();
+        }
+    }
+
+    /// Branches to the label `depth` labels out.
+    fn branch(&mut self, depth: usize) {
+        let idx = self.labels.len() - 1 - depth;
+        let label = &self.labels[idx];
+        let (height, results, is_loop) =
+            (label.height, self.stack.len() - label.arity, label.is_loop);
+        self.stack.drain(height..results);
+        self.labels.truncate(idx + 1);
+        if is_loop {
+            self.labels[idx].pc = 0;
+        } else {
+            self.end();
+        }
+    }
+
+    fn call(&mut self, func: usize) -> Result<(), Trap> {
+        let funcs = self.funcs;
+        match &funcs[func] {
+            FuncInst::Wasm {
+                tpe,
+                instance,
+                func,
+                ..
+            } => {
+                if self.frames.len() >= MAX_CALL_DEPTH {
+                    return Err(Trap::CallStackExhausted);
+                }
+                let instances = self.instances;
+                let module = &instances[*instance].module;
+                let func = &module.funcs[*func];
+                let locals = self.locals.len();
+                let args = self.stack.len() - tpe.params.types.len();
+                self.locals.extend(self.stack.drain(args..));
+                self.locals.extend(
+                    func.locals
+                        .iter()
+                        .map(|tpe| Value::default_in(*tpe, module)),
+                );
+                self.frames.push(Frame {
+                    instance: *instance,
+                    locals,
+                    label: self.labels.len(),
+                });
+                self.labels.push(Label {
+                    instrs: &func.body.instr,
+                    pc: 0,
+                    arity: tpe.result.types.len(),
+                    height: self.stack.len(),
+                    is_loop: false,
+                    handler: Handler::None,
+                });
+            }
+            FuncInst::Host { tpe, func, .. } => {
+                let args = self
+                    .stack
+                    .split_off(self.stack.len() - tpe.params.types.len());
+                let mem = self.frames.last().and_then(|frame| {
+                    let instances = self.instances;
+                    instances[frame.instance].mems.first()
+                });
+                let mut caller = Caller {
+                    memory: mem.map(|mem| self.mems[*mem].data.as_mut_slice()),
+                };
+                let results = func(&mut caller, &args)?;
+                if results.len() != tpe.result.types.len()
+                    || results
+                        .iter()
+                        .zip(&tpe.result.types)
+                        .any(|(v, t)| !v.matches(*t))
+                {
+                    return Err(Trap::Host(
+                        "host function returned values that don't match its type".to_string(),
+                    ));
+                }
+                self.stack.extend(results);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pops an index into the table and returns the function it refers to, which must be of
+    /// type `tpe`.
+    fn indirect(&mut self, table: &TableIdx, tpe: &TypeIdx) -> Result<usize, Trap> {
+        let instance = self.instance();
+        let table = &self.tables[instance.tables[*table.0 as usize]];
+        let i = pop_index(&mut self.stack, table.index);
+        let element = usize::try_from(i).ok().and_then(|i| table.elements.get(i));
+        let func = match element {
+            None => return Err(Trap::UndefinedElement),
+            Some(Value::FuncRef(None)) => return Err(Trap::UninitializedElement(i)),
+            Some(Value::FuncRef(Some(func))) => *func,
+            Some(_) => unreachable!("call_indirect validates against a funcref table"),
+        };
+        // The function may have a subtype of the expected type.
+        let actual = HeapType::Concrete(TypeIdx(U32(self.funcs[func].type_id())));
+        let expected = HeapType::Concrete(TypeIdx(U32(instance.types[*tpe.0 as usize])));
+        if !self.registry.is_heap_subtype(actual, expected) {
+            return Err(Trap::IndirectCallTypeMismatch);
+        }
+        Ok(func)
+    }
+
+    /// Tail call: replaces the frame of the current function with the one of `func`, so that
+    /// chains of tail calls run in constant space.
+    fn return_call(&mut self, func: usize) -> Result<(), Trap> {
+        let frame = self
+            .frames
+            .pop()
+            .expect("instructions execute within a frame");
+        let args = self.stack.len() - self.funcs[func].tpe().params.types.len();
+        let height = self.labels[frame.label].height;
+        self.stack.drain(height..args);
+        self.labels.truncate(frame.label);
+        self.locals.truncate(frame.locals);
+        self.call(func)
+    }
+
+    fn execute(&mut self, instr: &'a Instr) -> Result<(), Trap> {
+        match instr {
+            Instr::Numeric(instr) => numeric::execute(instr, &mut self.stack)?,
+            Instr::Vector(instr) => self.vector(instr)?,
+            Instr::Atomic(instr) => self.atomic(instr)?,
+            Instr::Reference(instr) => self.reference(instr)?,
+            Instr::Aggregate(instr) => self.aggregate(instr)?,
+            Instr::Dropp => {
+                self.stack.pop();
+            }
+            Instr::Select(_) => {
+                let cond = pop::<i32>(&mut self.stack);
+                let b = self.stack.pop();
+                let a = self.stack.pop();
+                self.stack.extend(if cond != 0 { a } else { b });
+            }
+            Instr::Variable(instr) => self.variable(instr),
+            Instr::Table(instr) => self.table(instr)?,
+            Instr::Memory(instr) => self.memory(instr)?,
+            Instr::Control(instr) => self.control(instr)?,
+        }
+        Ok(())
+    }
+
+    fn reference(&mut self, instr: &ReferenceInstr) -> Result<(), Trap> {
+        let value = match instr {
+            ReferenceInstr::RefNull(tpe) => Value::null(*tpe, &self.instance().module),
+            ReferenceInstr::RefIsNull => {
+                let value = self.stack.pop().expect("validated");
+                Value::I32(value.is_null() as i32)
+            }
+            ReferenceInstr::RefFunc(idx) => {
+                Value::FuncRef(Some(self.instance().funcs[*idx.0 as usize]))
+            }
+            ReferenceInstr::RefEq => {
+                let b = self.stack.pop().expect("validated");
+                let a = self.stack.pop().expect("validated");
+                Value::I32((a == b) as i32)
+            }
+            ReferenceInstr::RefAsNonNull => {
+                let value = self.stack.pop().expect("validated");
+                if value.is_null() {
+                    return Err(Trap::NullReference);
+                }
+                value
+            }
+            ReferenceInstr::RefTest(tpe) => {
+                let value = self.stack.pop().expect("validated");
+                Value::I32(self.has_type(&value, tpe) as i32)
+            }
+            ReferenceInstr::RefCast(tpe) => {
+                let value = self.stack.pop().expect("validated");
+                if !self.has_type(&value, tpe) {
+                    return Err(Trap::CastFailure);
+                }
+                value
+            }
+            ReferenceInstr::RefI31 => {
+                let value = pop::<i32>(&mut self.stack) as u32;
+                Value::AnyRef(Some(AnyRef::I31(value & 0x7FFF_FFFF)))
+            }
+            ReferenceInstr::I31Get(sign) => match self.stack.pop() {
+                Some(Value::AnyRef(Some(AnyRef::I31(value)))) => match sign {
+                    Sign::Signed => Value::I32((value << 1) as i32 >> 1),
+                    Sign::Unsigned => Value::I32(value as i32),
+                },
+                Some(Value::AnyRef(None)) => return Err(Trap::NullI31Ref),
+                _ => unreachable!("i31.get is validated to take an i31ref"),
+            },
+            ReferenceInstr::AnyConvertExtern => match self.stack.pop() {
+                Some(Value::ExternRef(value)) => Value::AnyRef(value.map(AnyRef::Extern)),
+                Some(Value::ExternAny(value)) => Value::AnyRef(Some(value)),
+                _ => unreachable!("any.convert_extern is validated to take an externref"),
+            },
+            ReferenceInstr::ExternConvertAny => match self.stack.pop() {
+                Some(Value::AnyRef(None)) => Value::ExternRef(None),
+                Some(Value::AnyRef(Some(AnyRef::Extern(value)))) => Value::ExternRef(Some(value)),
+                Some(Value::AnyRef(Some(value))) => Value::ExternAny(value),
+                _ => unreachable!("extern.convert_any is validated to take an anyref"),
+            },
+        };
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Type of a non-null reference, with the canonical identifier of concrete types. `None` for
+    /// the null references.
+    fn heap_type(&self, value: &Value) -> Option<HeapType> {
+        let concrete = |id| HeapType::Concrete(TypeIdx(U32(id)));
+        match value {
+            Value::FuncRef(Some(func)) => Some(concrete(self.funcs[*func].type_id())),
+            Value::AnyRef(Some(AnyRef::I31(_))) => Some(HeapType::I31),
+            Value::AnyRef(Some(AnyRef::Struct(object) | AnyRef::Array(object))) => {
+                Some(concrete(self.objects[*object].type_id))
+            }
+            Value::AnyRef(Some(AnyRef::Extern(_))) => Some(HeapType::Any),
+            Value::ExternRef(Some(_)) | Value::ExternAny(_) => Some(HeapType::Extern),
+            Value::ExnRef(Some(_)) => Some(HeapType::Exn),
+            _ => None,
+        }
+    }
+
+    /// Whether a reference has the type `tpe` of the current module.
+    fn has_type(&self, value: &Value, tpe: &RefType) -> bool {
+        let expected = match tpe.heap {
+            HeapType::Concrete(idx) => {
+                HeapType::Concrete(TypeIdx(U32(self.instance().types[*idx.0 as usize])))
+            }
+            heap => heap,
+        };
+        match self.heap_type(value) {
+            Some(heap) => self.registry.is_heap_subtype(heap, expected),
+            None => tpe.nullable,
+        }
+    }
+
+    /// Pops a reference to a struct or array and returns its address. Traps with `null` if the
+    /// reference is null.
+    fn pop_object(&mut self, null: Trap) -> Result<usize, Trap> {
+        match self.stack.pop() {
+            Some(Value::AnyRef(Some(AnyRef::Struct(object) | AnyRef::Array(object)))) => Ok(object),
+            Some(Value::AnyRef(None)) => Err(null),
+            _ => unreachable!("validated code pops references to the right type"),
+        }
+    }
+
+    /// Allocates a struct or array of the type `idx` of the current module and pushes a
+    /// reference to it.
+    fn alloc(&mut self, idx: &TypeIdx, fields: Vec<Value>) {
+        let type_id = self.instance().types[*idx.0 as usize];
+        self.objects.push(GcObject { type_id, fields });
+        let object = match self.registry.get(type_id).comp {
+            CompType::Array(_) => AnyRef::Array(self.objects.len() - 1),
+            _ => AnyRef::Struct(self.objects.len() - 1),
+        };
+        self.stack.push(Value::AnyRef(Some(object)));
+    }
+
+    /// Reads `n` elements of type `tpe` from `src` in the data segment `data` of the current
+    /// module.
+    fn read_data(&self, data: u32, tpe: StorageType, src: u32, n: u32) -> Result<Vec<Value>, Trap> {
+        let data = &self.datas[self.instance().datas[data as usize]];
+        let size = byte_size(tpe);
+        let range = range(
+            src.into(),
+            u64::from(n) * size as u64,
+            data.len(),
+            Trap::MemoryOutOfBounds,
+        )?;
+        Ok(data[range]
+            .chunks(size)
+            .map(|bytes| read_value(tpe, bytes))
+            .collect())
+    }
+
+    /// Reads `n` references from `src` in the element segment `elem` of the current module.
+    fn read_elem(&self, elem: u32, src: u32, n: u32) -> Result<Vec<Value>, Trap> {
+        let elem = &self.elems[self.instance().elems[elem as usize]];
+        let range = range(src.into(), n.into(), elem.len(), Trap::TableOutOfBounds)?;
+        Ok(elem[range].to_vec())
+    }
+
+    /// Pops the operands of `array.init_data` and `array.init_elem`: the array, the range of
+    /// elements to initialize, and the offset and number of elements within the segment.
+    fn pop_array_init(&mut self) -> Result<(usize, Range<usize>, u32, u32), Trap> {
+        let n = pop::<i32>(&mut self.stack) as u32;
+        let s = pop::<i32>(&mut self.stack) as u32;
+        let d = pop::<i32>(&mut self.stack) as u32;
+        let object = self.pop_object(Trap::NullArrayRef)?;
+        let len = self.objects[object].fields.len();
+        let d = range(d.into(), n.into(), len, Trap::ArrayOutOfBounds)?;
+        Ok((object, d, s, n))
+    }
+
+    fn aggregate(&mut self, instr: &AggregateInstr) -> Result<(), Trap> {
+        let module = &self.instance().module;
+        match instr {
+            AggregateInstr::StructNew(x) => {
+                let types = struct_fields(module, x);
+                let fields = self.stack.split_off(self.stack.len() - types.len());
+                let fields = types.iter().zip(fields).map(|(t, v)| pack(t, v)).collect();
+                self.alloc(x, fields);
+            }
+            AggregateInstr::StructNewDefault(x) => {
+                let fields = struct_fields(module, x)
+                    .iter()
+                    .map(|field| Value::default_in(field.tpe.unpacked(), module))
+                    .collect();
+                self.alloc(x, fields);
+            }
+            AggregateInstr::StructGet(sign, x, y) => {
+                let field = &struct_fields(module, x)[*y.0 as usize];
+                let object = self.pop_object(Trap::NullStructRef)?;
+                let value = self.objects[object].fields[*y.0 as usize];
+                self.stack.push(unpack(field, value, sign));
+            }
+            AggregateInstr::StructSet(x, y) => {
+                let field = &struct_fields(module, x)[*y.0 as usize];
+                let value = self.stack.pop().expect("validated");
+                let object = self.pop_object(Trap::NullStructRef)?;
+                self.objects[object].fields[*y.0 as usize] = pack(field, value);
+            }
+            AggregateInstr::ArrayNew(x) => {
+                let n = pop::<i32>(&mut self.stack) as u32;
+                let value = pack(
+                    &array_field(module, x),
+                    self.stack.pop().expect("validated"),
+                );
+                self.alloc(x, vec![value; n as usize]);
+            }
+            AggregateInstr::ArrayNewDefault(x) => {
+                let n = pop::<i32>(&mut self.stack) as u32;
+                let value = Value::default_in(array_field(module, x).tpe.unpacked(), module);
+                self.alloc(x, vec![value; n as usize]);
+            }
+            AggregateInstr::ArrayNewFixed(x, n) => {
+                let field = array_field(module, x);
+                let values = self.stack.split_off(self.stack.len() - **n as usize);
+                let values = values.into_iter().map(|v| pack(&field, v)).collect();
+                self.alloc(x, values);
+            }
+            AggregateInstr::ArrayNewData(x, y) => {
+                let n = pop::<i32>(&mut self.stack) as u32;
+                let s = pop::<i32>(&mut self.stack) as u32;
+                let values = self.read_data(*y.0, array_field(module, x).tpe, s, n)?;
+                self.alloc(x, values);
+            }
+            AggregateInstr::ArrayNewElem(x, y) => {
+                let n = pop::<i32>(&mut self.stack) as u32;
+                let s = pop::<i32>(&mut self.stack) as u32;
+                let values = self.read_elem(*y.0, s, n)?;
+                self.alloc(x, values);
+            }
+            AggregateInstr::ArrayGet(sign, x) => {
+                let i = pop::<i32>(&mut self.stack) as u32 as usize;
+                let object = self.pop_object(Trap::NullArrayRef)?;
+                let fields = &self.objects[object].fields;
+                let value = *fields.get(i).ok_or(Trap::ArrayOutOfBounds)?;
+                self.stack
+                    .push(unpack(&array_field(module, x), value, sign));
+            }
+            AggregateInstr::ArraySet(x) => {
+                let value = pack(
+                    &array_field(module, x),
+                    self.stack.pop().expect("validated"),
+                );
+                let i = pop::<i32>(&mut self.stack) as u32 as usize;
+                let object = self.pop_object(Trap::NullArrayRef)?;
+                let fields = &mut self.objects[object].fields;
+                *fields.get_mut(i).ok_or(Trap::ArrayOutOfBounds)? = value;
+            }
+            AggregateInstr::ArrayLen => {
+                let object = self.pop_object(Trap::NullArrayRef)?;
+                let len = self.objects[object].fields.len();
+                self.stack.push(Value::I32(len as i32));
+            }
+            AggregateInstr::ArrayFill(x) => {
+                let n = pop::<i32>(&mut self.stack) as u32;
+                let value = pack(
+                    &array_field(module, x),
+                    self.stack.pop().expect("validated"),
+                );
+                let i = pop::<i32>(&mut self.stack) as u32;
+                let object = self.pop_object(Trap::NullArrayRef)?;
+                let fields = &mut self.objects[object].fields;
+                let range = range(i.into(), n.into(), fields.len(), Trap::ArrayOutOfBounds)?;
+                fields[range].fill(value);
+            }
+            AggregateInstr::ArrayCopy(..) => {
+                let n = pop::<i32>(&mut self.stack) as u32;
+                let s = pop::<i32>(&mut self.stack) as u32;
+                let src = self.pop_object(Trap::NullArrayRef)?;
+                let d = pop::<i32>(&mut self.stack) as u32;
+                let dst = self.pop_object(Trap::NullArrayRef)?;
+                let len = self.objects[src].fields.len();
+                let s = range(s.into(), n.into(), len, Trap::ArrayOutOfBounds)?;
+                let len = self.objects[dst].fields.len();
+                let d = range(d.into(), n.into(), len, Trap::ArrayOutOfBounds)?;
+                // Packed values are already wrapped to the size of the destination.
+                let values = self.objects[src].fields[s].to_vec();
+                self.objects[dst].fields[d].copy_from_slice(&values);
+            }
+            AggregateInstr::ArrayInitData(x, y) => {
+                let (object, d, s, n) = self.pop_array_init()?;
+                let values = self.read_data(*y.0, array_field(module, x).tpe, s, n)?;
+                self.objects[object].fields[d].copy_from_slice(&values);
+            }
+            AggregateInstr::ArrayInitElem(_, y) => {
+                let (object, d, s, n) = self.pop_array_init()?;
+                let values = self.read_elem(*y.0, s, n)?;
+                self.objects[object].fields[d].copy_from_slice(&values);
+            }
+        }
+        Ok(())
+    }
+
+    fn variable(&mut self, instr: &VariableInstr) {
+        let locals = self.frame().locals;
+        match instr {
+            VariableInstr::LocalGet(idx) => {
+                let value = self.locals[locals + *idx.0 as usize];
+                self.stack.push(value);
+            }
+            VariableInstr::LocalSet(idx) => {
+                self.locals[locals + *idx.0 as usize] = self.stack.pop().expect("validated");
+            }
+            VariableInstr::LocalTee(idx) => {
+                self.locals[locals + *idx.0 as usize] = *self.stack.last().expect("validated");
+            }
+            VariableInstr::GlobalGet(idx) => {
+                let global = self.instance().globals[*idx.0 as usize];
+                self.stack.push(self.globals[global].value);
+            }
+            VariableInstr::GlobalSet(idx) => {
+                let global = self.instance().globals[*idx.0 as usize];
+                self.globals[global].value = self.stack.pop().expect("validated");
+            }
+        }
+    }
+
+    /// Copies `n` elements of the element segment at address `elem` from `src` into the table at
+    /// address `table` from `dst`. Traps before copying anything if either range is out of
+    /// bounds.
+    pub(crate) fn init_table(
+        &mut self,
+        table: usize,
+        elem: usize,
+        dst: u64,
+        src: u32,
+        n: u32,
+    ) -> Result<(), Trap> {
+        let elem = &self.elems[elem];
+        let table = &mut self.tables[table].elements;
+        let src = range(src.into(), n.into(), elem.len(), Trap::TableOutOfBounds)?;
+        let dst = range(dst, n.into(), table.len(), Trap::TableOutOfBounds)?;
+        table[dst].copy_from_slice(&elem[src]);
+        Ok(())
+    }
+
+    /// Copies `n` bytes of the data segment at address `data` from `src` into the memory at
+    /// address `mem` from `dst`. Traps before copying anything if either range is out of bounds.
+    pub(crate) fn init_memory(
+        &mut self,
+        mem: usize,
+        data: usize,
+        dst: u64,
+        src: u32,
+        n: u32,
+    ) -> Result<(), Trap> {
+        let data = &self.datas[data];
+        let mem = &mut self.mems[mem].data;
+        let src = range(src.into(), n.into(), data.len(), Trap::MemoryOutOfBounds)?;
+        let dst = range(dst, n.into(), mem.len(), Trap::MemoryOutOfBounds)?;
+        mem[dst].copy_from_slice(&data[src]);
+        Ok(())
+    }
+
+    pub(crate) fn drop_elem(&mut self, elem: usize) {
+        self.elems[elem] = Vec::new();
+    }
+
+    pub(crate) fn drop_data(&mut self, data: usize) {
+        self.datas[data] = Vec::new();
+    }
+
+    fn table(&mut self, instr: &TableInstr) -> Result<(), Trap> {
+        let instance = self.instance();
+        let table = |idx: &TableIdx| instance.tables[*idx.0 as usize];
+        let stack = &mut self.stack;
+        match instr {
+            TableInstr::TableGet(idx) => {
+                let table = &self.tables[table(idx)];
+                let i = pop_index(stack, table.index);
+                let i = range(i, 1, table.elements.len(), Trap::TableOutOfBounds)?;
+                stack.push(table.elements[i.start]);
+            }
+            TableInstr::TableSet(idx) => {
+                let table = &mut self.tables[table(idx)];
+                let value = stack.pop().expect("validated");
+                let i = pop_index(stack, table.index);
+                let i = range(i, 1, table.elements.len(), Trap::TableOutOfBounds)?;
+                table.elements[i.start] = value;
+            }
+            TableInstr::TableSize(idx) => {
+                let table = &self.tables[table(idx)];
+                stack.push(index_value(table.index, table.elements.len() as u64));
+            }
+            TableInstr::TableGrow(idx) => {
+                let table = &mut self.tables[table(idx)];
+                let n = pop_index(stack, table.index);
+                let init = stack.pop().expect("validated");
+                // Failing to grow gives -1, all bits set.
+                let size = table.grow(n, init).unwrap_or(u64::MAX);
+                stack.push(index_value(table.index, size));
+            }
+            TableInstr::TableFill(idx) => {
+                let table = &mut self.tables[table(idx)];
+                let n = pop_index(stack, table.index);
+                let value = stack.pop().expect("validated");
+                let i = pop_index(stack, table.index);
+                let range = range(i, n, table.elements.len(), Trap::TableOutOfBounds)?;
+                table.elements[range].fill(value);
+            }
+            TableInstr::TableCopy(dst, src) => {
+                let (dst, src) = (table(dst), table(src));
+                let (dst_index, src_index) = (self.tables[dst].index, self.tables[src].index);
+                // The length is an `i64` only between two 64-bit tables.
+                let n = if dst_index == IndexType::I64 && src_index == IndexType::I64 {
+                    pop::<i64>(stack) as u64
+                } else {
+                    u64::from(pop::<i32>(stack) as u32)
+                };
+                let s = pop_index(stack, src_index);
+                let d = pop_index(stack, dst_index);
+                let len = self.tables[src].elements.len();
+                let s = range(s, n, len, Trap::TableOutOfBounds)?;
+                let len = self.tables[dst].elements.len();
+                let d = range(d, n, len, Trap::TableOutOfBounds)?;
+                let values = self.tables[src].elements[s].to_vec();
+                self.tables[dst].elements[d].copy_from_slice(&values);
+            }
+            TableInstr::TableInit(idx, elem) => {
+                let n = pop::<i32>(stack) as u32;
+                let s = pop::<i32>(stack) as u32;
+                let d = pop_index(stack, self.tables[table(idx)].index);
+                let elem = instance.elems[*elem.0 as usize];
+                self.init_table(table(idx), elem, d, s, n)?;
+            }
+            TableInstr::ElemDrop(elem) => self.drop_elem(instance.elems[*elem.0 as usize]),
+        }
+        Ok(())
+    }
+
+    /// Pops an address into memory `mem`, which is an `i64` for 64-bit memories.
+    fn pop_addr(&mut self, mem: usize) -> u64 {
+        pop_index(&mut self.stack, self.mems[mem].index)
+    }
+
+    /// Value of an address or size of memory `mem`.
+    fn addr_value(&self, mem: usize, value: u64) -> Value {
+        index_value(self.mems[mem].index, value)
+    }
+
+    /// Pops an address and returns the range of `n` bytes at the effective address.
+    fn address(&mut self, arg: &MemArg, n: u64) -> Result<Range<usize>, Trap> {
+        let mem = self.mem(arg.mem);
+        let addr = self.pop_addr(mem);
+        let len = self.mems[mem].data.len();
+        let start = addr
+            .checked_add(*arg.offset)
+            .ok_or(Trap::MemoryOutOfBounds)?;
+        range(start, n, len, Trap::MemoryOutOfBounds)
+    }
+
+    fn load<const N: usize>(&mut self, arg: &MemArg) -> Result<[u8; N], Trap> {
+        let range = self.address(arg, N as u64)?;
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.mems[self.mem(arg.mem)].data[range]);
+        Ok(bytes)
+    }
+
+    fn store<const N: usize>(&mut self, arg: &MemArg, bytes: [u8; N]) -> Result<(), Trap> {
+        let range = self.address(arg, N as u64)?;
+        let mem = self.mem(arg.mem);
+        self.mems[mem].data[range].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Pops an address and returns the range of the `n` bytes an atomic instruction accesses,
+    /// which must be aligned to `n`.
+    fn atomic_address(&mut self, arg: &MemArg, n: u64) -> Result<Range<usize>, Trap> {
+        let range = self.address(arg, n)?;
+        if !(range.start as u64).is_multiple_of(n) {
+            return Err(Trap::UnalignedAtomic);
+        }
+        Ok(range)
+    }
+
+    /// Reads the little endian integer stored in `range` of memory `mem`, zero extended.
+    fn read(&self, mem: usize, range: Range<usize>) -> u64 {
+        let data = &self.mems[mem].data[range];
+        data.iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | u64::from(byte))
+    }
+
+    /// Writes the low bytes of `value` to `range` of memory `mem`.
+    fn write(&mut self, mem: usize, range: Range<usize>, value: u64) {
+        let n = range.len();
+        self.mems[mem].data[range].copy_from_slice(&value.to_le_bytes()[..n]);
+    }
+
+    fn atomic_load(&mut self, arg: &MemArg, n: u64, tpe: &IntType) -> Result<(), Trap> {
+        let range = self.atomic_address(arg, n)?;
+        let value = self.read(self.mem(arg.mem), range);
+        self.stack.push(int(tpe, value as i64));
+        Ok(())
+    }
+
+    fn atomic_store(&mut self, arg: &MemArg, n: u64, tpe: &IntType) -> Result<(), Trap> {
+        let value = self.pop_int(tpe);
+        let range = self.atomic_address(arg, n)?;
+        self.write(self.mem(arg.mem), range, value as u64);
+        Ok(())
+    }
+
+    /// Applies `op` to the value in memory and the operand, and pushes the previous value.
+    fn atomic_rmw(
+        &mut self,
+        arg: &MemArg,
+        n: u64,
+        tpe: &IntType,
+        op: fn(u64, u64) -> u64,
+    ) -> Result<(), Trap> {
+        let operand = self.pop_int(tpe) as u64;
+        let range = self.atomic_address(arg, n)?;
+        let mem = self.mem(arg.mem);
+        let old = self.read(mem, range.clone());
+        self.write(mem, range, op(old, operand));
+        self.stack.push(int(tpe, old as i64));
+        Ok(())
+    }
+
+    /// Replaces the value in memory if it's equal to the expected one, once both are truncated
+    /// to the width of the access, and pushes the previous value.
+    fn atomic_cmpxchg(&mut self, arg: &MemArg, n: u64, tpe: &IntType) -> Result<(), Trap> {
+        let replacement = self.pop_int(tpe) as u64;
+        let expected = self.pop_int(tpe) as u64;
+        let range = self.atomic_address(arg, n)?;
+        let mem = self.mem(arg.mem);
+        let old = self.read(mem, range.clone());
+        let mask = u64::MAX >> (64 - 8 * n);
+        if old == expected & mask {
+            self.write(mem, range, replacement);
+        }
+        self.stack.push(int(tpe, old as i64));
+        Ok(())
+    }
+
+    /// Executes `memory.atomic.wait32` or `memory.atomic.wait64`. Since the store runs on a
+    /// single thread, nothing can notify the waiter: the wait either returns "not-equal" (1) or
+    /// times out (2), and waiting forever is a deadlock.
+    fn wait(&mut self, arg: &MemArg, tpe: &IntType) -> Result<(), Trap> {
+        let timeout = pop::<i64>(&mut self.stack);
+        let expected = self.pop_int(tpe) as u64;
+        let n = match tpe {
+            IntType::I32 => 4,
+            IntType::I64 => 8,
+        };
+        let range = self.atomic_address(arg, n)?;
+        let mem = self.mem(arg.mem);
+        if !self.mems[mem].shared {
+            return Err(Trap::UnsharedMemory);
+        }
+        let mask = u64::MAX >> (64 - 8 * n);
+        let result = if self.read(mem, range) != expected & mask {
+            1
+        } else if timeout < 0 {
+            return Err(Trap::Deadlock);
+        } else {
+            2
+        };
+        self.stack.push(Value::I32(result));
+        Ok(())
+    }
+
+    fn atomic(&mut self, instr: &AtomicInstr) -> Result<(), Trap> {
+        use AtomicInstr::*;
+        use IntType::{I32, I64};
+        match instr {
+            AtomicFence => Ok(()),
+            MemoryAtomicNotify(arg) => {
+                pop::<i32>(&mut self.stack);
+                self.atomic_address(arg, 4)?;
+                // A single thread runs the store, so no one can be waiting.
+                self.stack.push(Value::I32(0));
+                Ok(())
+            }
+            MemoryAtomicWait32(arg) => self.wait(arg, &I32),
+            MemoryAtomicWait64(arg) => self.wait(arg, &I64),
+            I32AtomicLoad(arg) => self.atomic_load(arg, 4, &I32),
+            I64AtomicLoad(arg) => self.atomic_load(arg, 8, &I64),
+            I32AtomicLoad8U(arg) => self.atomic_load(arg, 1, &I32),
+            I32AtomicLoad16U(arg) => self.atomic_load(arg, 2, &I32),
+            I64AtomicLoad8U(arg) => self.atomic_load(arg, 1, &I64),
+            I64AtomicLoad16U(arg) => self.atomic_load(arg, 2, &I64),
+            I64AtomicLoad32U(arg) => self.atomic_load(arg, 4, &I64),
+            I32AtomicStore(arg) => self.atomic_store(arg, 4, &I32),
+            I64AtomicStore(arg) => self.atomic_store(arg, 8, &I64),
+            I32AtomicStore8(arg) => self.atomic_store(arg, 1, &I32),
+            I32AtomicStore16(arg) => self.atomic_store(arg, 2, &I32),
+            I64AtomicStore8(arg) => self.atomic_store(arg, 1, &I64),
+            I64AtomicStore16(arg) => self.atomic_store(arg, 2, &I64),
+            I64AtomicStore32(arg) => self.atomic_store(arg, 4, &I64),
+            I32AtomicRmwAdd(arg) => self.atomic_rmw(arg, 4, &I32, u64::wrapping_add),
+            I64AtomicRmwAdd(arg) => self.atomic_rmw(arg, 8, &I64, u64::wrapping_add),
+            I32AtomicRmw8AddU(arg) => self.atomic_rmw(arg, 1, &I32, u64::wrapping_add),
+            I32AtomicRmw16AddU(arg) => self.atomic_rmw(arg, 2, &I32, u64::wrapping_add),
+            I64AtomicRmw8AddU(arg) => self.atomic_rmw(arg, 1, &I64, u64::wrapping_add),
+            I64AtomicRmw16AddU(arg) => self.atomic_rmw(arg, 2, &I64, u64::wrapping_add),
+            I64AtomicRmw32AddU(arg) => self.atomic_rmw(arg, 4, &I64, u64::wrapping_add),
+            I32AtomicRmwSub(arg) => self.atomic_rmw(arg, 4, &I32, u64::wrapping_sub),
+            I64AtomicRmwSub(arg) => self.atomic_rmw(arg, 8, &I64, u64::wrapping_sub),
+            I32AtomicRmw8SubU(arg) => self.atomic_rmw(arg, 1, &I32, u64::wrapping_sub),
+            I32AtomicRmw16SubU(arg) => self.atomic_rmw(arg, 2, &I32, u64::wrapping_sub),
+            I64AtomicRmw8SubU(arg) => self.atomic_rmw(arg, 1, &I64, u64::wrapping_sub),
+            I64AtomicRmw16SubU(arg) => self.atomic_rmw(arg, 2, &I64, u64::wrapping_sub),
+            I64AtomicRmw32SubU(arg) => self.atomic_rmw(arg, 4, &I64, u64::wrapping_sub),
+            I32AtomicRmwAnd(arg) => self.atomic_rmw(arg, 4, &I32, |x, y| x & y),
+            I64AtomicRmwAnd(arg) => self.atomic_rmw(arg, 8, &I64, |x, y| x & y),
+            I32AtomicRmw8AndU(arg) => self.atomic_rmw(arg, 1, &I32, |x, y| x & y),
+            I32AtomicRmw16AndU(arg) => self.atomic_rmw(arg, 2, &I32, |x, y| x & y),
+            I64AtomicRmw8AndU(arg) => self.atomic_rmw(arg, 1, &I64, |x, y| x & y),
+            I64AtomicRmw16AndU(arg) => self.atomic_rmw(arg, 2, &I64, |x, y| x & y),
+            I64AtomicRmw32AndU(arg) => self.atomic_rmw(arg, 4, &I64, |x, y| x & y),
+            I32AtomicRmwOr(arg) => self.atomic_rmw(arg, 4, &I32, |x, y| x | y),
+            I64AtomicRmwOr(arg) => self.atomic_rmw(arg, 8, &I64, |x, y| x | y),
+            I32AtomicRmw8OrU(arg) => self.atomic_rmw(arg, 1, &I32, |x, y| x | y),
+            I32AtomicRmw16OrU(arg) => self.atomic_rmw(arg, 2, &I32, |x, y| x | y),
+            I64AtomicRmw8OrU(arg) => self.atomic_rmw(arg, 1, &I64, |x, y| x | y),
+            I64AtomicRmw16OrU(arg) => self.atomic_rmw(arg, 2, &I64, |x, y| x | y),
+            I64AtomicRmw32OrU(arg) => self.atomic_rmw(arg, 4, &I64, |x, y| x | y),
+            I32AtomicRmwXor(arg) => self.atomic_rmw(arg, 4, &I32, |x, y| x ^ y),
+            I64AtomicRmwXor(arg) => self.atomic_rmw(arg, 8, &I64, |x, y| x ^ y),
+            I32AtomicRmw8XorU(arg) => self.atomic_rmw(arg, 1, &I32, |x, y| x ^ y),
+            I32AtomicRmw16XorU(arg) => self.atomic_rmw(arg, 2, &I32, |x, y| x ^ y),
+            I64AtomicRmw8XorU(arg) => self.atomic_rmw(arg, 1, &I64, |x, y| x ^ y),
+            I64AtomicRmw16XorU(arg) => self.atomic_rmw(arg, 2, &I64, |x, y| x ^ y),
+            I64AtomicRmw32XorU(arg) => self.atomic_rmw(arg, 4, &I64, |x, y| x ^ y),
+            I32AtomicRmwXchg(arg) => self.atomic_rmw(arg, 4, &I32, |_, y| y),
+            I64AtomicRmwXchg(arg) => self.atomic_rmw(arg, 8, &I64, |_, y| y),
+            I32AtomicRmw8XchgU(arg) => self.atomic_rmw(arg, 1, &I32, |_, y| y),
+            I32AtomicRmw16XchgU(arg) => self.atomic_rmw(arg, 2, &I32, |_, y| y),
+            I64AtomicRmw8XchgU(arg) => self.atomic_rmw(arg, 1, &I64, |_, y| y),
+            I64AtomicRmw16XchgU(arg) => self.atomic_rmw(arg, 2, &I64, |_, y| y),
+            I64AtomicRmw32XchgU(arg) => self.atomic_rmw(arg, 4, &I64, |_, y| y),
+            I32AtomicRmwCmpxchg(arg) => self.atomic_cmpxchg(arg, 4, &I32),
+            I64AtomicRmwCmpxchg(arg) => self.atomic_cmpxchg(arg, 8, &I64),
+            I32AtomicRmw8CmpxchgU(arg) => self.atomic_cmpxchg(arg, 1, &I32),
+            I32AtomicRmw16CmpxchgU(arg) => self.atomic_cmpxchg(arg, 2, &I32),
+            I64AtomicRmw8CmpxchgU(arg) => self.atomic_cmpxchg(arg, 1, &I64),
+            I64AtomicRmw16CmpxchgU(arg) => self.atomic_cmpxchg(arg, 2, &I64),
+            I64AtomicRmw32CmpxchgU(arg) => self.atomic_cmpxchg(arg, 4, &I64),
+        }
+    }
+
+    /// Executes the vector memory accesses, and leaves the other vector instructions to
+    /// [vector::execute].
+    fn vector(&mut self, instr: &VectorInstr) -> Result<(), Trap> {
+        use VectorInstr::*;
+        let value = match instr {
+            V128Load(arg) => u128::from_le_bytes(self.load(arg)?),
+            V128Load8x8S(arg) => {
+                vector::extend(u64::from_le_bytes(self.load(arg)?).into(), 16, false, true)
+            }
+            V128Load8x8U(arg) => {
+                vector::extend(u64::from_le_bytes(self.load(arg)?).into(), 16, false, false)
+            }
+            V128Load16x4S(arg) => {
+                vector::extend(u64::from_le_bytes(self.load(arg)?).into(), 32, false, true)
+            }
+            V128Load16x4U(arg) => {
+                vector::extend(u64::from_le_bytes(self.load(arg)?).into(), 32, false, false)
+            }
+            V128Load32x2S(arg) => {
+                vector::extend(u64::from_le_bytes(self.load(arg)?).into(), 64, false, true)
+            }
+            V128Load32x2U(arg) => {
+                vector::extend(u64::from_le_bytes(self.load(arg)?).into(), 64, false, false)
+            }
+            V128Load8Splat(arg) => vector::splat(8, u8::from_le_bytes(self.load(arg)?).into()),
+            V128Load16Splat(arg) => vector::splat(16, u16::from_le_bytes(self.load(arg)?).into()),
+            V128Load32Splat(arg) => vector::splat(32, u32::from_le_bytes(self.load(arg)?).into()),
+            V128Load64Splat(arg) => vector::splat(64, u64::from_le_bytes(self.load(arg)?).into()),
+            V128Load32Zero(arg) => u32::from_le_bytes(self.load(arg)?).into(),
+            V128Load64Zero(arg) => u64::from_le_bytes(self.load(arg)?).into(),
+            V128Store(arg) => {
+                let value = pop::<u128>(&mut self.stack);
+                return self.store(arg, value.to_le_bytes());
+            }
+            V128Load8Lane(arg, lane) => {
+                let value = pop::<u128>(&mut self.stack);
+                let x = u8::from_le_bytes(self.load(arg)?);
+                vector::replace(value, 8, (*lane.0).into(), x.into())
+            }
+            V128Load16Lane(arg, lane) => {
+                let value = pop::<u128>(&mut self.stack);
+                let x = u16::from_le_bytes(self.load(arg)?);
+                vector::replace(value, 16, (*lane.0).into(), x.into())
+            }
+            V128Load32Lane(arg, lane) => {
+                let value = pop::<u128>(&mut self.stack);
+                let x = u32::from_le_bytes(self.load(arg)?);
+                vector::replace(value, 32, (*lane.0).into(), x.into())
+            }
+            V128Load64Lane(arg, lane) => {
+                let value = pop::<u128>(&mut self.stack);
+                let x = u64::from_le_bytes(self.load(arg)?);
+                vector::replace(value, 64, (*lane.0).into(), x.into())
+            }
+            V128Store8Lane(arg, lane) => {
+                let value = pop::<u128>(&mut self.stack);
+                let x = vector::lane(value, 8, (*lane.0).into()) as u8;
+                return self.store(arg, x.to_le_bytes());
+            }
+            V128Store16Lane(arg, lane) => {
+                let value = pop::<u128>(&mut self.stack);
+                let x = vector::lane(value, 16, (*lane.0).into()) as u16;
+                return self.store(arg, x.to_le_bytes());
+            }
+            V128Store32Lane(arg, lane) => {
+                let value = pop::<u128>(&mut self.stack);
+                let x = vector::lane(value, 32, (*lane.0).into()) as u32;
+                return self.store(arg, x.to_le_bytes());
+            }
+            V128Store64Lane(arg, lane) => {
+                let value = pop::<u128>(&mut self.stack);
+                let x = vector::lane(value, 64, (*lane.0).into()) as u64;
+                return self.store(arg, x.to_le_bytes());
+            }
+            instr => {
+                vector::execute(instr, &mut self.stack);
+                return Ok(());
+            }
+        };
+        self.stack.push(Value::V128(value));
+        Ok(())
+    }
+
+    fn memory(&mut self, instr: &MemoryInstr) -> Result<(), Trap> {
+        use MemoryInstr::*;
+        let value = match instr {
+            ILoad(IntType::I32, arg) => Value::I32(i32::from_le_bytes(self.load(arg)?)),
+            ILoad(IntType::I64, arg) => Value::I64(i64::from_le_bytes(self.load(arg)?)),
+            FLoad(FloatType::F32, arg) => Value::F32(f32::from_le_bytes(self.load(arg)?)),
+            FLoad(FloatType::F64, arg) => Value::F64(f64::from_le_bytes(self.load(arg)?)),
+            ILoad8(tpe, sign, arg) => {
+                let [byte] = self.load(arg)?;
+                let value = match sign {
+                    Sign::Signed => i64::from(byte as i8),
+                    Sign::Unsigned => i64::from(byte),
+                };
+                int(tpe, value)
+            }
+            ILoad16(tpe, sign, arg) => {
+                let bytes = self.load(arg)?;
+                let value = match sign {
+                    Sign::Signed => i64::from(i16::from_le_bytes(bytes)),
+                    Sign::Unsigned => i64::from(u16::from_le_bytes(bytes)),
+                };
+                int(tpe, value)
+            }
+            I64Load32(sign, arg) => {
+                let bytes = self.load(arg)?;
+                Value::I64(match sign {
+                    Sign::Signed => i64::from(i32::from_le_bytes(bytes)),
+                    Sign::Unsigned => i64::from(u32::from_le_bytes(bytes)),
+                })
+            }
+            IStore(IntType::I32, arg) => {
+                let value = pop::<i32>(&mut self.stack);
+                return self.store(arg, value.to_le_bytes());
+            }
+            IStore(IntType::I64, arg) => {
+                let value = pop::<i64>(&mut self.stack);
+                return self.store(arg, value.to_le_bytes());
+            }
+            FStore(FloatType::F32, arg) => {
+                let value = pop::<f32>(&mut self.stack);
+                return self.store(arg, value.to_le_bytes());
+            }
+            FStore(FloatType::F64, arg) => {
+                let value = pop::<f64>(&mut self.stack);
+                return self.store(arg, value.to_le_bytes());
+            }
+            IStore8(tpe, arg) => {
+                let value = self.pop_int(tpe);
+                return self.store(arg, [value as u8]);
+            }
+            IStore16(tpe, arg) => {
+                let value = self.pop_int(tpe);
+                return self.store(arg, (value as u16).to_le_bytes());
+            }
+            I64Store32(arg) => {
+                let value = pop::<i64>(&mut self.stack);
+                return self.store(arg, (value as u32).to_le_bytes());
+            }
+            MemorySize(idx) => {
+                let mem = self.mem(*idx);
+                self.addr_value(mem, self.mems[mem].pages())
+            }
+            MemoryGrow(idx) => {
+                let mem = self.mem(*idx);
+                let n = self.pop_addr(mem);
+                let pages = self.mems[mem].grow(n).unwrap_or(u64::MAX);
+                self.addr_value(mem, pages)
+            }
+            MemoryFill(idx) => {
+                let mem = self.mem(*idx);
+                let n = self.pop_addr(mem);
+                let value = pop::<i32>(&mut self.stack) as u8;
+                let d = self.pop_addr(mem);
+                let data = &mut self.mems[mem].data;
+                let d = range(d, n, data.len(), Trap::MemoryOutOfBounds)?;
+                data[d].fill(value);
+                return Ok(());
+            }
+            MemoryCopy(dst, src) => {
+                let (dst, src) = (self.mem(*dst), self.mem(*src));
+                // The length is an `i64` only between two 64-bit memories.
+                let n = if self.mems[dst].index == IndexType::I64
+                    && self.mems[src].index == IndexType::I64
+                {
+                    pop::<i64>(&mut self.stack) as u64
+                } else {
+                    u64::from(pop::<i32>(&mut self.stack) as u32)
+                };
+                let s = self.pop_addr(src);
+                let d = self.pop_addr(dst);
+                let len = self.mems[src].data.len();
+                let s = range(s, n, len, Trap::MemoryOutOfBounds)?;
+                let len = self.mems[dst].data.len();
+                let d = range(d, n, len, Trap::MemoryOutOfBounds)?;
+                if dst == src {
+                    self.mems[dst].data.copy_within(s, d.start);
+                } else {
+                    let bytes = self.mems[src].data[s].to_vec();
+                    self.mems[dst].data[d].copy_from_slice(&bytes);
+                }
+                return Ok(());
+            }
+            MemoryInit(mem, idx) => {
+                let mem = self.mem(*mem);
+                let n = pop::<i32>(&mut self.stack) as u32;
+                let s = pop::<i32>(&mut self.stack) as u32;
+                let d = self.pop_addr(mem);
+                let data = self.instance().datas[*idx.0 as usize];
+                return self.init_memory(mem, data, d, s, n);
+            }
+            DataDrop(idx) => {
+                self.drop_data(self.instance().datas[*idx.0 as usize]);
+                return Ok(());
+            }
+        };
+        self.stack.push(value);
+        Ok(())
+    }
+
+    /// Pops an integer of type `tpe`, sign extended to 64 bits.
+    fn pop_int(&mut self, tpe: &IntType) -> i64 {
+        match tpe {
+            IntType::I32 => i64::from(pop::<i32>(&mut self.stack)),
+            IntType::I64 => pop::<i64>(&mut self.stack),
+        }
+    }
+
+    fn control(&mut self, instr: &'a ControlInstr) -> Result<(), Trap> {
+        match instr {
+            ControlInstr::Nop => {}
+            ControlInstr::Unreachable => return Err(Trap::Unreachable),
+            ControlInstr::Block(block) => {
+                self.enter(&block.instr, &block.tpe, false, Handler::None)
+            }
+            ControlInstr::Loop(block) => self.enter(&block.instr, &block.tpe, true, Handler::None),
+            ControlInstr::If(block) => {
+                let instrs = if pop::<i32>(&mut self.stack) != 0 {
+                    &block.if_br
+                } else {
+                    &block.else_br
+                };
+                self.enter(instrs, &block.tpe, false, Handler::None);
+            }
+            ControlInstr::Branch(label) => self.branch(*label.0 as usize),
+            ControlInstr::BranchIf(label) => {
+                if pop::<i32>(&mut self.stack) != 0 {
+                    self.branch(*label.0 as usize);
+                }
+            }
+            ControlInstr::BranchTable(labels, default) => {
+                let i = pop::<i32>(&mut self.stack) as u32 as usize;
+                let label = labels.get(i).unwrap_or(default);
+                self.branch(*label.0 as usize);
+            }
+            ControlInstr::Return => {
+                let depth = self.labels.len() - 1 - self.frame().label;
+                self.branch(depth);
+            }
+            ControlInstr::Call(idx) => self.call(self.instance().funcs[*idx.0 as usize])?,
+            ControlInstr::CallIndirect(table, tpe) => {
+                let func = self.indirect(table, tpe)?;
+                self.call(func)?;
+            }
+            ControlInstr::ReturnCall(idx) => {
+                self.return_call(self.instance().funcs[*idx.0 as usize])?
+            }
+            ControlInstr::ReturnCallIndirect(table, tpe) => {
+                let func = self.indirect(table, tpe)?;
+                self.return_call(func)?;
+            }
+            ControlInstr::CallRef(_) => {
+                let func = self.pop_func()?;
+                self.call(func)?;
+            }
+            ControlInstr::ReturnCallRef(_) => {
+                let func = self.pop_func()?;
+                self.return_call(func)?;
+            }
+            ControlInstr::BrOnNull(label) => {
+                let value = self.stack.pop().expect("validated");
+                if value.is_null() {
+                    self.branch(*label.0 as usize);
+                } else {
+                    self.stack.push(value);
+                }
+            }
+            ControlInstr::BrOnNonNull(label) => {
+                let value = self.stack.pop().expect("validated");
+                if !value.is_null() {
+                    self.stack.push(value);
+                    self.branch(*label.0 as usize);
+                }
+            }
+            ControlInstr::BrOnCast(label, _, tpe) | ControlInstr::BrOnCastFail(label, _, tpe) => {
+                let value = self.stack.last().expect("validated");
+                let cast = self.has_type(value, tpe);
+                if cast == matches!(instr, ControlInstr::BrOnCast(..)) {
+                    self.branch(*label.0 as usize);
+                }
+            }
+            ControlInstr::Try(block) => {
+                let handler = Handler::Try(&block.handler);
+                self.enter(&block.instr, &block.tpe, false, handler);
+            }
+            ControlInstr::TryTable(block) => {
+                let handler = Handler::Table(&block.catches);
+                self.enter(&block.instr, &block.tpe, false, handler);
+            }
+            ControlInstr::Throw(idx) => {
+                let tag = self.tag(idx);
+                let fields = self.stack.len() - self.tags[tag].params.types.len();
+                let fields = self.stack.split_off(fields);
+                self.exns.push(ExnInst { tag, fields });
+                return self.throw(self.exns.len() - 1);
+            }
+            ControlInstr::Rethrow(label) => {
+                let idx = self.labels.len() - 1 - *label.0 as usize;
+                match self.labels[idx].handler {
+                    Handler::Caught(exn) => return self.throw(exn),
+                    _ => unreachable!("rethrow is validated to target a catch clause"),
+                }
+            }
+            ControlInstr::ThrowRef => match self.stack.pop() {
+                Some(Value::ExnRef(Some(exn))) => return self.throw(exn),
+                Some(Value::ExnRef(None)) => return Err(Trap::NullExnRef),
+                _ => unreachable!("throw_ref is validated to take an exnref"),
+            },
+        }
+        Ok(())
+    }
+
+    /// Pops a function reference and returns the function it refers to.
+    fn pop_func(&mut self) -> Result<usize, Trap> {
+        match self.stack.pop() {
+            Some(Value::FuncRef(Some(func))) => Ok(func),
+            Some(Value::FuncRef(None)) => Err(Trap::NullFuncRef),
+            _ => unreachable!("call_ref is validated to take a function reference"),
+        }
+    }
+
+    /// Address of a tag of the current module.
+    fn tag(&self, idx: &TagIdx) -> usize {
+        self.instance().tags[*idx.0 as usize]
+    }
+
+    /// Unwinds the labels and frames up to the innermost handler of the exception at address
+    /// `exn`, and continues in that handler. Traps if no handler catches the exception.
+    fn throw(&mut self, exn: usize) -> Result<(), Trap> {
+        let tag = self.exns[exn].tag;
+        let mut idx = self.labels.len();
+        while let Some(frame) = self.frames.last() {
+            if idx == frame.label {
+                self.labels.truncate(idx);
+                self.locals.truncate(frame.locals);
+                self.frames.pop();
+                continue;
+            }
+            idx -= 1;
+            let Label {
+                height,
+                arity,
+                handler,
+                ..
+            } = self.labels[idx];
+            match handler {
+                Handler::Try(TryHandler::Catch { catches, catch_all }) => {
+                    let clause = match catches.iter().find(|catch| self.tag(&catch.tag) == tag) {
+                        Some(catch) => Some((&catch.instr, true)),
+                        None => catch_all.as_ref().map(|instrs| (instrs, false)),
+                    };
+                    if let Some((instrs, fields)) = clause {
+                        self.stack.truncate(height);
+                        if fields {
+                            self.stack.extend_from_slice(&self.exns[exn].fields);
+                        }
+                        self.labels.truncate(idx);
+                        self.labels.push(Label {
+                            instrs,
+                            pc: 0,
+                            arity,
+                            height,
+                            is_loop: false,
+                            handler: Handler::Caught(exn),
+                        });
+                        return Ok(());
+                    }
+                }
+                // The search goes on from the label the exception is delegated to.
+                Handler::Try(TryHandler::Delegate(label)) => idx -= *label.0 as usize,
+                Handler::Table(catches) => {
+                    let target = catches.iter().find_map(|catch| match catch {
+                        Catch::Catch(x, label) if self.tag(x) == tag => Some((label, true, false)),
+                        Catch::CatchRef(x, label) if self.tag(x) == tag => {
+                            Some((label, true, true))
+                        }
+                        Catch::CatchAll(label) => Some((label, false, false)),
+                        Catch::CatchAllRef(label) => Some((label, false, true)),
+                        _ => None,
+                    });
+                    if let Some((label, fields, exnref)) = target {
+                        self.stack.truncate(height);
+                        if fields {
+                            self.stack.extend_from_slice(&self.exns[exn].fields);
+                        }
+                        if exnref {
+                            self.stack.push(Value::ExnRef(Some(exn)));
+                        }
+                        self.labels.truncate(idx);
+                        self.branch(*label.0 as usize);
+                        return Ok(());
+                    }
+                }
+                Handler::None | Handler::Caught(_) => {}
+            }
+        }
+        Err(Trap::UncaughtException)
+    }
+}
+
+/// Fields of the struct type `idx` of a valid module.
+fn struct_fields<'m>(module: &'m Module, idx: &TypeIdx) -> &'m [FieldType] {
+    match module.sub_type(*idx).map(|tpe| &tpe.comp) {
+        Some(CompType::Struct(tpe)) => &tpe.fields,
+        _ => unreachable!("struct instructions are validated to refer to struct types"),
+    }
+}
+
+/// Element type of the array type `idx` of a valid module.
+fn array_field(module: &Module, idx: &TypeIdx) -> FieldType {
+    match module.sub_type(*idx).map(|tpe| &tpe.comp) {
+        Some(CompType::Array(tpe)) => tpe.field,
+        _ => unreachable!("array instructions are validated to refer to array types"),
+    }
+}
+
+/// Wraps a value to the size of a packed field.
+fn pack(field: &FieldType, value: Value) -> Value {
+    match (field.tpe, value) {
+        (StorageType::I8, Value::I32(value)) => Value::I32(value & 0xFF),
+        (StorageType::I16, Value::I32(value)) => Value::I32(value & 0xFFFF),
+        (_, value) => value,
+    }
+}
+
+/// Extends the value of a packed field with the given sign, which is only given for the packed
+/// fields.
+fn unpack(field: &FieldType, value: Value, sign: &Option<Sign>) -> Value {
+    match (field.tpe, value, sign) {
+        (StorageType::I8, Value::I32(value), Some(Sign::Signed)) => Value::I32(value as i8 as i32),
+        (StorageType::I16, Value::I32(value), Some(Sign::Signed)) => {
+            Value::I32(value as i16 as i32)
+        }
+        (_, value, _) => value,
+    }
+}
+
+/// Size in bytes of a field of type `tpe` in a data segment.
+fn byte_size(tpe: StorageType) -> usize {
+    match tpe {
+        StorageType::I8 => 1,
+        StorageType::I16 => 2,
+        StorageType::Val(ValType::Num(NumType::I32 | NumType::F32)) => 4,
+        StorageType::Val(ValType::Num(NumType::I64 | NumType::F64)) => 8,
+        StorageType::Val(ValType::Vec(VecType::V128)) => 16,
+        StorageType::Val(ValType::Ref(_)) => {
+            unreachable!("arrays initialized from data are validated to hold numbers")
+        }
+    }
+}
+
+/// Decodes the little endian bytes of a field of type `tpe`.
+fn read_value(tpe: StorageType, bytes: &[u8]) -> Value {
+    let mut buf = [0; 16];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    let bits = u128::from_le_bytes(buf);
+    match tpe {
+        StorageType::I8 | StorageType::I16 | StorageType::Val(ValType::Num(NumType::I32)) => {
+            Value::I32(bits as i32)
+        }
+        StorageType::Val(ValType::Num(NumType::I64)) => Value::I64(bits as i64),
+        StorageType::Val(ValType::Num(NumType::F32)) => Value::F32(f32::from_bits(bits as u32)),
+        StorageType::Val(ValType::Num(NumType::F64)) => Value::F64(f64::from_bits(bits as u64)),
+        StorageType::Val(ValType::Vec(VecType::V128)) => Value::V128(bits),
+        StorageType::Val(ValType::Ref(_)) => {
+            unreachable!("arrays initialized from data are validated to hold numbers")
+        }
+    }
+}
+
+/// Wraps an integer, sign extended to 64 bits, into a value of type `tpe`.
+fn int(tpe: &IntType, value: i64) -> Value {
+    match tpe {
+        IntType::I32 => Value::I32(value as i32),
+        IntType::I64 => Value::I64(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::runtime::{Instance, Store, Trap, Value};
+    use crate::wasm::module::Module;
+
+    fn instance(text: &str) -> Instance {
+        let module = Module::from_wat(text).unwrap();
+        Instance::new(&Store::new(), &module).unwrap()
+    }
+
+    #[test]
+    fn control() {
+        let instance = instance(
+            r#"(module
+            (func (export "sum") (param $n i32) (result i32) (local $sum i32)
+                (block $out
+                    (loop $top
+                        (br_if $out (i32.eqz (local.get $n)))
+                        (local.set $sum (i32.add (local.get $sum) (local.get $n)))
+                        (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                        (br $top)))
+                (local.get $sum))
+            (func (export "switch") (param i32) (result i32)
+                (block $d (block $c (block $b (block $a
+                    (br_table $a $b $c $d (local.get 0)))
+                    (return (i32.const 10)))
+                    (return (i32.const 11)))
+                    (return (i32.const 12)))
+                (i32.const 13))
+            (func (export "params") (result i32 i32)
+                (i32.const 1)
+                (i32.const 2)
+                (block (param i32 i32) (result i32 i32)
+                    (loop (param i32 i32) (result i32 i32)
+                        (br_if 1 (i32.const 1))
+                        (unreachable))))
+            (func (export "unreachable") (unreachable)))"#,
+        );
+        assert_eq!(
+            instance.invoke("sum", &[Value::I32(100)]),
+            Ok(vec![Value::I32(5050)])
+        );
+        let switch = |i| instance.invoke("switch", &[Value::I32(i)]).unwrap();
+        assert_eq!(switch(0), vec![Value::I32(10)]);
+        assert_eq!(switch(2), vec![Value::I32(12)]);
+        assert_eq!(switch(-1), vec![Value::I32(13)]);
+        assert_eq!(
+            instance.invoke("params", &[]),
+            Ok(vec![Value::I32(1), Value::I32(2)])
+        );
+        assert_eq!(instance.invoke("unreachable", &[]), Err(Trap::Unreachable));
+    }
+
+    #[test]
+    fn calls() {
+        let instance = instance(
+            r#"(module
+            (type $unary (func (param i32) (result i32)))
+            (table funcref (elem $double $nullary))
+            (func $double (param i32) (result i32) (i32.mul (local.get 0) (i32.const 2)))
+            (func $nullary (result i32) (i32.const 0))
+            (func (export "indirect") (param i32 i32) (result i32)
+                (call_indirect (type $unary) (local.get 0) (local.get 1)))
+            (func $loop (export "loop") (call $loop)))"#,
+        );
+        let indirect = |i, x| instance.invoke("indirect", &[Value::I32(x), Value::I32(i)]);
+        assert_eq!(indirect(0, 21), Ok(vec![Value::I32(42)]));
+        assert_eq!(indirect(1, 0), Err(Trap::IndirectCallTypeMismatch));
+        assert_eq!(indirect(2, 0), Err(Trap::UndefinedElement));
+        assert_eq!(instance.invoke("loop", &[]), Err(Trap::CallStackExhausted));
+    }
+
+    #[test]
+    fn tail_calls() {
+        let instance = instance(
+            r#"(module
+            (type $count (func (param i32 i32) (result i32)))
+            (table funcref (elem $even $odd))
+            (func $even (param i32 i32) (result i32)
+                (if (result i32) (i32.eqz (local.get 0))
+                    (then (local.get 1))
+                    (else (return_call $odd (i32.sub (local.get 0) (i32.const 1))
+                        (i32.add (local.get 1) (i32.const 1))))))
+            (func $odd (param i32 i32) (result i32)
+                (return_call_indirect (type $count) (local.get 0) (local.get 1) (i32.const 0)))
+            (func (export "count") (param i32) (result i32)
+                (return_call $even (local.get 0) (i32.const 0)))
+            (func (export "mismatch") (result i32)
+                (return_call_indirect (result i32) (i32.const 0))))"#,
+        );
+        // Far deeper than the call stack could go without tail calls.
+        let n = 10 * super::MAX_CALL_DEPTH as i32;
+        assert_eq!(
+            instance.invoke("count", &[Value::I32(n)]),
+            Ok(vec![Value::I32(n)])
+        );
+        assert_eq!(
+            instance.invoke("mismatch", &[]),
+            Err(Trap::IndirectCallTypeMismatch)
+        );
+    }
+
+    #[test]
+    fn exceptions() {
+        let instance = instance(
+            r#"(module
+            (tag $e (param i32))
+            (tag $other)
+            (func $throw (param i32)
+                (if (local.get 0) (then (throw $e (local.get 0))))
+                (throw $other))
+            (func (export "try_table") (param i32) (result i32)
+                (block $caught (result i32)
+                    (try_table (catch $e $caught)
+                        (call $throw (local.get 0)))
+                    (i32.const -1)))
+            (func (export "try") (param i32) (result i32)
+                (try (result i32)
+                    (do (i32.const 7) (call $throw (local.get 0)) (drop) (i32.const -1))
+                    (catch $e (i32.add (i32.const 100)))
+                    (catch_all (i32.const 0))))
+            (func (export "rethrow") (param i32) (result i32)
+                (try $outer (result i32)
+                    (do
+                        (try (do (call $throw (local.get 0)))
+                            (catch_all (try (do (rethrow 1)) (delegate $outer))))
+                        (i32.const -1))
+                    (catch $e)))
+            (func (export "throw_ref") (param i32) (result i32)
+                (block $caught (result exnref)
+                    (try_table (catch_all_ref $caught) (call $throw (local.get 0)))
+                    (return (i32.const -1)))
+                (try_table (param exnref) (result i32) (catch $e 0) (throw_ref)))
+            (func (export "null") (throw_ref (ref.null exn))))"#,
+        );
+        let call = |name, x| instance.invoke(name, &[Value::I32(x)]);
+        assert_eq!(call("try_table", 3), Ok(vec![Value::I32(3)]));
+        assert_eq!(call("try_table", 0), Err(Trap::UncaughtException));
+        assert_eq!(call("try", 3), Ok(vec![Value::I32(103)]));
+        assert_eq!(call("try", 0), Ok(vec![Value::I32(0)]));
+        assert_eq!(call("rethrow", 5), Ok(vec![Value::I32(5)]));
+        assert_eq!(call("rethrow", 0), Err(Trap::UncaughtException));
+        assert_eq!(call("throw_ref", 9), Ok(vec![Value::I32(9)]));
+        assert_eq!(instance.invoke("null", &[]), Err(Trap::NullExnRef));
+    }
+
+    #[test]
+    fn memory() {
+        let instance = instance(
+            r#"(module
+            (memory 1 2)
+            (func (export "load8") (param i32) (result i32) (i32.load8_s offset=1 (local.get 0)))
+            (func (export "store") (param i32 i64) (i64.store (local.get 0) (local.get 1)))
+            (func (export "grow") (param i32) (result i32) (memory.grow (local.get 0)))
+            (func (export "size") (result i32) (memory.size))
+            (func (export "fill") (param i32 i32 i32)
+                (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "copy") (param i32 i32 i32)
+                (memory.copy (local.get 0) (local.get 1) (local.get 2))))"#,
+        );
+        let i32s = |values: &[i32]| values.iter().map(|v| Value::I32(*v)).collect::<Vec<_>>();
+        instance
+            .invoke("store", &[Value::I32(8), Value::I64(0x80FF)])
+            .unwrap();
+        assert_eq!(instance.invoke("load8", &i32s(&[8])), Ok(i32s(&[-128])));
+        assert_eq!(instance.invoke("load8", &i32s(&[7])), Ok(i32s(&[-1])));
+        assert_eq!(
+            instance.invoke("load8", &i32s(&[0xFFFF])),
+            Err(Trap::MemoryOutOfBounds)
+        );
+        assert_eq!(
+            instance.invoke("load8", &i32s(&[-1])),
+            Err(Trap::MemoryOutOfBounds)
+        );
+        assert_eq!(
+            instance.invoke("store", &[Value::I32(0xFFF9), Value::I64(0)]),
+            Err(Trap::MemoryOutOfBounds)
+        );
+
+        assert_eq!(instance.invoke("load8", &i32s(&[0])), Ok(i32s(&[0])));
+        instance.invoke("copy", &i32s(&[0, 8, 2])).unwrap();
+        assert_eq!(instance.invoke("load8", &i32s(&[0])), Ok(i32s(&[-128])));
+        instance.invoke("fill", &i32s(&[1, 0x17F, 1])).unwrap();
+        assert_eq!(instance.invoke("load8", &i32s(&[0])), Ok(i32s(&[127])));
+        assert_eq!(
+            instance.invoke("fill", &i32s(&[0xFFFF, 0, 2])),
+            Err(Trap::MemoryOutOfBounds)
+        );
+        assert_eq!(
+            instance.invoke("copy", &i32s(&[0x10000, 0, 0])),
+            Ok(Vec::new())
+        );
+        assert_eq!(
+            instance.invoke("copy", &i32s(&[0x10001, 0, 0])),
+            Err(Trap::MemoryOutOfBounds)
+        );
+
+        assert_eq!(instance.invoke("grow", &i32s(&[1])), Ok(i32s(&[1])));
+        assert_eq!(instance.invoke("grow", &i32s(&[1])), Ok(i32s(&[-1])));
+        assert_eq!(instance.invoke("size", &[]), Ok(i32s(&[2])));
+        assert_eq!(instance.invoke("load8", &i32s(&[0xFFFF])), Ok(i32s(&[0])));
+    }
+
+    #[test]
+    fn tables() {
+        let instance = instance(
+            r#"(module
+            (table $t 2 10 externref)
+            (table $f 1 funcref)
+            (elem $e func $get $get)
+            (func $get (export "get") (param i32) (result externref) (table.get $t (local.get 0)))
+            (func (export "set") (param i32 externref) (table.set $t (local.get 0) (local.get 1)))
+            (func (export "grow") (param i32) (result i32)
+                (table.grow $t (ref.null extern) (local.get 0)))
+            (func (export "init") (param i32 i32 i32)
+                (table.init $f $e (local.get 0) (local.get 1) (local.get 2)))
+            (func (export "drop") (elem.drop $e))
+            (func (export "is_null") (param i32) (result i32)
+                (ref.is_null (table.get $f (local.get 0)))))"#,
+        );
+        let extern_ref = Value::ExternRef(Some(7));
+        instance
+            .invoke("set", &[Value::I32(1), extern_ref])
+            .unwrap();
+        assert_eq!(
+            instance.invoke("get", &[Value::I32(1)]),
+            Ok(vec![extern_ref])
+        );
+        assert_eq!(
+            instance.invoke("get", &[Value::I32(0)]),
+            Ok(vec![Value::ExternRef(None)])
+        );
+        assert_eq!(
+            instance.invoke("get", &[Value::I32(2)]),
+            Err(Trap::TableOutOfBounds)
+        );
+        assert_eq!(
+            instance.invoke("grow", &[Value::I32(8)]),
+            Ok(vec![Value::I32(2)])
+        );
+        assert_eq!(
+            instance.invoke("grow", &[Value::I32(1)]),
+            Ok(vec![Value::I32(-1)])
+        );
+
+        let init =
+            |d, s, n| instance.invoke("init", &[Value::I32(d), Value::I32(s), Value::I32(n)]);
+        assert_eq!(
+            instance.invoke("is_null", &[Value::I32(0)]),
+            Ok(vec![Value::I32(1)])
+        );
+        assert_eq!(init(0, 1, 1), Ok(Vec::new()));
+        assert_eq!(
+            instance.invoke("is_null", &[Value::I32(0)]),
+            Ok(vec![Value::I32(0)])
+        );
+        assert_eq!(init(0, 1, 2), Err(Trap::TableOutOfBounds));
+        instance.invoke("drop", &[]).unwrap();
+        assert_eq!(init(0, 0, 0), Ok(Vec::new()));
+        assert_eq!(init(0, 0, 1), Err(Trap::TableOutOfBounds));
+    }
+}
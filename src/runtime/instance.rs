@@ -0,0 +1,375 @@
+//! Module instantiation.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::exec::Executor;
+use super::linker::Linker;
+use super::store::{ExternVal, FuncInst, GlobalInst, MemInst, ModuleInst, Store, TableInst};
+use super::values::Value;
+use super::{InstantiationError, Trap};
+use crate::wasm::data::DataMode;
+use crate::wasm::elem::ElemMode;
+use crate::wasm::export::ExportDesc;
+use crate::wasm::indices::TypeIdx;
+use crate::wasm::module::Module;
+use crate::wasm::registry::{canonical_ref, canonical_val};
+use crate::wasm::types::{GlobalType, TableType};
+
+/// Instantiated module.
+///
+/// Cloning an instance gives another handle to the same instance.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub(crate) store: Store,
+    pub(crate) addr: usize,
+}
+
+impl Instance {
+    /// Validates and instantiates a module without imports within `store`. Modules with imports
+    /// are instantiated through a [Linker].
+    ///
+    /// This allocates the functions, tables, memories and globals of the module, initializes the
+    /// tables and memories with the active segments and finally runs the start function.
+    pub fn new(store: &Store, module: &Module) -> Result<Instance, InstantiationError> {
+        Linker::new(store).instantiate(module)
+    }
+
+    /// Calls the exported function `name` with `args`.
+    pub fn invoke(&self, name: &str, args: &[Value]) -> Result<Vec<Value>, Trap> {
+        let mut store = self.store.data.borrow_mut();
+        let func = match store.instances[self.addr].exports.get(name) {
+            Some(ExternVal::Func(func)) => *func,
+            _ => return Err(Trap::UnknownExport(name.to_string())),
+        };
+        let params = &store.funcs[func].tpe().params.types;
+        if params.len() != args.len() || params.iter().zip(args).any(|(t, v)| !v.matches(*t)) {
+            return Err(Trap::ArgumentMismatch);
+        }
+        Executor::new(&mut store).invoke(func, args)
+    }
+
+    /// Current value of the exported global `name`.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        let store = self.store.data.borrow();
+        match store.instances[self.addr].exports.get(name) {
+            Some(ExternVal::Global(global)) => Some(store.globals[*global].value),
+            _ => None,
+        }
+    }
+}
+
+/// Instantiates a valid module, with the externs in `imports` matching its imports.
+pub(crate) fn instantiate(
+    store: &Store,
+    module: &Module,
+    imports: &[ExternVal],
+) -> Result<Instance, InstantiationError> {
+    let module = Rc::new(module.clone());
+    let mut data = store.data.borrow_mut();
+    let data = &mut *data;
+    let addr = data.instances.len();
+    let types = data
+        .registry
+        .add_module(module.types.iter().map(|group| &group.types[..]));
+
+    let mut instance = ModuleInst {
+        module: module.clone(),
+        types: types.clone(),
+        funcs: Vec::new(),
+        tables: Vec::new(),
+        mems: Vec::new(),
+        globals: Vec::new(),
+        tags: Vec::new(),
+        elems: Vec::new(),
+        datas: Vec::new(),
+        exports: HashMap::new(),
+    };
+    for import in imports {
+        match *import {
+            ExternVal::Func(func) => instance.funcs.push(func),
+            ExternVal::Table(table) => instance.tables.push(table),
+            ExternVal::Mem(mem) => instance.mems.push(mem),
+            ExternVal::Global(global) => instance.globals.push(global),
+            ExternVal::Tag(tag) => instance.tags.push(tag),
+        }
+    }
+    // Types are stored with canonical identifiers, so that they can be compared between modules.
+    let registry = &data.registry;
+    let func_type = |idx: &TypeIdx| {
+        let type_id = types[*idx.0 as usize];
+        let tpe = registry.get(type_id).func().expect("validated").clone();
+        (tpe, type_id)
+    };
+    for (i, func) in module.funcs.iter().enumerate() {
+        let (tpe, type_id) = func_type(&func.index);
+        instance.funcs.push(data.funcs.len());
+        data.funcs.push(FuncInst::Wasm {
+            tpe,
+            type_id,
+            instance: addr,
+            func: i,
+        });
+    }
+    for table in &module.tables {
+        let tpe = TableType {
+            lim: table.tpe.lim,
+            index: table.tpe.index,
+            tpe: canonical_ref(table.tpe.tpe, &types),
+        };
+        let init = Value::null(table.tpe.tpe.heap, &module);
+        instance.tables.push(data.tables.len());
+        data.tables.push(TableInst::new(tpe, init));
+    }
+    for mem in &module.mems {
+        instance.mems.push(data.mems.len());
+        data.mems.push(MemInst::new(mem.tpe));
+    }
+    for tag in &module.tags {
+        instance.tags.push(data.tags.len());
+        data.tags.push(func_type(&tag.tpe).0);
+    }
+    data.instances.push(instance);
+
+    // Constant expressions only refer to the functions and imported globals, which are all
+    // there already.
+    let mut exec = Executor::new(data);
+    let globals = module
+        .globals
+        .iter()
+        .map(|global| exec.eval(addr, &global.init))
+        .collect::<Result<Vec<_>, Trap>>()?;
+    let table_inits = module
+        .tables
+        .iter()
+        .map(|table| {
+            table
+                .init
+                .as_ref()
+                .map(|init| exec.eval(addr, init))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, Trap>>()?;
+    let elems = module
+        .elems
+        .iter()
+        .map(|elem| elem.init.iter().map(|init| exec.eval(addr, init)).collect())
+        .collect::<Result<Vec<Vec<_>>, Trap>>()?;
+
+    let instance = &mut data.instances[addr];
+    for (global, value) in module.globals.iter().zip(globals) {
+        instance.globals.push(data.globals.len());
+        data.globals.push(GlobalInst {
+            tpe: GlobalType {
+                mutability: global.tpe.mutability,
+                tpe: canonical_val(global.tpe.tpe, &types),
+            },
+            value,
+        });
+    }
+    let defined_tables = instance.tables.len() - module.tables.len();
+    for (table, init) in instance.tables[defined_tables..].iter().zip(table_inits) {
+        if let Some(init) = init {
+            data.tables[*table].elements.fill(init);
+        }
+    }
+    for elem in elems {
+        instance.elems.push(data.elems.len());
+        data.elems.push(elem);
+    }
+    for segment in &module.datas {
+        instance.datas.push(data.datas.len());
+        data.datas
+            .push(segment.init.iter().map(|byte| **byte).collect());
+    }
+    for export in &module.exports {
+        let extern_val = match export.desc {
+            ExportDesc::Func(idx) => ExternVal::Func(instance.funcs[*idx.0 as usize]),
+            ExportDesc::Table(idx) => ExternVal::Table(instance.tables[*idx.0 as usize]),
+            ExportDesc::Mem(idx) => ExternVal::Mem(instance.mems[*idx.0 as usize]),
+            ExportDesc::Global(idx) => ExternVal::Global(instance.globals[*idx.0 as usize]),
+            ExportDesc::Tag(idx) => ExternVal::Tag(instance.tags[*idx.0 as usize]),
+        };
+        instance
+            .exports
+            .insert(export.name.name.clone(), extern_val);
+    }
+    let instance = &data.instances[addr];
+    let (tables, elems, mems, datas) = (
+        instance.tables.clone(),
+        instance.elems.clone(),
+        instance.mems.clone(),
+        instance.datas.clone(),
+    );
+    let start = module
+        .start
+        .as_ref()
+        .map(|start| instance.funcs[*start.func.0 as usize]);
+
+    // Active segments are copied in order, as if by `table.init` and `memory.init`. Segments
+    // before an out of bounds one stay written.
+    let mut exec = Executor::new(data);
+    for (i, elem) in module.elems.iter().enumerate() {
+        match &elem.mode {
+            ElemMode::Active { table, offset } => {
+                let offset = segment_offset(exec.eval(addr, offset)?);
+                let n = elem.init.len() as u32;
+                exec.init_table(tables[*table.0 as usize], elems[i], offset, 0, n)?;
+                exec.drop_elem(elems[i]);
+            }
+            ElemMode::Declarative => exec.drop_elem(elems[i]),
+            ElemMode::Passive => {}
+        }
+    }
+    for (i, segment) in module.datas.iter().enumerate() {
+        if let DataMode::Active { memory, offset } = &segment.mode {
+            let offset = segment_offset(exec.eval(addr, offset)?);
+            let n = segment.init.len() as u32;
+            exec.init_memory(mems[*memory.0 as usize], datas[i], offset, 0, n)?;
+            exec.drop_data(datas[i]);
+        }
+    }
+    if let Some(start) = start {
+        exec.invoke(start, &[])?;
+    }
+
+    Ok(Instance {
+        store: store.clone(),
+        addr,
+    })
+}
+
+/// Offset of an active segment, which is an `i64` for 64-bit tables and memories.
+fn segment_offset(value: Value) -> u64 {
+    match value {
+        Value::I32(offset) => u64::from(offset as u32),
+        Value::I64(offset) => offset as u64,
+        _ => unreachable!("offsets are validated to be addresses"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(text: &str) -> Module {
+        Module::from_wat(text).unwrap()
+    }
+
+    #[test]
+    fn initialization() {
+        let store = Store::new();
+        let instance = Instance::new(
+            &store,
+            &module(
+                r#"(module
+                (memory 1)
+                (table 2 funcref)
+                (global $a (export "a") (mut i32) (i32.const 1))
+                (global $f funcref (ref.func $one))
+                (elem (i32.const 1) $one)
+                (data (i32.const 4) "\2a")
+                (func $one (result i32) (i32.const 1))
+                (func $start (global.set $a (i32.add (global.get $a) (i32.load (i32.const 4)))))
+                (func (export "call") (param i32) (result i32) (call_indirect (result i32) (local.get 0)))
+                (start $start))"#,
+            ),
+        )
+        .unwrap();
+        assert_eq!(instance.global("a"), Some(Value::I32(43)));
+        assert_eq!(instance.global("call"), None);
+        assert_eq!(
+            instance.invoke("call", &[Value::I32(1)]),
+            Ok(vec![Value::I32(1)])
+        );
+        assert_eq!(
+            instance.invoke("call", &[Value::I32(0)]),
+            Err(Trap::UninitializedElement(0))
+        );
+    }
+
+    #[test]
+    fn segments_out_of_bounds() {
+        let store = Store::new();
+        let error = Instance::new(
+            &store,
+            &module(r#"(module (memory 1) (data (i32.const 0) "a") (data (i32.const 65536) "b"))"#),
+        );
+        assert!(matches!(
+            error,
+            Err(InstantiationError::Trap(Trap::MemoryOutOfBounds))
+        ));
+        // The first segment stays written.
+        assert_eq!(store.data.borrow().mems[0].data[0], b'a');
+
+        let error = Instance::new(
+            &store,
+            &module(r#"(module (table 1 funcref) (elem (i32.const 1) $f) (func $f))"#),
+        );
+        assert!(matches!(
+            error,
+            Err(InstantiationError::Trap(Trap::TableOutOfBounds))
+        ));
+
+        // Empty segments at the very end are fine.
+        let instance = Instance::new(
+            &store,
+            &module(r#"(module (memory 1) (data (i32.const 65536) ""))"#),
+        );
+        assert!(instance.is_ok());
+    }
+
+    #[test]
+    fn errors() {
+        let store = Store::new();
+        assert!(matches!(
+            Instance::new(&store, &module(r#"(module (func (i32.add)))"#)),
+            Err(InstantiationError::Invalid(_))
+        ));
+        assert!(matches!(
+            Instance::new(&store, &module(r#"(module (import "env" "f" (func)))"#)),
+            Err(InstantiationError::UnresolvedImport { module, name }) if module == "env" && name == "f"
+        ));
+        assert!(matches!(
+            Instance::new(
+                &store,
+                &module(r#"(module (func $s (unreachable)) (start $s))"#)
+            ),
+            Err(InstantiationError::Trap(Trap::Unreachable))
+        ));
+
+        let instance = Instance::new(
+            &store,
+            &module(r#"(module (func (export "f") (param i64)) (memory (export "m") 0))"#),
+        )
+        .unwrap();
+        assert_eq!(
+            instance.invoke("g", &[]),
+            Err(Trap::UnknownExport("g".to_string()))
+        );
+        assert_eq!(
+            instance.invoke("m", &[]),
+            Err(Trap::UnknownExport("m".to_string()))
+        );
+        assert_eq!(
+            instance.invoke("f", &[Value::I32(0)]),
+            Err(Trap::ArgumentMismatch)
+        );
+        assert_eq!(instance.invoke("f", &[Value::I64(0)]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn shared_store() {
+        let store = Store::new();
+        let text = r#"(module
+            (global $n (export "n") (mut i64) (i64.const 0))
+            (func (export "inc") (global.set $n (i64.add (global.get $n) (i64.const 1)))))"#;
+        let a = Instance::new(&store, &module(text)).unwrap();
+        let b = Instance::new(&store, &module(text)).unwrap();
+        a.invoke("inc", &[]).unwrap();
+        a.invoke("inc", &[]).unwrap();
+        b.invoke("inc", &[]).unwrap();
+        assert_eq!(a.global("n"), Some(Value::I64(2)));
+        assert_eq!(b.global("n"), Some(Value::I64(1)));
+    }
+}
@@ -0,0 +1,481 @@
+//! Import resolution.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::instance::{instantiate, Instance};
+use super::store::{ExternVal, FuncInst, GlobalInst, MemInst, Store, TableInst};
+use super::values::Value;
+use super::{InstantiationError, Trap};
+use crate::wasm::import::ImportDesc;
+use crate::wasm::indices::TypeIdx;
+use crate::wasm::module::Module;
+use crate::wasm::registry::{canonical_ref, canonical_val, TypeRegistry};
+use crate::wasm::types::{
+    ExternType, FuncType, GlobalType, HeapType, Limits, MemType, Mutability, SubType, TableType,
+    ValType,
+};
+use crate::wasm::values::U32;
+
+/// Function implemented by the host.
+pub(crate) type HostFunc = Rc<dyn Fn(&mut Caller<'_>, &[Value]) -> Result<Vec<Value>, Trap>>;
+
+/// Context a host function is called in.
+pub struct Caller<'a> {
+    pub(crate) memory: Option<&'a mut [u8]>,
+}
+
+impl Caller<'_> {
+    /// Memory of the module instance that called the function, if it has one.
+    pub fn memory(&mut self) -> Option<&mut [u8]> {
+        self.memory.as_deref_mut()
+    }
+}
+
+/// Definitions that the imports of modules are resolved against.
+///
+/// Definitions are named by a module and a name, like imports. They are either provided by the
+/// host ([func](Linker::func), [memory](Linker::memory), [table](Linker::table) and
+/// [global](Linker::global)) or exported by other instances ([instance](Linker::instance)).
+///
+/// # Examples
+///
+/// ```
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// use wasm_parse::runtime::{Linker, Store, Value};
+/// use wasm_parse::wasm::module::Module;
+/// use wasm_parse::wasm::types::{FuncType, NumType, ResultType, ValType};
+///
+/// let module = Module::from_wat(
+///     r#"(module
+///         (import "env" "log" (func $log (param i32)))
+///         (func (export "run") (call $log (i32.const 42))))"#,
+/// )?;
+/// let logged = Rc::new(Cell::new(0));
+/// let log = logged.clone();
+/// let tpe = FuncType {
+///     params: ResultType { types: vec![ValType::Num(NumType::I32)] },
+///     result: ResultType { types: vec![] },
+/// };
+///
+/// let store = Store::new();
+/// let mut linker = Linker::new(&store);
+/// linker.func("env", "log", tpe, move |_, args| {
+///     log.set(args[0].i32().unwrap());
+///     Ok(Vec::new())
+/// });
+/// let instance = linker.instantiate(&module)?;
+/// instance.invoke("run", &[])?;
+/// assert_eq!(logged.get(), 42);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Linker {
+    store: Store,
+    externs: HashMap<(String, String), ExternVal>,
+}
+
+impl Linker {
+    /// Creates a linker without definitions. Host definitions are allocated within `store`, which
+    /// is also the store modules are instantiated in.
+    pub fn new(store: &Store) -> Linker {
+        Linker {
+            store: store.clone(),
+            externs: HashMap::new(),
+        }
+    }
+
+    fn define(&mut self, module: &str, name: &str, extern_val: ExternVal) -> &mut Linker {
+        self.externs
+            .insert((module.to_string(), name.to_string()), extern_val);
+        self
+    }
+
+    /// Defines a host function of type `tpe`.
+    ///
+    /// The function is called with arguments of the types of the parameters and must return
+    /// values of the types of the results. Traps it returns abort the call it is part of.
+    pub fn func(
+        &mut self,
+        module: &str,
+        name: &str,
+        tpe: FuncType,
+        func: impl Fn(&mut Caller<'_>, &[Value]) -> Result<Vec<Value>, Trap> + 'static,
+    ) -> &mut Linker {
+        let mut store = self.store.data.borrow_mut();
+        let addr = store.funcs.len();
+        let type_id = store.registry.add_group(&[SubType::from(tpe.clone())], &[])[0];
+        store.funcs.push(FuncInst::Host {
+            tpe,
+            type_id,
+            func: Rc::new(func),
+        });
+        drop(store);
+        self.define(module, name, ExternVal::Func(addr))
+    }
+
+    /// Defines a memory of type `tpe`, initially zeroed.
+    pub fn memory(&mut self, module: &str, name: &str, tpe: MemType) -> &mut Linker {
+        let mut store = self.store.data.borrow_mut();
+        let addr = store.mems.len();
+        store.mems.push(MemInst::new(tpe));
+        drop(store);
+        self.define(module, name, ExternVal::Mem(addr))
+    }
+
+    /// Defines a table of type `tpe`, initially filled with null references.
+    pub fn table(&mut self, module: &str, name: &str, tpe: TableType) -> &mut Linker {
+        let mut store = self.store.data.borrow_mut();
+        let addr = store.tables.len();
+        let init = Value::default_of(ValType::Ref(tpe.tpe));
+        store.tables.push(TableInst::new(tpe, init));
+        drop(store);
+        self.define(module, name, ExternVal::Table(addr))
+    }
+
+    /// Defines a global initialized to `value`. Its type is the type of `value`.
+    pub fn global(
+        &mut self,
+        module: &str,
+        name: &str,
+        mutability: Mutability,
+        value: Value,
+    ) -> &mut Linker {
+        let mut store = self.store.data.borrow_mut();
+        let addr = store.globals.len();
+        let tpe = GlobalType {
+            mutability,
+            tpe: value.tpe(),
+        };
+        store.globals.push(GlobalInst { tpe, value });
+        drop(store);
+        self.define(module, name, ExternVal::Global(addr))
+    }
+
+    /// Defines every export of `instance` under the module name `module`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instance belongs to another store.
+    pub fn instance(&mut self, module: &str, instance: &Instance) -> &mut Linker {
+        assert!(
+            Rc::ptr_eq(&self.store.data, &instance.store.data),
+            "the instance belongs to another store"
+        );
+        let store = self.store.data.borrow();
+        let exports = store.instances[instance.addr].exports.clone();
+        drop(store);
+        for (name, extern_val) in exports {
+            self.define(module, &name, extern_val);
+        }
+        self
+    }
+
+    /// Validates and instantiates `module`, resolving its imports against the definitions.
+    ///
+    /// Each import must be defined and the definition must match the type of the import: a
+    /// function of the same type, a table or memory whose limits fall within the imported ones,
+    /// or a global or tag of the same type.
+    pub fn instantiate(&self, module: &Module) -> Result<Instance, InstantiationError> {
+        module.validate()?;
+        let mut store = self.store.data.borrow_mut();
+        // Imports are compared through the canonical types, which are shared between modules.
+        let ids = store
+            .registry
+            .add_module(module.types.iter().map(|group| &group.types[..]));
+        let registry = &store.registry;
+        let func_type = |idx: &TypeIdx| {
+            registry
+                .get(ids[*idx.0 as usize])
+                .func()
+                .expect("validated")
+                .clone()
+        };
+        let mut externs = Vec::with_capacity(module.imports.len());
+        for import in &module.imports {
+            let key = (import.module.name.clone(), import.name.name.clone());
+            let extern_val = match self.externs.get(&key) {
+                Some(extern_val) => *extern_val,
+                None => {
+                    let (module, name) = key;
+                    return Err(InstantiationError::UnresolvedImport { module, name });
+                }
+            };
+            let expected = match &import.desc {
+                ImportDesc::Func(idx) => ExternType::Func(func_type(idx)),
+                ImportDesc::Table(tpe) => ExternType::Table(TableType {
+                    lim: tpe.lim,
+                    index: tpe.index,
+                    tpe: canonical_ref(tpe.tpe, &ids),
+                }),
+                ImportDesc::Mem(tpe) => ExternType::Mem(*tpe),
+                ImportDesc::Global(tpe) => ExternType::Global(GlobalType {
+                    mutability: tpe.mutability,
+                    tpe: canonical_val(tpe.tpe, &ids),
+                }),
+                ImportDesc::Tag(idx) => ExternType::Tag(func_type(idx)),
+            };
+            let actual = store.extern_type(extern_val);
+            let matches = match (&import.desc, extern_val) {
+                // Functions match their supertypes, which are only known by their identifiers.
+                (ImportDesc::Func(idx), ExternVal::Func(func)) => registry.is_heap_subtype(
+                    HeapType::Concrete(TypeIdx(U32(store.funcs[func].type_id()))),
+                    HeapType::Concrete(TypeIdx(U32(ids[*idx.0 as usize]))),
+                ),
+                _ => extern_matches(registry, &actual, &expected),
+            };
+            if !matches {
+                let (module, name) = key;
+                return Err(InstantiationError::IncompatibleImport {
+                    module,
+                    name,
+                    expected: Box::new(expected),
+                    actual: Box::new(actual),
+                });
+            }
+            externs.push(extern_val);
+        }
+        drop(store);
+        instantiate(&self.store, module, &externs)
+    }
+}
+
+/// Checks that limits `actual` fall within the limits `expected`.
+fn limits_match(actual: &Limits, expected: &Limits) -> bool {
+    *actual.min >= *expected.min
+        && match (actual.max, expected.max) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(actual), Some(expected)) => *actual <= *expected,
+        }
+}
+
+/// Checks that an extern of type `actual` can be imported as an extern of type `expected`, both
+/// with canonical types.
+fn extern_matches(registry: &TypeRegistry, actual: &ExternType, expected: &ExternType) -> bool {
+    match (actual, expected) {
+        (ExternType::Func(actual), ExternType::Func(expected)) => actual == expected,
+        (ExternType::Table(actual), ExternType::Table(expected)) => {
+            actual.tpe == expected.tpe
+                && actual.index == expected.index
+                && limits_match(&actual.lim, &expected.lim)
+        }
+        (ExternType::Mem(actual), ExternType::Mem(expected)) => {
+            actual.index == expected.index
+                && actual.shared == expected.shared
+                && limits_match(&actual.lim, &expected.lim)
+        }
+        (ExternType::Global(actual), ExternType::Global(expected)) => {
+            actual.mutability == expected.mutability
+                && match actual.mutability {
+                    Mutability::Const => registry.is_val_subtype(actual.tpe, expected.tpe),
+                    Mutability::Mut => actual.tpe == expected.tpe,
+                }
+        }
+        (ExternType::Tag(actual), ExternType::Tag(expected)) => actual == expected,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::types::{IndexType, NumType, RefType, ResultType, ValType};
+    use crate::wasm::values::U64;
+
+    const I32: ValType = ValType::Num(NumType::I32);
+
+    fn module(text: &str) -> Module {
+        Module::from_wat(text).unwrap()
+    }
+
+    fn func_type(params: &[ValType], result: &[ValType]) -> FuncType {
+        FuncType {
+            params: ResultType {
+                types: params.to_vec(),
+            },
+            result: ResultType {
+                types: result.to_vec(),
+            },
+        }
+    }
+
+    fn limits(min: u64, max: Option<u64>) -> Limits {
+        Limits {
+            min: U64(min),
+            max: max.map(U64),
+        }
+    }
+
+    #[test]
+    fn host_functions() {
+        let store = Store::new();
+        let mut linker = Linker::new(&store);
+        linker
+            .func("env", "add", func_type(&[I32, I32], &[I32]), |_, args| {
+                Ok(vec![Value::I32(
+                    args[0].i32().unwrap() + args[1].i32().unwrap(),
+                )])
+            })
+            .func("env", "fail", func_type(&[], &[]), |_, _| {
+                Err(Trap::Host("failed".to_string()))
+            })
+            .func(
+                "env",
+                "wrong",
+                func_type(&[], &[I32]),
+                |_, _| Ok(Vec::new()),
+            )
+            .func("env", "peek", func_type(&[I32], &[I32]), |caller, args| {
+                let memory = caller.memory().expect("the caller has a memory");
+                Ok(vec![Value::I32(
+                    memory[args[0].i32().unwrap() as usize].into(),
+                )])
+            });
+        let instance = linker
+            .instantiate(&module(
+                r#"(module
+                (import "env" "add" (func $add (param i32 i32) (result i32)))
+                (import "env" "fail" (func $fail))
+                (import "env" "wrong" (func $wrong (result i32)))
+                (import "env" "peek" (func $peek (param i32) (result i32)))
+                (memory 1)
+                (data (i32.const 3) "\07")
+                (func (export "add") (result i32) (call $add (i32.const 1) (i32.const 2)))
+                (func (export "fail") (call $fail))
+                (func (export "wrong") (result i32) (call $wrong))
+                (func (export "peek") (result i32) (call $peek (i32.const 3)))
+                (export "reexport" (func $add)))"#,
+            ))
+            .unwrap();
+        assert_eq!(instance.invoke("add", &[]), Ok(vec![Value::I32(3)]));
+        assert_eq!(
+            instance.invoke("reexport", &[Value::I32(2), Value::I32(2)]),
+            Ok(vec![Value::I32(4)])
+        );
+        assert_eq!(
+            instance.invoke("fail", &[]),
+            Err(Trap::Host("failed".to_string()))
+        );
+        assert!(matches!(instance.invoke("wrong", &[]), Err(Trap::Host(_))));
+        assert_eq!(instance.invoke("peek", &[]), Ok(vec![Value::I32(7)]));
+    }
+
+    #[test]
+    fn shared_entities() {
+        let store = Store::new();
+        let mut linker = Linker::new(&store);
+        linker
+            .memory(
+                "env",
+                "mem",
+                MemType {
+                    lim: limits(1, None),
+                    index: IndexType::I32,
+                    shared: false,
+                },
+            )
+            .global("env", "g", Mutability::Mut, Value::I32(5))
+            .table(
+                "env",
+                "t",
+                TableType {
+                    lim: limits(1, Some(1)),
+                    index: IndexType::I32,
+                    tpe: RefType::FUNCREF,
+                },
+            );
+        let first = linker
+            .instantiate(&module(
+                r#"(module
+                (import "env" "mem" (memory 1))
+                (import "env" "g" (global $g (mut i32)))
+                (import "env" "t" (table 1 funcref))
+                (elem (i32.const 0) $seven)
+                (func $seven (result i32) (i32.const 7))
+                (func (export "store") (i32.store (i32.const 0) (global.get $g)))
+                (func (export "inc") (global.set $g (i32.add (global.get $g) (i32.const 1)))))"#,
+            ))
+            .unwrap();
+        linker.instance("first", &first);
+        let second = linker
+            .instantiate(&module(
+                r#"(module
+                (import "env" "mem" (memory 0))
+                (import "env" "t" (table 0 funcref))
+                (import "first" "inc" (func $inc))
+                (func (export "load") (result i32) (call $inc) (i32.load (i32.const 0)))
+                (func (export "call") (result i32) (call_indirect (result i32) (i32.const 0))))"#,
+            ))
+            .unwrap();
+        first.invoke("store", &[]).unwrap();
+        assert_eq!(second.invoke("load", &[]), Ok(vec![Value::I32(5)]));
+        first.invoke("store", &[]).unwrap();
+        assert_eq!(second.invoke("load", &[]), Ok(vec![Value::I32(6)]));
+        assert_eq!(second.invoke("call", &[]), Ok(vec![Value::I32(7)]));
+    }
+
+    #[test]
+    fn link_errors() {
+        let store = Store::new();
+        let mut linker = Linker::new(&store);
+        linker
+            .memory(
+                "env",
+                "mem",
+                MemType {
+                    lim: limits(1, Some(4)),
+                    index: IndexType::I32,
+                    shared: false,
+                },
+            )
+            .global("env", "g", Mutability::Const, Value::I64(0))
+            .func("env", "f", func_type(&[I32], &[]), |_, _| Ok(Vec::new()));
+        let incompatible = |text| {
+            matches!(
+                linker.instantiate(&module(text)),
+                Err(InstantiationError::IncompatibleImport { .. })
+            )
+        };
+        assert!(matches!(
+            linker.instantiate(&module(r#"(module (import "env" "h" (func)))"#)),
+            Err(InstantiationError::UnresolvedImport { module, name }) if module == "env" && name == "h"
+        ));
+        assert!(incompatible(r#"(module (import "env" "f" (func)))"#));
+        assert!(incompatible(r#"(module (import "env" "mem" (memory 2)))"#));
+        assert!(incompatible(
+            r#"(module (import "env" "mem" (memory 1 3)))"#
+        ));
+        assert!(incompatible(
+            r#"(module (import "env" "g" (global (mut i64))))"#
+        ));
+        assert!(incompatible(r#"(module (import "env" "g" (global i32)))"#));
+        assert!(incompatible(r#"(module (import "env" "f" (memory 0)))"#));
+        assert!(incompatible(
+            r#"(module (import "env" "mem" (memory 0 5 shared)))"#
+        ));
+        assert!(incompatible(
+            r#"(module (import "env" "mem" (memory i64 0 5)))"#
+        ));
+        assert!(linker
+            .instantiate(&module(r#"(module (import "env" "mem" (memory 0 5)))"#))
+            .is_ok());
+        assert!(linker
+            .instantiate(&module(r#"(module (import "env" "mem" (memory 1)))"#))
+            .is_ok());
+        assert!(linker
+            .instantiate(&module(r#"(module (import "env" "g" (global i64)))"#))
+            .is_ok());
+    }
+
+    #[test]
+    fn limits_subtyping() {
+        assert!(limits_match(&limits(2, Some(3)), &limits(1, None)));
+        assert!(limits_match(&limits(2, Some(3)), &limits(2, Some(3))));
+        assert!(!limits_match(&limits(1, Some(3)), &limits(2, None)));
+        assert!(!limits_match(&limits(2, None), &limits(1, Some(3))));
+        assert!(!limits_match(&limits(2, Some(4)), &limits(1, Some(3))));
+    }
+}
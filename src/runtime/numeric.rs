@@ -0,0 +1,663 @@
+//! Execution of the numeric instructions.
+//!
+//! The integer and float operators are implemented once, generically over [Int] and [Float], and
+//! the conversions follow the rounding and trapping rules of the specification.
+
+use std::cmp::Ordering;
+use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Neg, Sub};
+
+use super::values::{pop, Num, Value};
+use super::Trap;
+use crate::wasm::instr::{
+    FBinop, FRelop, FUnop, FloatType, IBinop, IRelop, ITestop, IUnop, IntType, NumericInstr,
+};
+
+/// Integer that an [IntType] is represented by at runtime.
+///
+/// Values are stored signed. The operators that interpret them as unsigned convert them first.
+pub(crate) trait Int:
+    Num + Eq + Ord + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self>
+{
+    const ZERO: Self;
+    const MIN: Self;
+    const NEG_ONE: Self;
+
+    fn from_u32(value: u32) -> Self;
+    fn leading_zeros(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+    fn count_ones(self) -> u32;
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn wrapping_sub(self, rhs: Self) -> Self;
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    fn wrapping_div(self, rhs: Self) -> Self;
+    fn wrapping_rem(self, rhs: Self) -> Self;
+    fn div_u(self, rhs: Self) -> Self;
+    fn rem_u(self, rhs: Self) -> Self;
+    fn shl(self, rhs: Self) -> Self;
+    fn shr_s(self, rhs: Self) -> Self;
+    fn shr_u(self, rhs: Self) -> Self;
+    fn rotl(self, rhs: Self) -> Self;
+    fn rotr(self, rhs: Self) -> Self;
+    fn cmp_u(self, rhs: Self) -> Ordering;
+}
+
+impl Int for i32 {
+    const ZERO: i32 = 0;
+    const MIN: i32 = i32::MIN;
+    const NEG_ONE: i32 = -1;
+
+    fn from_u32(value: u32) -> i32 {
+        value as i32
+    }
+    fn leading_zeros(self) -> u32 {
+        self.leading_zeros()
+    }
+    fn trailing_zeros(self) -> u32 {
+        self.trailing_zeros()
+    }
+    fn count_ones(self) -> u32 {
+        self.count_ones()
+    }
+    fn wrapping_add(self, rhs: i32) -> i32 {
+        self.wrapping_add(rhs)
+    }
+    fn wrapping_sub(self, rhs: i32) -> i32 {
+        self.wrapping_sub(rhs)
+    }
+    fn wrapping_mul(self, rhs: i32) -> i32 {
+        self.wrapping_mul(rhs)
+    }
+    fn wrapping_div(self, rhs: i32) -> i32 {
+        self.wrapping_div(rhs)
+    }
+    fn wrapping_rem(self, rhs: i32) -> i32 {
+        self.wrapping_rem(rhs)
+    }
+    fn div_u(self, rhs: i32) -> i32 {
+        (self as u32 / rhs as u32) as i32
+    }
+    fn rem_u(self, rhs: i32) -> i32 {
+        (self as u32 % rhs as u32) as i32
+    }
+    fn shl(self, rhs: i32) -> i32 {
+        self.wrapping_shl(rhs as u32)
+    }
+    fn shr_s(self, rhs: i32) -> i32 {
+        self.wrapping_shr(rhs as u32)
+    }
+    fn shr_u(self, rhs: i32) -> i32 {
+        (self as u32).wrapping_shr(rhs as u32) as i32
+    }
+    fn rotl(self, rhs: i32) -> i32 {
+        self.rotate_left(rhs as u32)
+    }
+    fn rotr(self, rhs: i32) -> i32 {
+        self.rotate_right(rhs as u32)
+    }
+    fn cmp_u(self, rhs: i32) -> Ordering {
+        (self as u32).cmp(&(rhs as u32))
+    }
+}
+
+impl Int for i64 {
+    const ZERO: i64 = 0;
+    const MIN: i64 = i64::MIN;
+    const NEG_ONE: i64 = -1;
+
+    fn from_u32(value: u32) -> i64 {
+        i64::from(value)
+    }
+    fn leading_zeros(self) -> u32 {
+        self.leading_zeros()
+    }
+    fn trailing_zeros(self) -> u32 {
+        self.trailing_zeros()
+    }
+    fn count_ones(self) -> u32 {
+        self.count_ones()
+    }
+    fn wrapping_add(self, rhs: i64) -> i64 {
+        self.wrapping_add(rhs)
+    }
+    fn wrapping_sub(self, rhs: i64) -> i64 {
+        self.wrapping_sub(rhs)
+    }
+    fn wrapping_mul(self, rhs: i64) -> i64 {
+        self.wrapping_mul(rhs)
+    }
+    fn wrapping_div(self, rhs: i64) -> i64 {
+        self.wrapping_div(rhs)
+    }
+    fn wrapping_rem(self, rhs: i64) -> i64 {
+        self.wrapping_rem(rhs)
+    }
+    fn div_u(self, rhs: i64) -> i64 {
+        (self as u64 / rhs as u64) as i64
+    }
+    fn rem_u(self, rhs: i64) -> i64 {
+        (self as u64 % rhs as u64) as i64
+    }
+    fn shl(self, rhs: i64) -> i64 {
+        self.wrapping_shl(rhs as u32)
+    }
+    fn shr_s(self, rhs: i64) -> i64 {
+        self.wrapping_shr(rhs as u32)
+    }
+    fn shr_u(self, rhs: i64) -> i64 {
+        (self as u64).wrapping_shr(rhs as u32) as i64
+    }
+    fn rotl(self, rhs: i64) -> i64 {
+        self.rotate_left(rhs as u32)
+    }
+    fn rotr(self, rhs: i64) -> i64 {
+        self.rotate_right(rhs as u32)
+    }
+    fn cmp_u(self, rhs: i64) -> Ordering {
+        (self as u64).cmp(&(rhs as u64))
+    }
+}
+
+/// Float that a [FloatType] is represented by at runtime.
+pub(crate) trait Float:
+    Num
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    fn is_nan(self) -> bool;
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn ceil(self) -> Self;
+    fn floor(self) -> Self;
+    fn trunc(self) -> Self;
+    fn nearest(self) -> Self;
+    fn copysign(self, sign: Self) -> Self;
+    /// Bitwise or of the two floats, which is the minimum of `0.0` and `-0.0`.
+    fn or(self, rhs: Self) -> Self;
+    /// Bitwise and of the two floats, which is the maximum of `0.0` and `-0.0`.
+    fn and(self, rhs: Self) -> Self;
+}
+
+/// Sets the most significant bit of the payload of a NaN, as the rounding instructions must return
+/// an arithmetic NaN while the standard library passes signaling NaNs through unchanged.
+fn quiet_f32(a: f32) -> f32 {
+    if a.is_nan() {
+        f32::from_bits(a.to_bits() | 0x0040_0000)
+    } else {
+        a
+    }
+}
+
+/// See [quiet_f32].
+fn quiet_f64(a: f64) -> f64 {
+    if a.is_nan() {
+        f64::from_bits(a.to_bits() | 0x0008_0000_0000_0000)
+    } else {
+        a
+    }
+}
+
+impl Float for f32 {
+    fn is_nan(self) -> bool {
+        self.is_nan()
+    }
+    fn abs(self) -> f32 {
+        self.abs()
+    }
+    fn sqrt(self) -> f32 {
+        self.sqrt()
+    }
+    fn ceil(self) -> f32 {
+        quiet_f32(self.ceil())
+    }
+    fn floor(self) -> f32 {
+        quiet_f32(self.floor())
+    }
+    fn trunc(self) -> f32 {
+        quiet_f32(self.trunc())
+    }
+    fn nearest(self) -> f32 {
+        quiet_f32(self.round_ties_even())
+    }
+    fn copysign(self, sign: f32) -> f32 {
+        self.copysign(sign)
+    }
+    fn or(self, rhs: f32) -> f32 {
+        f32::from_bits(self.to_bits() | rhs.to_bits())
+    }
+    fn and(self, rhs: f32) -> f32 {
+        f32::from_bits(self.to_bits() & rhs.to_bits())
+    }
+}
+
+impl Float for f64 {
+    fn is_nan(self) -> bool {
+        self.is_nan()
+    }
+    fn abs(self) -> f64 {
+        self.abs()
+    }
+    fn sqrt(self) -> f64 {
+        self.sqrt()
+    }
+    fn ceil(self) -> f64 {
+        quiet_f64(self.ceil())
+    }
+    fn floor(self) -> f64 {
+        quiet_f64(self.floor())
+    }
+    fn trunc(self) -> f64 {
+        quiet_f64(self.trunc())
+    }
+    fn nearest(self) -> f64 {
+        quiet_f64(self.round_ties_even())
+    }
+    fn copysign(self, sign: f64) -> f64 {
+        self.copysign(sign)
+    }
+    fn or(self, rhs: f64) -> f64 {
+        f64::from_bits(self.to_bits() | rhs.to_bits())
+    }
+    fn and(self, rhs: f64) -> f64 {
+        f64::from_bits(self.to_bits() & rhs.to_bits())
+    }
+}
+
+fn iunop<const T: IntType, N: Int>(op: &IUnop<T>, a: N) -> N {
+    match op {
+        IUnop::Clz => N::from_u32(a.leading_zeros()),
+        IUnop::Ctz => N::from_u32(a.trailing_zeros()),
+        IUnop::Popcnt => N::from_u32(a.count_ones()),
+    }
+}
+
+fn ibinop<const T: IntType, N: Int>(op: &IBinop<T>, a: N, b: N) -> Result<N, Trap> {
+    let nonzero = |b: N| {
+        if b == N::ZERO {
+            Err(Trap::DivisionByZero)
+        } else {
+            Ok(b)
+        }
+    };
+    Ok(match op {
+        IBinop::Add => a.wrapping_add(b),
+        IBinop::Sub => a.wrapping_sub(b),
+        IBinop::Mul => a.wrapping_mul(b),
+        IBinop::DivS => {
+            if a == N::MIN && b == N::NEG_ONE {
+                return Err(Trap::IntegerOverflow);
+            }
+            a.wrapping_div(nonzero(b)?)
+        }
+        IBinop::DivU => a.div_u(nonzero(b)?),
+        IBinop::RemS => a.wrapping_rem(nonzero(b)?),
+        IBinop::RemU => a.rem_u(nonzero(b)?),
+        IBinop::And => a & b,
+        IBinop::Or => a | b,
+        IBinop::Xor => a ^ b,
+        IBinop::Shl => a.shl(b),
+        IBinop::ShrS => a.shr_s(b),
+        IBinop::ShrU => a.shr_u(b),
+        IBinop::Rotl => a.rotl(b),
+        IBinop::Rotr => a.rotr(b),
+    })
+}
+
+fn itestop<const T: IntType, N: Int>(op: &ITestop<T>, a: N) -> bool {
+    match op {
+        ITestop::Eqz => a == N::ZERO,
+    }
+}
+
+fn irelop<const T: IntType, N: Int>(op: &IRelop<T>, a: N, b: N) -> bool {
+    match op {
+        IRelop::Equ => a == b,
+        IRelop::Ne => a != b,
+        IRelop::LtS => a < b,
+        IRelop::LtU => a.cmp_u(b) == Ordering::Less,
+        IRelop::GtS => a > b,
+        IRelop::GtU => a.cmp_u(b) == Ordering::Greater,
+        IRelop::LeS => a <= b,
+        IRelop::LeU => a.cmp_u(b) != Ordering::Greater,
+        IRelop::GeS => a >= b,
+        IRelop::GeU => a.cmp_u(b) != Ordering::Less,
+    }
+}
+
+fn funop<const T: FloatType, N: Float>(op: &FUnop<T>, a: N) -> N {
+    match op {
+        FUnop::Abs => a.abs(),
+        FUnop::Neg => -a,
+        FUnop::Sqrt => a.sqrt(),
+        FUnop::Ceil => a.ceil(),
+        FUnop::Floor => a.floor(),
+        FUnop::Trunc => a.trunc(),
+        FUnop::Nearest => a.nearest(),
+    }
+}
+
+fn fbinop<const T: FloatType, N: Float>(op: &FBinop<T>, a: N, b: N) -> N {
+    match op {
+        FBinop::Add => a + b,
+        FBinop::Sub => a - b,
+        FBinop::Mul => a * b,
+        FBinop::Div => a / b,
+        FBinop::Min => fmin(a, b),
+        FBinop::Max => fmax(a, b),
+        FBinop::CopySign => a.copysign(b),
+    }
+}
+
+/// Minimum of two floats, where `-0.0` is less than `0.0` and a NaN operand gives a NaN.
+pub(super) fn fmin<N: Float>(a: N, b: N) -> N {
+    // Adding propagates a NaN operand, which the min of the host doesn't.
+    if a.is_nan() || b.is_nan() {
+        a + b
+    } else if a == b {
+        a.or(b)
+    } else if a < b {
+        a
+    } else {
+        b
+    }
+}
+
+/// Maximum of two floats, where `0.0` is greater than `-0.0` and a NaN operand gives a NaN.
+pub(super) fn fmax<N: Float>(a: N, b: N) -> N {
+    if a.is_nan() || b.is_nan() {
+        a + b
+    } else if a == b {
+        a.and(b)
+    } else if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+fn frelop<const T: FloatType, N: Float>(op: &FRelop<T>, a: N, b: N) -> bool {
+    match op {
+        FRelop::Equ => a == b,
+        FRelop::Ne => a != b,
+        FRelop::Lt => a < b,
+        FRelop::Gt => a > b,
+        FRelop::Le => a <= b,
+        FRelop::Ge => a >= b,
+    }
+}
+
+/// Checks that `value` truncates to an integer within the exclusive `bounds`.
+///
+/// Every f32 and the bounds of every integer type are exactly representable as f64, so one
+/// check covers all the trapping truncations.
+fn trunc(value: f64, (min, max): (f64, f64)) -> Result<f64, Trap> {
+    if value.is_nan() {
+        Err(Trap::InvalidConversion)
+    } else if value <= min || value >= max {
+        Err(Trap::IntegerOverflow)
+    } else {
+        Ok(value)
+    }
+}
+
+const I32_BOUNDS: (f64, f64) = (-2147483649.0, 2147483648.0);
+const U32_BOUNDS: (f64, f64) = (-1.0, 4294967296.0);
+/// The lower bound is the first f64 below `i64::MIN`.
+const I64_BOUNDS: (f64, f64) = (-9223372036854777856.0, 9223372036854775808.0);
+const U64_BOUNDS: (f64, f64) = (-1.0, 18446744073709551616.0);
+
+pub(super) fn unary<A: Num, R: Num>(stack: &mut Vec<Value>, f: impl FnOnce(A) -> R) {
+    let a = pop(stack);
+    stack.push(f(a).into());
+}
+
+fn try_unary<A: Num, R: Num>(
+    stack: &mut Vec<Value>,
+    f: impl FnOnce(A) -> Result<R, Trap>,
+) -> Result<(), Trap> {
+    let a = pop(stack);
+    stack.push(f(a)?.into());
+    Ok(())
+}
+
+pub(super) fn binary<A: Num, R: Num>(stack: &mut Vec<Value>, f: impl FnOnce(A, A) -> R) {
+    let b = pop(stack);
+    let a = pop(stack);
+    stack.push(f(a, b).into());
+}
+
+fn try_binary<A: Num, R: Num>(
+    stack: &mut Vec<Value>,
+    f: impl FnOnce(A, A) -> Result<R, Trap>,
+) -> Result<(), Trap> {
+    let b = pop(stack);
+    let a = pop(stack);
+    stack.push(f(a, b)?.into());
+    Ok(())
+}
+
+/// Executes a numeric instruction on top of the operand `stack`.
+pub(crate) fn execute(instr: &NumericInstr, stack: &mut Vec<Value>) -> Result<(), Trap> {
+    use NumericInstr::*;
+    match instr {
+        I32Const(value) => stack.push(Value::I32(**value as i32)),
+        I64Const(value) => stack.push(Value::I64(**value as i64)),
+        F32Const(value) => stack.push(Value::F32(**value)),
+        F64Const(value) => stack.push(Value::F64(**value)),
+        I32Unary(op) => unary(stack, |a: i32| iunop(op, a)),
+        I64Unary(op) => unary(stack, |a: i64| iunop(op, a)),
+        F32Unary(op) => unary(stack, |a: f32| funop(op, a)),
+        F64Unary(op) => unary(stack, |a: f64| funop(op, a)),
+        I32Binary(op) => try_binary(stack, |a: i32, b| ibinop(op, a, b))?,
+        I64Binary(op) => try_binary(stack, |a: i64, b| ibinop(op, a, b))?,
+        F32Binary(op) => binary(stack, |a: f32, b| fbinop(op, a, b)),
+        F64Binary(op) => binary(stack, |a: f64, b| fbinop(op, a, b)),
+        I32Test(op) => unary(stack, |a: i32| itestop(op, a) as i32),
+        I64Test(op) => unary(stack, |a: i64| itestop(op, a) as i32),
+        I32Relop(op) => binary(stack, |a: i32, b| irelop(op, a, b) as i32),
+        I64Relop(op) => binary(stack, |a: i64, b| irelop(op, a, b) as i32),
+        F32Relop(op) => binary(stack, |a: f32, b| frelop(op, a, b) as i32),
+        F64Relop(op) => binary(stack, |a: f64, b| frelop(op, a, b) as i32),
+        I32Extend8S => unary(stack, |a: i32| a as i8 as i32),
+        I64Extend8S => unary(stack, |a: i64| a as i8 as i64),
+        I32Extend16S => unary(stack, |a: i32| a as i16 as i32),
+        I64Extend16S => unary(stack, |a: i64| a as i16 as i64),
+        I64Extend32S => unary(stack, |a: i64| a as i32 as i64),
+        I32WrapI64 => unary(stack, |a: i64| a as i32),
+        I64ExtendI32S => unary(stack, |a: i32| i64::from(a)),
+        I64ExtendI32U => unary(stack, |a: i32| i64::from(a as u32)),
+        I32TruncF32S => try_unary(stack, |a: f32| Ok(trunc(a.into(), I32_BOUNDS)? as i32))?,
+        I32TruncF32U => try_unary(stack, |a: f32| {
+            Ok(trunc(a.into(), U32_BOUNDS)? as u32 as i32)
+        })?,
+        I64TruncF32S => try_unary(stack, |a: f32| Ok(trunc(a.into(), I64_BOUNDS)? as i64))?,
+        I64TruncF32U => try_unary(stack, |a: f32| {
+            Ok(trunc(a.into(), U64_BOUNDS)? as u64 as i64)
+        })?,
+        I32TruncF64S => try_unary(stack, |a: f64| Ok(trunc(a, I32_BOUNDS)? as i32))?,
+        I32TruncF64U => try_unary(stack, |a: f64| Ok(trunc(a, U32_BOUNDS)? as u32 as i32))?,
+        I64TruncF64S => try_unary(stack, |a: f64| Ok(trunc(a, I64_BOUNDS)? as i64))?,
+        I64TruncF64U => try_unary(stack, |a: f64| Ok(trunc(a, U64_BOUNDS)? as u64 as i64))?,
+        // Casts from floats to integers saturate and turn NaN into 0.
+        I32TruncSatF32S => unary(stack, |a: f32| a as i32),
+        I32TruncSatF32U => unary(stack, |a: f32| a as u32 as i32),
+        I32TruncSatF64S => unary(stack, |a: f64| a as i32),
+        I32TruncSatF64U => unary(stack, |a: f64| a as u32 as i32),
+        I64TruncSatF32S => unary(stack, |a: f32| a as i64),
+        I64TruncSatF32U => unary(stack, |a: f32| a as u64 as i64),
+        I64TruncSatF64S => unary(stack, |a: f64| a as i64),
+        I64TruncSatF64U => unary(stack, |a: f64| a as u64 as i64),
+        F32DemoteF64 => unary(stack, |a: f64| a as f32),
+        F64PromoteF32 => unary(stack, |a: f32| f64::from(a)),
+        F32ConvertI32S => unary(stack, |a: i32| a as f32),
+        F32ConvertI32U => unary(stack, |a: i32| a as u32 as f32),
+        F32ConvertI64S => unary(stack, |a: i64| a as f32),
+        F32ConvertI64U => unary(stack, |a: i64| a as u64 as f32),
+        F64ConvertI32S => unary(stack, |a: i32| f64::from(a)),
+        F64ConvertI32U => unary(stack, |a: i32| f64::from(a as u32)),
+        F64ConvertI64S => unary(stack, |a: i64| a as f64),
+        F64ConvertI64U => unary(stack, |a: i64| a as u64 as f64),
+        I32ReinterpretF32 => unary(stack, |a: f32| a.to_bits() as i32),
+        I64ReinterpretF64 => unary(stack, |a: f64| a.to_bits() as i64),
+        F32ReinterpretI32 => unary(stack, |a: i32| f32::from_bits(a as u32)),
+        F64ReinterpretI64 => unary(stack, |a: i64| f64::from_bits(a as u64)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(instr: NumericInstr, args: &[Value]) -> Result<Value, Trap> {
+        let mut stack = args.to_vec();
+        execute(&instr, &mut stack)?;
+        assert_eq!(stack.len(), 1);
+        Ok(stack[0])
+    }
+
+    #[test]
+    fn integer_traps() {
+        use NumericInstr::*;
+        let div_s = || I32Binary(IBinop::DivS);
+        assert_eq!(
+            run(div_s(), &[Value::I32(1), Value::I32(0)]),
+            Err(Trap::DivisionByZero)
+        );
+        assert_eq!(
+            run(div_s(), &[Value::I32(i32::MIN), Value::I32(-1)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(
+                I32Binary(IBinop::RemS),
+                &[Value::I32(i32::MIN), Value::I32(-1)]
+            ),
+            Ok(Value::I32(0))
+        );
+        assert_eq!(
+            run(I64Binary(IBinop::DivU), &[Value::I64(-1), Value::I64(2)]),
+            Ok(Value::I64(i64::MAX))
+        );
+        assert_eq!(
+            run(I64Binary(IBinop::RemU), &[Value::I64(1), Value::I64(0)]),
+            Err(Trap::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn integer_ops() {
+        use NumericInstr::*;
+        assert_eq!(
+            run(I32Binary(IBinop::Shl), &[Value::I32(1), Value::I32(33)]),
+            Ok(Value::I32(2))
+        );
+        assert_eq!(
+            run(I32Binary(IBinop::ShrU), &[Value::I32(-1), Value::I32(28)]),
+            Ok(Value::I32(0xF))
+        );
+        assert_eq!(
+            run(I64Binary(IBinop::Rotr), &[Value::I64(1), Value::I64(65)]),
+            Ok(Value::I64(i64::MIN))
+        );
+        assert_eq!(
+            run(I64Unary(IUnop::Clz), &[Value::I64(1)]),
+            Ok(Value::I64(63))
+        );
+        assert_eq!(
+            run(I32Relop(IRelop::LtU), &[Value::I32(1), Value::I32(-1)]),
+            Ok(Value::I32(1))
+        );
+        assert_eq!(
+            run(I32Relop(IRelop::LtS), &[Value::I32(1), Value::I32(-1)]),
+            Ok(Value::I32(0))
+        );
+        assert_eq!(
+            run(I64ExtendI32U, &[Value::I32(-1)]),
+            Ok(Value::I64(0xFFFF_FFFF))
+        );
+        assert_eq!(run(I32Extend8S, &[Value::I32(0x80)]), Ok(Value::I32(-128)));
+    }
+
+    #[test]
+    fn float_ops() {
+        use NumericInstr::*;
+        let min = |a: f32, b: f32| run(F32Binary(FBinop::Min), &[a.into(), b.into()]).unwrap();
+        let max = |a: f64, b: f64| run(F64Binary(FBinop::Max), &[a.into(), b.into()]).unwrap();
+        assert_eq!(min(0.0, -0.0), Value::F32(-0.0));
+        assert_eq!(min(-0.0, 0.0), Value::F32(-0.0));
+        assert_eq!(max(-0.0, 0.0), Value::F64(0.0));
+        assert_eq!(min(1.0, 2.0), Value::F32(1.0));
+        assert!(matches!(min(f32::NAN, 1.0), Value::F32(x) if x.is_nan()));
+        assert!(matches!(max(1.0, f64::NAN), Value::F64(x) if x.is_nan()));
+
+        let nearest = |a: f32| run(F32Unary(FUnop::Nearest), &[a.into()]).unwrap();
+        assert_eq!(nearest(2.5), Value::F32(2.0));
+        assert_eq!(nearest(-0.5), Value::F32(-0.0));
+        // Negation only flips the sign bit, even of a NaN.
+        assert_eq!(
+            run(
+                F32Unary(FUnop::Neg),
+                &[Value::F32(f32::from_bits(0x7FA0_0000))]
+            ),
+            Ok(Value::F32(f32::from_bits(0xFFA0_0000)))
+        );
+        assert_eq!(
+            run(
+                F64Binary(FBinop::CopySign),
+                &[Value::F64(1.0), Value::F64(-f64::NAN)]
+            ),
+            Ok(Value::F64(-1.0))
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        use NumericInstr::*;
+        assert_eq!(
+            run(I32TruncF32S, &[Value::F32(-2147483648.0)]),
+            Ok(Value::I32(i32::MIN))
+        );
+        assert_eq!(
+            run(I32TruncF32S, &[Value::F32(2147483648.0)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(run(I32TruncF64U, &[Value::F64(-0.9)]), Ok(Value::I32(0)));
+        assert_eq!(
+            run(I32TruncF64U, &[Value::F64(-1.0)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(I64TruncF64S, &[Value::F64(f64::NAN)]),
+            Err(Trap::InvalidConversion)
+        );
+        assert_eq!(
+            run(I64TruncF64S, &[Value::F64(-9223372036854775808.0)]),
+            Ok(Value::I64(i64::MIN))
+        );
+        assert_eq!(
+            run(I64TruncF32U, &[Value::F32(18446744073709551616.0)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(run(I32TruncSatF32U, &[Value::F32(-5.0)]), Ok(Value::I32(0)));
+        assert_eq!(
+            run(I64TruncSatF64S, &[Value::F64(f64::NAN)]),
+            Ok(Value::I64(0))
+        );
+        assert_eq!(
+            run(F32ConvertI64U, &[Value::I64(-1)]),
+            Ok(Value::F32(18446744073709551616.0))
+        );
+        assert_eq!(
+            run(F64ConvertI32U, &[Value::I32(-1)]),
+            Ok(Value::F64(4294967295.0))
+        );
+        assert_eq!(
+            run(I32ReinterpretF32, &[Value::F32(-0.0)]),
+            Ok(Value::I32(i32::MIN))
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! Runtime store.
+//!
+//! The store holds the runtime representation of every entity of every instantiated module
+//! (instances in the specification). Entities are referred to by their address, i.e. their
+//! index within the store.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::rc::Rc;
+
+use super::linker::HostFunc;
+use super::values::Value;
+use crate::wasm::module::Module;
+use crate::wasm::registry::TypeRegistry;
+use crate::wasm::types::{
+    ExternType, FuncType, GlobalType, IndexType, Limits, MemType, RefType, TableType,
+};
+use crate::wasm::values::U64;
+
+/// Size of a memory page in bytes.
+pub(crate) const PAGE_SIZE: usize = 1 << 16;
+/// Maximum number of pages a memory can have.
+pub(crate) const MAX_PAGES: u64 = 1 << 16;
+/// Maximum number of pages a 64-bit memory can have.
+pub(crate) const MAX_PAGES_64: u64 = 1 << 48;
+/// Maximum number of elements a table can grow to. This is an implementation limit, the
+/// specification allows up to `u32::MAX`, or `u64::MAX` for 64-bit tables.
+pub(crate) const MAX_TABLE_SIZE: u64 = 10_000_000;
+
+/// Owner of all the functions, tables, memories, globals and tags of the instantiated modules.
+///
+/// A store is a cheap handle: clones refer to the same store. Instances keep their store alive.
+#[derive(Clone, Default)]
+pub struct Store {
+    pub(crate) data: Rc<RefCell<StoreData>>,
+}
+
+impl Store {
+    /// Creates an empty store.
+    pub fn new() -> Store {
+        Store::default()
+    }
+}
+
+impl fmt::Debug for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = self.data.borrow();
+        f.debug_struct("Store")
+            .field("instances", &data.instances.len())
+            .field("funcs", &data.funcs.len())
+            .field("tables", &data.tables.len())
+            .field("mems", &data.mems.len())
+            .field("globals", &data.globals.len())
+            .field("tags", &data.tags.len())
+            .finish()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StoreData {
+    pub(crate) funcs: Vec<FuncInst>,
+    pub(crate) tables: Vec<TableInst>,
+    pub(crate) mems: Vec<MemInst>,
+    pub(crate) globals: Vec<GlobalInst>,
+    /// Tags, by their type. Each tag definition is a new tag, even when types are equal.
+    pub(crate) tags: Vec<FuncType>,
+    /// Thrown exceptions, which `exnref` values refer to.
+    pub(crate) exns: Vec<ExnInst>,
+    /// Element segments. Dropped segments are empty.
+    pub(crate) elems: Vec<Vec<Value>>,
+    /// Data segments. Dropped segments are empty.
+    pub(crate) datas: Vec<Vec<u8>>,
+    /// Structs and arrays. They are never collected.
+    pub(crate) objects: Vec<GcObject>,
+    /// Canonical types of the instantiated modules, which casts and indirect calls check against.
+    pub(crate) registry: TypeRegistry,
+    pub(crate) instances: Vec<ModuleInst>,
+}
+
+impl StoreData {
+    /// Type of an extern. Tables and memories have their current size as minimum.
+    pub(crate) fn extern_type(&self, extern_val: ExternVal) -> ExternType {
+        match extern_val {
+            ExternVal::Func(func) => ExternType::Func(self.funcs[func].tpe().clone()),
+            ExternVal::Table(table) => {
+                let table = &self.tables[table];
+                ExternType::Table(TableType {
+                    lim: Limits {
+                        min: U64(table.elements.len() as u64),
+                        max: table.max.map(U64),
+                    },
+                    index: table.index,
+                    tpe: table.tpe,
+                })
+            }
+            ExternVal::Mem(mem) => {
+                let mem = &self.mems[mem];
+                ExternType::Mem(MemType {
+                    lim: Limits {
+                        min: U64(mem.pages()),
+                        max: mem.max.map(U64),
+                    },
+                    index: mem.index,
+                    shared: mem.shared,
+                })
+            }
+            ExternVal::Global(global) => ExternType::Global(self.globals[global].tpe),
+            ExternVal::Tag(tag) => ExternType::Tag(self.tags[tag].clone()),
+        }
+    }
+}
+
+pub(crate) enum FuncInst {
+    /// Function `func` (within the functions defined by the module, i.e. excluding imports) of
+    /// the module instance `instance`.
+    Wasm {
+        tpe: FuncType,
+        /// Canonical identifier of the type, see [TypeRegistry].
+        type_id: u32,
+        instance: usize,
+        func: usize,
+    },
+    Host {
+        tpe: FuncType,
+        type_id: u32,
+        func: HostFunc,
+    },
+}
+
+impl FuncInst {
+    pub(crate) fn tpe(&self) -> &FuncType {
+        match self {
+            FuncInst::Wasm { tpe, .. } | FuncInst::Host { tpe, .. } => tpe,
+        }
+    }
+
+    pub(crate) fn type_id(&self) -> u32 {
+        match self {
+            FuncInst::Wasm { type_id, .. } | FuncInst::Host { type_id, .. } => *type_id,
+        }
+    }
+}
+
+impl fmt::Debug for FuncInst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FuncInst::Wasm {
+                tpe,
+                instance,
+                func,
+                ..
+            } => f
+                .debug_struct("Wasm")
+                .field("tpe", tpe)
+                .field("instance", instance)
+                .field("func", func)
+                .finish(),
+            FuncInst::Host { tpe, .. } => f.debug_struct("Host").field("tpe", tpe).finish(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TableInst {
+    pub(crate) elements: Vec<Value>,
+    pub(crate) max: Option<u64>,
+    pub(crate) index: IndexType,
+    pub(crate) tpe: RefType,
+}
+
+impl TableInst {
+    /// Creates a table of the given type filled with `init`.
+    pub(crate) fn new(tpe: TableType, init: Value) -> TableInst {
+        TableInst {
+            elements: vec![init; *tpe.lim.min as usize],
+            max: tpe.lim.max.map(|max| *max),
+            index: tpe.index,
+            tpe: tpe.tpe,
+        }
+    }
+
+    /// Grows the table by `n` elements set to `init`. Returns the previous size, or `None` if
+    /// the table can't grow that much.
+    pub(crate) fn grow(&mut self, n: u64, init: Value) -> Option<u64> {
+        let size = self.elements.len() as u64;
+        let new_size = size.checked_add(n)?;
+        if new_size > self.max.unwrap_or(MAX_TABLE_SIZE).min(MAX_TABLE_SIZE) {
+            return None;
+        }
+        self.elements.try_reserve_exact(n as usize).ok()?;
+        self.elements.resize(new_size as usize, init);
+        Some(size)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct MemInst {
+    pub(crate) data: Vec<u8>,
+    pub(crate) max: Option<u64>,
+    pub(crate) index: IndexType,
+    pub(crate) shared: bool,
+}
+
+impl MemInst {
+    pub(crate) fn new(tpe: MemType) -> MemInst {
+        MemInst {
+            data: vec![0; *tpe.lim.min as usize * PAGE_SIZE],
+            max: tpe.lim.max.map(|max| *max),
+            index: tpe.index,
+            shared: tpe.shared,
+        }
+    }
+
+    /// Size of the memory in pages.
+    pub(crate) fn pages(&self) -> u64 {
+        (self.data.len() / PAGE_SIZE) as u64
+    }
+
+    /// Grows the memory by `n` pages. Returns the previous size, or `None` if the memory can't
+    /// grow that much.
+    pub(crate) fn grow(&mut self, n: u64) -> Option<u64> {
+        let pages = self.pages();
+        let new_pages = pages.checked_add(n)?;
+        let limit = match self.index {
+            IndexType::I32 => MAX_PAGES,
+            IndexType::I64 => MAX_PAGES_64,
+        };
+        if new_pages > self.max.unwrap_or(limit).min(limit) {
+            return None;
+        }
+        let len = usize::try_from(new_pages).ok()?.checked_mul(PAGE_SIZE)?;
+        self.data.try_reserve_exact(len - self.data.len()).ok()?;
+        self.data.resize(len, 0);
+        Some(pages)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct GlobalInst {
+    pub(crate) tpe: GlobalType,
+    pub(crate) value: Value,
+}
+
+/// Exception: a tag and the values of its parameters.
+#[derive(Debug)]
+pub(crate) struct ExnInst {
+    pub(crate) tag: usize,
+    pub(crate) fields: Vec<Value>,
+}
+
+/// Struct or array: its canonical type and the values of its fields or elements. Packed fields
+/// hold an `i32` wrapped to their size.
+#[derive(Debug)]
+pub(crate) struct GcObject {
+    pub(crate) type_id: u32,
+    pub(crate) fields: Vec<Value>,
+}
+
+/// Entity exported by a module instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExternVal {
+    Func(usize),
+    Table(usize),
+    Mem(usize),
+    Global(usize),
+    Tag(usize),
+}
+
+/// Module instance: the addresses that the indices of the module refer to.
+#[derive(Debug)]
+pub(crate) struct ModuleInst {
+    pub(crate) module: Rc<Module>,
+    /// Canonical identifiers of the types of the module.
+    pub(crate) types: Vec<u32>,
+    pub(crate) funcs: Vec<usize>,
+    pub(crate) tables: Vec<usize>,
+    pub(crate) mems: Vec<usize>,
+    pub(crate) globals: Vec<usize>,
+    pub(crate) tags: Vec<usize>,
+    pub(crate) elems: Vec<usize>,
+    pub(crate) datas: Vec<usize>,
+    pub(crate) exports: HashMap<String, ExternVal>,
+}
@@ -0,0 +1,263 @@
+//! Runtime values.
+
+use crate::wasm::module::Module;
+use crate::wasm::types::{CompType, HeapType, NumType, RefType, ValType, VecType};
+
+/// Value of one of the web assembly [value types](ValType).
+///
+/// Floats compare by their bits, so NaNs with the same payload are equal.
+#[derive(Debug, Clone, Copy)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    /// 128 bit vector, with the first lane in the least significant bits.
+    V128(u128),
+    /// Reference to a function, by address within the [Store](super::Store). `None` is the null
+    /// reference.
+    FuncRef(Option<usize>),
+    /// Opaque reference provided by the host. `None` is the null reference.
+    ExternRef(Option<u32>),
+    /// Reference to a caught exception, by address within the [Store](super::Store). `None` is
+    /// the null reference.
+    ExnRef(Option<usize>),
+    /// Reference of the `any` hierarchy of the GC proposal. `None` is the null reference.
+    AnyRef(Option<AnyRef>),
+    /// Reference of the `any` hierarchy converted to an external reference with
+    /// `extern.convert_any`. Converted host references are plain [ExternRef](Value::ExternRef)s.
+    ExternAny(AnyRef),
+}
+
+/// Non-null reference of the `any` hierarchy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnyRef {
+    /// Unboxed 31-bit integer, in the low bits.
+    I31(u32),
+    /// Struct, by address within the [Store](super::Store).
+    Struct(usize),
+    /// Array, by address within the [Store](super::Store).
+    Array(usize),
+    /// Host reference converted with `any.convert_extern`.
+    Extern(u32),
+}
+
+impl Value {
+    /// Default value of the type, which locals start with: zero or the null reference. Types
+    /// referring to type definitions are resolved with [Value::default_in].
+    pub fn default_of(tpe: ValType) -> Value {
+        Value::default_in(tpe, &Module::default())
+    }
+
+    /// Default value of the type, whose type indices refer to the types of `module`.
+    pub fn default_in(tpe: ValType, module: &Module) -> Value {
+        match tpe {
+            ValType::Num(NumType::I32) => Value::I32(0),
+            ValType::Num(NumType::I64) => Value::I64(0),
+            ValType::Num(NumType::F32) => Value::F32(0.0),
+            ValType::Num(NumType::F64) => Value::F64(0.0),
+            ValType::Vec(VecType::V128) => Value::V128(0),
+            ValType::Ref(tpe) => Value::null(tpe.heap, module),
+        }
+    }
+
+    /// Null reference of the hierarchy of `heap`, whose type indices refer to the types of
+    /// `module`.
+    pub fn null(heap: HeapType, module: &Module) -> Value {
+        match heap {
+            HeapType::Func | HeapType::NoFunc => Value::FuncRef(None),
+            HeapType::Extern | HeapType::NoExtern => Value::ExternRef(None),
+            HeapType::Exn | HeapType::NoExn => Value::ExnRef(None),
+            HeapType::Concrete(idx) => match module.sub_type(idx).map(|tpe| &tpe.comp) {
+                Some(CompType::Func(_)) => Value::FuncRef(None),
+                _ => Value::AnyRef(None),
+            },
+            _ => Value::AnyRef(None),
+        }
+    }
+
+    /// Type of the value.
+    pub fn tpe(&self) -> ValType {
+        match self {
+            Value::I32(_) => ValType::Num(NumType::I32),
+            Value::I64(_) => ValType::Num(NumType::I64),
+            Value::F32(_) => ValType::Num(NumType::F32),
+            Value::F64(_) => ValType::Num(NumType::F64),
+            Value::V128(_) => ValType::Vec(VecType::V128),
+            Value::FuncRef(_) => ValType::Ref(RefType::FUNCREF),
+            Value::ExternRef(_) => ValType::Ref(RefType::EXTERNREF),
+            Value::ExnRef(_) => ValType::Ref(RefType::EXNREF),
+            Value::AnyRef(_) => ValType::Ref(RefType::null(HeapType::Any)),
+            Value::ExternAny(_) => ValType::Ref(RefType::EXTERNREF),
+        }
+    }
+
+    /// Whether the value can be passed where a value of type `tpe` is expected. References are
+    /// only checked against the hierarchy of the type: the store knows their concrete types.
+    pub(crate) fn matches(&self, tpe: ValType) -> bool {
+        let tpe = match tpe {
+            ValType::Ref(tpe) => tpe,
+            tpe => return self.tpe() == tpe,
+        };
+        if self.is_null() && !tpe.nullable {
+            return false;
+        }
+        use HeapType::*;
+        match (self, tpe.heap) {
+            (Value::FuncRef(_) | Value::AnyRef(_), Concrete(_)) => true,
+            (Value::FuncRef(_), heap) => matches!(heap, Func | NoFunc),
+            (Value::ExternRef(_) | Value::ExternAny(_), heap) => matches!(heap, Extern | NoExtern),
+            (Value::ExnRef(_), heap) => matches!(heap, Exn | NoExn),
+            (Value::AnyRef(_), heap) => matches!(heap, Any | Eq | I31 | Struct | Array | None),
+            _ => false,
+        }
+    }
+
+    /// The value if it is an `i32`.
+    pub fn i32(&self) -> Option<i32> {
+        i32::from_value(*self)
+    }
+
+    /// The value if it is an `i64`.
+    pub fn i64(&self) -> Option<i64> {
+        i64::from_value(*self)
+    }
+
+    /// The value if it is an `f32`.
+    pub fn f32(&self) -> Option<f32> {
+        f32::from_value(*self)
+    }
+
+    /// The value if it is an `f64`.
+    pub fn f64(&self) -> Option<f64> {
+        f64::from_value(*self)
+    }
+
+    /// The value if it is a `v128`.
+    pub fn v128(&self) -> Option<u128> {
+        u128::from_value(*self)
+    }
+
+    /// Whether the value is a null reference.
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            Value::FuncRef(None)
+                | Value::ExternRef(None)
+                | Value::ExnRef(None)
+                | Value::AnyRef(None)
+        )
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::I32(a), Value::I32(b)) => a == b,
+            (Value::I64(a), Value::I64(b)) => a == b,
+            (Value::F32(a), Value::F32(b)) => a.to_bits() == b.to_bits(),
+            (Value::F64(a), Value::F64(b)) => a.to_bits() == b.to_bits(),
+            (Value::V128(a), Value::V128(b)) => a == b,
+            (Value::FuncRef(a), Value::FuncRef(b)) => a == b,
+            (Value::ExternRef(a), Value::ExternRef(b)) => a == b,
+            (Value::ExnRef(a), Value::ExnRef(b)) => a == b,
+            (Value::AnyRef(a), Value::AnyRef(b)) => a == b,
+            (Value::ExternAny(a), Value::ExternAny(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Value {
+        Value::I32(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Value {
+        Value::I64(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Value {
+        Value::F32(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value::F64(value)
+    }
+}
+
+impl From<u128> for Value {
+    fn from(value: u128) -> Value {
+        Value::V128(value)
+    }
+}
+
+/// Number that a [Value] can hold.
+pub(crate) trait Num: Copy + Into<Value> {
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+impl Num for i32 {
+    fn from_value(value: Value) -> Option<i32> {
+        match value {
+            Value::I32(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Num for i64 {
+    fn from_value(value: Value) -> Option<i64> {
+        match value {
+            Value::I64(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Num for f32 {
+    fn from_value(value: Value) -> Option<f32> {
+        match value {
+            Value::F32(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Num for f64 {
+    fn from_value(value: Value) -> Option<f64> {
+        match value {
+            Value::F64(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Num for u128 {
+    fn from_value(value: Value) -> Option<u128> {
+        match value {
+            Value::V128(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Pops a number off the operand stack.
+///
+/// # Panics
+///
+/// Panics if the top of the stack isn't an `N`, which validation rules out.
+pub(crate) fn pop<N: Num>(stack: &mut Vec<Value>) -> N {
+    stack
+        .pop()
+        .and_then(N::from_value)
+        .expect("validated code pops operands of the right type")
+}
@@ -0,0 +1,607 @@
+//! Execution of the vector instructions.
+//!
+//! A `v128` is kept as a `u128` whose least significant bits hold the first lane. Integer lanes
+//! are widened to `i128`, either sign or zero extended, so that every lane width shares the same
+//! arithmetic. Results are truncated back to the width of the lane.
+
+use super::numeric::{binary, fmax, fmin, unary, Float};
+use super::values::{pop, Value};
+use crate::wasm::indices::LaneIdx;
+use crate::wasm::instr::VectorInstr;
+
+/// Float type of the lanes of a vector.
+trait FloatLane: Float {
+    const BITS: u32;
+
+    fn from_lane(bits: u128) -> Self;
+    fn to_lane(self) -> i128;
+}
+
+impl FloatLane for f32 {
+    const BITS: u32 = 32;
+
+    fn from_lane(bits: u128) -> f32 {
+        f32::from_bits(bits as u32)
+    }
+    fn to_lane(self) -> i128 {
+        self.to_bits().into()
+    }
+}
+
+impl FloatLane for f64 {
+    const BITS: u32 = 64;
+
+    fn from_lane(bits: u128) -> f64 {
+        f64::from_bits(bits as u64)
+    }
+    fn to_lane(self) -> i128 {
+        self.to_bits().into()
+    }
+}
+
+fn mask(bits: u32) -> u128 {
+    u128::MAX >> (128 - bits)
+}
+
+/// Bits of lane `i` of a vector of `bits` wide lanes.
+pub(super) fn lane(v: u128, bits: u32, i: u32) -> u128 {
+    (v >> (i * bits)) & mask(bits)
+}
+
+fn signed(v: u128, bits: u32, i: u32) -> i128 {
+    let shift = 128 - bits;
+    ((lane(v, bits, i) << shift) as i128) >> shift
+}
+
+fn unsigned(v: u128, bits: u32, i: u32) -> i128 {
+    lane(v, bits, i) as i128
+}
+
+fn float<F: FloatLane>(v: u128, i: u32) -> F {
+    F::from_lane(lane(v, F::BITS, i))
+}
+
+/// Builds a vector of `bits` wide lanes out of the value of each lane.
+fn lanes(bits: u32, mut f: impl FnMut(u32) -> i128) -> u128 {
+    (0..128 / bits).fold(0, |v, i| v | (f(i) as u128 & mask(bits)) << (i * bits))
+}
+
+/// Replaces lane `i` of a vector of `bits` wide lanes.
+pub(super) fn replace(v: u128, bits: u32, i: u32, x: u128) -> u128 {
+    let shift = i * bits;
+    v & !(mask(bits) << shift) | (x & mask(bits)) << shift
+}
+
+/// Vector with every lane set to `x`.
+pub(super) fn splat(bits: u32, x: i128) -> u128 {
+    lanes(bits, |_| x)
+}
+
+/// Widens the low or high half of the lanes of a vector to `bits` wide lanes.
+pub(super) fn extend(v: u128, bits: u32, high: bool, sign: bool) -> u128 {
+    let first = if high { 128 / bits } else { 0 };
+    let ext = if sign { signed } else { unsigned };
+    lanes(bits, |i| ext(v, bits / 2, first + i))
+}
+
+/// All ones if `b` holds, which is how comparisons give their result in each lane.
+fn bool_lane(b: bool) -> i128 {
+    -(b as i128)
+}
+
+fn map_s(a: u128, bits: u32, f: impl Fn(i128) -> i128) -> u128 {
+    lanes(bits, |i| f(signed(a, bits, i)))
+}
+
+fn map_u(a: u128, bits: u32, f: impl Fn(i128) -> i128) -> u128 {
+    lanes(bits, |i| f(unsigned(a, bits, i)))
+}
+
+fn zip_s(a: u128, b: u128, bits: u32, f: impl Fn(i128, i128) -> i128) -> u128 {
+    lanes(bits, |i| f(signed(a, bits, i), signed(b, bits, i)))
+}
+
+fn zip_u(a: u128, b: u128, bits: u32, f: impl Fn(i128, i128) -> i128) -> u128 {
+    lanes(bits, |i| f(unsigned(a, bits, i), unsigned(b, bits, i)))
+}
+
+fn fmap<F: FloatLane>(a: u128, f: impl Fn(F) -> F) -> u128 {
+    lanes(F::BITS, |i| f(float(a, i)).to_lane())
+}
+
+fn fzip<F: FloatLane>(a: u128, b: u128, f: impl Fn(F, F) -> F) -> u128 {
+    lanes(F::BITS, |i| f(float(a, i), float(b, i)).to_lane())
+}
+
+fn fcmp<F: FloatLane>(a: u128, b: u128, f: impl Fn(F, F) -> bool) -> u128 {
+    lanes(F::BITS, |i| bool_lane(f(float(a, i), float(b, i))))
+}
+
+fn sat_s(x: i128, bits: u32) -> i128 {
+    x.clamp(-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+}
+
+fn sat_u(x: i128, bits: u32) -> i128 {
+    x.clamp(0, (1 << bits) - 1)
+}
+
+/// Narrows the signed lanes of `a` and then `b` to `bits` wide lanes, saturating them.
+fn narrow(a: u128, b: u128, bits: u32, sign: bool) -> u128 {
+    let half = 64 / bits;
+    lanes(bits, |i| {
+        let x = if i < half {
+            signed(a, 2 * bits, i)
+        } else {
+            signed(b, 2 * bits, i - half)
+        };
+        if sign {
+            sat_s(x, bits)
+        } else {
+            sat_u(x, bits)
+        }
+    })
+}
+
+/// Multiplies the low or high half of the lanes of two vectors into `bits` wide lanes.
+fn extmul(a: u128, b: u128, bits: u32, high: bool, sign: bool) -> u128 {
+    zip_s(
+        extend(a, bits, high, sign),
+        extend(b, bits, high, sign),
+        bits,
+        |x, y| x * y,
+    )
+}
+
+/// Adds pairs of adjacent lanes into `bits` wide lanes.
+fn extadd(a: u128, bits: u32, sign: bool) -> u128 {
+    let ext = if sign { signed } else { unsigned };
+    lanes(bits, |i| {
+        ext(a, bits / 2, 2 * i) + ext(a, bits / 2, 2 * i + 1)
+    })
+}
+
+fn all_true(a: u128, bits: u32) -> i32 {
+    (0..128 / bits).all(|i| lane(a, bits, i) != 0) as i32
+}
+
+fn bitmask(a: u128, bits: u32) -> i32 {
+    (0..128 / bits).fold(0, |mask, i| {
+        mask | ((lane(a, bits, i) >> (bits - 1)) as i32) << i
+    })
+}
+
+/// Pops the shift count and then the vector, and pushes the shifted vector. The count is taken
+/// modulo the width of the lanes.
+fn shift(stack: &mut Vec<Value>, bits: u32, f: impl FnOnce(u128, u32) -> u128) {
+    let count = pop::<i32>(stack) as u32 % bits;
+    unary(stack, |a: u128| f(a, count));
+}
+
+fn idx(lane: &LaneIdx) -> u32 {
+    (*lane.0).into()
+}
+
+/// Executes a vector instruction other than a memory access on top of the operand `stack`.
+pub(crate) fn execute(instr: &VectorInstr, stack: &mut Vec<Value>) {
+    use VectorInstr::*;
+    match instr {
+        V128Const(value) => stack.push(Value::V128(**value)),
+        I8x16Shuffle(indices) => binary(stack, |a: u128, b| {
+            lanes(8, |i| match idx(&indices[i as usize]) {
+                j if j < 16 => unsigned(a, 8, j),
+                j => unsigned(b, 8, j - 16),
+            })
+        }),
+        I8x16Swizzle => binary(stack, |a: u128, b| {
+            lanes(8, |i| match unsigned(b, 8, i) {
+                j if j < 16 => unsigned(a, 8, j as u32),
+                _ => 0,
+            })
+        }),
+        I8x16Splat => unary(stack, |x: i32| splat(8, x.into())),
+        I16x8Splat => unary(stack, |x: i32| splat(16, x.into())),
+        I32x4Splat => unary(stack, |x: i32| splat(32, x.into())),
+        I64x2Splat => unary(stack, |x: i64| splat(64, x.into())),
+        F32x4Splat => unary(stack, |x: f32| splat(32, x.to_lane())),
+        F64x2Splat => unary(stack, |x: f64| splat(64, x.to_lane())),
+        I8x16ExtractLaneS(l) => unary(stack, |a: u128| signed(a, 8, idx(l)) as i32),
+        I8x16ExtractLaneU(l) => unary(stack, |a: u128| unsigned(a, 8, idx(l)) as i32),
+        I16x8ExtractLaneS(l) => unary(stack, |a: u128| signed(a, 16, idx(l)) as i32),
+        I16x8ExtractLaneU(l) => unary(stack, |a: u128| unsigned(a, 16, idx(l)) as i32),
+        I32x4ExtractLane(l) => unary(stack, |a: u128| lane(a, 32, idx(l)) as i32),
+        I64x2ExtractLane(l) => unary(stack, |a: u128| lane(a, 64, idx(l)) as i64),
+        F32x4ExtractLane(l) => unary(stack, |a: u128| float::<f32>(a, idx(l))),
+        F64x2ExtractLane(l) => unary(stack, |a: u128| float::<f64>(a, idx(l))),
+        I8x16ReplaceLane(l) => {
+            let x = pop::<i32>(stack) as u128;
+            unary(stack, |a: u128| replace(a, 8, idx(l), x));
+        }
+        I16x8ReplaceLane(l) => {
+            let x = pop::<i32>(stack) as u128;
+            unary(stack, |a: u128| replace(a, 16, idx(l), x));
+        }
+        I32x4ReplaceLane(l) => {
+            let x = pop::<i32>(stack) as u128;
+            unary(stack, |a: u128| replace(a, 32, idx(l), x));
+        }
+        I64x2ReplaceLane(l) => {
+            let x = pop::<i64>(stack) as u128;
+            unary(stack, |a: u128| replace(a, 64, idx(l), x));
+        }
+        F32x4ReplaceLane(l) => {
+            let x = pop::<f32>(stack).to_lane() as u128;
+            unary(stack, |a: u128| replace(a, 32, idx(l), x));
+        }
+        F64x2ReplaceLane(l) => {
+            let x = pop::<f64>(stack).to_lane() as u128;
+            unary(stack, |a: u128| replace(a, 64, idx(l), x));
+        }
+        I8x16Eq => binary(stack, |a, b| zip_s(a, b, 8, |x, y| bool_lane(x == y))),
+        I8x16Ne => binary(stack, |a, b| zip_s(a, b, 8, |x, y| bool_lane(x != y))),
+        I8x16LtS => binary(stack, |a, b| zip_s(a, b, 8, |x, y| bool_lane(x < y))),
+        I8x16LtU => binary(stack, |a, b| zip_u(a, b, 8, |x, y| bool_lane(x < y))),
+        I8x16GtS => binary(stack, |a, b| zip_s(a, b, 8, |x, y| bool_lane(x > y))),
+        I8x16GtU => binary(stack, |a, b| zip_u(a, b, 8, |x, y| bool_lane(x > y))),
+        I8x16LeS => binary(stack, |a, b| zip_s(a, b, 8, |x, y| bool_lane(x <= y))),
+        I8x16LeU => binary(stack, |a, b| zip_u(a, b, 8, |x, y| bool_lane(x <= y))),
+        I8x16GeS => binary(stack, |a, b| zip_s(a, b, 8, |x, y| bool_lane(x >= y))),
+        I8x16GeU => binary(stack, |a, b| zip_u(a, b, 8, |x, y| bool_lane(x >= y))),
+        I16x8Eq => binary(stack, |a, b| zip_s(a, b, 16, |x, y| bool_lane(x == y))),
+        I16x8Ne => binary(stack, |a, b| zip_s(a, b, 16, |x, y| bool_lane(x != y))),
+        I16x8LtS => binary(stack, |a, b| zip_s(a, b, 16, |x, y| bool_lane(x < y))),
+        I16x8LtU => binary(stack, |a, b| zip_u(a, b, 16, |x, y| bool_lane(x < y))),
+        I16x8GtS => binary(stack, |a, b| zip_s(a, b, 16, |x, y| bool_lane(x > y))),
+        I16x8GtU => binary(stack, |a, b| zip_u(a, b, 16, |x, y| bool_lane(x > y))),
+        I16x8LeS => binary(stack, |a, b| zip_s(a, b, 16, |x, y| bool_lane(x <= y))),
+        I16x8LeU => binary(stack, |a, b| zip_u(a, b, 16, |x, y| bool_lane(x <= y))),
+        I16x8GeS => binary(stack, |a, b| zip_s(a, b, 16, |x, y| bool_lane(x >= y))),
+        I16x8GeU => binary(stack, |a, b| zip_u(a, b, 16, |x, y| bool_lane(x >= y))),
+        I32x4Eq => binary(stack, |a, b| zip_s(a, b, 32, |x, y| bool_lane(x == y))),
+        I32x4Ne => binary(stack, |a, b| zip_s(a, b, 32, |x, y| bool_lane(x != y))),
+        I32x4LtS => binary(stack, |a, b| zip_s(a, b, 32, |x, y| bool_lane(x < y))),
+        I32x4LtU => binary(stack, |a, b| zip_u(a, b, 32, |x, y| bool_lane(x < y))),
+        I32x4GtS => binary(stack, |a, b| zip_s(a, b, 32, |x, y| bool_lane(x > y))),
+        I32x4GtU => binary(stack, |a, b| zip_u(a, b, 32, |x, y| bool_lane(x > y))),
+        I32x4LeS => binary(stack, |a, b| zip_s(a, b, 32, |x, y| bool_lane(x <= y))),
+        I32x4LeU => binary(stack, |a, b| zip_u(a, b, 32, |x, y| bool_lane(x <= y))),
+        I32x4GeS => binary(stack, |a, b| zip_s(a, b, 32, |x, y| bool_lane(x >= y))),
+        I32x4GeU => binary(stack, |a, b| zip_u(a, b, 32, |x, y| bool_lane(x >= y))),
+        I64x2Eq => binary(stack, |a, b| zip_s(a, b, 64, |x, y| bool_lane(x == y))),
+        I64x2Ne => binary(stack, |a, b| zip_s(a, b, 64, |x, y| bool_lane(x != y))),
+        I64x2LtS => binary(stack, |a, b| zip_s(a, b, 64, |x, y| bool_lane(x < y))),
+        I64x2GtS => binary(stack, |a, b| zip_s(a, b, 64, |x, y| bool_lane(x > y))),
+        I64x2LeS => binary(stack, |a, b| zip_s(a, b, 64, |x, y| bool_lane(x <= y))),
+        I64x2GeS => binary(stack, |a, b| zip_s(a, b, 64, |x, y| bool_lane(x >= y))),
+        F32x4Eq => binary(stack, |a, b| fcmp::<f32>(a, b, |x, y| x == y)),
+        F32x4Ne => binary(stack, |a, b| fcmp::<f32>(a, b, |x, y| x != y)),
+        F32x4Lt => binary(stack, |a, b| fcmp::<f32>(a, b, |x, y| x < y)),
+        F32x4Gt => binary(stack, |a, b| fcmp::<f32>(a, b, |x, y| x > y)),
+        F32x4Le => binary(stack, |a, b| fcmp::<f32>(a, b, |x, y| x <= y)),
+        F32x4Ge => binary(stack, |a, b| fcmp::<f32>(a, b, |x, y| x >= y)),
+        F64x2Eq => binary(stack, |a, b| fcmp::<f64>(a, b, |x, y| x == y)),
+        F64x2Ne => binary(stack, |a, b| fcmp::<f64>(a, b, |x, y| x != y)),
+        F64x2Lt => binary(stack, |a, b| fcmp::<f64>(a, b, |x, y| x < y)),
+        F64x2Gt => binary(stack, |a, b| fcmp::<f64>(a, b, |x, y| x > y)),
+        F64x2Le => binary(stack, |a, b| fcmp::<f64>(a, b, |x, y| x <= y)),
+        F64x2Ge => binary(stack, |a, b| fcmp::<f64>(a, b, |x, y| x >= y)),
+        V128Not => unary(stack, |a: u128| !a),
+        V128And => binary(stack, |a: u128, b| a & b),
+        V128Andnot => binary(stack, |a: u128, b| a & !b),
+        V128Or => binary(stack, |a: u128, b| a | b),
+        V128Xor => binary(stack, |a: u128, b| a ^ b),
+        V128Bitselect => {
+            let c = pop::<u128>(stack);
+            binary(stack, |a: u128, b| a & c | b & !c);
+        }
+        V128AnyTrue => unary(stack, |a: u128| (a != 0) as i32),
+        I8x16AllTrue => unary(stack, |a| all_true(a, 8)),
+        I16x8AllTrue => unary(stack, |a| all_true(a, 16)),
+        I32x4AllTrue => unary(stack, |a| all_true(a, 32)),
+        I64x2AllTrue => unary(stack, |a| all_true(a, 64)),
+        I8x16Bitmask => unary(stack, |a| bitmask(a, 8)),
+        I16x8Bitmask => unary(stack, |a| bitmask(a, 16)),
+        I32x4Bitmask => unary(stack, |a| bitmask(a, 32)),
+        I64x2Bitmask => unary(stack, |a| bitmask(a, 64)),
+        I8x16Abs => unary(stack, |a| map_s(a, 8, i128::abs)),
+        I16x8Abs => unary(stack, |a| map_s(a, 16, i128::abs)),
+        I32x4Abs => unary(stack, |a| map_s(a, 32, i128::abs)),
+        I64x2Abs => unary(stack, |a| map_s(a, 64, i128::abs)),
+        I8x16Neg => unary(stack, |a| map_s(a, 8, |x| -x)),
+        I16x8Neg => unary(stack, |a| map_s(a, 16, |x| -x)),
+        I32x4Neg => unary(stack, |a| map_s(a, 32, |x| -x)),
+        I64x2Neg => unary(stack, |a| map_s(a, 64, |x| -x)),
+        I8x16Popcnt => unary(stack, |a| map_u(a, 8, |x| x.count_ones().into())),
+        I8x16NarrowI16x8S => binary(stack, |a, b| narrow(a, b, 8, true)),
+        I8x16NarrowI16x8U => binary(stack, |a, b| narrow(a, b, 8, false)),
+        I16x8NarrowI32x4S => binary(stack, |a, b| narrow(a, b, 16, true)),
+        I16x8NarrowI32x4U => binary(stack, |a, b| narrow(a, b, 16, false)),
+        I16x8ExtendLowI8x16S => unary(stack, |a| extend(a, 16, false, true)),
+        I16x8ExtendHighI8x16S => unary(stack, |a| extend(a, 16, true, true)),
+        I16x8ExtendLowI8x16U => unary(stack, |a| extend(a, 16, false, false)),
+        I16x8ExtendHighI8x16U => unary(stack, |a| extend(a, 16, true, false)),
+        I32x4ExtendLowI16x8S => unary(stack, |a| extend(a, 32, false, true)),
+        I32x4ExtendHighI16x8S => unary(stack, |a| extend(a, 32, true, true)),
+        I32x4ExtendLowI16x8U => unary(stack, |a| extend(a, 32, false, false)),
+        I32x4ExtendHighI16x8U => unary(stack, |a| extend(a, 32, true, false)),
+        I64x2ExtendLowI32x4S => unary(stack, |a| extend(a, 64, false, true)),
+        I64x2ExtendHighI32x4S => unary(stack, |a| extend(a, 64, true, true)),
+        I64x2ExtendLowI32x4U => unary(stack, |a| extend(a, 64, false, false)),
+        I64x2ExtendHighI32x4U => unary(stack, |a| extend(a, 64, true, false)),
+        I8x16Shl => shift(stack, 8, |a, s| map_s(a, 8, |x| x << s)),
+        I8x16ShrS => shift(stack, 8, |a, s| map_s(a, 8, |x| x >> s)),
+        I8x16ShrU => shift(stack, 8, |a, s| map_u(a, 8, |x| x >> s)),
+        I16x8Shl => shift(stack, 16, |a, s| map_s(a, 16, |x| x << s)),
+        I16x8ShrS => shift(stack, 16, |a, s| map_s(a, 16, |x| x >> s)),
+        I16x8ShrU => shift(stack, 16, |a, s| map_u(a, 16, |x| x >> s)),
+        I32x4Shl => shift(stack, 32, |a, s| map_s(a, 32, |x| x << s)),
+        I32x4ShrS => shift(stack, 32, |a, s| map_s(a, 32, |x| x >> s)),
+        I32x4ShrU => shift(stack, 32, |a, s| map_u(a, 32, |x| x >> s)),
+        I64x2Shl => shift(stack, 64, |a, s| map_s(a, 64, |x| x << s)),
+        I64x2ShrS => shift(stack, 64, |a, s| map_s(a, 64, |x| x >> s)),
+        I64x2ShrU => shift(stack, 64, |a, s| map_u(a, 64, |x| x >> s)),
+        I8x16Add => binary(stack, |a, b| zip_s(a, b, 8, |x, y| x + y)),
+        I16x8Add => binary(stack, |a, b| zip_s(a, b, 16, |x, y| x + y)),
+        I32x4Add => binary(stack, |a, b| zip_s(a, b, 32, |x, y| x + y)),
+        I64x2Add => binary(stack, |a, b| zip_s(a, b, 64, |x, y| x + y)),
+        I8x16Sub => binary(stack, |a, b| zip_s(a, b, 8, |x, y| x - y)),
+        I16x8Sub => binary(stack, |a, b| zip_s(a, b, 16, |x, y| x - y)),
+        I32x4Sub => binary(stack, |a, b| zip_s(a, b, 32, |x, y| x - y)),
+        I64x2Sub => binary(stack, |a, b| zip_s(a, b, 64, |x, y| x - y)),
+        I16x8Mul => binary(stack, |a, b| zip_s(a, b, 16, |x, y| x * y)),
+        I32x4Mul => binary(stack, |a, b| zip_s(a, b, 32, |x, y| x * y)),
+        I64x2Mul => binary(stack, |a, b| zip_s(a, b, 64, |x, y| x * y)),
+        I8x16AddSatS => binary(stack, |a, b| zip_s(a, b, 8, |x, y| sat_s(x + y, 8))),
+        I8x16AddSatU => binary(stack, |a, b| zip_u(a, b, 8, |x, y| sat_u(x + y, 8))),
+        I8x16SubSatS => binary(stack, |a, b| zip_s(a, b, 8, |x, y| sat_s(x - y, 8))),
+        I8x16SubSatU => binary(stack, |a, b| zip_u(a, b, 8, |x, y| sat_u(x - y, 8))),
+        I16x8AddSatS => binary(stack, |a, b| zip_s(a, b, 16, |x, y| sat_s(x + y, 16))),
+        I16x8AddSatU => binary(stack, |a, b| zip_u(a, b, 16, |x, y| sat_u(x + y, 16))),
+        I16x8SubSatS => binary(stack, |a, b| zip_s(a, b, 16, |x, y| sat_s(x - y, 16))),
+        I16x8SubSatU => binary(stack, |a, b| zip_u(a, b, 16, |x, y| sat_u(x - y, 16))),
+        I8x16MinS => binary(stack, |a, b| zip_s(a, b, 8, i128::min)),
+        I8x16MinU => binary(stack, |a, b| zip_u(a, b, 8, i128::min)),
+        I8x16MaxS => binary(stack, |a, b| zip_s(a, b, 8, i128::max)),
+        I8x16MaxU => binary(stack, |a, b| zip_u(a, b, 8, i128::max)),
+        I16x8MinS => binary(stack, |a, b| zip_s(a, b, 16, i128::min)),
+        I16x8MinU => binary(stack, |a, b| zip_u(a, b, 16, i128::min)),
+        I16x8MaxS => binary(stack, |a, b| zip_s(a, b, 16, i128::max)),
+        I16x8MaxU => binary(stack, |a, b| zip_u(a, b, 16, i128::max)),
+        I32x4MinS => binary(stack, |a, b| zip_s(a, b, 32, i128::min)),
+        I32x4MinU => binary(stack, |a, b| zip_u(a, b, 32, i128::min)),
+        I32x4MaxS => binary(stack, |a, b| zip_s(a, b, 32, i128::max)),
+        I32x4MaxU => binary(stack, |a, b| zip_u(a, b, 32, i128::max)),
+        I8x16AvgrU => binary(stack, |a, b| zip_u(a, b, 8, |x, y| (x + y + 1) >> 1)),
+        I16x8AvgrU => binary(stack, |a, b| zip_u(a, b, 16, |x, y| (x + y + 1) >> 1)),
+        I16x8Q15mulrSatS => binary(stack, |a, b| {
+            zip_s(a, b, 16, |x, y| sat_s((x * y + 0x4000) >> 15, 16))
+        }),
+        I16x8ExtaddPairwiseI8x16S => unary(stack, |a| extadd(a, 16, true)),
+        I16x8ExtaddPairwiseI8x16U => unary(stack, |a| extadd(a, 16, false)),
+        I32x4ExtaddPairwiseI16x8S => unary(stack, |a| extadd(a, 32, true)),
+        I32x4ExtaddPairwiseI16x8U => unary(stack, |a| extadd(a, 32, false)),
+        I16x8ExtmulLowI8x16S => binary(stack, |a, b| extmul(a, b, 16, false, true)),
+        I16x8ExtmulHighI8x16S => binary(stack, |a, b| extmul(a, b, 16, true, true)),
+        I16x8ExtmulLowI8x16U => binary(stack, |a, b| extmul(a, b, 16, false, false)),
+        I16x8ExtmulHighI8x16U => binary(stack, |a, b| extmul(a, b, 16, true, false)),
+        I32x4ExtmulLowI16x8S => binary(stack, |a, b| extmul(a, b, 32, false, true)),
+        I32x4ExtmulHighI16x8S => binary(stack, |a, b| extmul(a, b, 32, true, true)),
+        I32x4ExtmulLowI16x8U => binary(stack, |a, b| extmul(a, b, 32, false, false)),
+        I32x4ExtmulHighI16x8U => binary(stack, |a, b| extmul(a, b, 32, true, false)),
+        I64x2ExtmulLowI32x4S => binary(stack, |a, b| extmul(a, b, 64, false, true)),
+        I64x2ExtmulHighI32x4S => binary(stack, |a, b| extmul(a, b, 64, true, true)),
+        I64x2ExtmulLowI32x4U => binary(stack, |a, b| extmul(a, b, 64, false, false)),
+        I64x2ExtmulHighI32x4U => binary(stack, |a, b| extmul(a, b, 64, true, false)),
+        I32x4DotI16x8S => binary(stack, |a, b| {
+            let product = |i| signed(a, 16, i) * signed(b, 16, i);
+            lanes(32, |i| product(2 * i) + product(2 * i + 1))
+        }),
+        F32x4Abs => unary(stack, |a| fmap::<f32>(a, Float::abs)),
+        F32x4Neg => unary(stack, |a| fmap::<f32>(a, |x| -x)),
+        F32x4Sqrt => unary(stack, |a| fmap::<f32>(a, Float::sqrt)),
+        F32x4Ceil => unary(stack, |a| fmap::<f32>(a, Float::ceil)),
+        F32x4Floor => unary(stack, |a| fmap::<f32>(a, Float::floor)),
+        F32x4Trunc => unary(stack, |a| fmap::<f32>(a, Float::trunc)),
+        F32x4Nearest => unary(stack, |a| fmap::<f32>(a, Float::nearest)),
+        F64x2Abs => unary(stack, |a| fmap::<f64>(a, Float::abs)),
+        F64x2Neg => unary(stack, |a| fmap::<f64>(a, |x| -x)),
+        F64x2Sqrt => unary(stack, |a| fmap::<f64>(a, Float::sqrt)),
+        F64x2Ceil => unary(stack, |a| fmap::<f64>(a, Float::ceil)),
+        F64x2Floor => unary(stack, |a| fmap::<f64>(a, Float::floor)),
+        F64x2Trunc => unary(stack, |a| fmap::<f64>(a, Float::trunc)),
+        F64x2Nearest => unary(stack, |a| fmap::<f64>(a, Float::nearest)),
+        F32x4Add => binary(stack, |a, b| fzip::<f32>(a, b, |x, y| x + y)),
+        F32x4Sub => binary(stack, |a, b| fzip::<f32>(a, b, |x, y| x - y)),
+        F32x4Mul => binary(stack, |a, b| fzip::<f32>(a, b, |x, y| x * y)),
+        F32x4Div => binary(stack, |a, b| fzip::<f32>(a, b, |x, y| x / y)),
+        F32x4Min => binary(stack, |a, b| fzip::<f32>(a, b, fmin)),
+        F32x4Max => binary(stack, |a, b| fzip::<f32>(a, b, fmax)),
+        F32x4Pmin => binary(stack, |a, b| {
+            fzip::<f32>(a, b, |x, y| if y < x { y } else { x })
+        }),
+        F32x4Pmax => binary(stack, |a, b| {
+            fzip::<f32>(a, b, |x, y| if x < y { y } else { x })
+        }),
+        F64x2Add => binary(stack, |a, b| fzip::<f64>(a, b, |x, y| x + y)),
+        F64x2Sub => binary(stack, |a, b| fzip::<f64>(a, b, |x, y| x - y)),
+        F64x2Mul => binary(stack, |a, b| fzip::<f64>(a, b, |x, y| x * y)),
+        F64x2Div => binary(stack, |a, b| fzip::<f64>(a, b, |x, y| x / y)),
+        F64x2Min => binary(stack, |a, b| fzip::<f64>(a, b, fmin)),
+        F64x2Max => binary(stack, |a, b| fzip::<f64>(a, b, fmax)),
+        F64x2Pmin => binary(stack, |a, b| {
+            fzip::<f64>(a, b, |x, y| if y < x { y } else { x })
+        }),
+        F64x2Pmax => binary(stack, |a, b| {
+            fzip::<f64>(a, b, |x, y| if x < y { y } else { x })
+        }),
+        // Casts from floats to integers saturate and turn NaN into 0.
+        I32x4TruncSatF32x4S => unary(stack, |a| lanes(32, |i| (float::<f32>(a, i) as i32).into())),
+        I32x4TruncSatF32x4U => unary(stack, |a| lanes(32, |i| (float::<f32>(a, i) as u32).into())),
+        I32x4TruncSatF64x2SZero => unary(stack, |a| {
+            lanes(32, |i| {
+                if i < 2 {
+                    (float::<f64>(a, i) as i32).into()
+                } else {
+                    0
+                }
+            })
+        }),
+        I32x4TruncSatF64x2UZero => unary(stack, |a| {
+            lanes(32, |i| {
+                if i < 2 {
+                    (float::<f64>(a, i) as u32).into()
+                } else {
+                    0
+                }
+            })
+        }),
+        F32x4ConvertI32x4S => unary(stack, |a| {
+            lanes(32, |i| (signed(a, 32, i) as f32).to_lane())
+        }),
+        F32x4ConvertI32x4U => unary(stack, |a| {
+            lanes(32, |i| (unsigned(a, 32, i) as f32).to_lane())
+        }),
+        F64x2ConvertLowI32x4S => unary(stack, |a| {
+            lanes(64, |i| (signed(a, 32, i) as f64).to_lane())
+        }),
+        F64x2ConvertLowI32x4U => unary(stack, |a| {
+            lanes(64, |i| (unsigned(a, 32, i) as f64).to_lane())
+        }),
+        F32x4DemoteF64x2Zero => unary(stack, |a| {
+            lanes(32, |i| {
+                if i < 2 {
+                    (float::<f64>(a, i) as f32).to_lane()
+                } else {
+                    0
+                }
+            })
+        }),
+        F64x2PromoteLowF32x4 => unary(stack, |a| {
+            lanes(64, |i| f64::from(float::<f32>(a, i)).to_lane())
+        }),
+        V128Load(_) | V128Load8x8S(_) | V128Load8x8U(_) | V128Load16x4S(_) | V128Load16x4U(_)
+        | V128Load32x2S(_) | V128Load32x2U(_) | V128Load8Splat(_) | V128Load16Splat(_)
+        | V128Load32Splat(_) | V128Load64Splat(_) | V128Load32Zero(_) | V128Load64Zero(_)
+        | V128Store(_) | V128Load8Lane(..) | V128Load16Lane(..) | V128Load32Lane(..)
+        | V128Load64Lane(..) | V128Store8Lane(..) | V128Store16Lane(..) | V128Store32Lane(..)
+        | V128Store64Lane(..) => unreachable!("Memory accesses are executed by the interpreter"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(instr: VectorInstr, args: &[Value]) -> Value {
+        let mut stack = args.to_vec();
+        execute(&instr, &mut stack);
+        assert_eq!(stack.len(), 1);
+        stack[0]
+    }
+
+    fn i8x16(lanes: [i8; 16]) -> Value {
+        Value::V128(u128::from_le_bytes(lanes.map(|x| x as u8)))
+    }
+
+    fn i32x4(lanes: [i32; 4]) -> Value {
+        Value::V128(super::lanes(32, |i| lanes[i as usize].into()))
+    }
+
+    #[test]
+    fn integer_lanes() {
+        use VectorInstr::*;
+        let a = i8x16([1, -1, 127, -128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5]);
+        let b = i8x16([1, 1, 1, -1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5]);
+        assert_eq!(
+            run(I8x16Add, &[a, b]),
+            i8x16([2, 0, -128, 127, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10])
+        );
+        assert_eq!(
+            run(I8x16AddSatS, &[a, b]),
+            i8x16([2, 0, 127, -128, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 10])
+        );
+        assert_eq!(
+            run(I8x16LtU, &[a, b]),
+            i8x16([0, 0, 0, -1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(run(I8x16Bitmask, &[a]), Value::I32(0b1010));
+        assert_eq!(run(I8x16AllTrue, &[a]), Value::I32(0));
+        assert_eq!(
+            run(I32x4Shl, &[i32x4([1, 2, 3, -1]), Value::I32(33)]),
+            i32x4([2, 4, 6, -2])
+        );
+        assert_eq!(
+            run(I32x4ShrU, &[i32x4([-1, 2, 3, 4]), Value::I32(28)]),
+            i32x4([0xF, 0, 0, 0])
+        );
+        assert_eq!(
+            run(
+                I16x8NarrowI32x4U,
+                &[i32x4([-5, 70000, 3, 4]), i32x4([0; 4])]
+            ),
+            Value::V128(0xFFFF_0000 | 3 << 32 | 4 << 48)
+        );
+        assert_eq!(
+            run(I64x2ExtmulLowI32x4U, &[i32x4([-1, 0, 0, 0]), i32x4([2; 4])]),
+            Value::V128(0x1_FFFF_FFFE)
+        );
+        assert_eq!(
+            run(
+                I32x4DotI16x8S,
+                &[Value::V128(0x0002_FFFF), Value::V128(0x0003_0004)]
+            ),
+            i32x4([2, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn lane_access() {
+        use VectorInstr::*;
+        let lane = |i| LaneIdx(crate::wasm::values::Byte(i));
+        let a = i32x4([1, -2, 3, 4]);
+        assert_eq!(run(I32x4ExtractLane(lane(1)), &[a]), Value::I32(-2));
+        assert_eq!(run(I16x8ExtractLaneU(lane(2)), &[a]), Value::I32(0xFFFE));
+        assert_eq!(run(I8x16ExtractLaneS(lane(4)), &[a]), Value::I32(-2));
+        assert_eq!(
+            run(I32x4ReplaceLane(lane(3)), &[a, Value::I32(7)]),
+            i32x4([1, -2, 3, 7])
+        );
+        let mut indices = [lane(0); 16];
+        for (i, index) in indices.iter_mut().enumerate() {
+            *index = lane(31 - i as u8);
+        }
+        let b = Value::V128(u128::from_le_bytes([
+            16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31,
+        ]));
+        assert_eq!(
+            run(I8x16Shuffle(indices), &[a, b]),
+            Value::V128(u128::from_le_bytes([
+                31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16
+            ]))
+        );
+        assert_eq!(
+            run(I8x16Swizzle, &[b, Value::V128(u128::MAX << 16 | 0x0F_00)]),
+            Value::V128(0x1F_10)
+        );
+    }
+
+    #[test]
+    fn float_lanes() {
+        use VectorInstr::*;
+        let f32x4 =
+            |lanes: [f32; 4]| Value::V128(super::lanes(32, |i| lanes[i as usize].to_lane()));
+        let a = f32x4([1.0, -0.0, f32::NAN, 2.5]);
+        let b = f32x4([2.0, 0.0, 1.0, -1.0]);
+        let min = run(F32x4Min, &[a, b]).v128().unwrap();
+        assert_eq!(float::<f32>(min, 0), 1.0);
+        assert_eq!(float::<f32>(min, 1).to_bits(), (-0.0f32).to_bits());
+        assert!(float::<f32>(min, 2).is_nan());
+        assert_eq!(float::<f32>(min, 3), -1.0);
+        assert_eq!(run(F32x4Pmin, &[a, b]), f32x4([1.0, -0.0, f32::NAN, -1.0]));
+        assert_eq!(
+            run(F32x4Nearest, &[f32x4([2.5, -0.5, 1.5, 3.0])]),
+            f32x4([2.0, -0.0, 2.0, 3.0])
+        );
+        assert_eq!(run(F32x4Lt, &[a, b]), i32x4([-1, 0, 0, 0]));
+        assert_eq!(run(I32x4TruncSatF32x4U, &[a]), i32x4([1, 0, 0, 2]));
+    }
+}
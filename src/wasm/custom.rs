@@ -0,0 +1,12 @@
+//! Web assembly custom section.
+
+/// Custom section whose name and contents borrow from the binary it was parsed from.
+///
+/// The contents are opaque to the module: apart from the `name` section, which is decoded into
+/// the [NameSection](super::names::NameSection) of the module, custom sections are left to the
+/// tools that know their format (e.g. debug information).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BorrowedCustomSection<'a> {
+    pub name: &'a str,
+    pub data: &'a [u8],
+}
@@ -15,3 +15,20 @@ pub enum DataMode {
     Passive,
     Active { memory: MemIdx, offset: Expr },
 }
+
+/// [Data] segment whose contents borrow from the binary it was parsed from.
+#[derive(Debug, Clone)]
+pub struct BorrowedData<'a> {
+    pub init: &'a [u8],
+    pub mode: DataMode,
+}
+
+impl BorrowedData<'_> {
+    /// Copies the contents of the segment.
+    pub fn into_owned(self) -> Data {
+        Data {
+            init: self.init.iter().copied().map(Byte).collect(),
+            mode: self.mode,
+        }
+    }
+}
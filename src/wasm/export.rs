@@ -1,7 +1,7 @@
 //! Web assembly export definition.
 
 use super::{
-    indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx},
+    indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx, TagIdx},
     values::Name,
 };
 
@@ -17,4 +17,5 @@ pub enum ExportDesc {
     Table(TableIdx),
     Mem(MemIdx),
     Global(GlobalIdx),
+    Tag(TagIdx),
 }
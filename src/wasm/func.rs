@@ -1,8 +1,12 @@
 //! Web assembly function definition.
 
+use std::ops::Range;
+
 use super::indices::TypeIdx;
-use super::instr::Expr;
+use super::instr::{Expr, Instr};
 use super::types::ValType;
+use crate::parse::binary::sections::code::LazyFunc;
+use crate::parse::binary::{ErrorContext, ParseError};
 
 #[derive(Debug, Clone)]
 pub struct Func {
@@ -10,3 +14,52 @@ pub struct Func {
     pub locals: Vec<ValType>,
     pub body: Expr,
 }
+
+/// [Func] whose body borrows from the binary it was parsed from and is only decoded when asked
+/// for. Only the locals are decoded upfront.
+#[derive(Debug, Clone)]
+pub struct BorrowedFunc<'a> {
+    pub index: TypeIdx,
+    /// Index of the function in the function index space, imported functions included.
+    pub(crate) func: u32,
+    pub(crate) code: LazyFunc<'a>,
+}
+
+impl<'a> BorrowedFunc<'a> {
+    pub fn locals(&self) -> &[ValType] {
+        &self.code.locals
+    }
+
+    /// Encoded instructions of the body, as a slice of the binary.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.code.code.bytes()
+    }
+
+    /// Byte range of the encoded instructions within the binary.
+    pub fn range(&self) -> Range<usize> {
+        let start = self.code.code.offset();
+        start..start + self.code.code.len()
+    }
+
+    /// Decodes the body. Errors are located within the binary and tagged with
+    /// [ErrorContext::Func] as when parsing a [Module](super::module::Module).
+    pub fn body(&self) -> Result<Expr, ParseError> {
+        self.code
+            .body()
+            .map_err(|err| err.with_context(ErrorContext::Func(self.func)))
+    }
+
+    /// Decodes the instructions of the body, without the final `end`.
+    pub fn instructions(&self) -> Result<Vec<Instr>, ParseError> {
+        Ok(self.body()?.instr)
+    }
+
+    /// Decodes the body into an owned function.
+    pub fn into_owned(self) -> Result<Func, ParseError> {
+        Ok(Func {
+            index: self.index,
+            body: self.body()?,
+            locals: self.code.locals,
+        })
+    }
+}
@@ -19,4 +19,6 @@ pub enum ImportDesc {
     Table(TableType),
     Mem(MemType),
     Global(GlobalType),
+    /// Tag whose type is the given function type.
+    Tag(TypeIdx),
 }
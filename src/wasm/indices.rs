@@ -1,8 +1,8 @@
 //! All web assembly indicies definitions.
 
-use super::values::U32;
+use super::values::{Byte, U32};
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct TypeIdx(pub U32);
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct FuncIdx(pub U32);
@@ -16,7 +16,16 @@ pub struct GlobalIdx(pub U32);
 pub struct ElemIdx(pub U32);
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct DataIdx(pub U32);
+/// Tag of the exception handling proposal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TagIdx(pub U32);
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct LocalIdx(pub U32);
+/// Field of a struct type, from the GC proposal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FieldIdx(pub U32);
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct LabelIdx(pub U32);
+/// Lane of a vector, in the immediates of the SIMD instructions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LaneIdx(pub Byte);
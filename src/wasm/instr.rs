@@ -1,15 +1,19 @@
 //! Web assembly instruction and expression definitions.
 
 use crate::wasm::indices::{
-    DataIdx, ElemIdx, FuncIdx, GlobalIdx, LabelIdx, LocalIdx, TableIdx, TypeIdx,
+    DataIdx, ElemIdx, FieldIdx, FuncIdx, GlobalIdx, LabelIdx, LaneIdx, LocalIdx, MemIdx, TableIdx,
+    TagIdx, TypeIdx,
 };
-use crate::wasm::types::{RefType, ValType};
-use crate::wasm::values::{F32, F64, I32, I64, U32};
+use crate::wasm::types::{HeapType, RefType, ValType};
+use crate::wasm::values::{F32, F64, I32, I64, U32, U64, V128};
 
 #[derive(Debug, Clone)]
 pub enum Instr {
     Numeric(NumericInstr),
+    Vector(VectorInstr),
+    Atomic(AtomicInstr),
     Reference(ReferenceInstr),
+    Aggregate(AggregateInstr),
     Dropp,
     Select(Vec<ValType>),
     Variable(VariableInstr),
@@ -59,8 +63,11 @@ pub enum TableInstr {
 
 #[derive(Debug, Clone)]
 pub struct MemArg {
-    pub offset: U32,
+    /// Offset added to the address. It only fits in 32 bits for memories with an `i32` index
+    /// type.
+    pub offset: U64,
     pub align: U32,
+    pub mem: MemIdx,
 }
 
 #[derive(Debug, Clone)]
@@ -75,11 +82,12 @@ pub enum MemoryInstr {
     IStore8(IntType, MemArg),
     IStore16(IntType, MemArg),
     I64Store32(MemArg),
-    MemorySize,
-    MemoryGrow,
-    MemoryFill,
-    MemoryCopy,
-    MemoryInit(DataIdx),
+    MemorySize(MemIdx),
+    MemoryGrow(MemIdx),
+    MemoryFill(MemIdx),
+    /// Copies from the second memory into the first one.
+    MemoryCopy(MemIdx, MemIdx),
+    MemoryInit(MemIdx, DataIdx),
     DataDrop(DataIdx),
 }
 
@@ -103,6 +111,73 @@ pub struct IfElseBlock {
     pub else_br: Vec<Instr>,
 }
 
+/// Legacy `try` block, whose handlers follow the body as `catch` and `catch_all` clauses or as a
+/// single `delegate`.
+#[derive(Debug, Clone)]
+pub struct TryBlock {
+    pub tpe: BlockType,
+    pub instr: Vec<Instr>,
+    pub handler: TryHandler,
+}
+
+impl TryBlock {
+    /// `try` block without any handler yet, whose clauses are added by [TryBlock::add_catch].
+    pub fn new(tpe: BlockType, instr: Vec<Instr>) -> TryBlock {
+        TryBlock {
+            tpe,
+            instr,
+            handler: TryHandler::Catch {
+                catches: Vec::new(),
+                catch_all: None,
+            },
+        }
+    }
+
+    /// Adds the `catch` clause of the given tag, or the `catch_all` clause if there is none. Does
+    /// nothing for a block ended by `delegate`.
+    pub fn add_catch(&mut self, tag: Option<TagIdx>, instr: Vec<Instr>) {
+        if let TryHandler::Catch { catches, catch_all } = &mut self.handler {
+            match tag {
+                Some(tag) => catches.push(CatchBranch { tag, instr }),
+                None => *catch_all = Some(instr),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TryHandler {
+    Catch {
+        catches: Vec<CatchBranch>,
+        catch_all: Option<Vec<Instr>>,
+    },
+    /// Forwards exceptions to the `try` block at the given label.
+    Delegate(LabelIdx),
+}
+
+#[derive(Debug, Clone)]
+pub struct CatchBranch {
+    pub tag: TagIdx,
+    pub instr: Vec<Instr>,
+}
+
+/// `try_table` block, whose handlers branch to labels outside of it.
+#[derive(Debug, Clone)]
+pub struct TryTableBlock {
+    pub tpe: BlockType,
+    pub catches: Vec<Catch>,
+    pub instr: Vec<Instr>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Catch {
+    Catch(TagIdx, LabelIdx),
+    /// Like `Catch`, also passing the exception as an `exnref`.
+    CatchRef(TagIdx, LabelIdx),
+    CatchAll(LabelIdx),
+    CatchAllRef(LabelIdx),
+}
+
 #[derive(Debug, Clone)]
 pub enum ControlInstr {
     Nop,
@@ -116,13 +191,64 @@ pub enum ControlInstr {
     Return,
     Call(FuncIdx),
     CallIndirect(TableIdx, TypeIdx),
+    /// Tail call: returns the results of the call from the current function.
+    ReturnCall(FuncIdx),
+    ReturnCallIndirect(TableIdx, TypeIdx),
+    /// Calls the function reference on top of the stack, whose type is given.
+    CallRef(TypeIdx),
+    ReturnCallRef(TypeIdx),
+    BrOnNull(LabelIdx),
+    BrOnNonNull(LabelIdx),
+    /// Branches if the reference matches the second type. The first one is the type of the
+    /// operand.
+    BrOnCast(LabelIdx, RefType, RefType),
+    BrOnCastFail(LabelIdx, RefType, RefType),
+    Try(TryBlock),
+    TryTable(TryTableBlock),
+    Throw(TagIdx),
+    /// Rethrows the exception caught by the `catch` clause at the given label.
+    Rethrow(LabelIdx),
+    ThrowRef,
 }
 
 #[derive(Debug, Clone)]
 pub enum ReferenceInstr {
-    RefNull(RefType),
+    RefNull(HeapType),
     RefIsNull,
     RefFunc(FuncIdx),
+    RefEq,
+    RefAsNonNull,
+    RefTest(RefType),
+    RefCast(RefType),
+    RefI31,
+    I31Get(Sign),
+    /// Converts an external reference into the `any` hierarchy.
+    AnyConvertExtern,
+    ExternConvertAny,
+}
+
+/// Struct and array instructions of the GC proposal. Reads of packed fields give their sign
+/// extension.
+#[derive(Debug, Clone)]
+pub enum AggregateInstr {
+    StructNew(TypeIdx),
+    StructNewDefault(TypeIdx),
+    StructGet(Option<Sign>, TypeIdx, FieldIdx),
+    StructSet(TypeIdx, FieldIdx),
+    ArrayNew(TypeIdx),
+    ArrayNewDefault(TypeIdx),
+    /// Array of the given number of operands.
+    ArrayNewFixed(TypeIdx, U32),
+    ArrayNewData(TypeIdx, DataIdx),
+    ArrayNewElem(TypeIdx, ElemIdx),
+    ArrayGet(Option<Sign>, TypeIdx),
+    ArraySet(TypeIdx),
+    ArrayLen,
+    ArrayFill(TypeIdx),
+    /// Copies from an array of the second type into one of the first type.
+    ArrayCopy(TypeIdx, TypeIdx),
+    ArrayInitData(TypeIdx, DataIdx),
+    ArrayInitElem(TypeIdx, ElemIdx),
 }
 
 #[derive(Debug, Clone)]
@@ -185,6 +311,321 @@ pub enum NumericInstr {
     F64ReinterpretI64,
 }
 
+/// Instructions of the fixed-width SIMD proposal, which operate on [V128] vectors. They're encoded
+/// with the `0xFD` prefix, one variant per opcode.
+#[derive(Debug, Clone)]
+pub enum VectorInstr {
+    V128Load(MemArg),
+    V128Load8x8S(MemArg),
+    V128Load8x8U(MemArg),
+    V128Load16x4S(MemArg),
+    V128Load16x4U(MemArg),
+    V128Load32x2S(MemArg),
+    V128Load32x2U(MemArg),
+    V128Load8Splat(MemArg),
+    V128Load16Splat(MemArg),
+    V128Load32Splat(MemArg),
+    V128Load64Splat(MemArg),
+    V128Store(MemArg),
+    V128Const(V128),
+    I8x16Shuffle([LaneIdx; 16]),
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS(LaneIdx),
+    I8x16ExtractLaneU(LaneIdx),
+    I8x16ReplaceLane(LaneIdx),
+    I16x8ExtractLaneS(LaneIdx),
+    I16x8ExtractLaneU(LaneIdx),
+    I16x8ReplaceLane(LaneIdx),
+    I32x4ExtractLane(LaneIdx),
+    I32x4ReplaceLane(LaneIdx),
+    I64x2ExtractLane(LaneIdx),
+    I64x2ReplaceLane(LaneIdx),
+    F32x4ExtractLane(LaneIdx),
+    F32x4ReplaceLane(LaneIdx),
+    F64x2ExtractLane(LaneIdx),
+    F64x2ReplaceLane(LaneIdx),
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128Andnot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128AnyTrue,
+    V128Load8Lane(MemArg, LaneIdx),
+    V128Load16Lane(MemArg, LaneIdx),
+    V128Load32Lane(MemArg, LaneIdx),
+    V128Load64Lane(MemArg, LaneIdx),
+    V128Store8Lane(MemArg, LaneIdx),
+    V128Store16Lane(MemArg, LaneIdx),
+    V128Store32Lane(MemArg, LaneIdx),
+    V128Store64Lane(MemArg, LaneIdx),
+    V128Load32Zero(MemArg),
+    V128Load64Zero(MemArg),
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    F64x2Ceil,
+    F64x2Floor,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    F64x2Trunc,
+    I8x16AvgrU,
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8Q15mulrSatS,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    F64x2Nearest,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+    I64x2Abs,
+    I64x2Neg,
+    I64x2AllTrue,
+    I64x2Bitmask,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
+}
+
+/// Instructions of the threads proposal, which access shared memories atomically. They're
+/// encoded with the `0xFE` prefix, one variant per opcode.
+#[derive(Debug, Clone)]
+pub enum AtomicInstr {
+    MemoryAtomicNotify(MemArg),
+    MemoryAtomicWait32(MemArg),
+    MemoryAtomicWait64(MemArg),
+    AtomicFence,
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8U(MemArg),
+    I32AtomicLoad16U(MemArg),
+    I64AtomicLoad8U(MemArg),
+    I64AtomicLoad16U(MemArg),
+    I64AtomicLoad32U(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8(MemArg),
+    I32AtomicStore16(MemArg),
+    I64AtomicStore8(MemArg),
+    I64AtomicStore16(MemArg),
+    I64AtomicStore32(MemArg),
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmw8AddU(MemArg),
+    I32AtomicRmw16AddU(MemArg),
+    I64AtomicRmw8AddU(MemArg),
+    I64AtomicRmw16AddU(MemArg),
+    I64AtomicRmw32AddU(MemArg),
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmw8SubU(MemArg),
+    I32AtomicRmw16SubU(MemArg),
+    I64AtomicRmw8SubU(MemArg),
+    I64AtomicRmw16SubU(MemArg),
+    I64AtomicRmw32SubU(MemArg),
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmw8AndU(MemArg),
+    I32AtomicRmw16AndU(MemArg),
+    I64AtomicRmw8AndU(MemArg),
+    I64AtomicRmw16AndU(MemArg),
+    I64AtomicRmw32AndU(MemArg),
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmw8OrU(MemArg),
+    I32AtomicRmw16OrU(MemArg),
+    I64AtomicRmw8OrU(MemArg),
+    I64AtomicRmw16OrU(MemArg),
+    I64AtomicRmw32OrU(MemArg),
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmw8XorU(MemArg),
+    I32AtomicRmw16XorU(MemArg),
+    I64AtomicRmw8XorU(MemArg),
+    I64AtomicRmw16XorU(MemArg),
+    I64AtomicRmw32XorU(MemArg),
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmw8XchgU(MemArg),
+    I32AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw8XchgU(MemArg),
+    I64AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw32XchgU(MemArg),
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmw8CmpxchgU(MemArg),
+    I32AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw8CmpxchgU(MemArg),
+    I64AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw32CmpxchgU(MemArg),
+}
+
 #[derive(Debug, Clone)]
 pub enum IUnop<const T: IntType> {
     Clz,
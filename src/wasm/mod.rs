@@ -3,6 +3,7 @@
 //! This module contains all the necessary definitions to define a web assembly module. The module
 //! is built through composition where larger structures contain the smaller structures.
 
+pub mod custom;
 pub mod data;
 pub mod elem;
 pub mod export;
@@ -13,7 +14,11 @@ pub mod indices;
 pub mod instr;
 pub mod mem;
 pub mod module;
+pub mod names;
+pub mod registry;
 pub mod start;
 pub mod table;
+pub mod tag;
 pub mod types;
+pub mod validate;
 pub mod values;
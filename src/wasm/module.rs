@@ -1,15 +1,28 @@
 //! Web Assembly Module definition.
 
-use super::{data, elem, export, func, global, import, mem, start, table, types};
-use crate::parse::binary::{Parse, ParsingData, WasmBinary};
+use std::fmt;
+
+use super::validate::{self, ValidationError};
+use super::{
+    custom, data, elem, export, func, global, import, mem, names, start, table, tag, types,
+};
+use crate::encode::binary::Encode;
+use crate::encode::text::{self as wat, Style};
+use crate::parse::binary::module::BinaryModule;
+use crate::parse::binary::spans::{self, Spans};
+use crate::parse::binary::{Parse, ParseError, ParsingData, WasmBinary};
+use crate::parse::text::{self, TextError};
+use crate::wasm::indices::TypeIdx;
 use crate::wasm::values::Name;
 
 #[derive(Debug, Clone, Default)]
 pub struct Module {
-    pub types: Vec<types::FuncType>,
+    /// Type definitions, by recursion group. See [Module::sub_type] for the type index space.
+    pub types: Vec<types::RecType>,
     pub funcs: Vec<func::Func>,
     pub tables: Vec<table::Table>,
     pub mems: Vec<mem::Mem>,
+    pub tags: Vec<tag::Tag>,
     pub globals: Vec<global::Global>,
     pub elems: Vec<elem::Elem>,
     pub datas: Vec<data::Data>,
@@ -17,6 +30,89 @@ pub struct Module {
     pub imports: Vec<import::Import>,
     pub exports: Vec<export::Export>,
     pub name: Option<Name>,
+    /// Debug names decoded from the `name` custom section, if present.
+    pub names: names::NameSection,
+}
+
+/// Module whose function bodies, data segments and custom sections borrow from the binary it was
+/// parsed from, so that large modules aren't held in memory twice. The other fields are the same
+/// as in [Module].
+///
+/// This is the lazy way to parse a module: parsing a [Module] decodes every function body upfront,
+/// while the bodies of a borrowed module are only decoded on demand, which makes parsing
+/// proportional to the size of the other sections.
+///
+/// ```
+/// use std::fs;
+///
+/// use wasm_parse::parse::{ParseBorrowed, ParsingData, WasmBinary};
+/// use wasm_parse::wasm::module::BorrowedModule;
+///
+/// let bytes: WasmBinary = fs::read("wasm-examples/hello.wasm")?.as_slice().into();
+/// let module = BorrowedModule::parse_borrowed(&mut ParsingData::new(&bytes))?;
+/// for data in &module.datas {
+///     assert!(bytes.as_ptr_range().contains(&data.init.as_ptr()));
+/// }
+/// for func in &module.funcs {
+///     let instructions = func.instructions()?;
+/// }
+/// let module = module.into_owned()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BorrowedModule<'a> {
+    pub types: Vec<types::RecType>,
+    pub funcs: Vec<func::BorrowedFunc<'a>>,
+    pub tables: Vec<table::Table>,
+    pub mems: Vec<mem::Mem>,
+    pub tags: Vec<tag::Tag>,
+    pub globals: Vec<global::Global>,
+    pub elems: Vec<elem::Elem>,
+    pub datas: Vec<data::BorrowedData<'a>>,
+    pub start: Option<start::Start>,
+    pub imports: Vec<import::Import>,
+    pub exports: Vec<export::Export>,
+    pub name: Option<Name>,
+    pub names: names::NameSection,
+    /// Custom sections in the order they appear, including the `name` section.
+    pub custom: Vec<custom::BorrowedCustomSection<'a>>,
+    /// Recoverable errors found while parsing the module (e.g. a malformed name section).
+    pub warnings: Vec<ParseError>,
+}
+
+impl BorrowedModule<'_> {
+    /// Decodes the function bodies and copies the contents of the data segments. The custom
+    /// sections other than the `name` section, which is already decoded, are dropped as [Module]
+    /// doesn't keep them.
+    ///
+    /// Errors within function bodies are tagged with
+    /// [ErrorContext::Func](crate::parse::binary::ErrorContext::Func) as when parsing a [Module].
+    pub fn into_owned(self) -> Result<Module, ParseError> {
+        let funcs = self
+            .funcs
+            .into_iter()
+            .map(func::BorrowedFunc::into_owned)
+            .collect::<Result<_, _>>()?;
+        Ok(Module {
+            types: self.types,
+            funcs,
+            tables: self.tables,
+            mems: self.mems,
+            tags: self.tags,
+            globals: self.globals,
+            elems: self.elems,
+            datas: self
+                .datas
+                .into_iter()
+                .map(data::BorrowedData::into_owned)
+                .collect(),
+            start: self.start,
+            imports: self.imports,
+            exports: self.exports,
+            name: self.name,
+            names: self.names,
+        })
+    }
 }
 
 impl Module {
@@ -25,4 +121,104 @@ impl Module {
         let mut parse = ParsingData::new(&bin);
         Module::parse(&mut parse).unwrap()
     }
+
+    /// Parses a module along with the byte ranges of its items within the binary, which
+    /// [Module::parse] doesn't keep.
+    ///
+    /// ```
+    /// use wasm_parse::parse::{ParsingData, WasmBinary};
+    /// use wasm_parse::wasm::module::Module;
+    ///
+    /// let bytes: WasmBinary = std::fs::read("wasm-examples/hello.wasm")?.as_slice().into();
+    /// let (module, spans) = Module::parse_with_spans(&mut ParsingData::new(&bytes))?;
+    /// assert_eq!(spans.funcs.len(), module.funcs.len());
+    /// for (func, instrs) in spans.funcs.iter().zip(&spans.instrs) {
+    ///     assert!(instrs.iter().all(|instr| func.contains(&instr.start)));
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn parse_with_spans(data: &mut ParsingData) -> Result<(Module, Spans), ParseError> {
+        spans::parse_spanned_module(data)
+    }
+
+    /// Parses a module like [Module::parse], along with the recoverable errors found while
+    /// parsing it, such as a malformed name section. The module is then parsed as if the faulty
+    /// custom section was missing.
+    ///
+    /// ```
+    /// use wasm_parse::parse::{ParsingData, WasmBinary};
+    /// use wasm_parse::wasm::module::Module;
+    ///
+    /// let bytes: WasmBinary = std::fs::read("wasm-examples/hello.wasm")?.as_slice().into();
+    /// let (module, warnings) = Module::parse_with_warnings(&mut ParsingData::new(&bytes))?;
+    /// assert!(warnings.is_empty());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn parse_with_warnings(
+        data: &mut ParsingData,
+    ) -> Result<(Module, Vec<ParseError>), ParseError> {
+        let mut binary =
+            BinaryModule::parse(data).map_err(|err| err.extend("Can't parse binary module"))?;
+        let warnings = std::mem::take(&mut binary.warnings);
+        Ok((binary.into(), warnings))
+    }
+
+    /// Parses a module written in the text format. See [text::parse].
+    pub fn from_wat(wat: &str) -> Result<Module, TextError> {
+        text::parse(wat)
+    }
+
+    /// Serializes the module into the web assembly binary format.
+    ///
+    /// The only custom section written is the `name` section, built from [Module::name] and
+    /// [Module::names]. A module doesn't keep the other custom sections of the binary it was
+    /// parsed from, so they don't survive a round trip through [Module].
+    pub fn to_binary(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+
+    /// Prints the module in the text format, with one instruction per line. See [wat::print].
+    pub fn to_wat(&self) -> String {
+        wat::print(self, Style::Flat)
+    }
+
+    /// Type definitions in the order of the type index space, i.e. with the recursion groups
+    /// flattened.
+    pub fn sub_types(&self) -> impl Iterator<Item = &types::SubType> {
+        self.types.iter().flat_map(|group| &group.types)
+    }
+
+    /// Type definition at index `idx`.
+    pub fn sub_type(&self, idx: TypeIdx) -> Option<&types::SubType> {
+        self.sub_types().nth(*idx.0 as usize)
+    }
+
+    /// Function type at index `idx`, if it is defined and is a function type.
+    pub fn func_type(&self, idx: TypeIdx) -> Option<&types::FuncType> {
+        self.sub_type(idx).and_then(types::SubType::func)
+    }
+
+    /// Validates the module. See [validate](super::validate).
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validate::validate(self)
+    }
+
+    /// Validates a module parsed with [parse_with_spans](Module::parse_with_spans), reporting
+    /// byte offsets. See [validate_with_spans](validate::validate_with_spans).
+    pub fn validate_with_spans(&self, spans: &Spans) -> Result<(), ValidationError> {
+        validate::validate_with_spans(self, spans)
+    }
+}
+
+/// Prints the module in the text format. The alternate flag (`{:#}`) selects the
+/// [folded](Style::Folded) style rather than the [flat](Style::Flat) one.
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let style = if f.alternate() {
+            Style::Folded
+        } else {
+            Style::Flat
+        };
+        f.write_str(&wat::print(self, style))
+    }
 }
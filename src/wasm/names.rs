@@ -0,0 +1,76 @@
+//! Web assembly name section definition.
+//!
+//! The name section is a custom section that associates human readable names with the entities
+//! of a module. It has no semantic meaning and is only used for debugging and tooling purposes.
+
+use std::collections::BTreeMap;
+
+use super::indices::{FuncIdx, LocalIdx};
+use super::values::Name;
+
+/// Association between indices and names.
+///
+/// The map is ordered by index which is also the order required by the binary format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameMap {
+    pub names: BTreeMap<u32, Name>,
+}
+
+/// Association between indices and [NameMap]s (e.g. function index to local names).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndirectNameMap {
+    pub names: BTreeMap<u32, NameMap>,
+}
+
+/// Decoded contents of the `name` custom section.
+///
+/// Besides the module, function and local subsections defined in the specification, this also
+/// holds the subsections from the extended name section proposal.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameSection {
+    pub module: Option<Name>,
+    pub funcs: NameMap,
+    pub locals: IndirectNameMap,
+    pub labels: IndirectNameMap,
+    pub types: NameMap,
+    pub tables: NameMap,
+    pub mems: NameMap,
+    pub globals: NameMap,
+    pub elems: NameMap,
+    pub datas: NameMap,
+    /// Names of the fields of struct types, by type index.
+    pub fields: IndirectNameMap,
+    pub tags: NameMap,
+}
+
+impl NameMap {
+    pub fn get(&self, idx: u32) -> Option<&Name> {
+        self.names.get(&idx)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl IndirectNameMap {
+    pub fn get(&self, outer: u32, inner: u32) -> Option<&Name> {
+        self.names.get(&outer).and_then(|map| map.get(inner))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl NameSection {
+    /// Returns the name of the function at index `func`, if any.
+    pub fn func(&self, func: FuncIdx) -> Option<&Name> {
+        self.funcs.get(*func.0)
+    }
+
+    /// Returns the name of the local `local` within the function `func`, if any.
+    pub fn local(&self, func: FuncIdx, local: LocalIdx) -> Option<&Name> {
+        self.locals.get(*func.0, *local.0)
+    }
+}
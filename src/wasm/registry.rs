@@ -0,0 +1,326 @@
+//! Canonical types of the GC proposal.
+//!
+//! Two type definitions are equivalent when their recursion groups have the same structure, no
+//! matter which module defines them or at which index. The [TypeRegistry] gives every definition
+//! a canonical identifier that equivalent definitions share, which reduces type equivalence to
+//! comparing identifiers, including across modules.
+
+use std::collections::HashMap;
+
+use super::indices::TypeIdx;
+use super::types::{
+    ArrayType, CompType, FieldType, FuncType, HeapType, Mutability, RefType, ResultType,
+    StorageType, StructType, SubType, ValType,
+};
+use super::values::U32;
+
+/// Registry of canonical type definitions. The type indices of the registered definitions are
+/// canonical identifiers.
+#[derive(Debug, Clone, Default)]
+pub struct TypeRegistry {
+    /// Identifier of the first type of every registered recursion group, by the group's
+    /// definitions in which references inside the group are relative to its first type, and
+    /// references outside the group are canonical identifiers offset by the size of the group.
+    groups: HashMap<Vec<SubType>, u32>,
+    types: Vec<SubType>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> TypeRegistry {
+        TypeRegistry::default()
+    }
+
+    /// Registers a recursion group which follows the types with the canonical identifiers `ids`
+    /// in the type index space of its module, and returns the identifiers of its types. The
+    /// definitions of the group must only refer to the previous types and to the group itself.
+    pub fn add_group(&mut self, group: &[SubType], ids: &[u32]) -> Vec<u32> {
+        let first = ids.len() as u32;
+        let len = group.len() as u32;
+        let key: Vec<SubType> = group
+            .iter()
+            .map(|tpe| {
+                map_sub(tpe, &|idx| match idx.checked_sub(first) {
+                    Some(rel) => rel,
+                    None => ids[idx as usize] + len,
+                })
+            })
+            .collect();
+        let start = match self.groups.get(&key) {
+            Some(start) => *start,
+            None => {
+                let start = self.types.len() as u32;
+                for tpe in group {
+                    let tpe = map_sub(tpe, &|idx| match idx.checked_sub(first) {
+                        Some(rel) => start + rel,
+                        None => ids[idx as usize],
+                    });
+                    self.types.push(tpe);
+                }
+                self.groups.insert(key, start);
+                start
+            }
+        };
+        (start..start + len).collect()
+    }
+
+    /// Registers all the recursion groups of a module, and returns the identifiers of its type
+    /// index space.
+    pub fn add_module<'a>(&mut self, groups: impl IntoIterator<Item = &'a [SubType]>) -> Vec<u32> {
+        let mut ids = Vec::new();
+        for group in groups {
+            let group_ids = self.add_group(group, &ids);
+            ids.extend(group_ids);
+        }
+        ids
+    }
+
+    /// Definition of the type with the canonical identifier `id`.
+    pub fn get(&self, id: u32) -> &SubType {
+        &self.types[id as usize]
+    }
+
+    /// Abstract type at the top of the hierarchy of `heap`.
+    pub fn top(&self, heap: HeapType) -> HeapType {
+        match heap {
+            HeapType::Func | HeapType::NoFunc => HeapType::Func,
+            HeapType::Extern | HeapType::NoExtern => HeapType::Extern,
+            HeapType::Exn | HeapType::NoExn => HeapType::Exn,
+            HeapType::Concrete(idx) => match self.get(*idx.0).comp {
+                CompType::Func(_) => HeapType::Func,
+                _ => HeapType::Any,
+            },
+            _ => HeapType::Any,
+        }
+    }
+
+    /// Abstract type at the bottom of the hierarchy of `heap`, the type of its null reference.
+    pub fn bottom(&self, heap: HeapType) -> HeapType {
+        match self.top(heap) {
+            HeapType::Func => HeapType::NoFunc,
+            HeapType::Extern => HeapType::NoExtern,
+            HeapType::Exn => HeapType::NoExn,
+            _ => HeapType::None,
+        }
+    }
+
+    /// Whether `sub` is a subtype of `sup`, with concrete types given by canonical identifiers.
+    pub fn is_heap_subtype(&self, sub: HeapType, sup: HeapType) -> bool {
+        use HeapType::*;
+        if sub == sup {
+            return true;
+        }
+        match (sub, sup) {
+            (NoFunc, _) | (NoExtern, _) | (NoExn, _) | (None, _) => self.top(sub) == self.top(sup),
+            (I31, Eq | Any) | (Struct, Eq | Any) | (Array, Eq | Any) | (Eq, Any) => true,
+            (Concrete(idx), _) => {
+                let tpe = self.get(*idx.0);
+                match (&tpe.comp, sup) {
+                    (CompType::Func(_), Func)
+                    | (CompType::Struct(_), Struct | Eq | Any)
+                    | (CompType::Array(_), Array | Eq | Any) => true,
+                    (_, Concrete(_)) => tpe
+                        .supers
+                        .iter()
+                        .any(|sub| self.is_heap_subtype(HeapType::Concrete(*sub), sup)),
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_ref_subtype(&self, sub: RefType, sup: RefType) -> bool {
+        (!sub.nullable || sup.nullable) && self.is_heap_subtype(sub.heap, sup.heap)
+    }
+
+    pub fn is_val_subtype(&self, sub: ValType, sup: ValType) -> bool {
+        match (sub, sup) {
+            (ValType::Ref(sub), ValType::Ref(sup)) => self.is_ref_subtype(sub, sup),
+            _ => sub == sup,
+        }
+    }
+
+    /// Whether the values of the types `sub` can be used where values of the types `sup` are
+    /// expected.
+    pub fn are_val_subtypes(&self, sub: &[ValType], sup: &[ValType]) -> bool {
+        sub.len() == sup.len()
+            && sub
+                .iter()
+                .zip(sup)
+                .all(|(sub, sup)| self.is_val_subtype(*sub, *sup))
+    }
+
+    /// Whether a type defined as `sub` can declare a supertype defined as `sup`.
+    pub fn comp_matches(&self, sub: &CompType, sup: &CompType) -> bool {
+        match (sub, sup) {
+            (CompType::Func(sub), CompType::Func(sup)) => {
+                self.are_val_subtypes(&sup.params.types, &sub.params.types)
+                    && self.are_val_subtypes(&sub.result.types, &sup.result.types)
+            }
+            (CompType::Struct(sub), CompType::Struct(sup)) => {
+                sub.fields.len() >= sup.fields.len()
+                    && sub
+                        .fields
+                        .iter()
+                        .zip(&sup.fields)
+                        .all(|(sub, sup)| self.field_matches(sub, sup))
+            }
+            (CompType::Array(sub), CompType::Array(sup)) => {
+                self.field_matches(&sub.field, &sup.field)
+            }
+            _ => false,
+        }
+    }
+
+    fn field_matches(&self, sub: &FieldType, sup: &FieldType) -> bool {
+        match (sub.tpe, sup.tpe) {
+            (StorageType::Val(tpe), StorageType::Val(sup_tpe))
+                if sub.mutability == Mutability::Const && sup.mutability == Mutability::Const =>
+            {
+                self.is_val_subtype(tpe, sup_tpe)
+            }
+            _ => sub == sup,
+        }
+    }
+}
+
+/// Replaces the type indices of a reference type with the canonical identifiers `ids` of the types
+/// of its module.
+pub fn canonical_ref(tpe: RefType, ids: &[u32]) -> RefType {
+    match tpe.heap {
+        HeapType::Concrete(idx) => RefType {
+            nullable: tpe.nullable,
+            heap: HeapType::Concrete(TypeIdx(U32(ids[*idx.0 as usize]))),
+        },
+        _ => tpe,
+    }
+}
+
+/// Replaces the type indices of a value type with the canonical identifiers `ids` of the types of
+/// its module.
+pub fn canonical_val(tpe: ValType, ids: &[u32]) -> ValType {
+    match tpe {
+        ValType::Ref(tpe) => ValType::Ref(canonical_ref(tpe, ids)),
+        tpe => tpe,
+    }
+}
+
+/// Replaces the type indices of a definition with `f` of their value.
+fn map_sub(tpe: &SubType, f: &dyn Fn(u32) -> u32) -> SubType {
+    let map_idx = |idx: &TypeIdx| TypeIdx(U32(f(*idx.0)));
+    let map_val = |tpe: ValType| match tpe {
+        ValType::Ref(RefType {
+            nullable,
+            heap: HeapType::Concrete(idx),
+        }) => ValType::Ref(RefType {
+            nullable,
+            heap: HeapType::Concrete(map_idx(&idx)),
+        }),
+        tpe => tpe,
+    };
+    let map_field = |field: &FieldType| FieldType {
+        tpe: match field.tpe {
+            StorageType::Val(tpe) => StorageType::Val(map_val(tpe)),
+            tpe => tpe,
+        },
+        mutability: field.mutability,
+    };
+    let map_result = |result: &ResultType| ResultType {
+        types: result.types.iter().copied().map(map_val).collect(),
+    };
+    SubType {
+        is_final: tpe.is_final,
+        supers: tpe.supers.iter().map(map_idx).collect(),
+        comp: match &tpe.comp {
+            CompType::Func(func) => CompType::Func(FuncType {
+                params: map_result(&func.params),
+                result: map_result(&func.result),
+            }),
+            CompType::Struct(st) => CompType::Struct(StructType {
+                fields: st.fields.iter().map(map_field).collect(),
+            }),
+            CompType::Array(array) => CompType::Array(ArrayType {
+                field: map_field(&array.field),
+            }),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::module::Module;
+
+    fn register(registry: &mut TypeRegistry, text: &str) -> Vec<u32> {
+        let module = Module::from_wat(text).unwrap();
+        registry.add_module(module.types.iter().map(|group| &group.types[..]))
+    }
+
+    fn concrete(id: u32) -> HeapType {
+        HeapType::Concrete(TypeIdx(U32(id)))
+    }
+
+    #[test]
+    fn equivalent_groups() {
+        let mut registry = TypeRegistry::new();
+        let list = "(rec (type $l (struct (field (ref null $l)))))";
+        let first = register(&mut registry, &format!("(type (func)) {}", list));
+        let second = register(&mut registry, &format!("{} (type (func))", list));
+        assert_eq!(first, vec![0, 1]);
+        assert_eq!(second, vec![1, 0]);
+
+        // Equivalent definitions in groups of different shapes are distinct types.
+        let pair = register(
+            &mut registry,
+            "(rec (type $l (struct (field (ref null $l)))) (type (struct)))",
+        );
+        assert_eq!(pair, vec![2, 3]);
+        // References outside of the group are compared by their canonical identifier.
+        let outer = register(
+            &mut registry,
+            "(type (func)) (type (struct (field (ref 0)))) \
+             (type (struct)) (type (struct (field (ref 0))))",
+        );
+        assert_eq!(outer, vec![0, 4, 5, 4]);
+    }
+
+    #[test]
+    fn subtypes() {
+        let mut registry = TypeRegistry::new();
+        let ids = register(
+            &mut registry,
+            "(type $f (func)) (type $s (sub (struct))) (type $t (sub $s (struct (field i32)))) \
+             (type $a (array i8))",
+        );
+        let (f, s, t, a) = (
+            concrete(ids[0]),
+            concrete(ids[1]),
+            concrete(ids[2]),
+            concrete(ids[3]),
+        );
+        assert!(registry.is_heap_subtype(t, s));
+        assert!(!registry.is_heap_subtype(s, t));
+        assert!(registry.is_heap_subtype(t, HeapType::Struct));
+        assert!(registry.is_heap_subtype(a, HeapType::Eq));
+        assert!(!registry.is_heap_subtype(a, HeapType::Struct));
+        assert!(registry.is_heap_subtype(f, HeapType::Func));
+        assert!(!registry.is_heap_subtype(f, HeapType::Any));
+        assert!(registry.is_heap_subtype(HeapType::None, t));
+        assert!(!registry.is_heap_subtype(HeapType::None, f));
+        assert!(registry.is_heap_subtype(HeapType::NoFunc, f));
+        assert!(registry.is_heap_subtype(HeapType::I31, HeapType::Any));
+        assert_eq!(registry.bottom(f), HeapType::NoFunc);
+        assert_eq!(registry.top(a), HeapType::Any);
+
+        let nullable = |heap| RefType {
+            nullable: true,
+            heap,
+        };
+        let non_null = |heap| RefType {
+            nullable: false,
+            heap,
+        };
+        assert!(registry.is_ref_subtype(non_null(t), nullable(s)));
+        assert!(!registry.is_ref_subtype(nullable(t), non_null(s)));
+    }
+}
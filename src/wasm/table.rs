@@ -1,8 +1,12 @@
 //! Web assembly table definition.
 
+use super::instr::Expr;
 use super::types::TableType;
 
 #[derive(Debug, Clone)]
 pub struct Table {
     pub tpe: TableType,
+    /// Initial value of the elements, which tables of non-nullable references require.
+    /// Otherwise they start as null references.
+    pub init: Option<Expr>,
 }
@@ -0,0 +1,10 @@
+//! Web assembly tag definition, from the exception handling proposal.
+
+use super::indices::TypeIdx;
+
+/// Tag of the exceptions thrown by `throw`. Its type is a function type whose parameters are the
+/// values an exception carries and whose results are empty.
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub tpe: TypeIdx,
+}
@@ -1,8 +1,9 @@
 //! Web assembly types definitions.
 
-use super::values::U32;
+use super::indices::TypeIdx;
+use super::values::U64;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum NumType {
     I32,
     I64,
@@ -10,43 +11,211 @@ pub enum NumType {
     F64,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum RefType {
-    FuncRef,
-    ExternRef,
+/// Type of the 128 bit vectors of the SIMD instructions.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum VecType {
+    V128,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Type of the values a reference can point to. Besides the abstract types, which form one
+/// hierarchy per kind of reference (`any`, `func`, `extern` and `exn`), references can be typed
+/// by a type definition of the module, as introduced by the typed function references and GC
+/// proposals.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum HeapType {
+    Func,
+    /// Bottom type of the `func` hierarchy, which only the null reference has.
+    NoFunc,
+    Extern,
+    NoExtern,
+    Any,
+    /// References that can be compared with `ref.eq`: `i31`, structs and arrays.
+    Eq,
+    /// Unboxed 31-bit integer.
+    I31,
+    Struct,
+    Array,
+    /// Bottom type of the `any` hierarchy.
+    None,
+    /// Caught exception, from the exception handling proposal.
+    Exn,
+    NoExn,
+    /// Type defined in the type section.
+    Concrete(TypeIdx),
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct RefType {
+    pub nullable: bool,
+    pub heap: HeapType,
+}
+
+impl RefType {
+    pub const FUNCREF: RefType = RefType::null(HeapType::Func);
+    pub const EXTERNREF: RefType = RefType::null(HeapType::Extern);
+    pub const EXNREF: RefType = RefType::null(HeapType::Exn);
+
+    /// Nullable reference to `heap`.
+    pub const fn null(heap: HeapType) -> RefType {
+        RefType {
+            nullable: true,
+            heap,
+        }
+    }
+
+    /// Non-nullable reference to `heap`.
+    pub const fn non_null(heap: HeapType) -> RefType {
+        RefType {
+            nullable: false,
+            heap,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum ValType {
     Num(NumType),
+    Vec(VecType),
     Ref(RefType),
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+impl ValType {
+    /// Whether locals of the type can start with a default value, which is the case of every
+    /// type but the non-nullable references.
+    pub fn is_defaultable(&self) -> bool {
+        !matches!(
+            self,
+            ValType::Ref(RefType {
+                nullable: false,
+                ..
+            })
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct ResultType {
     pub types: Vec<ValType>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct FuncType {
     pub params: ResultType,
     pub result: ResultType,
 }
 
+/// Type of the fields of structs and arrays, which can be packed integers.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum StorageType {
+    Val(ValType),
+    I8,
+    I16,
+}
+
+impl StorageType {
+    /// Type of the values the field holds on the operand stack: packed fields unpack to `i32`.
+    pub fn unpacked(&self) -> ValType {
+        match self {
+            StorageType::Val(tpe) => *tpe,
+            StorageType::I8 | StorageType::I16 => ValType::Num(NumType::I32),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct FieldType {
+    pub tpe: StorageType,
+    pub mutability: Mutability,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct StructType {
+    pub fields: Vec<FieldType>,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ArrayType {
+    pub field: FieldType,
+}
+
+/// Structure of a type definition.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum CompType {
+    Func(FuncType),
+    Struct(StructType),
+    Array(ArrayType),
+}
+
+/// Type definition, with its declared supertypes. Other types can only declare a type as their
+/// supertype if it isn't final.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct SubType {
+    pub is_final: bool,
+    pub supers: Vec<TypeIdx>,
+    pub comp: CompType,
+}
+
+impl SubType {
+    /// The definition if it is a function type.
+    pub fn func(&self) -> Option<&FuncType> {
+        match &self.comp {
+            CompType::Func(tpe) => Some(tpe),
+            _ => None,
+        }
+    }
+
+    /// Final type without supertypes, which is how types are defined without the GC proposal.
+    pub fn from_comp(comp: CompType) -> SubType {
+        SubType {
+            is_final: true,
+            supers: Vec::new(),
+            comp,
+        }
+    }
+}
+
+impl From<FuncType> for SubType {
+    fn from(tpe: FuncType) -> SubType {
+        SubType::from_comp(CompType::Func(tpe))
+    }
+}
+
+/// Recursion group: type definitions that can refer to each other. Each entry of the type
+/// section is a group, in which types outside any `rec` are alone.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct RecType {
+    pub types: Vec<SubType>,
+}
+
+/// Limits of a table or memory. They are 64 bits wide for the tables and memories with an `i64`
+/// index type, and fit in 32 bits otherwise.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Limits {
-    pub min: U32,
-    pub max: Option<U32>,
+    pub min: U64,
+    pub max: Option<U64>,
+}
+
+/// Type of the addresses of a memory or of the indices of a table. 64-bit memories and tables
+/// come from the memory64 proposal.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum IndexType {
+    I32,
+    I64,
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct MemType {
     pub lim: Limits,
+    pub index: IndexType,
+    /// Whether the memory can be shared between threads, which the atomic `wait` and `notify`
+    /// instructions require.
+    pub shared: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct TableType {
     pub lim: Limits,
+    pub index: IndexType,
     pub tpe: RefType,
 }
 
@@ -56,7 +225,7 @@ pub struct GlobalType {
     pub tpe: ValType,
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Mutability {
     Const,
     Mut,
@@ -68,4 +237,5 @@ pub enum ExternType {
     Table(TableType),
     Mem(MemType),
     Global(GlobalType),
+    Tag(FuncType),
 }
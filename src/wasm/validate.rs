@@ -0,0 +1,3030 @@
+//! Web assembly module validation.
+//!
+//! Parsing a module only checks that it is well formed. This module implements the validation
+//! algorithm from the specification which checks that the module is well typed: every index
+//! refers to an existing entity, every function body type checks against its signature and
+//! constant expressions are actually constant.
+//!
+//! Function bodies are checked with the operand and control stack algorithm described in the
+//! appendix of the specification.
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use crate::parse::binary::Spans;
+
+use super::data::DataMode;
+use super::elem::ElemMode;
+use super::export::ExportDesc;
+use super::import::ImportDesc;
+use super::indices::{
+    FieldIdx, FuncIdx, GlobalIdx, LabelIdx, LaneIdx, LocalIdx, MemIdx, TableIdx, TagIdx, TypeIdx,
+};
+use super::instr::{
+    AggregateInstr, AtomicInstr, BlockType, Catch, CatchBranch, ControlInstr, Expr, FloatType,
+    Instr, IntType, MemArg, MemoryInstr, NumericInstr, ReferenceInstr, Sign, TableInstr,
+    TryHandler, VariableInstr, VectorInstr,
+};
+use super::module::Module;
+use super::registry::TypeRegistry;
+use super::types::{
+    ArrayType, CompType, FieldType, FuncType, GlobalType, HeapType, IndexType, Limits, MemType,
+    Mutability, NumType, RefType, ResultType, StorageType, StructType, SubType, TableType, ValType,
+    VecType,
+};
+
+/// Maximum number of pages a memory can have.
+const MAX_PAGES: u64 = 1 << 16;
+/// Maximum number of pages a 64-bit memory can have.
+const MAX_PAGES_64: u64 = 1 << 48;
+
+/// Error returned when a module doesn't validate.
+///
+/// Errors within a function body carry the index of the function within the function index
+/// space (i.e. including imported functions) and the position of the offending instruction within
+/// the body, in the order of [Spans::instrs]. When the module is validated with
+/// [validate_with_spans], they also carry the byte offset of the instruction within the binary.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "Validation error{}: {}{}",
+    location(.func, .instr, .offset),
+    display_context(.context),
+    kind
+)]
+pub struct ValidationError {
+    kind: ValidationErrorKind,
+    func: Option<FuncIdx>,
+    instr: Option<usize>,
+    offset: Option<usize>,
+    context: Vec<String>,
+}
+
+/// The reason a [ValidationError] occurred.
+///
+/// Indices are the raw indices found in the module, within their respective index spaces.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ValidationErrorKind {
+    #[error("Unknown type {0}")]
+    UnknownType(u32),
+    #[error("Unknown function {0}")]
+    UnknownFunc(u32),
+    #[error("Unknown table {0}")]
+    UnknownTable(u32),
+    #[error("Unknown memory {0}")]
+    UnknownMem(u32),
+    #[error("Unknown global {0}")]
+    UnknownGlobal(u32),
+    #[error("Unknown tag {0}")]
+    UnknownTag(u32),
+    #[error("Unknown element segment {0}")]
+    UnknownElem(u32),
+    #[error("Unknown data segment {0}")]
+    UnknownData(u32),
+    #[error("Unknown local {0}")]
+    UnknownLocal(u32),
+    #[error("Unknown label {0}")]
+    UnknownLabel(u32),
+    #[error("Unknown field {field} of type {tpe}")]
+    UnknownField { tpe: u32, field: u32 },
+    #[error("Type mismatch. Type {0} is not a function type")]
+    NotFuncType(u32),
+    #[error("Type mismatch. Type {0} is not a struct type")]
+    NotStructType(u32),
+    #[error("Type mismatch. Type {0} is not an array type")]
+    NotArrayType(u32),
+    #[error("Type mismatch. Expected a value but the stack is empty")]
+    EmptyStack,
+    #[error("Type mismatch. Expected {expected:?}, got {actual:?}")]
+    TypeMismatch { expected: ValType, actual: ValType },
+    #[error("Type mismatch. Expected a reference, got {0:?}")]
+    ExpectedRef(ValType),
+    /// Values left on the stack at the end of a block. `None` stands for values of unknown type,
+    /// produced in unreachable code.
+    #[error(
+        "Type mismatch. Values remaining on the stack at the end of the block: {}",
+        display(.0)
+    )]
+    ValuesRemaining(Vec<Option<ValType>>),
+    #[error("Type mismatch. Label {0} must end with a reference type")]
+    LabelNotRef(u32),
+    #[error("Type mismatch. Label {label} expects {expected:?}, the branch passes {actual:?}")]
+    LabelMismatch {
+        label: u32,
+        expected: Vec<ValType>,
+        actual: Vec<ValType>,
+    },
+    #[error(
+        "Type mismatch in br_table. Label {label} has arity {arity} but the default has arity \
+         {default}"
+    )]
+    BrTableArity {
+        label: u32,
+        arity: usize,
+        default: usize,
+    },
+    #[error("Type mismatch. Cast type {cast:?} must be a subtype of {input:?}")]
+    CastMismatch { input: RefType, cast: RefType },
+    #[error("Tail call results {actual:?} don't match the function results {expected:?}")]
+    TailCallMismatch {
+        expected: Vec<ValType>,
+        actual: Vec<ValType>,
+    },
+    #[error("call_indirect requires a funcref table. Table {table} has type {tpe:?}")]
+    NotFuncRefTable { table: u32, tpe: RefType },
+    #[error("rethrow must refer to a catch clause. Label {0} doesn't")]
+    RethrowTarget(u32),
+    #[error("Type mismatch in select. Got {0:?} and {1:?}")]
+    SelectMismatch(ValType, ValType),
+    #[error("Untyped select requires numeric operands")]
+    UntypedSelect,
+    #[error("Select can have at most one type. Got {0}")]
+    SelectArity(usize),
+    #[error("Uninitialized local {0}")]
+    UninitializedLocal(u32),
+    #[error("Global {0} is immutable")]
+    ImmutableGlobal(u32),
+    #[error(
+        "Undeclared function reference {0}. Functions used in ref.func must be referenced \
+         outside of function bodies"
+    )]
+    UndeclaredFuncRef(u32),
+    #[error("Offset {0} out of range for a 32-bit memory")]
+    OffsetOutOfRange(u64),
+    #[error("Alignment 2^{align} must not be larger than natural alignment 2^{natural}")]
+    AlignmentTooLarge { align: u32, natural: u32 },
+    #[error("Atomic alignment 2^{align} must be equal to natural alignment 2^{natural}")]
+    AtomicAlignment { align: u32, natural: u32 },
+    #[error("Lane index {lane} out of range for {lanes} lanes")]
+    LaneOutOfRange { lane: u8, lanes: u8 },
+    #[error("Type mismatch in table.copy. Got {dst:?} and {src:?}")]
+    TableCopyMismatch { dst: RefType, src: RefType },
+    #[error("Type mismatch in table.init. Got {table:?} and {elem:?}")]
+    TableInitMismatch { table: RefType, elem: RefType },
+    #[error(
+        "Packed fields must be read with a sign extension, and only them. Field has type {0:?}"
+    )]
+    PackedField(StorageType),
+    #[error("Field is immutable")]
+    ImmutableField,
+    #[error("Type {0:?} is not defaultable")]
+    NotDefaultable(StorageType),
+    #[error("Type mismatch in array.copy. Can't copy {src:?} into {dst:?}")]
+    ArrayCopyMismatch { dst: StorageType, src: StorageType },
+    #[error(
+        "Type mismatch. Element segment {elem} of type {tpe:?} doesn't match the array type \
+         {array:?}"
+    )]
+    ElemArrayMismatch {
+        elem: u32,
+        tpe: RefType,
+        array: StorageType,
+    },
+    #[error("Type mismatch. Arrays of references ({0:?}) can't be initialized from data")]
+    DataArrayMismatch(RefType),
+    #[error("Tag types must not have results. Type {tpe} has results {results:?}")]
+    TagResults { tpe: u32, results: Vec<ValType> },
+    #[error("Instruction is not constant")]
+    NotConstant,
+    #[error("Constant expressions may only refer to imported globals. Global {0} isn't")]
+    ConstGlobalNotImported(u32),
+    #[error("Constant expressions may only refer to immutable globals. Global {0} is mutable")]
+    ConstGlobalMutable(u32),
+    #[error("Limit minimum {min} must not be larger than {range}")]
+    MinTooLarge { min: u64, range: u64 },
+    #[error("Limit maximum {max} must not be larger than {range}")]
+    MaxTooLarge { max: u64, range: u64 },
+    #[error("Limit maximum {max} must not be smaller than the minimum {min}")]
+    MaxBelowMin { min: u64, max: u64 },
+    #[error("Shared memory must have a maximum")]
+    SharedWithoutMax,
+    #[error("Tables of non-nullable type {0:?} need an initializer")]
+    TableWithoutInit(RefType),
+    #[error("Type mismatch. Table {table} has type {tpe:?} but the segment has type {elem:?}")]
+    ElemTableMismatch {
+        table: u32,
+        tpe: RefType,
+        elem: RefType,
+    },
+    #[error("Supertype {0} must be defined before the type")]
+    SupertypeOrder(u32),
+    #[error("A type can have at most one supertype")]
+    MultipleSupertypes,
+    #[error("Supertype {0} is final")]
+    FinalSupertype(u32),
+    #[error("Type mismatch. The type doesn't match its supertype {0}")]
+    SupertypeMismatch(u32),
+    #[error("Start function must have type [] -> []. Got {params:?} -> {results:?}")]
+    StartType {
+        params: Vec<ValType>,
+        results: Vec<ValType>,
+    },
+    #[error("Duplicate export name {0:?}")]
+    DuplicateExport(String),
+}
+
+fn location(func: &Option<FuncIdx>, instr: &Option<usize>, offset: &Option<usize>) -> String {
+    let at = match (instr, offset) {
+        (_, Some(offset)) => format!(" at byte {:#X}", offset),
+        (Some(instr), None) => format!(" at instruction {}", instr),
+        (None, None) => String::new(),
+    };
+    match func {
+        Some(func) => format!(" in function {}{}", *func.0, at),
+        None => at,
+    }
+}
+
+fn display_context(context: &[String]) -> String {
+    context
+        .iter()
+        .rev()
+        .map(|message| format!("{} --- ", message))
+        .collect()
+}
+
+impl ValidationError {
+    /// Constructs a ValidationError.
+    pub fn new(kind: ValidationErrorKind) -> ValidationError {
+        ValidationError {
+            kind,
+            func: None,
+            instr: None,
+            offset: None,
+            context: Vec::new(),
+        }
+    }
+
+    /// The reason validation failed.
+    pub fn kind(&self) -> &ValidationErrorKind {
+        &self.kind
+    }
+
+    /// The function the error occurred in, if any.
+    pub fn func(&self) -> Option<FuncIdx> {
+        self.func
+    }
+
+    /// Position of the instruction the error occurred at within its function body or constant
+    /// expression, if any. Instructions are counted in the order of [Spans::instrs], i.e. nested
+    /// ones included, and the final `end` of a body comes after the last instruction.
+    pub fn instr(&self) -> Option<usize> {
+        self.instr
+    }
+
+    /// Byte offset within the binary of the instruction the error occurred at. Only known for
+    /// function bodies validated with [validate_with_spans].
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// Notes describing what was being validated, from the innermost to the outermost.
+    pub fn context(&self) -> &[String] {
+        &self.context
+    }
+
+    fn in_func(mut self, func: FuncIdx) -> ValidationError {
+        self.func.get_or_insert(func);
+        self
+    }
+
+    fn at_instr(mut self, instr: usize) -> ValidationError {
+        self.instr.get_or_insert(instr);
+        self
+    }
+
+    /// Adds a note describing what was being validated when the error occurred.
+    fn extend(mut self, message: &str) -> ValidationError {
+        self.context.push(message.to_string());
+        self
+    }
+
+    /// Looks up the byte offset of the instruction in `spans`, given the number of imported
+    /// functions that precede the defined ones in the function index space.
+    fn locate(mut self, spans: &Spans, imported_funcs: usize) -> ValidationError {
+        if let (Some(func), Some(instr)) = (self.func, self.instr) {
+            let body = (*func.0 as usize).checked_sub(imported_funcs);
+            self.offset = body.and_then(|body| {
+                let instrs = spans.instrs.get(body)?;
+                match instrs.get(instr) {
+                    Some(range) => Some(range.start),
+                    // The final `end` of the body.
+                    None if instr == instrs.len() => Some(spans.funcs.get(body)?.end - 1),
+                    None => None,
+                }
+            });
+        }
+        self
+    }
+}
+
+fn err<T>(kind: ValidationErrorKind) -> Result<T, ValidationError> {
+    Err(ValidationError::new(kind))
+}
+
+/// Validation context (`C` in the specification).
+///
+/// The locals, labels and return type are kept by the [FuncValidator] instead.
+struct Context<'a> {
+    /// Type definitions, in the type index space.
+    types: Vec<&'a SubType>,
+    /// Canonical identifiers of the types, with which subtyping is checked.
+    ids: Vec<u32>,
+    registry: TypeRegistry,
+    funcs: Vec<TypeIdx>,
+    tables: Vec<TableType>,
+    mems: Vec<MemType>,
+    globals: Vec<GlobalType>,
+    /// Number of imported globals. Constant expressions may only refer to those.
+    imported_globals: usize,
+    tags: Vec<TypeIdx>,
+    elems: Vec<RefType>,
+    datas: usize,
+    refs: HashSet<u32>,
+}
+
+impl<'a> Context<'a> {
+    fn sub_type(&self, idx: TypeIdx) -> Result<&'a SubType, ValidationError> {
+        match self.types.get(*idx.0 as usize) {
+            Some(tpe) => Ok(tpe),
+            None => err(ValidationErrorKind::UnknownType(*idx.0)),
+        }
+    }
+
+    fn tpe(&self, idx: TypeIdx) -> Result<&'a FuncType, ValidationError> {
+        match &self.sub_type(idx)?.comp {
+            CompType::Func(tpe) => Ok(tpe),
+            _ => err(ValidationErrorKind::NotFuncType(*idx.0)),
+        }
+    }
+
+    fn struct_type(&self, idx: TypeIdx) -> Result<&'a StructType, ValidationError> {
+        match &self.sub_type(idx)?.comp {
+            CompType::Struct(tpe) => Ok(tpe),
+            _ => err(ValidationErrorKind::NotStructType(*idx.0)),
+        }
+    }
+
+    fn array_type(&self, idx: TypeIdx) -> Result<&'a ArrayType, ValidationError> {
+        match &self.sub_type(idx)?.comp {
+            CompType::Array(tpe) => Ok(tpe),
+            _ => err(ValidationErrorKind::NotArrayType(*idx.0)),
+        }
+    }
+
+    /// Checks that the type definitions a heap type refers to exist.
+    fn heap_type(&self, heap: HeapType) -> Result<(), ValidationError> {
+        match heap {
+            HeapType::Concrete(idx) => self.sub_type(idx).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    fn ref_type(&self, tpe: RefType) -> Result<(), ValidationError> {
+        self.heap_type(tpe.heap)
+    }
+
+    fn val_type(&self, tpe: ValType) -> Result<(), ValidationError> {
+        match tpe {
+            ValType::Ref(tpe) => self.ref_type(tpe),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replaces the type indices of a heap type with canonical identifiers.
+    fn canonical(&self, heap: HeapType) -> HeapType {
+        match heap {
+            HeapType::Concrete(idx) => {
+                HeapType::Concrete(TypeIdx(super::values::U32(self.ids[*idx.0 as usize])))
+            }
+            heap => heap,
+        }
+    }
+
+    /// Abstract type at the top of the hierarchy of `heap`.
+    fn top(&self, heap: HeapType) -> HeapType {
+        self.registry.top(self.canonical(heap))
+    }
+
+    fn ref_matches(&self, sub: RefType, sup: RefType) -> bool {
+        (!sub.nullable || sup.nullable)
+            && self
+                .registry
+                .is_heap_subtype(self.canonical(sub.heap), self.canonical(sup.heap))
+    }
+
+    fn matches(&self, sub: ValType, sup: ValType) -> bool {
+        match (sub, sup) {
+            (ValType::Ref(sub), ValType::Ref(sup)) => self.ref_matches(sub, sup),
+            _ => sub == sup,
+        }
+    }
+
+    fn all_match(&self, sub: &[ValType], sup: &[ValType]) -> bool {
+        sub.len() == sup.len() && sub.iter().zip(sup).all(|(t1, t2)| self.matches(*t1, *t2))
+    }
+
+    fn func(&self, idx: FuncIdx) -> Result<&FuncType, ValidationError> {
+        match self.funcs.get(*idx.0 as usize) {
+            Some(tpe) => self.tpe(*tpe),
+            None => err(ValidationErrorKind::UnknownFunc(*idx.0)),
+        }
+    }
+
+    fn table(&self, idx: TableIdx) -> Result<&TableType, ValidationError> {
+        match self.tables.get(*idx.0 as usize) {
+            Some(tpe) => Ok(tpe),
+            None => err(ValidationErrorKind::UnknownTable(*idx.0)),
+        }
+    }
+
+    fn mem(&self, idx: MemIdx) -> Result<&MemType, ValidationError> {
+        match self.mems.get(*idx.0 as usize) {
+            Some(tpe) => Ok(tpe),
+            None => err(ValidationErrorKind::UnknownMem(*idx.0)),
+        }
+    }
+
+    fn global(&self, idx: GlobalIdx) -> Result<&GlobalType, ValidationError> {
+        match self.globals.get(*idx.0 as usize) {
+            Some(tpe) => Ok(tpe),
+            None => err(ValidationErrorKind::UnknownGlobal(*idx.0)),
+        }
+    }
+
+    fn tag(&self, idx: TagIdx) -> Result<&FuncType, ValidationError> {
+        match self.tags.get(*idx.0 as usize) {
+            Some(tpe) => self.tpe(*tpe),
+            None => err(ValidationErrorKind::UnknownTag(*idx.0)),
+        }
+    }
+
+    fn elem(&self, idx: u32) -> Result<RefType, ValidationError> {
+        match self.elems.get(idx as usize) {
+            Some(tpe) => Ok(*tpe),
+            None => err(ValidationErrorKind::UnknownElem(idx)),
+        }
+    }
+
+    fn data(&self, idx: u32) -> Result<(), ValidationError> {
+        if (idx as usize) < self.datas {
+            Ok(())
+        } else {
+            err(ValidationErrorKind::UnknownData(idx))
+        }
+    }
+
+    fn block_type(&self, tpe: &BlockType) -> Result<(Vec<ValType>, Vec<ValType>), ValidationError> {
+        match tpe {
+            BlockType::Empty => Ok((Vec::new(), Vec::new())),
+            BlockType::Val(t) => {
+                self.val_type(*t)?;
+                Ok((Vec::new(), vec![*t]))
+            }
+            BlockType::Type(idx) => {
+                let tpe = self.tpe(*idx)?;
+                Ok((tpe.params.types.clone(), tpe.result.types.clone()))
+            }
+        }
+    }
+}
+
+/// Operand type. `None` represents an unknown type, produced in unreachable code.
+type Operand = Option<ValType>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    Func,
+    Block,
+    Loop,
+    If,
+    Else,
+    Try,
+    /// `catch` or `catch_all` clause of a `try` block, which `rethrow` may refer to.
+    Catch,
+}
+
+/// Control frame of the validation algorithm.
+struct Frame {
+    kind: FrameKind,
+    start: Vec<ValType>,
+    end: Vec<ValType>,
+    height: usize,
+    /// Height of the stack of initialized locals when the frame was entered.
+    inits: usize,
+    unreachable: bool,
+}
+
+impl Frame {
+    /// Types of the values a branch to this frame expects.
+    fn label_types(&self) -> &[ValType] {
+        if self.kind == FrameKind::Loop {
+            &self.start
+        } else {
+            &self.end
+        }
+    }
+}
+
+fn display(types: &[Operand]) -> String {
+    let types: Vec<String> = types
+        .iter()
+        .map(|t| match t {
+            Some(t) => format!("{:?}", t),
+            None => "Unknown".to_string(),
+        })
+        .collect();
+    format!("[{}]", types.join(", "))
+}
+
+const I32: ValType = ValType::Num(NumType::I32);
+const I64: ValType = ValType::Num(NumType::I64);
+const F32: ValType = ValType::Num(NumType::F32);
+const F64: ValType = ValType::Num(NumType::F64);
+const V128: ValType = ValType::Vec(VecType::V128);
+const EXNREF: ValType = ValType::Ref(RefType::EXNREF);
+
+/// Reference type with the given nullability.
+fn ref_type(nullable: bool, heap: HeapType) -> ValType {
+    ValType::Ref(RefType { nullable, heap })
+}
+
+/// Nullable reference to the type definition `idx`.
+fn ref_null(idx: TypeIdx) -> ValType {
+    ref_type(true, HeapType::Concrete(idx))
+}
+
+/// Type of the addresses of a memory or of the indices of a table.
+fn addr_type(index: IndexType) -> ValType {
+    match index {
+        IndexType::I32 => I32,
+        IndexType::I64 => I64,
+    }
+}
+
+fn int_type(tpe: &IntType) -> ValType {
+    match tpe {
+        IntType::I32 => I32,
+        IntType::I64 => I64,
+    }
+}
+
+fn float_type(tpe: &FloatType) -> ValType {
+    match tpe {
+        FloatType::F32 => F32,
+        FloatType::F64 => F64,
+    }
+}
+
+/// What to do once the instructions of a block have been validated.
+enum BlockEnd<'a> {
+    /// Top level sequence.
+    Seq,
+    /// End of a block, loop or else branch started at the given instruction offset.
+    Block(usize),
+    /// End of the then branch of an if started at the given offset. The else branch comes next.
+    Then(usize, &'a [Instr]),
+    /// End of the body or of a `catch` clause of a try started at the given offset. The remaining
+    /// `catch` clauses and the `catch_all` clause come next.
+    Try(usize, &'a [CatchBranch], Option<&'a [Instr]>),
+}
+
+/// Type checks a single function body.
+struct FuncValidator<'a> {
+    ctx: &'a Context<'a>,
+    locals: Vec<ValType>,
+    /// Whether each local is initialized. Only locals of non-defaultable types start
+    /// uninitialized, until they are set.
+    initialized: Vec<bool>,
+    /// Locals initialized within the current blocks, which are uninitialized again at their end.
+    inits: Vec<usize>,
+    vals: Vec<Operand>,
+    ctrls: Vec<Frame>,
+    /// Offset of the next instruction to be validated.
+    offset: usize,
+}
+
+impl<'a> FuncValidator<'a> {
+    fn new(ctx: &'a Context<'a>, tpe: &FuncType, locals: &[ValType]) -> FuncValidator<'a> {
+        let mut validator = FuncValidator {
+            ctx,
+            locals: tpe
+                .params
+                .types
+                .iter()
+                .chain(locals.iter())
+                .copied()
+                .collect(),
+            initialized: tpe
+                .params
+                .types
+                .iter()
+                .map(|_| true)
+                .chain(locals.iter().map(ValType::is_defaultable))
+                .collect(),
+            inits: Vec::new(),
+            vals: Vec::new(),
+            ctrls: Vec::new(),
+            offset: 0,
+        };
+        validator.push_ctrl(FrameKind::Func, Vec::new(), tpe.result.types.clone());
+        validator
+    }
+
+    fn push(&mut self, tpe: Operand) {
+        self.vals.push(tpe);
+    }
+
+    fn pop(&mut self) -> Result<Operand, ValidationError> {
+        let frame = self.ctrls.last().expect("Control stack can't be empty");
+        if self.vals.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return err(ValidationErrorKind::EmptyStack);
+        }
+        Ok(self.vals.pop().expect("Stack is above the frame height"))
+    }
+
+    fn pop_expect(&mut self, expect: Operand) -> Result<Operand, ValidationError> {
+        let actual = self.pop()?;
+        match (actual, expect) {
+            (None, expect) => Ok(expect),
+            (actual, None) => Ok(actual),
+            (Some(actual), Some(expect)) if self.ctx.matches(actual, expect) => Ok(Some(actual)),
+            (Some(actual), Some(expected)) => {
+                err(ValidationErrorKind::TypeMismatch { expected, actual })
+            }
+        }
+    }
+
+    fn pop_ref(&mut self) -> Result<Operand, ValidationError> {
+        match self.pop()? {
+            None => Ok(None),
+            Some(ValType::Ref(t)) => Ok(Some(ValType::Ref(t))),
+            Some(t) => err(ValidationErrorKind::ExpectedRef(t)),
+        }
+    }
+
+    fn push_vals(&mut self, types: &[ValType]) {
+        for tpe in types {
+            self.push(Some(*tpe));
+        }
+    }
+
+    fn pop_vals(&mut self, types: &[ValType]) -> Result<(), ValidationError> {
+        for tpe in types.iter().rev() {
+            self.pop_expect(Some(*tpe))?;
+        }
+        Ok(())
+    }
+
+    fn push_ctrl(&mut self, kind: FrameKind, start: Vec<ValType>, end: Vec<ValType>) {
+        let height = self.vals.len();
+        self.push_vals(&start);
+        self.ctrls.push(Frame {
+            kind,
+            start,
+            end,
+            height,
+            inits: self.inits.len(),
+            unreachable: false,
+        });
+    }
+
+    fn pop_ctrl(&mut self) -> Result<Frame, ValidationError> {
+        let end = self
+            .ctrls
+            .last()
+            .expect("Control stack can't be empty")
+            .end
+            .clone();
+        self.pop_vals(&end)?;
+        let frame = self.ctrls.pop().unwrap();
+        for local in self.inits.drain(frame.inits..) {
+            self.initialized[local] = false;
+        }
+        if self.vals.len() != frame.height {
+            return err(ValidationErrorKind::ValuesRemaining(
+                self.vals[frame.height..].to_vec(),
+            ));
+        }
+        Ok(frame)
+    }
+
+    fn label(&self, label: LabelIdx) -> Result<Vec<ValType>, ValidationError> {
+        let depth = *label.0 as usize;
+        if depth >= self.ctrls.len() {
+            return err(ValidationErrorKind::UnknownLabel(*label.0));
+        }
+        Ok(self.ctrls[self.ctrls.len() - 1 - depth]
+            .label_types()
+            .to_vec())
+    }
+
+    /// Checks the table of an indirect call and pops the index into it. Returns the type of the
+    /// callee.
+    fn call_indirect(&mut self, x: TableIdx, y: TypeIdx) -> Result<&'a FuncType, ValidationError> {
+        let table = self.ctx.table(x)?;
+        if !self.ctx.ref_matches(table.tpe, RefType::FUNCREF) {
+            return err(ValidationErrorKind::NotFuncRefTable {
+                table: *x.0,
+                tpe: table.tpe,
+            });
+        }
+        let addr = addr_type(table.index);
+        let tpe = self.ctx.tpe(y)?;
+        self.pop_expect(Some(addr))?;
+        Ok(tpe)
+    }
+
+    /// Type checks a tail call to a function of type `tpe`, whose results become the results of
+    /// the current function.
+    fn return_call(&mut self, tpe: &FuncType) -> Result<(), ValidationError> {
+        if !self.ctx.all_match(&tpe.result.types, &self.ctrls[0].end) {
+            return err(ValidationErrorKind::TailCallMismatch {
+                expected: self.ctrls[0].end.clone(),
+                actual: tpe.result.types.clone(),
+            });
+        }
+        self.pop_vals(&tpe.params.types)?;
+        self.unreachable();
+        Ok(())
+    }
+
+    fn unreachable(&mut self) {
+        let frame = self.ctrls.last_mut().expect("Control stack can't be empty");
+        self.vals.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn local(&self, idx: LocalIdx) -> Result<ValType, ValidationError> {
+        match self.locals.get(*idx.0 as usize) {
+            Some(tpe) => Ok(*tpe),
+            None => err(ValidationErrorKind::UnknownLocal(*idx.0)),
+        }
+    }
+
+    /// Marks a local as initialized until the end of the current block.
+    fn init_local(&mut self, idx: LocalIdx) {
+        let idx = *idx.0 as usize;
+        if !self.initialized[idx] {
+            self.initialized[idx] = true;
+            self.inits.push(idx);
+        }
+    }
+
+    /// Checks the label of a branch on a reference, whose last type must be a reference type.
+    /// Returns the other types of the label and the reference type.
+    fn ref_label(&self, label: LabelIdx) -> Result<(Vec<ValType>, RefType), ValidationError> {
+        let mut types = self.label(label)?;
+        match types.pop() {
+            Some(ValType::Ref(tpe)) => Ok((types, tpe)),
+            _ => err(ValidationErrorKind::LabelNotRef(*label.0)),
+        }
+    }
+
+    /// Validates the whole function body.
+    fn validate_body(mut self, body: &Expr) -> Result<(), ValidationError> {
+        for tpe in &self.locals {
+            self.ctx.val_type(*tpe)?;
+        }
+        self.validate_instrs(&body.instr)?;
+        let offset = self.offset;
+        self.pop_ctrl().map_err(|err| err.at_instr(offset))?;
+        Ok(())
+    }
+
+    /// Validates a sequence of instructions.
+    ///
+    /// Blocks are walked with an explicit stack rather than recursion so that deeply nested
+    /// bodies can't overflow the call stack.
+    fn validate_instrs(&mut self, instrs: &[Instr]) -> Result<(), ValidationError> {
+        let mut pending: Vec<(std::slice::Iter<Instr>, BlockEnd)> =
+            vec![(instrs.iter(), BlockEnd::Seq)];
+        while let Some((iter, _)) = pending.last_mut() {
+            let instr = match iter.next() {
+                Some(instr) => instr,
+                None => {
+                    match pending.pop().unwrap().1 {
+                        BlockEnd::Seq => {}
+                        BlockEnd::Block(offset) => {
+                            let frame = self.pop_ctrl().map_err(|err| err.at_instr(offset))?;
+                            self.push_vals(&frame.end);
+                        }
+                        BlockEnd::Then(offset, else_br) => {
+                            let frame = self.pop_ctrl().map_err(|err| err.at_instr(offset))?;
+                            // A missing else branch behaves like an empty one, which requires the
+                            // block's parameters to match its results.
+                            self.push_ctrl(FrameKind::Else, frame.start, frame.end);
+                            pending.push((else_br.iter(), BlockEnd::Block(offset)));
+                        }
+                        BlockEnd::Try(offset, catches, catch_all) => {
+                            let frame = self.pop_ctrl().map_err(|err| err.at_instr(offset))?;
+                            // Each clause starts with the values carried by the exception.
+                            if let Some((catch, rest)) = catches.split_first() {
+                                let tpe = self
+                                    .ctx
+                                    .tag(catch.tag)
+                                    .map_err(|err| err.at_instr(offset))?;
+                                self.push_ctrl(
+                                    FrameKind::Catch,
+                                    tpe.params.types.clone(),
+                                    frame.end,
+                                );
+                                let end = BlockEnd::Try(offset, rest, catch_all);
+                                pending.push((catch.instr.iter(), end));
+                            } else if let Some(instr) = catch_all {
+                                self.push_ctrl(FrameKind::Catch, Vec::new(), frame.end);
+                                pending.push((instr.iter(), BlockEnd::Block(offset)));
+                            } else {
+                                self.push_vals(&frame.end);
+                            }
+                        }
+                    }
+                    continue;
+                }
+            };
+            let offset = self.offset;
+            self.offset += 1;
+            match instr {
+                Instr::Control(ControlInstr::Block(block)) => {
+                    self.enter_block(FrameKind::Block, &block.tpe)
+                        .map_err(|err| err.at_instr(offset))?;
+                    pending.push((block.instr.iter(), BlockEnd::Block(offset)));
+                }
+                Instr::Control(ControlInstr::Loop(block)) => {
+                    self.enter_block(FrameKind::Loop, &block.tpe)
+                        .map_err(|err| err.at_instr(offset))?;
+                    pending.push((block.instr.iter(), BlockEnd::Block(offset)));
+                }
+                Instr::Control(ControlInstr::If(block)) => {
+                    self.pop_expect(Some(I32))
+                        .and_then(|_| self.enter_block(FrameKind::If, &block.tpe))
+                        .map_err(|err| err.at_instr(offset))?;
+                    pending.push((block.if_br.iter(), BlockEnd::Then(offset, &block.else_br)));
+                }
+                Instr::Control(ControlInstr::Try(block)) => {
+                    let end = match &block.handler {
+                        TryHandler::Catch { catches, catch_all } => {
+                            BlockEnd::Try(offset, catches, catch_all.as_deref())
+                        }
+                        // The label of `delegate` is outside of the block.
+                        TryHandler::Delegate(l) => {
+                            self.label(*l).map_err(|err| err.at_instr(offset))?;
+                            BlockEnd::Block(offset)
+                        }
+                    };
+                    self.enter_block(FrameKind::Try, &block.tpe)
+                        .map_err(|err| err.at_instr(offset))?;
+                    pending.push((block.instr.iter(), end));
+                }
+                Instr::Control(ControlInstr::TryTable(block)) => {
+                    // The handlers branch to labels outside of the block.
+                    block
+                        .catches
+                        .iter()
+                        .try_for_each(|catch| self.validate_catch(catch))
+                        .and_then(|_| self.enter_block(FrameKind::Block, &block.tpe))
+                        .map_err(|err| err.at_instr(offset))?;
+                    pending.push((block.instr.iter(), BlockEnd::Block(offset)));
+                }
+                instr => self
+                    .validate_instr(instr)
+                    .map_err(|err| err.at_instr(offset))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the values passed by a handler of `try_table` match the types of its label.
+    fn validate_catch(&self, catch: &Catch) -> Result<(), ValidationError> {
+        let (mut types, label) = match catch {
+            Catch::Catch(x, l) | Catch::CatchRef(x, l) => {
+                (self.ctx.tag(*x)?.params.types.clone(), l)
+            }
+            Catch::CatchAll(l) | Catch::CatchAllRef(l) => (Vec::new(), l),
+        };
+        if let Catch::CatchRef(..) | Catch::CatchAllRef(_) = catch {
+            types.push(EXNREF);
+        }
+        let expected = self.label(*label)?;
+        if !self.ctx.all_match(&types, &expected) {
+            return err(ValidationErrorKind::LabelMismatch {
+                label: *label.0,
+                expected,
+                actual: types,
+            });
+        }
+        Ok(())
+    }
+
+    /// Pops the block parameters and pushes a new control frame for it.
+    fn enter_block(&mut self, kind: FrameKind, tpe: &BlockType) -> Result<(), ValidationError> {
+        let (start, end) = self.ctx.block_type(tpe)?;
+        self.pop_vals(&start)?;
+        self.push_ctrl(kind, start, end);
+        Ok(())
+    }
+
+    fn constop(&mut self, tpe: ValType) -> Result<(), ValidationError> {
+        self.push(Some(tpe));
+        Ok(())
+    }
+
+    fn unop(&mut self, tpe: ValType) -> Result<(), ValidationError> {
+        self.pop_expect(Some(tpe))?;
+        self.push(Some(tpe));
+        Ok(())
+    }
+
+    fn binop(&mut self, tpe: ValType) -> Result<(), ValidationError> {
+        self.pop_expect(Some(tpe))?;
+        self.pop_expect(Some(tpe))?;
+        self.push(Some(tpe));
+        Ok(())
+    }
+
+    fn testop(&mut self, tpe: ValType) -> Result<(), ValidationError> {
+        self.pop_expect(Some(tpe))?;
+        self.push(Some(I32));
+        Ok(())
+    }
+
+    fn relop(&mut self, tpe: ValType) -> Result<(), ValidationError> {
+        self.pop_expect(Some(tpe))?;
+        self.pop_expect(Some(tpe))?;
+        self.push(Some(I32));
+        Ok(())
+    }
+
+    fn cvtop(&mut self, from: ValType, to: ValType) -> Result<(), ValidationError> {
+        self.pop_expect(Some(from))?;
+        self.push(Some(to));
+        Ok(())
+    }
+
+    fn load(&mut self, memarg: &MemArg, bits: u32, tpe: ValType) -> Result<(), ValidationError> {
+        let addr = self.memarg(memarg, bits)?;
+        self.pop_expect(Some(addr))?;
+        self.push(Some(tpe));
+        Ok(())
+    }
+
+    fn store(&mut self, memarg: &MemArg, bits: u32, tpe: ValType) -> Result<(), ValidationError> {
+        let addr = self.memarg(memarg, bits)?;
+        self.pop_expect(Some(tpe))?;
+        self.pop_expect(Some(addr))?;
+        Ok(())
+    }
+
+    /// Checks that the memory exists and that the offset fits its addresses. Returns the type of
+    /// the addresses.
+    fn mem_offset(&self, memarg: &MemArg) -> Result<ValType, ValidationError> {
+        let tpe = self.ctx.mem(memarg.mem)?;
+        if tpe.index == IndexType::I32 && *memarg.offset > u64::from(u32::MAX) {
+            return err(ValidationErrorKind::OffsetOutOfRange(*memarg.offset));
+        }
+        Ok(addr_type(tpe.index))
+    }
+
+    /// Checks that the memory exists and that the alignment is not larger than the natural
+    /// alignment of an access of `bits` width. Returns the type of the addresses.
+    fn memarg(&self, memarg: &MemArg, bits: u32) -> Result<ValType, ValidationError> {
+        let addr = self.mem_offset(memarg)?;
+        let natural = (bits / 8).trailing_zeros();
+        if *memarg.align > natural {
+            return err(ValidationErrorKind::AlignmentTooLarge {
+                align: *memarg.align,
+                natural,
+            });
+        }
+        Ok(addr)
+    }
+
+    /// Checks that the memory exists and that the alignment of an atomic access of `bits` width is
+    /// exactly its natural alignment. Returns the type of the addresses.
+    fn atomic_memarg(&self, memarg: &MemArg, bits: u32) -> Result<ValType, ValidationError> {
+        let addr = self.mem_offset(memarg)?;
+        let natural = (bits / 8).trailing_zeros();
+        if *memarg.align != natural {
+            return err(ValidationErrorKind::AtomicAlignment {
+                align: *memarg.align,
+                natural,
+            });
+        }
+        Ok(addr)
+    }
+
+    /// Checks that a lane immediate designates one of the `lanes` lanes.
+    fn lane(&self, lane: LaneIdx, lanes: u8) -> Result<(), ValidationError> {
+        if *lane.0 >= lanes {
+            return err(ValidationErrorKind::LaneOutOfRange {
+                lane: *lane.0,
+                lanes,
+            });
+        }
+        Ok(())
+    }
+
+    fn validate_instr(&mut self, instr: &Instr) -> Result<(), ValidationError> {
+        match instr {
+            Instr::Numeric(instr) => self.validate_numeric(instr),
+            Instr::Vector(instr) => self.validate_vector(instr),
+            Instr::Atomic(instr) => self.validate_atomic(instr),
+            Instr::Reference(instr) => self.validate_reference(instr),
+            Instr::Aggregate(instr) => self.validate_aggregate(instr),
+            Instr::Dropp => {
+                self.pop()?;
+                Ok(())
+            }
+            Instr::Select(types) => self.validate_select(types),
+            Instr::Variable(instr) => self.validate_variable(instr),
+            Instr::Table(instr) => self.validate_table(instr),
+            Instr::Memory(instr) => self.validate_memory(instr),
+            Instr::Control(instr) => self.validate_control(instr),
+        }
+    }
+
+    fn validate_numeric(&mut self, instr: &NumericInstr) -> Result<(), ValidationError> {
+        match instr {
+            NumericInstr::I32Const(_) => self.constop(I32),
+            NumericInstr::I64Const(_) => self.constop(I64),
+            NumericInstr::F32Const(_) => self.constop(F32),
+            NumericInstr::F64Const(_) => self.constop(F64),
+            NumericInstr::I32Unary(_) => self.unop(I32),
+            NumericInstr::I64Unary(_) => self.unop(I64),
+            NumericInstr::F32Unary(_) => self.unop(F32),
+            NumericInstr::F64Unary(_) => self.unop(F64),
+            NumericInstr::I32Binary(_) => self.binop(I32),
+            NumericInstr::I64Binary(_) => self.binop(I64),
+            NumericInstr::F32Binary(_) => self.binop(F32),
+            NumericInstr::F64Binary(_) => self.binop(F64),
+            NumericInstr::I32Test(_) => self.testop(I32),
+            NumericInstr::I64Test(_) => self.testop(I64),
+            NumericInstr::I32Relop(_) => self.relop(I32),
+            NumericInstr::I64Relop(_) => self.relop(I64),
+            NumericInstr::F32Relop(_) => self.relop(F32),
+            NumericInstr::F64Relop(_) => self.relop(F64),
+            NumericInstr::I32Extend8S | NumericInstr::I32Extend16S => self.unop(I32),
+            NumericInstr::I64Extend8S | NumericInstr::I64Extend16S | NumericInstr::I64Extend32S => {
+                self.unop(I64)
+            }
+            NumericInstr::I32WrapI64 => self.cvtop(I64, I32),
+            NumericInstr::I64ExtendI32S | NumericInstr::I64ExtendI32U => self.cvtop(I32, I64),
+            NumericInstr::I32TruncF32S
+            | NumericInstr::I32TruncF32U
+            | NumericInstr::I32TruncSatF32S
+            | NumericInstr::I32TruncSatF32U
+            | NumericInstr::I32ReinterpretF32 => self.cvtop(F32, I32),
+            NumericInstr::I32TruncF64S
+            | NumericInstr::I32TruncF64U
+            | NumericInstr::I32TruncSatF64S
+            | NumericInstr::I32TruncSatF64U => self.cvtop(F64, I32),
+            NumericInstr::I64TruncF32S
+            | NumericInstr::I64TruncF32U
+            | NumericInstr::I64TruncSatF32S
+            | NumericInstr::I64TruncSatF32U => self.cvtop(F32, I64),
+            NumericInstr::I64TruncF64S
+            | NumericInstr::I64TruncF64U
+            | NumericInstr::I64TruncSatF64S
+            | NumericInstr::I64TruncSatF64U
+            | NumericInstr::I64ReinterpretF64 => self.cvtop(F64, I64),
+            NumericInstr::F32DemoteF64 => self.cvtop(F64, F32),
+            NumericInstr::F64PromoteF32 => self.cvtop(F32, F64),
+            NumericInstr::F32ConvertI32S
+            | NumericInstr::F32ConvertI32U
+            | NumericInstr::F32ReinterpretI32 => self.cvtop(I32, F32),
+            NumericInstr::F32ConvertI64S | NumericInstr::F32ConvertI64U => self.cvtop(I64, F32),
+            NumericInstr::F64ConvertI32S | NumericInstr::F64ConvertI32U => self.cvtop(I32, F64),
+            NumericInstr::F64ConvertI64S
+            | NumericInstr::F64ConvertI64U
+            | NumericInstr::F64ReinterpretI64 => self.cvtop(I64, F64),
+        }
+    }
+
+    fn validate_vector(&mut self, instr: &VectorInstr) -> Result<(), ValidationError> {
+        match instr {
+            VectorInstr::V128Const(_) => self.constop(V128),
+            VectorInstr::V128Load(m) => self.load(m, 128, V128),
+            VectorInstr::V128Load8x8S(m) => self.load(m, 64, V128),
+            VectorInstr::V128Load8x8U(m) => self.load(m, 64, V128),
+            VectorInstr::V128Load16x4S(m) => self.load(m, 64, V128),
+            VectorInstr::V128Load16x4U(m) => self.load(m, 64, V128),
+            VectorInstr::V128Load32x2S(m) => self.load(m, 64, V128),
+            VectorInstr::V128Load32x2U(m) => self.load(m, 64, V128),
+            VectorInstr::V128Load8Splat(m) => self.load(m, 8, V128),
+            VectorInstr::V128Load16Splat(m) => self.load(m, 16, V128),
+            VectorInstr::V128Load32Splat(m) => self.load(m, 32, V128),
+            VectorInstr::V128Load64Splat(m) => self.load(m, 64, V128),
+            VectorInstr::V128Load32Zero(m) => self.load(m, 32, V128),
+            VectorInstr::V128Load64Zero(m) => self.load(m, 64, V128),
+            VectorInstr::V128Store(m) => self.store(m, 128, V128),
+            VectorInstr::V128Load8Lane(m, lane) => {
+                self.lane(*lane, 16)?;
+                let addr = self.memarg(m, 8)?;
+                self.pop_vals(&[addr, V128])?;
+                self.push(Some(V128));
+                Ok(())
+            }
+            VectorInstr::V128Load16Lane(m, lane) => {
+                self.lane(*lane, 8)?;
+                let addr = self.memarg(m, 16)?;
+                self.pop_vals(&[addr, V128])?;
+                self.push(Some(V128));
+                Ok(())
+            }
+            VectorInstr::V128Load32Lane(m, lane) => {
+                self.lane(*lane, 4)?;
+                let addr = self.memarg(m, 32)?;
+                self.pop_vals(&[addr, V128])?;
+                self.push(Some(V128));
+                Ok(())
+            }
+            VectorInstr::V128Load64Lane(m, lane) => {
+                self.lane(*lane, 2)?;
+                let addr = self.memarg(m, 64)?;
+                self.pop_vals(&[addr, V128])?;
+                self.push(Some(V128));
+                Ok(())
+            }
+            VectorInstr::V128Store8Lane(m, lane) => {
+                self.lane(*lane, 16)?;
+                let addr = self.memarg(m, 8)?;
+                self.pop_vals(&[addr, V128])?;
+                Ok(())
+            }
+            VectorInstr::V128Store16Lane(m, lane) => {
+                self.lane(*lane, 8)?;
+                let addr = self.memarg(m, 16)?;
+                self.pop_vals(&[addr, V128])?;
+                Ok(())
+            }
+            VectorInstr::V128Store32Lane(m, lane) => {
+                self.lane(*lane, 4)?;
+                let addr = self.memarg(m, 32)?;
+                self.pop_vals(&[addr, V128])?;
+                Ok(())
+            }
+            VectorInstr::V128Store64Lane(m, lane) => {
+                self.lane(*lane, 2)?;
+                let addr = self.memarg(m, 64)?;
+                self.pop_vals(&[addr, V128])?;
+                Ok(())
+            }
+            VectorInstr::I8x16Shuffle(lanes) => {
+                for lane in lanes {
+                    self.lane(*lane, 32)?;
+                }
+                self.binop(V128)
+            }
+            VectorInstr::I8x16Splat => self.cvtop(I32, V128),
+            VectorInstr::I16x8Splat => self.cvtop(I32, V128),
+            VectorInstr::I32x4Splat => self.cvtop(I32, V128),
+            VectorInstr::I64x2Splat => self.cvtop(I64, V128),
+            VectorInstr::F32x4Splat => self.cvtop(F32, V128),
+            VectorInstr::F64x2Splat => self.cvtop(F64, V128),
+            VectorInstr::I8x16ExtractLaneS(lane) => {
+                self.lane(*lane, 16)?;
+                self.cvtop(V128, I32)
+            }
+            VectorInstr::I8x16ExtractLaneU(lane) => {
+                self.lane(*lane, 16)?;
+                self.cvtop(V128, I32)
+            }
+            VectorInstr::I16x8ExtractLaneS(lane) => {
+                self.lane(*lane, 8)?;
+                self.cvtop(V128, I32)
+            }
+            VectorInstr::I16x8ExtractLaneU(lane) => {
+                self.lane(*lane, 8)?;
+                self.cvtop(V128, I32)
+            }
+            VectorInstr::I32x4ExtractLane(lane) => {
+                self.lane(*lane, 4)?;
+                self.cvtop(V128, I32)
+            }
+            VectorInstr::I64x2ExtractLane(lane) => {
+                self.lane(*lane, 2)?;
+                self.cvtop(V128, I64)
+            }
+            VectorInstr::F32x4ExtractLane(lane) => {
+                self.lane(*lane, 4)?;
+                self.cvtop(V128, F32)
+            }
+            VectorInstr::F64x2ExtractLane(lane) => {
+                self.lane(*lane, 2)?;
+                self.cvtop(V128, F64)
+            }
+            VectorInstr::I8x16ReplaceLane(lane) => {
+                self.lane(*lane, 16)?;
+                self.pop_expect(Some(I32))?;
+                self.unop(V128)
+            }
+            VectorInstr::I16x8ReplaceLane(lane) => {
+                self.lane(*lane, 8)?;
+                self.pop_expect(Some(I32))?;
+                self.unop(V128)
+            }
+            VectorInstr::I32x4ReplaceLane(lane) => {
+                self.lane(*lane, 4)?;
+                self.pop_expect(Some(I32))?;
+                self.unop(V128)
+            }
+            VectorInstr::I64x2ReplaceLane(lane) => {
+                self.lane(*lane, 2)?;
+                self.pop_expect(Some(I64))?;
+                self.unop(V128)
+            }
+            VectorInstr::F32x4ReplaceLane(lane) => {
+                self.lane(*lane, 4)?;
+                self.pop_expect(Some(F32))?;
+                self.unop(V128)
+            }
+            VectorInstr::F64x2ReplaceLane(lane) => {
+                self.lane(*lane, 2)?;
+                self.pop_expect(Some(F64))?;
+                self.unop(V128)
+            }
+            VectorInstr::V128AnyTrue
+            | VectorInstr::I8x16AllTrue
+            | VectorInstr::I8x16Bitmask
+            | VectorInstr::I16x8AllTrue
+            | VectorInstr::I16x8Bitmask
+            | VectorInstr::I32x4AllTrue
+            | VectorInstr::I32x4Bitmask
+            | VectorInstr::I64x2AllTrue
+            | VectorInstr::I64x2Bitmask => self.testop(V128),
+            VectorInstr::I8x16Shl
+            | VectorInstr::I8x16ShrS
+            | VectorInstr::I8x16ShrU
+            | VectorInstr::I16x8Shl
+            | VectorInstr::I16x8ShrS
+            | VectorInstr::I16x8ShrU
+            | VectorInstr::I32x4Shl
+            | VectorInstr::I32x4ShrS
+            | VectorInstr::I32x4ShrU
+            | VectorInstr::I64x2Shl
+            | VectorInstr::I64x2ShrS
+            | VectorInstr::I64x2ShrU => {
+                self.pop_expect(Some(I32))?;
+                self.unop(V128)
+            }
+            VectorInstr::V128Bitselect => {
+                self.pop_expect(Some(V128))?;
+                self.binop(V128)
+            }
+            VectorInstr::V128Not
+            | VectorInstr::F32x4DemoteF64x2Zero
+            | VectorInstr::F64x2PromoteLowF32x4
+            | VectorInstr::I8x16Abs
+            | VectorInstr::I8x16Neg
+            | VectorInstr::I8x16Popcnt
+            | VectorInstr::F32x4Ceil
+            | VectorInstr::F32x4Floor
+            | VectorInstr::F32x4Trunc
+            | VectorInstr::F32x4Nearest
+            | VectorInstr::F64x2Ceil
+            | VectorInstr::F64x2Floor
+            | VectorInstr::F64x2Trunc
+            | VectorInstr::I16x8ExtaddPairwiseI8x16S
+            | VectorInstr::I16x8ExtaddPairwiseI8x16U
+            | VectorInstr::I32x4ExtaddPairwiseI16x8S
+            | VectorInstr::I32x4ExtaddPairwiseI16x8U
+            | VectorInstr::I16x8Abs
+            | VectorInstr::I16x8Neg
+            | VectorInstr::I16x8ExtendLowI8x16S
+            | VectorInstr::I16x8ExtendHighI8x16S
+            | VectorInstr::I16x8ExtendLowI8x16U
+            | VectorInstr::I16x8ExtendHighI8x16U
+            | VectorInstr::F64x2Nearest
+            | VectorInstr::I32x4Abs
+            | VectorInstr::I32x4Neg
+            | VectorInstr::I32x4ExtendLowI16x8S
+            | VectorInstr::I32x4ExtendHighI16x8S
+            | VectorInstr::I32x4ExtendLowI16x8U
+            | VectorInstr::I32x4ExtendHighI16x8U
+            | VectorInstr::I64x2Abs
+            | VectorInstr::I64x2Neg
+            | VectorInstr::I64x2ExtendLowI32x4S
+            | VectorInstr::I64x2ExtendHighI32x4S
+            | VectorInstr::I64x2ExtendLowI32x4U
+            | VectorInstr::I64x2ExtendHighI32x4U
+            | VectorInstr::F32x4Abs
+            | VectorInstr::F32x4Neg
+            | VectorInstr::F32x4Sqrt
+            | VectorInstr::F64x2Abs
+            | VectorInstr::F64x2Neg
+            | VectorInstr::F64x2Sqrt
+            | VectorInstr::I32x4TruncSatF32x4S
+            | VectorInstr::I32x4TruncSatF32x4U
+            | VectorInstr::F32x4ConvertI32x4S
+            | VectorInstr::F32x4ConvertI32x4U
+            | VectorInstr::I32x4TruncSatF64x2SZero
+            | VectorInstr::I32x4TruncSatF64x2UZero
+            | VectorInstr::F64x2ConvertLowI32x4S
+            | VectorInstr::F64x2ConvertLowI32x4U => self.unop(V128),
+            // The remaining instructions are lane-wise binary or comparison operators.
+            _ => self.binop(V128),
+        }
+    }
+
+    fn validate_atomic(&mut self, instr: &AtomicInstr) -> Result<(), ValidationError> {
+        use AtomicInstr::*;
+        // The operands that follow the address, whose type depends on the memory.
+        let (m, bits, params, result): (_, _, &[ValType], _) = match instr {
+            AtomicFence => return Ok(()),
+            MemoryAtomicNotify(m) => (m, 32, &[I32], Some(I32)),
+            MemoryAtomicWait32(m) => (m, 32, &[I32, I64], Some(I32)),
+            MemoryAtomicWait64(m) => (m, 64, &[I64, I64], Some(I32)),
+            I32AtomicLoad(m) => (m, 32, &[], Some(I32)),
+            I64AtomicLoad(m) => (m, 64, &[], Some(I64)),
+            I32AtomicLoad8U(m) => (m, 8, &[], Some(I32)),
+            I32AtomicLoad16U(m) => (m, 16, &[], Some(I32)),
+            I64AtomicLoad8U(m) => (m, 8, &[], Some(I64)),
+            I64AtomicLoad16U(m) => (m, 16, &[], Some(I64)),
+            I64AtomicLoad32U(m) => (m, 32, &[], Some(I64)),
+            I32AtomicStore(m) => (m, 32, &[I32], None),
+            I64AtomicStore(m) => (m, 64, &[I64], None),
+            I32AtomicStore8(m) => (m, 8, &[I32], None),
+            I32AtomicStore16(m) => (m, 16, &[I32], None),
+            I64AtomicStore8(m) => (m, 8, &[I64], None),
+            I64AtomicStore16(m) => (m, 16, &[I64], None),
+            I64AtomicStore32(m) => (m, 32, &[I64], None),
+            I32AtomicRmwAdd(m) => (m, 32, &[I32], Some(I32)),
+            I64AtomicRmwAdd(m) => (m, 64, &[I64], Some(I64)),
+            I32AtomicRmw8AddU(m) => (m, 8, &[I32], Some(I32)),
+            I32AtomicRmw16AddU(m) => (m, 16, &[I32], Some(I32)),
+            I64AtomicRmw8AddU(m) => (m, 8, &[I64], Some(I64)),
+            I64AtomicRmw16AddU(m) => (m, 16, &[I64], Some(I64)),
+            I64AtomicRmw32AddU(m) => (m, 32, &[I64], Some(I64)),
+            I32AtomicRmwSub(m) => (m, 32, &[I32], Some(I32)),
+            I64AtomicRmwSub(m) => (m, 64, &[I64], Some(I64)),
+            I32AtomicRmw8SubU(m) => (m, 8, &[I32], Some(I32)),
+            I32AtomicRmw16SubU(m) => (m, 16, &[I32], Some(I32)),
+            I64AtomicRmw8SubU(m) => (m, 8, &[I64], Some(I64)),
+            I64AtomicRmw16SubU(m) => (m, 16, &[I64], Some(I64)),
+            I64AtomicRmw32SubU(m) => (m, 32, &[I64], Some(I64)),
+            I32AtomicRmwAnd(m) => (m, 32, &[I32], Some(I32)),
+            I64AtomicRmwAnd(m) => (m, 64, &[I64], Some(I64)),
+            I32AtomicRmw8AndU(m) => (m, 8, &[I32], Some(I32)),
+            I32AtomicRmw16AndU(m) => (m, 16, &[I32], Some(I32)),
+            I64AtomicRmw8AndU(m) => (m, 8, &[I64], Some(I64)),
+            I64AtomicRmw16AndU(m) => (m, 16, &[I64], Some(I64)),
+            I64AtomicRmw32AndU(m) => (m, 32, &[I64], Some(I64)),
+            I32AtomicRmwOr(m) => (m, 32, &[I32], Some(I32)),
+            I64AtomicRmwOr(m) => (m, 64, &[I64], Some(I64)),
+            I32AtomicRmw8OrU(m) => (m, 8, &[I32], Some(I32)),
+            I32AtomicRmw16OrU(m) => (m, 16, &[I32], Some(I32)),
+            I64AtomicRmw8OrU(m) => (m, 8, &[I64], Some(I64)),
+            I64AtomicRmw16OrU(m) => (m, 16, &[I64], Some(I64)),
+            I64AtomicRmw32OrU(m) => (m, 32, &[I64], Some(I64)),
+            I32AtomicRmwXor(m) => (m, 32, &[I32], Some(I32)),
+            I64AtomicRmwXor(m) => (m, 64, &[I64], Some(I64)),
+            I32AtomicRmw8XorU(m) => (m, 8, &[I32], Some(I32)),
+            I32AtomicRmw16XorU(m) => (m, 16, &[I32], Some(I32)),
+            I64AtomicRmw8XorU(m) => (m, 8, &[I64], Some(I64)),
+            I64AtomicRmw16XorU(m) => (m, 16, &[I64], Some(I64)),
+            I64AtomicRmw32XorU(m) => (m, 32, &[I64], Some(I64)),
+            I32AtomicRmwXchg(m) => (m, 32, &[I32], Some(I32)),
+            I64AtomicRmwXchg(m) => (m, 64, &[I64], Some(I64)),
+            I32AtomicRmw8XchgU(m) => (m, 8, &[I32], Some(I32)),
+            I32AtomicRmw16XchgU(m) => (m, 16, &[I32], Some(I32)),
+            I64AtomicRmw8XchgU(m) => (m, 8, &[I64], Some(I64)),
+            I64AtomicRmw16XchgU(m) => (m, 16, &[I64], Some(I64)),
+            I64AtomicRmw32XchgU(m) => (m, 32, &[I64], Some(I64)),
+            I32AtomicRmwCmpxchg(m) => (m, 32, &[I32, I32], Some(I32)),
+            I64AtomicRmwCmpxchg(m) => (m, 64, &[I64, I64], Some(I64)),
+            I32AtomicRmw8CmpxchgU(m) => (m, 8, &[I32, I32], Some(I32)),
+            I32AtomicRmw16CmpxchgU(m) => (m, 16, &[I32, I32], Some(I32)),
+            I64AtomicRmw8CmpxchgU(m) => (m, 8, &[I64, I64], Some(I64)),
+            I64AtomicRmw16CmpxchgU(m) => (m, 16, &[I64, I64], Some(I64)),
+            I64AtomicRmw32CmpxchgU(m) => (m, 32, &[I64, I64], Some(I64)),
+        };
+        let addr = self.atomic_memarg(m, bits)?;
+        self.pop_vals(params)?;
+        self.pop_expect(Some(addr))?;
+        if let Some(tpe) = result {
+            self.push(Some(tpe));
+        }
+        Ok(())
+    }
+
+    fn validate_reference(&mut self, instr: &ReferenceInstr) -> Result<(), ValidationError> {
+        match instr {
+            ReferenceInstr::RefNull(t) => {
+                self.ctx.heap_type(*t)?;
+                self.push(Some(ref_type(true, *t)));
+            }
+            ReferenceInstr::RefIsNull => {
+                self.pop_ref()?;
+                self.push(Some(I32));
+            }
+            ReferenceInstr::RefFunc(x) => {
+                self.ctx.func(*x)?;
+                if !self.ctx.refs.contains(&*x.0) {
+                    return err(ValidationErrorKind::UndeclaredFuncRef(*x.0));
+                }
+                let tpe = self.ctx.funcs[*x.0 as usize];
+                self.push(Some(ref_type(false, HeapType::Concrete(tpe))));
+            }
+            ReferenceInstr::RefEq => {
+                self.pop_vals(&[ref_type(true, HeapType::Eq), ref_type(true, HeapType::Eq)])?;
+                self.push(Some(I32));
+            }
+            ReferenceInstr::RefAsNonNull => match self.pop_ref()? {
+                Some(ValType::Ref(tpe)) => self.push(Some(ref_type(false, tpe.heap))),
+                tpe => self.push(tpe),
+            },
+            ReferenceInstr::RefTest(rt) => {
+                self.ctx.ref_type(*rt)?;
+                self.pop_expect(Some(ref_type(true, self.ctx.top(rt.heap))))?;
+                self.push(Some(I32));
+            }
+            ReferenceInstr::RefCast(rt) => {
+                self.ctx.ref_type(*rt)?;
+                self.pop_expect(Some(ref_type(true, self.ctx.top(rt.heap))))?;
+                self.push(Some(ValType::Ref(*rt)));
+            }
+            ReferenceInstr::RefI31 => {
+                self.pop_expect(Some(I32))?;
+                self.push(Some(ref_type(false, HeapType::I31)));
+            }
+            ReferenceInstr::I31Get(_) => {
+                self.pop_expect(Some(ref_type(true, HeapType::I31)))?;
+                self.push(Some(I32));
+            }
+            ReferenceInstr::AnyConvertExtern => {
+                let nullable = self.pop_nullable(HeapType::Extern)?;
+                self.push(Some(ref_type(nullable, HeapType::Any)));
+            }
+            ReferenceInstr::ExternConvertAny => {
+                let nullable = self.pop_nullable(HeapType::Any)?;
+                self.push(Some(ref_type(nullable, HeapType::Extern)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Pops a reference to `heap` and returns whether it is nullable.
+    fn pop_nullable(&mut self, heap: HeapType) -> Result<bool, ValidationError> {
+        match self.pop_expect(Some(ref_type(true, heap)))? {
+            Some(ValType::Ref(tpe)) => Ok(tpe.nullable),
+            _ => Ok(true),
+        }
+    }
+
+    /// Checks that a field can be read, with a sign extension if and only if it is packed.
+    fn field_get(
+        &self,
+        field: &FieldType,
+        sign: &Option<Sign>,
+    ) -> Result<ValType, ValidationError> {
+        let packed = !matches!(field.tpe, StorageType::Val(_));
+        if packed != sign.is_some() {
+            return err(ValidationErrorKind::PackedField(field.tpe));
+        }
+        Ok(field.tpe.unpacked())
+    }
+
+    /// Checks that a field can be written. Returns the type of its values.
+    fn field_set(&self, field: &FieldType) -> Result<ValType, ValidationError> {
+        if field.mutability != Mutability::Mut {
+            return err(ValidationErrorKind::ImmutableField);
+        }
+        Ok(field.tpe.unpacked())
+    }
+
+    fn struct_field(&self, x: TypeIdx, y: FieldIdx) -> Result<&'a FieldType, ValidationError> {
+        match self.ctx.struct_type(x)?.fields.get(*y.0 as usize) {
+            Some(field) => Ok(field),
+            None => err(ValidationErrorKind::UnknownField {
+                tpe: *x.0,
+                field: *y.0,
+            }),
+        }
+    }
+
+    /// Checks that the values of an element segment can be stored in an array of type `x`.
+    fn array_elem(&self, x: TypeIdx, y: u32) -> Result<&'a ArrayType, ValidationError> {
+        let tpe = self.ctx.array_type(x)?;
+        let elem = self.ctx.elem(y)?;
+        match tpe.field.tpe {
+            StorageType::Val(t) if self.ctx.matches(ValType::Ref(elem), t) => Ok(tpe),
+            array => err(ValidationErrorKind::ElemArrayMismatch {
+                elem: y,
+                tpe: elem,
+                array,
+            }),
+        }
+    }
+
+    /// Checks that data segment can initialize arrays of type `x`, whose elements must be numbers
+    /// or vectors.
+    fn array_data(&self, x: TypeIdx, y: u32) -> Result<&'a ArrayType, ValidationError> {
+        let tpe = self.ctx.array_type(x)?;
+        self.ctx.data(y)?;
+        if let StorageType::Val(ValType::Ref(t)) = tpe.field.tpe {
+            return err(ValidationErrorKind::DataArrayMismatch(t));
+        }
+        Ok(tpe)
+    }
+
+    fn validate_aggregate(&mut self, instr: &AggregateInstr) -> Result<(), ValidationError> {
+        match instr {
+            AggregateInstr::StructNew(x) => {
+                let types: Vec<ValType> = self
+                    .ctx
+                    .struct_type(*x)?
+                    .fields
+                    .iter()
+                    .map(|field| field.tpe.unpacked())
+                    .collect();
+                self.pop_vals(&types)?;
+                self.push(Some(ref_type(false, HeapType::Concrete(*x))));
+            }
+            AggregateInstr::StructNewDefault(x) => {
+                let tpe = self.ctx.struct_type(*x)?;
+                if let Some(field) = tpe
+                    .fields
+                    .iter()
+                    .find(|f| !f.tpe.unpacked().is_defaultable())
+                {
+                    return err(ValidationErrorKind::NotDefaultable(field.tpe));
+                }
+                self.push(Some(ref_type(false, HeapType::Concrete(*x))));
+            }
+            AggregateInstr::StructGet(sign, x, y) => {
+                let tpe = self.field_get(self.struct_field(*x, *y)?, sign)?;
+                self.pop_expect(Some(ref_null(*x)))?;
+                self.push(Some(tpe));
+            }
+            AggregateInstr::StructSet(x, y) => {
+                let tpe = self.field_set(self.struct_field(*x, *y)?)?;
+                self.pop_vals(&[ref_null(*x), tpe])?;
+            }
+            AggregateInstr::ArrayNew(x) => {
+                let tpe = self.ctx.array_type(*x)?.field.tpe.unpacked();
+                self.pop_vals(&[tpe, I32])?;
+                self.push(Some(ref_type(false, HeapType::Concrete(*x))));
+            }
+            AggregateInstr::ArrayNewDefault(x) => {
+                let tpe = self.ctx.array_type(*x)?.field.tpe;
+                if !tpe.unpacked().is_defaultable() {
+                    return err(ValidationErrorKind::NotDefaultable(tpe));
+                }
+                self.pop_expect(Some(I32))?;
+                self.push(Some(ref_type(false, HeapType::Concrete(*x))));
+            }
+            AggregateInstr::ArrayNewFixed(x, n) => {
+                let tpe = self.ctx.array_type(*x)?.field.tpe.unpacked();
+                for _ in 0..**n {
+                    self.pop_expect(Some(tpe))?;
+                }
+                self.push(Some(ref_type(false, HeapType::Concrete(*x))));
+            }
+            AggregateInstr::ArrayNewData(x, y) => {
+                self.array_data(*x, *y.0)?;
+                self.pop_vals(&[I32, I32])?;
+                self.push(Some(ref_type(false, HeapType::Concrete(*x))));
+            }
+            AggregateInstr::ArrayNewElem(x, y) => {
+                self.array_elem(*x, *y.0)?;
+                self.pop_vals(&[I32, I32])?;
+                self.push(Some(ref_type(false, HeapType::Concrete(*x))));
+            }
+            AggregateInstr::ArrayGet(sign, x) => {
+                let tpe = self.field_get(&self.ctx.array_type(*x)?.field, sign)?;
+                self.pop_vals(&[ref_null(*x), I32])?;
+                self.push(Some(tpe));
+            }
+            AggregateInstr::ArraySet(x) => {
+                let tpe = self.field_set(&self.ctx.array_type(*x)?.field)?;
+                self.pop_vals(&[ref_null(*x), I32, tpe])?;
+            }
+            AggregateInstr::ArrayLen => {
+                self.pop_expect(Some(ref_type(true, HeapType::Array)))?;
+                self.push(Some(I32));
+            }
+            AggregateInstr::ArrayFill(x) => {
+                let tpe = self.field_set(&self.ctx.array_type(*x)?.field)?;
+                self.pop_vals(&[ref_null(*x), I32, tpe, I32])?;
+            }
+            AggregateInstr::ArrayCopy(x, y) => {
+                let dst = self.ctx.array_type(*x)?.field;
+                let src = self.ctx.array_type(*y)?.field;
+                self.field_set(&dst)?;
+                let compatible = match (src.tpe, dst.tpe) {
+                    (StorageType::Val(t1), StorageType::Val(t2)) => self.ctx.matches(t1, t2),
+                    (t1, t2) => t1 == t2,
+                };
+                if !compatible {
+                    return err(ValidationErrorKind::ArrayCopyMismatch {
+                        dst: dst.tpe,
+                        src: src.tpe,
+                    });
+                }
+                self.pop_vals(&[ref_null(*x), I32, ref_null(*y), I32, I32])?;
+            }
+            AggregateInstr::ArrayInitData(x, y) => {
+                let tpe = self.array_data(*x, *y.0)?;
+                self.field_set(&tpe.field)?;
+                self.pop_vals(&[ref_null(*x), I32, I32, I32])?;
+            }
+            AggregateInstr::ArrayInitElem(x, y) => {
+                let tpe = self.array_elem(*x, *y.0)?;
+                self.field_set(&tpe.field)?;
+                self.pop_vals(&[ref_null(*x), I32, I32, I32])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_select(&mut self, types: &[ValType]) -> Result<(), ValidationError> {
+        match types {
+            [] => {
+                self.pop_expect(Some(I32))?;
+                let t1 = self.pop()?;
+                let t2 = self.pop()?;
+                let is_num = |t: Operand| !matches!(t, Some(ValType::Ref(_)));
+                if !is_num(t1) || !is_num(t2) {
+                    return err(ValidationErrorKind::UntypedSelect);
+                }
+                match (t1, t2) {
+                    (Some(t1), Some(t2)) if t1 != t2 => {
+                        err(ValidationErrorKind::SelectMismatch(t1, t2))
+                    }
+                    (None, t) | (t, _) => {
+                        self.push(t);
+                        Ok(())
+                    }
+                }
+            }
+            [t] => {
+                self.ctx.val_type(*t)?;
+                self.pop_expect(Some(I32))?;
+                self.pop_expect(Some(*t))?;
+                self.pop_expect(Some(*t))?;
+                self.push(Some(*t));
+                Ok(())
+            }
+            _ => err(ValidationErrorKind::SelectArity(types.len())),
+        }
+    }
+
+    fn validate_variable(&mut self, instr: &VariableInstr) -> Result<(), ValidationError> {
+        match instr {
+            VariableInstr::LocalGet(x) => {
+                let tpe = self.local(*x)?;
+                if !self.initialized[*x.0 as usize] {
+                    return err(ValidationErrorKind::UninitializedLocal(*x.0));
+                }
+                self.push(Some(tpe));
+            }
+            VariableInstr::LocalSet(x) => {
+                let tpe = self.local(*x)?;
+                self.pop_expect(Some(tpe))?;
+                self.init_local(*x);
+            }
+            VariableInstr::LocalTee(x) => {
+                let tpe = self.local(*x)?;
+                self.pop_expect(Some(tpe))?;
+                self.init_local(*x);
+                self.push(Some(tpe));
+            }
+            VariableInstr::GlobalGet(x) => {
+                let tpe = self.ctx.global(*x)?.tpe;
+                self.push(Some(tpe));
+            }
+            VariableInstr::GlobalSet(x) => {
+                let global = *self.ctx.global(*x)?;
+                if global.mutability != Mutability::Mut {
+                    return err(ValidationErrorKind::ImmutableGlobal(*x.0));
+                }
+                self.pop_expect(Some(global.tpe))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_table(&mut self, instr: &TableInstr) -> Result<(), ValidationError> {
+        match instr {
+            TableInstr::TableGet(x) => {
+                let table = self.ctx.table(*x)?;
+                self.pop_expect(Some(addr_type(table.index)))?;
+                self.push(Some(ValType::Ref(table.tpe)));
+            }
+            TableInstr::TableSet(x) => {
+                let table = self.ctx.table(*x)?;
+                self.pop_expect(Some(ValType::Ref(table.tpe)))?;
+                self.pop_expect(Some(addr_type(table.index)))?;
+            }
+            TableInstr::TableSize(x) => {
+                let table = self.ctx.table(*x)?;
+                self.push(Some(addr_type(table.index)));
+            }
+            TableInstr::TableGrow(x) => {
+                let table = self.ctx.table(*x)?;
+                let addr = addr_type(table.index);
+                self.pop_expect(Some(addr))?;
+                self.pop_expect(Some(ValType::Ref(table.tpe)))?;
+                self.push(Some(addr));
+            }
+            TableInstr::TableFill(x) => {
+                let table = self.ctx.table(*x)?;
+                let addr = addr_type(table.index);
+                self.pop_expect(Some(addr))?;
+                self.pop_expect(Some(ValType::Ref(table.tpe)))?;
+                self.pop_expect(Some(addr))?;
+            }
+            TableInstr::TableCopy(x, y) => {
+                let t1 = self.ctx.table(*x)?;
+                let t2 = self.ctx.table(*y)?;
+                if !self.ctx.ref_matches(t2.tpe, t1.tpe) {
+                    return err(ValidationErrorKind::TableCopyMismatch {
+                        dst: t1.tpe,
+                        src: t2.tpe,
+                    });
+                }
+                let dst = addr_type(t1.index);
+                let src = addr_type(t2.index);
+                // The length must fit in both tables.
+                let len = if dst == I64 && src == I64 { I64 } else { I32 };
+                self.pop_vals(&[dst, src, len])?;
+            }
+            TableInstr::TableInit(x, y) => {
+                let t1 = self.ctx.table(*x)?;
+                let t2 = self.ctx.elem(*y.0)?;
+                if !self.ctx.ref_matches(t2, t1.tpe) {
+                    return err(ValidationErrorKind::TableInitMismatch {
+                        table: t1.tpe,
+                        elem: t2,
+                    });
+                }
+                self.pop_vals(&[addr_type(t1.index), I32, I32])?;
+            }
+            TableInstr::ElemDrop(x) => {
+                self.ctx.elem(*x.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_memory(&mut self, instr: &MemoryInstr) -> Result<(), ValidationError> {
+        match instr {
+            MemoryInstr::ILoad(t, m) => {
+                let tpe = int_type(t);
+                let bits = if tpe == I32 { 32 } else { 64 };
+                self.load(m, bits, tpe)
+            }
+            MemoryInstr::FLoad(t, m) => {
+                let tpe = float_type(t);
+                let bits = if tpe == F32 { 32 } else { 64 };
+                self.load(m, bits, tpe)
+            }
+            MemoryInstr::IStore(t, m) => {
+                let tpe = int_type(t);
+                let bits = if tpe == I32 { 32 } else { 64 };
+                self.store(m, bits, tpe)
+            }
+            MemoryInstr::FStore(t, m) => {
+                let tpe = float_type(t);
+                let bits = if tpe == F32 { 32 } else { 64 };
+                self.store(m, bits, tpe)
+            }
+            MemoryInstr::ILoad8(t, _, m) => self.load(m, 8, int_type(t)),
+            MemoryInstr::ILoad16(t, _, m) => self.load(m, 16, int_type(t)),
+            MemoryInstr::I64Load32(_, m) => self.load(m, 32, I64),
+            MemoryInstr::IStore8(t, m) => self.store(m, 8, int_type(t)),
+            MemoryInstr::IStore16(t, m) => self.store(m, 16, int_type(t)),
+            MemoryInstr::I64Store32(m) => self.store(m, 32, I64),
+            MemoryInstr::MemorySize(x) => {
+                let addr = addr_type(self.ctx.mem(*x)?.index);
+                self.push(Some(addr));
+                Ok(())
+            }
+            MemoryInstr::MemoryGrow(x) => {
+                let addr = addr_type(self.ctx.mem(*x)?.index);
+                self.pop_expect(Some(addr))?;
+                self.push(Some(addr));
+                Ok(())
+            }
+            MemoryInstr::MemoryFill(x) => {
+                let addr = addr_type(self.ctx.mem(*x)?.index);
+                self.pop_vals(&[addr, I32, addr])
+            }
+            MemoryInstr::MemoryCopy(x, y) => {
+                let dst = addr_type(self.ctx.mem(*x)?.index);
+                let src = addr_type(self.ctx.mem(*y)?.index);
+                // The length must fit in both memories.
+                let len = if dst == I64 && src == I64 { I64 } else { I32 };
+                self.pop_vals(&[dst, src, len])
+            }
+            MemoryInstr::MemoryInit(x, y) => {
+                let addr = addr_type(self.ctx.mem(*x)?.index);
+                self.ctx.data(*y.0)?;
+                self.pop_vals(&[addr, I32, I32])
+            }
+            MemoryInstr::DataDrop(x) => self.ctx.data(*x.0),
+        }
+    }
+
+    fn validate_control(&mut self, instr: &ControlInstr) -> Result<(), ValidationError> {
+        match instr {
+            ControlInstr::Nop => {}
+            ControlInstr::Unreachable => self.unreachable(),
+            ControlInstr::Block(_)
+            | ControlInstr::Loop(_)
+            | ControlInstr::If(_)
+            | ControlInstr::Try(_)
+            | ControlInstr::TryTable(_) => {
+                unreachable!("Blocks are handled by validate_instrs")
+            }
+            ControlInstr::Branch(l) => {
+                let types = self.label(*l)?;
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            ControlInstr::BranchIf(l) => {
+                self.pop_expect(Some(I32))?;
+                let types = self.label(*l)?;
+                self.pop_vals(&types)?;
+                self.push_vals(&types);
+            }
+            ControlInstr::BranchTable(labels, default) => {
+                self.pop_expect(Some(I32))?;
+                let default = self.label(*default)?;
+                for label in labels {
+                    let types = self.label(*label)?;
+                    if types.len() != default.len() {
+                        return err(ValidationErrorKind::BrTableArity {
+                            label: *label.0,
+                            arity: types.len(),
+                            default: default.len(),
+                        });
+                    }
+                    // Checks the operands against the label without consuming them.
+                    let mut popped = Vec::with_capacity(types.len());
+                    for tpe in types.iter().rev() {
+                        popped.push(self.pop_expect(Some(*tpe))?);
+                    }
+                    for tpe in popped.into_iter().rev() {
+                        self.push(tpe);
+                    }
+                }
+                self.pop_vals(&default)?;
+                self.unreachable();
+            }
+            ControlInstr::Return => {
+                let types = self.ctrls[0].end.clone();
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            ControlInstr::Call(x) => {
+                let tpe = self.ctx.func(*x)?;
+                self.pop_vals(&tpe.params.types)?;
+                self.push_vals(&tpe.result.types);
+            }
+            ControlInstr::CallIndirect(x, y) => {
+                let tpe = self.call_indirect(*x, *y)?;
+                self.pop_vals(&tpe.params.types)?;
+                self.push_vals(&tpe.result.types);
+            }
+            ControlInstr::ReturnCall(x) => {
+                let tpe = self.ctx.func(*x)?;
+                self.return_call(tpe)?;
+            }
+            ControlInstr::ReturnCallIndirect(x, y) => {
+                let tpe = self.call_indirect(*x, *y)?;
+                self.return_call(tpe)?;
+            }
+            ControlInstr::CallRef(x) => {
+                let tpe = self.ctx.tpe(*x)?;
+                self.pop_expect(Some(ref_null(*x)))?;
+                self.pop_vals(&tpe.params.types)?;
+                self.push_vals(&tpe.result.types);
+            }
+            ControlInstr::ReturnCallRef(x) => {
+                let tpe = self.ctx.tpe(*x)?;
+                self.pop_expect(Some(ref_null(*x)))?;
+                self.return_call(tpe)?;
+            }
+            ControlInstr::BrOnNull(l) => {
+                let tpe = self.pop_ref()?;
+                let types = self.label(*l)?;
+                self.pop_vals(&types)?;
+                self.push_vals(&types);
+                match tpe {
+                    Some(ValType::Ref(tpe)) => self.push(Some(ref_type(false, tpe.heap))),
+                    tpe => self.push(tpe),
+                }
+            }
+            ControlInstr::BrOnNonNull(l) => {
+                let (types, label) = self.ref_label(*l)?;
+                if let Some(ValType::Ref(tpe)) = self.pop_ref()? {
+                    if !self.ctx.ref_matches(RefType::non_null(tpe.heap), label) {
+                        return err(ValidationErrorKind::LabelMismatch {
+                            label: *l.0,
+                            expected: vec![ValType::Ref(label)],
+                            actual: vec![ValType::Ref(RefType::non_null(tpe.heap))],
+                        });
+                    }
+                }
+                self.pop_vals(&types)?;
+                self.push_vals(&types);
+            }
+            ControlInstr::BrOnCast(l, rt1, rt2) | ControlInstr::BrOnCastFail(l, rt1, rt2) => {
+                self.ctx.ref_type(*rt1)?;
+                self.ctx.ref_type(*rt2)?;
+                if !self.ctx.ref_matches(*rt2, *rt1) {
+                    return err(ValidationErrorKind::CastMismatch {
+                        input: *rt1,
+                        cast: *rt2,
+                    });
+                }
+                // The type of the references failing the cast.
+                let diff = RefType {
+                    nullable: rt1.nullable && !rt2.nullable,
+                    heap: rt1.heap,
+                };
+                let (branch, fallthrough) = match instr {
+                    ControlInstr::BrOnCast(..) => (*rt2, diff),
+                    _ => (diff, *rt2),
+                };
+                let (types, label) = self.ref_label(*l)?;
+                if !self.ctx.ref_matches(branch, label) {
+                    return err(ValidationErrorKind::LabelMismatch {
+                        label: *l.0,
+                        expected: vec![ValType::Ref(label)],
+                        actual: vec![ValType::Ref(branch)],
+                    });
+                }
+                self.pop_expect(Some(ValType::Ref(*rt1)))?;
+                self.pop_vals(&types)?;
+                self.push_vals(&types);
+                self.push(Some(ValType::Ref(fallthrough)));
+            }
+            ControlInstr::Throw(x) => {
+                let tpe = self.ctx.tag(*x)?;
+                self.pop_vals(&tpe.params.types)?;
+                self.unreachable();
+            }
+            ControlInstr::Rethrow(l) => {
+                self.label(*l)?;
+                let frame = &self.ctrls[self.ctrls.len() - 1 - *l.0 as usize];
+                if frame.kind != FrameKind::Catch {
+                    return err(ValidationErrorKind::RethrowTarget(*l.0));
+                }
+                self.unreachable();
+            }
+            ControlInstr::ThrowRef => {
+                self.pop_expect(Some(EXNREF))?;
+                self.unreachable();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validates the type of a tag, which must have no results.
+fn validate_tag_type(ctx: &Context, idx: TypeIdx) -> Result<(), ValidationError> {
+    let tpe = ctx.tpe(idx)?;
+    if !tpe.result.types.is_empty() {
+        return err(ValidationErrorKind::TagResults {
+            tpe: *idx.0,
+            results: tpe.result.types.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Validates a constant expression producing a single value of type `expect`.
+fn validate_const(ctx: &Context, expr: &Expr, expect: ValType) -> Result<(), ValidationError> {
+    for (offset, instr) in expr.instr.iter().enumerate() {
+        match instr {
+            Instr::Numeric(NumericInstr::I32Const(_))
+            | Instr::Numeric(NumericInstr::I64Const(_))
+            | Instr::Numeric(NumericInstr::F32Const(_))
+            | Instr::Numeric(NumericInstr::F64Const(_))
+            | Instr::Vector(VectorInstr::V128Const(_))
+            | Instr::Reference(ReferenceInstr::RefNull(_))
+            | Instr::Reference(ReferenceInstr::RefFunc(_))
+            | Instr::Reference(ReferenceInstr::RefI31)
+            | Instr::Reference(ReferenceInstr::AnyConvertExtern)
+            | Instr::Reference(ReferenceInstr::ExternConvertAny)
+            | Instr::Aggregate(AggregateInstr::StructNew(_))
+            | Instr::Aggregate(AggregateInstr::StructNewDefault(_))
+            | Instr::Aggregate(AggregateInstr::ArrayNew(_))
+            | Instr::Aggregate(AggregateInstr::ArrayNewDefault(_))
+            | Instr::Aggregate(AggregateInstr::ArrayNewFixed(..)) => {}
+            Instr::Variable(VariableInstr::GlobalGet(x)) => {
+                if *x.0 as usize >= ctx.imported_globals {
+                    return Err(
+                        ValidationError::new(ValidationErrorKind::ConstGlobalNotImported(*x.0))
+                            .at_instr(offset),
+                    );
+                }
+                let global = ctx.global(*x)?;
+                if global.mutability != Mutability::Const {
+                    return Err(
+                        ValidationError::new(ValidationErrorKind::ConstGlobalMutable(*x.0))
+                            .at_instr(offset),
+                    );
+                }
+            }
+            _ => {
+                return Err(ValidationError::new(ValidationErrorKind::NotConstant).at_instr(offset))
+            }
+        }
+    }
+    // Once known to be constant, the expression type checks like a function body returning the
+    // value.
+    let tpe = FuncType {
+        params: ResultType { types: Vec::new() },
+        result: ResultType {
+            types: vec![expect],
+        },
+    };
+    FuncValidator::new(ctx, &tpe, &[])
+        .validate_body(expr)
+        .map_err(|err| err.extend("Invalid constant expression"))
+}
+
+fn validate_limits(lim: &Limits, range: u64) -> Result<(), ValidationError> {
+    if *lim.min > range {
+        return err(ValidationErrorKind::MinTooLarge {
+            min: *lim.min,
+            range,
+        });
+    }
+    if let Some(max) = lim.max {
+        if *max > range {
+            return err(ValidationErrorKind::MaxTooLarge { max: *max, range });
+        }
+        if *max < *lim.min {
+            return err(ValidationErrorKind::MaxBelowMin {
+                min: *lim.min,
+                max: *max,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn validate_mem_type(tpe: &MemType) -> Result<(), ValidationError> {
+    let range = match tpe.index {
+        IndexType::I32 => MAX_PAGES,
+        IndexType::I64 => MAX_PAGES_64,
+    };
+    validate_limits(&tpe.lim, range)?;
+    if tpe.shared && tpe.lim.max.is_none() {
+        return err(ValidationErrorKind::SharedWithoutMax);
+    }
+    Ok(())
+}
+
+fn validate_table_type(ctx: &Context, tpe: &TableType) -> Result<(), ValidationError> {
+    ctx.ref_type(tpe.tpe)?;
+    let range = match tpe.index {
+        IndexType::I32 => u32::MAX.into(),
+        IndexType::I64 => u64::MAX,
+    };
+    validate_limits(&tpe.lim, range)
+}
+
+/// Checks the references of the type definitions of a recursion group, which have already been
+/// added to the context, starting at index `first`. Types can refer to the previous groups and to
+/// their own group, but only declare previous types as their supertype.
+fn validate_rec_type(
+    ctx: &Context,
+    group: &[SubType],
+    first: usize,
+) -> Result<(), ValidationError> {
+    for (i, tpe) in group.iter().enumerate() {
+        let idx = first + i;
+        let extend = |err: ValidationError| err.extend(&format!("Invalid type {}", idx));
+        let fields = match &tpe.comp {
+            CompType::Func(func) => func
+                .params
+                .types
+                .iter()
+                .chain(&func.result.types)
+                .try_for_each(|t| ctx.val_type(*t)),
+            CompType::Struct(st) => st.fields.iter().try_for_each(|f| match f.tpe {
+                StorageType::Val(t) => ctx.val_type(t),
+                _ => Ok(()),
+            }),
+            CompType::Array(array) => match array.field.tpe {
+                StorageType::Val(t) => ctx.val_type(t),
+                _ => Ok(()),
+            },
+        };
+        fields.map_err(extend)?;
+        match tpe.supers[..] {
+            [] => {}
+            [sup] if (*sup.0 as usize) < idx => {}
+            [sup] => {
+                return Err(extend(ValidationError::new(
+                    ValidationErrorKind::SupertypeOrder(*sup.0),
+                )))
+            }
+            _ => {
+                return Err(extend(ValidationError::new(
+                    ValidationErrorKind::MultipleSupertypes,
+                )))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the type definitions of a recursion group, once canonicalized, match their
+/// supertype.
+fn validate_supertypes(
+    ctx: &Context,
+    group: &[SubType],
+    first: usize,
+) -> Result<(), ValidationError> {
+    for (i, tpe) in group.iter().enumerate() {
+        let idx = first + i;
+        let extend = |err: ValidationError| err.extend(&format!("Invalid type {}", idx));
+        if let Some(sup) = tpe.supers.first() {
+            if ctx.types[*sup.0 as usize].is_final {
+                return Err(extend(ValidationError::new(
+                    ValidationErrorKind::FinalSupertype(*sup.0),
+                )));
+            }
+            let registry = &ctx.registry;
+            let sub = &registry.get(ctx.ids[idx]).comp;
+            if !registry.comp_matches(sub, &registry.get(ctx.ids[*sup.0 as usize]).comp) {
+                return Err(extend(ValidationError::new(
+                    ValidationErrorKind::SupertypeMismatch(*sup.0),
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects the function indices referenced outside of function bodies (`C.refs`).
+fn collect_refs(module: &Module) -> HashSet<u32> {
+    let mut refs = HashSet::new();
+    let exprs = module
+        .globals
+        .iter()
+        .map(|global| &global.init)
+        .chain(module.tables.iter().filter_map(|table| table.init.as_ref()))
+        .chain(module.elems.iter().flat_map(|elem| elem.init.iter()));
+    for expr in exprs {
+        for instr in &expr.instr {
+            if let Instr::Reference(ReferenceInstr::RefFunc(x)) = instr {
+                refs.insert(*x.0);
+            }
+        }
+    }
+    for export in &module.exports {
+        if let ExportDesc::Func(x) = export.desc {
+            refs.insert(*x.0);
+        }
+    }
+    refs
+}
+
+/// Validates the module.
+///
+/// ```
+/// use std::fs;
+///
+/// use wasm_parse::wasm::module::Module;
+/// use wasm_parse::wasm::validate::validate;
+///
+/// let module = Module::from_binary(fs::read("wasm-examples/hello.wasm")?);
+/// assert!(validate(&module).is_ok());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn validate(module: &Module) -> Result<(), ValidationError> {
+    let mut ctx = Context {
+        types: Vec::new(),
+        ids: Vec::new(),
+        registry: TypeRegistry::new(),
+        funcs: Vec::new(),
+        tables: Vec::new(),
+        mems: Vec::new(),
+        globals: Vec::new(),
+        imported_globals: 0,
+        tags: Vec::new(),
+        elems: module.elems.iter().map(|elem| elem.tpe).collect(),
+        datas: module.datas.len(),
+        refs: collect_refs(module),
+    };
+
+    // Types are only checked once their whole group is known, since they can refer to each
+    // other.
+    for group in &module.types {
+        let first = ctx.types.len();
+        ctx.types.extend(&group.types);
+        // Canonicalization needs the references to be valid.
+        validate_rec_type(&ctx, &group.types, first)?;
+        let ids = ctx.registry.add_group(&group.types, &ctx.ids);
+        ctx.ids.extend(ids);
+        validate_supertypes(&ctx, &group.types, first)?;
+    }
+
+    for (i, import) in module.imports.iter().enumerate() {
+        let extend = |err: ValidationError| err.extend(&format!("Invalid import {}", i));
+        match &import.desc {
+            ImportDesc::Func(x) => {
+                ctx.tpe(*x).map_err(extend)?;
+                ctx.funcs.push(*x);
+            }
+            ImportDesc::Table(tpe) => {
+                validate_table_type(&ctx, tpe).map_err(extend)?;
+                ctx.tables.push(*tpe);
+            }
+            ImportDesc::Mem(tpe) => {
+                validate_mem_type(tpe).map_err(extend)?;
+                ctx.mems.push(*tpe);
+            }
+            ImportDesc::Global(tpe) => {
+                ctx.val_type(tpe.tpe).map_err(extend)?;
+                ctx.globals.push(*tpe);
+                ctx.imported_globals += 1;
+            }
+            ImportDesc::Tag(x) => {
+                validate_tag_type(&ctx, *x).map_err(extend)?;
+                ctx.tags.push(*x);
+            }
+        }
+    }
+    let imported_funcs = ctx.funcs.len();
+    for (i, func) in module.funcs.iter().enumerate() {
+        let idx = FuncIdx(super::values::U32((imported_funcs + i) as u32));
+        ctx.tpe(func.index).map_err(|err| err.in_func(idx))?;
+        ctx.funcs.push(func.index);
+    }
+    for (i, table) in module.tables.iter().enumerate() {
+        let extend = |err: ValidationError| err.extend(&format!("Invalid table {}", i));
+        validate_table_type(&ctx, &table.tpe).map_err(extend)?;
+        match &table.init {
+            Some(init) => {
+                validate_const(&ctx, init, ValType::Ref(table.tpe.tpe)).map_err(extend)?
+            }
+            None if !table.tpe.tpe.nullable => {
+                return Err(extend(ValidationError::new(
+                    ValidationErrorKind::TableWithoutInit(table.tpe.tpe),
+                )))
+            }
+            None => {}
+        }
+        ctx.tables.push(table.tpe);
+    }
+    for (i, mem) in module.mems.iter().enumerate() {
+        validate_mem_type(&mem.tpe).map_err(|err| err.extend(&format!("Invalid memory {}", i)))?;
+        ctx.mems.push(mem.tpe);
+    }
+    for (i, tag) in module.tags.iter().enumerate() {
+        validate_tag_type(&ctx, tag.tpe)
+            .map_err(|err| err.extend(&format!("Invalid tag {}", i)))?;
+        ctx.tags.push(tag.tpe);
+    }
+    for (i, global) in module.globals.iter().enumerate() {
+        ctx.val_type(global.tpe.tpe)
+            .and_then(|_| validate_const(&ctx, &global.init, global.tpe.tpe))
+            .map_err(|err| err.extend(&format!("Invalid global {}", i)))?;
+    }
+    ctx.globals
+        .extend(module.globals.iter().map(|global| global.tpe));
+
+    for (i, elem) in module.elems.iter().enumerate() {
+        let extend = |err: ValidationError| err.extend(&format!("Invalid element segment {}", i));
+        ctx.ref_type(elem.tpe).map_err(extend)?;
+        for expr in &elem.init {
+            validate_const(&ctx, expr, ValType::Ref(elem.tpe)).map_err(extend)?;
+        }
+        if let ElemMode::Active { table, offset } = &elem.mode {
+            let tpe = ctx.table(*table).map_err(extend)?;
+            if !ctx.ref_matches(elem.tpe, tpe.tpe) {
+                return Err(extend(ValidationError::new(
+                    ValidationErrorKind::ElemTableMismatch {
+                        table: *table.0,
+                        tpe: tpe.tpe,
+                        elem: elem.tpe,
+                    },
+                )));
+            }
+            validate_const(&ctx, offset, addr_type(tpe.index)).map_err(extend)?;
+        }
+    }
+    for (i, data) in module.datas.iter().enumerate() {
+        let extend = |err: ValidationError| err.extend(&format!("Invalid data segment {}", i));
+        if let DataMode::Active { memory, offset } = &data.mode {
+            let tpe = ctx.mem(*memory).map_err(extend)?;
+            validate_const(&ctx, offset, addr_type(tpe.index)).map_err(extend)?;
+        }
+    }
+
+    for (i, func) in module.funcs.iter().enumerate() {
+        let idx = FuncIdx(super::values::U32((imported_funcs + i) as u32));
+        let tpe = ctx.tpe(func.index)?;
+        FuncValidator::new(&ctx, tpe, &func.locals)
+            .validate_body(&func.body)
+            .map_err(|err| err.in_func(idx))?;
+    }
+
+    if let Some(start) = &module.start {
+        let tpe = ctx
+            .func(start.func)
+            .map_err(|err| err.extend("Invalid start function"))?;
+        if !tpe.params.types.is_empty() || !tpe.result.types.is_empty() {
+            return Err(ValidationError::new(ValidationErrorKind::StartType {
+                params: tpe.params.types.clone(),
+                results: tpe.result.types.clone(),
+            })
+            .in_func(start.func));
+        }
+    }
+
+    let mut names = HashSet::new();
+    for export in &module.exports {
+        if !names.insert(&export.name.name) {
+            return err(ValidationErrorKind::DuplicateExport(
+                export.name.name.clone(),
+            ));
+        }
+        let extend =
+            |err: ValidationError| err.extend(&format!("Invalid export {:?}", export.name.name));
+        match export.desc {
+            ExportDesc::Func(x) => ctx.func(x).map(|_| ()),
+            ExportDesc::Table(x) => ctx.table(x).map(|_| ()),
+            ExportDesc::Mem(x) => ctx.mem(x).map(|_| ()),
+            ExportDesc::Global(x) => ctx.global(x).map(|_| ()),
+            ExportDesc::Tag(x) => ctx.tag(x).map(|_| ()),
+        }
+        .map_err(extend)?;
+    }
+
+    Ok(())
+}
+
+/// Validates a module parsed with [Module::parse_with_spans] like [validate], and reports the
+/// byte offset of the offending instruction within the binary.
+///
+/// ```
+/// use wasm_parse::parse::{ParsingData, WasmBinary};
+/// use wasm_parse::wasm::module::Module;
+/// use wasm_parse::wasm::validate::{validate_with_spans, ValidationErrorKind};
+///
+/// let wasm: WasmBinary = vec![
+///     0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // Preamble
+///     0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7F, // Type section: [] -> [i32]
+///     0x03, 0x02, 0x01, 0x00, // Function section: 1 function
+///     0x0A, 0x06, 0x01, 0x04, 0x00, // Code section: 1 body without locals
+///     0x42, 0x00, 0x0B, // i64.const 0 end
+/// ]
+/// .into();
+/// let (module, spans) = Module::parse_with_spans(&mut ParsingData::new(&wasm))?;
+/// let err = validate_with_spans(&module, &spans).unwrap_err();
+/// assert!(matches!(err.kind(), ValidationErrorKind::TypeMismatch { .. }));
+/// // The type mismatch is found at the final `end` of the body.
+/// assert_eq!(err.offset(), Some(26));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn validate_with_spans(module: &Module, spans: &Spans) -> Result<(), ValidationError> {
+    let imported_funcs = module
+        .imports
+        .iter()
+        .filter(|import| matches!(import.desc, ImportDesc::Func(_)))
+        .count();
+    validate(module).map_err(|err| err.locate(spans, imported_funcs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::binary::{ParsingData, WasmBinary};
+    use crate::wasm::export::Export;
+    use crate::wasm::func::Func;
+    use crate::wasm::global::Global;
+    use crate::wasm::instr::{Block, IBinop, IfElseBlock};
+    use crate::wasm::start::Start;
+    use crate::wasm::types::RecType;
+    use crate::wasm::values::{Name, I32 as I32Val, I64 as I64Val, U32, U64};
+    use std::fs;
+
+    /// Recursion group of a single function type.
+    fn func_type(params: &[ValType], result: &[ValType]) -> RecType {
+        let tpe = FuncType {
+            params: ResultType {
+                types: params.to_vec(),
+            },
+            result: ResultType {
+                types: result.to_vec(),
+            },
+        };
+        RecType {
+            types: vec![tpe.into()],
+        }
+    }
+
+    /// Creates a module with a single function with the given type and body.
+    fn single_func(params: &[ValType], result: &[ValType], instr: Vec<Instr>) -> Module {
+        Module {
+            types: vec![func_type(params, result)],
+            funcs: vec![Func {
+                index: TypeIdx(U32(0)),
+                locals: Vec::new(),
+                body: Expr { instr },
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn i32_const(x: u32) -> Instr {
+        Instr::Numeric(NumericInstr::I32Const(I32Val(x)))
+    }
+
+    fn i32_add() -> Instr {
+        Instr::Numeric(NumericInstr::I32Binary(IBinop::Add))
+    }
+
+    fn local_get(x: u32) -> Instr {
+        Instr::Variable(VariableInstr::LocalGet(LocalIdx(U32(x))))
+    }
+
+    #[test]
+    fn examples() {
+        for entry in fs::read_dir("wasm-examples").unwrap() {
+            let path = entry.unwrap().path();
+            let module = Module::from_binary(fs::read(&path).unwrap());
+            assert!(validate(&module).is_ok(), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn valid_function() {
+        let module = single_func(
+            &[I32, I32],
+            &[I32],
+            vec![local_get(0), local_get(1), i32_add()],
+        );
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let module = single_func(
+            &[],
+            &[I32],
+            vec![
+                i32_const(1),
+                Instr::Numeric(NumericInstr::I64Const(I64Val(2))),
+                i32_add(),
+            ],
+        );
+        let err = validate(&module).unwrap_err();
+        assert_eq!(
+            err.kind(),
+            &ValidationErrorKind::TypeMismatch {
+                expected: I32,
+                actual: I64,
+            }
+        );
+        assert_eq!(err.func(), Some(FuncIdx(U32(0))));
+        assert_eq!(err.instr(), Some(2));
+        assert_eq!(err.offset(), None);
+    }
+
+    #[test]
+    fn missing_result() {
+        let module = single_func(&[], &[I32], vec![Instr::Control(ControlInstr::Nop)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn remaining_values() {
+        let module = single_func(&[], &[], vec![i32_const(1)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn byte_offset() {
+        let wasm: WasmBinary = vec![
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // Preamble
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // Type section: [] -> []
+            0x02, 0x07, 0x01, 0x01, b'm', 0x01, b'f', 0x00, 0x00, // Import "m" "f" (func 0)
+            0x03, 0x02, 0x01, 0x00, // Function section: 1 function
+            0x0A, 0x09, 0x01, 0x07, 0x00, // Code section: 1 body without locals
+            0x02, 0x40, 0x01, 0x6A, 0x0B, 0x0B, // block nop i32.add end end
+        ]
+        .into();
+        let (module, spans) = Module::parse_with_spans(&mut ParsingData::new(&wasm)).unwrap();
+        let err = validate_with_spans(&module, &spans).unwrap_err();
+        assert_eq!(err.kind(), &ValidationErrorKind::EmptyStack);
+        assert_eq!(err.func(), Some(FuncIdx(U32(1))));
+        // The block and the nop come first.
+        assert_eq!(err.instr(), Some(2));
+        assert_eq!(err.offset(), Some(35));
+    }
+
+    #[test]
+    fn call_arity() {
+        let mut module = single_func(&[I32], &[], Vec::new());
+        module.types.push(func_type(&[], &[]));
+        module.funcs.push(Func {
+            index: TypeIdx(U32(1)),
+            locals: Vec::new(),
+            body: Expr {
+                instr: vec![Instr::Control(ControlInstr::Call(FuncIdx(U32(0))))],
+            },
+        });
+        let err = validate(&module).unwrap_err();
+        assert_eq!(err.func(), Some(FuncIdx(U32(1))));
+        assert_eq!(err.instr(), Some(0));
+    }
+
+    #[test]
+    fn unknown_function() {
+        let module = single_func(
+            &[],
+            &[],
+            vec![Instr::Control(ControlInstr::Call(FuncIdx(U32(5))))],
+        );
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn unknown_local() {
+        let module = single_func(&[I32], &[I32], vec![local_get(1)]);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn unreachable_is_polymorphic() {
+        let module = single_func(
+            &[],
+            &[I32],
+            vec![Instr::Control(ControlInstr::Unreachable), i32_add()],
+        );
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn tail_call_results() {
+        let return_call = |x| Instr::Control(ControlInstr::ReturnCall(FuncIdx(U32(x))));
+        let module = single_func(&[I32], &[I32], vec![local_get(0), return_call(0)]);
+        assert!(validate(&module).is_ok());
+
+        // The callee returns an i32, which the caller doesn't.
+        let mut module = single_func(&[], &[], vec![i32_const(1), return_call(1)]);
+        module.types.push(func_type(&[I32], &[I32]));
+        module.funcs.push(Func {
+            index: TypeIdx(U32(1)),
+            locals: Vec::new(),
+            body: Expr {
+                instr: vec![local_get(0)],
+            },
+        });
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn branch_to_block() {
+        let module = single_func(
+            &[],
+            &[I32],
+            vec![Instr::Control(ControlInstr::Block(Block {
+                tpe: BlockType::Val(I32),
+                instr: vec![
+                    i32_const(1),
+                    Instr::Control(ControlInstr::Branch(LabelIdx(U32(0)))),
+                    // Unreachable, so anything goes.
+                    Instr::Dropp,
+                ],
+            }))],
+        );
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn unknown_label() {
+        let module = single_func(
+            &[],
+            &[],
+            vec![Instr::Control(ControlInstr::Branch(LabelIdx(U32(1))))],
+        );
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn if_without_else() {
+        let if_block = |tpe| {
+            Instr::Control(ControlInstr::If(IfElseBlock {
+                tpe,
+                if_br: Vec::new(),
+                else_br: Vec::new(),
+            }))
+        };
+        let module = single_func(&[], &[], vec![i32_const(1), if_block(BlockType::Empty)]);
+        assert!(validate(&module).is_ok());
+        // The missing else branch can't produce the result.
+        let module = single_func(
+            &[],
+            &[I32],
+            vec![i32_const(1), if_block(BlockType::Val(I32))],
+        );
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn untyped_select() {
+        let module = single_func(
+            &[],
+            &[I32],
+            vec![
+                i32_const(1),
+                i32_const(2),
+                i32_const(0),
+                Instr::Select(Vec::new()),
+            ],
+        );
+        assert!(validate(&module).is_ok());
+        let module = single_func(
+            &[],
+            &[],
+            vec![
+                Instr::Reference(ReferenceInstr::RefNull(HeapType::Func)),
+                Instr::Reference(ReferenceInstr::RefNull(HeapType::Func)),
+                i32_const(0),
+                Instr::Select(Vec::new()),
+                Instr::Dropp,
+            ],
+        );
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn undeclared_function_reference() {
+        let mut module = single_func(
+            &[],
+            &[],
+            vec![
+                Instr::Reference(ReferenceInstr::RefFunc(FuncIdx(U32(0)))),
+                Instr::Dropp,
+            ],
+        );
+        assert!(validate(&module).is_err());
+        module.exports.push(Export {
+            name: Name {
+                name: "f".to_string(),
+            },
+            desc: ExportDesc::Func(FuncIdx(U32(0))),
+        });
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn memory_alignment() {
+        let load = |align| {
+            Instr::Memory(MemoryInstr::ILoad(
+                IntType::I32,
+                MemArg {
+                    offset: U64(0),
+                    align: U32(align),
+                    mem: MemIdx(U32(0)),
+                },
+            ))
+        };
+        let mut module = single_func(&[], &[I32], vec![i32_const(0), load(2)]);
+        // No memory.
+        assert!(validate(&module).is_err());
+        module.mems.push(crate::wasm::mem::Mem {
+            tpe: MemType {
+                lim: Limits {
+                    min: U64(1),
+                    max: None,
+                },
+                index: IndexType::I32,
+                shared: false,
+            },
+        });
+        assert!(validate(&module).is_ok());
+        module.funcs[0].body.instr[1] = load(3);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn multiple_memories() {
+        let check = |body: &str| {
+            let text = format!(
+                "(module (memory 1) (memory 1) (data \"\") (func (param i32) {}))",
+                body
+            );
+            validate(&Module::from_wat(&text).unwrap())
+        };
+        assert!(check("local.get 0 i32.load 1 drop").is_ok());
+        assert!(check("local.get 0 i32.load 2 drop").is_err());
+        assert!(check("memory.size 1 memory.grow 1 drop").is_ok());
+        assert!(check("memory.size 2 drop").is_err());
+        assert!(check("local.get 0 local.get 0 local.get 0 memory.copy 1 0").is_ok());
+        assert!(check("local.get 0 local.get 0 local.get 0 memory.copy 0 2").is_err());
+        assert!(check("local.get 0 local.get 0 local.get 0 memory.init 1 0").is_ok());
+        assert!(check("local.get 0 local.get 0 local.get 0 memory.init 2 0").is_err());
+        let module = Module::from_wat("(module (memory 1) (data (memory 1) (i32.const 0)))");
+        assert!(validate(&module.unwrap()).is_err());
+    }
+
+    #[test]
+    fn memory64() {
+        let check = |body: &str| {
+            let text = format!(
+                "(module (memory i64 1) (memory 1) (data (i64.const 0)) (func (param i64 i32) {}))",
+                body
+            );
+            validate(&Module::from_wat(&text).unwrap())
+        };
+        assert!(check("local.get 0 i32.load offset=0x100000000 drop").is_ok());
+        assert!(check("local.get 1 i32.load drop").is_err());
+        assert!(check("local.get 1 i32.load 1 offset=0x100000000 drop").is_err());
+        assert!(check("local.get 0 local.get 1 i32.atomic.store16 align=2").is_ok());
+        assert!(check("local.get 0 local.get 1 v128.load8_lane 0 drop").is_err());
+        assert!(check("memory.size i64.const 1 memory.grow i64.add drop").is_ok());
+        assert!(check("memory.size 1 memory.grow 1 drop").is_ok());
+        assert!(check("local.get 0 local.get 1 local.get 0 memory.fill").is_ok());
+        assert!(check("local.get 0 local.get 0 local.get 0 memory.copy").is_ok());
+        assert!(check("local.get 0 local.get 1 local.get 1 memory.copy 0 1").is_ok());
+        assert!(check("local.get 1 local.get 0 local.get 0 memory.copy 1 0").is_err());
+        assert!(check("local.get 0 local.get 1 local.get 1 memory.init 0").is_ok());
+        let module = Module::from_wat("(module (memory i64 1) (data (i32.const 0)))").unwrap();
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn vector_instructions() {
+        let check = |body: &str| {
+            let text = format!(
+                "(module (memory 1) (func (param v128 i32) (result v128) {}))",
+                body
+            );
+            validate(&Module::from_wat(&text).unwrap())
+        };
+        assert!(check(
+            "local.get 0 local.get 0 i8x16.shuffle 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 31"
+        )
+        .is_ok());
+        assert!(check(
+            "local.get 0 local.get 0 i8x16.shuffle 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 32"
+        )
+        .is_err());
+        assert!(check("local.get 0 f32x4.extract_lane 3 f32x4.splat").is_ok());
+        assert!(check("local.get 0 f32x4.extract_lane 4 f32x4.splat").is_err());
+        assert!(check("local.get 0 i64x2.extract_lane 1 i32x4.splat").is_err());
+        assert!(check("local.get 0 local.get 1 i16x8.shl").is_ok());
+        assert!(check("local.get 1 local.get 0 i16x8.shl").is_err());
+        assert!(check("local.get 1 local.get 0 v128.load32_lane align=4 3").is_ok());
+        assert!(check("local.get 1 local.get 0 v128.load32_lane align=8 3").is_err());
+        assert!(check("local.get 1 v128.load8x8_s align=8").is_ok());
+        assert!(check("local.get 1 v128.load8x8_s align=16").is_err());
+        assert!(check("local.get 1 i32x4.splat i32x4.all_true drop local.get 0").is_ok());
+    }
+
+    #[test]
+    fn atomic_instructions() {
+        let check = |body: &str| {
+            let text = format!(
+                "(module (memory 1 1 shared) (func (param i32 i64) (result i32) {}))",
+                body
+            );
+            validate(&Module::from_wat(&text).unwrap())
+        };
+        assert!(check("local.get 0 i32.atomic.load").is_ok());
+        assert!(check("local.get 0 i32.atomic.load align=2").is_err());
+        assert!(check("local.get 0 i32.atomic.load16_u align=2").is_ok());
+        assert!(check("local.get 0 local.get 1 i64.atomic.rmw.xchg i32.wrap_i64").is_ok());
+        assert!(check("local.get 0 local.get 0 i64.atomic.rmw.xchg i32.wrap_i64").is_err());
+        assert!(check("local.get 0 local.get 0 local.get 0 i32.atomic.rmw16.cmpxchg_u").is_ok());
+        assert!(check("local.get 0 local.get 0 local.get 1 memory.atomic.wait32").is_ok());
+        assert!(check("local.get 0 local.get 0 local.get 1 memory.atomic.wait64").is_err());
+        assert!(check("atomic.fence local.get 0 local.get 0 memory.atomic.notify").is_ok());
+
+        // Shared memories need a maximum.
+        let module = Module::from_wat("(module (memory 1 shared))").unwrap();
+        assert!(validate(&module).is_err());
+        let module = Module::from_wat("(func (result i32) i32.const 0 i32.atomic.load)").unwrap();
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn typed_references() {
+        let check = |body: &str| {
+            let text = format!(
+                "(module (type $f (func (param i32) (result i32))) (elem declare func 0) \
+                 (func (type $f) {}))",
+                body
+            );
+            validate(&Module::from_wat(&text).unwrap())
+        };
+        assert!(check("local.get 0 ref.func 0 call_ref $f").is_ok());
+        assert!(check("local.get 0 ref.null $f call_ref $f").is_ok());
+        assert!(check("local.get 0 ref.null func call_ref $f").is_err());
+        assert!(check("ref.func 0 ref.as_non_null drop local.get 0").is_ok());
+        assert!(check(
+            "block (result (ref $f)) ref.null $f br_on_non_null 0 unreachable end \
+                       local.get 0 return_call_ref $f"
+        )
+        .is_err());
+        assert!(check(
+            "local.get 0 block (result (ref $f)) ref.null $f br_on_non_null 0 \
+                       unreachable end return_call_ref $f"
+        )
+        .is_ok());
+
+        // Non-nullable locals need to be set before they are read, in the same block.
+        let check = |body: &str| {
+            let text = format!(
+                "(module (type $v (func)) (func $g) (elem declare func $g) \
+                 (func (local $r (ref $v)) {}))",
+                body
+            );
+            validate(&Module::from_wat(&text).unwrap())
+        };
+        assert!(check("ref.func $g local.set $r local.get $r drop").is_ok());
+        assert!(check("ref.func $g local.tee $r drop local.get $r drop").is_ok());
+        assert!(check("local.get $r drop").is_err());
+        assert!(check("block ref.func $g local.set $r end local.get $r drop").is_err());
+    }
+
+    #[test]
+    fn gc_instructions() {
+        let check = |body: &str| {
+            let text = format!(
+                "(module (type $s (struct (field i32) (field (mut i64)))) \
+                 (type $a (array (mut i8))) (type $c (array f32)) (data \"\") \
+                 (func (param (ref null $s) (ref $a) anyref) (result i32) {}))",
+                body
+            );
+            validate(&Module::from_wat(&text).unwrap())
+        };
+        assert!(check("local.get 0 struct.get $s 0").is_ok());
+        assert!(check("local.get 0 struct.get $s 1").is_err());
+        assert!(check("local.get 0 struct.get $s 2").is_err());
+        assert!(check("local.get 0 i64.const 1 struct.set $s 1 i32.const 0").is_ok());
+        assert!(check("local.get 0 i32.const 1 struct.set $s 0 i32.const 0").is_err());
+        assert!(check("i32.const 1 i64.const 2 struct.new $s struct.get $s 0").is_ok());
+        assert!(check("local.get 1 i32.const 0 array.get_u $a").is_ok());
+        assert!(check("local.get 1 i32.const 0 array.get $a").is_err());
+        assert!(check("local.get 1 array.len").is_ok());
+        assert!(check("local.get 2 array.len").is_err());
+        assert!(check("i32.const 0 i32.const 1 array.new_data $a 0 array.len").is_ok());
+        assert!(check("f32.const 0 i32.const 1 array.new $c array.len").is_ok());
+        assert!(check(
+            "local.get 1 i32.const 0 i32.const 0 i32.const 0 array.init_data $a 0 \
+                       i32.const 0"
+        )
+        .is_ok());
+        assert!(check("local.get 2 ref.cast (ref $s) struct.get $s 0").is_ok());
+        assert!(check("local.get 2 ref.test (ref $a)").is_ok());
+        assert!(check("local.get 0 ref.test (ref func)").is_err());
+        assert!(check("local.get 0 local.get 0 ref.eq").is_ok());
+        assert!(check("local.get 2 local.get 0 ref.eq").is_err());
+        assert!(check("i32.const 1 ref.i31 i31.get_s").is_ok());
+        assert!(check("local.get 2 extern.convert_any any.convert_extern ref.is_null").is_ok());
+
+        // Casts need a target within the hierarchy of the operand.
+        let module = Module::from_wat(
+            "(module (type $s (struct)) (func (param externref) (result i32) \
+             (block (result (ref $s)) local.get 0 br_on_cast 0 externref (ref $s) \
+             unreachable) drop i32.const 0))",
+        )
+        .unwrap();
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn sub_types() {
+        let check = |types: &str| validate(&Module::from_wat(types).unwrap());
+        assert!(check(
+            "(type $t (sub (struct (field i32)))) (type (sub $t (struct (field i32) (field i64))))"
+        )
+        .is_ok());
+        assert!(
+            check("(type $t (struct (field i32))) (type (sub $t (struct (field i32))))").is_err()
+        );
+        assert!(check("(type $t (sub (struct (field i32)))) (type (sub $t (struct)))").is_err());
+        assert!(check(
+            "(type $t (sub (struct (field (mut i32))))) (type (sub $t (struct (field i32))))"
+        )
+        .is_err());
+        assert!(check(
+            "(type $t (sub (func (param anyref)))) (type (sub $t (func (param eqref))))"
+        )
+        .is_err());
+        assert!(check(
+            "(type $t (sub (func (param eqref)))) (type (sub $t (func (param anyref))))"
+        )
+        .is_ok());
+        // Supertypes must be defined before their subtypes.
+        assert!(check("(type (sub 1 (struct))) (type (sub (struct)))").is_err());
+        assert!(check("(rec (type (sub (struct))) (type (sub 0 (struct))))").is_ok());
+    }
+
+    #[test]
+    fn global_initializers() {
+        let global = |mutability, init| Global {
+            tpe: GlobalType {
+                mutability,
+                tpe: I32,
+            },
+            init: Expr { instr: init },
+        };
+        let mut module = Module::default();
+        module
+            .globals
+            .push(global(Mutability::Const, vec![i32_const(1)]));
+        assert!(validate(&module).is_ok());
+        module.globals.push(global(
+            Mutability::Mut,
+            vec![i32_const(1), i32_const(2), i32_add()],
+        ));
+        assert!(validate(&module).is_err());
+        module.globals[1].init.instr =
+            vec![Instr::Variable(VariableInstr::GlobalGet(GlobalIdx(U32(0))))];
+        // Only imported globals can be referenced.
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn immutable_global_set() {
+        let mut module = single_func(
+            &[],
+            &[],
+            vec![
+                i32_const(1),
+                Instr::Variable(VariableInstr::GlobalSet(GlobalIdx(U32(0)))),
+            ],
+        );
+        module.globals.push(Global {
+            tpe: GlobalType {
+                mutability: Mutability::Const,
+                tpe: I32,
+            },
+            init: Expr {
+                instr: vec![i32_const(0)],
+            },
+        });
+        assert!(validate(&module).is_err());
+        module.globals[0].tpe.mutability = Mutability::Mut;
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn start_function() {
+        let mut module = single_func(&[I32], &[], Vec::new());
+        module.start = Some(Start {
+            func: FuncIdx(U32(0)),
+        });
+        assert!(validate(&module).is_err());
+        module.types[0] = func_type(&[], &[]);
+        assert!(validate(&module).is_ok());
+    }
+
+    #[test]
+    fn duplicate_exports() {
+        let mut module = single_func(&[], &[], Vec::new());
+        let export = Export {
+            name: Name {
+                name: "f".to_string(),
+            },
+            desc: ExportDesc::Func(FuncIdx(U32(0))),
+        };
+        module.exports.push(export.clone());
+        assert!(validate(&module).is_ok());
+        module.exports.push(export);
+        assert!(validate(&module).is_err());
+    }
+
+    #[test]
+    fn memory_limits() {
+        let mut module = Module::default();
+        module.mems.push(crate::wasm::mem::Mem {
+            tpe: MemType {
+                lim: Limits {
+                    min: U64(2),
+                    max: Some(U64(1)),
+                },
+                index: IndexType::I32,
+                shared: false,
+            },
+        });
+        assert!(validate(&module).is_err());
+        module.mems[0].tpe.lim = Limits {
+            min: U64(MAX_PAGES + 1),
+            max: None,
+        };
+        assert!(validate(&module).is_err());
+        module.mems[0].tpe.index = IndexType::I64;
+        assert!(validate(&module).is_ok());
+        module.mems[0].tpe.lim.min = U64(MAX_PAGES_64 + 1);
+        assert!(validate(&module).is_err());
+    }
+}
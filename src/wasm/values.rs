@@ -13,7 +13,7 @@ impl Deref for Byte {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 #[repr(transparent)]
 pub struct U32(pub u32);
 
@@ -101,6 +101,18 @@ impl Deref for I64 {
     }
 }
 
+/// 128 bit vector, with the first lane in the least significant bits.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[repr(transparent)]
+pub struct V128(pub u128);
+
+impl Deref for V128 {
+    type Target = u128;
+    fn deref(&self) -> &u128 {
+        &self.0
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 #[repr(transparent)]
 pub struct F64(pub f64);
@@ -0,0 +1,179 @@
+//! Script format of the specification test suite (`.wast`).
+//!
+//! A script is a sequence of commands that define modules, invoke their exports and assert the
+//! outcome: results, traps, and modules that must fail to parse (malformed), validate (invalid),
+//! link (unlinkable) or instantiate. [run] executes a script against the crate's parser,
+//! validator and [runtime](crate::runtime), and reports the outcome of every assertion.
+//!
+//! Modules can import from the `spectest` module, which provides the functions, globals, table
+//! and memory the test suite expects.
+//!
+//! # Examples
+//!
+//! ```
+//! use wasm_parse::wast;
+//!
+//! let report = wast::run(
+//!     r#"(module (func (export "div") (param i32 i32) (result i32)
+//!         (i32.div_u (local.get 0) (local.get 1))))
+//!     (assert_return (invoke "div" (i32.const 7) (i32.const 2)) (i32.const 3))
+//!     (assert_trap (invoke "div" (i32.const 7) (i32.const 0)) "integer divide by zero")
+//!     (assert_invalid (module (func (result i32))) "type mismatch")"#,
+//! )?;
+//! assert_eq!(report.passed, 4);
+//! assert!(report.failures.is_empty());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::parse::text::TextError;
+use crate::runtime::Value;
+use crate::wasm::types::{HeapType, NumType};
+
+mod run;
+mod script;
+
+pub use run::Runner;
+
+/// Parses a script into its commands.
+pub fn parse(text: &str) -> Result<Vec<Command>, TextError> {
+    script::parse(text)
+}
+
+/// Parses and runs a script with a fresh [Runner].
+pub fn run(text: &str) -> Result<Report, TextError> {
+    let mut runner = Runner::new();
+    for command in parse(text)? {
+        runner.run(&command);
+    }
+    Ok(runner.report())
+}
+
+/// Command of a script along with the line (starting at 1) it starts at.
+#[derive(Debug, Clone)]
+pub struct Command {
+    pub line: usize,
+    pub kind: CommandKind,
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum CommandKind {
+    /// Defines a module and makes it the current one.
+    Module {
+        id: Option<String>,
+        module: ScriptModule,
+    },
+    /// Makes the exports of a module importable under `name`.
+    Register {
+        name: String,
+        id: Option<String>,
+    },
+    Action(Action),
+    AssertReturn {
+        action: Action,
+        expected: Vec<Expected>,
+    },
+    AssertTrap {
+        action: Action,
+        message: String,
+    },
+    AssertExhaustion {
+        action: Action,
+        message: String,
+    },
+    /// The action throws an exception that no handler catches.
+    AssertException {
+        action: Action,
+    },
+    AssertMalformed {
+        module: ScriptModule,
+        message: String,
+    },
+    AssertInvalid {
+        module: ScriptModule,
+        message: String,
+    },
+    AssertUnlinkable {
+        module: ScriptModule,
+        message: String,
+    },
+    /// `assert_trap` of a module: instantiating it traps.
+    AssertUninstantiable {
+        module: ScriptModule,
+        message: String,
+    },
+    /// A command this crate doesn't support, e.g. from a proposal it doesn't implement.
+    Unsupported(String),
+}
+
+/// Module defined within a script.
+#[derive(Debug, Clone)]
+pub enum ScriptModule {
+    /// Module in the text format, written out within the script.
+    Text(String),
+    /// Module in the text format given as strings (`module quote`). The text isn't necessarily
+    /// valid UTF-8.
+    Quote(Vec<u8>),
+    /// Module in the binary format.
+    Binary(Vec<u8>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// Calls the exported function `name` of the module `id`, or of the current module.
+    Invoke {
+        id: Option<String>,
+        name: String,
+        args: Vec<Value>,
+    },
+    /// Reads the exported global `name` of the module `id`, or of the current module.
+    Get { id: Option<String>, name: String },
+}
+
+/// Expected result of an action.
+#[derive(Debug, Clone)]
+pub enum Expected {
+    /// Exactly this value. Floats compare by their bits.
+    Value(Value),
+    /// Canonical NaN of the float type, with either sign.
+    CanonicalNan(NumType),
+    /// Any NaN of the float type with the most significant bit of the payload set.
+    ArithmeticNan(NumType),
+    /// Any non-null function reference.
+    FuncRef,
+    /// Any non-null external reference.
+    ExternRef,
+    /// Any non-null reference of the abstract type `any`, `eq`, `i31`, `struct` or `array`.
+    Ref(HeapType),
+    /// Any null reference.
+    Null,
+    /// Any of the results.
+    Either(Vec<Expected>),
+    /// Vector of `f32x4` or `f64x2` shape whose lanes each match a float or a NaN pattern.
+    Lanes(Vec<Expected>),
+}
+
+/// Outcome of running a script.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// Number of commands that behaved as expected.
+    pub passed: usize,
+    /// Commands that weren't run because the crate doesn't support them.
+    pub skipped: Vec<Skipped>,
+    pub failures: Vec<Failure>,
+}
+
+/// Command that wasn't run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+    pub line: usize,
+    /// Keyword of the command, e.g. `module` for a module definition.
+    pub command: String,
+}
+
+/// Command that didn't behave as expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub line: usize,
+    pub reason: String,
+}
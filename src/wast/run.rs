@@ -0,0 +1,542 @@
+//! Script execution.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{Action, Command, CommandKind, Expected, Failure, Report, ScriptModule, Skipped};
+use crate::parse::binary::{Parse, ParseError, ParseErrorKind, ParsingData, WasmBinary};
+use crate::parse::text::{TextError, TextErrorKind};
+use crate::runtime::{AnyRef, Instance, InstantiationError, Linker, Store, Trap, Value};
+use crate::wasm::module::Module;
+use crate::wasm::types::{
+    FuncType, HeapType, IndexType, Limits, MemType, Mutability, NumType, RefType, ResultType,
+    TableType, ValType,
+};
+use crate::wasm::validate::ValidationErrorKind;
+use crate::wasm::values::U64;
+
+/// Runs the commands of a script, keeping the modules they define and register.
+///
+/// # Examples
+///
+/// ```
+/// use wasm_parse::wast::{self, Runner};
+///
+/// let mut runner = Runner::new();
+/// let script = r#"(module (func (export "f") (result i32) (i32.const 1)))
+///     (assert_return (invoke "f") (i32.const 2))"#;
+/// for command in wast::parse(script)? {
+///     runner.run(&command);
+/// }
+/// let report = runner.report();
+/// assert_eq!(report.failures[0].line, 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Runner {
+    linker: Linker,
+    /// Instances of the modules with an id.
+    instances: HashMap<String, Instance>,
+    /// Instance of the last module defined.
+    current: Option<Instance>,
+    report: Report,
+}
+
+impl Runner {
+    /// Creates a runner whose modules can import from the `spectest` module.
+    pub fn new() -> Runner {
+        let mut linker = Linker::new(&Store::new());
+        let num = |tpe| ValType::Num(tpe);
+        let prints: [(&str, Vec<ValType>); 7] = [
+            ("print", vec![]),
+            ("print_i32", vec![num(NumType::I32)]),
+            ("print_i64", vec![num(NumType::I64)]),
+            ("print_f32", vec![num(NumType::F32)]),
+            ("print_f64", vec![num(NumType::F64)]),
+            ("print_i32_f32", vec![num(NumType::I32), num(NumType::F32)]),
+            ("print_f64_f64", vec![num(NumType::F64), num(NumType::F64)]),
+        ];
+        for (name, params) in prints.iter() {
+            let tpe = FuncType {
+                params: ResultType {
+                    types: params.clone(),
+                },
+                result: ResultType { types: Vec::new() },
+            };
+            linker.func("spectest", name, tpe, |_, _| Ok(Vec::new()));
+        }
+        let limits = |min, max| Limits {
+            min: U64(min),
+            max: Some(U64(max)),
+        };
+        linker
+            .global("spectest", "global_i32", Mutability::Const, Value::I32(666))
+            .global("spectest", "global_i64", Mutability::Const, Value::I64(666))
+            .global(
+                "spectest",
+                "global_f32",
+                Mutability::Const,
+                Value::F32(666.6),
+            )
+            .global(
+                "spectest",
+                "global_f64",
+                Mutability::Const,
+                Value::F64(666.6),
+            )
+            .table(
+                "spectest",
+                "table",
+                TableType {
+                    lim: limits(10, 20),
+                    index: IndexType::I32,
+                    tpe: RefType::FUNCREF,
+                },
+            )
+            .memory(
+                "spectest",
+                "memory",
+                MemType {
+                    lim: limits(1, 2),
+                    index: IndexType::I32,
+                    shared: false,
+                },
+            );
+        Runner {
+            linker,
+            instances: HashMap::new(),
+            current: None,
+            report: Report::default(),
+        }
+    }
+
+    /// Runs a command and records its outcome.
+    pub fn run(&mut self, command: &Command) {
+        let outcome = match &command.kind {
+            CommandKind::Unsupported(kw) => {
+                self.report.skipped.push(Skipped {
+                    line: command.line,
+                    command: kw.clone(),
+                });
+                return;
+            }
+            kind => self.outcome(kind),
+        };
+        match outcome {
+            Ok(()) => self.report.passed += 1,
+            Err(reason) => self.report.failures.push(Failure {
+                line: command.line,
+                reason,
+            }),
+        }
+    }
+
+    /// Outcome of the commands run so far.
+    pub fn report(&self) -> Report {
+        self.report.clone()
+    }
+
+    fn outcome(&mut self, kind: &CommandKind) -> Result<(), String> {
+        match kind {
+            CommandKind::Module { id, module } => {
+                let module = parse(module)?;
+                let instance = self
+                    .linker
+                    .instantiate(&module)
+                    .map_err(|err| err.to_string())?;
+                if let Some(id) = id {
+                    self.instances.insert(id.clone(), instance.clone());
+                }
+                self.current = Some(instance);
+                Ok(())
+            }
+            CommandKind::Register { name, id } => {
+                let instance = self.instance(id)?.clone();
+                self.linker.instance(name, &instance);
+                Ok(())
+            }
+            CommandKind::Action(action) => self.action(action).map(drop).map_err(expected_result),
+            CommandKind::AssertReturn { action, expected } => {
+                let results = self.action(action).map_err(expected_result)?;
+                let matches = results.len() == expected.len()
+                    && results.iter().zip(expected).all(|(v, e)| matches(*v, e));
+                if matches {
+                    Ok(())
+                } else {
+                    Err(format!("expected {:?}, got {:?}", expected, results))
+                }
+            }
+            CommandKind::AssertTrap { action, message } => match self.action(action) {
+                Err(trap) => trap_matches(&trap, message),
+                Ok(results) => Err(format!("expected trap, got {:?}", results)),
+            },
+            CommandKind::AssertExhaustion { action, .. } => match self.action(action) {
+                Err(Trap::CallStackExhausted) => Ok(()),
+                outcome => Err(format!("expected call stack exhaustion, got {:?}", outcome)),
+            },
+            CommandKind::AssertException { action } => match self.action(action) {
+                Err(Trap::UncaughtException) => Ok(()),
+                outcome => Err(format!("expected exception, got {:?}", outcome)),
+            },
+            CommandKind::AssertMalformed { module, message } => match parse_module(module) {
+                Err(err) => error_matches(&err, malformed_messages(&err), message),
+                Ok(_) => Err("expected malformed module, parsed".to_string()),
+            },
+            CommandKind::AssertInvalid { module, message } => match parse(module)?.validate() {
+                Err(err) => error_matches(&err, invalid_messages(err.kind()), message),
+                Ok(()) => Err("expected invalid module, validated".to_string()),
+            },
+            CommandKind::AssertUnlinkable { module, .. } => {
+                match self.linker.instantiate(&parse(module)?) {
+                    Err(InstantiationError::UnresolvedImport { .. })
+                    | Err(InstantiationError::IncompatibleImport { .. }) => Ok(()),
+                    Err(err) => Err(format!("expected unlinkable module, got: {}", err)),
+                    Ok(_) => Err("expected unlinkable module, instantiated".to_string()),
+                }
+            }
+            CommandKind::AssertUninstantiable { module, message } => {
+                match self.linker.instantiate(&parse(module)?) {
+                    Err(InstantiationError::Trap(trap)) => trap_matches(&trap, message),
+                    Err(err) => Err(format!("expected trap, got: {}", err)),
+                    Ok(_) => Err("expected trap, instantiated".to_string()),
+                }
+            }
+            CommandKind::Unsupported(_) => unreachable!("unsupported commands are skipped"),
+        }
+    }
+
+    fn instance(&self, id: &Option<String>) -> Result<&Instance, String> {
+        match id {
+            Some(id) => self
+                .instances
+                .get(id)
+                .ok_or_else(|| format!("unknown module {}", id)),
+            None => self.current.as_ref().ok_or_else(|| "no module".to_string()),
+        }
+    }
+
+    fn action(&self, action: &Action) -> Result<Vec<Value>, Trap> {
+        match action {
+            Action::Invoke { id, name, args } => {
+                let instance = self.instance(id).map_err(Trap::Host)?;
+                instance.invoke(name, args)
+            }
+            Action::Get { id, name } => {
+                let instance = self.instance(id).map_err(Trap::Host)?;
+                match instance.global(name) {
+                    Some(value) => Ok(vec![value]),
+                    None => Err(Trap::UnknownExport(name.clone())),
+                }
+            }
+        }
+    }
+}
+
+impl Default for Runner {
+    fn default() -> Runner {
+        Runner::new()
+    }
+}
+
+/// Error of a module that couldn't be parsed, from the text or the binary format.
+#[derive(Debug)]
+enum ModuleError {
+    Text(TextError),
+    Binary(ParseError),
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModuleError::Text(err) => err.fmt(f),
+            ModuleError::Binary(err) => err.fmt(f),
+        }
+    }
+}
+
+fn parse_module(module: &ScriptModule) -> Result<Module, ModuleError> {
+    match module {
+        ScriptModule::Text(text) => Module::from_wat(text).map_err(ModuleError::Text),
+        ScriptModule::Quote(bytes) => match std::str::from_utf8(bytes) {
+            Ok(text) => Module::from_wat(text).map_err(ModuleError::Text),
+            Err(err) => {
+                let text = String::from_utf8_lossy(bytes);
+                let kind = TextErrorKind::InvalidUtf8;
+                Err(ModuleError::Text(TextError::new(
+                    &text,
+                    err.valid_up_to(),
+                    kind,
+                )))
+            }
+        },
+        ScriptModule::Binary(bytes) => {
+            let bin = WasmBinary::from(bytes.clone().into_boxed_slice());
+            Module::parse(&mut ParsingData::new(&bin)).map_err(ModuleError::Binary)
+        }
+    }
+}
+
+fn parse(module: &ScriptModule) -> Result<Module, String> {
+    parse_module(module).map_err(|err| err.to_string())
+}
+
+fn expected_result(trap: Trap) -> String {
+    format!("expected result, got trap: {}", trap)
+}
+
+/// Checks that the message of a trap starts with the expected one. The test suite sometimes
+/// only gives the start of the message.
+fn trap_matches(trap: &Trap, message: &str) -> Result<(), String> {
+    if trap.to_string().starts_with(message) {
+        Ok(())
+    } else {
+        Err(format!("expected trap \"{}\", got \"{}\"", message, trap))
+    }
+}
+
+/// Messages of the reference interpreter for the errors of the text format that don't have a
+/// more specific one. Which of them it gives depends on how its lexer splits the tokens.
+const SYNTAX: &[&str] = &[
+    "unexpected token",
+    "unexpected end",
+    "unknown operator",
+    "constant out of range",
+    "result before parameter",
+];
+
+/// Starts of the messages the test suite may expect for a module that can't be parsed. The
+/// messages are the ones of the reference interpreter, so only their category is compared.
+fn malformed_messages(err: &ModuleError) -> &'static [&'static str] {
+    match err {
+        ModuleError::Text(err) => match err.kind() {
+            TextErrorKind::InvalidUtf8 => &["malformed UTF-8 encoding"],
+            TextErrorKind::UnknownIdentifier { .. } => &["unknown"],
+            TextErrorKind::DuplicateIdentifier { .. } => &["duplicate"],
+            TextErrorKind::MismatchedLabel(_) => &["mismatching label"],
+            TextErrorKind::ImportAfterDefinition => &["import after"],
+            TextErrorKind::TypeMismatch(_) => &["inline function type", "unexpected token"],
+            TextErrorKind::InvalidAlignment(_) => &["alignment"],
+            TextErrorKind::MultipleStart => &["multiple start sections"],
+            TextErrorKind::UnexpectedEof
+            | TextErrorKind::UnexpectedChar(_)
+            | TextErrorKind::UnterminatedString
+            | TextErrorKind::UnterminatedComment
+            | TextErrorKind::Unexpected { .. }
+            | TextErrorKind::InvalidNumber(_)
+            | TextErrorKind::InvalidString(_)
+            | TextErrorKind::UnknownInstruction(_)
+            | TextErrorKind::NestingTooDeep => SYNTAX,
+        },
+        ModuleError::Binary(err) => match err.kind() {
+            ParseErrorKind::UnexpectedEof => &["unexpected end", "length out of bounds"],
+            ParseErrorKind::InvalidMagic => &["magic header not detected"],
+            ParseErrorKind::UnsupportedVersion(_) => &["unknown binary version"],
+            ParseErrorKind::UnknownSection(_) => &["malformed section id"],
+            ParseErrorKind::UnknownOpcode { .. } => &["illegal opcode", "unknown operator"],
+            ParseErrorKind::LebOverflow => {
+                &["integer representation too long", "integer too large"]
+            }
+            ParseErrorKind::InvalidUtf8 => &["malformed UTF-8 encoding"],
+            ParseErrorKind::SectionSizeMismatch { .. } => {
+                &["section size mismatch", "unexpected end"]
+            }
+            ParseErrorKind::OutOfOrderSection(_) | ParseErrorKind::DuplicateSection(_) => &[
+                "unexpected content after last section",
+                "junk after last section",
+            ],
+            ParseErrorKind::InvalidEncoding { .. } | ParseErrorKind::InvalidBlockType(_) => {
+                &["malformed", "invalid", "integer", "tag attribute"]
+            }
+            ParseErrorKind::ZeroByteExpected(_) => &["zero"],
+            ParseErrorKind::UnsortedIndices(_) => &["malformed"],
+            ParseErrorKind::FuncCodeMismatch { .. } => {
+                &["function and code section have inconsistent lengths"]
+            }
+            ParseErrorKind::DataCountMismatch { .. } => {
+                &["data count and data section have inconsistent lengths"]
+            }
+            ParseErrorKind::DataCountRequired => &["data count section required"],
+            ParseErrorKind::TooManyLocals(_) => &["too many locals"],
+            ParseErrorKind::NestingTooDeep => &["unexpected end"],
+        },
+    }
+}
+
+/// Starts of the messages the test suite may expect for an invalid module.
+fn invalid_messages(kind: &ValidationErrorKind) -> &'static [&'static str] {
+    use ValidationErrorKind::*;
+    match kind {
+        UnknownType(_) => &["unknown type"],
+        UnknownFunc(_) => &["unknown function"],
+        UnknownTable(_) => &["unknown table"],
+        UnknownMem(_) => &["unknown memory"],
+        UnknownGlobal(_) => &["unknown global"],
+        UnknownTag(_) => &["unknown tag"],
+        UnknownElem(_) => &["unknown elem segment"],
+        UnknownData(_) => &["unknown data segment"],
+        UnknownLocal(_) => &["unknown local"],
+        UnknownLabel(_) => &["unknown label"],
+        UnknownField { .. } => &["unknown field"],
+        NotFuncType(_)
+        | NotStructType(_)
+        | NotArrayType(_)
+        | EmptyStack
+        | TypeMismatch { .. }
+        | ExpectedRef(_)
+        | ValuesRemaining(_)
+        | LabelNotRef(_)
+        | LabelMismatch { .. }
+        | BrTableArity { .. }
+        | CastMismatch { .. }
+        | TailCallMismatch { .. }
+        | NotFuncRefTable { .. }
+        | SelectMismatch(..)
+        | UntypedSelect
+        | PackedField(_)
+        | NotDefaultable(_)
+        | TableCopyMismatch { .. }
+        | TableInitMismatch { .. }
+        | ArrayCopyMismatch { .. }
+        | ElemArrayMismatch { .. }
+        | DataArrayMismatch(_)
+        | TableWithoutInit(_)
+        | ElemTableMismatch { .. } => &["type mismatch"],
+        RethrowTarget(_) => &["invalid rethrow label"],
+        SelectArity(_) => &["invalid result arity"],
+        UninitializedLocal(_) => &["uninitialized local"],
+        ImmutableGlobal(_) => &["global is immutable"],
+        ImmutableField => &["field is immutable"],
+        UndeclaredFuncRef(_) => &["undeclared function reference"],
+        OffsetOutOfRange(_) => &["offset out of range"],
+        AlignmentTooLarge { .. } | AtomicAlignment { .. } => &["alignment"],
+        LaneOutOfRange { .. } => &["invalid lane index"],
+        TagResults { .. } => &["non-empty tag result type"],
+        NotConstant | ConstGlobalMutable(_) => &["constant expression required"],
+        ConstGlobalNotImported(_) => &["unknown global", "constant expression required"],
+        MinTooLarge { .. } | MaxTooLarge { .. } => &["memory size", "table size"],
+        MaxBelowMin { .. } => &["size minimum must not be greater than maximum"],
+        SharedWithoutMax => &["shared memory must have maximum"],
+        SupertypeOrder(_) | MultipleSupertypes | FinalSupertype(_) | SupertypeMismatch(_) => {
+            &["sub type", "unknown type"]
+        }
+        StartType { .. } => &["start function"],
+        DuplicateExport(_) => &["duplicate export name"],
+    }
+}
+
+/// Checks that the expected message of an error falls in one of the given categories.
+fn error_matches(err: &dyn fmt::Display, messages: &[&str], message: &str) -> Result<(), String> {
+    if messages.iter().any(|start| message.starts_with(start)) {
+        Ok(())
+    } else {
+        Err(format!("expected \"{}\", got \"{}\"", message, err))
+    }
+}
+
+fn matches(value: Value, expected: &Expected) -> bool {
+    match (expected, value) {
+        (Expected::Value(expected), value) => *expected == value,
+        (Expected::CanonicalNan(NumType::F32), Value::F32(x)) => {
+            x.to_bits() & 0x7FFF_FFFF == 0x7FC0_0000
+        }
+        (Expected::CanonicalNan(NumType::F64), Value::F64(x)) => {
+            x.to_bits() & 0x7FFF_FFFF_FFFF_FFFF == 0x7FF8_0000_0000_0000
+        }
+        (Expected::ArithmeticNan(NumType::F32), Value::F32(x)) => {
+            x.is_nan() && x.to_bits() & 0x0040_0000 != 0
+        }
+        (Expected::ArithmeticNan(NumType::F64), Value::F64(x)) => {
+            x.is_nan() && x.to_bits() & 0x0008_0000_0000_0000 != 0
+        }
+        (Expected::FuncRef, Value::FuncRef(Some(_))) => true,
+        (Expected::ExternRef, Value::ExternRef(Some(_))) => true,
+        (Expected::Ref(heap), Value::AnyRef(Some(any))) => matches!(
+            (heap, any),
+            (HeapType::Any, _)
+                | (
+                    HeapType::Eq,
+                    AnyRef::I31(_) | AnyRef::Struct(_) | AnyRef::Array(_)
+                )
+                | (HeapType::I31, AnyRef::I31(_))
+                | (HeapType::Struct, AnyRef::Struct(_))
+                | (HeapType::Array, AnyRef::Array(_))
+        ),
+        (Expected::Null, value) => value.is_null(),
+        (Expected::Either(results), value) => results.iter().any(|e| matches(value, e)),
+        (Expected::Lanes(lanes), Value::V128(v)) => {
+            let bits = 128 / lanes.len();
+            lanes.iter().enumerate().all(|(i, expected)| {
+                let lane = v >> (i * bits);
+                let value = match bits {
+                    32 => Value::F32(f32::from_bits(lane as u32)),
+                    _ => Value::F64(f64::from_bits(lane as u64)),
+                };
+                matches(value, expected)
+            })
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::wast::{self, Skipped};
+
+    fn failures(script: &str) -> Vec<usize> {
+        let report = wast::run(script).unwrap();
+        report.failures.iter().map(|failure| failure.line).collect()
+    }
+
+    #[test]
+    fn assertions() {
+        let report = wast::run(
+            r#"(module $a
+                (global (export "g") i32 (i32.const 7))
+                (func (export "nan") (result f32) (f32.div (f32.const 0) (f32.const 0)))
+                (func (export "loop") (call 1)))
+            (register "a" $a)
+            (module (import "a" "g" (global i32)) (import "spectest" "print_i32" (func (param i32))))
+            (assert_return (get $a "g") (i32.const 7))
+            (assert_return (invoke $a "nan") (f32.const nan:canonical))
+            (assert_return (invoke $a "nan") (either (f32.const 0) (f32.const nan:arithmetic)))
+            (assert_exhaustion (invoke $a "loop") "call stack exhausted")
+            (assert_malformed (module quote "(module (func (i32.const)))") "unexpected token")
+            (assert_malformed (module binary "\00asm\02\00\00\00") "unknown binary version")
+            (assert_unlinkable (module (import "a" "g" (global i64))) "incompatible import type")
+            (assert_unlinkable (module (import "a" "h" (func))) "unknown import")
+            (assert_trap (module (memory 0) (data (i32.const 1) "x")) "out of bounds memory access")
+            (module (tag $e (param i32)) (func (export "throw") (throw $e (i32.const 1))))
+            (assert_exception (invoke "throw"))
+            (module definition (func))"#,
+        )
+        .unwrap();
+        assert_eq!(report.failures, Vec::new());
+        assert_eq!(report.passed, 14);
+        assert_eq!(
+            report.skipped,
+            [Skipped {
+                line: 18,
+                command: "module".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        assert_eq!(
+            failures(
+                r#"(module (func (export "f") (param i32) (result i32) (local.get 0)))
+                (assert_return (invoke "f" (i32.const 1)) (i32.const 2))
+                (assert_return (invoke "f" (i64.const 1)) (i32.const 1))
+                (assert_trap (invoke "f" (i32.const 1)) "unreachable")
+                (assert_invalid (module (func)) "type mismatch")
+                (assert_malformed (module quote "(module)") "unexpected token")
+                (assert_return (invoke $b "f" (i32.const 1)) (i32.const 1))
+                (module (func (i32.add)))
+                (assert_invalid (module (func (local.get 0))) "type mismatch")
+                (assert_malformed (module binary "\00asm\02\00\00\00") "magic header")"#
+            ),
+            vec![2, 3, 4, 5, 6, 7, 8, 9, 10]
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Script parser.
+//!
+//! Commands are read with the tokens and helpers of the [text parser](crate::parse::text). The
+//! modules within a script are kept as source and parsed when the command runs, since some of
+//! them are expected to fail.
+
+use super::{Action, Command, CommandKind, Expected, ScriptModule};
+use crate::parse::text::lexer::Token;
+use crate::parse::text::parser::{abstract_heap_type, Parser};
+use crate::parse::text::values;
+use crate::parse::text::{TextError, TextErrorKind};
+use crate::runtime::Value;
+use crate::wasm::module::Module;
+use crate::wasm::types::{HeapType, NumType};
+
+/// Fields of a module, which make up the whole script when it's a single module written without
+/// the enclosing `(module ...)`.
+const MODULE_FIELDS: [&str; 12] = [
+    "type", "rec", "import", "func", "table", "memory", "tag", "global", "export", "start", "elem",
+    "data",
+];
+
+pub(super) fn parse(text: &str) -> Result<Vec<Command>, TextError> {
+    let mut parser = Parser::new(text)?;
+    if MODULE_FIELDS.iter().any(|field| parser.peek_field(field)) {
+        let module = ScriptModule::Text(format!("(module {})", text));
+        let kind = CommandKind::Module { id: None, module };
+        return Ok(vec![Command { line: 1, kind }]);
+    }
+    let mut commands = Vec::new();
+    while parser.peek().is_some() {
+        let offset = parser.offset_at(parser.position());
+        let line = text[..offset].matches('\n').count() + 1;
+        let kind = command(&mut parser)?;
+        commands.push(Command { line, kind });
+    }
+    Ok(commands)
+}
+
+fn command(p: &mut Parser) -> Result<CommandKind, TextError> {
+    if p.peek_field("module") {
+        return Ok(match module(p)? {
+            Some((id, module)) => CommandKind::Module { id, module },
+            None => CommandKind::Unsupported("module".to_string()),
+        });
+    }
+    if p.peek_field("invoke") || p.peek_field("get") {
+        return Ok(CommandKind::Action(action(p)?));
+    }
+    p.lparen()?;
+    let kw = p.any_keyword()?;
+    let kind = match kw {
+        "register" => CommandKind::Register {
+            name: p.name()?.name,
+            id: p.eat_id().map(str::to_string),
+        },
+        "assert_return" => {
+            let action = action(p)?;
+            let mut expected = Vec::new();
+            while p.peek() == Some(&Token::LParen) {
+                expected.push(result(p)?);
+            }
+            CommandKind::AssertReturn { action, expected }
+        }
+        "assert_trap" if p.peek_field("module") => match module(p)? {
+            Some((_, module)) => CommandKind::AssertUninstantiable {
+                module,
+                message: p.name()?.name,
+            },
+            None => return unsupported(p, kw),
+        },
+        "assert_trap" => CommandKind::AssertTrap {
+            action: action(p)?,
+            message: p.name()?.name,
+        },
+        "assert_exhaustion" => CommandKind::AssertExhaustion {
+            action: action(p)?,
+            message: p.name()?.name,
+        },
+        "assert_exception" => CommandKind::AssertException { action: action(p)? },
+        "assert_malformed" | "assert_invalid" | "assert_unlinkable" => {
+            let module = match module(p)? {
+                Some((_, module)) => module,
+                None => return unsupported(p, kw),
+            };
+            let message = p.name()?.name;
+            match kw {
+                "assert_malformed" => CommandKind::AssertMalformed { module, message },
+                "assert_invalid" => CommandKind::AssertInvalid { module, message },
+                _ => CommandKind::AssertUnlinkable { module, message },
+            }
+        }
+        _ => return unsupported(p, kw),
+    };
+    p.rparen()?;
+    Ok(kind)
+}
+
+/// Skips the rest of a command the crate doesn't support.
+fn unsupported(p: &mut Parser, kw: &str) -> Result<CommandKind, TextError> {
+    p.skip_to_close()?;
+    Ok(CommandKind::Unsupported(kw.to_string()))
+}
+
+/// Parses `(module $id? ...)`. Returns `None` for the module definitions and instances of newer
+/// versions of the test suite, which aren't supported.
+fn module(p: &mut Parser) -> Result<Option<(Option<String>, ScriptModule)>, TextError> {
+    let start = p.offset_at(p.position());
+    p.lparen()?;
+    p.keyword("module")?;
+    let id = p.eat_id().map(str::to_string);
+    let module = if p.eat_keyword("binary") {
+        ScriptModule::Binary(strings(p)?)
+    } else if p.eat_keyword("quote") {
+        ScriptModule::Quote(strings(p)?)
+    } else if p.eat_keyword("definition") || p.eat_keyword("instance") {
+        p.skip_to_close()?;
+        return Ok(None);
+    } else {
+        p.skip_to_close()?;
+        let end = p.offset_at(p.position() - 1) + 1;
+        ScriptModule::Text(p.text()[start..end].to_string())
+    };
+    Ok(Some((id, module)))
+}
+
+/// Concatenates the strings up to the closing parenthesis.
+fn strings(p: &mut Parser) -> Result<Vec<u8>, TextError> {
+    let mut bytes = Vec::new();
+    while p.peek() != Some(&Token::RParen) {
+        bytes.extend(p.string()?);
+    }
+    p.rparen()?;
+    Ok(bytes)
+}
+
+fn action(p: &mut Parser) -> Result<Action, TextError> {
+    p.lparen()?;
+    let action = if p.eat_keyword("invoke") {
+        let id = p.eat_id().map(str::to_string);
+        let name = p.name()?.name;
+        let mut args = Vec::new();
+        while p.peek() == Some(&Token::LParen) {
+            p.lparen()?;
+            let kw = p.any_keyword()?;
+            args.push(constant(p, kw)?);
+            p.rparen()?;
+        }
+        Action::Invoke { id, name, args }
+    } else {
+        p.keyword("get")?;
+        let id = p.eat_id().map(str::to_string);
+        Action::Get {
+            id,
+            name: p.name()?.name,
+        }
+    };
+    p.rparen()?;
+    Ok(action)
+}
+
+/// Parses the constant `(kw ...)` after its keyword.
+fn constant(p: &mut Parser, kw: &str) -> Result<Value, TextError> {
+    Ok(match kw {
+        "i32.const" => Value::I32(p.number(values::parse_i32)? as i32),
+        "i64.const" => Value::I64(p.number(values::parse_i64)? as i64),
+        "f32.const" => Value::F32(f32::from_bits(p.number(values::parse_f32)?)),
+        "f64.const" => Value::F64(f64::from_bits(p.number(values::parse_f64)?)),
+        "ref.null" => {
+            let heap = match p.peek() {
+                Some(&Token::Keyword(kw)) => abstract_heap_type(kw),
+                _ => None,
+            };
+            let heap = heap.ok_or_else(|| p.unexpected("abstract heap type"))?;
+            p.next()?;
+            Value::null(heap, &Module::default())
+        }
+        "ref.extern" | "ref.host" => Value::ExternRef(Some(p.u32()?)),
+        "v128.const" => Value::V128(*p.v128()?),
+        _ => {
+            let kind = TextErrorKind::Unexpected {
+                expected: "constant",
+                found: kw.to_string(),
+            };
+            return Err(p.error_at(p.position() - 1, kind));
+        }
+    })
+}
+
+/// Parses an expected result.
+fn result(p: &mut Parser) -> Result<Expected, TextError> {
+    p.lparen()?;
+    let kw = p.any_keyword()?;
+    let expected = match kw {
+        "either" => {
+            let mut results = Vec::new();
+            while p.peek() == Some(&Token::LParen) {
+                results.push(result(p)?);
+            }
+            Expected::Either(results)
+        }
+        "f32.const" => float(p, NumType::F32)?,
+        "f64.const" => float(p, NumType::F64)?,
+        "v128.const" if p.eat_keyword("f32x4") => Expected::Lanes(
+            (0..4)
+                .map(|_| float(p, NumType::F32))
+                .collect::<Result<_, _>>()?,
+        ),
+        "v128.const" if p.eat_keyword("f64x2") => Expected::Lanes(
+            (0..2)
+                .map(|_| float(p, NumType::F64))
+                .collect::<Result<_, _>>()?,
+        ),
+        "ref.func" if p.peek() == Some(&Token::RParen) => Expected::FuncRef,
+        "ref.extern" if p.peek() == Some(&Token::RParen) => Expected::ExternRef,
+        "ref.null" if p.peek() == Some(&Token::RParen) => Expected::Null,
+        "ref.any" => Expected::Ref(HeapType::Any),
+        "ref.eq" => Expected::Ref(HeapType::Eq),
+        "ref.i31" => Expected::Ref(HeapType::I31),
+        "ref.struct" => Expected::Ref(HeapType::Struct),
+        "ref.array" => Expected::Ref(HeapType::Array),
+        _ => Expected::Value(constant(p, kw)?),
+    };
+    p.rparen()?;
+    Ok(expected)
+}
+
+/// Parses an expected float, which is either a NaN pattern or a value.
+fn float(p: &mut Parser, tpe: NumType) -> Result<Expected, TextError> {
+    let expected = match p.peek() {
+        Some(Token::Number("nan:canonical")) => Expected::CanonicalNan(tpe),
+        Some(Token::Number("nan:arithmetic")) => Expected::ArithmeticNan(tpe),
+        _ if tpe == NumType::F32 => return Ok(Expected::Value(constant(p, "f32.const")?)),
+        _ => return Ok(Expected::Value(constant(p, "f64.const")?)),
+    };
+    p.next()?;
+    Ok(expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        let commands = parse(
+            r#"(module $m (func (export "f") (param i32) (result i32) (local.get 0)))
+            (register "m" $m)
+            (invoke "f" (i32.const -1))
+            ;; comment
+            (assert_return (invoke $m "f" (i32.const 1)) (i32.const 1))
+            (assert_return (get "g") (f32.const nan:canonical) (either (ref.func) (ref.null extern)))
+            (assert_trap (invoke "f" (f64.const -0x1p-1) (ref.extern 3)) "unreachable")
+            (assert_malformed (module quote "(func" ")") "unexpected end")
+            (assert_malformed (module binary "\00asm" "\01\00\00\00") "length")
+            (assert_trap (module (start 0) (func unreachable)) "unreachable")
+            (assert_exception (invoke "f"))"#,
+        )
+        .unwrap();
+        assert_eq!(commands.len(), 10);
+        assert!(matches!(
+            &commands[0].kind,
+            CommandKind::Module { id: Some(id), module: ScriptModule::Text(text) }
+                if id == "$m" && text.starts_with("(module $m") && text.ends_with("0)))")
+        ));
+        assert!(matches!(
+            &commands[1].kind,
+            CommandKind::Register { name, id: Some(_) } if name == "m"
+        ));
+        assert!(matches!(
+            &commands[2].kind,
+            CommandKind::Action(Action::Invoke { id: None, args, .. }) if args == &[Value::I32(-1)]
+        ));
+        assert_eq!(commands[3].line, 5);
+        match &commands[4].kind {
+            CommandKind::AssertReturn { action, expected } => {
+                assert!(matches!(action, Action::Get { name, .. } if name == "g"));
+                assert!(matches!(expected[0], Expected::CanonicalNan(NumType::F32)));
+                assert!(matches!(
+                    &expected[1],
+                    Expected::Either(results) if results.len() == 2
+                ));
+            }
+            kind => panic!("{:?}", kind),
+        }
+        assert!(matches!(
+            &commands[5].kind,
+            CommandKind::AssertTrap { action: Action::Invoke { args, .. }, message }
+                if args == &[Value::F64(-0.5), Value::ExternRef(Some(3))] && message == "unreachable"
+        ));
+        assert!(matches!(
+            &commands[6].kind,
+            CommandKind::AssertMalformed { module: ScriptModule::Quote(text), .. } if text == b"(func)"
+        ));
+        assert!(matches!(
+            &commands[7].kind,
+            CommandKind::AssertMalformed { module: ScriptModule::Binary(bytes), .. } if bytes.len() == 8
+        ));
+        assert!(matches!(
+            &commands[8].kind,
+            CommandKind::AssertUninstantiable { .. }
+        ));
+        assert!(matches!(
+            &commands[9].kind,
+            CommandKind::AssertException { action: Action::Invoke { name, .. } } if name == "f"
+        ));
+    }
+
+    #[test]
+    fn inline_module() {
+        let commands = parse("(func) (memory 0)\n(func (export \"f\"))").unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(matches!(
+            &commands[0].kind,
+            CommandKind::Module { id: None, module: ScriptModule::Text(text) }
+                if text.starts_with("(module (func)") && text.ends_with("\"f\")))")
+        ));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("(assert_return (invoke \"f\" (i32.const x)))").is_err());
+        assert!(parse("(assert_return (invoke \"f\") (i32.add))").is_err());
+        assert!(parse("(module").is_err());
+    }
+}
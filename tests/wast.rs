@@ -0,0 +1,250 @@
+//! Runs the `.wast` scripts of `tests/wast` and of the specification test suite vendored in
+//! `tests/spec`, and reports the outcome of each file.
+//!
+//! Every command must pass, except the ones listed in [EXPECTED] along with the reason they fail
+//! or are skipped. An entry that passes is reported as well, so that the list doesn't outlive the
+//! reasons for it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use wasm_parse::wast;
+
+/// Commands of the vendored test suite that fail or are skipped: the path of the script within
+/// `tests`, the lines of the commands and why.
+///
+/// The suite dates from before several proposals were merged into the specification, and some of
+/// its assertions were changed by them since. The crate follows the current specification. The
+/// messages of `assert_invalid` and `assert_malformed` are the ones of the reference interpreter of
+/// the time, and a few of them depend on how it reads past the end of a section.
+const EXPECTED: &[(&str, &[usize], &str)] = &[
+    (
+        "spec/core/address.wast",
+        &[206],
+        "memory64: offsets are u64, out of range ones are invalid",
+    ),
+    (
+        "spec/core/binary-leb128.wast",
+        &[289, 346, 390],
+        "the integer runs past the end of its section, which is reported before its length",
+    ),
+    (
+        "spec/core/binary-leb128.wast",
+        &[403, 460, 729, 748, 842, 861],
+        "memory64: offsets are u64",
+    ),
+    (
+        "spec/core/binary.wast",
+        BINARY,
+        "reference types and multi-memory: table and memory indices",
+    ),
+    (
+        "spec/core/binary.wast",
+        &[625],
+        "the section ends before the second segment, which the interpreter reads from the next one",
+    ),
+    (
+        "spec/core/binary.wast",
+        &[762],
+        "multi-value: a block type can be a type index, so the body ends before its `end`",
+    ),
+    (
+        "spec/core/data.wast",
+        &[5],
+        "bulk memory: `$m` names the segment, not the memory",
+    ),
+    (
+        "spec/core/data.wast",
+        &[
+            161, 169, 177, 185, 193, 210, 219, 226, 234, 242, 250, 257, 265, 272,
+        ],
+        "bulk memory: out of bounds segments trap during instantiation",
+    ),
+    (
+        "spec/core/elem.wast",
+        &[4],
+        "bulk memory: `$t` names the segment, not the table",
+    ),
+    (
+        "spec/core/elem.wast",
+        &[142, 151, 160, 169, 177, 185, 194, 202, 211, 219, 228, 236],
+        "bulk memory: out of bounds segments trap during instantiation",
+    ),
+    (
+        "spec/core/func.wast",
+        &[492, 496],
+        "multi-value: several results are valid",
+    ),
+    (
+        "spec/core/imports.wast",
+        IMPORTS,
+        "multi-memory and reference types: several memories and tables",
+    ),
+    (
+        "spec/core/linking.wast",
+        &[206, 227, 236, 238, 248, 298, 334, 342, 344, 354],
+        "bulk memory: segments are initialized in order until one traps",
+    ),
+    (
+        "spec/core/memory.wast",
+        &[8, 9],
+        "multi-memory: several memories are valid",
+    ),
+    (
+        "spec/core/type.wast",
+        &[52, 56],
+        "multi-value: several results are valid",
+    ),
+    (
+        "spec/proposals/bulk-memory-operations/binary.wast",
+        &[
+            294, 313, 332, 350, 368, 387, 407, 427, 446, 465, 485, 504, 523, 541, 559,
+        ],
+        "reference types and multi-memory: table and memory indices",
+    ),
+    (
+        "spec/proposals/bulk-memory-operations/binary.wast",
+        &[724, 800],
+        "reference types: element expressions and `ref.null` take a heap type",
+    ),
+    (
+        "spec/proposals/bulk-memory-operations/binary.wast",
+        &[1032],
+        "the section ends before the second segment, which the interpreter reads from the next one",
+    ),
+    (
+        "spec/proposals/bulk-memory-operations/binary.wast",
+        &[1169],
+        "multi-value: a block type can be a type index, so the body ends before its `end`",
+    ),
+    (
+        "spec/proposals/bulk-memory-operations/bulk.wast",
+        &[6],
+        "reference types: `ref.null` takes a heap type",
+    ),
+    (
+        "spec/proposals/bulk-memory-operations/elem.wast",
+        &[4, 68],
+        "reference types: `ref.null` takes a heap type",
+    ),
+    (
+        "spec/proposals/bulk-memory-operations/imports.wast",
+        IMPORTS,
+        "multi-memory and reference types: several memories and tables",
+    ),
+    (
+        "spec/proposals/multi-value/binary.wast",
+        BINARY,
+        "reference types and multi-memory: table and memory indices",
+    ),
+    (
+        "spec/proposals/simd/simd_address.wast",
+        &[137, 145],
+        "memory64: offsets are u64, out of range ones are invalid",
+    ),
+    (
+        "spec/proposals/simd/simd_f64x2_arith.wast",
+        &[5299],
+        "the sign of a NaN result is nondeterministic, the script expects a positive one",
+    ),
+    (
+        "spec/proposals/simd/simd_i16x8_arith2.wast",
+        &[339],
+        "`i64x2.abs` was added to the proposal since",
+    ),
+    (
+        "spec/proposals/threads/memory.wast",
+        &[12, 13],
+        "multi-memory: several memories are valid",
+    ),
+];
+
+/// Lines of `binary.wast` with a table index in `call_indirect` or a memory index in
+/// `memory.size` and `memory.grow`.
+const BINARY: &[usize] = &[
+    49, 68, 87, 105, 123, 142, 162, 182, 201, 220, 240, 259, 278, 296, 314,
+];
+
+/// Lines of `imports.wast` with several memories or tables.
+const IMPORTS: &[usize] = &[309, 313, 317, 404, 408, 412];
+
+/// Scripts within `dir` and its subdirectories.
+fn collect(dir: &Path, paths: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, paths);
+        } else if path.extension().map_or(false, |ext| ext == "wast") {
+            paths.push(path);
+        }
+    }
+}
+
+#[test]
+fn scripts() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let mut paths = Vec::new();
+    collect(&root.join("wast"), &mut paths);
+    collect(&root.join("spec"), &mut paths);
+    paths.sort();
+    assert!(!paths.is_empty(), "no scripts in {}", root.display());
+
+    let mut unexpected = 0;
+    let mut expected: Vec<(&str, usize, &str)> = EXPECTED
+        .iter()
+        .flat_map(|(name, lines, reason)| lines.iter().map(move |line| (*name, *line, *reason)))
+        .collect();
+    for path in &paths {
+        let name = path
+            .strip_prefix(&root)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        let text = fs::read_to_string(path).unwrap();
+        let report = match wast::run(&text) {
+            Ok(report) => report,
+            Err(err) => {
+                println!("{}: can't parse the script: {}", name, err);
+                unexpected += 1;
+                continue;
+            }
+        };
+        println!(
+            "{}: {} passed, {} failed, {} skipped",
+            name,
+            report.passed,
+            report.failures.len(),
+            report.skipped.len()
+        );
+        let outcomes = report
+            .failures
+            .iter()
+            .map(|failure| (failure.line, failure.reason.clone()))
+            .chain(report.skipped.iter().map(|skipped| {
+                let reason = format!("unsupported command `{}`", skipped.command);
+                (skipped.line, reason)
+            }));
+        for (line, reason) in outcomes {
+            match expected
+                .iter()
+                .position(|(n, l, _)| *n == name && *l == line)
+            {
+                Some(i) => {
+                    expected.remove(i);
+                }
+                None => {
+                    println!("  {}:{}: {}", name, line, reason);
+                    unexpected += 1;
+                }
+            }
+        }
+    }
+    for (name, line, reason) in &expected {
+        println!(
+            "{}:{}: passed although expected not to ({})",
+            name, line, reason
+        );
+    }
+    assert_eq!(unexpected, 0, "some commands failed or were skipped");
+    assert!(expected.is_empty(), "some expected failures passed");
+}
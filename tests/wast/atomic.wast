@@ -0,0 +1,83 @@
+;; Threads and atomics
+
+(module
+  (memory 1 1 shared)
+  (data (i32.const 0) "\01\02\03\04\05\06\07\08\ff\ff\ff\ff\ff\ff\ff\ff")
+  (func (export "i32.atomic.load") (param i32) (result i32)
+    (i32.atomic.load (local.get 0)))
+  (func (export "i64.atomic.load16_u") (param i32) (result i64)
+    (i64.atomic.load16_u (local.get 0)))
+  (func (export "i64.atomic.store") (param i32 i64)
+    (i64.atomic.store (local.get 0) (local.get 1)))
+  (func (export "i32.atomic.store8") (param i32 i32)
+    (i32.atomic.store8 (local.get 0) (local.get 1)))
+  (func (export "i64.atomic.load") (param i32) (result i64)
+    (i64.atomic.load (local.get 0)))
+  (func (export "i32.atomic.rmw.add") (param i32 i32) (result i32)
+    (i32.atomic.rmw.add (local.get 0) (local.get 1)))
+  (func (export "i32.atomic.rmw8.sub_u") (param i32 i32) (result i32)
+    (i32.atomic.rmw8.sub_u (local.get 0) (local.get 1)))
+  (func (export "i64.atomic.rmw32.xchg_u") (param i32 i64) (result i64)
+    (i64.atomic.rmw32.xchg_u (local.get 0) (local.get 1)))
+  (func (export "i64.atomic.rmw.and") (param i32 i64) (result i64)
+    (i64.atomic.rmw.and (local.get 0) (local.get 1)))
+  (func (export "i32.atomic.rmw16.cmpxchg_u") (param i32 i32 i32) (result i32)
+    (i32.atomic.rmw16.cmpxchg_u (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "i64.atomic.rmw.cmpxchg") (param i32 i64 i64) (result i64)
+    (i64.atomic.rmw.cmpxchg (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "memory.atomic.notify") (param i32 i32) (result i32)
+    (memory.atomic.notify (local.get 0) (local.get 1)))
+  (func (export "memory.atomic.wait32") (param i32 i32 i64) (result i32)
+    (memory.atomic.wait32 (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "memory.atomic.wait64") (param i32 i64 i64) (result i32)
+    (memory.atomic.wait64 (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "atomic.fence") (atomic.fence))
+)
+
+(assert_return (invoke "i32.atomic.load" (i32.const 4)) (i32.const 0x08070605))
+(assert_return (invoke "i64.atomic.load16_u" (i32.const 8)) (i64.const 0xffff))
+(assert_return (invoke "i32.atomic.rmw.add" (i32.const 0) (i32.const 1)) (i32.const 0x04030201))
+(assert_return (invoke "i32.atomic.load" (i32.const 0)) (i32.const 0x04030202))
+(assert_return (invoke "i32.atomic.rmw8.sub_u" (i32.const 1) (i32.const 3)) (i32.const 2))
+(assert_return (invoke "i32.atomic.load" (i32.const 0)) (i32.const 0x0403ff02))
+(assert_return (invoke "i64.atomic.rmw32.xchg_u" (i32.const 8) (i64.const 0x1_0000_0007)) (i64.const 0xffffffff))
+(assert_return (invoke "i64.atomic.load" (i32.const 8)) (i64.const 0xffffffff_00000007))
+(assert_return (invoke "i64.atomic.rmw.and" (i32.const 8) (i64.const 0xf0)) (i64.const 0xffffffff_00000007))
+(assert_return (invoke "i64.atomic.load" (i32.const 8)) (i64.const 0))
+(assert_return (invoke "i32.atomic.rmw16.cmpxchg_u" (i32.const 0) (i32.const 0x1_ff02) (i32.const 0xabcd)) (i32.const 0xff02))
+(assert_return (invoke "i32.atomic.load" (i32.const 0)) (i32.const 0x0403abcd))
+(assert_return (invoke "i64.atomic.rmw.cmpxchg" (i32.const 8) (i64.const 1) (i64.const 2)) (i64.const 0))
+(assert_return (invoke "i64.atomic.load" (i32.const 8)) (i64.const 0))
+(assert_return (invoke "i64.atomic.store" (i32.const 16) (i64.const -2)))
+(assert_return (invoke "i32.atomic.store8" (i32.const 16) (i32.const 0x1234)))
+(assert_return (invoke "i64.atomic.load" (i32.const 16)) (i64.const 0xffffffff_ffffff34))
+(assert_return (invoke "memory.atomic.notify" (i32.const 0) (i32.const 10)) (i32.const 0))
+(assert_return (invoke "memory.atomic.wait32" (i32.const 0) (i32.const 0) (i64.const 0)) (i32.const 1))
+(assert_return (invoke "memory.atomic.wait32" (i32.const 0) (i32.const 0x0403abcd) (i64.const 10)) (i32.const 2))
+(assert_return (invoke "memory.atomic.wait64" (i32.const 8) (i64.const 0) (i64.const 0)) (i32.const 2))
+(assert_return (invoke "atomic.fence"))
+
+(assert_trap (invoke "i32.atomic.load" (i32.const 2)) "unaligned atomic")
+(assert_trap (invoke "i32.atomic.rmw.add" (i32.const 1) (i32.const 0)) "unaligned atomic")
+(assert_trap (invoke "i64.atomic.load" (i32.const 65536)) "out of bounds memory access")
+(assert_trap (invoke "memory.atomic.wait32" (i32.const 0) (i32.const 0x0403abcd) (i64.const -1)) "deadlock")
+
+(module
+  (memory 1)
+  (func (export "wait") (result i32)
+    (memory.atomic.wait32 (i32.const 0) (i32.const 0) (i64.const 0)))
+  (func (export "notify") (result i32)
+    (memory.atomic.notify (i32.const 0) (i32.const 1)))
+)
+(assert_trap (invoke "wait") "expected shared memory")
+(assert_return (invoke "notify") (i32.const 0))
+
+(assert_invalid
+  (module (memory 1 1 shared) (func (result i32) (i32.atomic.load align=2 (i32.const 0))))
+  "alignment must be equal to natural")
+(assert_invalid
+  (module (memory 1 shared))
+  "shared memory must have maximum")
+(assert_unlinkable
+  (module (import "spectest" "memory" (memory 1 2 shared)))
+  "incompatible import type")
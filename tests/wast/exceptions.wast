@@ -0,0 +1,164 @@
+;; Exception handling
+
+(module $thrower
+  (tag $e0 (export "e0"))
+  (tag $e1 (export "e1") (param i32))
+  (func (export "throw") (param i32)
+    (if (i32.eqz (local.get 0)) (then (throw $e0)))
+    (throw $e1 (local.get 0)))
+)
+(register "thrower" $thrower)
+
+(module
+  (import "thrower" "e1" (tag $e1 (param i32)))
+  (import "thrower" "throw" (func $throw (param i32)))
+  (tag $e2 (param i32 i64))
+
+  (func (export "catch") (param i32) (result i32)
+    (block $h (result i32)
+      (try_table (result i32) (catch $e1 $h)
+        (call $throw (local.get 0))
+        (i32.const -1))))
+
+  (func (export "catch-all") (param i32) (result i32)
+    (block $h
+      (try_table (catch $e1 1) (catch_all $h)
+        (call $throw (local.get 0)))
+      (return (i32.const -1)))
+    (i32.const 0))
+
+  (func (export "multi") (param i32) (result i64) (local i64)
+    (block $h (result i32 i64)
+      (try_table (catch $e2 $h)
+        (throw $e2 (local.get 0) (i64.const 42)))
+      (unreachable))
+    (local.set 1)
+    (i64.add (i64.extend_i32_u) (local.get 1)))
+
+  (func $rethrow-ref (param i32)
+    (block $h (result exnref)
+      (try_table (catch_all_ref $h) (call $throw (local.get 0)))
+      (return))
+    (throw_ref))
+
+  (func (export "throw-ref") (param i32) (result i32)
+    (block $h (result i32 exnref)
+      (try_table (catch_ref $e1 $h) (call $rethrow-ref (local.get 0)))
+      (return (i32.const -1)))
+    (drop))
+
+  (func (export "throw-ref-null") (throw_ref (ref.null exn)))
+
+  (func (export "nested") (param i32) (result i32)
+    (block $outer (result i32)
+      (block $inner
+        (try_table (catch_all $inner)
+          (try_table (catch $e1 $outer)
+            (call $throw (local.get 0)))))
+      (i32.const 0)))
+
+  (func (export "uncaught") (param i32) (result i32)
+    (try_table (catch $e1 0) (call $throw (local.get 0)))
+    (i32.const -1))
+)
+
+(assert_return (invoke "catch" (i32.const 3)) (i32.const 3))
+(assert_exception (invoke "catch" (i32.const 0)))
+(assert_return (invoke "catch-all" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "catch-all" (i32.const 5)) (i32.const 5))
+(assert_return (invoke "multi" (i32.const 1)) (i64.const 43))
+(assert_return (invoke "throw-ref" (i32.const 7)) (i32.const 7))
+(assert_exception (invoke "throw-ref" (i32.const 0)))
+(assert_trap (invoke "throw-ref-null") "null exception reference")
+(assert_return (invoke "nested" (i32.const 9)) (i32.const 9))
+(assert_return (invoke "nested" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "uncaught" (i32.const 1)) (i32.const 1))
+(assert_exception (invoke "uncaught" (i32.const 0)))
+
+;; Legacy try blocks
+(module
+  (tag $e (param i32))
+  (tag $f)
+
+  (func $throw (param i32)
+    (if (i32.eqz (local.get 0)) (then (throw $f)))
+    (throw $e (local.get 0)))
+
+  (func (export "try") (param i32) (result i32)
+    (try (result i32)
+      (do (call $throw (local.get 0)) (i32.const -1))
+      (catch $e (i32.mul (i32.const 2)))
+      (catch_all (i32.const 0))))
+
+  (func (export "try-no-throw") (result i32)
+    (try (result i32)
+      (do (i32.const 1))
+      (catch $e)))
+
+  (func (export "rethrow") (param i32) (result i32)
+    (try (result i32)
+      (do
+        (try (do (call $throw (local.get 0)))
+          (catch $e (drop) (rethrow 0)))
+        (i32.const -1))
+      (catch $e)))
+
+  (func (export "delegate") (param i32) (result i32)
+    (try $t (result i32)
+      (do
+        (try (result i32)
+          (do (call $throw (local.get 0)) (i32.const -1))
+          (delegate $t)))
+      (catch $e (i32.const 100) (i32.add))))
+
+  (func (export "delegate-out") (param i32)
+    (try (do (call $throw (local.get 0))) (delegate 0)))
+)
+
+(assert_return (invoke "try" (i32.const 4)) (i32.const 8))
+(assert_return (invoke "try" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "try-no-throw") (i32.const 1))
+(assert_return (invoke "rethrow" (i32.const 3)) (i32.const 3))
+(assert_exception (invoke "rethrow" (i32.const 0)))
+(assert_return (invoke "delegate" (i32.const 3)) (i32.const 103))
+(assert_exception (invoke "delegate" (i32.const 0)))
+(assert_exception (invoke "delegate-out" (i32.const 1)))
+
+(assert_unlinkable
+  (module (import "thrower" "e1" (tag (param i64))))
+  "incompatible import type"
+)
+
+(assert_invalid
+  (module (tag (result i32)))
+  "non-empty tag result type"
+)
+(assert_invalid
+  (module (tag (param i32)) (func (throw 0)))
+  "type mismatch"
+)
+(assert_invalid
+  (module (func (block (rethrow 0))))
+  "invalid rethrow label"
+)
+(assert_invalid
+  (module (tag (param i64)) (func (result i32) (try_table (catch 0 0))))
+  "type mismatch"
+)
+(assert_invalid
+  (module (func (throw_ref (ref.null func))))
+  "type mismatch"
+)
+(assert_invalid
+  (module (func (throw 0)))
+  "unknown tag"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01\60\00\00"          ;; type section
+    "\0d\03\01\01\00"             ;; tag section with attribute 1
+  )
+  "tag attribute"
+)
@@ -0,0 +1,126 @@
+;; Typed function references
+
+(module
+  (type $ii (func (param i32) (result i32)))
+  (type $v (func))
+
+  (func $inc (type $ii) (i32.add (local.get 0) (i32.const 1)))
+  (func $dbl (type $ii) (i32.mul (local.get 0) (i32.const 2)))
+  (elem declare func $inc $dbl)
+
+  (func $apply (param $f (ref $ii)) (param i32) (result i32)
+    (call_ref $ii (local.get 1) (local.get $f)))
+
+  (func (export "inc") (param i32) (result i32)
+    (call $apply (ref.func $inc) (local.get 0)))
+  (func (export "dbl") (param i32) (result i32)
+    (call $apply (ref.func $dbl) (local.get 0)))
+
+  (func (export "call-null") (result i32)
+    (call_ref $ii (i32.const 0) (ref.null $ii)))
+
+  (func $count (param i32 i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then (local.get 1))
+      (else
+        (return_call_ref $count-type
+          (i32.sub (local.get 0) (i32.const 1))
+          (i32.add (local.get 1) (i32.const 1))
+          (ref.func $count)))))
+  (type $count-type (func (param i32 i32) (result i32)))
+  (elem declare func $count)
+  (func (export "count") (param i32) (result i32)
+    (call $count (local.get 0) (i32.const 0)))
+
+  (func (export "as-non-null") (param i32) (result i32)
+    (call_ref $ii (i32.const 5)
+      (ref.as_non_null
+        (select (result (ref null $ii))
+          (ref.func $inc) (ref.null $ii) (local.get 0)))))
+
+  (func (export "br-on-null") (param i32) (result i32)
+    (block $null
+      (return
+        (call_ref $ii (i32.const 21)
+          (br_on_null $null
+            (select (result (ref null $ii))
+              (ref.func $dbl) (ref.null $ii) (local.get 0))))))
+    (i32.const -1))
+
+  (func (export "br-on-non-null") (param i32) (result i32)
+    (call_ref $ii (i32.const 41)
+      (block $some (result (ref $ii))
+        (br_on_non_null $some
+          (select (result (ref null $ii))
+            (ref.func $inc) (ref.null $ii) (local.get 0)))
+        (return (i32.const -1)))))
+
+  (func (export "is-null") (param i32) (result i32)
+    (ref.is_null
+      (select (result (ref null $v)) (ref.null $v) (ref.null nofunc) (local.get 0))))
+)
+
+(assert_return (invoke "inc" (i32.const 4)) (i32.const 5))
+(assert_return (invoke "dbl" (i32.const 4)) (i32.const 8))
+(assert_trap (invoke "call-null") "null function reference")
+(assert_return (invoke "count" (i32.const 100000)) (i32.const 100000))
+(assert_return (invoke "as-non-null" (i32.const 1)) (i32.const 6))
+(assert_trap (invoke "as-non-null" (i32.const 0)) "null reference")
+(assert_return (invoke "br-on-null" (i32.const 1)) (i32.const 42))
+(assert_return (invoke "br-on-null" (i32.const 0)) (i32.const -1))
+(assert_return (invoke "br-on-non-null" (i32.const 1)) (i32.const 42))
+(assert_return (invoke "br-on-non-null" (i32.const 0)) (i32.const -1))
+(assert_return (invoke "is-null" (i32.const 0)) (i32.const 1))
+
+;; Non-nullable locals must be set before they are read.
+(module
+  (type $v (func))
+  (func $f)
+  (elem declare func $f)
+  (func (export "set-first") (result i32)
+    (local $r (ref $v))
+    (local.set $r (ref.func $f))
+    (ref.is_null (local.get $r)))
+)
+(assert_return (invoke "set-first") (i32.const 0))
+
+(assert_invalid
+  (module
+    (type $v (func))
+    (func (local $r (ref $v)) (drop (local.get $r))))
+  "uninitialized local")
+(assert_invalid
+  (module
+    (type $v (func))
+    (elem declare func 0)
+    (func (local $r (ref $v))
+      (block (local.set $r (ref.func 0)))
+      (drop (local.get $r))))
+  "uninitialized local")
+(assert_invalid
+  (module
+    (type $ii (func (param i32) (result i32)))
+    (func (param (ref null $ii)) (result i32)
+      (call_ref $ii (i32.const 0) (local.get 0) (i32.const 1))))
+  "type mismatch")
+(assert_invalid
+  (module
+    (type $v (func))
+    (func (result (ref $v)) (ref.null $v)))
+  "type mismatch")
+(assert_invalid
+  (module
+    (type $v (func))
+    (table 1 (ref $v)))
+  "type mismatch")
+
+;; Tables of non-nullable references need an initializer.
+(module
+  (type $ii (func (param i32) (result i32)))
+  (func $neg (type $ii) (i32.sub (i32.const 0) (local.get 0)))
+  (table $t 2 (ref $ii) (ref.func $neg))
+  (func (export "call") (param i32 i32) (result i32)
+    (call_ref $ii (local.get 0) (table.get $t (local.get 1))))
+)
+(assert_return (invoke "call" (i32.const 3) (i32.const 1)) (i32.const -3))
+(assert_trap (invoke "call" (i32.const 3) (i32.const 2)) "out of bounds table access")
@@ -0,0 +1,184 @@
+;; Garbage collected structs, arrays and i31 references
+
+(module
+  (type $point (struct (field $x (mut i32)) (field $y (mut i32))))
+  (type $bytes (array (mut i8)))
+  (type $vec (array (mut f64)))
+
+  (data $hello "hello")
+
+  (func $point (param i32 i32) (result (ref $point))
+    (struct.new $point (local.get 0) (local.get 1)))
+
+  (func (export "sum") (param i32 i32) (result i32)
+    (local $p (ref $point))
+    (local.set $p (call $point (local.get 0) (local.get 1)))
+    (i32.add (struct.get $point $x (local.get $p)) (struct.get $point $y (local.get $p))))
+
+  (func (export "set") (param i32) (result i32)
+    (local $p (ref null $point))
+    (local.set $p (struct.new_default $point))
+    (struct.set $point $y (local.get $p) (local.get 0))
+    (struct.get $point 1 (local.get $p)))
+
+  (func (export "get-null") (result i32)
+    (struct.get $point $x (ref.null $point)))
+
+  (func (export "bytes") (param i32) (result i32)
+    (local $a (ref $bytes))
+    (local.set $a (array.new $bytes (i32.const 255) (i32.const 4)))
+    (array.set $bytes (local.get $a) (i32.const 1) (i32.const 7))
+    (i32.add
+      (array.get_s $bytes (local.get $a) (local.get 0))
+      (array.len (local.get $a))))
+
+  (func (export "bytes-u") (param i32) (result i32)
+    (array.get_u $bytes (array.new_default $bytes (i32.const 2)) (local.get 0)))
+
+  (func (export "data") (param i32) (result i32)
+    (array.get_u $bytes (array.new_data $bytes $hello (i32.const 1) (i32.const 3))
+      (local.get 0)))
+
+  (func (export "fixed") (result f64)
+    (local $a (ref $vec))
+    (local.set $a (array.new_fixed $vec 3 (f64.const 1) (f64.const 2) (f64.const 3)))
+    (array.fill $vec (local.get $a) (i32.const 0) (f64.const 10) (i32.const 1))
+    (array.copy $vec $vec (local.get $a) (i32.const 2) (local.get $a) (i32.const 0) (i32.const 1))
+    (f64.add (array.get $vec (local.get $a) (i32.const 1))
+      (array.get $vec (local.get $a) (i32.const 2))))
+
+  (func (export "i31") (param i32) (result i32 i32)
+    (i31.get_s (ref.i31 (local.get 0)))
+    (i31.get_u (ref.i31 (local.get 0))))
+
+  (func (export "i31-null") (result i32)
+    (i31.get_u (ref.null i31)))
+
+  (func (export "new-point") (result (ref null struct)) (call $point (i32.const 1) (i32.const 2)))
+  (func (export "new-array") (result anyref) (array.new_default $vec (i32.const 0)))
+  (func (export "new-i31") (result eqref) (ref.i31 (i32.const 3)))
+  (func (export "null-any") (result anyref) (ref.null none))
+)
+
+(assert_return (invoke "sum" (i32.const 3) (i32.const 4)) (i32.const 7))
+(assert_return (invoke "set" (i32.const 9)) (i32.const 9))
+(assert_trap (invoke "get-null") "null structure reference")
+(assert_return (invoke "bytes" (i32.const 0)) (i32.const 3))
+(assert_return (invoke "bytes" (i32.const 1)) (i32.const 11))
+(assert_trap (invoke "bytes" (i32.const 4)) "out of bounds array access")
+(assert_return (invoke "bytes-u" (i32.const 1)) (i32.const 0))
+(assert_return (invoke "data" (i32.const 0)) (i32.const 0x65))
+(assert_return (invoke "data" (i32.const 2)) (i32.const 0x6c))
+(assert_trap (invoke "data" (i32.const 3)) "out of bounds array access")
+(assert_return (invoke "fixed") (f64.const 12))
+(assert_return (invoke "i31" (i32.const -1)) (i32.const -1) (i32.const 0x7fffffff))
+(assert_return (invoke "i31" (i32.const 0x40000000)) (i32.const -0x40000000) (i32.const 0x40000000))
+(assert_trap (invoke "i31-null") "null i31 reference")
+(assert_return (invoke "new-point") (ref.struct))
+(assert_return (invoke "new-array") (ref.array))
+(assert_return (invoke "new-i31") (ref.i31))
+(assert_return (invoke "new-i31") (ref.eq))
+(assert_return (invoke "null-any") (ref.null any))
+(assert_return (invoke "null-any") (ref.null))
+
+;; Subtyping, casts and recursive types
+(module
+  (rec
+    (type $node (sub (struct (field $next (ref null $node)))))
+    (type $pair (sub $node (struct (field $next (ref null $node)) (field $val i32)))))
+  (type $leaf (sub final $pair (struct (field (ref null $node)) (field i32))))
+  (type $other (struct (field i64)))
+
+  (func $make (param i32) (result (ref $node))
+    (if (result (ref $node)) (i32.eqz (local.get 0))
+      (then (struct.new $node (ref.null none)))
+      (else (struct.new $leaf (ref.null none) (local.get 0)))))
+
+  (func (export "test") (param i32) (result i32 i32 i32)
+    (ref.test (ref $pair) (call $make (local.get 0)))
+    (ref.test (ref $node) (call $make (local.get 0)))
+    (ref.test (ref eq) (call $make (local.get 0))))
+
+  (func (export "test-null") (result i32 i32)
+    (ref.test (ref $node) (ref.null $node))
+    (ref.test (ref null $other) (ref.null $node)))
+
+  (func (export "cast") (param i32) (result i32)
+    (struct.get $pair $val (ref.cast (ref $pair) (call $make (local.get 0)))))
+
+  (func (export "br-on-cast") (param i32) (result i32)
+    (block $pair (result (ref $pair))
+      (br_on_cast $pair (ref $node) (ref $pair) (call $make (local.get 0)))
+      (return (i32.const -1)))
+    (struct.get $pair $val))
+
+  (func (export "br-on-cast-fail") (param i32) (result i32)
+    (drop
+      (block $fail (result (ref $node))
+        (return
+          (struct.get $pair $val
+            (br_on_cast_fail $fail (ref $node) (ref $pair) (call $make (local.get 0)))))))
+    (i32.const -1))
+
+  (func (export "eq") (param i32) (result i32)
+    (local $n (ref $node))
+    (local.set $n (call $make (i32.const 1)))
+    (ref.eq (local.get $n)
+      (select (result (ref $node)) (local.get $n) (call $make (i32.const 1)) (local.get 0))))
+
+  (func (export "extern") (param i32) (result i32)
+    (i31.get_u
+      (ref.cast (ref i31)
+        (any.convert_extern (extern.convert_any (ref.i31 (local.get 0)))))))
+)
+
+(assert_return (invoke "test" (i32.const 0)) (i32.const 0) (i32.const 1) (i32.const 1))
+(assert_return (invoke "test" (i32.const 5)) (i32.const 1) (i32.const 1) (i32.const 1))
+(assert_return (invoke "test-null") (i32.const 0) (i32.const 1))
+(assert_return (invoke "cast" (i32.const 5)) (i32.const 5))
+(assert_trap (invoke "cast" (i32.const 0)) "cast failure")
+(assert_return (invoke "br-on-cast" (i32.const 6)) (i32.const 6))
+(assert_return (invoke "br-on-cast" (i32.const 0)) (i32.const -1))
+(assert_return (invoke "br-on-cast-fail" (i32.const 6)) (i32.const 6))
+(assert_return (invoke "br-on-cast-fail" (i32.const 0)) (i32.const -1))
+(assert_return (invoke "eq" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "eq" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "extern" (i32.const 12)) (i32.const 12))
+
+;; Equivalent recursion groups define the same types across modules.
+(module $exporter
+  (rec (type $a (struct (field (ref null $b)))) (type $b (struct (field (ref null $a)))))
+  (func (export "make") (result (ref null $a)) (struct.new_default $a))
+)
+(register "exporter" $exporter)
+(module
+  (rec (type $a (struct (field (ref null $b)))) (type $b (struct (field (ref null $a)))))
+  (import "exporter" "make" (func $make (result (ref null $a))))
+  (func (export "check") (result i32) (ref.test (ref $a) (call $make)))
+)
+(assert_return (invoke "check") (i32.const 1))
+(assert_unlinkable
+  (module
+    (rec (type $b (struct (field (ref null $a)))) (type $a (struct (field (ref null $b)))))
+    (import "exporter" "make" (func (result (ref null $a)))))
+  "incompatible import type")
+
+(assert_invalid
+  (module (type $s (struct (field i32))) (func (struct.set $s 0 (ref.null $s) (i32.const 0))))
+  "field is immutable")
+(assert_invalid
+  (module
+    (type $t (sub final (struct)))
+    (type $u (sub $t (struct))))
+  "sub type")
+(assert_invalid
+  (module
+    (type $t (sub (struct (field i32))))
+    (type $u (sub $t (struct (field i64)))))
+  "sub type")
+(assert_invalid
+  (module (type $a (array i8)) (func (result i32) (array.get $a (ref.null $a) (i32.const 0))))
+  "type mismatch")
+(assert_invalid
+  (module (type $t (struct)) (func (result (ref $t)) (ref.cast (ref $t) (ref.null extern))))
+  "type mismatch")
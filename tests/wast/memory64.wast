@@ -0,0 +1,124 @@
+;; 64-bit memories
+
+(module
+  (memory $m i64 1 3)
+  (memory $n 1)
+  (data (i64.const 8) "\01\02\03\04")
+  (func (export "load") (param i64) (result i32)
+    (i32.load8_u (local.get 0)))
+  (func (export "load_offset") (param i64) (result i32)
+    (i32.load offset=0x100000000 (local.get 0)))
+  (func (export "store") (param i64 i64)
+    (i64.store (local.get 0) (local.get 1)))
+  (func (export "load64") (param i64) (result i64)
+    (i64.load (local.get 0)))
+  (func (export "size") (result i64) (memory.size))
+  (func (export "grow") (param i64) (result i64) (memory.grow (local.get 0)))
+  (func (export "fill") (param i64 i32 i64)
+    (memory.fill (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "copy_to_n") (param i32 i64 i32)
+    (memory.copy $n $m (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "load_n") (param i32) (result i32)
+    (i32.load8_u $n (local.get 0)))
+)
+
+(assert_return (invoke "load" (i64.const 9)) (i32.const 2))
+(assert_return (invoke "load" (i64.const 0xffff)) (i32.const 0))
+(assert_trap (invoke "load" (i64.const 0x10000)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const 0x100000000)) "out of bounds memory access")
+(assert_trap (invoke "load" (i64.const -1)) "out of bounds memory access")
+(assert_trap (invoke "load_offset" (i64.const 0)) "out of bounds memory access")
+(assert_trap (invoke "load_offset" (i64.const -1)) "out of bounds memory access")
+(invoke "store" (i64.const 16) (i64.const 0x0102030405060708))
+(assert_return (invoke "load64" (i64.const 16)) (i64.const 0x0102030405060708))
+(assert_return (invoke "size") (i64.const 1))
+(assert_return (invoke "grow" (i64.const 1)) (i64.const 1))
+(assert_return (invoke "grow" (i64.const 2)) (i64.const -1))
+(assert_return (invoke "grow" (i64.const 0x100000000)) (i64.const -1))
+(assert_return (invoke "size") (i64.const 2))
+(assert_return (invoke "load" (i64.const 0x1ffff)) (i32.const 0))
+(invoke "fill" (i64.const 0x1fffe) (i32.const 7) (i64.const 2))
+(assert_return (invoke "load" (i64.const 0x1ffff)) (i32.const 7))
+(assert_trap (invoke "fill" (i64.const 0x1ffff) (i32.const 7) (i64.const 2)) "out of bounds memory access")
+(invoke "copy_to_n" (i32.const 0) (i64.const 8) (i32.const 4))
+(assert_return (invoke "load_n" (i32.const 3)) (i32.const 4))
+
+(assert_invalid
+  (module (memory i64 1) (func (drop (i32.load (i32.const 0)))))
+  "type mismatch")
+(assert_invalid
+  (module (memory i64 1) (func (drop (i32.add (memory.size) (i32.const 0)))))
+  "type mismatch")
+(assert_invalid
+  (module (memory 1) (func (drop (i32.load offset=0x100000000 (i32.const 0)))))
+  "offset out of range")
+(assert_invalid
+  (module (memory i64 1) (data (i32.const 0)))
+  "type mismatch")
+(module binary
+  "\00asm" "\01\00\00\00"
+  "\05\08\01"                   ;; memory section with one entry
+  "\05\00\80\80\80\80\10"       ;; 64-bit limits with a large maximum
+)
+
+;; 64-bit tables
+
+(module
+  (type $f (func (result i32)))
+  (table $t i64 2 4 funcref)
+  (table $u 3 funcref)
+  (elem (table $t) (i64.const 1) func $seven)
+  (func $seven (result i32) (i32.const 7))
+  (func (export "call") (param i64) (result i32)
+    (call_indirect $t (type $f) (local.get 0)))
+  (func (export "is_null") (param i64) (result i32)
+    (ref.is_null (table.get $t (local.get 0))))
+  (func (export "size") (result i64) (table.size $t))
+  (func (export "grow") (param i64) (result i64)
+    (table.grow $t (ref.null func) (local.get 0)))
+  (func (export "fill") (param i64 i64)
+    (table.fill $t (local.get 0) (ref.func $seven) (local.get 1)))
+  (func (export "copy_to_u") (param i32 i64 i32)
+    (table.copy $u $t (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "call_u") (param i32) (result i32)
+    (call_indirect $u (type $f) (local.get 0)))
+)
+
+(assert_return (invoke "call" (i64.const 1)) (i32.const 7))
+(assert_trap (invoke "call" (i64.const 0)) "uninitialized element")
+(assert_trap (invoke "call" (i64.const 0x100000001)) "undefined element")
+(assert_return (invoke "is_null" (i64.const 0)) (i32.const 1))
+(assert_trap (invoke "is_null" (i64.const 2)) "out of bounds table access")
+(assert_trap (invoke "is_null" (i64.const -1)) "out of bounds table access")
+(assert_return (invoke "size") (i64.const 2))
+(assert_return (invoke "grow" (i64.const 1)) (i64.const 2))
+(assert_return (invoke "grow" (i64.const 2)) (i64.const -1))
+(assert_return (invoke "grow" (i64.const 0x100000000)) (i64.const -1))
+(assert_return (invoke "size") (i64.const 3))
+(invoke "fill" (i64.const 2) (i64.const 1))
+(assert_return (invoke "call" (i64.const 2)) (i32.const 7))
+(assert_trap (invoke "fill" (i64.const 2) (i64.const 2)) "out of bounds table access")
+(invoke "copy_to_u" (i32.const 0) (i64.const 1) (i32.const 2))
+(assert_return (invoke "call_u" (i32.const 1)) (i32.const 7))
+
+(module $t64 (table (export "t") i64 1 funcref))
+(register "t64" $t64)
+(module (import "t64" "t" (table i64 1 funcref)))
+(assert_unlinkable
+  (module (import "t64" "t" (table 1 funcref)))
+  "incompatible import type")
+
+(assert_invalid
+  (module (table i64 1 funcref) (func (drop (table.get 0 (i32.const 0)))))
+  "type mismatch")
+(assert_invalid
+  (module (table i64 1 funcref) (func (drop (i32.add (table.size 0) (i32.const 0)))))
+  "type mismatch")
+(assert_invalid
+  (module (table i64 1 funcref) (elem (i32.const 0) func))
+  "type mismatch")
+(module binary
+  "\00asm" "\01\00\00\00"
+  "\04\04\01"                   ;; table section with one entry
+  "\70\04\00"                   ;; funcref table with 64-bit limits
+)
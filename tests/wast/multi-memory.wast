@@ -0,0 +1,62 @@
+;; Multiple memories
+
+(module
+  (memory $a 1)
+  (memory $b 1 2)
+  (data (memory $b) (i32.const 0) "\01\02\03\04")
+  (data $passive "\aa\bb")
+  (func (export "load_a") (param i32) (result i32)
+    (i32.load8_u $a (local.get 0)))
+  (func (export "load_b") (param i32) (result i32)
+    (i32.load8_u $b (local.get 0)))
+  (func (export "load32_b") (param i32) (result i32)
+    (i32.load $b offset=1 (local.get 0)))
+  (func (export "store_b") (param i32 i32)
+    (i32.store8 $b (local.get 0) (local.get 1)))
+  (func (export "copy") (param i32 i32 i32)
+    (memory.copy $a $b (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "init_b") (param i32)
+    (memory.init $b $passive (local.get 0) (i32.const 0) (i32.const 2)))
+  (func (export "fill_b") (param i32 i32 i32)
+    (memory.fill $b (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "size_a") (result i32) (memory.size $a))
+  (func (export "size_b") (result i32) (memory.size $b))
+  (func (export "grow_b") (param i32) (result i32) (memory.grow $b (local.get 0)))
+)
+
+(assert_return (invoke "load_a" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load_b" (i32.const 2)) (i32.const 3))
+(assert_return (invoke "load32_b" (i32.const 0)) (i32.const 0x00040302))
+(invoke "store_b" (i32.const 4) (i32.const 5))
+(assert_return (invoke "load_b" (i32.const 4)) (i32.const 5))
+(assert_return (invoke "load_a" (i32.const 4)) (i32.const 0))
+(invoke "copy" (i32.const 10) (i32.const 1) (i32.const 4))
+(assert_return (invoke "load_a" (i32.const 10)) (i32.const 2))
+(assert_return (invoke "load_a" (i32.const 13)) (i32.const 5))
+(assert_return (invoke "load_b" (i32.const 10)) (i32.const 0))
+(invoke "init_b" (i32.const 20))
+(assert_return (invoke "load_b" (i32.const 21)) (i32.const 0xbb))
+(invoke "fill_b" (i32.const 30) (i32.const 7) (i32.const 2))
+(assert_return (invoke "load_b" (i32.const 31)) (i32.const 7))
+(assert_return (invoke "load_a" (i32.const 31)) (i32.const 0))
+(assert_return (invoke "grow_b" (i32.const 1)) (i32.const 1))
+(assert_return (invoke "grow_b" (i32.const 1)) (i32.const -1))
+(assert_return (invoke "size_a") (i32.const 1))
+(assert_return (invoke "size_b") (i32.const 2))
+(assert_return (invoke "load_b" (i32.const 0x1ffff)) (i32.const 0))
+(assert_trap (invoke "load_a" (i32.const 0x10000)) "out of bounds memory access")
+(assert_trap (invoke "copy" (i32.const 0) (i32.const 0x1ffff) (i32.const 2)) "out of bounds memory access")
+
+(module
+  (memory 1)
+  (memory (export "m") 0)
+  (func (export "store") (i32.store 1 (i32.const 0) (i32.const 1)))
+)
+(assert_trap (invoke "store") "out of bounds memory access")
+
+(assert_invalid
+  (module (memory 1) (func (drop (i32.load 1 (i32.const 0)))))
+  "unknown memory")
+(assert_invalid
+  (module (memory 1) (func (drop (memory.size 1))))
+  "unknown memory")
@@ -0,0 +1,70 @@
+;; Tail calls
+
+(module
+  (func $print (import "spectest" "print_i32") (param i32))
+  (type $i64-i64 (func (param i64) (result i64)))
+  (table funcref (elem $fac-acc $even $odd))
+
+  (func $fac-acc (param i64 i64) (result i64)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (local.get 1))
+      (else
+        (return_call $fac-acc
+          (i64.sub (local.get 0) (i64.const 1))
+          (i64.mul (local.get 0) (local.get 1))))))
+  (func (export "fac") (param i64) (result i64)
+    (return_call $fac-acc (local.get 0) (i64.const 1)))
+
+  (func $even (export "even") (param i64) (result i64)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (i64.const 44))
+      (else
+        (return_call_indirect (type $i64-i64)
+          (i64.sub (local.get 0) (i64.const 1)) (i32.const 2)))))
+  (func $odd (param i64) (result i64)
+    (if (result i64) (i64.eqz (local.get 0))
+      (then (i64.const 99))
+      (else
+        (return_call_indirect (type $i64-i64)
+          (i64.sub (local.get 0) (i64.const 1)) (i32.const 1)))))
+
+  (func (export "print") (param i32)
+    (return_call $print (local.get 0)))
+
+  (func (export "out-of-bounds") (result i64)
+    (return_call_indirect (type $i64-i64) (i64.const 0) (i32.const 3)))
+  (func (export "wrong-type") (result i64)
+    (return_call_indirect (type $i64-i64) (i64.const 0) (i32.const 0)))
+)
+
+(assert_return (invoke "fac" (i64.const 0)) (i64.const 1))
+(assert_return (invoke "fac" (i64.const 5)) (i64.const 120))
+(assert_return (invoke "fac" (i64.const 25)) (i64.const 7034535277573963776))
+(assert_return (invoke "even" (i64.const 0)) (i64.const 44))
+(assert_return (invoke "even" (i64.const 1)) (i64.const 99))
+(assert_return (invoke "even" (i64.const 1000000)) (i64.const 44))
+(assert_return (invoke "even" (i64.const 1000001)) (i64.const 99))
+(assert_return (invoke "print" (i32.const 1)))
+(assert_trap (invoke "out-of-bounds") "undefined element")
+(assert_trap (invoke "wrong-type") "indirect call type mismatch")
+
+(assert_invalid
+  (module (func $f (result i32) (i32.const 0)) (func (return_call $f)))
+  "type mismatch"
+)
+(assert_invalid
+  (module (func $f (result i64) (i64.const 0)) (func (result i32) (return_call $f)))
+  "type mismatch"
+)
+(assert_invalid
+  (module (func $f (param i32)) (func (return_call $f (i64.const 0))))
+  "type mismatch"
+)
+(assert_invalid
+  (module (table 1 externref) (func (return_call_indirect (i32.const 0))))
+  "type mismatch"
+)
+(assert_invalid
+  (module (func (return_call 1)))
+  "unknown function"
+)
@@ -0,0 +1,261 @@
+;; Fixed-width SIMD instructions
+
+(module
+  (memory 1)
+  (data (i32.const 0) "\01\02\03\04\05\06\07\08\09\0a\0b\0c\0d\0e\0f\10\80\ff")
+  (func (export "i8x16.add_sat_s") (param v128 v128) (result v128)
+    (i8x16.add_sat_s (local.get 0) (local.get 1)))
+  (func (export "i8x16.sub_sat_u") (param v128 v128) (result v128)
+    (i8x16.sub_sat_u (local.get 0) (local.get 1)))
+  (func (export "i16x8.mul") (param v128 v128) (result v128)
+    (i16x8.mul (local.get 0) (local.get 1)))
+  (func (export "i32x4.lt_u") (param v128 v128) (result v128)
+    (i32x4.lt_u (local.get 0) (local.get 1)))
+  (func (export "i64x2.shr_s") (param v128 i32) (result v128)
+    (i64x2.shr_s (local.get 0) (local.get 1)))
+  (func (export "i8x16.shuffle") (param v128 v128) (result v128)
+    (i8x16.shuffle 0 16 1 17 2 18 3 19 4 20 5 21 6 22 7 23 (local.get 0) (local.get 1)))
+  (func (export "i8x16.swizzle") (param v128 v128) (result v128)
+    (i8x16.swizzle (local.get 0) (local.get 1)))
+  (func (export "i8x16.bitmask") (param v128) (result i32)
+    (i8x16.bitmask (local.get 0)))
+  (func (export "v128.any_true") (param v128) (result i32)
+    (v128.any_true (local.get 0)))
+  (func (export "i16x8.extract_lane_s") (param v128) (result i32)
+    (i16x8.extract_lane_s 7 (local.get 0)))
+  (func (export "i32x4.replace_lane") (param v128 i32) (result v128)
+    (i32x4.replace_lane 2 (local.get 0) (local.get 1)))
+  (func (export "f32x4.splat") (param f32) (result v128)
+    (f32x4.splat (local.get 0)))
+  (func (export "f32x4.min") (param v128 v128) (result v128)
+    (f32x4.min (local.get 0) (local.get 1)))
+  (func (export "f64x2.div") (param v128 v128) (result v128)
+    (f64x2.div (local.get 0) (local.get 1)))
+  (func (export "f64x2.extract_lane") (param v128) (result f64)
+    (f64x2.extract_lane 1 (local.get 0)))
+  (func (export "i32x4.trunc_sat_f32x4_s") (param v128) (result v128)
+    (i32x4.trunc_sat_f32x4_s (local.get 0)))
+  (func (export "i16x8.narrow_i32x4_s") (param v128 v128) (result v128)
+    (i16x8.narrow_i32x4_s (local.get 0) (local.get 1)))
+  (func (export "i32x4.dot_i16x8_s") (param v128 v128) (result v128)
+    (i32x4.dot_i16x8_s (local.get 0) (local.get 1)))
+  (func (export "v128.bitselect") (param v128 v128 v128) (result v128)
+    (v128.bitselect (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "v128.load") (param i32) (result v128)
+    (v128.load (local.get 0)))
+  (func (export "v128.load8x8_s") (param i32) (result v128)
+    (v128.load8x8_s offset=12 (local.get 0)))
+  (func (export "v128.load16_splat") (param i32) (result v128)
+    (v128.load16_splat (local.get 0)))
+  (func (export "v128.load32_zero") (param i32) (result v128)
+    (v128.load32_zero (local.get 0)))
+  (func (export "v128.load8_lane") (param i32 v128) (result v128)
+    (v128.load8_lane 15 (local.get 0) (local.get 1)))
+  (func (export "v128.store16_lane") (param i32 v128) (result i32)
+    (v128.store16_lane 1 (local.get 0) (local.get 1))
+    (i32.load16_u (local.get 0)))
+  (func (export "v128.store") (param i32 v128) (result v128)
+    (v128.store (local.get 0) (local.get 1))
+    (v128.load (local.get 0)))
+  (func (export "i16x8.extend_low_i8x16_s") (param v128) (result v128)
+    (i16x8.extend_low_i8x16_s (local.get 0)))
+  (func (export "i16x8.extend_low_i8x16_u") (param v128) (result v128)
+    (i16x8.extend_low_i8x16_u (local.get 0)))
+  (func (export "i16x8.extend_high_i8x16_s") (param v128) (result v128)
+    (i16x8.extend_high_i8x16_s (local.get 0)))
+  (func (export "i16x8.extend_high_i8x16_u") (param v128) (result v128)
+    (i16x8.extend_high_i8x16_u (local.get 0)))
+  (func (export "i16x8.extmul_low_i8x16_s") (param v128 v128) (result v128)
+    (i16x8.extmul_low_i8x16_s (local.get 0) (local.get 1)))
+  (func (export "i16x8.extmul_low_i8x16_u") (param v128 v128) (result v128)
+    (i16x8.extmul_low_i8x16_u (local.get 0) (local.get 1)))
+  (func (export "i16x8.extmul_high_i8x16_s") (param v128 v128) (result v128)
+    (i16x8.extmul_high_i8x16_s (local.get 0) (local.get 1)))
+  (func (export "i16x8.extmul_high_i8x16_u") (param v128 v128) (result v128)
+    (i16x8.extmul_high_i8x16_u (local.get 0) (local.get 1)))
+  (func (export "i32x4.extend_low_i16x8_s") (param v128) (result v128)
+    (i32x4.extend_low_i16x8_s (local.get 0)))
+  (func (export "i32x4.extend_low_i16x8_u") (param v128) (result v128)
+    (i32x4.extend_low_i16x8_u (local.get 0)))
+  (func (export "i32x4.extend_high_i16x8_s") (param v128) (result v128)
+    (i32x4.extend_high_i16x8_s (local.get 0)))
+  (func (export "i32x4.extend_high_i16x8_u") (param v128) (result v128)
+    (i32x4.extend_high_i16x8_u (local.get 0)))
+  (func (export "i32x4.extmul_low_i16x8_s") (param v128 v128) (result v128)
+    (i32x4.extmul_low_i16x8_s (local.get 0) (local.get 1)))
+  (func (export "i32x4.extmul_low_i16x8_u") (param v128 v128) (result v128)
+    (i32x4.extmul_low_i16x8_u (local.get 0) (local.get 1)))
+  (func (export "i32x4.extmul_high_i16x8_s") (param v128 v128) (result v128)
+    (i32x4.extmul_high_i16x8_s (local.get 0) (local.get 1)))
+  (func (export "i32x4.extmul_high_i16x8_u") (param v128 v128) (result v128)
+    (i32x4.extmul_high_i16x8_u (local.get 0) (local.get 1)))
+  (func (export "i64x2.extend_low_i32x4_s") (param v128) (result v128)
+    (i64x2.extend_low_i32x4_s (local.get 0)))
+  (func (export "i64x2.extend_low_i32x4_u") (param v128) (result v128)
+    (i64x2.extend_low_i32x4_u (local.get 0)))
+  (func (export "i64x2.extend_high_i32x4_s") (param v128) (result v128)
+    (i64x2.extend_high_i32x4_s (local.get 0)))
+  (func (export "i64x2.extend_high_i32x4_u") (param v128) (result v128)
+    (i64x2.extend_high_i32x4_u (local.get 0)))
+  (func (export "i64x2.extmul_low_i32x4_s") (param v128 v128) (result v128)
+    (i64x2.extmul_low_i32x4_s (local.get 0) (local.get 1)))
+  (func (export "i64x2.extmul_low_i32x4_u") (param v128 v128) (result v128)
+    (i64x2.extmul_low_i32x4_u (local.get 0) (local.get 1)))
+  (func (export "i64x2.extmul_high_i32x4_s") (param v128 v128) (result v128)
+    (i64x2.extmul_high_i32x4_s (local.get 0) (local.get 1)))
+  (func (export "i64x2.extmul_high_i32x4_u") (param v128 v128) (result v128)
+    (i64x2.extmul_high_i32x4_u (local.get 0) (local.get 1)))
+)
+
+(assert_return
+  (invoke "i8x16.add_sat_s"
+    (v128.const i8x16 127 -128 1 2 3 4 5 6 7 8 9 10 11 12 13 14)
+    (v128.const i8x16 1 -1 1 1 1 1 1 1 1 1 1 1 1 1 1 1))
+  (v128.const i8x16 127 -128 2 3 4 5 6 7 8 9 10 11 12 13 14 15))
+(assert_return
+  (invoke "i8x16.sub_sat_u"
+    (v128.const i8x16 0 255 1 2 3 4 5 6 7 8 9 10 11 12 13 14)
+    (v128.const i8x16 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1))
+  (v128.const i8x16 0 254 0 1 2 3 4 5 6 7 8 9 10 11 12 13))
+(assert_return
+  (invoke "i16x8.mul" (v128.const i16x8 -1 2 0x100 0 0 0 0 7) (v128.const i16x8 3 -4 0x100 0 0 0 0 7))
+  (v128.const i16x8 -3 -8 0 0 0 0 0 49))
+(assert_return
+  (invoke "i32x4.lt_u" (v128.const i32x4 1 -1 5 0) (v128.const i32x4 -1 1 5 1))
+  (v128.const i32x4 -1 0 0 -1))
+(assert_return
+  (invoke "i64x2.shr_s" (v128.const i64x2 -16 16) (i32.const 66))
+  (v128.const i64x2 -4 4))
+(assert_return
+  (invoke "i8x16.shuffle"
+    (v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)
+    (v128.const i8x16 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31))
+  (v128.const i8x16 0 16 1 17 2 18 3 19 4 20 5 21 6 22 7 23))
+(assert_return
+  (invoke "i8x16.swizzle"
+    (v128.const i8x16 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25)
+    (v128.const i8x16 15 0 16 -1 1 1 1 1 1 1 1 1 1 1 1 1))
+  (v128.const i8x16 25 10 0 0 11 11 11 11 11 11 11 11 11 11 11 11))
+(assert_return (invoke "i8x16.bitmask" (v128.const i8x16 -1 0 -1 0 0 0 0 0 0 0 0 0 0 0 0 -128)) (i32.const 0x8005))
+(assert_return (invoke "v128.any_true" (v128.const i64x2 0 0)) (i32.const 0))
+(assert_return (invoke "v128.any_true" (v128.const i64x2 0 1)) (i32.const 1))
+(assert_return (invoke "i16x8.extract_lane_s" (v128.const i16x8 0 0 0 0 0 0 0 -2)) (i32.const -2))
+(assert_return
+  (invoke "i32x4.replace_lane" (v128.const i32x4 1 2 3 4) (i32.const 9))
+  (v128.const i32x4 1 2 9 4))
+(assert_return (invoke "f32x4.splat" (f32.const -1.5)) (v128.const f32x4 -1.5 -1.5 -1.5 -1.5))
+(assert_return
+  (invoke "f32x4.min" (v128.const f32x4 0 -0 nan 1) (v128.const f32x4 -0 0 1 2))
+  (v128.const f32x4 -0 -0 nan:canonical 1))
+(assert_return
+  (invoke "f64x2.div" (v128.const f64x2 1 0) (v128.const f64x2 4 0))
+  (v128.const f64x2 0.25 nan:arithmetic))
+(assert_return (invoke "f64x2.extract_lane" (v128.const f64x2 1 -0x1p-1)) (f64.const -0.5))
+(assert_return
+  (invoke "i32x4.trunc_sat_f32x4_s" (v128.const f32x4 1.9 -1.9 nan 3e9))
+  (v128.const i32x4 1 -1 0 0x7fffffff))
+(assert_return
+  (invoke "i16x8.narrow_i32x4_s" (v128.const i32x4 40000 -40000 1 -1) (v128.const i32x4 0 0 0 0))
+  (v128.const i16x8 32767 -32768 1 -1 0 0 0 0))
+(assert_return
+  (invoke "i32x4.dot_i16x8_s" (v128.const i16x8 1 2 3 4 -5 6 7 8) (v128.const i16x8 1 1 2 2 3 3 4 4))
+  (v128.const i32x4 3 14 3 60))
+(assert_return
+  (invoke "v128.bitselect" (v128.const i32x4 -1 -1 0 0) (v128.const i32x4 0 0 -1 -1) (v128.const i32x4 0xffff 0 -1 0))
+  (v128.const i32x4 0xffff 0 0 -1))
+(assert_return (invoke "v128.load" (i32.const 0)) (v128.const i32x4 0x04030201 0x08070605 0x0c0b0a09 0x100f0e0d))
+(assert_return (invoke "v128.load8x8_s" (i32.const 0)) (v128.const i16x8 0x0d 0x0e 0x0f 0x10 -128 -1 0 0))
+(assert_return (invoke "v128.load16_splat" (i32.const 16)) (v128.const i16x8 -128 -128 -128 -128 -128 -128 -128 -128))
+(assert_return (invoke "v128.load32_zero" (i32.const 1)) (v128.const i32x4 0x05040302 0 0 0))
+(assert_return (invoke "v128.load8_lane" (i32.const 17) (v128.const i64x2 0 0)) (v128.const i8x16 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 -1))
+(assert_return (invoke "v128.store16_lane" (i32.const 100) (v128.const i16x8 1 0xbeef 3 4 5 6 7 8)) (i32.const 0xbeef))
+(assert_return
+  (invoke "v128.store" (i32.const 200) (v128.const i64x2 -1 0x0102030405060708))
+  (v128.const i64x2 -1 0x0102030405060708))
+(assert_return
+  (invoke "i16x8.extend_low_i8x16_s" (v128.const i8x16 1 -2 3 -4 5 -6 7 -8 9 -10 11 -12 13 -14 15 -128))
+  (v128.const i16x8 1 -2 3 -4 5 -6 7 -8))
+(assert_return
+  (invoke "i16x8.extend_high_i8x16_s" (v128.const i8x16 1 -2 3 -4 5 -6 7 -8 9 -10 11 -12 13 -14 15 -128))
+  (v128.const i16x8 9 -10 11 -12 13 -14 15 -128))
+(assert_return
+  (invoke "i16x8.extend_low_i8x16_u" (v128.const i8x16 1 -2 3 -4 5 -6 7 -8 9 -10 11 -12 13 -14 15 -128))
+  (v128.const i16x8 1 254 3 252 5 250 7 248))
+(assert_return
+  (invoke "i16x8.extend_high_i8x16_u" (v128.const i8x16 1 -2 3 -4 5 -6 7 -8 9 -10 11 -12 13 -14 15 -128))
+  (v128.const i16x8 9 246 11 244 13 242 15 128))
+(assert_return
+  (invoke "i16x8.extmul_low_i8x16_s" (v128.const i8x16 1 -2 3 -4 5 -6 7 -8 9 -10 11 -12 13 -14 15 -128) (v128.const i8x16 2 2 2 2 2 2 2 2 -1 -1 -1 -1 -1 -1 -1 -1))
+  (v128.const i16x8 2 -4 6 -8 10 -12 14 -16))
+(assert_return
+  (invoke "i16x8.extmul_high_i8x16_s" (v128.const i8x16 1 -2 3 -4 5 -6 7 -8 9 -10 11 -12 13 -14 15 -128) (v128.const i8x16 2 2 2 2 2 2 2 2 -1 -1 -1 -1 -1 -1 -1 -1))
+  (v128.const i16x8 -9 10 -11 12 -13 14 -15 128))
+(assert_return
+  (invoke "i16x8.extmul_low_i8x16_u" (v128.const i8x16 1 -2 3 -4 5 -6 7 -8 9 -10 11 -12 13 -14 15 -128) (v128.const i8x16 2 2 2 2 2 2 2 2 -1 -1 -1 -1 -1 -1 -1 -1))
+  (v128.const i16x8 2 508 6 504 10 500 14 496))
+(assert_return
+  (invoke "i16x8.extmul_high_i8x16_u" (v128.const i8x16 1 -2 3 -4 5 -6 7 -8 9 -10 11 -12 13 -14 15 -128) (v128.const i8x16 2 2 2 2 2 2 2 2 -1 -1 -1 -1 -1 -1 -1 -1))
+  (v128.const i16x8 2295 62730 2805 62220 3315 61710 3825 32640))
+(assert_return
+  (invoke "i32x4.extend_low_i16x8_s" (v128.const i16x8 1 -2 3 -4 5 -6 7 -32768))
+  (v128.const i32x4 1 -2 3 -4))
+(assert_return
+  (invoke "i32x4.extend_high_i16x8_s" (v128.const i16x8 1 -2 3 -4 5 -6 7 -32768))
+  (v128.const i32x4 5 -6 7 -32768))
+(assert_return
+  (invoke "i32x4.extend_low_i16x8_u" (v128.const i16x8 1 -2 3 -4 5 -6 7 -32768))
+  (v128.const i32x4 1 65534 3 65532))
+(assert_return
+  (invoke "i32x4.extend_high_i16x8_u" (v128.const i16x8 1 -2 3 -4 5 -6 7 -32768))
+  (v128.const i32x4 5 65530 7 32768))
+(assert_return
+  (invoke "i32x4.extmul_low_i16x8_s" (v128.const i16x8 1 -2 3 -4 5 -6 7 -32768) (v128.const i16x8 3 3 3 3 -1 -1 -1 -1))
+  (v128.const i32x4 3 -6 9 -12))
+(assert_return
+  (invoke "i32x4.extmul_high_i16x8_s" (v128.const i16x8 1 -2 3 -4 5 -6 7 -32768) (v128.const i16x8 3 3 3 3 -1 -1 -1 -1))
+  (v128.const i32x4 -5 6 -7 32768))
+(assert_return
+  (invoke "i32x4.extmul_low_i16x8_u" (v128.const i16x8 1 -2 3 -4 5 -6 7 -32768) (v128.const i16x8 3 3 3 3 -1 -1 -1 -1))
+  (v128.const i32x4 3 196602 9 196596))
+(assert_return
+  (invoke "i32x4.extmul_high_i16x8_u" (v128.const i16x8 1 -2 3 -4 5 -6 7 -32768) (v128.const i16x8 3 3 3 3 -1 -1 -1 -1))
+  (v128.const i32x4 327675 4294508550 458745 2147450880))
+(assert_return
+  (invoke "i64x2.extend_low_i32x4_s" (v128.const i32x4 1 -2 -3 4))
+  (v128.const i64x2 1 -2))
+(assert_return
+  (invoke "i64x2.extend_high_i32x4_s" (v128.const i32x4 1 -2 -3 4))
+  (v128.const i64x2 -3 4))
+(assert_return
+  (invoke "i64x2.extend_low_i32x4_u" (v128.const i32x4 1 -2 -3 4))
+  (v128.const i64x2 1 4294967294))
+(assert_return
+  (invoke "i64x2.extend_high_i32x4_u" (v128.const i32x4 1 -2 -3 4))
+  (v128.const i64x2 4294967293 4))
+(assert_return
+  (invoke "i64x2.extmul_low_i32x4_s" (v128.const i32x4 1 -2 -3 4) (v128.const i32x4 5 5 -1 -1))
+  (v128.const i64x2 5 -10))
+(assert_return
+  (invoke "i64x2.extmul_high_i32x4_s" (v128.const i32x4 1 -2 -3 4) (v128.const i32x4 5 5 -1 -1))
+  (v128.const i64x2 3 -4))
+(assert_return
+  (invoke "i64x2.extmul_low_i32x4_u" (v128.const i32x4 1 -2 -3 4) (v128.const i32x4 5 5 -1 -1))
+  (v128.const i64x2 5 21474836470))
+(assert_return
+  (invoke "i64x2.extmul_high_i32x4_u" (v128.const i32x4 1 -2 -3 4) (v128.const i32x4 5 5 -1 -1))
+  (v128.const i64x2 18446744056529682435 17179869180))
+(assert_trap (invoke "v128.load" (i32.const 65521)) "out of bounds memory access")
+(assert_trap (invoke "v128.load32_zero" (i32.const 65533)) "out of bounds memory access")
+
+(assert_invalid
+  (module (func (result v128) (i8x16.extract_lane_s 16 (v128.const i64x2 0 0))))
+  "invalid lane index")
+(assert_invalid
+  (module (func (result v128) (i32x4.add (v128.const i64x2 0 0) (i32.const 0))))
+  "type mismatch")
+(assert_malformed
+  (module quote "(func (result v128) (v128.const i32x4 0 0 0))")
+  "unexpected token")
+(assert_malformed
+  (module quote "(func (result v128) (v128.const i8x16 256 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0))")
+  "constant out of range")
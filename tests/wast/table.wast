@@ -0,0 +1,51 @@
+;; Tables, references and indirect calls
+
+(module
+  (type $i32 (func (result i32)))
+  (table $t 4 10 funcref)
+  (table $ext (export "ext") 2 externref)
+  (elem (table $t) (i32.const 0) func $one $two)
+  (elem $passive func $three)
+  (elem declare func $other)
+  (func $one (type $i32) (i32.const 1))
+  (func $two (type $i32) (i32.const 2))
+  (func $three (type $i32) (i32.const 3))
+  (func $other (param i32))
+  (func (export "call") (param i32) (result i32) (call_indirect $t (type $i32) (local.get 0)))
+  (func (export "size") (result i32) (table.size $t))
+  (func (export "grow") (param i32) (result i32) (table.grow $t (ref.null func) (local.get 0)))
+  (func (export "init") (param i32 i32 i32) (table.init $t $passive (local.get 0) (local.get 1) (local.get 2)))
+  (func (export "set-other") (param i32) (table.set $t (local.get 0) (ref.func $other)))
+  (func (export "is-null") (param i32) (result i32) (ref.is_null (table.get $t (local.get 0))))
+  (func (export "get-ext") (param i32) (result externref) (table.get $ext (local.get 0)))
+  (func (export "set-ext") (param i32 externref) (table.set $ext (local.get 0) (local.get 1)))
+  (func (export "func") (result funcref) (ref.func $one))
+)
+
+(assert_return (invoke "call" (i32.const 0)) (i32.const 1))
+(assert_return (invoke "call" (i32.const 1)) (i32.const 2))
+(assert_trap (invoke "call" (i32.const 2)) "uninitialized element")
+(assert_trap (invoke "call" (i32.const 4)) "undefined element")
+(invoke "set-other" (i32.const 2))
+(assert_trap (invoke "call" (i32.const 2)) "indirect call type mismatch")
+(assert_return (invoke "is-null" (i32.const 2)) (i32.const 0))
+(assert_return (invoke "is-null" (i32.const 3)) (i32.const 1))
+(invoke "init" (i32.const 3) (i32.const 0) (i32.const 1))
+(assert_return (invoke "call" (i32.const 3)) (i32.const 3))
+(assert_trap (invoke "init" (i32.const 3) (i32.const 1) (i32.const 1)) "out of bounds table access")
+
+(assert_return (invoke "size") (i32.const 4))
+(assert_return (invoke "grow" (i32.const 6)) (i32.const 4))
+(assert_return (invoke "grow" (i32.const 1)) (i32.const -1))
+(assert_trap (invoke "call" (i32.const 9)) "uninitialized element")
+
+(assert_return (invoke "get-ext" (i32.const 0)) (ref.null extern))
+(invoke "set-ext" (i32.const 1) (ref.extern 7))
+(assert_return (invoke "get-ext" (i32.const 1)) (ref.extern 7))
+(assert_return (invoke "get-ext" (i32.const 1)) (ref.extern))
+(assert_trap (invoke "get-ext" (i32.const 2)) "out of bounds table access")
+(assert_return (invoke "func") (ref.func))
+
+(assert_trap (module (table 1 funcref) (func $f) (elem (i32.const 1) $f)) "out of bounds table access")
+(assert_invalid (module (func (drop (table.size 0)))) "unknown table")
+(assert_invalid (module (table 1 externref) (func (call_indirect (i32.const 0)))) "type mismatch")